@@ -0,0 +1,5 @@
+// fragment hook: dark contour lines every 0.2 units of world height
+let level = in.vPosition.y * 5.0;
+if fract(level) < fwidth(level) {
+    color = vec4f(0.1 * color.rgb, 1.0);
+}
@@ -0,0 +1,36 @@
+// vertex shader coloring the surface by how fast each vertex moves; heat_color
+// comes from motion::HEAT_WGSL
+struct Uniforms {   
+    vpMat : mat4x4f,
+    modelMat : mat4x4f,           
+    normalMat : mat4x4f,            
+    heat : vec4f, // speed factor (1 / frame time), gain
+};
+@binding(0) @group(0) var<uniform> uniforms : Uniforms;
+
+struct Input {
+    @location(0) position: vec4f, 
+    @location(1) normal: vec4f, 
+    @location(2) color: vec4f,
+    @location(3) previousPosition: vec3f,
+}
+
+struct Output {
+    @builtin(position) position : vec4f,
+    @location(0) vPosition : vec4f,
+    @location(1) vNormal : vec4f,
+    @location(2) vColor: vec4f,
+};
+
+@vertex
+fn vs_main(in:Input) -> Output {    
+    var output: Output;            
+    // the speed in model space, so spinning the whole surface does not count
+    let speed = length(in.position.xyz - in.previousPosition) * uniforms.heat.x;
+    let mPosition = uniforms.modelMat * in.position; 
+    output.vPosition = mPosition;                  
+    output.vNormal =  uniforms.normalMat * in.normal;
+    output.position = uniforms.vpMat * mPosition; 
+    output.vColor = vec4f(heat_color(in.color.rgb, speed, uniforms.heat.y), 1.0);
+    return output;
+}
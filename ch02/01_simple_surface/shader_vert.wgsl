@@ -19,13 +19,19 @@ struct Output {
     @location(2) vColor: vec4f,
 };
 
+// @hook vertex_declarations
+
 @vertex
 fn vs_main(in:Input) -> Output {    
     var output: Output;            
-    let mPosition = uniforms.modelMat * in.position; 
+    var position = in.position;
+    var normal = in.normal;
+    var color = in.color;
+    // @hook vertex
+    let mPosition = uniforms.modelMat * position; 
     output.vPosition = mPosition;                  
-    output.vNormal =  uniforms.normalMat * in.normal;
+    output.vNormal =  uniforms.normalMat * normal;
     output.position = uniforms.vpMat * mPosition; 
-    output.vColor = in.color;              
+    output.vColor = color;              
     return output;
 }
@@ -1,60 +1,145 @@
 use std::sync::Arc;
 use bytemuck::cast_slice;
-use cgmath::{Matrix, Matrix4, SquareMatrix};
-use wgpu::util::DeviceExt;
+use cgmath::{InnerSpace, Matrix, Matrix4, Point3, SquareMatrix, Transform};
 use winit::{
-    event::ElementState, event::KeyEvent, event::WindowEvent, keyboard::Key, keyboard::NamedKey,
-    window::Window,
+    event::ElementState, event::KeyEvent, event::MouseButton, event::WindowEvent,
+    window::CursorIcon, window::Window,
 };
 
+use wgpu_surfaces::app::{self, Action, InputMap, SurfaceApp};
+use wgpu_surfaces::animation::{AnimationCurve, Easing, SurfaceField, Timeline};
+use wgpu_surfaces::appearance::{Appearance, DirectionalLight};
+use wgpu_surfaces::capabilities::Subsystem;
+use wgpu_surfaces::export;
+use wgpu_surfaces::gizmo::{self, Gizmo, GizmoMode, Guides, LineRenderer, NormalLines};
+use wgpu_surfaces::gpu_surface::{self, DisplacedSurface, GpuSurface};
+use wgpu_surfaces::labels::NumberFormat;
+use wgpu_surfaces::lighting::{self, Light, LightRig, LightSet, LightSetBinding};
+use wgpu_surfaces::material::{Material, MaterialKind};
+use wgpu_surfaces::motion::{self, MotionHistory};
+use wgpu_surfaces::overlay::{self, SceneStats, StatsOverlay};
+use wgpu_surfaces::params::{self, ParamSet};
+use wgpu_surfaces::picking::{self, Crosshair, PickHit, ProbeEvent, ProbeHooks, Ray};
+use wgpu_surfaces::recorder::{self, RecordFormat, Recorder};
+use wgpu_surfaces::scene::{self, Scene};
+use wgpu_surfaces::shader_hooks::ShaderHooks;
 use wgpu_surfaces::surface_data as sd;
+use wgpu_surfaces::cli::SurfaceArgs;
 use wgpu_surfaces::wgpu_simplified as ws;
 
 use crate::vertex::{create_vertices, Vertex};
 
+// default key bindings, remapped with --keymap (see app::InputMap)
+const KEYS: &[(Action, &str)] = &[
+    (Action::CyclePlotType, "Space"),
+    (Action::NextSurfaceType, "Control"),
+    (Action::CycleColormapDirection, "Alt"),
+    (Action::IncreaseUResolution, "q"),
+    (Action::DecreaseUResolution, "a"),
+    (Action::IncreaseVResolution, "w"),
+    (Action::DecreaseVResolution, "s"),
+    (Action::IncreaseAnimationSpeed, "e"),
+    (Action::DecreaseAnimationSpeed, "d"),
+    (Action::IncreaseRotationSpeed, "r"),
+    (Action::DecreaseRotationSpeed, "f"),
+    (Action::SelectPreviousParam, "ArrowUp"),
+    (Action::SelectNextParam, "ArrowDown"),
+    (Action::DecreaseParam, "ArrowLeft"),
+    (Action::IncreaseParam, "ArrowRight"),
+    (Action::ToggleMaterialSliders, "k"),
+    (Action::TurnLightRight, "p"),
+    (Action::CycleColorField, "g"),
+    (Action::ToggleMorph, "m"),
+    (Action::ToggleGpuSurface, "c"),
+    (Action::ToggleVertexDisplacement, "C"),
+    (Action::ToggleHeat, "h"),
+    (Action::CycleLights, "l"),
+    (Action::CycleGizmo, "t"),
+    (Action::ToggleSnapping, "n"),
+    (Action::ExportMesh, "o"),
+    (Action::PrintMetrics, "b"),
+    (Action::ToggleGpuTimer, "i"),
+    (Action::ToggleStats, overlay::STATS_KEY),
+    (Action::ToggleRecording, recorder::RECORD_KEY),
+    (Action::ToggleProbe, picking::PROBE_KEY),
+    (Action::SaveScene, "S"),
+    (Action::LoadScene, "L"),
+];
+
 pub struct State {
     init: ws::InitWgpu,
-    pipelines: Vec<wgpu::RenderPipeline>,
-    vertex_buffers: Vec<wgpu::Buffer>,
-    index_buffers: Vec<wgpu::Buffer>,
-    uniform_bind_groups: Vec<wgpu::BindGroup>,
-    uniform_buffers: Vec<wgpu::Buffer>,
+    pipelines: app::SurfacePipelines,
+    heat_pipeline: wgpu::RenderPipeline,
+    lights_pipeline: wgpu::RenderPipeline,
+    displaced_pipelines: [wgpu::RenderPipeline; 2], // shape, wireframe
+    vertex_buffers: Vec<ws::GrowableBuffer>,
+    index_buffers: Vec<ws::GrowableBuffer>,
+    vert_uniform_buffer: wgpu::Buffer,
     view_mat: Matrix4<f32>,
+    camera: ws::OrbitCamera,
     project_mat: Matrix4<f32>,
-    msaa_texture_view: wgpu::TextureView,
-    depth_texture_view: wgpu::TextureView,
+    model_mat: Matrix4<f32>,
+    cursor: [f32; 2],
+    probe_hooks: ProbeHooks,
+    probing: bool, // key j: crosshair on the surface point under the cursor
+    crosshair: Crosshair,
+    targets: app::RenderTargets,
     indices_lens: Vec<u32>,
     plot_type: u32,
     recreate_buffers: bool,
     animation_speed: f32,
     rotation_speed: f32,
+    morph_timeline: Timeline<sd::ISimpleSurface>,
+    morph_enabled: bool,
+    pre_morph: (f32, [u16; 2], Option<[f32; 2]>), // scale, resolution and colormap range
+    params: ParamSet<sd::ISimpleSurface>,
 
     simple_surface: sd::ISimpleSurface,
+    gpu_surface: Option<(u32, GpuSurface)>, // surface type generated by compute shaders
+    displaced_surface: Option<DisplacedSurface>, // key C: animated in the vertex shader
+    motion: MotionHistory, // previous positions for the heat coloring
+    show_heat: bool,
+    light_set: LightSetBinding, // several lights instead of the single directional one
+    show_lights: bool,
+    light_rig: Option<LightRig>, // a preset, or the colored rig of light_rig()
+    material: MaterialKind,
+    gizmo: Gizmo, // placement of the surface, moved with the mouse while shown
+    show_gizmo: bool,
+    gizmo_buffer: wgpu::Buffer,
+    gizmo_count: u32,
+    guides: Guides, // axes, ground grid and bounding box, key v
+    normal_lines: NormalLines, // vertex normals, key u
+    surface_bounds: Option<[[f32; 3]; 2]>,
+    input_map: InputMap,
     fps_counter: ws::FpsCounter,
+    stats_overlay: StatsOverlay, // key x: frame statistics over the scene instead of printed
+    overlay_lines: LineRenderer,
+    show_stats: bool,
+    gpu_timer: Option<ws::GpuTimer>, // key i: GPU time of the main pass, per plot type
+    gpu_ms: Option<f32>,             // of the last frame the timer read, for the overlay
+    render_hooks: ws::RenderHooks,
+    recorder: Recorder, // key z: records recording.gif
+    appearance: Appearance, // light, material and colormap uniforms, keys k and p
+    material_params: ParamSet<Material>,
+    tuning_material: bool, // the arrow keys change the material instead of the surface
+    scene_file: String,    // where S saves the scene and L loads it
 }
 
-impl State {
-    pub async fn new(
-        window: Arc<Window>,
-        sample_count: u32,
-        colormap_name: &str,
-        wireframe_color: &str,
-    ) -> Self {
-        let init = ws::InitWgpu::init_wgpu(window, sample_count).await;
+impl SurfaceApp for State {
+    async fn new(window: Arc<Window>, args: &SurfaceArgs) -> wgpu_surfaces::Result<Self> {
+        let init = ws::InitWgpu::init_wgpu(window, args.msaa, args.present_mode).await?;
 
         // Loading Shaders
-        let vs_shader = init
-            .device
-            .create_shader_module(wgpu::include_wgsl!("shader_vert.wgsl"));
-        let fs_shader = init
-            .device
-            .create_shader_module(wgpu::include_wgsl!("../common/directional_frag.wgsl"));
+        let (vs_shader, fs_shader, lights_fs_shader) =
+            load_shaders(&init.device, &args.positional, args.material).unwrap_or_else(|e| {
+                eprintln!("error: {}", e);
+                std::process::exit(2);
+            });
 
         // uniform data
         let camera_position = (4.0, 4.0, 4.0).into();
         let look_direction = (0.0, 0.0, 0.0).into();
         let up_direction = cgmath::Vector3::unit_y();
-        let light_direction = [-0.5f32, -0.5, -0.5];
 
         let (view_mat, project_mat, _) = ws::create_vp_mat(
             camera_position,
@@ -67,78 +152,21 @@ impl State {
         // model_mat and vp_mat will be stored in vertex_uniform_buffer inside the update function
         let vert_uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Vertex Uniform Buffer"),
-            size: 192,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        // create light uniform buffer. here we set eye_position = camera_position
-        let light_uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Light Uniform Buffer"),
-            size: 48,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        let eye_position: &[f32; 3] = camera_position.as_ref();
-        init.queue.write_buffer(
-            &light_uniform_buffer,
-            0,
-            cast_slice(light_direction.as_ref()),
-        );
-        init.queue
-            .write_buffer(&light_uniform_buffer, 16, cast_slice(eye_position));
-
-        // set specular light color to white
-        let specular_color: [f32; 3] = [1.0, 1.0, 1.0];
-        init.queue.write_buffer(
-            &light_uniform_buffer,
-            32,
-            cast_slice(specular_color.as_ref()),
-        );
-
-        // material uniform buffer
-        let material_uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Material Uniform Buffer"),
-            size: 16,
+            size: 208, // vp, model and normal matrices, heat parameters
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
-        // set default material parameters
-        let material = [0.1f32, 0.7, 0.4, 30.0];
-        init.queue
-            .write_buffer(&material_uniform_buffer, 0, cast_slice(material.as_ref()));
-
-        // uniform bind group for vertex shader
-        let (vert_bind_group_layout, vert_bind_group) = ws::create_bind_group(
-            &init.device,
-            vec![wgpu::ShaderStages::VERTEX],
-            &[vert_uniform_buffer.as_entire_binding()],
-        );
-        let (vert_bind_group_layout2, vert_bind_group2) = ws::create_bind_group(
-            &init.device,
-            vec![wgpu::ShaderStages::VERTEX],
-            &[vert_uniform_buffer.as_entire_binding()],
-        );
-
-        // uniform bind group for fragment shader
-        let (frag_bind_group_layout, frag_bind_group) = ws::create_bind_group(
-            &init.device,
-            vec![wgpu::ShaderStages::FRAGMENT, wgpu::ShaderStages::FRAGMENT],
-            &[
-                light_uniform_buffer.as_entire_binding(),
-                material_uniform_buffer.as_entire_binding(),
-            ],
-        );
-        let (frag_bind_group_layout2, frag_bind_group2) = ws::create_bind_group(
-            &init.device,
-            vec![wgpu::ShaderStages::FRAGMENT, wgpu::ShaderStages::FRAGMENT],
-            &[
-                light_uniform_buffer.as_entire_binding(),
-                material_uniform_buffer.as_entire_binding(),
-            ],
-        );
+        // light, material and colormap, changed at runtime by writing their buffers
+        // only; here we set eye_position = camera_position
+        let light = DirectionalLight {
+            eye_position: camera_position.into(),
+            ..Default::default()
+        };
+        let material = args.material.default_material();
+        let mut appearance =
+            Appearance::new(&init.device, &init.queue, light, material, &args.colormap);
+        appearance.hooks.add(|change| println!("{}", change));
 
         let vertex_buffer_layout = wgpu::VertexBufferLayout {
             array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
@@ -146,152 +174,250 @@ impl State {
             attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3],
             // pos, norm, col
         };
+        let pipelines = app::SurfacePipelines::new(
+            &init,
+            &vs_shader,
+            &fs_shader,
+            std::slice::from_ref(&vertex_buffer_layout),
+            ws::create_bind_group(
+                &init.device,
+                vec![wgpu::ShaderStages::VERTEX],
+                &[vert_uniform_buffer.as_entire_binding()],
+            )?,
+            appearance.bind_group(&init.device)?,
+        )?;
+        let [vert_bind_group_layout, frag_bind_group_layout] = &pipelines.bind_group_layouts;
 
-        let pipeline_layout = init
+        // shape lit by a set of point, spot and directional lights
+        let light_set = LightSetBinding::new(&init.device, MAX_LIGHTS)?;
+        let lights_pipeline_layout =
+            init.device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("Light Set Pipeline Layout"),
+                    bind_group_layouts: &[
+                        vert_bind_group_layout,
+                        frag_bind_group_layout,
+                        &light_set.layout,
+                    ],
+                    push_constant_ranges: &[],
+                });
+        let lights_pipeline = ws::IRenderPipeline {
+            vs_shader: Some(&vs_shader),
+            fs_shader: Some(&lights_fs_shader),
+            pipeline_layout: Some(&lights_pipeline_layout),
+            vertex_buffer_layout: std::slice::from_ref(&vertex_buffer_layout),
+            ..Default::default()
+        }
+        .new(&init)?;
+
+        // shape colored by vertex speed, with the previous positions in a second buffer
+        let heat_shader = init
             .device
-            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&vert_bind_group_layout, &frag_bind_group_layout],
-                push_constant_ranges: &[],
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("heat_vert.wgsl"),
+                source: wgpu::ShaderSource::Wgsl(
+                    motion::with_heat(include_str!("heat_vert.wgsl")).into(),
+                ),
             });
-
-        let mut ppl = ws::IRenderPipeline {
-            vs_shader: Some(&vs_shader),
+        let heat_pipeline = ws::IRenderPipeline {
+            vs_shader: Some(&heat_shader),
             fs_shader: Some(&fs_shader),
-            pipeline_layout: Some(&pipeline_layout),
-            vertex_buffer_layout: &[vertex_buffer_layout],
+            pipeline_layout: Some(&pipelines.layout),
+            vertex_buffer_layout: &[
+                vertex_buffer_layout,
+                MotionHistory::vertex_buffer_layout(),
+            ],
             ..Default::default()
-        };
-        let pipeline = ppl.new(&init);
-
-        let vertex_buffer_layout2 = wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3],
-            // pos, norm, col
-        };
+        }
+        .new(&init)?;
 
-        let pipeline_layout2 =
+        // shape and wireframe displaced in the vertex shader, drawn without vertex buffers
+        let displaced_shader = DisplacedSurface::vertex_shader(&init.device);
+        let displacement_layout = DisplacedSurface::bind_group_layout(&init.device);
+        let displaced_layout =
             init.device
                 .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: Some("Render Pipeline Layout 2"),
-                    bind_group_layouts: &[&vert_bind_group_layout2, &frag_bind_group_layout2],
+                    label: Some("Displaced Surface Pipeline Layout"),
+                    bind_group_layouts: &[
+                        vert_bind_group_layout,
+                        frag_bind_group_layout,
+                        &displacement_layout,
+                    ],
                     push_constant_ranges: &[],
                 });
+        let displaced_pipelines = ["vs_main", "vs_wireframe"].map(|vs_entry| {
+            let topology = match vs_entry {
+                "vs_main" => wgpu::PrimitiveTopology::TriangleList,
+                _ => wgpu::PrimitiveTopology::LineList,
+            };
+            ws::IRenderPipeline {
+                topology,
+                vs_shader: Some(&displaced_shader),
+                fs_shader: Some(&fs_shader),
+                pipeline_layout: Some(&displaced_layout),
+                vs_entry: vs_entry.to_string(),
+                ..Default::default()
+            }
+            .new(&init)
+        });
+        let [displaced_pipeline, displaced_pipeline2] = displaced_pipelines;
 
-        let mut ppl2 = ws::IRenderPipeline {
-            topology: wgpu::PrimitiveTopology::LineList,
-            vs_shader: Some(&vs_shader),
-            fs_shader: Some(&fs_shader),
-            pipeline_layout: Some(&pipeline_layout2),
-            vertex_buffer_layout: &[vertex_buffer_layout2],
-            ..Default::default()
-        };
-        let pipeline2 = ppl2.new(&init);
-
-        let msaa_texture_view = ws::create_msaa_texture_view(&init);
-        let depth_texture_view = ws::create_depth_view(&init);
+        let targets = app::RenderTargets::new(&init);
 
         let mut ss = sd::ISimpleSurface {
             scale: 3.0,
-            colormap_name: colormap_name.to_string(),
-            wireframe_color: wireframe_color.to_string(),
+            colormap_name: args.colormap.clone(),
+            wireframe_color: args.wireframe_color.clone(),
             ..Default::default()
         };
+        if let Some(resolution) = args.resolution {
+            (ss.x_resolution, ss.z_resolution) = (resolution, resolution);
+        }
+        if let Some(surface_type) = args.surface_type {
+            ss.surface_type = surface_type;
+        }
         let data = create_vertices(ss.new());
 
-        let vertex_buffer = init
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Vertex Buffer"),
-                contents: cast_slice(&data.0),
-                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            });
+        let vertex_buffer = ws::GrowableBuffer::new(
+            &init.device,
+            "Vertex Buffer",
+            wgpu::BufferUsages::VERTEX,
+            cast_slice(&data.0),
+        );
 
-        let vertex_buffer2 = init
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Vertex Buffer 2"),
-                contents: cast_slice(&data.1),
-                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            });
+        let vertex_buffer2 = ws::GrowableBuffer::new(
+            &init.device,
+            "Vertex Buffer 2",
+            wgpu::BufferUsages::VERTEX,
+            cast_slice(&data.1),
+        );
 
-        let index_buffer = init
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Index Buffer"),
-                contents: bytemuck::cast_slice(&data.2),
-                usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
-            });
+        let index_buffer = ws::GrowableBuffer::new(
+            &init.device,
+            "Index Buffer",
+            wgpu::BufferUsages::INDEX,
+            cast_slice(&data.2),
+        );
 
-        let index_buffer2 = init
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Index Buffer 2"),
-                contents: bytemuck::cast_slice(&data.3),
-                usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
-            });
+        let index_buffer2 = ws::GrowableBuffer::new(
+            &init.device,
+            "Index Buffer 2",
+            wgpu::BufferUsages::INDEX,
+            cast_slice(&data.3),
+        );
+
+        let gizmo_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Gizmo Vertex Buffer"),
+            size: (gizmo::MAX_LINE_VERTICES * std::mem::size_of::<Vertex>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let motion = MotionHistory::new(&init.device);
+        let guides = Guides::new(&init)?;
+        let normal_lines = NormalLines::new(&init)?;
+        let overlay_lines = LineRenderer::new(&init)?;
+        let surface_bounds = gizmo::bounds(data.0.iter().map(|v| v.position));
 
-        Self {
+        let mut state = Self {
             init,
-            pipelines: vec![pipeline, pipeline2],
+            pipelines,
+            heat_pipeline,
+            lights_pipeline,
+            displaced_pipelines: [displaced_pipeline?, displaced_pipeline2?],
             vertex_buffers: vec![vertex_buffer, vertex_buffer2],
             index_buffers: vec![index_buffer, index_buffer2],
-            uniform_bind_groups: vec![
-                vert_bind_group,
-                frag_bind_group,
-                vert_bind_group2,
-                frag_bind_group2,
-            ],
-            uniform_buffers: vec![
-                vert_uniform_buffer,
-                light_uniform_buffer,
-                material_uniform_buffer,
-            ],
+            vert_uniform_buffer,
             view_mat,
+            camera: ws::OrbitCamera::new(camera_position, look_direction),
             project_mat,
-            msaa_texture_view,
-            depth_texture_view,
+            model_mat: Matrix4::identity(),
+            cursor: [0.0, 0.0],
+            probe_hooks: default_probe_hooks(args.number_format),
+            probing: false,
+            crosshair: Crosshair::default(),
+            targets,
             indices_lens: vec![data.2.len() as u32, data.3.len() as u32],
             plot_type: 0,
             recreate_buffers: false,
             animation_speed: 1.0,
             rotation_speed: 1.0,
+            morph_timeline: morph_timeline(),
+            morph_enabled: false,
+            pre_morph: (3.0, [30, 30], None),
+            params: params::simple_params(),
 
             simple_surface: ss,
+            gpu_surface: None,
+            displaced_surface: None,
+            motion,
+            show_heat: false,
+            light_set,
+            show_lights: false,
+            light_rig: None,
+            material: args.material,
+            gizmo: Gizmo::new(GizmoMode::Translate, [0.0, 1.0, 0.0], 2.0),
+            show_gizmo: false,
+            gizmo_buffer,
+            gizmo_count: 0,
+            guides,
+            normal_lines,
+            surface_bounds,
+            input_map: InputMap::with_args(KEYS, args)?,
             fps_counter: ws::FpsCounter::default(),
+            stats_overlay: StatsOverlay {
+                number_format: args.number_format,
+                ..StatsOverlay::themed(args.theme, args.ui_scale)
+            },
+            overlay_lines,
+            show_stats: false,
+            gpu_timer: None,
+            gpu_ms: None,
+            render_hooks: ws::RenderHooks::default(),
+            recorder: Recorder::new(RecordFormat::Gif, 25.0, 250),
+            material_params: params::material_params(appearance.material().kind()),
+            appearance,
+            tuning_material: false,
+            scene_file: args.scene_file.clone().unwrap_or(scene::SCENE_FILE.to_string()),
+        };
+        if let Some(scene) = &args.scene {
+            state.set_scene(scene);
         }
+        Ok(state)
     }
 
-    pub fn window(&self) -> &Window {
-        &self.init.window
+    fn init(&self) -> &ws::InitWgpu {
+        &self.init
     }
 
-    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
-        self.init.size
+    fn init_mut(&mut self) -> &mut ws::InitWgpu {
+        &mut self.init
     }
 
-    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
-        if new_size.width > 0 && new_size.height > 0 {
-            self.init.size = new_size;
-            // The surface needs to be reconfigured every time the window is resized.
-            self.init.config.width = new_size.width;
-            self.init.config.height = new_size.height;
-            self.init
-                .surface
-                .configure(&self.init.device, &self.init.config);
-
-            self.project_mat =
-                ws::create_projection_mat(new_size.width as f32 / new_size.height as f32, true);
-            self.depth_texture_view = ws::create_depth_view(&self.init);
-            if self.init.sample_count > 1 {
-                self.msaa_texture_view = ws::create_msaa_texture_view(&self.init);
-            }
-        }
+    fn resized(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.project_mat =
+            ws::create_projection_mat(new_size.width as f32 / new_size.height as f32, true);
+        self.targets.resize(&self.init);
     }
 
-    pub fn input(&mut self, event: &WindowEvent) -> bool {
-        match event {
+    // the scene saved by the crash recovery file (--recovery-file)
+    fn snapshot(&self) -> Option<Scene> {
+        Some(self.scene())
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        if self.gizmo_input(event)
+            || self.guides.input(event)
+            || self.normal_lines.input(event)
+        {
+            return true;
+        }
+        // left drag rotates, right drag pans and the wheel zooms
+        let moved = self.camera.input(event);
+        if moved {
+            self.view_mat = self.camera.view_mat();
+        }
+        let handled = match event {
             WindowEvent::KeyboardInput {
                 event:
                     KeyEvent {
@@ -300,96 +426,367 @@ impl State {
                         ..
                     },
                 ..
-            } => match key.as_ref() {
-                Key::Named(NamedKey::Space) => {
+            } => match self.input_map.action(key) {
+                Some(Action::CyclePlotType) => {
                     self.plot_type = (self.plot_type + 1) % 3;
-                    return true;
+                    true
                 }
-                Key::Named(NamedKey::Control) => {
+                Some(Action::NextSurfaceType) => {
                     self.simple_surface.surface_type = (self.simple_surface.surface_type + 1) % 3;
-                    return true;
+                    true
                 }
-                Key::Named(NamedKey::Alt) => {
+                Some(Action::CycleColormapDirection) => {
                     self.simple_surface.colormap_direction =
                         (self.simple_surface.colormap_direction + 1) % 3;
-                    return true;
+                    true
                 }
-                Key::Character("q") => {
+                Some(Action::IncreaseUResolution) => {
                     self.simple_surface.x_resolution += 1;
                     if self.simple_surface.x_resolution > 250 {
                         self.simple_surface.x_resolution = 250;
                     }
                     println!("x_resolution: {}", self.simple_surface.x_resolution);
                     self.recreate_buffers = true;
-                    return true;
+                    true
                 }
-                Key::Character("a") => {
+                Some(Action::DecreaseUResolution) => {
                     self.simple_surface.x_resolution -= 1;
                     if self.simple_surface.x_resolution < 8 {
                         self.simple_surface.x_resolution = 8;
                     }
                     println!("x_resolution: {}", self.simple_surface.x_resolution);
                     self.recreate_buffers = true;
-                    return true;
+                    true
                 }
-                Key::Character("w") => {
+                Some(Action::IncreaseVResolution) => {
                     self.simple_surface.z_resolution += 1;
                     if self.simple_surface.z_resolution > 250 {
                         self.simple_surface.z_resolution = 250;
                     }
                     println!("z_resolution: {}", self.simple_surface.z_resolution);
                     self.recreate_buffers = true;
-                    return true;
+                    true
                 }
-                Key::Character("s") => {
+                Some(Action::DecreaseVResolution) => {
                     self.simple_surface.z_resolution -= 1;
                     if self.simple_surface.z_resolution < 8 {
                         self.simple_surface.z_resolution = 8;
                     }
                     println!("z_resolution: {}", self.simple_surface.z_resolution);
                     self.recreate_buffers = true;
-                    return true;
+                    true
                 }
-                Key::Character("e") => {
+                Some(Action::IncreaseAnimationSpeed) => {
                     self.animation_speed += 0.1;
-                    return true;
+                    true
                 }
-                Key::Character("d") => {
+                Some(Action::DecreaseAnimationSpeed) => {
                     self.animation_speed -= 0.1;
                     if self.animation_speed < 0.0 {
                         self.animation_speed = 0.0;
                     }
-                    return true;
+                    true
                 }
-                Key::Character("r") => {
+                Some(Action::IncreaseRotationSpeed) => {
                     self.rotation_speed += 0.1;
-                    return true;
+                    true
+                }
+                // parameter sliders: up/down selects a field, left/right changes it
+                Some(Action::SelectPreviousParam) if self.tuning_material => {
+                    self.material_params.select_previous();
+                    let material = self.appearance.material();
+                    println!("{}", self.material_params.describe(&material, 20));
+                    true
+                }
+                Some(Action::SelectNextParam) if self.tuning_material => {
+                    self.material_params.select_next();
+                    let material = self.appearance.material();
+                    println!("{}", self.material_params.describe(&material, 20));
+                    true
+                }
+                Some(action @ (Action::DecreaseParam | Action::IncreaseParam))
+                    if self.tuning_material =>
+                {
+                    let steps = if action == Action::DecreaseParam { -1.0 } else { 1.0 };
+                    let mut material = self.appearance.material();
+                    self.material_params.nudge_selected(&mut material, steps);
+                    // same kind, so this only writes the material buffer
+                    if let Err(e) = self.appearance.set_material(&self.init.queue, material) {
+                        println!("{}", e);
+                    }
+                    true
+                }
+                Some(Action::SelectPreviousParam) => {
+                    self.params.select_previous();
+                    println!("{}", self.params.describe(&self.simple_surface, 20));
+                    true
+                }
+                Some(Action::SelectNextParam) => {
+                    self.params.select_next();
+                    println!("{}", self.params.describe(&self.simple_surface, 20));
+                    true
+                }
+                Some(action @ (Action::DecreaseParam | Action::IncreaseParam)) => {
+                    let steps = if action == Action::DecreaseParam { -1.0 } else { 1.0 };
+                    self.recreate_buffers |=
+                        self.params.nudge_selected(&mut self.simple_surface, steps);
+                    println!("{}", self.params.describe(&self.simple_surface, 20));
+                    true
+                }
+                // the sliders switch between the surface and the material values
+                Some(Action::ToggleMaterialSliders) => {
+                    self.tuning_material = !self.tuning_material;
+                    match self.tuning_material {
+                        true => println!("sliders: material"),
+                        false => println!("sliders: surface"),
+                    }
+                    true
+                }
+                // turns the directional light by 45 degrees around the vertical
+                Some(Action::TurnLightRight) => {
+                    let mut light = self.appearance.light();
+                    let [x, y, z] = light.direction;
+                    let (sin, cos) = std::f32::consts::FRAC_PI_4.sin_cos();
+                    light.direction = [x * cos - z * sin, y, x * sin + z * cos];
+                    self.appearance.set_light(&self.init.queue, light);
+                    true
+                }
+                Some(Action::CycleColorField) => {
+                    // color by own height, then by the height of each other surface type
+                    let own = self.simple_surface.surface_type;
+                    let offset = self.simple_surface.color_field.map_or(0, |f| (f + 3 - own) % 3);
+                    self.simple_surface.color_field = (offset < 2).then(|| (own + offset + 1) % 3);
+                    self.print_color_field();
+                    true
+                }
+                Some(Action::ToggleMorph) => {
+                    self.morph_enabled = !self.morph_enabled;
+                    let ss = &mut self.simple_surface;
+                    if self.morph_enabled {
+                        self.pre_morph =
+                            (ss.scale, [ss.x_resolution, ss.z_resolution], ss.colormap_range);
+                    } else {
+                        // the timeline changed the grid, so go back to the pre-morph one
+                        let (scale, [x, z], range) = self.pre_morph;
+                        (ss.scale, ss.x_resolution, ss.z_resolution) = (scale, x, z);
+                        ss.colormap_range = range;
+                        self.recreate_buffers = true;
+                    }
+                    true
+                }
+                Some(Action::ToggleGpuSurface) => {
+                    // generate the vertices with compute shaders instead of on the CPU
+                    if let Err(e) = self.init.capabilities.require(Subsystem::GpuSurfaces) {
+                        println!("{}", e);
+                        return true;
+                    }
+                    self.gpu_surface = match self.gpu_surface {
+                        Some(_) => None,
+                        None => Some((
+                            self.simple_surface.surface_type,
+                            GpuSurface::from_simple(&self.init.device, &self.simple_surface),
+                        )),
+                    };
+                    self.displaced_surface = None;
+                    let mode = if self.gpu_surface.is_some() { "gpu" } else { "cpu" };
+                    println!("surface generation: {}", mode);
+                    true
+                }
+                Some(Action::ToggleVertexDisplacement) => {
+                    // evaluate the surface in the vertex shader, writing only the uniforms
+                    self.displaced_surface = match self.displaced_surface {
+                        Some(_) => None,
+                        None => Some(DisplacedSurface::from_simple(
+                            &self.init.device,
+                            &self.simple_surface,
+                        )),
+                    };
+                    self.gpu_surface = None;
+                    let mode = match self.displaced_surface {
+                        Some(_) => "vertex shader",
+                        None => "cpu",
+                    };
+                    println!("surface generation: {}", mode);
+                    true
+                }
+                // colors the shape by how fast each vertex moves
+                Some(Action::ToggleHeat) => {
+                    self.show_heat = !self.show_heat;
+                    self.motion.clear();
+                    let on_gpu = self.gpu_surface.is_some() || self.displaced_surface.is_some();
+                    match (self.show_heat, on_gpu) {
+                        (true, true) => {
+                            println!("heat coloring needs the CPU vertices (keys c and C)")
+                        }
+                        (true, false) => println!("heat coloring on"),
+                        (false, _) => println!("heat coloring off"),
+                    }
+                    true
+                }
+                // cycles a rig of colored point lights, a spot and a dim directional light,
+                // then the preset rigs, back to the single directional light
+                Some(Action::CycleLights) => {
+                    if self.material != MaterialKind::Phong {
+                        println!("multiple lights need --material phong");
+                        return true;
+                    }
+                    let last = LightRig::ALL[LightRig::ALL.len() - 1];
+                    (self.show_lights, self.light_rig) = match self.light_rig {
+                        _ if !self.show_lights => (true, None),
+                        None => (true, Some(LightRig::ALL[0])),
+                        Some(rig) if rig == last => (false, None),
+                        Some(rig) => (true, Some(rig.next())),
+                    };
+                    match (self.show_lights, self.light_rig) {
+                        (true, None) => println!("lights: {} in the rig", light_rig(0.0).len()),
+                        (true, Some(rig)) => println!("lights: {} rig", rig.name()),
+                        (false, _) => println!("lights: directional"),
+                    }
+                    true
+                }
+                // gizmo: off, translate arrows, rotate rings; n toggles snapping
+                Some(Action::CycleGizmo) => {
+                    self.gizmo.end_drag();
+                    (self.show_gizmo, self.gizmo.mode) = match (self.show_gizmo, self.gizmo.mode) {
+                        (false, _) => (true, GizmoMode::Translate),
+                        (true, GizmoMode::Translate) => (true, GizmoMode::Rotate),
+                        (true, GizmoMode::Rotate) => (false, GizmoMode::Translate),
+                    };
+                    match self.show_gizmo {
+                        true => println!("gizmo: {:?}", self.gizmo.mode),
+                        false => println!("gizmo off"),
+                    }
+                    true
                 }
-                Key::Character("f") => {
+                Some(Action::ToggleSnapping) => {
+                    let snap = &mut self.gizmo.snap;
+                    (snap.translate, snap.rotate) = match snap.translate {
+                        Some(_) => (None, None),
+                        None => (Some(0.25), Some(15f32.to_radians())),
+                    };
+                    let on = snap.translate.is_some();
+                    println!("gizmo snapping: {}", if on { "on" } else { "off" });
+                    true
+                }
+                // writes the current surface to surface.obj and surface.ply
+                Some(Action::ExportMesh) => {
+                    self.export_mesh();
+                    true
+                }
+                Some(Action::SaveScene) => {
+                    match self.scene().save(&self.scene_file) {
+                        Ok(_) => println!("scene saved to {}", self.scene_file),
+                        Err(e) => println!("{}", e),
+                    }
+                    true
+                }
+                Some(Action::LoadScene) => {
+                    match Scene::load(&self.scene_file) {
+                        Ok(scene) => self.set_scene(&scene),
+                        Err(e) => println!("{}", e),
+                    }
+                    true
+                }
+                // area, volume if closed, centroid and inertia of the current mesh
+                Some(Action::PrintMetrics) => {
+                    println!("{}", self.simple_surface.new().metrics());
+                    true
+                }
+                // times the main pass on the GPU, to compare the plot types
+                Some(Action::ToggleGpuTimer) => {
+                    if let Err(e) = self.init.capabilities.require(Subsystem::TimestampProfiling) {
+                        println!("{}", e);
+                        return true;
+                    }
+                    self.gpu_timer = match self.gpu_timer {
+                        Some(_) => None,
+                        None => Some(ws::GpuTimer::new(&self.init.device, &self.init.queue, 1)),
+                    };
+                    self.gpu_ms = None;
+                    println!("gpu timer: {}", if self.gpu_timer.is_some() { "on" } else { "off" });
+                    true
+                }
+                Some(Action::ToggleStats) => {
+                    self.show_stats = !self.show_stats;
+                    println!("stats overlay: {}", if self.show_stats { "on" } else { "off" });
+                    true
+                }
+                Some(Action::ToggleRecording) => {
+                    if self.recorder.toggle() {
+                        println!("recording, {} again to stop", app::key_name(key));
+                    } else {
+                        self.save_recording();
+                    }
+                    true
+                }
+                Some(Action::ToggleProbe) => {
+                    self.probing = !self.probing;
+                    let icon = match self.probing {
+                        true => self.crosshair.cursor_icon,
+                        false => CursorIcon::Default,
+                    };
+                    self.init.window.set_cursor(icon);
+                    println!("probe mode: {}", if self.probing { "on" } else { "off" });
+                    true
+                }
+                Some(Action::DecreaseRotationSpeed) => {
                     self.rotation_speed -= 0.1;
                     if self.rotation_speed < 0.0 {
                         self.rotation_speed = 0.0;
                     }
-                    return true;
+                    true
                 }
                 _ => false,
             },
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = [position.x as f32, position.y as f32];
+                false
+            }
+            // clicking the surface probes the value under the cursor
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => {
+                if let Some(event) = self.probe() {
+                    self.probe_hooks.emit(&event);
+                }
+                true
+            }
             _ => false,
-        }
+        };
+        moved || handled
     }
 
-    pub fn update(&mut self, dt: std::time::Duration) {
+    fn update(&mut self, dt: std::time::Duration) {
+        // drive the surface parameters from the morph timeline
+        if self.morph_enabled {
+            self.recreate_buffers |= self
+                .morph_timeline
+                .apply(&mut self.simple_surface, dt.as_secs_f32());
+        }
+
         // update uniform buffer
         let dt1 = self.rotation_speed * dt.as_secs_f32();
 
-        let model_mat = ws::create_model_mat(
-            [0.0, 1.0, 0.0],
-            [dt1.sin(), dt1.cos(), 0.0],
-            [1.0, 1.0, 1.0],
-        );
+        let model_mat = self.gizmo.model_mat()
+            * ws::create_model_mat([0.0, 0.0, 0.0], [dt1.sin(), dt1.cos(), 0.0], [1.0, 1.0, 1.0]);
         let view_project_mat = self.project_mat * self.view_mat;
+        let eye = self.camera.eye();
+        self.appearance.set_eye_position(&self.init.queue, eye.into());
+        if self.show_lights {
+            let lights = match self.light_rig {
+                Some(rig) => rig.lights(eye.into(), self.camera.target.into()),
+                None => light_rig(dt.as_secs_f32()),
+            };
+            self.light_set.write(&self.init.queue, &lights);
+        }
 
         let normal_mat = (model_mat.invert().unwrap()).transpose();
+        self.model_mat = model_mat;
+        if self.show_gizmo {
+            self.write_gizmo();
+        }
 
         let model_ref: &[f32; 16] = model_mat.as_ref();
         let view_projection_ref: &[f32; 16] = view_project_mat.as_ref();
@@ -397,60 +794,87 @@ impl State {
 
         self.init
             .queue
-            .write_buffer(&self.uniform_buffers[0], 0, cast_slice(view_projection_ref));
+            .write_buffer(&self.vert_uniform_buffer, 0, cast_slice(view_projection_ref));
         self.init
             .queue
-            .write_buffer(&self.uniform_buffers[0], 64, cast_slice(model_ref));
+            .write_buffer(&self.vert_uniform_buffer, 64, cast_slice(model_ref));
         self.init
             .queue
-            .write_buffer(&self.uniform_buffers[0], 128, cast_slice(normal_ref));
+            .write_buffer(&self.vert_uniform_buffer, 128, cast_slice(normal_ref));
+        // the crosshair follows the surface as it moves under the cursor
+        let mut lines = self.guides.lines(self.surface_bounds, &model_mat);
+        if self.probing
+            && let Some(hit) = self.pick()
+        {
+            lines.extend(self.crosshair.lines(&hit, &model_mat));
+        }
+        let (device, queue) = (&self.init.device, &self.init.queue);
+        self.guides
+            .write_lines(device, queue, &view_project_mat, &lines);
+        self.normal_lines
+            .update(device, queue, &view_project_mat, &model_mat);
 
-        // recreate vertex and index buffers
+        // resize vertex and index buffers, reallocating only when the data outgrows them
         if self.recreate_buffers {
             let data = create_vertices(self.simple_surface.new());
+            self.surface_bounds = gizmo::bounds(data.0.iter().map(|v| v.position));
             self.indices_lens = vec![data.2.len() as u32, data.3.len() as u32];
             let vertex_data = [data.0, data.1];
             let index_data = [data.2, data.3];
 
             for i in 0..2 {
-                self.vertex_buffers[i].destroy();
-                self.vertex_buffers[i] =
-                    self.init
-                        .device
-                        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                            label: Some("Vertex Buffer"),
-                            contents: cast_slice(&vertex_data[i]),
-                            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-                        });
-                self.index_buffers[i].destroy();
-                self.index_buffers[i] =
-                    self.init
-                        .device
-                        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                            label: Some("Index Buffer"),
-                            contents: cast_slice(&index_data[i]),
-                            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
-                        });
+                let (device, queue) = (&self.init.device, &self.init.queue);
+                self.vertex_buffers[i].write(device, queue, cast_slice(&vertex_data[i]));
+                self.index_buffers[i].write(device, queue, cast_slice(&index_data[i]));
             }
             self.recreate_buffers = false;
         }
 
-        // update vertex buffer for every frame
-        self.simple_surface.t = self.animation_speed * dt.as_secs_f32();
-        let data = create_vertices(self.simple_surface.new());
-        self.init
-            .queue
-            .write_buffer(&self.vertex_buffers[0], 0, cast_slice(&data.0));
-        self.init
-            .queue
-            .write_buffer(&self.vertex_buffers[1], 0, cast_slice(&data.1));
+        // update vertex buffer for every frame; on the GPU the compute pass is recorded
+        // in render; a morph timeline with a T track has already set the time
+        if !(self.morph_enabled && self.morph_timeline.drives(SurfaceField::T)) {
+            self.simple_surface.t = self.animation_speed * dt.as_secs_f32();
+        }
+        if let Some((surface_type, gpu_surface)) = &mut self.gpu_surface {
+            if *surface_type != self.simple_surface.surface_type {
+                *surface_type = self.simple_surface.surface_type;
+                *gpu_surface = GpuSurface::from_simple(&self.init.device, &self.simple_surface);
+            }
+            let resolution =
+                [self.simple_surface.x_resolution as u32, self.simple_surface.z_resolution as u32];
+            gpu_surface.set_resolution(&self.init.device, resolution);
+            gpu_surface.apply_simple(&self.simple_surface);
+            // the generated normals stay on the GPU
+            self.normal_lines.set_mesh(&sd::ISurfaceOutput::default());
+            return;
+        }
+        if let Some(displaced) = &mut self.displaced_surface {
+            displaced.apply_simple(&self.init.device, &self.simple_surface);
+            displaced.write(&self.init.queue);
+            self.normal_lines.set_mesh(&sd::ISurfaceOutput::default());
+            return;
+        }
+        let output = self.simple_surface.new();
+        self.surface_bounds = gizmo::bounds(output.positions.iter().copied());
+        self.normal_lines.set_mesh(&output);
+        if self.show_heat {
+            let (device, queue) = (&self.init.device, &self.init.queue);
+            self.motion
+                .push(device, queue, &output.positions, dt.as_secs_f32());
+            let heat = [self.motion.speed_factor(), HEAT_GAIN, 0.0, 0.0];
+            queue.write_buffer(&self.vert_uniform_buffer, 192, cast_slice(&heat));
+        }
+        let data = create_vertices(output);
+        self.vertex_buffers[0].write(&self.init.device, &self.init.queue, cast_slice(&data.0));
+        self.vertex_buffers[1].write(&self.init.device, &self.init.queue, cast_slice(&data.1));
     }
 
-    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        if self.show_stats {
+            self.write_stats();
+        }
         let output = self.init.surface.get_current_texture()?;
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        let view = self.init.surface_view(&output.texture);
 
         let mut encoder =
             self.init
@@ -459,58 +883,434 @@ impl State {
                     label: Some("Render Encoder"),
                 });
 
+        let targets = ws::FrameTargets::new(
+            &self.init,
+            &view,
+            &self.targets.msaa_texture_view,
+            &self.targets.depth_texture_view,
+        );
+        self.render_hooks.run_pre_passes(&mut encoder, &targets);
+        if let Some((_, gpu_surface)) = &self.gpu_surface {
+            gpu_surface.compute(&self.init.queue, &mut encoder);
+        }
+
+        let plot_type = if self.plot_type == 1 {
+            "shape_only"
+        } else if self.plot_type == 2 {
+            "wireframe_only"
+        } else {
+            "both"
+        };
+
         {
-            let color_attach = ws::create_color_attachment(&view);
-            let msaa_attach = ws::create_msaa_color_attachment(&view, &self.msaa_texture_view);
+            let depth_attachment =
+                ws::create_depth_stencil_attachment(&self.targets.depth_texture_view);
+            let timestamp_writes =
+                self.gpu_timer.as_mut().and_then(|timer| timer.timestamp_writes(plot_type));
+            let mut render_pass = app::begin_timed_main_pass(
+                &mut encoder,
+                &self.init,
+                &view,
+                &self.targets.msaa_texture_view,
+                depth_attachment,
+                timestamp_writes,
+            );
 
-            let color_attachment = if self.init.sample_count == 1 {
-                color_attach
-            } else {
-                msaa_attach
-            };
-            let depth_attachment = ws::create_depth_stencil_attachment(&self.depth_texture_view);
-
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(color_attachment)],
-                depth_stencil_attachment: Some(depth_attachment),
-                occlusion_query_set: None,
-                timestamp_writes: None,
-            });
+            let (vertex_buffers, index_buffers, indices_lens, index_format) =
+                match &self.gpu_surface {
+                    Some((_, gpu)) => (
+                        [0, 1].map(|i| gpu.vertex_buffers[i].slice(..)),
+                        [0, 1].map(|i| gpu.index_buffers[i].slice(..)),
+                        &gpu.indices_lens[..],
+                        gpu.index_format(),
+                    ),
+                    None => (
+                        [0, 1].map(|i| self.vertex_buffers[i].slice()),
+                        [0, 1].map(|i| self.index_buffers[i].slice()),
+                        &self.indices_lens[..],
+                        wgpu::IndexFormat::Uint16,
+                    ),
+                };
 
-            let plot_type = if self.plot_type == 1 {
-                "shape_only"
-            } else if self.plot_type == 2 {
-                "wireframe_only"
-            } else {
-                "both"
-            };
+            // displaced surfaces have no vertices to bind, the index is the grid point
+            let displaced = self.displaced_surface.as_ref();
+            let show_shape = plot_type == "shape_only" || plot_type == "both";
+            let show_wireframe = plot_type == "wireframe_only" || plot_type == "both";
+            if let Some(displaced) = displaced {
+                let group = gpu_surface::DISPLACEMENT_GROUP;
+                render_pass.set_bind_group(group, &displaced.bind_group, &[]);
+                let [indices, indices2] = &displaced.index_buffers;
+                let index_format = displaced.index_format();
+                self.pipelines.bind_groups(&mut render_pass);
+                if show_shape {
+                    render_pass.set_pipeline(&self.displaced_pipelines[0]);
+                    render_pass.set_index_buffer(indices.slice(..), index_format);
+                    render_pass.draw_indexed(0..displaced.indices_lens[0], 0, 0..1);
+                }
+                if show_wireframe {
+                    render_pass.set_pipeline(&self.displaced_pipelines[1]);
+                    render_pass.set_index_buffer(indices2.slice(..), index_format);
+                    render_pass.draw_indexed(0..displaced.indices_lens[1], 0, 0..1);
+                }
+            }
+
+            if show_shape && displaced.is_none() {
+                self.pipelines.bind(&mut render_pass, false);
+                if self.show_heat && self.gpu_surface.is_none() {
+                    render_pass.set_pipeline(&self.heat_pipeline);
+                    render_pass.set_vertex_buffer(1, self.motion.previous().slice());
+                } else if self.show_lights {
+                    render_pass.set_pipeline(&self.lights_pipeline);
+                    render_pass.set_bind_group(
+                        lighting::LIGHT_SET_GROUP,
+                        &self.light_set.bind_group,
+                        &[],
+                    );
+                }
+                render_pass.set_vertex_buffer(0, vertex_buffers[0]);
+                render_pass.set_index_buffer(index_buffers[0], index_format);
+                render_pass.draw_indexed(0..indices_lens[0], 0, 0..1);
+            }
 
-            if plot_type == "shape_only" || plot_type == "both" {
-                render_pass.set_pipeline(&self.pipelines[0]);
-                render_pass.set_vertex_buffer(0, self.vertex_buffers[0].slice(..));
-                render_pass
-                    .set_index_buffer(self.index_buffers[0].slice(..), wgpu::IndexFormat::Uint16);
-                render_pass.set_bind_group(0, &self.uniform_bind_groups[0], &[]);
-                render_pass.set_bind_group(1, &self.uniform_bind_groups[1], &[]);
-                render_pass.draw_indexed(0..self.indices_lens[0], 0, 0..1);
+            if show_wireframe && displaced.is_none() {
+                self.pipelines.bind(&mut render_pass, true);
+                render_pass.set_vertex_buffer(0, vertex_buffers[1]);
+                render_pass.set_index_buffer(index_buffers[1], index_format);
+                render_pass.draw_indexed(0..indices_lens[1], 0, 0..1);
             }
 
-            if plot_type == "wireframe_only" || plot_type == "both" {
-                render_pass.set_pipeline(&self.pipelines[1]);
-                render_pass.set_vertex_buffer(0, self.vertex_buffers[1].slice(..));
-                render_pass
-                    .set_index_buffer(self.index_buffers[1].slice(..), wgpu::IndexFormat::Uint16);
-                render_pass.set_bind_group(0, &self.uniform_bind_groups[2], &[]);
-                render_pass.set_bind_group(1, &self.uniform_bind_groups[3], &[]);
-                render_pass.draw_indexed(0..self.indices_lens[1], 0, 0..1);
+            if self.show_gizmo {
+                self.pipelines.bind(&mut render_pass, true);
+                render_pass.set_vertex_buffer(0, self.gizmo_buffer.slice(..));
+                render_pass.draw(0..self.gizmo_count, 0..1);
             }
+            self.guides.draw(&mut render_pass);
+            self.normal_lines.draw(&mut render_pass);
+            if self.show_stats {
+                self.overlay_lines.draw(&mut render_pass);
+            }
+        }
+
+        self.render_hooks.run_post_passes(&mut encoder, &targets);
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.resolve(&mut encoder);
         }
 
-        self.fps_counter.print_fps(5);
+        if !self.show_stats {
+            self.fps_counter.log_fps(5);
+        }
+        if self.init.config.usage.contains(wgpu::TextureUsages::COPY_SRC) {
+            let format = self.init.config.format;
+            self.recorder
+                .copy_frame(&self.init.device, &mut encoder, &output.texture, format);
+        }
         self.init.queue.submit(std::iter::once(encoder.finish()));
+        if let Some(timer) = &mut self.gpu_timer {
+            match timer.read(&self.init.device) {
+                // shown by the overlay, logged without it like the frame rate
+                Ok(times) => {
+                    self.gpu_ms = Some(times.iter().map(|time| time.ms as f32).sum());
+                    if !self.show_stats {
+                        timer.log_times(5);
+                    }
+                }
+                Err(e) => {
+                    println!("gpu timer stopped: {}", e);
+                    self.gpu_timer = None;
+                    self.gpu_ms = None;
+                }
+            }
+        }
+        match self.recorder.read_frame(&self.init.device) {
+            Ok(true) => self.save_recording(),
+            Ok(false) => {}
+            Err(e) => {
+                println!("recording stopped: {}", e);
+                self.recorder.stop();
+            }
+        }
         output.present();
 
         Ok(())
     }
 }
+
+impl State {
+    // world space ray through the cursor
+    fn export_mesh(&mut self) {
+        let output = self.simple_surface.new();
+        for (path, result) in [
+            ("surface.obj", export::export_obj(&output, "surface.obj")),
+            ("surface.ply", export::export_ply(&output, "surface.ply")),
+        ] {
+            match result {
+                Ok(_) => println!("surface saved to {}", path),
+                Err(e) => println!("failed to save {}: {}", path, e),
+            }
+        }
+    }
+
+    // the surface, camera, material and plot type as a scene file would keep them
+    fn scene(&self) -> Scene {
+        let ss = &self.simple_surface;
+        let mut scene = Scene {
+            surface_type: ss.surface_type,
+            resolution: [ss.x_resolution, ss.z_resolution],
+            scale: ss.scale,
+            colormap: ss.colormap_name.clone(),
+            wireframe_color: ss.wireframe_color.clone(),
+            material: self.appearance.material(),
+            msaa: self.init.sample_count,
+            plot_type: self.plot_type,
+            ..Default::default()
+        };
+        scene.set_camera(&self.camera);
+        scene
+    }
+
+    // the sample count and the material kind stay those the pipelines were built with
+    fn set_scene(&mut self, scene: &Scene) {
+        let ss = &mut self.simple_surface;
+        ss.surface_type = scene.surface_type % 3;
+        [ss.x_resolution, ss.z_resolution] = scene.resolution.map(|n| n.max(1));
+        ss.scale = scene.scale;
+        ss.colormap_name = scene.colormap.clone();
+        ss.wireframe_color = scene.wireframe_color.clone();
+        self.camera = scene.camera();
+        self.view_mat = self.camera.view_mat();
+        self.plot_type = scene.plot_type % 3;
+        if let Err(e) = self.appearance.set_material(&self.init.queue, scene.material) {
+            println!("{}; restart with --scene {}", e, self.scene_file);
+        }
+        if scene.msaa != self.init.sample_count {
+            println!("msaa {} needs a restart with --scene {}", scene.msaa, self.scene_file);
+        }
+        self.recreate_buffers = true;
+    }
+
+    fn save_recording(&self) {
+        let path = "recording.gif";
+        match self.recorder.save(path) {
+            Ok(_) => println!("{} frames saved to {}", self.recorder.frames().len(), path),
+            Err(e) => println!("failed to save {}: {}", path, e),
+        }
+    }
+
+    fn cursor_ray(&self) -> Option<Ray> {
+        let size = [self.init.size.width as f32, self.init.size.height as f32];
+        Ray::from_screen(&(self.project_mat * self.view_mat), self.cursor, size)
+    }
+
+    // with the gizmo shown, a left drag on one of its handles moves the surface instead
+    // of the camera; returns true if the gizmo took the event
+    fn gizmo_input(&mut self, event: &WindowEvent) -> bool {
+        if !self.show_gizmo {
+            return false;
+        }
+        match event {
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => self.cursor_ray().is_some_and(|ray| self.gizmo.begin_drag(&ray)),
+            WindowEvent::MouseInput {
+                state: ElementState::Released,
+                button: MouseButton::Left,
+                ..
+            } if self.gizmo.is_dragging() => {
+                self.gizmo.end_drag();
+                let [x, y, z] = self.gizmo.position;
+                println!("surface at ({x:.2}, {y:.2}, {z:.2})");
+                true
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = [position.x as f32, position.y as f32];
+                let Some(ray) = self.cursor_ray() else {
+                    return false;
+                };
+                if self.gizmo.is_dragging() {
+                    self.gizmo.drag(&ray);
+                    return true;
+                }
+                self.gizmo.hovered = self.gizmo.pick(&ray);
+                false
+            }
+            _ => false,
+        }
+    }
+
+    fn probe(&mut self) -> Option<ProbeEvent> {
+        Some(ProbeEvent::from_hit(&self.pick()?))
+    }
+
+    // the surface point under the cursor, in model space
+    fn pick(&mut self) -> Option<PickHit> {
+        let ray = self.cursor_ray()?;
+        let ray = ray.transform(&self.model_mat.invert()?);
+        picking::pick_mesh(&ray, &self.simple_surface.new())
+    }
+
+    // the gizmo is drawn with the wireframe pipeline, so its world space lines are
+    // taken into the model space of the surface; the normals face the eye
+    fn write_gizmo(&mut self) {
+        let Some(inverse) = self.model_mat.invert() else {
+            return;
+        };
+        let eye = inverse.transform_point(self.camera.eye());
+        let vertices: Vec<Vertex> = self
+            .gizmo
+            .line_vertices()
+            .into_iter()
+            .map(|(position, color)| {
+                let position = inverse.transform_point(Point3::from(position));
+                Vertex {
+                    position: position.into(),
+                    normal: (eye - position).normalize().into(),
+                    color,
+                }
+            })
+            .collect();
+        self.gizmo_count = vertices.len() as u32;
+        self.init
+            .queue
+            .write_buffer(&self.gizmo_buffer, 0, cast_slice(&vertices));
+    }
+
+    // counts the frame and writes the overlay of the draws the plot type makes: the
+    // shape and the wireframe each have their own vertices, the gizmo its lines
+    fn write_stats(&mut self) {
+        self.fps_counter.tick();
+        let vertex_count = match (&self.gpu_surface, &self.displaced_surface) {
+            (Some((_, gpu)), _) => gpu.vertex_count(),
+            (None, Some(displaced)) => displaced.vertex_count(),
+            (None, None) => self.vertex_buffers[0].len() as usize / std::mem::size_of::<Vertex>(),
+        };
+        let surface_draws = if self.plot_type == 1 || self.plot_type == 2 { 1 } else { 2 };
+        let mut scene = SceneStats {
+            vertices: surface_draws * vertex_count,
+            draw_calls: surface_draws as u32,
+            gpu_ms: self.gpu_ms,
+        };
+        if self.show_gizmo {
+            scene.vertices += self.gizmo_count as usize;
+            scene.draw_calls += 1;
+        }
+        let size = [self.init.config.width, self.init.config.height];
+        let lines = self.stats_overlay.lines(&self.fps_counter, &scene, size);
+        self.overlay_lines
+            .write(&self.init.device, &self.init.queue, &Matrix4::identity(), &lines);
+    }
+
+    // text colorbar of the color field: its surface type and data range
+    fn print_color_field(&self) {
+        let ss = &self.simple_surface;
+        match ss.color_field {
+            Some(field) => {
+                let [min, max] = ss.color_field_range(&ss.color_field_fn(field));
+                println!(
+                    "height: surface type {}, color: surface type {field} [{min:.3}, {max:.3}]",
+                    ss.surface_type
+                );
+            }
+            None => println!("color: height"),
+        }
+    }
+}
+
+// speed at which the heat coloring saturates: 1 - exp(-gain * speed)
+const HEAT_GAIN: f32 = 1.5;
+
+const MAX_LIGHTS: usize = 8;
+
+// two colored point lights circling the surface, a spot from above and a dim key light
+fn light_rig(t: f32) -> LightSet {
+    let (s, c) = (0.7 * t).sin_cos();
+    LightSet::new()
+        .with(Light::directional([-0.5, -0.5, -0.5]).intensity(0.3))
+        .with(Light::point([2.5 * c, 2.0, 2.5 * s], 8.0).color([1.0, 0.35, 0.2]).intensity(6.0))
+        .with(Light::point([-2.5 * c, 2.0, -2.5 * s], 8.0).color([0.2, 0.45, 1.0]).intensity(6.0))
+        .with(
+            Light::spot([0.0, 5.0, 0.0], [0.0, -1.0, 0.0], 25f32.to_radians(), 10.0)
+                .color([1.0, 0.95, 0.8])
+                .intensity(20.0),
+        )
+}
+
+// standard shaders, the fragment one of the material kind and the multi-light one, with
+// the snippets of hook=file.wgsl arguments spliced in
+fn load_shaders(
+    device: &wgpu::Device,
+    args: &[String],
+    material: MaterialKind,
+) -> anyhow::Result<(wgpu::ShaderModule, wgpu::ShaderModule, wgpu::ShaderModule)> {
+    let vs_source = include_str!("shader_vert.wgsl");
+    let fs_source = material.fragment_source();
+    let lights_source = lighting::multi_light_fragment();
+    let hooks = ShaderHooks::from_args(args)?;
+    hooks.check(&[vs_source, fs_source])?;
+    Ok((
+        hooks.create_shader_module(device, "shader_vert.wgsl", vs_source)?,
+        hooks.create_shader_module(device, material.fragment_label(), fs_source)?,
+        hooks.create_shader_module(device, "multi_light_frag.wgsl", &lights_source)?,
+    ))
+}
+
+// scripted morphing sequence: scale pulse, resolution sweep and a moving colormap window
+fn morph_timeline() -> Timeline<sd::ISimpleSurface> {
+    let mut timeline = Timeline::new();
+    timeline.looping = true;
+    timeline.add_field(
+        SurfaceField::Scale,
+        AnimationCurve::new()
+            .key(0.0, 3.0, Easing::Linear)
+            .key(4.0, 1.5, Easing::EaseInOut)
+            .key(8.0, 3.0, Easing::EaseInOut)
+            .key(12.0, 3.0, Easing::Linear),
+    );
+    timeline.add_field(
+        SurfaceField::UResolution,
+        AnimationCurve::new()
+            .key(0.0, 30.0, Easing::Linear)
+            .key(6.0, 12.0, Easing::Step)
+            .key(12.0, 30.0, Easing::Step),
+    );
+    timeline.add_field(
+        SurfaceField::VResolution,
+        AnimationCurve::new()
+            .key(0.0, 30.0, Easing::Linear)
+            .key(6.0, 12.0, Easing::Step)
+            .key(12.0, 30.0, Easing::Step),
+    );
+    timeline.add_field(
+        SurfaceField::ColormapMin,
+        AnimationCurve::new()
+            .key(0.0, -1.5, Easing::Linear)
+            .key(6.0, 0.0, Easing::Sine)
+            .key(12.0, -1.5, Easing::Sine),
+    );
+    timeline.add_field(
+        SurfaceField::ColormapMax,
+        AnimationCurve::new()
+            .key(0.0, 1.5, Easing::Linear)
+            .key(6.0, 0.5, Easing::Sine)
+            .key(12.0, 1.5, Easing::Sine),
+    );
+    timeline
+}
+
+// prints each probe with a pitch for the value over the height range of the default
+// scale; replace or extend the callbacks to drive audio or haptics
+fn default_probe_hooks(format: NumberFormat) -> ProbeHooks {
+    let mut hooks = ProbeHooks::default();
+    let hz = format.decimals(0).unit("Hz");
+    hooks.add(move |event| {
+        println!(
+            "probe: value = {}, gradient = [{}, {}], tone = {}",
+            format.format(event.value),
+            format.format(event.gradient[0]),
+            format.format(event.gradient[1]),
+            hz.format(event.tone_frequency([-3.0, 3.0], 220.0, 2.0))
+        );
+    });
+    hooks
+}
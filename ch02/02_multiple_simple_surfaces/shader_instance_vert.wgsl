@@ -17,15 +17,21 @@ struct Output {
     @location(2) vColor: vec4f,
 };
 
+// @hook vertex_declarations
+
 @vertex
 fn vs_main(in: Input) -> Output {    
     var output: Output;     
     let modelMat = modelMat[in.idx];
     let normalMat = normalMat[in.idx];
-    let mPosition = modelMat * in.position; 
+    var position = in.position;
+    var normal = in.normal;
+    var color = in.color;
+    // @hook vertex
+    let mPosition = modelMat * position; 
     output.vPosition = mPosition;                  
-    output.vNormal =  normalMat * in.normal;
+    output.vNormal =  normalMat * normal;
     output.position = vpMat * mPosition;   
-    output.vColor = in.color;            
+    output.vColor = color;            
     return output;
 }
\ No newline at end of file
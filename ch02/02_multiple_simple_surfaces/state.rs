@@ -1,29 +1,40 @@
 use std::sync::Arc;
 use bytemuck::cast_slice;
 use cgmath::{Matrix, Matrix4, SquareMatrix};
-use wgpu::util::DeviceExt;
-use winit::{
-    event::ElementState, event::KeyEvent, event::WindowEvent, keyboard::Key, keyboard::NamedKey,
-    window::Window,
-};
+use winit::{event::ElementState, event::KeyEvent, event::WindowEvent, window::Window};
 
+use wgpu_surfaces::app::{self, Action, InputMap, SurfaceApp};
+use wgpu_surfaces::appearance::DirectionalLight;
+use wgpu_surfaces::instancing::{Frustum, IPackedMeshes, InstanceBatches, LodLevels, MeshRange};
 use wgpu_surfaces::surface_data as sd;
+use wgpu_surfaces::cli::SurfaceArgs;
 use wgpu_surfaces::wgpu_simplified as ws;
 
 use crate::vertex::{create_vertices, Vertex};
 
+// default key bindings, remapped with --keymap (see app::InputMap)
+const KEYS: &[(Action, &str)] = &[
+    (Action::CyclePlotType, "Space"),
+    (Action::NextSurfaceType, "Control"),
+    (Action::CycleColormapDirection, "Alt"),
+    (Action::IncreaseAnimationSpeed, "q"),
+    (Action::DecreaseAnimationSpeed, "a"),
+    (Action::IncreaseRotationSpeed, "w"),
+    (Action::DecreaseRotationSpeed, "s"),
+    (Action::ToggleCulling, "c"),
+    (Action::ToggleLod, "l"),
+];
+
 pub struct State {
     init: ws::InitWgpu,
-    pipelines: Vec<wgpu::RenderPipeline>,
-    vertex_buffers: Vec<wgpu::Buffer>,
-    index_buffers: Vec<wgpu::Buffer>,
-    uniform_bind_groups: Vec<wgpu::BindGroup>,
-    uniform_buffers: Vec<wgpu::Buffer>,
+    pipelines: app::SurfacePipelines,
+    vertex_buffers: Vec<ws::GrowableBuffer>,
+    index_buffers: Vec<ws::GrowableBuffer>,
+    uniform_buffers: Vec<wgpu::Buffer>, // view-projection, model and normal matrices
+    targets: app::RenderTargets,
     view_mat: Matrix4<f32>,
     project_mat: Matrix4<f32>,
-    msaa_texture_view: wgpu::TextureView,
-    depth_texture_view: wgpu::TextureView,
-    indices_lens: Vec<u32>,
+    mesh_ranges: Vec<Vec<MeshRange>>, // per level: triangles, lines
     plot_type: u32,
     recreate_buffers: bool,
     animation_speed: f32,
@@ -33,18 +44,23 @@ pub struct State {
     z_num: u32,
     objects_count: u32,
 
+    // coarser surfaces for the instances far from the camera
+    camera_position: [f32; 3],
+    lod: LodLevels,
+    lod_on: bool,
+    culling: bool, // only the instances in view are written and drawn
+    bounding_radius: f32,
+    batches: InstanceBatches,
+
     simple_surface: sd::ISimpleSurface,
+    input_map: InputMap,
     fps_counter: ws::FpsCounter,
+    render_hooks: ws::RenderHooks,
 }
 
-impl State {
-    pub async fn new(
-        window: Arc<Window>,
-        sample_count: u32,
-        colormap_name: &str,
-        wireframe_color: &str,
-    ) -> Self {
-        let init = ws::InitWgpu::init_wgpu(window, sample_count).await;
+impl SurfaceApp for State {
+    async fn new(window: Arc<Window>, args: &SurfaceArgs) -> wgpu_surfaces::Result<Self> {
+        let init = ws::InitWgpu::init_wgpu(window, args.msaa, args.present_mode).await?;
 
         // Loading Shaders
         let vs_shader = init
@@ -58,7 +74,6 @@ impl State {
         let camera_position = (3.0, 4.5, 5.2).into();
         let look_direction = (0.0, 0.0, 0.0).into();
         let up_direction = cgmath::Vector3::unit_y();
-        let light_direction = [-0.5f32, -0.5, -0.5];
 
         let (view_mat, project_mat, vp_mat) = ws::create_vp_mat(
             camera_position,
@@ -101,65 +116,15 @@ impl State {
             mapped_at_creation: false,
         });
 
-        // create light uniform buffer. here we set eye_position = camera_position
-        let light_uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Light Uniform Buffer"),
-            size: 48,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        let eye_position: &[f32; 3] = camera_position.as_ref();
-        init.queue.write_buffer(
-            &light_uniform_buffer,
-            0,
-            cast_slice(light_direction.as_ref()),
-        );
-        init.queue
-            .write_buffer(&light_uniform_buffer, 16, cast_slice(eye_position));
-
-        // set specular light color to white
-        let specular_color: [f32; 3] = [1.0, 1.0, 1.0];
-        init.queue.write_buffer(
-            &light_uniform_buffer,
-            32,
-            cast_slice(specular_color.as_ref()),
-        );
-
-        // material uniform buffer
-        let material_uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Material Uniform Buffer"),
-            size: 16,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        // set default material parameters
-        let material = [0.1f32, 0.7, 0.4, 30.0];
-        init.queue
-            .write_buffer(&material_uniform_buffer, 0, cast_slice(material.as_ref()));
+        // light and material uniforms; here we set eye_position = camera_position
+        let light = DirectionalLight {
+            eye_position: camera_position.into(),
+            ..Default::default()
+        };
+        let lighting = app::LightUniforms::new(&init, &light, [0.1, 0.7, 0.4, 30.0]);
 
         // uniform bind group for vertex shader
-        let (vert_bind_group_layout, vert_bind_group) = ws::create_bind_group_storage(
-            &init.device,
-            vec![
-                wgpu::ShaderStages::VERTEX,
-                wgpu::ShaderStages::VERTEX,
-                wgpu::ShaderStages::VERTEX,
-            ],
-            vec![
-                wgpu::BufferBindingType::Uniform,
-                wgpu::BufferBindingType::Storage { read_only: true },
-                wgpu::BufferBindingType::Storage { read_only: true },
-            ],
-            &[
-                vp_uniform_buffer.as_entire_binding(),
-                model_uniform_buffer.as_entire_binding(),
-                normal_uniform_buffer.as_entire_binding(),
-            ],
-        );
-
-        let (vert_bind_group_layout2, vert_bind_group2) = ws::create_bind_group_storage(
+        let vert_bind_group = ws::create_bind_group_storage(
             &init.device,
             vec![
                 wgpu::ShaderStages::VERTEX,
@@ -176,141 +141,77 @@ impl State {
                 model_uniform_buffer.as_entire_binding(),
                 normal_uniform_buffer.as_entire_binding(),
             ],
-        );
-
-        // uniform bind group for fragment shader
-        let (frag_bind_group_layout, frag_bind_group) = ws::create_bind_group(
-            &init.device,
-            vec![wgpu::ShaderStages::FRAGMENT, wgpu::ShaderStages::FRAGMENT],
-            &[
-                light_uniform_buffer.as_entire_binding(),
-                material_uniform_buffer.as_entire_binding(),
-            ],
-        );
-        let (frag_bind_group_layout2, frag_bind_group2) = ws::create_bind_group(
-            &init.device,
-            vec![wgpu::ShaderStages::FRAGMENT, wgpu::ShaderStages::FRAGMENT],
-            &[
-                light_uniform_buffer.as_entire_binding(),
-                material_uniform_buffer.as_entire_binding(),
-            ],
-        );
+        )?;
 
-        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+        let vertex_buffer_layout = [wgpu::VertexBufferLayout {
             array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
             step_mode: wgpu::VertexStepMode::Vertex,
             attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3],
             // pos, norm, col
-        };
-
-        let pipeline_layout = init
-            .device
-            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&vert_bind_group_layout, &frag_bind_group_layout],
-                push_constant_ranges: &[],
-            });
-
-        let mut ppl = ws::IRenderPipeline {
-            vs_shader: Some(&vs_shader),
-            fs_shader: Some(&fs_shader),
-            pipeline_layout: Some(&pipeline_layout),
-            vertex_buffer_layout: &[vertex_buffer_layout],
-            ..Default::default()
-        };
-        let pipeline = ppl.new(&init);
-
-        let vertex_buffer_layout2 = wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3],
-            // pos, norm, col
-        };
-
-        let pipeline_layout2 =
-            init.device
-                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: Some("Render Pipeline Layout 2"),
-                    bind_group_layouts: &[&vert_bind_group_layout2, &frag_bind_group_layout2],
-                    push_constant_ranges: &[],
-                });
-
-        let mut ppl2 = ws::IRenderPipeline {
-            topology: wgpu::PrimitiveTopology::LineList,
-            vs_shader: Some(&vs_shader),
-            fs_shader: Some(&fs_shader),
-            pipeline_layout: Some(&pipeline_layout2),
-            vertex_buffer_layout: &[vertex_buffer_layout2],
-            ..Default::default()
-        };
-        let pipeline2 = ppl2.new(&init);
-
-        let msaa_texture_view = ws::create_msaa_texture_view(&init);
-        let depth_texture_view = ws::create_depth_view(&init);
+        }];
+        let pipelines = app::SurfacePipelines::new(
+            &init,
+            &vs_shader,
+            &fs_shader,
+            &vertex_buffer_layout,
+            vert_bind_group,
+            lighting.bind_group(&init.device)?,
+        )?;
+        let targets = app::RenderTargets::new(&init);
 
         let mut ss = sd::ISimpleSurface {
             scale: 0.5,
-            colormap_name: colormap_name.to_string(),
-            wireframe_color: wireframe_color.to_string(),
+            colormap_name: args.colormap.clone(),
+            wireframe_color: args.wireframe_color.clone(),
             ..Default::default()
         };
-        let data = create_vertices(ss.new());
+        if let Some(resolution) = args.resolution {
+            (ss.x_resolution, ss.z_resolution) = (resolution, resolution);
+        }
+        if let Some(surface_type) = args.surface_type {
+            ss.surface_type = surface_type;
+        }
+        let lod = LodLevels::halving(ss.x_resolution, 4, 12.0);
+        let data = pack_levels(&mut ss, &lod);
 
-        let vertex_buffer = init
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Vertex Buffer"),
-                contents: cast_slice(&data.0),
-                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            });
+        let vertex_buffer = ws::GrowableBuffer::new(
+            &init.device,
+            "Vertex Buffer",
+            wgpu::BufferUsages::VERTEX,
+            cast_slice(&data.0.vertices),
+        );
 
-        let vertex_buffer2 = init
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Vertex Buffer 2"),
-                contents: cast_slice(&data.1),
-                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            });
+        let vertex_buffer2 = ws::GrowableBuffer::new(
+            &init.device,
+            "Vertex Buffer 2",
+            wgpu::BufferUsages::VERTEX,
+            cast_slice(&data.1.vertices),
+        );
 
-        let index_buffer = init
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Index Buffer"),
-                contents: bytemuck::cast_slice(&data.2),
-                usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
-            });
+        let index_buffer = ws::GrowableBuffer::new(
+            &init.device,
+            "Index Buffer",
+            wgpu::BufferUsages::INDEX,
+            cast_slice(&data.0.indices),
+        );
 
-        let index_buffer2 = init
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Index Buffer 2"),
-                contents: bytemuck::cast_slice(&data.3),
-                usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
-            });
+        let index_buffer2 = ws::GrowableBuffer::new(
+            &init.device,
+            "Index Buffer 2",
+            wgpu::BufferUsages::INDEX,
+            cast_slice(&data.1.indices),
+        );
 
-        Self {
+        Ok(Self {
             init,
-            pipelines: vec![pipeline, pipeline2],
+            pipelines,
             vertex_buffers: vec![vertex_buffer, vertex_buffer2],
             index_buffers: vec![index_buffer, index_buffer2],
-            uniform_bind_groups: vec![
-                vert_bind_group,
-                frag_bind_group,
-                vert_bind_group2,
-                frag_bind_group2,
-            ],
-            uniform_buffers: vec![
-                vp_uniform_buffer,
-                model_uniform_buffer,
-                normal_uniform_buffer,
-                light_uniform_buffer,
-                material_uniform_buffer,
-            ],
+            uniform_buffers: vec![vp_uniform_buffer, model_uniform_buffer, normal_uniform_buffer],
+            targets,
             view_mat,
             project_mat,
-            msaa_texture_view,
-            depth_texture_view,
-            indices_lens: vec![data.2.len() as u32, data.3.len() as u32],
+            mesh_ranges: vec![data.0.ranges, data.1.ranges],
             plot_type: 1,
             recreate_buffers: false,
             animation_speed: 1.0,
@@ -320,39 +221,35 @@ impl State {
             z_num,
             objects_count,
 
+            camera_position: *camera_position.as_ref(),
+            lod,
+            lod_on: true,
+            culling: true,
+            bounding_radius: bounding_radius(&data.0.vertices),
+            batches: InstanceBatches::single(objects_count),
+
             simple_surface: ss,
+            input_map: InputMap::with_args(KEYS, args)?,
             fps_counter: ws::FpsCounter::default(),
-        }
+            render_hooks: ws::RenderHooks::default(),
+        })
     }
 
-    pub fn window(&self) -> &Window {
-        &self.init.window
+    fn init(&self) -> &ws::InitWgpu {
+        &self.init
     }
 
-    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
-        self.init.size
+    fn init_mut(&mut self) -> &mut ws::InitWgpu {
+        &mut self.init
     }
 
-    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
-        if new_size.width > 0 && new_size.height > 0 {
-            self.init.size = new_size;
-            // The surface needs to be reconfigured every time the window is resized.
-            self.init.config.width = new_size.width;
-            self.init.config.height = new_size.height;
-            self.init
-                .surface
-                .configure(&self.init.device, &self.init.config);
-
-            self.project_mat =
-                ws::create_projection_mat(new_size.width as f32 / new_size.height as f32, true);
-            self.depth_texture_view = ws::create_depth_view(&self.init);
-            if self.init.sample_count > 1 {
-                self.msaa_texture_view = ws::create_msaa_texture_view(&self.init);
-            }
-        }
+    fn resized(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.project_mat =
+            ws::create_projection_mat(new_size.width as f32 / new_size.height as f32, true);
+        self.targets.resize(&self.init);
     }
 
-    pub fn input(&mut self, event: &WindowEvent) -> bool {
+    fn input(&mut self, event: &WindowEvent) -> bool {
         match event {
             WindowEvent::KeyboardInput {
                 event:
@@ -362,41 +259,53 @@ impl State {
                         ..
                     },
                 ..
-            } => match key.as_ref() {
-                Key::Named(NamedKey::Space) => {
+            } => match self.input_map.action(key) {
+                Some(Action::CyclePlotType) => {
                     self.plot_type = (self.plot_type + 1) % 3;
-                    return true;
+                    true
                 }
-                Key::Named(NamedKey::Control) => {
+                Some(Action::NextSurfaceType) => {
                     self.simple_surface.surface_type = (self.simple_surface.surface_type + 1) % 3;
-                    return true;
+                    true
                 }
-                Key::Named(NamedKey::Alt) => {
+                Some(Action::CycleColormapDirection) => {
                     self.simple_surface.colormap_direction =
                         (self.simple_surface.colormap_direction + 1) % 3;
-                    return true;
+                    true
                 }
-                Key::Character("q") => {
+                Some(Action::IncreaseAnimationSpeed) => {
                     self.animation_speed += 0.1;
-                    return true;
+                    true
                 }
-                Key::Character("a") => {
+                Some(Action::DecreaseAnimationSpeed) => {
                     self.animation_speed -= 0.1;
                     if self.animation_speed < 0.0 {
                         self.animation_speed = 0.0;
                     }
-                    return true;
+                    true
                 }
-                Key::Character("w") => {
+                Some(Action::IncreaseRotationSpeed) => {
                     self.rotation_speed += 0.1;
-                    return true;
+                    true
                 }
-                Key::Character("s") => {
+                Some(Action::DecreaseRotationSpeed) => {
                     self.rotation_speed -= 0.1;
                     if self.rotation_speed < 0.0 {
                         self.rotation_speed = 0.0;
                     }
-                    return true;
+                    true
+                }
+                // frustum culling on or off
+                Some(Action::ToggleCulling) => {
+                    self.culling = !self.culling;
+                    println!("frustum culling: {}", if self.culling { "on" } else { "off" });
+                    true
+                }
+                // distance-based level of detail on or off
+                Some(Action::ToggleLod) => {
+                    self.lod_on = !self.lod_on;
+                    println!("level of detail: {}", if self.lod_on { "on" } else { "off" });
+                    true
                 }
                 _ => false,
             },
@@ -404,10 +313,11 @@ impl State {
         }
     }
 
-    pub fn update(&mut self, dt: std::time::Duration) {
+    fn update(&mut self, dt: std::time::Duration) {
         // update uniform buffer
         let mut model_mat: Vec<[f32; 16]> = vec![];
         let mut normal_mat: Vec<[f32; 16]> = vec![];
+        let mut positions: Vec<[f32; 3]> = vec![];
         let dt1 = self.rotation_speed * dt.as_secs_f32();
         for i in 0..self.x_num {
             for j in 0..self.z_num {
@@ -422,16 +332,40 @@ impl State {
                 let n = (m.invert().unwrap()).transpose();
                 model_mat.push(*(m.as_ref()));
                 normal_mat.push(*(n.as_ref()));
+                positions.push(translation);
             }
         }
-        self.init
-            .queue
-            .write_buffer(&self.uniform_buffers[1], 0, cast_slice(&model_mat));
-        self.init
-            .queue
-            .write_buffer(&self.uniform_buffers[2], 0, cast_slice(&normal_mat));
 
         let view_project_mat = self.project_mat * self.view_mat;
+
+        // the visible instances bucketed by level, their matrices compacted in the order
+        // of the buckets
+        let visible: Vec<u32> = if self.culling {
+            Frustum::from_matrix(&view_project_mat).visible(&positions, self.bounding_radius)
+        } else {
+            (0..self.objects_count).collect()
+        };
+        let batches = if self.lod_on {
+            self.lod.visible_batches(self.camera_position, &positions, &visible)
+        } else {
+            InstanceBatches::subset(&visible, |_| 0, 1)
+        };
+        if batches.batches != self.batches.batches {
+            let counts: Vec<_> = batches.batches.iter().map(|(l, r)| (l, r.len())).collect();
+            println!("(level, instances): {:?} of {}", counts, self.objects_count);
+        }
+        self.batches = batches;
+        self.init.queue.write_buffer(
+            &self.uniform_buffers[1],
+            0,
+            cast_slice(&self.batches.reorder(&model_mat)),
+        );
+        self.init.queue.write_buffer(
+            &self.uniform_buffers[2],
+            0,
+            cast_slice(&self.batches.reorder(&normal_mat)),
+        );
+
         let view_projection_ref: &[f32; 16] = view_project_mat.as_ref();
 
         self.init.queue.write_buffer(
@@ -440,52 +374,33 @@ impl State {
             bytemuck::cast_slice(view_projection_ref),
         );
 
-        // recreate vertex and index buffers
+        // resize vertex and index buffers, reallocating only when the data outgrows them
         if self.recreate_buffers {
-            let data = create_vertices(self.simple_surface.new());
-            self.indices_lens = vec![data.2.len() as u32, data.3.len() as u32];
-            let vertex_data = [data.0, data.1];
-            let index_data = [data.2, data.3];
+            let data = pack_levels(&mut self.simple_surface, &self.lod);
+            self.mesh_ranges = vec![data.0.ranges, data.1.ranges];
+            let vertex_data = [data.0.vertices, data.1.vertices];
+            let index_data = [data.0.indices, data.1.indices];
 
             for i in 0..2 {
-                self.vertex_buffers[i].destroy();
-                self.vertex_buffers[i] =
-                    self.init
-                        .device
-                        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                            label: Some("Vertex Buffer"),
-                            contents: cast_slice(&vertex_data[i]),
-                            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-                        });
-                self.index_buffers[i].destroy();
-                self.index_buffers[i] =
-                    self.init
-                        .device
-                        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                            label: Some("Index Buffer"),
-                            contents: cast_slice(&index_data[i]),
-                            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
-                        });
+                let (device, queue) = (&self.init.device, &self.init.queue);
+                self.vertex_buffers[i].write(device, queue, cast_slice(&vertex_data[i]));
+                self.index_buffers[i].write(device, queue, cast_slice(&index_data[i]));
             }
             self.recreate_buffers = false;
         }
 
         // update vertex buffer for every frame
         self.simple_surface.t = self.animation_speed * dt.as_secs_f32();
-        let data = create_vertices(self.simple_surface.new());
-        self.init
-            .queue
-            .write_buffer(&self.vertex_buffers[0], 0, cast_slice(&data.0));
-        self.init
-            .queue
-            .write_buffer(&self.vertex_buffers[1], 0, cast_slice(&data.1));
+        let data = pack_levels(&mut self.simple_surface, &self.lod);
+        self.bounding_radius = bounding_radius(&data.0.vertices);
+        let (device, queue) = (&self.init.device, &self.init.queue);
+        self.vertex_buffers[0].write(device, queue, cast_slice(&data.0.vertices));
+        self.vertex_buffers[1].write(device, queue, cast_slice(&data.1.vertices));
     }
 
-    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
         let output = self.init.surface.get_current_texture()?;
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        let view = self.init.surface_view(&output.texture);
 
         let mut encoder =
             self.init
@@ -494,23 +409,16 @@ impl State {
                     label: Some("Render Encoder"),
                 });
 
-        {
-            let color_attach = ws::create_color_attachment(&view);
-            let msaa_attach = ws::create_msaa_color_attachment(&view, &self.msaa_texture_view);
-            let color_attachment = if self.init.sample_count == 1 {
-                color_attach
-            } else {
-                msaa_attach
-            };
-            let depth_attachment = ws::create_depth_stencil_attachment(&self.depth_texture_view);
+        let targets = ws::FrameTargets::new(
+            &self.init,
+            &view,
+            &self.targets.msaa_texture_view,
+            &self.targets.depth_texture_view,
+        );
+        self.render_hooks.run_pre_passes(&mut encoder, &targets);
 
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(color_attachment)],
-                depth_stencil_attachment: Some(depth_attachment),
-                occlusion_query_set: None,
-                timestamp_writes: None,
-            });
+        {
+            let mut render_pass = self.targets.begin_pass(&mut encoder, &self.init, &view);
 
             let plot_type = if self.plot_type == 1 {
                 "shape_only"
@@ -521,31 +429,54 @@ impl State {
             };
 
             if plot_type == "shape_only" || plot_type == "both" {
-                render_pass.set_pipeline(&self.pipelines[0]);
-                render_pass.set_vertex_buffer(0, self.vertex_buffers[0].slice(..));
+                self.pipelines.bind(&mut render_pass, false);
+                render_pass.set_vertex_buffer(0, self.vertex_buffers[0].slice());
                 render_pass
-                    .set_index_buffer(self.index_buffers[0].slice(..), wgpu::IndexFormat::Uint16);
-                render_pass.set_bind_group(0, &self.uniform_bind_groups[0], &[]);
-                render_pass.set_bind_group(1, &self.uniform_bind_groups[1], &[]);
-                render_pass.draw_indexed(0..self.indices_lens[0], 0, 0..self.objects_count);
+                    .set_index_buffer(self.index_buffers[0].slice(), wgpu::IndexFormat::Uint16);
+                self.batches.draw(&mut render_pass, &self.mesh_ranges[0]);
             }
 
             if plot_type == "wireframe_only" || plot_type == "both" {
-                render_pass.set_pipeline(&self.pipelines[1]);
-                render_pass.set_vertex_buffer(0, self.vertex_buffers[1].slice(..));
+                self.pipelines.bind(&mut render_pass, true);
+                render_pass.set_vertex_buffer(0, self.vertex_buffers[1].slice());
                 render_pass
-                    .set_index_buffer(self.index_buffers[1].slice(..), wgpu::IndexFormat::Uint16);
-                render_pass.set_bind_group(0, &self.uniform_bind_groups[2], &[]);
-                render_pass.set_bind_group(1, &self.uniform_bind_groups[3], &[]);
-                render_pass.draw_indexed(0..self.indices_lens[1], 0, 0..self.objects_count);
+                    .set_index_buffer(self.index_buffers[1].slice(), wgpu::IndexFormat::Uint16);
+                self.batches.draw(&mut render_pass, &self.mesh_ranges[1]);
             }
 
-			self.fps_counter.print_fps(5);
+			self.fps_counter.log_fps(5);
         }
 
+        self.render_hooks.run_post_passes(&mut encoder, &targets);
+
         self.init.queue.submit(std::iter::once(encoder.finish()));
         output.present();
 
         Ok(())
     }
 }
+
+// the surface at every level of detail, as packed triangle and line meshes
+fn pack_levels(
+    ss: &mut sd::ISimpleSurface,
+    lod: &LodLevels,
+) -> (IPackedMeshes<Vertex>, IPackedMeshes<Vertex>) {
+    let resolution = (ss.x_resolution, ss.z_resolution);
+    let (mut triangles, mut lines) = (IPackedMeshes::default(), IPackedMeshes::default());
+    for &r in lod.resolutions.iter() {
+        (ss.x_resolution, ss.z_resolution) = (r, r);
+        let data = create_vertices(ss.new());
+        triangles.push(&data.0, &data.2);
+        lines.push(&data.1, &data.3);
+    }
+    (ss.x_resolution, ss.z_resolution) = resolution;
+    (triangles, lines)
+}
+
+// radius of a sphere around the model origin holding every vertex of an instance
+fn bounding_radius(vertices: &[Vertex]) -> f32 {
+    vertices.iter().fold(0.0, |r: f32, v| {
+        let [x, y, z] = v.position;
+        r.max((x * x + y * y + z * z).sqrt())
+    })
+}
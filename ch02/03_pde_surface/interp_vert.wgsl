@@ -0,0 +1,37 @@
+// vertex shader blending the previous and the next data frame
+struct Uniforms {   
+    vpMat : mat4x4f,
+    modelMat : mat4x4f,           
+    normalMat : mat4x4f,            
+    mixFactor : f32, // 0: previous frame, 1: next frame
+};
+@binding(0) @group(0) var<uniform> uniforms : Uniforms;
+
+struct Input {
+    @location(0) position: vec4f, 
+    @location(1) normal: vec4f, 
+    @location(2) color: vec4f,
+    @location(3) position2: vec4f, 
+    @location(4) normal2: vec4f, 
+    @location(5) color2: vec4f,
+}
+
+struct Output {
+    @builtin(position) position : vec4f,
+    @location(0) vPosition : vec4f,
+    @location(1) vNormal : vec4f,
+    @location(2) vColor: vec4f,
+};
+
+@vertex
+fn vs_main(in:Input) -> Output {    
+    var output: Output;            
+    let position = mix(in.position, in.position2, uniforms.mixFactor);
+    let normal = mix(in.normal, in.normal2, uniforms.mixFactor);
+    let mPosition = uniforms.modelMat * position; 
+    output.vPosition = mPosition;                  
+    output.vNormal =  uniforms.normalMat * normal;
+    output.position = uniforms.vpMat * mPosition; 
+    output.vColor = mix(in.color, in.color2, uniforms.mixFactor);              
+    return output;
+}
@@ -0,0 +1,19 @@
+#[path = "../common/vertex.rs"]
+mod vertex;
+mod state;
+
+use wgpu_surfaces::app;
+use wgpu_surfaces::cli::SurfaceArgs;
+
+use crate::state::State;
+
+fn main() {
+    let args = SurfaceArgs::default().parse_env("");
+
+    let title = "ch02 pde surface";
+
+    if let Err(e) = app::run_app::<State>(&args, title) {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,642 @@
+use std::sync::Arc;
+use bytemuck::{cast_slice, cast_slice_mut};
+use cgmath::{Matrix, Matrix4, SquareMatrix};
+use winit::{event::ElementState, event::KeyEvent, event::WindowEvent, window::Window};
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use wgpu_surfaces::app::{self, Action, InputMap, SurfaceApp};
+use wgpu_surfaces::appearance::DirectionalLight;
+use wgpu_surfaces::animation::FrameInterpolator;
+use wgpu_surfaces::pde::{PdeKind, PdeMode, PdeSolver};
+use wgpu_surfaces::scene::{self, Scene};
+use wgpu_surfaces::surface_data as sd;
+use wgpu_surfaces::cli::SurfaceArgs;
+use wgpu_surfaces::wgpu_simplified as ws;
+
+use crate::vertex::{create_vertices, Vertex};
+
+// seconds between uploads while the slow data modes simulate a slow producer
+const SLOW_DATA_INTERVAL: f32 = 0.5;
+
+// with the analytic reference on, residuals from 0 to RESIDUAL_RANGE span the colormap;
+// the range is fixed, so a growing discretization error shows as a color change
+const RESIDUAL_RANGE: f32 = 0.05;
+const REFERENCE_OPACITY: f32 = 0.35;
+
+// default key bindings, remapped with --keymap (see app::InputMap)
+const KEYS: &[(Action, &str)] = &[
+    (Action::CyclePlotType, "Space"),
+    (Action::NextSurfaceType, "Control"),
+    (Action::CycleColormapDirection, "Alt"),
+    (Action::Disturb, "b"),
+    (Action::ResetSimulation, "c"),
+    (Action::CycleDataMode, "i"),
+    (Action::ToggleReference, "g"),
+    (Action::IncreaseUResolution, "q"),
+    (Action::DecreaseUResolution, "a"),
+    (Action::IncreaseAnimationSpeed, "e"),
+    (Action::DecreaseAnimationSpeed, "d"),
+    (Action::IncreaseRotationSpeed, "r"),
+    (Action::DecreaseRotationSpeed, "f"),
+    (Action::SaveScene, "S"),
+    (Action::LoadScene, "L"),
+];
+
+pub struct State {
+    init: ws::InitWgpu,
+    pipelines: app::SurfacePipelines,
+    reference_pipeline: wgpu::RenderPipeline,
+    vertex_buffers: Vec<ws::GrowableBuffer>, // next frame (surface, wireframe), then previous frame
+    frames: Vec<Vec<Vertex>>, // the data of vertex_buffers, to blend the previous frame from
+    index_buffers: Vec<ws::GrowableBuffer>,
+    vert_uniform_buffer: wgpu::Buffer,
+    lighting: app::LightUniforms,
+    view_mat: Matrix4<f32>,
+    camera: ws::OrbitCamera,
+    project_mat: Matrix4<f32>,
+    targets: app::RenderTargets,
+    indices_lens: Vec<u32>,
+    plot_type: u32,
+    recreate_buffers: bool,
+    animation_speed: f32,
+    rotation_speed: f32,
+    rng: StdRng,
+    seed: u64, // of rng, saved with the scene
+    last_time: f32,
+    data_mode: u32, // 0: every frame, 1: slow data, 2: slow data interpolated
+    last_upload: f32,
+    interpolator: FrameInterpolator,
+
+    solver: PdeSolver,
+    streaming_surface: sd::IStreamingSurface,
+    reference: Option<PdeMode>, // eigenmode whose analytic solution is overlaid
+    reference_buffer: ws::GrowableBuffer,
+    input_map: InputMap,
+    fps_counter: ws::FpsCounter,
+    render_hooks: ws::RenderHooks,
+    scene_file: String, // where S saves the scene and L loads it
+}
+
+impl SurfaceApp for State {
+    async fn new(window: Arc<Window>, args: &SurfaceArgs) -> wgpu_surfaces::Result<Self> {
+        let init = ws::InitWgpu::init_wgpu(window, args.msaa, args.present_mode).await?;
+
+        // Loading Shaders
+        let vs_shader = init
+            .device
+            .create_shader_module(wgpu::include_wgsl!("interp_vert.wgsl"));
+        let fs_shader = init
+            .device
+            .create_shader_module(wgpu::include_wgsl!("../common/directional_frag.wgsl"));
+
+        // uniform data
+        let camera_position = (4.0, 4.0, 4.0).into();
+        let look_direction = (0.0, 0.0, 0.0).into();
+        let up_direction = cgmath::Vector3::unit_y();
+
+        let (view_mat, project_mat, _) = ws::create_vp_mat(
+            camera_position,
+            look_direction,
+            up_direction,
+            init.config.width as f32 / init.config.height as f32,
+        );
+
+        // create vertex uniform buffers
+        // model_mat and vp_mat will be stored in vertex_uniform_buffer inside the update function
+        let vert_uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Vertex Uniform Buffer"),
+            size: 208,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // light and material uniforms; the eye position follows the camera in update
+        let light = DirectionalLight {
+            eye_position: camera_position.into(),
+            ..Default::default()
+        };
+        let lighting = app::LightUniforms::new(&init, &light, [0.1, 0.7, 0.4, 30.0]);
+
+        // previous frame in slot 0, next frame in slot 1
+        let vertex_buffer_layout = [
+            wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &wgpu::vertex_attr_array![
+                    0 => Float32x3, 1 => Float32x3, 2 => Float32x3
+                ],
+                // pos, norm, col
+            },
+            wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &wgpu::vertex_attr_array![
+                    3 => Float32x3, 4 => Float32x3, 5 => Float32x3
+                ],
+                // pos, norm, col
+            },
+        ];
+
+        let pipelines = app::SurfacePipelines::new(
+            &init,
+            &vs_shader,
+            &fs_shader,
+            &vertex_buffer_layout,
+            ws::create_bind_group(
+                &init.device,
+                vec![wgpu::ShaderStages::VERTEX],
+                &[vert_uniform_buffer.as_entire_binding()],
+            )?,
+            lighting.bind_group(&init.device)?,
+        )?;
+
+        // analytic reference over the computed surface, blended with the render pass
+        // blend constant
+        let reference_pipeline = ws::IRenderPipeline {
+            vs_shader: Some(&vs_shader),
+            fs_shader: Some(&fs_shader),
+            pipeline_layout: Some(&pipelines.layout),
+            vertex_buffer_layout: &vertex_buffer_layout,
+            depth_write_enabled: false,
+            blend: Some(ws::CONSTANT_ALPHA_BLENDING),
+            ..Default::default()
+        }
+        .new(&init)?;
+        let targets = app::RenderTargets::new(&init);
+
+        let n = args.resolution.unwrap_or(64);
+        let mut solver = PdeSolver::new(PdeKind::Heat, n, n);
+        solver.add_gaussian(0.0, 0.0, 1.0, 0.25);
+        let mut ss = sd::IStreamingSurface {
+            scale: 2.0,
+            colormap_name: args.colormap.clone(),
+            wireframe_color: args.wireframe_color.clone(),
+            // keep the height scale fixed so the decay of the solution stays visible
+            value_range: Some([-1.0, 1.0]),
+            ..Default::default()
+        };
+        solver.write_to(&mut ss);
+        let data = create_vertices(ss.new());
+
+        let vertex_buffers = create_vertex_buffers(&init, &data.0, &data.1);
+
+        let index_buffer = ws::GrowableBuffer::new(
+            &init.device,
+            "Index Buffer",
+            wgpu::BufferUsages::INDEX,
+            cast_slice(&data.2),
+        );
+
+        let index_buffer2 = ws::GrowableBuffer::new(
+            &init.device,
+            "Index Buffer 2",
+            wgpu::BufferUsages::INDEX,
+            cast_slice(&data.3),
+        );
+
+        let reference_buffer = ws::GrowableBuffer::new(
+            &init.device,
+            "Reference Vertex Buffer",
+            wgpu::BufferUsages::VERTEX,
+            &[],
+        );
+        // the random bumps come out the same for a given seed
+        let seed = args.seed.unwrap_or_else(rand::random);
+
+        let mut state = Self {
+            init,
+            pipelines,
+            reference_pipeline,
+            vertex_buffers,
+            frames: vec![data.0.clone(), data.1.clone(), data.0.clone(), data.1.clone()],
+            index_buffers: vec![index_buffer, index_buffer2],
+            vert_uniform_buffer,
+            lighting,
+            view_mat,
+            camera: ws::OrbitCamera::new(camera_position, look_direction),
+            project_mat,
+            targets,
+            indices_lens: vec![data.2.len() as u32, data.3.len() as u32],
+            plot_type: 0,
+            recreate_buffers: false,
+            animation_speed: 1.0,
+            rotation_speed: 0.2,
+            rng: StdRng::seed_from_u64(seed),
+            seed,
+            last_time: 0.0,
+            data_mode: 0,
+            last_upload: 0.0,
+            interpolator: FrameInterpolator::default(),
+
+            solver,
+            streaming_surface: ss,
+            reference: None,
+            reference_buffer,
+            input_map: InputMap::with_args(KEYS, args)?,
+            fps_counter: ws::FpsCounter::default(),
+            render_hooks: ws::RenderHooks::default(),
+            scene_file: args.scene_file.clone().unwrap_or(scene::SCENE_FILE.to_string()),
+        };
+        if let Some(scene) = &args.scene {
+            state.set_scene(scene);
+        }
+        Ok(state)
+    }
+
+    fn init(&self) -> &ws::InitWgpu {
+        &self.init
+    }
+
+    fn init_mut(&mut self) -> &mut ws::InitWgpu {
+        &mut self.init
+    }
+
+    fn resized(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.project_mat =
+            ws::create_projection_mat(new_size.width as f32 / new_size.height as f32, true);
+        self.targets.resize(&self.init);
+    }
+
+    // the scene saved by the crash recovery file (--recovery-file)
+    fn snapshot(&self) -> Option<Scene> {
+        Some(self.scene())
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        // left drag rotates, right drag pans and the wheel zooms
+        let moved = self.camera.input(event);
+        if moved {
+            self.view_mat = self.camera.view_mat();
+        }
+        let handled = match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: key,
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => match self.input_map.action(key) {
+                Some(Action::CyclePlotType) => {
+                    self.plot_type = (self.plot_type + 1) % 3;
+                    true
+                }
+                // switches between the heat and the wave equation
+                Some(Action::NextSurfaceType) => {
+                    let kind = match self.solver.kind {
+                        PdeKind::Heat => PdeKind::Wave,
+                        PdeKind::Wave => PdeKind::Heat,
+                    };
+                    self.restart(kind, self.solver.rows);
+                    println!("{:?} equation", kind);
+                    true
+                }
+                Some(Action::CycleColormapDirection) => {
+                    self.streaming_surface.colormap_direction =
+                        (self.streaming_surface.colormap_direction + 1) % 3;
+                    true
+                }
+                // drops a random gaussian bump onto the grid
+                Some(Action::Disturb) => {
+                    let x = self.rng.random_range(-0.7..0.7);
+                    let z = self.rng.random_range(-0.7..0.7);
+                    let amplitude = self.rng.random_range(-1.0..1.0);
+                    self.solver.add_gaussian(x, z, amplitude, 0.15);
+                    true
+                }
+                Some(Action::ResetSimulation) => {
+                    match self.reference {
+                        Some(mode) => self.solver.set_mode(&mode),
+                        None => self.solver.reset(),
+                    }
+                    true
+                }
+                // cycles live data, slow data and slow data with interpolation
+                Some(Action::CycleDataMode) => {
+                    self.data_mode = (self.data_mode + 1) % 3;
+                    let modes = ["live data", "slow data", "slow data interpolated"];
+                    println!("{}", modes[self.data_mode as usize]);
+                    true
+                }
+                // starts over from an eigenmode of the grid and overlays its analytic
+                // solution, coloring the computed surface by the residual
+                Some(Action::ToggleReference) => {
+                    self.reference = match self.reference {
+                        Some(_) => None,
+                        None => Some(PdeMode::default()),
+                    };
+                    self.restart(self.solver.kind, self.solver.rows);
+                    self.recreate_buffers = true;
+                    match self.reference {
+                        Some(mode) => println!("analytic reference: mode ({}, {})", mode.m, mode.n),
+                        None => println!("analytic reference off"),
+                    }
+                    true
+                }
+                Some(Action::SaveScene) => {
+                    match self.scene().save(&self.scene_file) {
+                        Ok(_) => println!("scene saved to {}", self.scene_file),
+                        Err(e) => println!("{}", e),
+                    }
+                    true
+                }
+                Some(Action::LoadScene) => {
+                    match Scene::load(&self.scene_file) {
+                        Ok(scene) => self.set_scene(&scene),
+                        Err(e) => println!("{}", e),
+                    }
+                    true
+                }
+                Some(Action::IncreaseUResolution) => {
+                    let n = (self.solver.rows + 8).min(200);
+                    self.resize_grid(n);
+                    true
+                }
+                Some(Action::DecreaseUResolution) => {
+                    let n = self.solver.rows.saturating_sub(8).max(16);
+                    self.resize_grid(n);
+                    true
+                }
+                Some(Action::IncreaseAnimationSpeed) => {
+                    self.animation_speed += 0.1;
+                    true
+                }
+                Some(Action::DecreaseAnimationSpeed) => {
+                    self.animation_speed -= 0.1;
+                    if self.animation_speed < 0.0 {
+                        self.animation_speed = 0.0;
+                    }
+                    true
+                }
+                Some(Action::IncreaseRotationSpeed) => {
+                    self.rotation_speed += 0.1;
+                    true
+                }
+                Some(Action::DecreaseRotationSpeed) => {
+                    self.rotation_speed -= 0.1;
+                    if self.rotation_speed < 0.0 {
+                        self.rotation_speed = 0.0;
+                    }
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        };
+        moved || handled
+    }
+
+    fn update(&mut self, dt: std::time::Duration) {
+        // advance the solution by the frame time, limited so that a stalled window
+        // does not trigger a burst of solver steps
+        let frame_time = (dt.as_secs_f32() - self.last_time).clamp(0.0, 0.1);
+        self.last_time = dt.as_secs_f32();
+        self.solver.advance(self.animation_speed * frame_time);
+
+        // update uniform buffer
+        let dt1 = self.rotation_speed * dt.as_secs_f32();
+
+        let model_mat = ws::create_model_mat(
+            [0.0, 1.0, 0.0],
+            [dt1.sin(), dt1.cos(), 0.0],
+            [1.0, 1.0, 1.0],
+        );
+        let view_project_mat = self.project_mat * self.view_mat;
+        self.lighting.set_eye_position(&self.init.queue, self.camera.eye().into());
+
+        let normal_mat = (model_mat.invert().unwrap()).transpose();
+
+        let model_ref: &[f32; 16] = model_mat.as_ref();
+        let view_projection_ref: &[f32; 16] = view_project_mat.as_ref();
+        let normal_ref: &[f32; 16] = normal_mat.as_ref();
+
+        self.init
+            .queue
+            .write_buffer(&self.vert_uniform_buffer, 0, cast_slice(view_projection_ref));
+        self.init
+            .queue
+            .write_buffer(&self.vert_uniform_buffer, 64, cast_slice(model_ref));
+        self.init
+            .queue
+            .write_buffer(&self.vert_uniform_buffer, 128, cast_slice(normal_ref));
+
+        self.init
+            .queue
+            .write_buffer(&self.vert_uniform_buffer, 192, cast_slice(&[self.mix_factor()]));
+
+        // resize vertex and index buffers, reallocating only when the data outgrows them
+        if self.recreate_buffers {
+            let data = self.surface_vertices();
+            self.indices_lens = vec![data.2.len() as u32, data.3.len() as u32];
+            let (device, queue) = (&self.init.device, &self.init.queue);
+            self.frames = vec![data.0.clone(), data.1.clone(), data.0, data.1];
+            for (buffer, vertices) in self.vertex_buffers.iter_mut().zip(&self.frames) {
+                buffer.write(device, queue, cast_slice(vertices));
+            }
+            let index_data = [data.2, data.3];
+            for (buffer, indices) in self.index_buffers.iter_mut().zip(index_data.iter()) {
+                buffer.write(device, queue, cast_slice(indices));
+            }
+            self.recreate_buffers = false;
+        }
+
+        // upload the solution every frame, or at a low rate in the slow data modes
+        let now = dt.as_secs_f32();
+        if self.data_mode == 0 || now - self.last_upload >= SLOW_DATA_INTERVAL {
+            self.last_upload = now;
+            let data = self.surface_vertices();
+
+            // the frame shown until now becomes the previous one, so a frame arriving
+            // before the blend has reached the next one does not make the surface jump
+            let shown = self.interpolator.push_frame();
+            let shown = if self.data_mode == 2 { shown } else { 1.0 };
+            for (i, next) in [data.0, data.1].into_iter().enumerate() {
+                let next = std::mem::replace(&mut self.frames[i], next);
+                let previous = &mut self.frames[i + 2];
+                FrameInterpolator::blend(cast_slice_mut(previous), cast_slice(&next), shown);
+            }
+            let (device, queue) = (&self.init.device, &self.init.queue);
+            for (buffer, vertices) in self.vertex_buffers.iter_mut().zip(&self.frames) {
+                buffer.write(device, queue, cast_slice(vertices));
+            }
+        }
+    }
+
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        let output = self.init.surface.get_current_texture()?;
+        let view = self.init.surface_view(&output.texture);
+
+        let mut encoder =
+            self.init
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Render Encoder"),
+                });
+
+        let targets = ws::FrameTargets::new(
+            &self.init,
+            &view,
+            &self.targets.msaa_texture_view,
+            &self.targets.depth_texture_view,
+        );
+        self.render_hooks.run_pre_passes(&mut encoder, &targets);
+
+        {
+            let mut render_pass = self.targets.begin_pass(&mut encoder, &self.init, &view);
+
+            let plot_type = if self.plot_type == 1 {
+                "shape_only"
+            } else if self.plot_type == 2 {
+                "wireframe_only"
+            } else {
+                "both"
+            };
+
+            if plot_type == "shape_only" || plot_type == "both" {
+                self.pipelines.bind(&mut render_pass, false);
+                render_pass.set_vertex_buffer(0, self.vertex_buffers[2].slice());
+                render_pass.set_vertex_buffer(1, self.vertex_buffers[0].slice());
+                render_pass
+                    .set_index_buffer(self.index_buffers[0].slice(), wgpu::IndexFormat::Uint16);
+                render_pass.draw_indexed(0..self.indices_lens[0], 0, 0..1);
+            }
+
+            if plot_type == "wireframe_only" || plot_type == "both" {
+                self.pipelines.bind(&mut render_pass, true);
+                render_pass.set_vertex_buffer(0, self.vertex_buffers[3].slice());
+                render_pass.set_vertex_buffer(1, self.vertex_buffers[1].slice());
+                render_pass
+                    .set_index_buffer(self.index_buffers[1].slice(), wgpu::IndexFormat::Uint16);
+                render_pass.draw_indexed(0..self.indices_lens[1], 0, 0..1);
+            }
+
+            // the reference goes last, over the opaque surface and wireframe
+            if self.reference.is_some() {
+                let a = REFERENCE_OPACITY as f64;
+                render_pass.set_blend_constant(wgpu::Color { r: a, g: a, b: a, a });
+                render_pass.set_pipeline(&self.reference_pipeline);
+                self.pipelines.bind_groups(&mut render_pass);
+                render_pass.set_vertex_buffer(0, self.reference_buffer.slice());
+                render_pass.set_vertex_buffer(1, self.reference_buffer.slice());
+                render_pass
+                    .set_index_buffer(self.index_buffers[0].slice(), wgpu::IndexFormat::Uint16);
+                render_pass.draw_indexed(0..self.indices_lens[0], 0, 0..1);
+            }
+        }
+
+        self.render_hooks.run_post_passes(&mut encoder, &targets);
+
+        self.fps_counter.log_fps(5);
+        self.init.queue.submit(std::iter::once(encoder.finish()));
+        output.present();
+
+        Ok(())
+    }
+}
+
+impl State {
+    // restarts the simulation on an n x n grid
+    fn resize_grid(&mut self, n: u16) {
+        self.restart(self.solver.kind, n);
+        println!("grid: {} x {}", n, n);
+        self.recreate_buffers = true;
+    }
+
+    // a new solver on an n x n grid, starting from the reference mode while it is shown
+    fn restart(&mut self, kind: PdeKind, n: u16) {
+        self.solver = PdeSolver::new(kind, n, n);
+        match self.reference {
+            Some(mode) => self.solver.set_mode(&mode),
+            None => self.solver.add_gaussian(0.0, 0.0, 1.0, 0.25),
+        }
+    }
+
+    // the equation (surface type 0: heat, 1: wave), grid, camera and plot type as a scene;
+    // the material stays the default one of this example
+    fn scene(&self) -> Scene {
+        let ss = &self.streaming_surface;
+        let mut scene = Scene {
+            surface_type: match self.solver.kind {
+                PdeKind::Heat => 0,
+                PdeKind::Wave => 1,
+            },
+            resolution: [self.solver.rows, self.solver.cols],
+            scale: ss.scale,
+            colormap: ss.colormap_name.clone(),
+            wireframe_color: ss.wireframe_color.clone(),
+            msaa: self.init.sample_count,
+            plot_type: self.plot_type,
+            seed: self.seed,
+            ..Default::default()
+        };
+        scene.set_camera(&self.camera);
+        scene
+    }
+
+    // restarts the simulation with the equation and grid of the scene
+    fn set_scene(&mut self, scene: &Scene) {
+        let kind = match scene.surface_type {
+            1 => PdeKind::Wave,
+            _ => PdeKind::Heat,
+        };
+        let ss = &mut self.streaming_surface;
+        ss.scale = scene.scale;
+        ss.colormap_name = scene.colormap.clone();
+        ss.wireframe_color = scene.wireframe_color.clone();
+        self.camera = scene.camera();
+        self.view_mat = self.camera.view_mat();
+        self.plot_type = scene.plot_type % 3;
+        self.restart(kind, scene.resolution[0].clamp(16, 200));
+        if scene.msaa != self.init.sample_count {
+            println!("msaa {} needs a restart with --scene {}", scene.msaa, self.scene_file);
+        }
+        self.recreate_buffers = true;
+    }
+
+    // the blend of the previous and next frames shown, the next frame unless interpolating
+    fn mix_factor(&self) -> f32 {
+        if self.data_mode == 2 {
+            self.interpolator.mix_factor()
+        } else {
+            1.0
+        }
+    }
+
+    // vertices of the current solution; with the reference on, the surface is colored by
+    // the residual and the reference vertices, in a neutral white, are uploaded as well
+    fn surface_vertices(&mut self) -> (Vec<Vertex>, Vec<Vertex>, Vec<u16>, Vec<u16>) {
+        let ss = &mut self.streaming_surface;
+        self.solver.write_to(ss);
+        let mut output = ss.new();
+        if let Some(mode) = self.reference {
+            let residual: Vec<f32> = self.solver.residual(&mode).iter().map(|r| r.abs()).collect();
+            ss.color_by_values(&mut output, &residual, [0.0, RESIDUAL_RANGE]);
+            ss.set_frame(&self.solver.reference(&mode));
+            let reference: Vec<Vertex> = create_vertices(ss.new())
+                .0
+                .into_iter()
+                .map(|v| Vertex { color: [1.0; 3], ..v })
+                .collect();
+            self.reference_buffer
+                .write(&self.init.device, &self.init.queue, cast_slice(&reference));
+        }
+        create_vertices(output)
+    }
+}
+
+// next and previous frame buffers for the surface and the wireframe, both frames
+// starting with the same data
+fn create_vertex_buffers(
+    init: &ws::InitWgpu,
+    surface: &[Vertex],
+    wireframe: &[Vertex],
+) -> Vec<ws::GrowableBuffer> {
+    let usage = wgpu::BufferUsages::VERTEX;
+    [surface, wireframe, surface, wireframe]
+        .iter()
+        .map(|data| ws::GrowableBuffer::new(&init.device, "Vertex Buffer", usage, cast_slice(data)))
+        .collect()
+}
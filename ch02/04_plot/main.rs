@@ -0,0 +1,150 @@
+use wgpu_surfaces::app;
+use wgpu_surfaces::cli::SurfaceArgs;
+use wgpu_surfaces::plot::{self, ProbeAxis};
+use wgpu_surfaces::series::{FnFrames, RawFramesSource};
+use wgpu_surfaces::surface_data::IHeightmapSurface;
+
+// the whole viewer in one call; options as for the other examples (--colormap,
+// --resolution, ...), followed by a mode:
+// mode "live": the window runs on its own thread and the main thread keeps
+// pushing new data through the plot handle
+// mode "grid": four subplots in one window, the last one unlit
+// mode "band": the surface with a +/- 2 sigma uncertainty envelope
+// mode "mask": everything below zero blanked out like the ocean of an elevation map
+// mode "probe": a slicing plane at x = 2 with the cross-section in an inset; x cycles
+// the axis, shift + drag moves the plane
+// mode "sync": a wave and its time derivative side by side, driven by one clock
+// mode "series [file rows cols]": a time series of grids with a scrubber, read from
+// a raw f32 file with the frames back to back, or a generated wave without a file
+// mode "heightmap file": a grid of measurements from a CSV file or the gray levels of
+// a PNG heightmap, with missing CSV cells left out
+fn main() -> anyhow::Result<()> {
+    let args = SurfaceArgs::default()
+        .parse_env("[live|grid|band|mask|probe|sync|series [file rows cols]|heightmap file]");
+    let mode = args.positional.first().map_or("", |mode| mode.as_str());
+
+    app::init_logger();
+    let plot = plot::plot_surface(|x, z| sinc(x, z, 0.0))
+        .x_range(-8.0, 8.0)
+        .z_range(-8.0, 8.0)
+        .colormap(&args.colormap)
+        .wireframe(&args.wireframe_color)
+        .resolution(args.resolution.unwrap_or(128))
+        .sample_count(args.msaa)
+        .number_format(args.number_format)
+        .theme(args.theme)
+        .ui_scale(args.ui_scale)
+        .title("ch02 one-call plot");
+    match mode {
+        "live" => {}
+        "grid" => {
+            return plot::subplots(2, 2)
+                .subplot(0, plot)
+                .subplot(1, plot::plot_surface(|x, z| x * z).colormap("cool"))
+                .subplot(
+                    2,
+                    plot::plot_surface(|x, z| x * x - z * z)
+                        .colormap("hot")
+                        .wireframe("white"),
+                )
+                .subplot(
+                    3,
+                    plot::plot_surface(|x, z| (-4.0 * (x * x + z * z)).exp())
+                        .colormap("jet")
+                        .lighting(false),
+                )
+                .title("ch02 subplots")
+                .sample_count(args.msaa)
+                .show();
+        }
+        "band" => {
+            return plot
+                .uncertainty(|x, z| 0.02 + 0.01 * (x * x + z * z).sqrt())
+                .band(2.0, 0.3)
+                .show();
+        }
+        "mask" => return plot.mask(|x, z| sinc(x, z, 0.0) < 0.0).show(),
+        "probe" => return plot.probe(ProbeAxis::X, 2.0).show(),
+        "series" => {
+            let plot = match &args.positional[1..] {
+                [file, rows, cols] => {
+                    plot::plot_series(RawFramesSource::open(file, rows.parse()?, cols.parse()?)?)
+                }
+                _ => plot::plot_series(FnFrames {
+                    rows: 96,
+                    cols: 96,
+                    steps: 240,
+                    f: Box::new(|step, i, j| {
+                        let (x, z) = (i as f32 / 6.0 - 8.0, j as f32 / 6.0 - 8.0);
+                        sinc(x, z, step as f32 * 0.05) * (-0.01 * step as f32).exp()
+                    }),
+                }),
+            };
+            return plot
+                .colormap(&args.colormap)
+                .steps_per_second(20.0)
+                .sample_count(args.msaa)
+                .number_format(args.number_format)
+                .theme(args.theme)
+                .ui_scale(args.ui_scale)
+                .title("ch02 time series")
+                .show();
+        }
+        "heightmap" => {
+            let Some(path) = args.positional.get(1) else {
+                anyhow::bail!("heightmap needs a .csv or .png file");
+            };
+            let heightmap = IHeightmapSurface::load(path)?;
+            let (rows, cols) = (heightmap.rows, heightmap.cols);
+            return plot::plot_values(rows, cols, heightmap.filled_values())
+                .mask_values(rows, cols, heightmap.missing)
+                .colormap(&args.colormap)
+                .wireframe(&args.wireframe_color)
+                .sample_count(args.msaa)
+                .number_format(args.number_format)
+                .theme(args.theme)
+                .ui_scale(args.ui_scale)
+                .title("ch02 heightmap")
+                .show();
+        }
+        "sync" => {
+            let n = args.resolution.unwrap_or(128);
+            let panel = |f: fn(f32, f32, f32) -> f32| {
+                plot::plot_animated(f)
+                    .x_range(-8.0, 8.0)
+                    .z_range(-8.0, 8.0)
+                    .colormap(&args.colormap)
+                    .resolution(n)
+                    .number_format(args.number_format)
+                    .theme(args.theme)
+                    .ui_scale(args.ui_scale)
+            };
+            return plot::subplots(1, 2)
+                .subplot(0, panel(sinc))
+                .subplot(
+                    1,
+                    panel(|x, z, t| (sinc(x, z, t + 0.01) - sinc(x, z, t)) / 0.01),
+                )
+                .title("ch02 synchronized plots")
+                .sample_count(args.msaa)
+                .show();
+        }
+        _ => return plot.show(),
+    }
+
+    let handle = plot.show_async()?;
+    let mut t = 0.0_f32;
+    while handle.is_open() {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        t += 0.05;
+        if handle.set_function(move |x, z| sinc(x, z, t)).is_err() {
+            break;
+        }
+    }
+    handle.wait()
+}
+
+fn sinc(x: f32, z: f32, t: f32) -> f32 {
+    let r = (x * x + z * z).sqrt() + 1e-4;
+    (r - 2.0 * t).sin() / r
+}
@@ -0,0 +1,54 @@
+#[path = "../common/vertex.rs"]
+mod vertex;
+mod state;
+
+use wgpu_surfaces::app;
+use wgpu_surfaces::cli::SurfaceArgs;
+use wgpu_surfaces::tiles::{FnSource, RawFileSource, TileSource};
+
+use crate::state::State;
+
+fn main() {
+    let args = SurfaceArgs::default().colormap("terrain").parse_env("[path rows cols]");
+
+    let title = "ch02 tiled surface";
+
+    if let Err(e) = app::run_app::<State>(&args, title) {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
+}
+
+// a raw little-endian f32 grid given as "path rows cols", otherwise a procedural
+// 16385 x 16385 terrain that would need 4 GB as a single mesh
+pub fn tile_source(positional: &[String]) -> Box<dyn TileSource> {
+    if let [path, rows, cols, ..] = positional {
+        let source = match (rows.parse::<u32>(), cols.parse::<u32>()) {
+            (Ok(rows), Ok(cols)) => RawFileSource::open(path, rows, cols),
+            _ => Err(std::io::Error::other("rows and cols must be numbers")),
+        };
+        match source {
+            Ok(source) => return Box::new(source),
+            Err(e) => println!("cannot open {}: {}", path, e),
+        }
+    }
+    Box::new(FnSource {
+        rows: 16385,
+        cols: 16385,
+        f: Box::new(|i, j| {
+            let (x, z) = (i as f32 / 16384.0, j as f32 / 16384.0);
+            let mut v = 0.0;
+            let mut amplitude = 1.0;
+            let mut frequency = 3.0;
+            // a few octaves, so there is detail left to see at every level
+            for _ in 0..8 {
+                v += amplitude
+                    * (frequency * x + 1.3 * (frequency * z).sin()).sin()
+                    * (frequency * z - 0.7 * (frequency * x).cos()).cos();
+                amplitude *= 0.5;
+                frequency *= 2.1;
+            }
+            v
+        }),
+    })
+}
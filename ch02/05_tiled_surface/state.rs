@@ -0,0 +1,310 @@
+use bytemuck::cast_slice;
+use cgmath::{Matrix4, Point3, SquareMatrix};
+use std::sync::Arc;
+use wgpu::util::DeviceExt;
+use winit::{event::ElementState, event::KeyEvent, event::WindowEvent, window::Window};
+
+use wgpu_surfaces::app::{self, Action, InputMap, SurfaceApp};
+use wgpu_surfaces::appearance::DirectionalLight;
+use wgpu_surfaces::tiles::{TileCache, TileKey, TiledSurface};
+use wgpu_surfaces::cli::SurfaceArgs;
+use wgpu_surfaces::wgpu_simplified as ws;
+
+use crate::vertex::{Vertex, create_vertices};
+
+// GPU buffers of one resident tile: surface and wireframe
+struct TileBuffers {
+    vertex_buffers: [wgpu::Buffer; 2],
+    index_buffers: [wgpu::Buffer; 2],
+    indices_lens: [u32; 2],
+}
+
+// default key bindings, remapped with --keymap (see app::InputMap)
+const KEYS: &[(Action, &str)] = &[
+    (Action::CyclePlotType, "Space"),
+    (Action::PrintTiles, "t"),
+    (Action::IncreaseAnimationSpeed, "e"),
+    (Action::DecreaseAnimationSpeed, "d"),
+    (Action::RaiseAltitude, "r"),
+    (Action::LowerAltitude, "f"),
+];
+
+pub struct State {
+    init: ws::InitWgpu,
+    pipelines: app::SurfacePipelines,
+    vert_uniform_buffer: wgpu::Buffer,
+    lighting: app::LightUniforms,
+    targets: app::RenderTargets,
+    project_mat: Matrix4<f32>,
+    plot_type: u32,
+    flight_speed: f32,
+    altitude: f32,
+    flight_angle: f32,
+    last_time: f32,
+
+    surface: TiledSurface,
+    cache: TileCache<TileBuffers>,
+    drawn: Vec<TileKey>,
+    input_map: InputMap,
+    fps_counter: ws::FpsCounter,
+}
+
+impl SurfaceApp for State {
+    async fn new(window: Arc<Window>, args: &SurfaceArgs) -> wgpu_surfaces::Result<Self> {
+        let init = ws::InitWgpu::init_wgpu(window, args.msaa, args.present_mode).await?;
+
+        // Loading Shaders
+        let vs_shader = init
+            .device
+            .create_shader_module(wgpu::include_wgsl!("../01_simple_surface/shader_vert.wgsl"));
+        let fs_shader = init
+            .device
+            .create_shader_module(wgpu::include_wgsl!("../common/directional_frag.wgsl"));
+
+        let project_mat =
+            ws::create_projection_mat(init.config.width as f32 / init.config.height as f32, true);
+
+        // the tiles are built in world coordinates, so the model matrix stays the identity
+        let vert_uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Vertex Uniform Buffer"),
+            size: 192,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let identity: [f32; 16] = *Matrix4::<f32>::identity().as_ref();
+        init.queue
+            .write_buffer(&vert_uniform_buffer, 64, cast_slice(&identity));
+        init.queue
+            .write_buffer(&vert_uniform_buffer, 128, cast_slice(&identity));
+
+        // the eye position of the light follows the camera in update, with little specular
+        // light on the terrain
+        let lighting = app::LightUniforms::new(
+            &init,
+            &DirectionalLight::default(),
+            [0.2, 0.8, 0.1, 30.0],
+        );
+
+        let vertex_buffer_layout = [wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3],
+            // pos, norm, col
+        }];
+        let pipelines = app::SurfacePipelines::new(
+            &init,
+            &vs_shader,
+            &fs_shader,
+            &vertex_buffer_layout,
+            ws::create_bind_group(
+                &init.device,
+                vec![wgpu::ShaderStages::VERTEX],
+                &[vert_uniform_buffer.as_entire_binding()],
+            )?,
+            lighting.bind_group(&init.device)?,
+        )?;
+        let targets = app::RenderTargets::new(&init);
+
+        let mut surface =
+            TiledSurface::from_source(crate::tile_source(&args.positional)).expect("cannot read the grid");
+        surface.scale = 4.0;
+        surface.aspect_ratio = 0.05;
+        if let Some(resolution) = args.resolution {
+            surface.tile_size = resolution as u32;
+        }
+        surface.colormap_name = args.colormap.clone();
+        surface.wireframe_color = args.wireframe_color.clone();
+        let [rows, cols] = surface.source.size();
+        println!(
+            "grid: {} x {}, {} levels",
+            rows,
+            cols,
+            surface.root_level() + 1
+        );
+
+        Ok(Self {
+            init,
+            pipelines,
+            vert_uniform_buffer,
+            lighting,
+            targets,
+            project_mat,
+            plot_type: 1,
+            flight_speed: 0.05,
+            altitude: 0.6,
+            flight_angle: 0.0,
+            last_time: 0.0,
+
+            surface,
+            cache: TileCache::new(512, 8),
+            drawn: vec![],
+            input_map: InputMap::with_args(KEYS, args)?,
+            fps_counter: ws::FpsCounter::default(),
+        })
+    }
+
+    fn init(&self) -> &ws::InitWgpu {
+        &self.init
+    }
+
+    fn init_mut(&mut self) -> &mut ws::InitWgpu {
+        &mut self.init
+    }
+
+    fn resized(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.project_mat =
+            ws::create_projection_mat(new_size.width as f32 / new_size.height as f32, true);
+        self.targets.resize(&self.init);
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: key,
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => match self.input_map.action(key) {
+                Some(Action::CyclePlotType) => {
+                    self.plot_type = (self.plot_type + 1) % 3;
+                    true
+                }
+                // prints the tiles drawn and resident
+                Some(Action::PrintTiles) => {
+                    let mut levels: Vec<u32> = self.drawn.iter().map(|k| k.level).collect();
+                    levels.dedup();
+                    println!(
+                        "tiles drawn: {}, resident: {}, levels: {:?}",
+                        self.drawn.len(),
+                        self.cache.len(),
+                        levels
+                    );
+                    true
+                }
+                Some(Action::IncreaseAnimationSpeed) => {
+                    self.flight_speed += 0.02;
+                    true
+                }
+                Some(Action::DecreaseAnimationSpeed) => {
+                    self.flight_speed = (self.flight_speed - 0.02).max(0.0);
+                    true
+                }
+                Some(Action::RaiseAltitude) => {
+                    self.altitude += 0.1;
+                    true
+                }
+                Some(Action::LowerAltitude) => {
+                    self.altitude = (self.altitude - 0.1).max(0.1);
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn update(&mut self, dt: std::time::Duration) {
+        let frame_time = (dt.as_secs_f32() - self.last_time).clamp(0.0, 0.1);
+        self.last_time = dt.as_secs_f32();
+        self.flight_angle += self.flight_speed * frame_time;
+
+        // fly around the center of the grid, looking ahead and down
+        let radius = 0.6 * self.surface.scale;
+        let a = self.flight_angle;
+        let eye = Point3::new(radius * a.cos(), self.altitude, radius * a.sin());
+        let target = Point3::new(radius * (a + 0.3).cos(), 0.0, radius * (a + 0.3).sin());
+        let view_mat = ws::create_view_mat(eye, target, cgmath::Vector3::unit_y());
+        let view_project_mat = self.project_mat * view_mat;
+
+        let view_projection_ref: &[f32; 16] = view_project_mat.as_ref();
+        self.init
+            .queue
+            .write_buffer(&self.vert_uniform_buffer, 0, cast_slice(view_projection_ref));
+        self.lighting.set_eye_position(&self.init.queue, eye.into());
+
+        // pick the tiles for this view and load the missing ones, a few per frame
+        let selected = self.surface.select(eye, &view_project_mat);
+        let (init, surface) = (&self.init, &self.surface);
+        match self.cache.update(&selected, surface.root(), |key| {
+            surface
+                .tile_data(key)
+                .map(|data| create_tile_buffers(init, create_vertices(data)))
+        }) {
+            Ok(drawn) => self.drawn = drawn,
+            Err(e) => println!("cannot load tile: {}", e),
+        }
+    }
+
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        let output = self.init.surface.get_current_texture()?;
+        let view = self.init.surface_view(&output.texture);
+
+        let mut encoder =
+            self.init
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Render Encoder"),
+                });
+
+        {
+            let mut render_pass = self.targets.begin_pass(&mut encoder, &self.init, &view);
+
+            // 0: both, 1: shape only, 2: wireframe only
+            let passes: &[usize] = match self.plot_type {
+                1 => &[0],
+                2 => &[1],
+                _ => &[0, 1],
+            };
+            for &k in passes.iter() {
+                self.pipelines.bind(&mut render_pass, k == 1);
+                for key in self.drawn.iter() {
+                    let Some(tile) = self.cache.get(key) else {
+                        continue;
+                    };
+                    render_pass.set_vertex_buffer(0, tile.vertex_buffers[k].slice(..));
+                    render_pass.set_index_buffer(
+                        tile.index_buffers[k].slice(..),
+                        wgpu::IndexFormat::Uint16,
+                    );
+                    render_pass.draw_indexed(0..tile.indices_lens[k], 0, 0..1);
+                }
+            }
+        }
+
+        self.fps_counter.log_fps(5);
+        self.init.queue.submit(std::iter::once(encoder.finish()));
+        output.present();
+
+        Ok(())
+    }
+}
+
+fn create_tile_buffers(
+    init: &ws::InitWgpu,
+    data: (Vec<Vertex>, Vec<Vertex>, Vec<u16>, Vec<u16>),
+) -> TileBuffers {
+    let vertex_buffer = |vertices: &[Vertex]| {
+        init.device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Tile Vertex Buffer"),
+                contents: cast_slice(vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            })
+    };
+    let index_buffer = |indices: &[u16]| {
+        init.device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Tile Index Buffer"),
+                contents: cast_slice(indices),
+                usage: wgpu::BufferUsages::INDEX,
+            })
+    };
+    TileBuffers {
+        vertex_buffers: [vertex_buffer(&data.0), vertex_buffer(&data.1)],
+        index_buffers: [index_buffer(&data.2), index_buffer(&data.3)],
+        indices_lens: [data.2.len() as u32, data.3.len() as u32],
+    }
+}
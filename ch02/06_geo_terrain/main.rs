@@ -0,0 +1,44 @@
+#[path = "../common/vertex.rs"]
+mod vertex;
+mod state;
+
+use wgpu_surfaces::app;
+use wgpu_surfaces::cli::SurfaceArgs;
+use wgpu_surfaces::geo::GeoTerrain;
+
+use crate::state::State;
+
+fn main() {
+    let args = SurfaceArgs::default().colormap("terrain").parse_env("[file.xyz]");
+
+    let title = "ch02 geo terrain";
+
+    if let Err(e) = app::run_app::<State>(&args, title) {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
+}
+
+// a gdal2xyz style "lon lat elevation" file, otherwise a synthetic volcano on a
+// 2 x 2 degree area at 60N
+pub fn terrain(positional: &[String]) -> GeoTerrain {
+    if let Some(path) = positional.first() {
+        let file = std::fs::File::open(path).map(std::io::BufReader::new);
+        match file.and_then(GeoTerrain::read_xyz) {
+            Ok(terrain) => return terrain,
+            Err(e) => println!("cannot read {}: {}", path, e),
+        }
+    }
+    let n = 201u16;
+    let mut elevations = vec![];
+    for i in 0..n {
+        for j in 0..n {
+            let (x, z) = (i as f32 / 50.0 - 2.0, j as f32 / 50.0 - 2.0);
+            let r = (x * x + z * z).sqrt();
+            // a cone with a crater, on rolling hills
+            let cone = 2500.0 * (-r * r).exp() - 800.0 * (-8.0 * r * r).exp();
+            elevations.push(cone + 150.0 * (3.0 * x).sin() * (2.0 * z).cos());
+        }
+    }
+    GeoTerrain::from_regular(n, n, [59.0, 61.0], [24.0, 26.0], elevations)
+}
@@ -0,0 +1,506 @@
+use std::sync::Arc;
+use bytemuck::cast_slice;
+use cgmath::{Matrix4, SquareMatrix};
+use winit::{event::ElementState, event::KeyEvent, event::WindowEvent, window::Window};
+
+use wgpu_surfaces::app::{self, Action, InputMap, SurfaceApp};
+use wgpu_surfaces::appearance::DirectionalLight;
+use wgpu_surfaces::geo::{GeoTerrain, MapProjection};
+use wgpu_surfaces::cli::SurfaceArgs;
+use wgpu_surfaces::noise::NoiseParams;
+use wgpu_surfaces::normal_map::{self, MicroBumps, NormalMap, TangentVertex};
+use wgpu_surfaces::wgpu_simplified as ws;
+use wgpu_surfaces::wireframe::{self, BarycentricVertex, WireframeStyle};
+
+use crate::vertex::{create_vertices, Vertex};
+
+// default key bindings, remapped with --keymap (see app::InputMap)
+const KEYS: &[(Action, &str)] = &[
+    (Action::CyclePlotType, "Space"),
+    (Action::CycleProjection, "p"),
+    (Action::IncreaseExaggeration, "e"),
+    (Action::DecreaseExaggeration, "d"),
+    (Action::ToggleNormalMap, "n"),
+    (Action::ToggleMicroBumps, "b"),
+    (Action::CycleWireframeStyle, "w"),
+];
+
+pub struct State {
+    init: ws::InitWgpu,
+    pipelines: Vec<wgpu::RenderPipeline>,
+    vertex_buffers: Vec<ws::GrowableBuffer>,
+    index_buffers: Vec<ws::GrowableBuffer>,
+    uniform_bind_groups: Vec<wgpu::BindGroup>,
+    uniform_buffers: Vec<wgpu::Buffer>, // vertex and micro-bump uniforms
+    lighting: app::LightUniforms,
+    targets: app::RenderTargets,
+    camera: ws::OrbitCamera,
+    project_mat: Matrix4<f32>,
+    indices_lens: Vec<u32>,
+    plot_type: u32,
+    recreate_buffers: bool,
+
+    terrain: GeoTerrain,
+    normal_map: NormalMap,
+    tangent_buffer: ws::GrowableBuffer, // surface vertices of the normal-mapped pipeline
+    normal_mapped: bool,
+    micro_bumps: MicroBumps,
+    micro_bumps_on: bool,
+    micro_bumps_bind_group: wgpu::BindGroup,
+    barycentric_buffer: ws::GrowableBuffer, // one-pass surface and wireframe vertices
+    barycentric_len: u32,
+    wireframe_style: WireframeStyle,
+    input_map: InputMap,
+    fps_counter: ws::FpsCounter,
+}
+
+impl SurfaceApp for State {
+    async fn new(window: Arc<Window>, args: &SurfaceArgs) -> wgpu_surfaces::Result<Self> {
+        let init = ws::InitWgpu::init_wgpu(window, args.msaa, args.present_mode).await?;
+
+        // Loading Shaders
+        let vs_shader = init
+            .device
+            .create_shader_module(wgpu::include_wgsl!("../01_simple_surface/shader_vert.wgsl"));
+        let fs_shader = init
+            .device
+            .create_shader_module(wgpu::include_wgsl!("../common/directional_frag.wgsl"));
+
+        let camera = ws::OrbitCamera::new((2.0, 2.5, 3.0).into(), (0.0, 0.0, 0.0).into());
+        let project_mat =
+            ws::create_projection_mat(init.config.width as f32 / init.config.height as f32, true);
+
+        // the terrain is built in world coordinates, so the model matrix stays the identity
+        let vert_uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Vertex Uniform Buffer"),
+            size: 192,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let identity: [f32; 16] = *Matrix4::<f32>::identity().as_ref();
+        init.queue
+            .write_buffer(&vert_uniform_buffer, 64, cast_slice(&identity));
+        init.queue
+            .write_buffer(&vert_uniform_buffer, 128, cast_slice(&identity));
+
+        // the eye position of the light follows the camera in update; a low evening sun,
+        // so the relief casts long shading
+        let light = DirectionalLight {
+            direction: [-0.7, -0.3, -0.4],
+            ..Default::default()
+        };
+        let lighting = app::LightUniforms::new(&init, &light, [0.2, 0.8, 0.1, 30.0]);
+
+        let (vert_bind_group_layout, vert_bind_group) = ws::create_bind_group(
+            &init.device,
+            vec![wgpu::ShaderStages::VERTEX],
+            &[vert_uniform_buffer.as_entire_binding()],
+        )?;
+        let (frag_bind_group_layout, frag_bind_group) = lighting.bind_group(&init.device)?;
+
+        let vertex_buffer_layout = [wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3],
+            // pos, norm, col
+        }];
+
+        let pipeline_layout = init
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &[&vert_bind_group_layout, &frag_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        // the surface gets micro-bumps close to the camera, the wireframe does not
+        let bumps_fs_shader = init.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Micro Bumps Fragment Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                MicroBumps::hooks(2)
+                    .apply(include_str!("../common/directional_frag.wgsl"))
+                    .into(),
+            ),
+        });
+        let micro_bumps_uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Micro Bumps Uniform Buffer"),
+            size: normal_map::MICRO_BUMPS_UNIFORM_SIZE,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        // the terrain is about 2 units across: bumps a few meters apart at full strength
+        // within half a unit of the eye
+        let micro_bumps = MicroBumps {
+            frequency: 60.0,
+            fade_near: 0.5,
+            fade_far: 1.5,
+            ..Default::default()
+        };
+        let (bumps_bind_group_layout, micro_bumps_bind_group) = ws::create_bind_group(
+            &init.device,
+            vec![wgpu::ShaderStages::FRAGMENT],
+            &[micro_bumps_uniform_buffer.as_entire_binding()],
+        )?;
+        let bumps_pipeline_layout = init
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Micro Bumps Pipeline Layout"),
+                bind_group_layouts: &[
+                    &vert_bind_group_layout,
+                    &frag_bind_group_layout,
+                    &bumps_bind_group_layout,
+                ],
+                push_constant_ranges: &[],
+            });
+
+        let mut ppl = ws::IRenderPipeline {
+            vs_shader: Some(&vs_shader),
+            fs_shader: Some(&bumps_fs_shader),
+            pipeline_layout: Some(&bumps_pipeline_layout),
+            vertex_buffer_layout: &vertex_buffer_layout,
+            ..Default::default()
+        };
+        let pipeline = ppl.new(&init)?;
+
+        let mut ppl2 = ws::IRenderPipeline {
+            topology: wgpu::PrimitiveTopology::LineList,
+            vs_shader: Some(&vs_shader),
+            fs_shader: Some(&fs_shader),
+            pipeline_layout: Some(&pipeline_layout),
+            vertex_buffer_layout: &vertex_buffer_layout,
+            ..Default::default()
+        };
+        let pipeline2 = ppl2.new(&init)?;
+
+        // the normal-mapped variant of the surface pipeline: a tiled detail map of
+        // fractal noise adds relief finer than the elevation grid
+        let detail = NoiseParams {
+            frequency: 8.0,
+            ..Default::default()
+        };
+        let rgba = normal_map::detail_normal_map(256, &detail, 24.0);
+        let mut normal_map = NormalMap::new(&init.device, &init.queue, 256, 256, &rgba);
+        normal_map.tiling = 8.0;
+        normal_map.write(&init.queue);
+        let nm_vs_shader = init.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Normal Map Vertex Shader"),
+            source: wgpu::ShaderSource::Wgsl(normal_map::NORMAL_MAP_VERT_WGSL.into()),
+        });
+        let nm_fs_shader = init.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Normal Map Fragment Shader"),
+            source: wgpu::ShaderSource::Wgsl(normal_map::NORMAL_MAP_FRAG_WGSL.into()),
+        });
+        let nm_pipeline_layout = init
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Normal Map Pipeline Layout"),
+                bind_group_layouts: &[
+                    &vert_bind_group_layout,
+                    &frag_bind_group_layout,
+                    &normal_map.layout,
+                ],
+                push_constant_ranges: &[],
+            });
+        let mut ppl3 = ws::IRenderPipeline {
+            vs_shader: Some(&nm_vs_shader),
+            fs_shader: Some(&nm_fs_shader),
+            pipeline_layout: Some(&nm_pipeline_layout),
+            vertex_buffer_layout: &[TangentVertex::layout()],
+            ..Default::default()
+        };
+        let pipeline3 = ppl3.new(&init)?;
+
+        // the Barycentric wireframe style: surface and wireframe in one draw, or the
+        // wireframe alone blended over the frame
+        // thicker lines with --theme high_contrast
+        let bary_source = wireframe::barycentric_wireframe_wgsl(args.theme.wireframe_width());
+        let bary_shader = init.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Barycentric Wireframe Shader"),
+            source: wgpu::ShaderSource::Wgsl(bary_source.into()),
+        });
+        let bary_pipelines = [false, true].map(|wire_only| {
+            ws::IRenderPipeline {
+                shader: Some(&bary_shader),
+                pipeline_layout: Some(&pipeline_layout),
+                vertex_buffer_layout: &[BarycentricVertex::layout()],
+                blend: wire_only.then_some(wgpu::BlendState::ALPHA_BLENDING),
+                fs_entry: WireframeStyle::Barycentric.fs_entry(wire_only).to_string(),
+                ..Default::default()
+            }
+            .new(&init)
+        });
+        let [pipeline4, pipeline5] = bary_pipelines;
+
+        let targets = app::RenderTargets::new(&init);
+
+        let mut terrain = crate::terrain(&args.positional);
+        terrain.colormap_name = args.colormap.clone();
+        terrain.wireframe_color = args.wireframe_color.clone();
+        // a few kilometers of relief are invisible at the scale of a whole region
+        terrain.vertical_exaggeration = 10.0;
+        print_terrain(&terrain);
+
+        let output = terrain.new();
+        let tangent_buffer = ws::GrowableBuffer::new(
+            &init.device,
+            "Tangent Vertex Buffer",
+            wgpu::BufferUsages::VERTEX,
+            cast_slice(&normal_map::tangent_vertices(&output)),
+        );
+        let barycentric_vertices = wireframe::barycentric_vertices(&output);
+        let barycentric_buffer = ws::GrowableBuffer::new(
+            &init.device,
+            "Barycentric Vertex Buffer",
+            wgpu::BufferUsages::VERTEX,
+            cast_slice(&barycentric_vertices),
+        );
+        let data = create_vertices(output);
+        let (vertex_buffers, index_buffers) = create_buffers(&init, &data);
+
+        let state = Self {
+            init,
+            pipelines: vec![pipeline, pipeline2, pipeline3, pipeline4?, pipeline5?],
+            vertex_buffers,
+            index_buffers,
+            uniform_bind_groups: vec![vert_bind_group, frag_bind_group],
+            uniform_buffers: vec![vert_uniform_buffer, micro_bumps_uniform_buffer],
+            lighting,
+            targets,
+            camera,
+            project_mat,
+            indices_lens: vec![data.2.len() as u32, data.3.len() as u32],
+            plot_type: 1,
+            recreate_buffers: false,
+
+            terrain,
+            normal_map,
+            tangent_buffer,
+            normal_mapped: false,
+            micro_bumps,
+            micro_bumps_on: false,
+            micro_bumps_bind_group,
+            barycentric_buffer,
+            barycentric_len: barycentric_vertices.len() as u32,
+            wireframe_style: WireframeStyle::default(),
+            input_map: InputMap::with_args(KEYS, args)?,
+            fps_counter: ws::FpsCounter::default(),
+        };
+        state.write_micro_bumps();
+        Ok(state)
+    }
+
+    fn init(&self) -> &ws::InitWgpu {
+        &self.init
+    }
+
+    fn init_mut(&mut self) -> &mut ws::InitWgpu {
+        &mut self.init
+    }
+
+    fn resized(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.project_mat =
+            ws::create_projection_mat(new_size.width as f32 / new_size.height as f32, true);
+        self.targets.resize(&self.init);
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        // left drag rotates, right drag pans and the wheel zooms
+        if self.camera.input(event) {
+            return true;
+        }
+        match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: key,
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => match self.input_map.action(key) {
+                Some(Action::CyclePlotType) => {
+                    self.plot_type = (self.plot_type + 1) % 3;
+                    true
+                }
+                // cycles equirectangular, mercator and globe
+                Some(Action::CycleProjection) => {
+                    self.terrain.projection = match self.terrain.projection {
+                        MapProjection::Equirectangular => MapProjection::Mercator,
+                        MapProjection::Mercator => MapProjection::Globe,
+                        MapProjection::Globe => MapProjection::Equirectangular,
+                    };
+                    print_terrain(&self.terrain);
+                    self.recreate_buffers = true;
+                    true
+                }
+                Some(Action::IncreaseExaggeration) => {
+                    self.terrain.vertical_exaggeration *= 2.0;
+                    print_terrain(&self.terrain);
+                    self.recreate_buffers = true;
+                    true
+                }
+                Some(Action::DecreaseExaggeration) => {
+                    self.terrain.vertical_exaggeration =
+                        (self.terrain.vertical_exaggeration * 0.5).max(1.0);
+                    print_terrain(&self.terrain);
+                    self.recreate_buffers = true;
+                    true
+                }
+                // detail normal map on or off
+                Some(Action::ToggleNormalMap) => {
+                    self.normal_mapped = !self.normal_mapped;
+                    println!("detail normal map: {}", self.normal_mapped);
+                    true
+                }
+                // micro-bumps on or off; they show when zoomed in
+                Some(Action::ToggleMicroBumps) => {
+                    self.micro_bumps_on = !self.micro_bumps_on;
+                    println!("micro-bumps: {}", self.micro_bumps_on);
+                    self.write_micro_bumps();
+                    true
+                }
+                // line list or one-pass barycentric wireframe
+                Some(Action::CycleWireframeStyle) => {
+                    self.wireframe_style = self.wireframe_style.next();
+                    println!("wireframe: {}", self.wireframe_style.name());
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn update(&mut self, _dt: std::time::Duration) {
+        let view_project_mat = self.project_mat * self.camera.view_mat();
+        let view_projection_ref: &[f32; 16] = view_project_mat.as_ref();
+        self.init
+            .queue
+            .write_buffer(&self.uniform_buffers[0], 0, cast_slice(view_projection_ref));
+        self.lighting.set_eye_position(&self.init.queue, self.camera.eye().into());
+
+        // the vertex count stays the same, so the buffers are only rewritten
+        if self.recreate_buffers {
+            let output = self.terrain.new();
+            let (device, queue) = (&self.init.device, &self.init.queue);
+            let tangent_vertices = normal_map::tangent_vertices(&output);
+            self.tangent_buffer
+                .write(device, queue, cast_slice(&tangent_vertices));
+            let barycentric_vertices = wireframe::barycentric_vertices(&output);
+            self.barycentric_buffer
+                .write(device, queue, cast_slice(&barycentric_vertices));
+            self.barycentric_len = barycentric_vertices.len() as u32;
+            let data = create_vertices(output);
+            self.vertex_buffers[0].write(device, queue, cast_slice(&data.0));
+            self.vertex_buffers[1].write(device, queue, cast_slice(&data.1));
+            self.index_buffers[0].write(device, queue, cast_slice(&data.2));
+            self.index_buffers[1].write(device, queue, cast_slice(&data.3));
+            self.indices_lens = vec![data.2.len() as u32, data.3.len() as u32];
+            self.recreate_buffers = false;
+        }
+    }
+
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        let output = self.init.surface.get_current_texture()?;
+        let view = self.init.surface_view(&output.texture);
+
+        let mut encoder =
+            self.init
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Render Encoder"),
+                });
+
+        {
+            let mut render_pass = self.targets.begin_pass(&mut encoder, &self.init, &view);
+
+            render_pass.set_bind_group(0, &self.uniform_bind_groups[0], &[]);
+            render_pass.set_bind_group(1, &self.uniform_bind_groups[1], &[]);
+
+            // 0: both, 1: shape only, 2: wireframe only; the barycentric pass draws
+            // the surface without the detail normal map
+            if self.wireframe_style == WireframeStyle::Barycentric && self.plot_type != 1 {
+                let k = if self.plot_type == 2 { 4 } else { 3 };
+                render_pass.set_pipeline(&self.pipelines[k]);
+                render_pass.set_vertex_buffer(0, self.barycentric_buffer.slice());
+                render_pass.draw(0..self.barycentric_len, 0..1);
+            }
+            let passes: &[usize] = match (self.plot_type, self.wireframe_style) {
+                (1, _) => &[0],
+                (_, WireframeStyle::Barycentric) => &[],
+                (2, _) => &[1],
+                _ => &[0, 1],
+            };
+            for &k in passes.iter() {
+                if k == 0 && self.normal_mapped {
+                    render_pass.set_pipeline(&self.pipelines[2]);
+                    render_pass.set_bind_group(2, &self.normal_map.bind_group, &[]);
+                    render_pass.set_vertex_buffer(0, self.tangent_buffer.slice());
+                } else {
+                    if k == 0 {
+                        render_pass.set_bind_group(2, &self.micro_bumps_bind_group, &[]);
+                    }
+                    render_pass.set_pipeline(&self.pipelines[k]);
+                    render_pass.set_vertex_buffer(0, self.vertex_buffers[k].slice());
+                }
+                render_pass
+                    .set_index_buffer(self.index_buffers[k].slice(), wgpu::IndexFormat::Uint16);
+                render_pass.draw_indexed(0..self.indices_lens[k], 0, 0..1);
+            }
+        }
+
+        self.fps_counter.log_fps(5);
+        self.init.queue.submit(std::iter::once(encoder.finish()));
+        output.present();
+
+        Ok(())
+    }
+}
+
+impl State {
+    // off is a zero strength, so the pipeline stays the same
+    fn write_micro_bumps(&self) {
+        let mut bumps = self.micro_bumps;
+        if !self.micro_bumps_on {
+            bumps.strength = 0.0;
+        }
+        self.init
+            .queue
+            .write_buffer(&self.uniform_buffers[1], 0, cast_slice(&bumps.uniform_data()));
+    }
+}
+
+fn print_terrain(terrain: &GeoTerrain) {
+    let [lat, lon] = terrain.center();
+    let [min, max] = terrain.elevation_range();
+    println!(
+        "{} x {} grid at {:.3}, {:.3}, elevation {:.0} to {:.0} m, {:?}, exaggeration {}",
+        terrain.rows,
+        terrain.cols,
+        lat,
+        lon,
+        min,
+        max,
+        terrain.projection,
+        terrain.vertical_exaggeration
+    );
+}
+
+// surface and wireframe vertex and index buffers
+fn create_buffers(
+    init: &ws::InitWgpu,
+    data: &(Vec<Vertex>, Vec<Vertex>, Vec<u16>, Vec<u16>),
+) -> (Vec<ws::GrowableBuffer>, Vec<ws::GrowableBuffer>) {
+    let buffer = |label: &str, usage: wgpu::BufferUsages, contents: &[u8]| {
+        ws::GrowableBuffer::new(&init.device, label, usage, contents)
+    };
+    let vertex_buffers = [&data.0, &data.1]
+        .iter()
+        .map(|vertices| buffer("Vertex Buffer", wgpu::BufferUsages::VERTEX, cast_slice(vertices)))
+        .collect();
+    let index_buffers = [&data.2, &data.3]
+        .iter()
+        .map(|indices| buffer("Index Buffer", wgpu::BufferUsages::INDEX, cast_slice(indices)))
+        .collect();
+    (vertex_buffers, index_buffers)
+}
@@ -0,0 +1,296 @@
+#[path = "../common/vertex.rs"]
+mod vertex;
+
+use bytemuck::cast_slice;
+use cgmath::{Matrix, SquareMatrix};
+use wgpu::util::DeviceExt;
+
+use wgpu_surfaces::capture;
+#[cfg(feature = "stream")]
+use wgpu_surfaces::stream::FrameServer;
+use wgpu_surfaces::cli::SurfaceArgs;
+use wgpu_surfaces::surface_data as sd;
+use wgpu_surfaces::wgpu_simplified as ws;
+
+use crate::vertex::{Vertex, create_vertices};
+
+// renders the simple surface into a PNG without opening a window, e.g. on CI or to
+// generate figures in a batch script; positional arguments: output width height and
+// optionally a number of jittered frames averaged into a publication-quality still.
+// With "transparent" among them the background is left out: the PNG keeps only the
+// surface, with an alpha channel, for documents with any background color. With
+// serve=ADDR (the stream feature) the surface is animated and its frames are streamed
+// to browsers opening http://ADDR/ instead
+fn main() {
+    let args = SurfaceArgs::default()
+        .surface_types(sd::SIMPLE_SURFACE_COUNT)
+        .parse_env("[output.png [width height [frames]]] [transparent] [serve=ADDR]");
+    let transparent = args.positional.iter().any(|arg| arg == "transparent");
+    let serve = args
+        .positional
+        .iter()
+        .find_map(|arg| arg.strip_prefix("serve="));
+    let positional: Vec<&str> = args
+        .positional
+        .iter()
+        .map(|arg| arg.as_str())
+        .filter(|&arg| arg != "transparent" && !arg.starts_with("serve="))
+        .collect();
+    let output = positional.first().copied().unwrap_or("simple_surface.png");
+    let numbers: Vec<u32> = positional[1.min(positional.len())..]
+        .iter()
+        .filter_map(|arg| arg.parse().ok())
+        .collect();
+    let (width, height) = match numbers[..] {
+        [width, height, ..] if width > 0 && height > 0 => (width, height),
+        _ => (1024, 768),
+    };
+    let frames = numbers.get(2).copied().unwrap_or(1).max(1);
+
+    env_logger::init();
+    let render = render(&args, output, width, height, frames, transparent, serve);
+    if let Err(e) = pollster::block_on(render) {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
+}
+
+async fn render(
+    args: &SurfaceArgs,
+    output: &str,
+    width: u32,
+    height: u32,
+    frames: u32,
+    transparent: bool,
+    serve: Option<&str>,
+) -> anyhow::Result<()> {
+    let init = ws::InitWgpuHeadless::init_wgpu(width, height, args.msaa).await?;
+    let info = init.adapter.get_info();
+    println!("rendering on {} ({:?})", info.name, info.backend);
+
+    let mut simple_surface = sd::ISimpleSurface {
+        surface_type: args.surface_type.unwrap_or(0),
+        colormap_name: args.colormap.clone(),
+        wireframe_color: args.wireframe_color.clone(),
+        ..Default::default()
+    };
+    if let Some(resolution) = args.resolution {
+        simple_surface.x_resolution = resolution;
+        simple_surface.z_resolution = resolution;
+    }
+    let (vertices, vertices2, indices, indices2) = create_vertices(simple_surface.new());
+
+    let vs_shader = init
+        .device
+        .create_shader_module(wgpu::include_wgsl!("../01_simple_surface/shader_vert.wgsl"));
+    let fs_shader = init
+        .device
+        .create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(args.material.fragment_label()),
+            source: wgpu::ShaderSource::Wgsl(args.material.fragment_source().into()),
+        });
+
+    // the same camera, light and material as the windowed example
+    let eye = cgmath::Point3::new(4.0, 4.0, 4.0);
+    let (view_mat, project_mat, _) = ws::create_vp_mat(
+        eye,
+        (0.0, 0.0, 0.0).into(),
+        cgmath::Vector3::unit_y(),
+        width as f32 / height as f32,
+    );
+    let model_mat = ws::create_model_mat([0.0, 0.0, 0.0], [0.0, 0.0, 0.0], [1.0, 1.0, 1.0]);
+    let normal_mat = model_mat.invert().unwrap().transpose();
+    // the view-projection matrix is written per frame, with its jitter
+    let mut vert_uniforms = vec![0.0f32; 16];
+    for mat in [model_mat, normal_mat] {
+        let mat_ref: &[f32; 16] = mat.as_ref();
+        vert_uniforms.extend_from_slice(mat_ref);
+    }
+    let light_uniforms = [
+        -0.5f32, -0.5, -0.5, 0.0, eye.x, eye.y, eye.z, 0.0, 1.0, 1.0, 1.0, 0.0,
+    ];
+    let material_uniforms = args.material.default_material().uniform_data();
+    let uniform_buffer = |label: &str, contents: &[f32]| {
+        init.device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents: cast_slice(contents),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            })
+    };
+    let vert_uniform_buffer = uniform_buffer("Vertex Uniform Buffer", &vert_uniforms);
+    let light_uniform_buffer = uniform_buffer("Light Uniform Buffer", &light_uniforms);
+    let material_uniform_buffer = uniform_buffer("Material Uniform Buffer", &material_uniforms);
+
+    let (vert_bind_group_layout, vert_bind_group) = ws::create_bind_group(
+        &init.device,
+        vec![wgpu::ShaderStages::VERTEX],
+        &[vert_uniform_buffer.as_entire_binding()],
+    )?;
+    let (frag_bind_group_layout, frag_bind_group) = ws::create_bind_group(
+        &init.device,
+        vec![wgpu::ShaderStages::FRAGMENT, wgpu::ShaderStages::FRAGMENT],
+        &[
+            light_uniform_buffer.as_entire_binding(),
+            material_uniform_buffer.as_entire_binding(),
+        ],
+    )?;
+    let pipeline_layout = init
+        .device
+        .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Render Pipeline Layout"),
+            bind_group_layouts: &[&vert_bind_group_layout, &frag_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+    let vertex_buffer_layout = [wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3],
+        // pos, norm, col
+    }];
+    // shape and wireframe
+    let [pipeline, wireframe_pipeline] = [
+        wgpu::PrimitiveTopology::TriangleList,
+        wgpu::PrimitiveTopology::LineList,
+    ]
+    .map(|topology| {
+        ws::IRenderPipeline {
+            topology,
+            vs_shader: Some(&vs_shader),
+            fs_shader: Some(&fs_shader),
+            pipeline_layout: Some(&pipeline_layout),
+            vertex_buffer_layout: &vertex_buffer_layout,
+            ..Default::default()
+        }
+        .new(&init)
+    });
+    let pipelines = [pipeline?, wireframe_pipeline?];
+
+    let buffer = |label: &str, contents: &[u8], usage: wgpu::BufferUsages| {
+        init.device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents,
+                usage,
+            })
+    };
+    // rewritten for every frame of a stream
+    let vertex_usage = wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST;
+    let vertex_buffers = [
+        buffer("Vertex Buffer", cast_slice(&vertices), vertex_usage),
+        buffer("Vertex Buffer 2", cast_slice(&vertices2), vertex_usage),
+    ];
+    let index_buffers = [
+        buffer(
+            "Index Buffer",
+            cast_slice(&indices),
+            wgpu::BufferUsages::INDEX,
+        ),
+        buffer(
+            "Index Buffer 2",
+            cast_slice(&indices2),
+            wgpu::BufferUsages::INDEX,
+        ),
+    ];
+    let index_counts = [indices.len() as u32, indices2.len() as u32];
+
+    let msaa_texture_view = ws::create_msaa_texture_view(&init);
+    let depth_texture_view = ws::create_depth_view(&init);
+    let draw = |vp_mat: cgmath::Matrix4<f32>| -> anyhow::Result<()> {
+        let vp_ref: &[f32; 16] = vp_mat.as_ref();
+        init.queue
+            .write_buffer(&vert_uniform_buffer, 0, cast_slice(vp_ref));
+        let mut encoder = init
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Headless Encoder"),
+            });
+        {
+            let mut color_attachment = if init.sample_count == 1 {
+                ws::create_color_attachment(&init.view)
+            } else {
+                ws::create_msaa_color_attachment(&init.view, &msaa_texture_view)
+            };
+            if transparent {
+                color_attachment.ops.load = wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT);
+            }
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Headless Pass"),
+                color_attachments: &[Some(color_attachment)],
+                depth_stencil_attachment: Some(ws::create_depth_stencil_attachment(
+                    &depth_texture_view,
+                )),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            render_pass.set_bind_group(0, &vert_bind_group, &[]);
+            render_pass.set_bind_group(1, &frag_bind_group, &[]);
+            for i in 0..2 {
+                render_pass.set_pipeline(&pipelines[i]);
+                render_pass.set_vertex_buffer(0, vertex_buffers[i].slice(..));
+                render_pass.set_index_buffer(index_buffers[i].slice(..), wgpu::IndexFormat::Uint16);
+                render_pass.draw_indexed(0..index_counts[i], 0, 0..1);
+            }
+        }
+        init.queue.submit(std::iter::once(encoder.finish()));
+        Ok(())
+    };
+
+    if let Some(addr) = serve {
+        return stream_frames(&init, addr, |t| {
+            simple_surface.t = t;
+            let (vertices, vertices2, _, _) = create_vertices(simple_surface.new());
+            init.queue
+                .write_buffer(&vertex_buffers[0], 0, cast_slice(&vertices));
+            init.queue
+                .write_buffer(&vertex_buffers[1], 0, cast_slice(&vertices2));
+            draw(project_mat * view_mat)
+        });
+    }
+
+    // a single frame is not jittered; more frames are averaged, each shifted by a
+    // different sub-pixel offset
+    let mut accumulator = capture::Accumulator::new(width, height);
+    for offset in capture::jitter_offsets(frames) {
+        draw(capture::jitter_mat(offset, width, height) * project_mat * view_mat)?;
+        accumulator.add(&init.read_rgba()?)?;
+    }
+    if frames > 1 {
+        println!("averaged {frames} jittered frames");
+    }
+
+    let mut rgba = accumulator.resolve();
+    if transparent {
+        capture::unpremultiply(&mut rgba);
+    }
+    capture::save_png(output, width, height, &rgba)?;
+    println!("saved {output} ({width} x {height})");
+    Ok(())
+}
+
+// renders the frame at the time in seconds since the start and publishes it, at
+// about 30 frames per second until the process is stopped
+#[cfg(feature = "stream")]
+fn stream_frames(
+    init: &ws::InitWgpuHeadless,
+    addr: &str,
+    mut render_frame: impl FnMut(f32) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let server = FrameServer::bind(addr)?;
+    println!("streaming on http://{}/", server.addr);
+    let start = std::time::Instant::now();
+    loop {
+        render_frame(start.elapsed().as_secs_f32())?;
+        server.publish_target(init)?;
+        std::thread::sleep(std::time::Duration::from_millis(33));
+    }
+}
+
+#[cfg(not(feature = "stream"))]
+fn stream_frames(
+    _init: &ws::InitWgpuHeadless,
+    _addr: &str,
+    _render_frame: impl FnMut(f32) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    anyhow::bail!("serve=ADDR needs the stream feature (--features stream)")
+}
@@ -0,0 +1,22 @@
+#[path = "../common/vertex.rs"]
+mod vertex;
+mod state;
+
+use wgpu_surfaces::app;
+use wgpu_surfaces::cli::SurfaceArgs;
+use wgpu_surfaces::surface_data as sd;
+
+use crate::state::State;
+
+fn main() {
+    let args = SurfaceArgs::default()
+        .surface_types(sd::SIMPLE_SURFACE_COUNT)
+        .parse_env("");
+
+    let title = "ch02 shadow surface";
+
+    if let Err(e) = app::run_app::<State>(&args, title) {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,44 @@
+// directional_frag.wgsl with the direct light dimmed in shadow; prepend
+// shadow::shadow_wgsl(2) for shadow_factor
+struct LightUniforms {
+    lightDirection : vec4f,
+    eyePosition : vec4f,
+    specularColor : vec4f,
+}
+@group(1) @binding(0) var<uniform> light : LightUniforms;
+
+struct MaterialUniforms {
+    ambient: f32,
+    diffuse: f32,
+    specular: f32,
+    shininess: f32,
+}
+@group(1) @binding(1) var<uniform> material : MaterialUniforms;
+
+struct Input {
+    @location(0) vPosition:vec4f,
+    @location(1) vNormal:vec4f,
+    @location(2) vColor: vec4f,
+}
+
+fn blinnPhong(N:vec3f, L:vec3f, V:vec3f) -> vec2f{
+    let H = normalize(L + V);
+    var diffuse = material.diffuse * max(dot(N, L), 0.0);
+    diffuse += material.diffuse * max(dot(-N, L), 0.0);
+    var specular = material.specular * pow(max(dot(N, H), 0.0), material.shininess);
+    specular += material.specular * pow(max(dot(-N, H),0.0), material.shininess);
+    return vec2(diffuse, specular);
+}
+
+@fragment
+fn fs_main(in:Input) ->  @location(0) vec4f {
+    var N = normalize(in.vNormal.xyz);
+    let L = normalize(-light.lightDirection.xyz);
+    let V = normalize(light.eyePosition.xyz - in.vPosition.xyz);
+
+    let bp = blinnPhong(N, L, V);
+    let lit = shadow_factor(in.vPosition.xyz);
+    let finalColor = in.vColor * (material.ambient + lit * bp[0]) + light.specularColor * lit * bp[1];
+
+    return vec4<f32>(finalColor.rgb, 1.0);
+}
@@ -0,0 +1,484 @@
+use std::sync::Arc;
+use bytemuck::cast_slice;
+use cgmath::{Matrix, Matrix4, SquareMatrix};
+use winit::{event::ElementState, event::KeyEvent, event::WindowEvent, window::Window};
+
+use wgpu_surfaces::app::{self, Action, InputMap, SurfaceApp};
+use wgpu_surfaces::appearance::DirectionalLight;
+use wgpu_surfaces::cli::SurfaceArgs;
+use wgpu_surfaces::color;
+use wgpu_surfaces::shadow::{self, ShadowMap};
+use wgpu_surfaces::surface_data as sd;
+use wgpu_surfaces::wgpu_simplified as ws;
+
+use crate::vertex::{create_vertices, Vertex};
+
+// the surface floats above a ground plane and casts its shadow onto it
+const GROUND_HALF_SIZE: f32 = 4.0;
+const GROUND_HEIGHT: f32 = -1.2;
+const SURFACE_HEIGHT: f32 = 1.0;
+const SHADOW_MAP_SIZE: u32 = 2048;
+// bounding sphere of the scene for the light-space box
+const SCENE_CENTER: [f32; 3] = [0.0, 0.5, 0.0];
+const SCENE_RADIUS: f32 = 6.0;
+
+// default key bindings, remapped with --keymap (see app::InputMap)
+const KEYS: &[(Action, &str)] = &[
+    (Action::NextSurfaceType, "Control"),
+    (Action::TurnLightLeft, "ArrowLeft"),
+    (Action::TurnLightRight, "ArrowRight"),
+    (Action::ToggleShadow, "b"),
+    (Action::ToggleShadowCatcher, "g"),
+    (Action::CycleShadowFilter, "p"),
+    (Action::IncreaseAnimationSpeed, "e"),
+    (Action::DecreaseAnimationSpeed, "d"),
+    (Action::IncreaseRotationSpeed, "r"),
+    (Action::DecreaseRotationSpeed, "f"),
+];
+
+pub struct State {
+    init: ws::InitWgpu,
+    pipelines: Vec<wgpu::RenderPipeline>, // lit and shadowed, depth-only shadow pass, catcher
+    vertex_buffers: Vec<ws::GrowableBuffer>, // surface, ground
+    index_buffers: Vec<ws::GrowableBuffer>,
+    uniform_bind_groups: Vec<wgpu::BindGroup>, // surface and ground vertex uniforms, light
+    uniform_buffers: Vec<wgpu::Buffer>, // surface and ground vertex uniforms
+    lighting: app::LightUniforms,
+    shadow_bind_groups: Vec<wgpu::BindGroup>, // light-space and model matrix per caster
+    shadow_buffers: Vec<wgpu::Buffer>,
+    shadow_map: ShadowMap,
+    camera: ws::OrbitCamera,
+    project_mat: Matrix4<f32>,
+    targets: app::RenderTargets,
+    indices_lens: Vec<u32>,
+    animation_speed: f32,
+    rotation_speed: f32,
+    light_azimuth: f32,
+    show_shadow: bool,
+    shadow_catcher: bool, // the ground shows only its shadow
+
+    simple_surface: sd::ISimpleSurface,
+    input_map: InputMap,
+    fps_counter: ws::FpsCounter,
+}
+
+impl SurfaceApp for State {
+    async fn new(window: Arc<Window>, args: &SurfaceArgs) -> wgpu_surfaces::Result<Self> {
+        let init = ws::InitWgpu::init_wgpu(window, args.msaa, args.present_mode).await?;
+
+        // Loading Shaders
+        let vs_shader = init
+            .device
+            .create_shader_module(wgpu::include_wgsl!("../01_simple_surface/shader_vert.wgsl"));
+        let fs_shader = init.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("shadow_frag.wgsl"),
+            source: wgpu::ShaderSource::Wgsl(
+                shadow::with_shadow(2, include_str!("shadow_frag.wgsl")).into(),
+            ),
+        });
+        let catcher_shader = init.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shadow Catcher Shader"),
+            source: wgpu::ShaderSource::Wgsl(shadow::shadow_catcher_wgsl(2).into()),
+        });
+        let shadow_shader = init.device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shadow Depth Shader"),
+            source: wgpu::ShaderSource::Wgsl(shadow::SHADOW_DEPTH_WGSL.into()),
+        });
+
+        let camera = ws::OrbitCamera::new((5.0, 4.0, 5.0).into(), (0.0, 0.0, 0.0).into());
+        let project_mat =
+            ws::create_projection_mat(init.config.width as f32 / init.config.height as f32, true);
+
+        // vp, model and normal matrices of the surface and of the ground, which is built
+        // in world coordinates
+        let vert_uniform_buffers: Vec<wgpu::Buffer> = ["Surface", "Ground"]
+            .iter()
+            .map(|name| {
+                init.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some(&format!("{} Vertex Uniform Buffer", name)),
+                    size: 192,
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                })
+            })
+            .collect();
+        let identity: [f32; 16] = *Matrix4::<f32>::identity().as_ref();
+        init.queue
+            .write_buffer(&vert_uniform_buffers[1], 64, cast_slice(&identity));
+        init.queue
+            .write_buffer(&vert_uniform_buffers[1], 128, cast_slice(&identity));
+
+        // the light direction and eye position are written in update
+        let lighting =
+            app::LightUniforms::new(&init, &DirectionalLight::default(), [0.15, 0.75, 0.3, 30.0]);
+
+        let vert_bind_group_layout =
+            ws::create_bind_group_layout(&init.device, vec![wgpu::ShaderStages::VERTEX]);
+        let vert_bind_groups: Vec<wgpu::BindGroup> = vert_uniform_buffers
+            .iter()
+            .map(|buffer| {
+                ws::create_bind_group(
+                    &init.device,
+                    vec![wgpu::ShaderStages::VERTEX],
+                    &[buffer.as_entire_binding()],
+                )
+                .map(|(_, bind_group)| bind_group)
+            })
+            .collect::<wgpu_surfaces::Result<_>>()?;
+        let (frag_bind_group_layout, frag_bind_group) = lighting.bind_group(&init.device)?;
+
+        // the shadow map, its comparison sampler and light-space matrix at group 2
+        let shadow_map = ShadowMap::new(&init, SHADOW_MAP_SIZE);
+
+        let vertex_buffer_layout = [wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3],
+            // pos, norm, col
+        }];
+
+        let pipeline_layout = init
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &[
+                    &vert_bind_group_layout,
+                    &frag_bind_group_layout,
+                    &shadow_map.layout,
+                ],
+                push_constant_ranges: &[],
+            });
+
+        let mut ppl = ws::IRenderPipeline {
+            vs_shader: Some(&vs_shader),
+            fs_shader: Some(&fs_shader),
+            pipeline_layout: Some(&pipeline_layout),
+            vertex_buffer_layout: &vertex_buffer_layout,
+            ..Default::default()
+        };
+        let pipeline = ppl.new(&init)?;
+
+        // the ground as a shadow catcher, blended over what is behind it
+        let mut ppl2 = ws::IRenderPipeline {
+            vs_shader: Some(&vs_shader),
+            fs_shader: Some(&catcher_shader),
+            pipeline_layout: Some(&pipeline_layout),
+            vertex_buffer_layout: &vertex_buffer_layout,
+            blend: Some(shadow::SHADOW_CATCHER_BLENDING),
+            depth_write_enabled: false,
+            ..Default::default()
+        };
+        let catcher_pipeline = ppl2.new(&init)?;
+
+        // depth-only pass from the light, one light-space and model matrix per caster
+        let shadow_buffers: Vec<wgpu::Buffer> = ["Surface", "Ground"]
+            .iter()
+            .map(|name| {
+                init.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some(&format!("{} Shadow Pass Buffer", name)),
+                    size: 128,
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                })
+            })
+            .collect();
+        init.queue
+            .write_buffer(&shadow_buffers[1], 64, cast_slice(&identity));
+        let shadow_bind_groups: Vec<wgpu::BindGroup> = shadow_buffers
+            .iter()
+            .map(|buffer| {
+                ws::create_bind_group(
+                    &init.device,
+                    vec![wgpu::ShaderStages::VERTEX],
+                    &[buffer.as_entire_binding()],
+                )
+                .map(|(_, bind_group)| bind_group)
+            })
+            .collect::<wgpu_surfaces::Result<_>>()?;
+        let shadow_pipeline_layout =
+            init.device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("Shadow Pipeline Layout"),
+                    bind_group_layouts: &[&vert_bind_group_layout],
+                    push_constant_ranges: &[],
+                });
+        let shadow_pipeline = shadow::create_shadow_pipeline(
+            &init.device,
+            &shadow_shader,
+            &shadow_pipeline_layout,
+            &vertex_buffer_layout,
+        );
+
+        let targets = app::RenderTargets::new(&init);
+
+        let mut ss = sd::ISimpleSurface {
+            scale: 1.5,
+            colormap_name: args.colormap.clone(),
+            ..Default::default()
+        };
+        if let Some(resolution) = args.resolution {
+            (ss.x_resolution, ss.z_resolution) = (resolution, resolution);
+        }
+        if let Some(surface_type) = args.surface_type {
+            ss.surface_type = surface_type;
+        }
+        let data = create_vertices(ss.new());
+        let (ground_vertices, ground_indices) = ground();
+
+        let vertex_buffers = vec![
+            ws::GrowableBuffer::new(
+                &init.device,
+                "Vertex Buffer",
+                wgpu::BufferUsages::VERTEX,
+                cast_slice(&data.0),
+            ),
+            ws::GrowableBuffer::new(
+                &init.device,
+                "Ground Vertex Buffer",
+                wgpu::BufferUsages::VERTEX,
+                cast_slice(&ground_vertices),
+            ),
+        ];
+        let index_buffers = vec![
+            ws::GrowableBuffer::new(
+                &init.device,
+                "Index Buffer",
+                wgpu::BufferUsages::INDEX,
+                cast_slice(&data.2),
+            ),
+            ws::GrowableBuffer::new(
+                &init.device,
+                "Ground Index Buffer",
+                wgpu::BufferUsages::INDEX,
+                cast_slice(&ground_indices),
+            ),
+        ];
+
+        let mut uniform_bind_groups = vert_bind_groups;
+        uniform_bind_groups.push(frag_bind_group);
+
+        Ok(Self {
+            init,
+            pipelines: vec![pipeline, shadow_pipeline, catcher_pipeline],
+            vertex_buffers,
+            index_buffers,
+            uniform_bind_groups,
+            uniform_buffers: vert_uniform_buffers,
+            lighting,
+            shadow_bind_groups,
+            shadow_buffers,
+            shadow_map,
+            camera,
+            project_mat,
+            targets,
+            indices_lens: vec![data.2.len() as u32, ground_indices.len() as u32],
+            animation_speed: 1.0,
+            rotation_speed: 0.5,
+            light_azimuth: 0.6,
+            show_shadow: true,
+            shadow_catcher: false,
+
+            simple_surface: ss,
+            input_map: InputMap::with_args(KEYS, args)?,
+            fps_counter: ws::FpsCounter::default(),
+        })
+    }
+
+    fn init(&self) -> &ws::InitWgpu {
+        &self.init
+    }
+
+    fn init_mut(&mut self) -> &mut ws::InitWgpu {
+        &mut self.init
+    }
+
+    fn resized(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.project_mat =
+            ws::create_projection_mat(new_size.width as f32 / new_size.height as f32, true);
+        self.targets.resize(&self.init);
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        // left drag rotates, right drag pans and the wheel zooms
+        if self.camera.input(event) {
+            return true;
+        }
+        match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: key,
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => match self.input_map.action(key) {
+                Some(Action::NextSurfaceType) => {
+                    self.simple_surface.surface_type =
+                        (self.simple_surface.surface_type + 1) % sd::SIMPLE_SURFACE_COUNT;
+                    true
+                }
+                // left/right moves the light around the scene
+                Some(Action::TurnLightLeft) => {
+                    self.light_azimuth -= 0.1;
+                    true
+                }
+                Some(Action::TurnLightRight) => {
+                    self.light_azimuth += 0.1;
+                    true
+                }
+                Some(Action::ToggleShadow) => {
+                    self.show_shadow = !self.show_shadow;
+                    true
+                }
+                // the ground only as the shadow it receives
+                Some(Action::ToggleShadowCatcher) => {
+                    self.shadow_catcher = !self.shadow_catcher;
+                    match self.shadow_catcher {
+                        true => println!("ground: shadow catcher"),
+                        false => println!("ground: lit"),
+                    }
+                    true
+                }
+                // hard shadow edges, then softer ones
+                Some(Action::CycleShadowFilter) => {
+                    let map = &mut self.shadow_map;
+                    map.pcf_radius = (map.pcf_radius + 1.0) % 4.0;
+                    println!("shadow filter radius: {} texels", map.pcf_radius);
+                    true
+                }
+                Some(Action::IncreaseAnimationSpeed) => {
+                    self.animation_speed += 0.1;
+                    true
+                }
+                Some(Action::DecreaseAnimationSpeed) => {
+                    self.animation_speed = (self.animation_speed - 0.1).max(0.0);
+                    true
+                }
+                Some(Action::IncreaseRotationSpeed) => {
+                    self.rotation_speed += 0.1;
+                    true
+                }
+                Some(Action::DecreaseRotationSpeed) => {
+                    self.rotation_speed = (self.rotation_speed - 0.1).max(0.0);
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn update(&mut self, dt: std::time::Duration) {
+        let queue = &self.init.queue;
+        let view_project_mat = self.project_mat * self.camera.view_mat();
+        let view_projection_ref: &[f32; 16] = view_project_mat.as_ref();
+        let angle = self.rotation_speed * dt.as_secs_f32();
+        let model_mat = ws::create_model_mat(
+            [0.0, SURFACE_HEIGHT, 0.0],
+            [0.0, angle, 0.0],
+            [1.0, 1.0, 1.0],
+        );
+        let normal_mat = (model_mat.invert().unwrap()).transpose();
+        let model_ref: &[f32; 16] = model_mat.as_ref();
+        let normal_ref: &[f32; 16] = normal_mat.as_ref();
+        for buffer in &self.uniform_buffers {
+            queue.write_buffer(buffer, 0, cast_slice(view_projection_ref));
+        }
+        queue.write_buffer(&self.uniform_buffers[0], 64, cast_slice(model_ref));
+        queue.write_buffer(&self.uniform_buffers[0], 128, cast_slice(normal_ref));
+
+        // a high sun circling the scene
+        let (s, c) = self.light_azimuth.sin_cos();
+        let light_direction = [-0.5 * c, -1.0, -0.5 * s];
+        let light = DirectionalLight {
+            direction: light_direction,
+            eye_position: self.camera.eye().into(),
+            ..Default::default()
+        };
+        self.lighting.set_light(queue, &light);
+
+        let light_mat = shadow::light_space_mat(light_direction, SCENE_CENTER, SCENE_RADIUS);
+        let light_ref: &[f32; 16] = light_mat.as_ref();
+        for buffer in &self.shadow_buffers {
+            queue.write_buffer(buffer, 0, cast_slice(light_ref));
+        }
+        queue.write_buffer(&self.shadow_buffers[0], 64, cast_slice(model_ref));
+        let darkness = self.shadow_map.darkness;
+        if !self.show_shadow {
+            self.shadow_map.darkness = 0.0;
+        }
+        self.shadow_map.write(queue, &light_mat);
+        self.shadow_map.darkness = darkness;
+
+        // the animated surface; the vertex count only changes with the surface type
+        self.simple_surface.t = self.animation_speed * dt.as_secs_f32();
+        let data = create_vertices(self.simple_surface.new());
+        let device = &self.init.device;
+        self.vertex_buffers[0].write(device, queue, cast_slice(&data.0));
+        self.index_buffers[0].write(device, queue, cast_slice(&data.2));
+        self.indices_lens[0] = data.2.len() as u32;
+    }
+
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        let output = self.init.surface.get_current_texture()?;
+        let view = self.init.surface_view(&output.texture);
+
+        let mut encoder =
+            self.init
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Render Encoder"),
+                });
+
+        // surface and ground into the shadow map
+        {
+            let mut shadow_pass = self.shadow_map.begin_pass(&mut encoder);
+            shadow_pass.set_pipeline(&self.pipelines[1]);
+            for k in 0..2 {
+                shadow_pass.set_bind_group(0, &self.shadow_bind_groups[k], &[]);
+                shadow_pass.set_vertex_buffer(0, self.vertex_buffers[k].slice());
+                shadow_pass
+                    .set_index_buffer(self.index_buffers[k].slice(), wgpu::IndexFormat::Uint16);
+                shadow_pass.draw_indexed(0..self.indices_lens[k], 0, 0..1);
+            }
+        }
+
+        {
+            let mut render_pass = self.targets.begin_pass(&mut encoder, &self.init, &view);
+
+            render_pass.set_bind_group(1, &self.uniform_bind_groups[2], &[]);
+            render_pass.set_bind_group(2, &self.shadow_map.bind_group, &[]);
+            // the surface first, so the catcher blends over it
+            for k in 0..2 {
+                let pipeline = if k == 1 && self.shadow_catcher { 2 } else { 0 };
+                render_pass.set_pipeline(&self.pipelines[pipeline]);
+                render_pass.set_bind_group(0, &self.uniform_bind_groups[k], &[]);
+                render_pass.set_vertex_buffer(0, self.vertex_buffers[k].slice());
+                render_pass
+                    .set_index_buffer(self.index_buffers[k].slice(), wgpu::IndexFormat::Uint16);
+                render_pass.draw_indexed(0..self.indices_lens[k], 0, 0..1);
+            }
+        }
+
+        self.fps_counter.log_fps(5);
+        self.init.queue.submit(std::iter::once(encoder.finish()));
+        output.present();
+
+        Ok(())
+    }
+}
+
+// a light gray square facing up, in world coordinates
+fn ground() -> (Vec<Vertex>, Vec<u16>) {
+    let h = GROUND_HALF_SIZE;
+    let vertices = [[-h, -h], [h, -h], [h, h], [-h, h]]
+        .iter()
+        .map(|&[x, z]| Vertex {
+            position: [x, GROUND_HEIGHT, z],
+            normal: [0.0, 1.0, 0.0],
+            color: color::to_linear([0.75, 0.75, 0.72]),
+        })
+        .collect();
+    (vertices, vec![0, 1, 2, 2, 3, 0])
+}
@@ -0,0 +1,34 @@
+#[path = "../common/vertex.rs"]
+mod vertex;
+mod state;
+
+use wgpu_surfaces::app;
+use wgpu_surfaces::cli::SurfaceArgs;
+
+use crate::state::State;
+
+fn main() {
+    let args = SurfaceArgs::default().parse_env("[listen address, e.g. 127.0.0.1:7878]");
+
+    let title = "ch02 telemetry surface";
+
+    if let Err(e) = app::run_app::<State>(&args, title) {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
+}
+
+// a row of synthetic sensor readings at time t: a traveling wave, a peak that sweeps
+// across the sensors and some deterministic jitter
+pub fn sensor_row(samples: u16, t: f32) -> Vec<f32> {
+    (0..samples)
+        .map(|j| {
+            let x = 16.0 * j as f32 / (samples - 1) as f32 - 8.0;
+            let wave = 0.3 * (1.7 * t + 0.6 * x).sin();
+            let center = 6.0 * (0.4 * t).sin();
+            let peak = 0.8 * (-(x - center) * (x - center) / 2.0).exp();
+            let jitter = 0.05 * (12.9898 * x + 78.233 * t).sin().fract();
+            wave + peak + jitter
+        })
+        .collect()
+}
@@ -0,0 +1,368 @@
+use std::sync::Arc;
+use bytemuck::cast_slice;
+use cgmath::{Matrix4, SquareMatrix};
+use winit::{event::ElementState, event::KeyEvent, event::WindowEvent, window::Window};
+
+use wgpu_surfaces::app::{self, Action, InputMap, SurfaceApp};
+use wgpu_surfaces::appearance::DirectionalLight;
+use wgpu_surfaces::cli::SurfaceArgs;
+use wgpu_surfaces::ingest::DataReceiver;
+use wgpu_surfaces::surface_data::{ISurfaceOutput, ITelemetrySurface, ScrollAxis};
+use wgpu_surfaces::wgpu_simplified as ws;
+
+use crate::vertex::{create_vertices, Vertex};
+
+// default key bindings, remapped with --keymap (see app::InputMap)
+const KEYS: &[(Action, &str)] = &[
+    (Action::CyclePlotType, "Space"),
+    (Action::TogglePause, "p"),
+    (Action::IncreaseDataRate, "f"),
+    (Action::DecreaseDataRate, "s"),
+    (Action::ToggleScrollAxis, "a"),
+];
+
+pub struct State {
+    init: ws::InitWgpu,
+    pipelines: app::SurfacePipelines,
+    vertex_buffers: Vec<ws::GrowableBuffer>,
+    index_buffers: Vec<ws::GrowableBuffer>,
+    vert_uniform_buffer: wgpu::Buffer,
+    lighting: app::LightUniforms,
+    targets: app::RenderTargets,
+    camera: ws::OrbitCamera,
+    project_mat: Matrix4<f32>,
+    plot_type: u32,
+
+    telemetry: ITelemetrySurface,
+    output: ISurfaceOutput,
+    vertices: [Vec<Vertex>; 2], // what the vertex buffers hold
+    dirty: [ws::DirtyRanges; 2],
+    receiver: Option<DataReceiver>, // rows from a producer instead of the synthetic sensors
+    rows_per_second: f32,
+    paused: bool,
+    t: f32, // time of the synthetic sensors
+    last_dt: f32,
+    uploaded: u64, // vertex bytes written since the last report
+    last_report: f32,
+    input_map: InputMap,
+    fps_counter: ws::FpsCounter,
+}
+
+impl SurfaceApp for State {
+    async fn new(window: Arc<Window>, args: &SurfaceArgs) -> wgpu_surfaces::Result<Self> {
+        let init = ws::InitWgpu::init_wgpu(window, args.msaa, args.present_mode).await?;
+
+        // Loading Shaders
+        let vs_shader = init
+            .device
+            .create_shader_module(wgpu::include_wgsl!("../01_simple_surface/shader_vert.wgsl"));
+        let fs_shader = init
+            .device
+            .create_shader_module(wgpu::include_wgsl!("../common/directional_frag.wgsl"));
+
+        let camera = ws::OrbitCamera::new((2.5, 2.0, 3.0).into(), (0.0, 0.0, 0.0).into());
+        let project_mat =
+            ws::create_projection_mat(init.config.width as f32 / init.config.height as f32, true);
+
+        // the model matrix scrolls the history, see update
+        let vert_uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Vertex Uniform Buffer"),
+            size: 192,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let identity: [f32; 16] = *Matrix4::<f32>::identity().as_ref();
+        init.queue
+            .write_buffer(&vert_uniform_buffer, 128, cast_slice(&identity));
+
+        // the eye position of the light follows the camera in update
+        let lighting = app::LightUniforms::new(
+            &init,
+            &DirectionalLight::default(),
+            [0.2, 0.8, 0.4, 30.0],
+        );
+
+        let vertex_buffer_layout = [wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3],
+            // pos, norm, col
+        }];
+        let pipelines = app::SurfacePipelines::new(
+            &init,
+            &vs_shader,
+            &fs_shader,
+            &vertex_buffer_layout,
+            ws::create_bind_group(
+                &init.device,
+                vec![wgpu::ShaderStages::VERTEX],
+                &[vert_uniform_buffer.as_entire_binding()],
+            )?,
+            lighting.bind_group(&init.device)?,
+        )?;
+        let targets = app::RenderTargets::new(&init);
+
+        let receiver = args.positional.first().and_then(|addr| match DataReceiver::bind(addr) {
+            Ok(receiver) => {
+                println!("listening for rows on {}", receiver.addr);
+                Some(receiver)
+            }
+            Err(e) => {
+                println!("cannot listen on {}: {}, showing synthetic sensors", addr, e);
+                None
+            }
+        });
+
+        let mut telemetry = ITelemetrySurface::with_size(160, args.resolution.unwrap_or(64));
+        telemetry.grid.colormap_name = args.colormap.clone();
+        telemetry.grid.wireframe_color = args.wireframe_color.clone();
+        if receiver.is_none() {
+            // the synthetic readings stay in this range, so the vertices are only laid
+            // out again once per history
+            telemetry.grid.value_range = Some([-0.5, 1.2]);
+        }
+        let output = telemetry.new();
+        let data = create_vertices(output.clone());
+        let (vertex_buffers, index_buffers) = create_buffers(&init, &data);
+
+        Ok(Self {
+            init,
+            pipelines,
+            vertex_buffers,
+            index_buffers,
+            vert_uniform_buffer,
+            lighting,
+            targets,
+            camera,
+            project_mat,
+            plot_type: 1,
+
+            telemetry,
+            output,
+            vertices: [data.0, data.1],
+            dirty: Default::default(),
+            receiver,
+            rows_per_second: 30.0,
+            paused: false,
+            t: 0.0,
+            last_dt: 0.0,
+            uploaded: 0,
+            last_report: 0.0,
+            input_map: InputMap::with_args(KEYS, args)?,
+            fps_counter: ws::FpsCounter::default(),
+        })
+    }
+
+    fn init(&self) -> &ws::InitWgpu {
+        &self.init
+    }
+
+    fn init_mut(&mut self) -> &mut ws::InitWgpu {
+        &mut self.init
+    }
+
+    fn resized(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.project_mat =
+            ws::create_projection_mat(new_size.width as f32 / new_size.height as f32, true);
+        self.targets.resize(&self.init);
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        // left drag rotates, right drag pans and the wheel zooms
+        if self.camera.input(event) {
+            return true;
+        }
+        match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: key,
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => match self.input_map.action(key) {
+                Some(Action::CyclePlotType) => {
+                    self.plot_type = (self.plot_type + 1) % 3;
+                    true
+                }
+                Some(Action::TogglePause) => {
+                    self.paused = !self.paused;
+                    true
+                }
+                Some(Action::IncreaseDataRate) => {
+                    self.rows_per_second = (self.rows_per_second * 2.0).min(960.0);
+                    println!("{} rows per second", self.rows_per_second);
+                    true
+                }
+                Some(Action::DecreaseDataRate) => {
+                    self.rows_per_second = (self.rows_per_second * 0.5).max(1.0);
+                    println!("{} rows per second", self.rows_per_second);
+                    true
+                }
+                // scrolls along x or z; the indices change, so everything is rewritten
+                Some(Action::ToggleScrollAxis) => {
+                    self.telemetry.scroll_axis = match self.telemetry.scroll_axis {
+                        ScrollAxis::X => ScrollAxis::Z,
+                        ScrollAxis::Z => ScrollAxis::X,
+                    };
+                    self.output = self.telemetry.new();
+                    let data = create_vertices(self.output.clone());
+                    let (device, queue) = (&self.init.device, &self.init.queue);
+                    self.vertex_buffers[0].write(device, queue, cast_slice(&data.0));
+                    self.vertex_buffers[1].write(device, queue, cast_slice(&data.1));
+                    self.index_buffers[0].write(device, queue, cast_slice(&data.2));
+                    self.index_buffers[1].write(device, queue, cast_slice(&data.3));
+                    self.vertices = [data.0, data.1];
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn update(&mut self, dt: std::time::Duration) {
+        let view_project_mat = self.project_mat * self.camera.view_mat();
+        let view_projection_ref: &[f32; 16] = view_project_mat.as_ref();
+        self.init
+            .queue
+            .write_buffer(&self.vert_uniform_buffer, 0, cast_slice(view_projection_ref));
+        self.lighting.set_eye_position(&self.init.queue, self.camera.eye().into());
+
+        let elapsed = dt.as_secs_f32() - self.last_dt;
+        self.last_dt = dt.as_secs_f32();
+        self.push_rows(elapsed);
+        self.upload_rows();
+
+        let model_mat = Matrix4::from_translation(self.telemetry.scroll_offset().into());
+        let model_ref: &[f32; 16] = model_mat.as_ref();
+        self.init
+            .queue
+            .write_buffer(&self.vert_uniform_buffer, 64, cast_slice(model_ref));
+
+        if self.last_dt - self.last_report >= 5.0 {
+            let seconds = self.last_dt - self.last_report;
+            let full = std::mem::size_of_val(&self.vertices[0][..]) * 2;
+            println!(
+                "{} rows, {:.1} KiB/s of vertices uploaded ({} KiB per full upload)",
+                self.telemetry.rows_pushed(),
+                self.uploaded as f32 / 1024.0 / seconds,
+                full / 1024
+            );
+            self.uploaded = 0;
+            self.last_report = self.last_dt;
+        }
+    }
+
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        let output = self.init.surface.get_current_texture()?;
+        let view = self.init.surface_view(&output.texture);
+
+        let mut encoder =
+            self.init
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Render Encoder"),
+                });
+
+        {
+            let mut render_pass = self.targets.begin_pass(&mut encoder, &self.init, &view);
+
+            // the ring is drawn in two parts, around the seam between the newest and
+            // the oldest row
+            let (triangles, lines) = self.telemetry.draw_ranges();
+            // 0: both, 1: shape only, 2: wireframe only
+            let passes: &[usize] = match self.plot_type {
+                1 => &[0],
+                2 => &[1],
+                _ => &[0, 1],
+            };
+            for &k in passes.iter() {
+                self.pipelines.bind(&mut render_pass, k == 1);
+                render_pass.set_vertex_buffer(0, self.vertex_buffers[k].slice());
+                render_pass
+                    .set_index_buffer(self.index_buffers[k].slice(), wgpu::IndexFormat::Uint16);
+                for range in [&triangles, &lines][k].iter() {
+                    render_pass.draw_indexed(range.clone(), 0, 0..1);
+                }
+            }
+        }
+
+        self.fps_counter.log_fps(5);
+        self.init.queue.submit(std::iter::once(encoder.finish()));
+        output.present();
+
+        Ok(())
+    }
+}
+
+impl State {
+    // rows from the producer, or the synthetic sensors at rows_per_second
+    fn push_rows(&mut self, elapsed: f32) {
+        // a frame from the producer replaces the whole history
+        if let Some(receiver) = &self.receiver {
+            receiver.apply(&mut self.telemetry);
+            return;
+        }
+        if self.paused {
+            return;
+        }
+        let rows_before = (self.t * self.rows_per_second) as u64;
+        self.t += elapsed.min(0.25);
+        let rows = (self.t * self.rows_per_second) as u64 - rows_before;
+        for k in 0..rows {
+            let t = (rows_before + k + 1) as f32 / self.rows_per_second;
+            let row = crate::sensor_row(self.telemetry.samples(), t);
+            self.telemetry.push_row(&row);
+        }
+    }
+
+    // writes only the vertices of the new rows to the vertex buffers
+    fn upload_rows(&mut self) {
+        let ranges = self.telemetry.update(&mut self.output);
+        if ranges.is_empty() {
+            return;
+        }
+        let stride = std::mem::size_of::<Vertex>();
+        let out = &self.output;
+        for range in ranges {
+            for k in range.clone() {
+                for (i, colors) in [&out.colors, &out.colors2].into_iter().enumerate() {
+                    self.vertices[i][k] = Vertex {
+                        position: out.positions[k],
+                        normal: out.normals[k],
+                        color: wgpu_surfaces::color::to_linear(colors[k]),
+                    };
+                }
+            }
+            for dirty in self.dirty.iter_mut() {
+                dirty.mark_elements(range.clone(), stride);
+            }
+        }
+        let (device, queue) = (&self.init.device, &self.init.queue);
+        for (i, buffer) in self.vertex_buffers.iter_mut().enumerate() {
+            self.uploaded += self.dirty[i].bytes();
+            buffer.write_dirty(device, queue, cast_slice(&self.vertices[i]), &mut self.dirty[i]);
+        }
+    }
+}
+
+// surface and wireframe vertex and index buffers
+fn create_buffers(
+    init: &ws::InitWgpu,
+    data: &(Vec<Vertex>, Vec<Vertex>, Vec<u16>, Vec<u16>),
+) -> (Vec<ws::GrowableBuffer>, Vec<ws::GrowableBuffer>) {
+    let buffer = |label: &str, usage: wgpu::BufferUsages, contents: &[u8]| {
+        ws::GrowableBuffer::new(&init.device, label, usage, contents)
+    };
+    let vertex_buffers = [&data.0, &data.1]
+        .iter()
+        .map(|vertices| buffer("Vertex Buffer", wgpu::BufferUsages::VERTEX, cast_slice(vertices)))
+        .collect();
+    let index_buffers = [&data.2, &data.3]
+        .iter()
+        .map(|indices| buffer("Index Buffer", wgpu::BufferUsages::INDEX, cast_slice(indices)))
+        .collect();
+    (vertex_buffers, index_buffers)
+}
@@ -28,14 +28,19 @@ fn blinnPhong(N:vec3f, L:vec3f, V:vec3f) -> vec2f{
     return vec2(diffuse, specular);
 }
 
+// @hook fragment_declarations
+
 @fragment
 fn fs_main(in:Input) ->  @location(0) vec4f {
     var N = normalize(in.vNormal.xyz);                  
     let L = normalize(-light.lightDirection.xyz);  
     let V = normalize(light.eyePosition.xyz - in.vPosition.xyz);   
+    // @hook fragment_normal
     
     let bp = blinnPhong(N, L, V);           
     let finalColor = in.vColor * (material.ambient + bp[0]) + light.specularColor * bp[1]; 
 
-    return vec4<f32>(finalColor.rgb, 1.0);
+    var color = vec4<f32>(finalColor.rgb, 1.0);
+    // @hook fragment
+    return color;
 }
\ No newline at end of file
@@ -0,0 +1,56 @@
+struct LightUniforms {
+    lightDirection : vec4f,
+    eyePosition : vec4f,  
+    specularColor : vec4f,
+}
+@group(1) @binding(0) var<uniform> light : LightUniforms;
+
+// parameters of the procedural matcap sphere, in place of the Blinn-Phong ones of
+// directional_frag.wgsl
+struct MaterialUniforms {
+    ambient: f32,
+    highlight: f32,
+    rim: f32,
+    sharpness: f32,
+}
+@group(1) @binding(1) var<uniform> material : MaterialUniforms;
+
+struct Input {
+    @location(0) vPosition:vec4f, 
+    @location(1) vNormal:vec4f, 
+    @location(2) vColor: vec4f,
+}
+
+// key light of the matcap sphere, up and to the left of the viewer
+const KEY = vec3f(-0.4, 0.5, 0.77);
+
+// @hook fragment_declarations
+
+// The shading depends only on the normal as seen from the eye, like a lit sphere
+// photographed once: it ignores the light direction and always looks the same
+// from every side.
+@fragment
+fn fs_main(in:Input) ->  @location(0) vec4f {
+    var N = normalize(in.vNormal.xyz);                  
+    let L = normalize(-light.lightDirection.xyz);  
+    let V = normalize(light.eyePosition.xyz - in.vPosition.xyz);   
+    if dot(N, V) < 0.0 {
+        N = -N;
+    }
+    // @hook fragment_normal
+
+    // view space normal from a basis around the view direction
+    let worldUp = select(vec3f(0.0, 1.0, 0.0), vec3f(0.0, 0.0, 1.0), abs(V.y) > 0.999);
+    let right = normalize(cross(worldUp, V));
+    let up = cross(V, right);
+    let n = vec3f(dot(N, right), dot(N, up), dot(N, V));
+
+    let key = max(dot(n, normalize(KEY)), 0.0);
+    let shade = material.ambient + (1.0 - material.ambient) * key;
+    let highlight = material.highlight * pow(key, material.sharpness);
+    let rim = material.rim * pow(1.0 - n.z, 3.0);
+
+    var color = vec4<f32>(in.vColor.rgb * shade + vec3f(highlight + rim), 1.0);
+    // @hook fragment
+    return color;
+}
@@ -0,0 +1,53 @@
+// Blinn-Phong lit by every light of lighting::LightSet; prepend lighting::LIGHTS_WGSL
+struct LightUniforms {
+    lightDirection : vec4f,
+    eyePosition : vec4f,
+    specularColor : vec4f,
+}
+@group(1) @binding(0) var<uniform> light : LightUniforms;
+
+struct MaterialUniforms {
+    ambient: f32,
+    diffuse: f32,
+    specular: f32,
+    shininess: f32,
+}
+@group(1) @binding(1) var<uniform> material : MaterialUniforms;
+
+struct Input {
+    @location(0) vPosition:vec4f,
+    @location(1) vNormal:vec4f,
+    @location(2) vColor: vec4f,
+}
+
+fn blinnPhong(N:vec3f, L:vec3f, V:vec3f) -> vec2f{
+    let H = normalize(L + V);
+    var diffuse = material.diffuse * max(dot(N, L), 0.0);
+    diffuse += material.diffuse * max(dot(-N, L), 0.0);
+    var specular = material.specular * pow(max(dot(N, H), 0.0), material.shininess);
+    specular += material.specular * pow(max(dot(-N, H),0.0), material.shininess);
+    return vec2(diffuse, specular);
+}
+
+// @hook fragment_declarations
+
+@fragment
+fn fs_main(in:Input) ->  @location(0) vec4f {
+    var N = normalize(in.vNormal.xyz);
+    let V = normalize(light.eyePosition.xyz - in.vPosition.xyz);
+    // @hook fragment_normal
+
+    var diffuse = vec3f(0.0);
+    var specular = vec3f(0.0);
+    for (var i = 0u; i < lightSet.count; i++) {
+        let s = light_sample(lightSet.lights[i], in.vPosition.xyz);
+        let bp = blinnPhong(N, s.toLight, V);
+        diffuse += s.radiance * bp[0];
+        specular += s.radiance * bp[1];
+    }
+    let finalColor = in.vColor.rgb * (material.ambient + diffuse) + light.specularColor.rgb * specular;
+
+    var color = vec4<f32>(finalColor, 1.0);
+    // @hook fragment
+    return color;
+}
@@ -0,0 +1,76 @@
+struct LightUniforms {
+    lightDirection : vec4f,
+    eyePosition : vec4f,  
+    specularColor : vec4f,
+}
+@group(1) @binding(0) var<uniform> light : LightUniforms;
+
+// metal-rough parameters, in place of the Blinn-Phong ones of directional_frag.wgsl
+struct MaterialUniforms {
+    metallic: f32,
+    roughness: f32,
+    ambient: f32,
+    reflectance: f32, // specular reflectance of dielectrics, 0.5 gives F0 = 0.04
+}
+@group(1) @binding(1) var<uniform> material : MaterialUniforms;
+
+struct Input {
+    @location(0) vPosition:vec4f, 
+    @location(1) vNormal:vec4f, 
+    @location(2) vColor: vec4f,
+}
+
+const PI: f32 = 3.14159265;
+
+// GGX normal distribution, alpha = roughness^2
+fn distributionGGX(NdotH: f32, alpha: f32) -> f32 {
+    let a2 = alpha * alpha;
+    let d = NdotH * NdotH * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+// Smith shadowing-masking with the Schlick-GGX approximation for direct light
+fn geometrySmith(NdotV: f32, NdotL: f32, roughness: f32) -> f32 {
+    let k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    return NdotV / (NdotV * (1.0 - k) + k) * NdotL / (NdotL * (1.0 - k) + k);
+}
+
+fn fresnelSchlick(cosTheta: f32, F0: vec3f) -> vec3f {
+    return F0 + (vec3f(1.0) - F0) * pow(1.0 - cosTheta, 5.0);
+}
+
+// @hook fragment_declarations
+
+@fragment
+fn fs_main(in:Input) ->  @location(0) vec4f {
+    var N = normalize(in.vNormal.xyz);                  
+    let L = normalize(-light.lightDirection.xyz);  
+    let V = normalize(light.eyePosition.xyz - in.vPosition.xyz);   
+    // two-sided: the side facing the eye is shaded
+    if dot(N, V) < 0.0 {
+        N = -N;
+    }
+    // @hook fragment_normal
+
+    let H = normalize(L + V);
+    let NdotL = max(dot(N, L), 0.0);
+    let NdotV = max(dot(N, V), 1e-4);
+    let roughness = clamp(material.roughness, 0.04, 1.0);
+    let baseColor = in.vColor.rgb;
+    let dielectric = vec3f(0.16 * material.reflectance * material.reflectance);
+    let F0 = mix(dielectric, baseColor, material.metallic);
+
+    let F = fresnelSchlick(max(dot(H, V), 0.0), F0);
+    let D = distributionGGX(max(dot(N, H), 0.0), roughness * roughness);
+    let G = geometrySmith(NdotV, NdotL, roughness);
+    let specular = D * G * F / (4.0 * NdotV * max(NdotL, 1e-4));
+    let kd = (vec3f(1.0) - F) * (1.0 - material.metallic);
+    // the light color scaled so a white diffuse surface facing the light is white
+    let radiance = light.specularColor.rgb * PI;
+    let direct = (kd * baseColor / PI + specular) * radiance * NdotL;
+    let ambient = material.ambient * (kd * baseColor + F0);
+
+    var color = vec4<f32>(direct + ambient, 1.0);
+    // @hook fragment
+    return color;
+}
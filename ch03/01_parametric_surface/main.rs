@@ -1,45 +1,45 @@
-#[path = "../common/app.rs"]
-mod app;
 #[path = "../common/vertex.rs"]
 mod vertex;
 mod state;
 
-use winit::event_loop::EventLoop;
+use wgpu_surfaces::app;
+use wgpu_surfaces::cli::SurfaceArgs;
+use wgpu_surfaces::gallery;
+use wgpu_surfaces::playlist::IPlaylist;
+use wgpu_surfaces::surface_data as sd;
 
-use crate::app::Application;
+use crate::state::State;
 
 fn main() {
-    let mut sample_count = 1 as u32;
-    let mut colormap_name = "jet";
-    let mut wireframe_color = "white";
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() > 1 {
-        sample_count = args[1].parse::<u32>().unwrap();
-    }
-    if args.len() > 2 {
-        colormap_name = &args[2];
-    }
-    if args.len() > 3 {
-        wireframe_color = &args[3];
-    }
+    let args = SurfaceArgs::default()
+        .surface_types(sd::PARAMETRIC_SURFACE_COUNT)
+        .parse_env("[playlist.json | gallery preset]");
 
     let title = "ch03 parametric surface";
 
-    let _ = run(sample_count, colormap_name, wireframe_color, title);
-
-    pub fn run(
-        sample_count: u32,
-        colormap_name: &str,
-        wireframe_color: &str,
-        title: &str,
-    ) -> anyhow::Result<()> {
-        env_logger::init();
-
-        let event_loop = EventLoop::builder().build()?;
-        let mut app = Application::new(sample_count, colormap_name, wireframe_color, title, None);
-
-        event_loop.run_app(&mut app)?;
+    if let Err(e) = run(&args, title) {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
 
-        Ok(())
+    pub fn run(args: &SurfaceArgs, title: &str) -> anyhow::Result<()> {
+        // a gallery preset name, else the path of a playlist file
+        let playlist = match args.positional.first() {
+            Some(arg) => Some(match gallery::preset(arg) {
+                Some(playlist) => playlist,
+                None => IPlaylist::load(arg).map_err(|e| {
+                    let presets = gallery::PRESET_NAMES.join(", ");
+                    anyhow::anyhow!("{arg}: {e} (gallery presets: {presets})")
+                })?,
+            }),
+            None => None,
+        };
+
+        match playlist {
+            Some(playlist) => app::run_app_with(args, title, |state: &mut State| {
+                state.set_playlist(playlist)
+            }),
+            None => app::run_app::<State>(args, title),
+        }
     }
 }
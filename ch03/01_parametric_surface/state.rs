@@ -1,65 +1,152 @@
 use std::sync::Arc;
 use bytemuck::cast_slice;
-use cgmath::{Matrix, Matrix4, SquareMatrix};
-use wgpu::util::DeviceExt;
+use cgmath::{Matrix, Matrix4, Point3, SquareMatrix};
 use winit::{
-    event::ElementState, event::KeyEvent, event::WindowEvent, keyboard::Key, keyboard::NamedKey,
-    window::Window,
+    event::ElementState, event::KeyEvent, event::MouseButton, event::WindowEvent, window::Window,
 };
-use rand::Rng;
-use rand::rngs::ThreadRng;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
 
+use wgpu_surfaces::app::{self, Action, InputMap, SurfaceApp};
+use wgpu_surfaces::animation::{AnimationCurve, Easing, SurfaceField, Timeline};
+use wgpu_surfaces::appearance::DirectionalLight;
+use wgpu_surfaces::capture::{self, FrameCapture};
+use wgpu_surfaces::export::{self, ExportConvention};
+use wgpu_surfaces::gizmo::{self, Guides, LineRenderer, NormalLines};
+use wgpu_surfaces::params::{self, ParamSet};
+use wgpu_surfaces::picking::{self, Crosshair, ParamInset, PickHit, Ray};
+use wgpu_surfaces::playlist::{IPlaylist, PlaylistRunner};
+use wgpu_surfaces::report::{self, ICameraInfo};
 use wgpu_surfaces::surface_data as sd;
+use wgpu_surfaces::cli::SurfaceArgs;
+use wgpu_surfaces::transition::CrossFade;
+use wgpu_surfaces::unfold::{self, Unfold, UnfoldVertex};
 use wgpu_surfaces::wgpu_simplified as ws;
 
 use crate::vertex::{create_vertices, Vertex};
 
+// default key bindings, remapped with --keymap (see app::InputMap)
+const KEYS: &[(Action, &str)] = &[
+    (Action::CyclePlotType, "Space"),
+    (Action::NextSurfaceType, "Control"),
+    (Action::CycleColormapDirection, "Shift"),
+    (Action::ToggleRandomShape, "Alt"),
+    (Action::IncreaseUResolution, "q"),
+    (Action::DecreaseUResolution, "a"),
+    (Action::IncreaseVResolution, "w"),
+    (Action::DecreaseVResolution, "s"),
+    (Action::ExportReport, "h"),
+    (Action::ToggleOpacity, "x"),
+    (Action::NextKnotP, "p"),
+    (Action::NextKnotQ, "k"),
+    (Action::IncreaseTubeRadius, "t"),
+    (Action::DecreaseTubeRadius, "g"),
+    (Action::SelectPreviousParam, "ArrowUp"),
+    (Action::SelectNextParam, "ArrowDown"),
+    (Action::DecreaseParam, "ArrowLeft"),
+    (Action::IncreaseParam, "ArrowRight"),
+    (Action::ToggleMorph, "c"),
+    (Action::CycleImplicitSurface, "i"),
+    (Action::CycleNormalOrientation, "n"),
+    (Action::CycleNormalMode, "m"),
+    (Action::CycleShading, "f"),
+    (Action::ToggleInset, picking::INSET_KEY),
+    (Action::ToggleCloseSeams, "l"),
+    (Action::ToggleUnfold, "z"),
+    (Action::ToggleCrossFade, "r"),
+    (Action::ExportMesh, "o"),
+    (Action::PrintMetrics, "b"),
+    (Action::IncreaseRotationSpeed, "e"),
+    (Action::DecreaseRotationSpeed, "d"),
+];
+
 pub struct State {
     init: ws::InitWgpu,
-    pipelines: Vec<wgpu::RenderPipeline>,
-    vertex_buffers: Vec<wgpu::Buffer>,
-    index_buffers: Vec<wgpu::Buffer>,
-    uniform_bind_groups: Vec<wgpu::BindGroup>,
-    uniform_buffers: Vec<wgpu::Buffer>,
+    pipelines: app::SurfacePipelines,
+    transparent_pipeline: wgpu::RenderPipeline,
+    vertex_buffers: Vec<ws::GrowableBuffer>,
+    index_buffers: Vec<ws::GrowableBuffer>,
+    vert_uniform_buffer: wgpu::Buffer,
     view_mat: Matrix4<f32>,
+    camera_position: Point3<f32>,
+    look_direction: Point3<f32>,
     project_mat: Matrix4<f32>,
-    msaa_texture_view: wgpu::TextureView,
-    depth_texture_view: wgpu::TextureView,
+    targets: app::RenderTargets,
     indices_lens: Vec<u32>,
     plot_type: u32,
     update_buffers: bool,
     recreate_buffers: bool,
     rotation_speed: f32,
-    rng: ThreadRng,
+    rng: StdRng, // seeded by --seed
     t0: std::time::Instant,
     random_shape_change: u32,
 
     parametric_surface: sd::IParametricSurface,
+    input_map: InputMap,
     fps_counter: ws::FpsCounter,
+    render_hooks: ws::RenderHooks,
+    playlist: Option<PlaylistRunner>,
+    frame_capture: Option<FrameCapture>,
+    export_convention: ExportConvention,
+    export_report: bool,
+    opacity: f32, // below 1 the surface is drawn transparent
+    implicit_surface: sd::IImplicitSurface,
+    show_implicit: bool,
+    morph: Option<(Timeline<sd::IParametricSurface>, std::time::Instant)>,
+    params: ParamSet<sd::IParametricSurface>,
+    guides: Guides, // axes, ground grid and bounding box, key v
+    normal_lines: NormalLines, // vertex normals, key u
+    surface_bounds: Option<[[f32; 3]; 2]>,
+    model_mat: Matrix4<f32>,
+    cursor: [f32; 2],
+
+    // parameter domain inset, key y: a click in it marks (u, v) on the surface and a
+    // click on the surface marks its (u, v) in the inset
+    inset: ParamInset,
+    inset_lines: LineRenderer,
+    show_inset: bool,
+    inset_uv: Option<[f32; 2]>,
+    inset_hit: Option<PickHit>,
+    crosshair: Crosshair,
+
+    // unfolding onto the parameter rectangle, key z: drawn from vertex buffers 2 and 3
+    unfold: Unfold,
+    unfold_pipelines: [wgpu::RenderPipeline; 2], // shape, wireframe
+    unfold_bind_group: wgpu::BindGroup,
+    unfold_uniform_buffer: wgpu::Buffer,
+    frame_time: f32, // of the last update, for the frame time of unfolding and fading
+
+    // cross-fade between surface types, key r
+    fade: CrossFade,
+    cross_fade: bool,
+    fade_next: bool, // the next buffer update shows another surface type
 }
 
-impl State {
-    pub async fn new(
-        window: Arc<Window>,
-        sample_count: u32,
-        colormap_name: &str,
-        wireframe_color: &str,
-    ) -> Self {
-        let init = ws::InitWgpu::init_wgpu(window, sample_count).await;
+impl SurfaceApp for State {
+    async fn new(window: Arc<Window>, args: &SurfaceArgs) -> wgpu_surfaces::Result<Self> {
+        let init = ws::InitWgpu::init_wgpu(window, args.msaa, args.present_mode).await?;
 
         // Loading Shaders
         let vs_shader = init
             .device
             .create_shader_module(wgpu::include_wgsl!("../../ch02/01_simple_surface/shader_vert.wgsl"));
+        let unfold_vs_shader = init
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Unfold Vertex Shader"),
+                source: wgpu::ShaderSource::Wgsl(unfold::UNFOLD_VERT_WGSL.into()),
+            });
         let fs_shader = init
             .device
-            .create_shader_module(wgpu::include_wgsl!("../../ch02/common/directional_frag.wgsl"));
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some(args.material.fragment_label()),
+                source: wgpu::ShaderSource::Wgsl(args.material.fragment_source().into()),
+            });
 
         // uniform data
-        let camera_position = (2.0, 2.0, 4.0).into();
-        let look_direction = (0.0, 0.0, 0.0).into();
+        let camera_position: Point3<f32> = (2.0, 2.0, 4.0).into();
+        let look_direction: Point3<f32> = (0.0, 0.0, 0.0).into();
         let up_direction = cgmath::Vector3::unit_y();
-        let light_direction = [-0.5f32, -0.5, -0.5];
 
         let (view_mat, project_mat, _) = ws::create_vp_mat(
             camera_position,
@@ -78,229 +165,237 @@ impl State {
             mapped_at_creation: false,
         });
 
-        // create light uniform buffer. here we set eye_position = camera_position
-        let light_uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Light Uniform Buffer"),
-            size: 48,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        let eye_position: &[f32; 3] = camera_position.as_ref();
-        init.queue.write_buffer(
-            &light_uniform_buffer,
-            0,
-            cast_slice(light_direction.as_ref()),
-        );
-        init.queue
-            .write_buffer(&light_uniform_buffer, 16, cast_slice(eye_position));
-
-        // set specular light color to white
-        let specular_color: [f32; 3] = [1.0, 1.0, 1.0];
-        init.queue.write_buffer(
-            &light_uniform_buffer,
-            32,
-            cast_slice(specular_color.as_ref()),
-        );
-
-        // material uniform buffer
-        let material_uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Material Uniform Buffer"),
-            size: 16,
+        // blend between the surface and its parameter rectangle
+        let unfold_uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Unfold Uniform Buffer"),
+            size: unfold::UNFOLD_UNIFORM_SIZE,
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
-        // set default material parameters
-        let material = [0.1f32, 0.7, 0.4, 30.0];
-        init.queue
-            .write_buffer(&material_uniform_buffer, 0, cast_slice(material.as_ref()));
+        // light and material uniforms, with the default material of the --material kind;
+        // here we set eye_position = camera_position
+        let light = DirectionalLight {
+            eye_position: camera_position.into(),
+            ..Default::default()
+        };
+        let material = args.material.default_material().uniform_data();
+        let lighting = app::LightUniforms::new(&init, &light, material);
 
         // uniform bind group for vertex shader
-        let (vert_bind_group_layout, vert_bind_group) = ws::create_bind_group(
+        let vert_bind_group = ws::create_bind_group(
             &init.device,
             vec![wgpu::ShaderStages::VERTEX],
             &[vert_uniform_buffer.as_entire_binding()],
-        );
-        let (vert_bind_group_layout2, vert_bind_group2) = ws::create_bind_group(
-            &init.device,
-            vec![wgpu::ShaderStages::VERTEX],
-            &[vert_uniform_buffer.as_entire_binding()],
-        );
-
-        // uniform bind group for fragment shader
-        let (frag_bind_group_layout, frag_bind_group) = ws::create_bind_group(
-            &init.device,
-            vec![wgpu::ShaderStages::FRAGMENT, wgpu::ShaderStages::FRAGMENT],
-            &[
-                light_uniform_buffer.as_entire_binding(),
-                material_uniform_buffer.as_entire_binding(),
-            ],
-        );
-        let (frag_bind_group_layout2, frag_bind_group2) = ws::create_bind_group(
+        )?;
+        let (unfold_bind_group_layout, unfold_bind_group) = ws::create_bind_group(
             &init.device,
-            vec![wgpu::ShaderStages::FRAGMENT, wgpu::ShaderStages::FRAGMENT],
+            vec![wgpu::ShaderStages::VERTEX, wgpu::ShaderStages::VERTEX],
             &[
-                light_uniform_buffer.as_entire_binding(),
-                material_uniform_buffer.as_entire_binding(),
+                vert_uniform_buffer.as_entire_binding(),
+                unfold_uniform_buffer.as_entire_binding(),
             ],
-        );
+        )?;
 
-        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+        let vertex_buffer_layout = [wgpu::VertexBufferLayout {
             array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
             step_mode: wgpu::VertexStepMode::Vertex,
             attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3],
             // pos, norm, col
-        };
-
-        let pipeline_layout = init
-            .device
-            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&vert_bind_group_layout, &frag_bind_group_layout],
-                push_constant_ranges: &[],
-            });
+        }];
+        let pipelines = app::SurfacePipelines::new(
+            &init,
+            &vs_shader,
+            &fs_shader,
+            &vertex_buffer_layout,
+            vert_bind_group,
+            lighting.bind_group(&init.device)?,
+        )?;
 
-        let mut ppl = ws::IRenderPipeline {
+        // see-through variant of the surface pipeline, so the self-intersections of
+        // non-orientable immersions stay visible
+        let transparent_pipeline = ws::IRenderPipeline {
             vs_shader: Some(&vs_shader),
             fs_shader: Some(&fs_shader),
-            pipeline_layout: Some(&pipeline_layout),
-            vertex_buffer_layout: &[vertex_buffer_layout],
+            pipeline_layout: Some(&pipelines.layout),
+            vertex_buffer_layout: &vertex_buffer_layout,
+            depth_write_enabled: false,
+            blend: Some(ws::CONSTANT_ALPHA_BLENDING),
             ..Default::default()
-        };
-        let pipeline = ppl.new(&init);
-
-        let vertex_buffer_layout2 = wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3],
-            // pos, norm, col
-        };
+        }
+        .new(&init)?;
 
-        let pipeline_layout2 =
+        // surface and wireframe blended with their parameter rectangle
+        let unfold_pipeline_layout =
             init.device
                 .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: Some("Render Pipeline Layout 2"),
-                    bind_group_layouts: &[&vert_bind_group_layout2, &frag_bind_group_layout2],
+                    label: Some("Unfold Pipeline Layout"),
+                    bind_group_layouts: &[
+                        &unfold_bind_group_layout,
+                        &pipelines.bind_group_layouts[1],
+                    ],
                     push_constant_ranges: &[],
                 });
+        let unfold_pipelines = [
+            wgpu::PrimitiveTopology::TriangleList,
+            wgpu::PrimitiveTopology::LineList,
+        ]
+        .map(|topology| {
+            ws::IRenderPipeline {
+                topology,
+                vs_shader: Some(&unfold_vs_shader),
+                fs_shader: Some(&fs_shader),
+                pipeline_layout: Some(&unfold_pipeline_layout),
+                vertex_buffer_layout: &[UnfoldVertex::layout()],
+                ..Default::default()
+            }
+            .new(&init)
+        });
+        let [unfold_pipeline, unfold_pipeline2] = unfold_pipelines;
 
-        let mut ppl2 = ws::IRenderPipeline {
-            topology: wgpu::PrimitiveTopology::LineList,
-            vs_shader: Some(&vs_shader),
-            fs_shader: Some(&fs_shader),
-            pipeline_layout: Some(&pipeline_layout2),
-            vertex_buffer_layout: &[vertex_buffer_layout2],
-            ..Default::default()
-        };
-        let pipeline2 = ppl2.new(&init);
-
-        let msaa_texture_view = ws::create_msaa_texture_view(&init);
-        let depth_texture_view = ws::create_depth_view(&init);
+        let targets = app::RenderTargets::new(&init);
 
         let mut ps = sd::IParametricSurface {
             scale: 4.5,
             surface_type: 0,
-            colormap_name: colormap_name.to_string(),
-            wireframe_color: wireframe_color.to_string(),
+            colormap_name: args.colormap.clone(),
+            wireframe_color: args.wireframe_color.clone(),
             ..Default::default()
         };
-        let data = create_vertices(ps.new());
+        if let Some(resolution) = args.resolution {
+            (ps.u_resolution, ps.v_resolution) = (resolution, resolution);
+        }
+        if let Some(surface_type) = args.surface_type {
+            ps.surface_type = surface_type;
+        }
+        let output = ps.new();
+        let mut normal_lines = NormalLines::new(&init)?;
+        normal_lines.set_mesh(&output);
+        let data = create_vertices(output);
 
-        let vertex_buffer = init
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Vertex Buffer"),
-                contents: cast_slice(&data.0),
-                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            });
+        // the shape buffers are copied when the surface type changes with a cross-fade
+        let vertex_buffer = ws::GrowableBuffer::new(
+            &init.device,
+            "Vertex Buffer",
+            wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_SRC,
+            cast_slice(&data.0),
+        );
 
-        let vertex_buffer2 = init
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Vertex Buffer 2"),
-                contents: cast_slice(&data.1),
-                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            });
+        let vertex_buffer2 = ws::GrowableBuffer::new(
+            &init.device,
+            "Vertex Buffer 2",
+            wgpu::BufferUsages::VERTEX,
+            cast_slice(&data.1),
+        );
 
-        let index_buffer = init
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Index Buffer"),
-                contents: bytemuck::cast_slice(&data.2),
-                usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
-            });
+        let index_buffer = ws::GrowableBuffer::new(
+            &init.device,
+            "Index Buffer",
+            wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_SRC,
+            cast_slice(&data.2),
+        );
 
-        let index_buffer2 = init
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Index Buffer 2"),
-                contents: bytemuck::cast_slice(&data.3),
-                usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+        let index_buffer2 = ws::GrowableBuffer::new(
+            &init.device,
+            "Index Buffer 2",
+            wgpu::BufferUsages::INDEX,
+            cast_slice(&data.3),
+        );
+
+        // written once the surface starts to unfold
+        let [vertex_buffer3, vertex_buffer4] =
+            ["Unfold Vertex Buffer", "Unfold Vertex Buffer 2"].map(|label| {
+                ws::GrowableBuffer::new(&init.device, label, wgpu::BufferUsages::VERTEX, &[])
             });
 
-        Self {
+        let fade = CrossFade::new(&init.device);
+        let guides = Guides::new(&init)?;
+        let inset_lines = LineRenderer::new(&init)?;
+        let inset = ParamInset::new(&ps);
+        let surface_bounds = gizmo::bounds(data.0.iter().map(|v| v.position));
+
+        Ok(Self {
             init,
-            pipelines: vec![pipeline, pipeline2],
-            vertex_buffers: vec![vertex_buffer, vertex_buffer2],
+            pipelines,
+            transparent_pipeline,
+            vertex_buffers: vec![vertex_buffer, vertex_buffer2, vertex_buffer3, vertex_buffer4],
             index_buffers: vec![index_buffer, index_buffer2],
-            uniform_bind_groups: vec![
-                vert_bind_group,
-                frag_bind_group,
-                vert_bind_group2,
-                frag_bind_group2,
-            ],
-            uniform_buffers: vec![
-                vert_uniform_buffer,
-                light_uniform_buffer,
-                material_uniform_buffer,
-            ],
+            vert_uniform_buffer,
             view_mat,
+            camera_position,
+            look_direction,
             project_mat,
-            msaa_texture_view,
-            depth_texture_view,
+            targets,
             indices_lens: vec![data.2.len() as u32, data.3.len() as u32],
             plot_type: 1,
             update_buffers: false,
             recreate_buffers: false,
             rotation_speed: 1.0,
-            rng: rand::rng(),
+            rng: StdRng::seed_from_u64(args.seed.unwrap_or_else(rand::random)),
             t0: std::time::Instant::now(),
             random_shape_change: 1,
 
             parametric_surface: ps,
+            input_map: InputMap::with_args(KEYS, args)?,
             fps_counter: ws::FpsCounter::default(),
-        }
+            render_hooks: ws::RenderHooks::default(),
+            playlist: None,
+            frame_capture: None,
+            export_convention: ExportConvention::default(),
+            export_report: false,
+            opacity: 1.0,
+            implicit_surface: sd::IImplicitSurface {
+                scale: 4.5,
+                colormap_name: args.colormap.clone(),
+                wireframe_color: args.wireframe_color.clone(),
+                ..Default::default()
+            },
+            show_implicit: false,
+            morph: None,
+            params: params::parametric_params(),
+            guides,
+            normal_lines,
+            surface_bounds,
+            model_mat: Matrix4::identity(),
+            cursor: [0.0, 0.0],
+
+            inset,
+            inset_lines,
+            show_inset: false,
+            inset_uv: None,
+            inset_hit: None,
+            crosshair: Crosshair::default(),
+
+            unfold: Unfold::default(),
+            unfold_pipelines: [unfold_pipeline?, unfold_pipeline2?],
+            unfold_bind_group,
+            unfold_uniform_buffer,
+            frame_time: 0.0,
+
+            fade,
+            cross_fade: false,
+            fade_next: false,
+        })
     }
 
-    pub fn window(&self) -> &Window {
-        &self.init.window
+    fn init(&self) -> &ws::InitWgpu {
+        &self.init
     }
 
-    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
-        self.init.size
+    fn init_mut(&mut self) -> &mut ws::InitWgpu {
+        &mut self.init
     }
 
-    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
-        if new_size.width > 0 && new_size.height > 0 {
-            self.init.size = new_size;
-            // The surface needs to be reconfigured every time the window is resized.
-            self.init.config.width = new_size.width;
-            self.init.config.height = new_size.height;
-            self.init
-                .surface
-                .configure(&self.init.device, &self.init.config);
-
-            self.project_mat =
-                ws::create_projection_mat(new_size.width as f32 / new_size.height as f32, true);
-            self.depth_texture_view = ws::create_depth_view(&self.init);
-            if self.init.sample_count > 1 {
-                self.msaa_texture_view = ws::create_msaa_texture_view(&self.init);
-            }
-        }
+    fn resized(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.project_mat =
+            ws::create_projection_mat(new_size.width as f32 / new_size.height as f32, true);
+        self.targets.resize(&self.init);
     }
 
-    pub fn input(&mut self, event: &WindowEvent) -> bool {
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        if self.guides.input(event) || self.normal_lines.input(event) {
+            return true;
+        }
         match event {
             WindowEvent::KeyboardInput {
                 event:
@@ -310,71 +405,286 @@ impl State {
                         ..
                     },
                 ..
-            } => match key.as_ref() {
-                Key::Named(NamedKey::Space) => {
+            } => match self.input_map.action(key) {
+                Some(Action::CyclePlotType) => {
                     self.plot_type = (self.plot_type + 1) % 3;
-                    return true;
+                    true
                 }
-                Key::Named(NamedKey::Control) => {
+                Some(Action::NextSurfaceType) => {
                     self.parametric_surface.surface_type =
-                        (self.parametric_surface.surface_type + 1) % 23;
-                    self.update_buffers = true;
-                    return true;
+                        (self.parametric_surface.surface_type + 1) % sd::PARAMETRIC_SURFACE_COUNT;
+                    self.fade_next = self.cross_fade;
+                    self.show_parametric();
+                    true
                 }
-                Key::Named(NamedKey::Shift) => {
+                Some(Action::CycleColormapDirection) => {
                     self.parametric_surface.colormap_direction =
                         (self.parametric_surface.colormap_direction + 1) % 3;
                     self.update_buffers = true;
-                    return true;
+                    true
                 }
-                Key::Named(NamedKey::Alt) => {
+                Some(Action::ToggleRandomShape) => {
                     self.random_shape_change = (self.random_shape_change + 1) % 2;
-                    return true;
+                    true
                 }
-                Key::Character("q") => {
+                Some(Action::IncreaseUResolution) => {
                     self.parametric_surface.u_resolution += 1;
                     self.recreate_buffers = true;
-                    return true;
+                    true
                 }
-                Key::Character("a") => {
+                Some(Action::DecreaseUResolution) => {
                     self.parametric_surface.u_resolution -= 1;
                     if self.parametric_surface.u_resolution < 8 {
                         self.parametric_surface.u_resolution = 8;
                     }
                     self.recreate_buffers = true;
-                    return true;
+                    true
                 }
-                Key::Character("w") => {
+                Some(Action::IncreaseVResolution) => {
                     self.parametric_surface.v_resolution += 1;
                     self.recreate_buffers = true;
-                    return true;
+                    true
                 }
-                Key::Character("s") => {
+                Some(Action::DecreaseVResolution) => {
                     self.parametric_surface.v_resolution -= 1;
                     if self.parametric_surface.v_resolution < 8 {
                         self.parametric_surface.v_resolution = 8;
                     }
                     self.recreate_buffers = true;
-                    return true;
+                    true
+                }
+                Some(Action::ExportReport) => {
+                    self.export_report = true;
+                    true
+                }
+                Some(Action::ToggleOpacity) => {
+                    self.opacity = if self.opacity < 1.0 { 1.0 } else { 0.5 };
+                    true
+                }
+                // shape parameters of the torus knot and klein bagel
+                Some(Action::NextKnotP) => {
+                    self.parametric_surface.knot_p = self.parametric_surface.knot_p % 9 + 1;
+                    self.print_shape_params();
+                    self.update_buffers = true;
+                    true
+                }
+                Some(Action::NextKnotQ) => {
+                    self.parametric_surface.knot_q = self.parametric_surface.knot_q % 9 + 1;
+                    self.print_shape_params();
+                    self.update_buffers = true;
+                    true
+                }
+                Some(Action::IncreaseTubeRadius) => {
+                    self.parametric_surface.tube_radius =
+                        (self.parametric_surface.tube_radius + 0.05).min(0.8);
+                    self.print_shape_params();
+                    self.update_buffers = true;
+                    true
+                }
+                Some(Action::DecreaseTubeRadius) => {
+                    self.parametric_surface.tube_radius =
+                        (self.parametric_surface.tube_radius - 0.05).max(0.05);
+                    self.print_shape_params();
+                    self.update_buffers = true;
+                    true
+                }
+                // parameter sliders: up/down selects a field, left/right changes it
+                Some(Action::SelectPreviousParam) => {
+                    self.params.select_previous();
+                    println!("{}", self.params.describe(&self.parametric_surface, 20));
+                    true
+                }
+                Some(Action::SelectNextParam) => {
+                    self.params.select_next();
+                    println!("{}", self.params.describe(&self.parametric_surface, 20));
+                    true
+                }
+                Some(action @ (Action::DecreaseParam | Action::IncreaseParam)) => {
+                    let steps = if action == Action::DecreaseParam { -1.0 } else { 1.0 };
+                    if self.params.nudge_selected(&mut self.parametric_surface, steps) {
+                        self.recreate_buffers = true;
+                    } else {
+                        self.update_buffers = true;
+                    }
+                    println!("{}", self.params.describe(&self.parametric_surface, 20));
+                    true
+                }
+                // catenoid-helicoid morph
+                Some(Action::ToggleMorph) => {
+                    if self.morph.take().is_none() {
+                        let mut timeline = Timeline::new();
+                        timeline.looping = true;
+                        timeline.add_field(
+                            SurfaceField::Deformation,
+                            AnimationCurve::new()
+                                .key(0.0, 0.0, Easing::Linear)
+                                .key(3.0, 0.5 * std::f32::consts::PI, Easing::EaseInOut)
+                                .key(6.0, 0.0, Easing::EaseInOut),
+                        );
+                        self.morph = Some((timeline, std::time::Instant::now()));
+                        self.parametric_surface.surface_type = 28;
+                        self.show_parametric();
+                    }
+                    true
+                }
+                // cycles through the implicit surfaces and back to the parametric one
+                Some(Action::CycleImplicitSurface) => {
+                    if !self.show_implicit {
+                        self.implicit_surface.surface_type = 0;
+                        self.show_implicit = true;
+                        self.morph = None;
+                    } else if self.implicit_surface.surface_type + 1 < sd::IMPLICIT_SURFACE_COUNT {
+                        self.implicit_surface.surface_type += 1;
+                    } else {
+                        self.show_implicit = false;
+                    }
+                    if self.show_implicit {
+                        let is = &self.implicit_surface;
+                        println!("implicit surface: {}", is.surface_type_map[&is.surface_type]);
+                    }
+                    self.recreate_buffers = true;
+                    true
                 }
-                Key::Character("e") => {
+                // normal orientation of the surface type, then always kept or flipped
+                Some(Action::CycleNormalOrientation) => {
+                    let ps = &mut self.parametric_surface;
+                    ps.normal_orientation = match ps.normal_orientation {
+                        None => Some(sd::NormalOrientation::Keep),
+                        Some(sd::NormalOrientation::Keep) => Some(sd::NormalOrientation::Flip),
+                        Some(_) => None,
+                    };
+                    let flipped = if ps.flips_normals() { "flipped" } else { "as generated" };
+                    println!("normals: {:?}, {}", ps.orientation(), flipped);
+                    self.update_buffers = true;
+                    true
+                }
+                // analytic or finite-difference normals
+                Some(Action::CycleNormalMode) => {
+                    let ps = &mut self.parametric_surface;
+                    ps.normal_mode = ps.normal_mode.next();
+                    let fallback = match ps.partials_fn() {
+                        Some(_) => "",
+                        None => " (no derivatives, using finite differences)",
+                    };
+                    println!("normal mode: {}{}", ps.normal_mode.name(), fallback);
+                    self.update_buffers = true;
+                    true
+                }
+                // smooth or faceted surface
+                Some(Action::CycleShading) => {
+                    let ps = &mut self.parametric_surface;
+                    ps.shading = ps.shading.next();
+                    println!("shading: {}", ps.shading.name());
+                    self.recreate_buffers = true;
+                    true
+                }
+                Some(Action::ToggleInset) => {
+                    self.show_inset = !self.show_inset;
+                    println!("parameter inset: {}", if self.show_inset { "on" } else { "off" });
+                    true
+                }
+                // welds the seams of closed surfaces
+                Some(Action::ToggleCloseSeams) => {
+                    let ps = &mut self.parametric_surface;
+                    ps.close_seams = !ps.close_seams;
+                    println!("close seams: {}", ps.close_seams);
+                    self.recreate_buffers = true;
+                    true
+                }
+                // unfolds the surface onto its parameter rectangle and back
+                Some(Action::ToggleUnfold) => {
+                    if self.show_implicit {
+                        println!("unfold: implicit surfaces have no parameters");
+                        return true;
+                    }
+                    let refold = self.unfold.target > 0.0;
+                    if self.unfold.is_folded() {
+                        let output = self.surface_output();
+                        self.unfold.toggle();
+                        self.write_unfold(&output);
+                    } else {
+                        self.unfold.toggle();
+                    }
+                    println!("unfold: {}", if refold { "folding" } else { "unfolding" });
+                    true
+                }
+                // surface type changes fade over instead of swapping
+                Some(Action::ToggleCrossFade) => {
+                    self.cross_fade = !self.cross_fade;
+                    println!("cross-fade: {}", if self.cross_fade { "on" } else { "off" });
+                    true
+                }
+                // writes the current surface to surface.obj and surface.ply
+                Some(Action::ExportMesh) => {
+                    self.export_mesh();
+                    true
+                }
+                // area, volume if closed, centroid and inertia of the current mesh
+                Some(Action::PrintMetrics) => {
+                    println!("{}", self.surface_output().metrics());
+                    true
+                }
+                Some(Action::IncreaseRotationSpeed) => {
                     self.rotation_speed += 0.1;
-                    return true;
+                    true
                 }
-                Key::Character("d") => {
+                Some(Action::DecreaseRotationSpeed) => {
                     self.rotation_speed -= 0.1;
                     if self.rotation_speed < 0.0 {
                         self.rotation_speed = 0.0;
                     }
-                    return true;
+                    true
                 }
                 _ => false,
             },
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = [position.x as f32, position.y as f32];
+                false
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } if self.show_inset => self.select_uv(),
             _ => false,
         }
     }
 
-    pub fn update(&mut self, dt: std::time::Duration) {
+    fn update(&mut self, dt: std::time::Duration) {
+        // a recorded playlist runs on the fixed timestep of its frames
+        let dt = self.playlist.as_ref().map_or(dt, |runner| runner.clock(dt));
+        // drive surface type and camera from the playlist
+        if let Some(runner) = &mut self.playlist {
+            match runner.update(dt.as_secs_f32()) {
+                Some(frame) => {
+                    if frame.scene_changed {
+                        let scene = frame.scene;
+                        self.parametric_surface.surface_type = scene.surface_type;
+                        self.morph = None;
+                        self.recreate_buffers |= self.show_implicit;
+                        self.show_implicit = false;
+                        self.update_buffers = true;
+                        if let Some(name) = &scene.colormap_name {
+                            self.parametric_surface.colormap_name = name.clone();
+                        }
+                        if let Some(speed) = scene.rotation_speed {
+                            self.rotation_speed = speed;
+                        }
+                        self.init.window.set_title(&scene.caption);
+                        println!("scene {}: {}", frame.scene_index, scene.caption);
+                    }
+                    self.camera_position = frame.camera_position;
+                    self.look_direction = frame.look_at;
+                    self.view_mat = ws::create_view_mat(
+                        frame.camera_position,
+                        frame.look_at,
+                        frame.up_direction,
+                    );
+                }
+                None => self.playlist = None,
+            }
+        }
+
         // update uniform buffer
         let dt1 = self.rotation_speed * dt.as_secs_f32();
 
@@ -386,68 +696,97 @@ impl State {
         let view_project_mat = self.project_mat * self.view_mat;
 
         let normal_mat = (model_mat.invert().unwrap()).transpose();
+        self.model_mat = model_mat;
 
         let model_ref: &[f32; 16] = model_mat.as_ref();
         let view_projection_ref: &[f32; 16] = view_project_mat.as_ref();
         let normal_ref: &[f32; 16] = normal_mat.as_ref();
 
         self.init.queue.write_buffer(
-            &self.uniform_buffers[0],
+            &self.vert_uniform_buffer,
             0,
             bytemuck::cast_slice(view_projection_ref),
         );
         self.init.queue.write_buffer(
-            &self.uniform_buffers[0],
+            &self.vert_uniform_buffer,
             64,
             bytemuck::cast_slice(model_ref),
         );
         self.init.queue.write_buffer(
-            &self.uniform_buffers[0],
+            &self.vert_uniform_buffer,
             128,
             bytemuck::cast_slice(normal_ref),
         );
 
-        // recreate vertex and index buffers
+        // the frame time drives the unfolding and fading, dt being the time since the start
+        let time = dt.as_secs_f32();
+        let frame_dt = (time - self.frame_time).max(0.0);
+        self.unfold.advance(frame_dt);
+        self.fade.advance(frame_dt);
+        self.frame_time = time;
+        self.init.queue.write_buffer(
+            &self.unfold_uniform_buffer,
+            0,
+            bytemuck::cast_slice(&self.unfold.uniform_data()),
+        );
+
+        if let Some((timeline, t0)) = &self.morph {
+            timeline.apply(&mut self.parametric_surface, t0.elapsed().as_secs_f32());
+            self.update_buffers = true;
+        }
+
+        if std::mem::take(&mut self.fade_next) && (self.recreate_buffers || self.update_buffers) {
+            self.start_fade();
+        }
+
+        // resize vertex and index buffers, reallocating only when the data outgrows them
         if self.recreate_buffers {
-            let data = create_vertices(self.parametric_surface.new());
+            let output = self.surface_output();
+            self.normal_lines.set_mesh(&output);
+            self.refresh_inset(&output);
+            self.write_unfold(&output);
+            let data = create_vertices(output);
+            self.surface_bounds = gizmo::bounds(data.0.iter().map(|v| v.position));
             self.indices_lens = vec![data.2.len() as u32, data.3.len() as u32];
             let vertex_data = [data.0, data.1];
             let index_data = [data.2, data.3];
 
             for i in 0..2 {
-                self.vertex_buffers[i].destroy();
-                self.vertex_buffers[i] =
-                    self.init
-                        .device
-                        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                            label: Some("Vertex Buffer"),
-                            contents: cast_slice(&vertex_data[i]),
-                            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-                        });
-                self.index_buffers[i].destroy();
-                self.index_buffers[i] =
-                    self.init
-                        .device
-                        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                            label: Some("Index Buffer"),
-                            contents: cast_slice(&index_data[i]),
-                            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
-                        });
+                let (device, queue) = (&self.init.device, &self.init.queue);
+                self.vertex_buffers[i].write(device, queue, cast_slice(&vertex_data[i]));
+                self.index_buffers[i].write(device, queue, cast_slice(&index_data[i]));
             }
             self.recreate_buffers = false;
+            self.update_buffers = false;
         }
 
         // update vertex buffer for every 5 seconds
         let elapsed = self.t0.elapsed();
-        if elapsed >= std::time::Duration::from_secs(5) && self.random_shape_change == 1 {
-            self.parametric_surface.surface_type = self.rng.random_range(0..=22) as u32;
-            let data = create_vertices(self.parametric_surface.new());
-            self.init
-                .queue
-                .write_buffer(&self.vertex_buffers[0], 0, cast_slice(&data.0));
-            self.init
-                .queue
-                .write_buffer(&self.vertex_buffers[1], 0, cast_slice(&data.1));
+        if elapsed >= std::time::Duration::from_secs(5)
+            && self.random_shape_change == 1
+            && !self.show_implicit
+            && self.morph.is_none()
+        {
+            self.parametric_surface.surface_type =
+                self.rng.random_range(0..sd::PARAMETRIC_SURFACE_COUNT);
+            if self.cross_fade {
+                self.start_fade();
+            }
+            let output = self.parametric_surface.new();
+            self.normal_lines.set_mesh(&output);
+            self.refresh_inset(&output);
+            self.write_unfold(&output);
+            let data = create_vertices(output);
+            self.surface_bounds = gizmo::bounds(data.0.iter().map(|v| v.position));
+            self.vertex_buffers[0].write(&self.init.device, &self.init.queue, cast_slice(&data.0));
+            self.vertex_buffers[1].write(&self.init.device, &self.init.queue, cast_slice(&data.1));
+            // closed seams take a different number of vertices from one surface to the next
+            if self.parametric_surface.close_seams {
+                self.indices_lens = vec![data.2.len() as u32, data.3.len() as u32];
+                let (device, queue) = (&self.init.device, &self.init.queue);
+                self.index_buffers[0].write(device, queue, cast_slice(&data.2));
+                self.index_buffers[1].write(device, queue, cast_slice(&data.3));
+            }
             self.t0 = std::time::Instant::now();
 
             println!(
@@ -459,22 +798,38 @@ impl State {
 
         // update vertex buffer when data changed
         if self.update_buffers {
-            let data = create_vertices(self.parametric_surface.new());
-            self.init
-                .queue
-                .write_buffer(&self.vertex_buffers[0], 0, cast_slice(&data.0));
-            self.init
-                .queue
-                .write_buffer(&self.vertex_buffers[1], 0, cast_slice(&data.1));
+            let output = self.surface_output();
+            self.normal_lines.set_mesh(&output);
+            self.refresh_inset(&output);
+            self.write_unfold(&output);
+            let data = create_vertices(output);
+            self.surface_bounds = gizmo::bounds(data.0.iter().map(|v| v.position));
+            self.vertex_buffers[0].write(&self.init.device, &self.init.queue, cast_slice(&data.0));
+            self.vertex_buffers[1].write(&self.init.device, &self.init.queue, cast_slice(&data.1));
             self.update_buffers = false;
         }
+
+        // the point of the inset's (u, v) is marked on the surface
+        let mut lines = self.guides.lines(self.surface_bounds, &model_mat);
+        let mut inset_lines = vec![];
+        if self.show_inset {
+            if let Some(hit) = &self.inset_hit {
+                lines.extend(self.crosshair.lines(hit, &model_mat));
+            }
+            inset_lines = self.inset.lines(self.inset_uv);
+        }
+        let (device, queue) = (&self.init.device, &self.init.queue);
+        self.guides
+            .write_lines(device, queue, &view_project_mat, &lines);
+        self.inset_lines
+            .write(device, queue, &Matrix4::identity(), &inset_lines);
+        self.normal_lines
+            .update(device, queue, &view_project_mat, &model_mat);
     }
 
-    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
         let output = self.init.surface.get_current_texture()?;
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        let view = self.init.surface_view(&output.texture);
 
         let mut encoder =
             self.init
@@ -483,23 +838,16 @@ impl State {
                     label: Some("Render Encoder"),
                 });
 
+        let targets = ws::FrameTargets::new(
+            &self.init,
+            &view,
+            &self.targets.msaa_texture_view,
+            &self.targets.depth_texture_view,
+        );
+        self.render_hooks.run_pre_passes(&mut encoder, &targets);
+
         {
-            let color_attach = ws::create_color_attachment(&view);
-            let msaa_attach = ws::create_msaa_color_attachment(&view, &self.msaa_texture_view);
-            let color_attachment = if self.init.sample_count == 1 {
-                color_attach
-            } else {
-                msaa_attach
-            };
-            let depth_attachment = ws::create_depth_stencil_attachment(&self.depth_texture_view);
-
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(color_attachment)],
-                depth_stencil_attachment: Some(depth_attachment),
-                occlusion_query_set: None,
-                timestamp_writes: None,
-            });
+            let mut render_pass = self.targets.begin_pass(&mut encoder, &self.init, &view);
 
             let plot_type = if self.plot_type == 1 {
                 "shape_only"
@@ -509,32 +857,256 @@ impl State {
                 "both"
             };
 
-            if plot_type == "shape_only" || plot_type == "both" {
-                render_pass.set_pipeline(&self.pipelines[0]);
-                render_pass.set_vertex_buffer(0, self.vertex_buffers[0].slice(..));
+            // a transparent surface is blended over the wireframe, so it is drawn last
+            let transparent = self.opacity < 1.0;
+            let draw_shape = plot_type == "shape_only" || plot_type == "both";
+
+            // the unfolding surface is drawn opaque, from vertex buffers 2 and 3; while
+            // fading, the surface shown before and the new one are both drawn transparent
+            let unfolding = !self.unfold.is_folded() && !self.show_implicit;
+            let fading = self.fade.is_fading() && !unfolding;
+            // pipeline and vertex bind group of the surface (0) or wireframe (1), returning
+            // its vertex buffer
+            let bind = |render_pass: &mut wgpu::RenderPass, k: usize| {
+                if unfolding {
+                    render_pass.set_pipeline(&self.unfold_pipelines[k]);
+                    render_pass.set_bind_group(0, &self.unfold_bind_group, &[]);
+                    render_pass.set_bind_group(1, &self.pipelines.bind_groups[1], &[]);
+                    &self.vertex_buffers[k + 2]
+                } else {
+                    self.pipelines.bind(render_pass, k == 1);
+                    &self.vertex_buffers[k]
+                }
+            };
+
+            if draw_shape && (!transparent || unfolding) && !fading {
+                let vertex_buffer = bind(&mut render_pass, 0);
+                render_pass.set_vertex_buffer(0, vertex_buffer.slice());
                 render_pass
-                    .set_index_buffer(self.index_buffers[0].slice(..), wgpu::IndexFormat::Uint16);
-                render_pass.set_bind_group(0, &self.uniform_bind_groups[0], &[]);
-                render_pass.set_bind_group(1, &self.uniform_bind_groups[1], &[]);
+                    .set_index_buffer(self.index_buffers[0].slice(), wgpu::IndexFormat::Uint16);
                 render_pass.draw_indexed(0..self.indices_lens[0], 0, 0..1);
             }
 
             if plot_type == "wireframe_only" || plot_type == "both" {
-                render_pass.set_pipeline(&self.pipelines[1]);
-                render_pass.set_vertex_buffer(0, self.vertex_buffers[1].slice(..));
+                let vertex_buffer = bind(&mut render_pass, 1);
+                render_pass.set_vertex_buffer(0, vertex_buffer.slice());
                 render_pass
-                    .set_index_buffer(self.index_buffers[1].slice(..), wgpu::IndexFormat::Uint16);
-                render_pass.set_bind_group(0, &self.uniform_bind_groups[2], &[]);
-                render_pass.set_bind_group(1, &self.uniform_bind_groups[3], &[]);
+                    .set_index_buffer(self.index_buffers[1].slice(), wgpu::IndexFormat::Uint16);
                 render_pass.draw_indexed(0..self.indices_lens[1], 0, 0..1);
             }
+
+            self.guides.draw(&mut render_pass);
+            self.normal_lines.draw(&mut render_pass);
+            self.inset_lines.draw(&mut render_pass);
+
+            if draw_shape && ((transparent && !unfolding) || fading) {
+                let [outgoing, incoming] = self.fade.opacities().map(|a| (a * self.opacity) as f64);
+                self.pipelines.bind_groups(&mut render_pass);
+                if fading {
+                    let a = outgoing;
+                    render_pass.set_blend_constant(wgpu::Color { r: a, g: a, b: a, a });
+                    let format = wgpu::IndexFormat::Uint16;
+                    self.fade.draw_outgoing(&mut render_pass, &self.transparent_pipeline, format);
+                }
+                let a = incoming;
+                render_pass.set_blend_constant(wgpu::Color { r: a, g: a, b: a, a });
+                render_pass.set_pipeline(&self.transparent_pipeline);
+                render_pass.set_vertex_buffer(0, self.vertex_buffers[0].slice());
+                render_pass
+                    .set_index_buffer(self.index_buffers[0].slice(), wgpu::IndexFormat::Uint16);
+                render_pass.draw_indexed(0..self.indices_lens[0], 0, 0..1);
+            }
             
-            self.fps_counter.print_fps(5);
+            self.fps_counter.log_fps(5);
+        }
+
+        self.render_hooks.run_post_passes(&mut encoder, &targets);
+
+        // record the frame when the playlist or a report export asks for it
+        let frame_path = self.playlist.as_mut().and_then(|r| r.next_frame_path());
+        let can_copy = self.init.config.usage.contains(wgpu::TextureUsages::COPY_SRC);
+        let capture_frame = (frame_path.is_some() || self.export_report) && can_copy;
+        if capture_frame {
+            let (width, height) = (self.init.config.width, self.init.config.height);
+            if self
+                .frame_capture
+                .as_ref()
+                .is_none_or(|c| c.width != width || c.height != height)
+            {
+                self.frame_capture = Some(FrameCapture::new(
+                    &self.init.device,
+                    width,
+                    height,
+                    self.init.config.format,
+                ));
+            }
+            if let Some(capture) = &self.frame_capture {
+                capture.copy_from_texture(&mut encoder, &output.texture);
+            }
         }
 
         self.init.queue.submit(std::iter::once(encoder.finish()));
+
+        if let (true, Some(capture)) = (capture_frame, &self.frame_capture) {
+            match capture.read_rgba(&self.init.device) {
+                Ok(rgba) => {
+                    if let Some(path) = &frame_path
+                        && let Err(e) = capture::save_png_as(
+                            path,
+                            capture.width,
+                            capture.height,
+                            &rgba,
+                            &self.export_convention,
+                        )
+                    {
+                        println!("failed to save {:?}: {}", path, e);
+                    }
+                    if self.export_report {
+                        match self.save_report(capture.width, capture.height, &rgba) {
+                            Ok(_) => println!("report saved to report.html"),
+                            Err(e) => println!("failed to save report: {}", e),
+                        }
+                    }
+                }
+                Err(e) => println!("failed to read frame: {}", e),
+            }
+        }
+        self.export_report = false;
         output.present();
 
         Ok(())
     }
 }
+
+impl State {
+    pub fn set_playlist(&mut self, playlist: IPlaylist) {
+        if let Some(dir) = &playlist.record_dir {
+            std::fs::create_dir_all(dir).unwrap();
+        }
+        self.random_shape_change = 0;
+        self.export_convention = playlist.export_convention;
+        self.playlist = Some(PlaylistRunner::new(playlist));
+    }
+
+    // switching back from an implicit surface changes the vertex count
+    fn show_parametric(&mut self) {
+        if self.show_implicit {
+            self.show_implicit = false;
+            self.recreate_buffers = true;
+        } else {
+            self.update_buffers = true;
+        }
+    }
+
+    // a click in the inset takes its (u, v), a click on the surface the (u, v) of the
+    // picked point; implicit surfaces have no parameters
+    fn select_uv(&mut self) -> bool {
+        if self.show_implicit {
+            return false;
+        }
+        let size = [self.init.size.width as f32, self.init.size.height as f32];
+        let uv = match self.inset.uv_at_cursor(self.cursor, size) {
+            Some(uv) => Some(uv),
+            None => self.pick().and_then(|hit| self.inset.uv_of_hit(&hit)),
+        };
+        let Some([u, v]) = uv else {
+            return false;
+        };
+        println!("u = {u:.3}, v = {v:.3}");
+        self.inset_uv = uv;
+        let output = self.surface_output();
+        self.refresh_inset(&output);
+        true
+    }
+
+    // the surface point under the cursor, in model space
+    fn pick(&mut self) -> Option<PickHit> {
+        let size = [self.init.size.width as f32, self.init.size.height as f32];
+        let ray = Ray::from_screen(&(self.project_mat * self.view_mat), self.cursor, size)?;
+        let ray = ray.transform(&self.model_mat.invert()?);
+        picking::pick_mesh(&ray, &self.surface_output())
+    }
+
+    // follows the surface, its domain and resolution, with the marked point
+    fn refresh_inset(&mut self, output: &sd::ISurfaceOutput) {
+        self.inset.set_surface(&self.parametric_surface);
+        self.inset_hit = match (self.show_implicit, self.inset_uv) {
+            (false, Some(uv)) => self.inset.hit_at_uv(output, uv),
+            _ => None,
+        };
+    }
+
+    // keeps the surface shown to fade it out, before the buffers get the next one
+    fn start_fade(&mut self) {
+        let (device, queue) = (&self.init.device, &self.init.queue);
+        let (vertex_buffer, index_buffer) = (&self.vertex_buffers[0], &self.index_buffers[0]);
+        self.fade
+            .start(device, queue, vertex_buffer, index_buffer, self.indices_lens[0]);
+    }
+
+    // both ends of the unfolding, while the surface is not folded
+    fn write_unfold(&mut self, output: &sd::ISurfaceOutput) {
+        if self.show_implicit || self.unfold.is_folded() {
+            return;
+        }
+        let flat = self.parametric_surface.flat_positions(output);
+        let (device, queue) = (&self.init.device, &self.init.queue);
+        for (i, wireframe) in [(2, false), (3, true)] {
+            let vertices = unfold::unfold_vertices(output, &flat, wireframe);
+            self.vertex_buffers[i].write(device, queue, cast_slice(&vertices));
+        }
+    }
+
+    // an implicit surface too fine for u16 indices falls back to the parametric one
+    fn surface_output(&mut self) -> sd::ISurfaceOutput {
+        if self.show_implicit {
+            match self.implicit_surface.new() {
+                Ok(output) => return output,
+                Err(e) => {
+                    log::warn!("{}", e);
+                    self.show_implicit = false;
+                }
+            }
+        }
+        self.parametric_surface.new()
+    }
+
+    // mesh files in the coordinates of the export convention
+    fn export_mesh(&mut self) {
+        let output = self.surface_output();
+        let convention = self.export_convention;
+        for (path, result) in [
+            ("surface.obj", export::export_obj_as(&output, "surface.obj", &convention)),
+            ("surface.ply", export::export_ply_as(&output, "surface.ply", &convention)),
+        ] {
+            match result {
+                Ok(_) => println!("surface saved to {}", path),
+                Err(e) => println!("failed to save {}: {}", path, e),
+            }
+        }
+    }
+
+    fn print_shape_params(&self) {
+        let ps = &self.parametric_surface;
+        println!(
+            "p = {}, q = {}, tube radius = {:.2}",
+            ps.knot_p, ps.knot_q, ps.tube_radius
+        );
+    }
+
+    fn save_report(&self, width: u32, height: u32, rgba: &[u8]) -> anyhow::Result<()> {
+        let mut report = report::parametric_surface_report(&self.parametric_surface);
+        report.title = self.init.window.title();
+        report.add_image_rgba("current frame", width, height, rgba)?;
+        // camera values are reported in the coordinates of the export convention
+        let convention = &self.export_convention;
+        report.camera = Some(ICameraInfo {
+            position: convention.transform_point(self.camera_position.into()),
+            look_at: convention.transform_point(self.look_direction.into()),
+            up: convention.transform_normal([0.0, 1.0, 0.0]),
+            fovy_degrees: 72.0,
+        });
+        report.parameter("rotation speed", self.rotation_speed);
+        report.save("report.html")
+    }
+}
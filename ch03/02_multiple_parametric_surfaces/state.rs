@@ -1,36 +1,56 @@
 use std::sync::Arc;
 use bytemuck::cast_slice;
 use cgmath::{Matrix, Matrix4, SquareMatrix};
-use wgpu::util::DeviceExt;
-use winit::{
-    event::ElementState, event::KeyEvent, event::WindowEvent, keyboard::Key, keyboard::NamedKey,
-    window::Window,
-};
-use rand::Rng;
-use rand::rngs::ThreadRng;
-
+use winit::{event::ElementState, event::KeyEvent, event::MouseButton, event::WindowEvent};
+use winit::window::{CursorIcon, Window};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use wgpu_surfaces::app::{self, Action, InputMap, SurfaceApp};
+use wgpu_surfaces::appearance::DirectionalLight;
+use wgpu_surfaces::capture::{self, FrameCapture};
+use wgpu_surfaces::depth::{LinearDepthView, NearFarTuner};
+use wgpu_surfaces::export::ExportConvention;
+use wgpu_surfaces::instancing::{IPackedMeshes, InstanceBatches, MeshRange};
+use wgpu_surfaces::picking::{self, DepthPicker, ProbeHooks};
+use wgpu_surfaces::playlist::{IPlaylist, PlaylistRunner};
 use wgpu_surfaces::surface_data as sd;
+use wgpu_surfaces::cli::SurfaceArgs;
 use wgpu_surfaces::wgpu_simplified as ws;
 
 use crate::vertex::{create_vertices, Vertex};
 
+// default key bindings, remapped with --keymap (see app::InputMap)
+const KEYS: &[(Action, &str)] = &[
+    (Action::CyclePlotType, "Space"),
+    (Action::NextSurfaceType, "Control"),
+    (Action::CycleColormapDirection, "Shift"),
+    (Action::ToggleRandomShape, "Alt"),
+    (Action::IncreaseRotationSpeed, "q"),
+    (Action::DecreaseRotationSpeed, "a"),
+    (Action::ToggleDepthView, "d"),
+    (Action::ToggleMixed, "m"),
+    (Action::ToggleAutoNearFar, "n"),
+    (Action::ToggleProbe, picking::PROBE_KEY),
+];
+
 pub struct State {
     init: ws::InitWgpu,
-    pipelines: Vec<wgpu::RenderPipeline>,
-    vertex_buffers: Vec<wgpu::Buffer>,
-    index_buffers: Vec<wgpu::Buffer>,
-    uniform_bind_groups: Vec<wgpu::BindGroup>,
-    uniform_buffers: Vec<wgpu::Buffer>,
+    pipelines: app::SurfacePipelines,
+    vertex_buffers: Vec<ws::GrowableBuffer>,
+    index_buffers: Vec<ws::GrowableBuffer>,
+    uniform_buffers: Vec<wgpu::Buffer>, // view-projection, model and normal matrices
+    targets: app::RenderTargets,
     view_mat: Matrix4<f32>,
     project_mat: Matrix4<f32>,
-    msaa_texture_view: wgpu::TextureView,
-    depth_texture_view: wgpu::TextureView,
-    indices_lens: Vec<u32>,
+    mesh_ranges: [Vec<MeshRange>; 2], // shape, wireframe
+    batches: InstanceBatches,
+    mixed: bool, // several surface types across the grid
     plot_type: u32,
     update_buffers: bool,
     recreate_buffers: bool,
     rotation_speed: f32,
-    rng: ThreadRng,
+    rng: StdRng, // seeded by --seed
     t0: std::time::Instant,
     random_shape_change: u32,
 
@@ -38,17 +58,29 @@ pub struct State {
     z_num: u32,
     objects_count: u32,
     parametric_surface: sd::IParametricSurface,
+    input_map: InputMap,
     fps_counter: ws::FpsCounter,
+    render_hooks: ws::RenderHooks,
+    playlist: Option<PlaylistRunner>,
+    frame_capture: Option<FrameCapture>,
+    export_convention: ExportConvention,
+
+    linear_depth: LinearDepthView,
+    show_depth: bool,
+    auto_near_far: bool,
+    near_far_tuner: NearFarTuner,
+    near_far: [f32; 2],
+
+    depth_picker: DepthPicker,
+    cursor: [f32; 2],
+    probe_hooks: ProbeHooks,
+    probing: bool, // key j: clicks report the surface point under the cursor
+    pick_requested: bool,
 }
 
-impl State {
-    pub async fn new(
-        window: Arc<Window>,
-        sample_count: u32,
-        colormap_name: &str,
-        wireframe_color: &str,
-    ) -> Self {
-        let init = ws::InitWgpu::init_wgpu(window, sample_count).await;
+impl SurfaceApp for State {
+    async fn new(window: Arc<Window>, args: &SurfaceArgs) -> wgpu_surfaces::Result<Self> {
+        let init = ws::InitWgpu::init_wgpu(window, args.msaa, args.present_mode).await?;
 
         // Loading Shaders
         let vs_shader = init
@@ -62,7 +94,6 @@ impl State {
         let camera_position = (3.0, 4.5, 5.2).into();
         let look_direction = (0.0, 0.0, 0.0).into();
         let up_direction = cgmath::Vector3::unit_y();
-        let light_direction = [-0.5f32, -0.5, -0.5];
 
         let (view_mat, project_mat, vp_mat) = ws::create_vp_mat(
             camera_position,
@@ -105,46 +136,15 @@ impl State {
             mapped_at_creation: false,
         });
 
-        // create light uniform buffer. here we set eye_position = camera_position
-        let light_uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Light Uniform Buffer"),
-            size: 48,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        let eye_position: &[f32; 3] = camera_position.as_ref();
-        init.queue.write_buffer(
-            &light_uniform_buffer,
-            0,
-            cast_slice(light_direction.as_ref()),
-        );
-        init.queue
-            .write_buffer(&light_uniform_buffer, 16, cast_slice(eye_position));
-
-        // set specular light color to white
-        let specular_color: [f32; 3] = [1.0, 1.0, 1.0];
-        init.queue.write_buffer(
-            &light_uniform_buffer,
-            32,
-            cast_slice(specular_color.as_ref()),
-        );
-
-        // material uniform buffer
-        let material_uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Material Uniform Buffer"),
-            size: 16,
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        // set default material parameters
-        let material = [0.1f32, 0.7, 0.4, 30.0];
-        init.queue
-            .write_buffer(&material_uniform_buffer, 0, cast_slice(material.as_ref()));
+        // light and material uniforms; here we set eye_position = camera_position
+        let light = DirectionalLight {
+            eye_position: camera_position.into(),
+            ..Default::default()
+        };
+        let lighting = app::LightUniforms::new(&init, &light, [0.1, 0.7, 0.4, 30.0]);
 
         // uniform bind group for vertex shader
-        let (vert_bind_group_layout, vert_bind_group) = ws::create_bind_group_storage(
+        let vert_bind_group = ws::create_bind_group_storage(
             &init.device,
             vec![
                 wgpu::ShaderStages::VERTEX,
@@ -161,168 +161,93 @@ impl State {
                 model_uniform_buffer.as_entire_binding(),
                 normal_uniform_buffer.as_entire_binding(),
             ],
-        );
+        )?;
 
-        let (vert_bind_group_layout2, vert_bind_group2) = ws::create_bind_group_storage(
-            &init.device,
-            vec![
-                wgpu::ShaderStages::VERTEX,
-                wgpu::ShaderStages::VERTEX,
-                wgpu::ShaderStages::VERTEX,
-            ],
-            vec![
-                wgpu::BufferBindingType::Uniform,
-                wgpu::BufferBindingType::Storage { read_only: true },
-                wgpu::BufferBindingType::Storage { read_only: true },
-            ],
-            &[
-                vp_uniform_buffer.as_entire_binding(),
-                model_uniform_buffer.as_entire_binding(),
-                normal_uniform_buffer.as_entire_binding(),
-            ],
-        );
-
-        // uniform bind group for fragment shader
-        let (frag_bind_group_layout, frag_bind_group) = ws::create_bind_group(
-            &init.device,
-            vec![wgpu::ShaderStages::FRAGMENT, wgpu::ShaderStages::FRAGMENT],
-            &[
-                light_uniform_buffer.as_entire_binding(),
-                material_uniform_buffer.as_entire_binding(),
-            ],
-        );
-        let (frag_bind_group_layout2, frag_bind_group2) = ws::create_bind_group(
-            &init.device,
-            vec![wgpu::ShaderStages::FRAGMENT, wgpu::ShaderStages::FRAGMENT],
-            &[
-                light_uniform_buffer.as_entire_binding(),
-                material_uniform_buffer.as_entire_binding(),
-            ],
-        );
-
-        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+        let vertex_buffer_layout = [wgpu::VertexBufferLayout {
             array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
             step_mode: wgpu::VertexStepMode::Vertex,
             attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3],
             // pos, norm, col
-        };
-
-        let pipeline_layout = init
-            .device
-            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&vert_bind_group_layout, &frag_bind_group_layout],
-                push_constant_ranges: &[],
-            });
-
-        let mut ppl = ws::IRenderPipeline {
-            vs_shader: Some(&vs_shader),
-            fs_shader: Some(&fs_shader),
-            pipeline_layout: Some(&pipeline_layout),
-            vertex_buffer_layout: &[vertex_buffer_layout],
-            ..Default::default()
-        };
-        let pipeline = ppl.new(&init);
-
-        let vertex_buffer_layout2 = wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3],
-            // pos, norm, col
-        };
-
-        let pipeline_layout2 =
-            init.device
-                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                    label: Some("Render Pipeline Layout 2"),
-                    bind_group_layouts: &[&vert_bind_group_layout2, &frag_bind_group_layout2],
-                    push_constant_ranges: &[],
-                });
-
-        let mut ppl2 = ws::IRenderPipeline {
-            topology: wgpu::PrimitiveTopology::LineList,
-            vs_shader: Some(&vs_shader),
-            fs_shader: Some(&fs_shader),
-            pipeline_layout: Some(&pipeline_layout2),
-            vertex_buffer_layout: &[vertex_buffer_layout2],
-            ..Default::default()
-        };
-        let pipeline2 = ppl2.new(&init);
-
-        let msaa_texture_view = ws::create_msaa_texture_view(&init);
-        let depth_texture_view = ws::create_depth_view(&init);
+        }];
+        let pipelines = app::SurfacePipelines::new(
+            &init,
+            &vs_shader,
+            &fs_shader,
+            &vertex_buffer_layout,
+            vert_bind_group,
+            lighting.bind_group(&init.device)?,
+        )?;
+
+        // bindable so the linear depth view can read it
+        let targets = app::RenderTargets::with_depth(&init, true);
+        let near_far_tuner = NearFarTuner::default();
+        let near_far = [near_far_tuner.min_near, near_far_tuner.max_far];
+        let linear_depth = LinearDepthView::new(&init, &targets.depth_texture_view);
+        linear_depth.set_near_far(&init, near_far[0], near_far[1]);
+        let depth_picker = DepthPicker::new(&init, &targets.depth_texture_view);
 
         let mut ps = sd::IParametricSurface {
             scale: 1.2,
             surface_type: 0,
             u_resolution: 64,
             v_resolution: 64,
-            colormap_name: colormap_name.to_string(),
-            wireframe_color: wireframe_color.to_string(),
+            colormap_name: args.colormap.clone(),
+            wireframe_color: args.wireframe_color.clone(),
             ..Default::default()
         };
-        let data = create_vertices(ps.new());
+        if let Some(resolution) = args.resolution {
+            (ps.u_resolution, ps.v_resolution) = (resolution, resolution);
+        }
+        if let Some(surface_type) = args.surface_type {
+            ps.surface_type = surface_type;
+        }
+        let [shapes, wireframes] = pack_surfaces(&mut ps, false);
 
-        let vertex_buffer = init
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Vertex Buffer"),
-                contents: cast_slice(&data.0),
-                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            });
+        let vertex_buffer = ws::GrowableBuffer::new(
+            &init.device,
+            "Vertex Buffer",
+            wgpu::BufferUsages::VERTEX,
+            cast_slice(&shapes.vertices),
+        );
 
-        let vertex_buffer2 = init
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Vertex Buffer 2"),
-                contents: cast_slice(&data.1),
-                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            });
+        let vertex_buffer2 = ws::GrowableBuffer::new(
+            &init.device,
+            "Vertex Buffer 2",
+            wgpu::BufferUsages::VERTEX,
+            cast_slice(&wireframes.vertices),
+        );
 
-        let index_buffer = init
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Index Buffer"),
-                contents: bytemuck::cast_slice(&data.2),
-                usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
-            });
+        let index_buffer = ws::GrowableBuffer::new(
+            &init.device,
+            "Index Buffer",
+            wgpu::BufferUsages::INDEX,
+            cast_slice(&shapes.indices),
+        );
 
-        let index_buffer2 = init
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Index Buffer 2"),
-                contents: bytemuck::cast_slice(&data.3),
-                usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
-            });
+        let index_buffer2 = ws::GrowableBuffer::new(
+            &init.device,
+            "Index Buffer 2",
+            wgpu::BufferUsages::INDEX,
+            cast_slice(&wireframes.indices),
+        );
 
-        Self {
+        Ok(Self {
             init,
-            pipelines: vec![pipeline, pipeline2],
+            pipelines,
             vertex_buffers: vec![vertex_buffer, vertex_buffer2],
             index_buffers: vec![index_buffer, index_buffer2],
-            uniform_bind_groups: vec![
-                vert_bind_group,
-                frag_bind_group,
-                vert_bind_group2,
-                frag_bind_group2,
-            ],
-            uniform_buffers: vec![
-                vp_uniform_buffer,
-                model_uniform_buffer,
-                normal_uniform_buffer,
-                light_uniform_buffer,
-                material_uniform_buffer,
-            ],
+            uniform_buffers: vec![vp_uniform_buffer, model_uniform_buffer, normal_uniform_buffer],
+            targets,
             view_mat,
             project_mat,
-            msaa_texture_view,
-            depth_texture_view,
-            indices_lens: vec![data.2.len() as u32, data.3.len() as u32],
+            mesh_ranges: [shapes.ranges, wireframes.ranges],
+            batches: InstanceBatches::single(objects_count),
+            mixed: false,
             plot_type: 1,
             update_buffers: false,
             recreate_buffers: false,
             rotation_speed: 1.0,
-            rng: rand::rng(),
+            rng: StdRng::seed_from_u64(args.seed.unwrap_or_else(rand::random)),
             t0: std::time::Instant::now(),
             random_shape_change: 0,
 
@@ -331,39 +256,50 @@ impl State {
             objects_count,
 
             parametric_surface: ps,
+            input_map: InputMap::with_args(KEYS, args)?,
             fps_counter: ws::FpsCounter::default(),
-        }
+            render_hooks: ws::RenderHooks::default(),
+            playlist: None,
+            frame_capture: None,
+            export_convention: ExportConvention::default(),
+
+            linear_depth,
+            show_depth: false,
+            auto_near_far: false,
+            near_far_tuner,
+            near_far,
+
+            depth_picker,
+            cursor: [0.0, 0.0],
+            probe_hooks: default_probe_hooks(x_num, z_num),
+            probing: false,
+            pick_requested: false,
+        })
     }
 
-    pub fn window(&self) -> &Window {
-        &self.init.window
+    fn init(&self) -> &ws::InitWgpu {
+        &self.init
     }
 
-    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
-        self.init.size
+    fn init_mut(&mut self) -> &mut ws::InitWgpu {
+        &mut self.init
     }
 
-    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
-        if new_size.width > 0 && new_size.height > 0 {
-            self.init.size = new_size;
-            // The surface needs to be reconfigured every time the window is resized.
-            self.init.config.width = new_size.width;
-            self.init.config.height = new_size.height;
-            self.init
-                .surface
-                .configure(&self.init.device, &self.init.config);
-
-            self.project_mat =
-                ws::create_projection_mat(new_size.width as f32 / new_size.height as f32, true);
+    fn resized(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.project_mat = ws::create_perspective_mat(
+            new_size.width as f32 / new_size.height as f32,
+            self.near_far[0],
+            self.near_far[1],
+        );
 
-            self.depth_texture_view = ws::create_depth_view(&self.init);
-            if self.init.sample_count > 1 {
-                self.msaa_texture_view = ws::create_msaa_texture_view(&self.init);
-            }
-        }
+        self.targets.resize(&self.init);
+        self.linear_depth
+            .set_depth_view(&self.init, &self.targets.depth_texture_view);
+        self.depth_picker
+            .set_depth_view(&self.init, &self.targets.depth_texture_view);
     }
 
-    pub fn input(&mut self, event: &WindowEvent) -> bool {
+    fn input(&mut self, event: &WindowEvent) -> bool {
         match event {
             WindowEvent::KeyboardInput {
                 event:
@@ -373,45 +309,123 @@ impl State {
                         ..
                     },
                 ..
-            } => match key.as_ref() {
-                Key::Named(NamedKey::Space) => {
+            } => match self.input_map.action(key) {
+                Some(Action::CyclePlotType) => {
                     self.plot_type = (self.plot_type + 1) % 3;
-                    return true;
+                    true
                 }
-                Key::Named(NamedKey::Control) => {
+                Some(Action::NextSurfaceType) => {
                     self.parametric_surface.surface_type =
-                        (self.parametric_surface.surface_type + 1) % 23;
+                        (self.parametric_surface.surface_type + 1) % sd::PARAMETRIC_SURFACE_COUNT;
                     self.update_buffers = true;
-                    return true;
+                    true
                 }
-                Key::Named(NamedKey::Shift) => {
+                Some(Action::CycleColormapDirection) => {
                     self.parametric_surface.colormap_direction =
                         (self.parametric_surface.colormap_direction + 1) % 3;
                     self.update_buffers = true;
-                    return true;
+                    true
                 }
-                Key::Named(NamedKey::Alt) => {
+                Some(Action::ToggleRandomShape) => {
                     self.random_shape_change = (self.random_shape_change + 1) % 2;
-                    return true;
+                    true
                 }
-                Key::Character("q") => {
+                Some(Action::IncreaseRotationSpeed) => {
                     self.rotation_speed += 0.1;
-                    return true;
+                    true
                 }
-                Key::Character("a") => {
+                Some(Action::DecreaseRotationSpeed) => {
                     self.rotation_speed -= 0.1;
                     if self.rotation_speed < 0.0 {
                         self.rotation_speed = 0.0;
                     }
-                    return true;
+                    true
+                }
+                Some(Action::ToggleDepthView) => {
+                    self.show_depth = !self.show_depth;
+                    true
+                }
+                // several surface types in one grid, one draw call per type
+                Some(Action::ToggleMixed) => {
+                    self.mixed = !self.mixed;
+                    self.write_surface_buffers();
+                    println!(
+                        "mixed surfaces: {} ({} draw calls per pass)",
+                        self.mixed,
+                        self.batches.batches.len()
+                    );
+                    true
+                }
+                Some(Action::ToggleAutoNearFar) => {
+                    self.auto_near_far = !self.auto_near_far;
+                    if !self.auto_near_far {
+                        self.set_near_far([
+                            self.near_far_tuner.min_near,
+                            self.near_far_tuner.max_far,
+                        ]);
+                    }
+                    println!("auto near/far: {}", self.auto_near_far);
+                    true
+                }
+                Some(Action::ToggleProbe) => {
+                    self.probing = !self.probing;
+                    let icon = match self.probing {
+                        true => CursorIcon::Crosshair,
+                        false => CursorIcon::Default,
+                    };
+                    self.init.window.set_cursor(icon);
+                    println!("probe mode: {}", if self.probing { "on" } else { "off" });
+                    true
                 }
                 _ => false,
             },
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = [position.x as f32, position.y as f32];
+                false
+            }
+            // in probe mode a click reads the depth buffer under the cursor next frame
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } if self.probing => {
+                self.pick_requested = true;
+                true
+            }
             _ => false,
         }
     }
 
-    pub fn update(&mut self, dt: std::time::Duration) {
+    fn update(&mut self, dt: std::time::Duration) {
+        // a recorded playlist runs on the fixed timestep of its frames
+        let dt = self.playlist.as_ref().map_or(dt, |runner| runner.clock(dt));
+        // drive surface type and camera from the playlist
+        if let Some(runner) = &mut self.playlist {
+            match runner.update(dt.as_secs_f32()) {
+                Some(frame) => {
+                    if frame.scene_changed {
+                        let scene = frame.scene;
+                        self.parametric_surface.surface_type = scene.surface_type;
+                        if let Some(name) = &scene.colormap_name {
+                            self.parametric_surface.colormap_name = name.clone();
+                        }
+                        if let Some(speed) = scene.rotation_speed {
+                            self.rotation_speed = speed;
+                        }
+                        self.init.window.set_title(&scene.caption);
+                        println!("scene {}: {}", frame.scene_index, scene.caption);
+                        self.update_buffers = true;
+                    }
+                    self.view_mat = ws::create_view_mat(
+                        frame.camera_position,
+                        frame.look_at,
+                        frame.up_direction,
+                    );
+                }
+                None => self.playlist = None,
+            }
+        }
+
         // update uniform buffer
         let mut model_mat: Vec<[f32; 16]> = vec![];
         let mut normal_mat: Vec<[f32; 16]> = vec![];
@@ -432,6 +446,11 @@ impl State {
                 normal_mat.push(*(n.as_ref()));
             }
         }
+        // the instances of each surface type are drawn as one range
+        if self.mixed {
+            model_mat = self.batches.reorder(&model_mat);
+            normal_mat = self.batches.reorder(&normal_mat);
+        }
         self.init
             .queue
             .write_buffer(&self.uniform_buffers[1], 0, cast_slice(&model_mat));
@@ -439,6 +458,13 @@ impl State {
             .queue
             .write_buffer(&self.uniform_buffers[2], 0, cast_slice(&normal_mat));
 
+        // fit the clip planes around the instance grid
+        if self.auto_near_far {
+            let (bounds_min, bounds_max) = self.scene_bounds();
+            let near_far = self.near_far_tuner.fit(&self.view_mat, bounds_min, bounds_max);
+            self.set_near_far(near_far);
+        }
+
         let view_project_mat = self.project_mat * self.view_mat;
         let view_projection_ref: &[f32; 16] = view_project_mat.as_ref();
 
@@ -448,47 +474,18 @@ impl State {
             bytemuck::cast_slice(view_projection_ref),
         );
 
-        // recreate vertex and index buffers
+        // resize vertex and index buffers
         if self.recreate_buffers {
-            let data = create_vertices(self.parametric_surface.new());
-            self.indices_lens = vec![data.2.len() as u32, data.3.len() as u32];
-            let vertex_data = [data.0, data.1];
-            let index_data = [data.2, data.3];
-
-            for i in 0..2 {
-                self.vertex_buffers[i].destroy();
-                self.vertex_buffers[i] =
-                    self.init
-                        .device
-                        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                            label: Some("Vertex Buffer"),
-                            contents: cast_slice(&vertex_data[i]),
-                            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-                        });
-                self.index_buffers[i].destroy();
-                self.index_buffers[i] =
-                    self.init
-                        .device
-                        .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                            label: Some("Index Buffer"),
-                            contents: cast_slice(&index_data[i]),
-                            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
-                        });
-            }
+            self.write_surface_buffers();
             self.recreate_buffers = false;
         }
 
         // update vertex buffer for every 5 seconds
         let elapsed = self.t0.elapsed();
         if elapsed >= std::time::Duration::from_secs(5) && self.random_shape_change == 0 {
-            self.parametric_surface.surface_type = self.rng.random_range(0..=22) as u32;
-            let data = create_vertices(self.parametric_surface.new());
-            self.init
-                .queue
-                .write_buffer(&self.vertex_buffers[0], 0, cast_slice(&data.0));
-            self.init
-                .queue
-                .write_buffer(&self.vertex_buffers[1], 0, cast_slice(&data.1));
+            self.parametric_surface.surface_type =
+                self.rng.random_range(0..sd::PARAMETRIC_SURFACE_COUNT);
+            self.write_surface_buffers();
             self.t0 = std::time::Instant::now();
 
             println!(
@@ -500,22 +497,14 @@ impl State {
 
         // update vertex buffer when data changed
         if self.update_buffers {
-            let data = create_vertices(self.parametric_surface.new());
-            self.init
-                .queue
-                .write_buffer(&self.vertex_buffers[0], 0, cast_slice(&data.0));
-            self.init
-                .queue
-                .write_buffer(&self.vertex_buffers[1], 0, cast_slice(&data.1));
+            self.write_surface_buffers();
             self.update_buffers = false;
         }
     }
 
-    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
         let output = self.init.surface.get_current_texture()?;
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        let view = self.init.surface_view(&output.texture);
 
         let mut encoder =
             self.init
@@ -524,23 +513,32 @@ impl State {
                     label: Some("Render Encoder"),
                 });
 
+        let targets = ws::FrameTargets::new(
+            &self.init,
+            &view,
+            &self.targets.msaa_texture_view,
+            &self.targets.depth_texture_view,
+        );
+        self.render_hooks.run_pre_passes(&mut encoder, &targets);
+
         {
-            let color_attach = ws::create_color_attachment(&view);
-            let msaa_attach = ws::create_msaa_color_attachment(&view, &self.msaa_texture_view);
-            let color_attachment = if self.init.sample_count == 1 {
-                color_attach
+            // the linear depth view and the picker read the depth buffer after this pass
+            let depth_store = if self.show_depth || self.pick_requested {
+                wgpu::StoreOp::Store
             } else {
-                msaa_attach
+                wgpu::StoreOp::Discard
             };
-            let depth_attachment = ws::create_depth_stencil_attachment(&self.depth_texture_view);
-
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(color_attachment)],
-                depth_stencil_attachment: Some(depth_attachment),
-                occlusion_query_set: None,
-                timestamp_writes: None,
-            });
+            let depth_attachment = ws::create_depth_stencil_attachment_store(
+                &self.targets.depth_texture_view,
+                depth_store,
+            );
+            let mut render_pass = app::begin_main_pass(
+                &mut encoder,
+                &self.init,
+                &view,
+                &self.targets.msaa_texture_view,
+                depth_attachment,
+            );
 
             let plot_type = if self.plot_type == 1 {
                 "shape_only"
@@ -551,31 +549,177 @@ impl State {
             };
 
             if plot_type == "shape_only" || plot_type == "both" {
-                render_pass.set_pipeline(&self.pipelines[0]);
-                render_pass.set_vertex_buffer(0, self.vertex_buffers[0].slice(..));
+                self.pipelines.bind(&mut render_pass, false);
+                render_pass.set_vertex_buffer(0, self.vertex_buffers[0].slice());
                 render_pass
-                    .set_index_buffer(self.index_buffers[0].slice(..), wgpu::IndexFormat::Uint16);
-                render_pass.set_bind_group(0, &self.uniform_bind_groups[0], &[]);
-                render_pass.set_bind_group(1, &self.uniform_bind_groups[1], &[]);
-                render_pass.draw_indexed(0..self.indices_lens[0], 0, 0..self.objects_count);
+                    .set_index_buffer(self.index_buffers[0].slice(), wgpu::IndexFormat::Uint16);
+                self.batches.draw(&mut render_pass, &self.mesh_ranges[0]);
             }
 
             if plot_type == "wireframe_only" || plot_type == "both" {
-                render_pass.set_pipeline(&self.pipelines[1]);
-                render_pass.set_vertex_buffer(0, self.vertex_buffers[1].slice(..));
+                self.pipelines.bind(&mut render_pass, true);
+                render_pass.set_vertex_buffer(0, self.vertex_buffers[1].slice());
                 render_pass
-                    .set_index_buffer(self.index_buffers[1].slice(..), wgpu::IndexFormat::Uint16);
-                render_pass.set_bind_group(0, &self.uniform_bind_groups[2], &[]);
-                render_pass.set_bind_group(1, &self.uniform_bind_groups[3], &[]);
-                render_pass.draw_indexed(0..self.indices_lens[1], 0, 0..self.objects_count);
+                    .set_index_buffer(self.index_buffers[1].slice(), wgpu::IndexFormat::Uint16);
+                self.batches.draw(&mut render_pass, &self.mesh_ranges[1]);
             }
 
-            self.fps_counter.print_fps(5);
+            self.fps_counter.log_fps(5);
+        }
+
+        self.render_hooks.run_post_passes(&mut encoder, &targets);
+
+        let size = [self.init.config.width as f32, self.init.config.height as f32];
+        let picking = self.pick_requested
+            && self
+                .depth_picker
+                .request(&self.init.queue, &mut encoder, self.cursor, size);
+        self.pick_requested = false;
+
+        if self.show_depth {
+            self.linear_depth.draw(&mut encoder, &view);
+        }
+
+        // record the frame when the playlist asks for it
+        let frame_path = self.playlist.as_mut().and_then(|r| r.next_frame_path());
+        let can_copy = self.init.config.usage.contains(wgpu::TextureUsages::COPY_SRC);
+        if frame_path.is_some() && can_copy {
+            let (width, height) = (self.init.config.width, self.init.config.height);
+            if self
+                .frame_capture
+                .as_ref()
+                .is_none_or(|c| c.width != width || c.height != height)
+            {
+                self.frame_capture = Some(FrameCapture::new(
+                    &self.init.device,
+                    width,
+                    height,
+                    self.init.config.format,
+                ));
+            }
+            if let Some(capture) = &self.frame_capture {
+                capture.copy_from_texture(&mut encoder, &output.texture);
+            }
         }
 
         self.init.queue.submit(std::iter::once(encoder.finish()));
+
+        if picking {
+            let view_project_mat = self.project_mat * self.view_mat;
+            match self.depth_picker.read(&self.init.device, &view_project_mat) {
+                Ok(Some(hit)) => self.probe_hooks.emit(&hit.probe_event()),
+                Ok(None) => println!("probe: background"),
+                Err(e) => println!("failed to read depth: {}", e),
+            }
+        }
+
+        if let (Some(path), Some(capture)) = (frame_path, &self.frame_capture) {
+            match capture.read_rgba(&self.init.device) {
+                Ok(rgba) => {
+                    if let Err(e) = capture::save_png_as(
+                        &path,
+                        capture.width,
+                        capture.height,
+                        &rgba,
+                        &self.export_convention,
+                    ) {
+                        println!("failed to save {:?}: {}", path, e);
+                    }
+                }
+                Err(e) => println!("failed to read frame: {}", e),
+            }
+        }
         output.present();
 
         Ok(())
     }
 }
+
+impl State {
+    pub fn set_playlist(&mut self, playlist: IPlaylist) {
+        if let Some(dir) = &playlist.record_dir {
+            std::fs::create_dir_all(dir).unwrap();
+        }
+        self.random_shape_change = 1;
+        self.export_convention = playlist.export_convention;
+        self.playlist = Some(PlaylistRunner::new(playlist));
+    }
+
+    // rewrites the vertex and index buffers with the surface, or with the packed
+    // surfaces of the mixed grid, reallocating only when the data outgrows them
+    fn write_surface_buffers(&mut self) {
+        let packs = pack_surfaces(&mut self.parametric_surface, self.mixed);
+        let (device, queue) = (&self.init.device, &self.init.queue);
+        for (i, pack) in packs.iter().enumerate() {
+            self.vertex_buffers[i].write(device, queue, cast_slice(&pack.vertices));
+            self.index_buffers[i].write(device, queue, cast_slice(&pack.indices));
+        }
+        self.mesh_ranges = packs.map(|pack| pack.ranges);
+        self.batches = if self.mixed {
+            // diagonal stripes of the surface types
+            let mesh_of_instance: Vec<usize> = (0..self.x_num)
+                .flat_map(|i| (0..self.z_num).map(move |j| ((i + j) % MIXED_SURFACES) as usize))
+                .collect();
+            InstanceBatches::new(&mesh_of_instance, MIXED_SURFACES as usize)
+        } else {
+            InstanceBatches::single(self.objects_count)
+        };
+    }
+
+    // world-space box enclosing all instances, including their rotation
+    fn scene_bounds(&self) -> ([f32; 3], [f32; 3]) {
+        let radius = 0.5 * self.parametric_surface.scale * 3f32.sqrt();
+        let x_max = -150.0 + 2.0 * (self.x_num - 1) as f32;
+        let z_max = -180.0 + 2.0 * (self.z_num - 1) as f32;
+        (
+            [-150.0 - radius, 2.0 - radius, -180.0 - radius],
+            [x_max + radius, 2.0 + radius, z_max + radius],
+        )
+    }
+
+    fn set_near_far(&mut self, near_far: [f32; 2]) {
+        self.near_far = near_far;
+        self.project_mat = ws::create_perspective_mat(
+            self.init.config.width as f32 / self.init.config.height as f32,
+            near_far[0],
+            near_far[1],
+        );
+        self.linear_depth
+            .set_near_far(&self.init, near_far[0], near_far[1]);
+    }
+}
+
+// prints the picked point with the grid cell of the instance nearest to it
+fn default_probe_hooks(x_num: u32, z_num: u32) -> ProbeHooks {
+    let mut hooks = ProbeHooks::default();
+    hooks.add(move |event| {
+        let [x, y, z] = event.position;
+        let i = ((x + 150.0) / 2.0).round().clamp(0.0, (x_num - 1) as f32);
+        let j = ((z + 180.0) / 2.0).round().clamp(0.0, (z_num - 1) as f32);
+        println!(
+            "probe: position = [{:.3}, {:.3}, {:.3}], instance = ({}, {})",
+            x, y, z, i, j
+        );
+    });
+    hooks
+}
+
+// surface types of the mixed grid, starting at the current one
+const MIXED_SURFACES: u32 = 6;
+
+// shape and wireframe meshes of the surface, or of MIXED_SURFACES surface types spread
+// over all of them
+fn pack_surfaces(ps: &mut sd::IParametricSurface, mixed: bool) -> [IPackedMeshes<Vertex>; 2] {
+    let current = ps.surface_type;
+    let count = if mixed { MIXED_SURFACES } else { 1 };
+    let step = sd::PARAMETRIC_SURFACE_COUNT / MIXED_SURFACES;
+    let mut packs = [IPackedMeshes::default(), IPackedMeshes::default()];
+    for k in 0..count {
+        ps.surface_type = (current + k * step) % sd::PARAMETRIC_SURFACE_COUNT;
+        let (vertices, vertices2, indices, indices2) = create_vertices(ps.new());
+        packs[0].push(&vertices, &indices);
+        packs[1].push(&vertices2, &indices2);
+    }
+    ps.surface_type = current;
+    packs
+}
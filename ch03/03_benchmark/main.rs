@@ -0,0 +1,37 @@
+use wgpu_surfaces::app;
+use wgpu_surfaces::benchmark::{self, IBenchmark};
+use wgpu_surfaces::cli::SurfaceArgs;
+use wgpu_surfaces::surface_data as sd;
+
+// renders the same headless workload on every available adapter and prints the
+// timings side by side; positional arguments: frames width height
+fn main() {
+    let args = SurfaceArgs::default()
+        .surface_types(sd::PARAMETRIC_SURFACE_COUNT)
+        .parse_env("[frames [width [height]]]");
+    let mut bench = IBenchmark::default();
+    bench.resolution = args.resolution.unwrap_or(bench.resolution);
+    bench.surface_type = args.surface_type.unwrap_or(bench.surface_type);
+    let sizes = [&mut bench.frames, &mut bench.width, &mut bench.height];
+    for (value, arg) in sizes.into_iter().zip(args.positional.iter()) {
+        match arg.parse::<u32>() {
+            Ok(v) if v > 0 => *value = v,
+            _ => {
+                eprintln!("error: expected a positive number, not '{}'", arg);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    app::init_logger();
+    println!(
+        "rendering {} frames of {} x {} on each adapter...",
+        bench.frames, bench.width, bench.height
+    );
+    let results = bench.run();
+    if results.is_empty() {
+        println!("no adapters found");
+        return;
+    }
+    print!("{}", benchmark::results_table(&results));
+}
@@ -1,3 +1,4 @@
+use wgpu_surfaces::color;
 use wgpu_surfaces::surface_data as sd;
 
 #[repr(C)]
@@ -8,6 +9,7 @@ pub struct Vertex {
     pub color: [f32; 3],
 }
 
+// vertex colors are linear, converted from the display colors of the surface
 pub fn create_vertices(
     ss_data: sd::ISurfaceOutput,
 ) -> (Vec<Vertex>, Vec<Vertex>, Vec<u16>, Vec<u16>) {
@@ -17,12 +19,12 @@ pub fn create_vertices(
         data.push(Vertex {
             position: ss_data.positions[i],
             normal: ss_data.normals[i],
-            color: ss_data.colors[i],
+            color: color::to_linear(ss_data.colors[i]),
         });
         data2.push(Vertex {
             position: ss_data.positions[i],
             normal: ss_data.normals[i],
-            color: ss_data.colors2[i],
+            color: color::to_linear(ss_data.colors2[i]),
         });
     }
     (
@@ -0,0 +1,441 @@
+use super::surface_data::{IParametricSurface, ISimpleSurface, MAX_STREAMING_SIDE};
+use std::f32::consts::PI;
+use std::sync::{Arc, Mutex, MutexGuard};
+use web_time::Instant;
+
+// region: easing
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Easing {
+    Step,
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    Sine,
+}
+
+impl Easing {
+    // maps a normalized segment time s in [0, 1] to an eased blend factor in [0, 1]
+    pub fn ease(&self, s: f32) -> f32 {
+        let s = s.clamp(0.0, 1.0);
+        match self {
+            Easing::Step => {
+                if s < 1.0 {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+            Easing::Linear => s,
+            Easing::EaseIn => s * s * s,
+            Easing::EaseOut => 1.0 - (1.0 - s).powi(3),
+            Easing::EaseInOut => s * s * (3.0 - 2.0 * s),
+            Easing::Sine => 0.5 - 0.5 * (PI * s).cos(),
+        }
+    }
+}
+// endregion: easing
+
+// region: animation curve
+#[derive(Clone, Copy, Debug)]
+pub struct Keyframe {
+    pub time: f32,
+    pub value: f32,
+    pub easing: Easing, // easing of the segment that ends at this keyframe
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AnimationCurve {
+    pub keyframes: Vec<Keyframe>,
+    pub looping: bool,
+}
+
+impl AnimationCurve {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // adds a keyframe, keeping the keyframes sorted by time
+    pub fn key(mut self, time: f32, value: f32, easing: Easing) -> Self {
+        let idx = self.keyframes.partition_point(|k| k.time <= time);
+        self.keyframes.insert(
+            idx,
+            Keyframe {
+                time,
+                value,
+                easing,
+            },
+        );
+        self
+    }
+
+    pub fn looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |k| k.time)
+    }
+
+    pub fn sample(&self, mut t: f32) -> f32 {
+        let (first, last) = match (self.keyframes.first(), self.keyframes.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return 0.0,
+        };
+
+        let duration = last.time - first.time;
+        if self.looping && duration > 0.0 {
+            t = first.time + (t - first.time).rem_euclid(duration);
+        }
+        if t <= first.time {
+            return first.value;
+        }
+        if t >= last.time {
+            return last.value;
+        }
+
+        let idx = self.keyframes.partition_point(|k| k.time <= t);
+        let (k0, k1) = (&self.keyframes[idx - 1], &self.keyframes[idx]);
+        let s = (t - k0.time) / (k1.time - k0.time);
+        k0.value + (k1.value - k0.value) * k1.easing.ease(s)
+    }
+}
+// endregion: animation curve
+
+// region: surface fields
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SurfaceField {
+    Scale,
+    UResolution, // x_resolution for simple surfaces
+    VResolution, // z_resolution for simple surfaces
+    T,
+    ColormapMin,
+    ColormapMax,
+    TubeRadius,  // parametric torus knot and klein bagel only
+    Deformation, // parametric catenoid-helicoid only
+}
+
+pub trait Animatable {
+    // sets a numeric field and returns true if the vertex count changed, i.e. buffers
+    // have to be recreated instead of just rewritten
+    fn set_field(&mut self, field: SurfaceField, value: f32) -> bool;
+}
+
+// a resolution of n has n + 1 vertices per side, so the largest one keeps the grid
+// within the u16 indices like a streaming surface of MAX_STREAMING_SIDE
+const MAX_RESOLUTION: u16 = MAX_STREAMING_SIDE - 1;
+
+fn to_resolution(value: f32) -> u16 {
+    value.round().clamp(2.0, MAX_RESOLUTION as f32) as u16
+}
+
+fn set_colormap_range(range: &mut Option<[f32; 2]>, idx: usize, value: f32) {
+    let mut r = range.unwrap_or([-1.0, 1.0]);
+    r[idx] = value;
+    *range = Some(r);
+}
+
+impl Animatable for IParametricSurface {
+    fn set_field(&mut self, field: SurfaceField, value: f32) -> bool {
+        match field {
+            SurfaceField::Scale => self.scale = value,
+            SurfaceField::UResolution => {
+                let old = self.u_resolution;
+                self.u_resolution = to_resolution(value);
+                return old != self.u_resolution;
+            }
+            SurfaceField::VResolution => {
+                let old = self.v_resolution;
+                self.v_resolution = to_resolution(value);
+                return old != self.v_resolution;
+            }
+            SurfaceField::T => self.t = value,
+            SurfaceField::ColormapMin => set_colormap_range(&mut self.colormap_range, 0, value),
+            SurfaceField::ColormapMax => set_colormap_range(&mut self.colormap_range, 1, value),
+            SurfaceField::TubeRadius => self.tube_radius = value,
+            SurfaceField::Deformation => self.deformation = value,
+        }
+        false
+    }
+}
+
+impl Animatable for ISimpleSurface {
+    fn set_field(&mut self, field: SurfaceField, value: f32) -> bool {
+        match field {
+            SurfaceField::Scale => self.scale = value,
+            SurfaceField::UResolution => {
+                let old = self.x_resolution;
+                self.x_resolution = to_resolution(value);
+                return old != self.x_resolution;
+            }
+            SurfaceField::VResolution => {
+                let old = self.z_resolution;
+                self.z_resolution = to_resolution(value);
+                return old != self.z_resolution;
+            }
+            SurfaceField::T => self.t = value,
+            SurfaceField::ColormapMin => set_colormap_range(&mut self.colormap_range, 0, value),
+            SurfaceField::ColormapMax => set_colormap_range(&mut self.colormap_range, 1, value),
+            SurfaceField::TubeRadius | SurfaceField::Deformation => {}
+        }
+        false
+    }
+}
+// endregion: surface fields
+
+// region: timeline
+pub struct Timeline<S> {
+    #[allow(clippy::type_complexity)]
+    tracks: Vec<(AnimationCurve, Box<dyn Fn(&mut S, f32) -> bool>)>,
+    fields: Vec<SurfaceField>, // fields driven through add_field
+    pub looping: bool,
+}
+
+impl<S> Default for Timeline<S> {
+    fn default() -> Self {
+        Self {
+            tracks: vec![],
+            fields: vec![],
+            looping: false,
+        }
+    }
+}
+
+impl<S> Timeline<S> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // adds a track driving an arbitrary target value; the setter returns true if
+    // the buffers have to be recreated
+    pub fn add_track(
+        &mut self,
+        curve: AnimationCurve,
+        setter: impl Fn(&mut S, f32) -> bool + 'static,
+    ) {
+        self.tracks.push((curve, Box::new(setter)));
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.tracks
+            .iter()
+            .map(|(curve, _)| curve.duration())
+            .fold(0.0, f32::max)
+    }
+
+    // samples all tracks at the time t and writes the values into target; returns true
+    // if any track changed the vertex count of the target
+    pub fn apply(&self, target: &mut S, mut t: f32) -> bool {
+        let duration = self.duration();
+        if self.looping && duration > 0.0 {
+            t = t.rem_euclid(duration);
+        }
+        let mut recreate = false;
+        for (curve, setter) in self.tracks.iter() {
+            recreate |= setter(target, curve.sample(t));
+        }
+        recreate
+    }
+}
+
+impl<S: Animatable> Timeline<S> {
+    pub fn add_field(&mut self, field: SurfaceField, curve: AnimationCurve) {
+        self.fields.push(field);
+        self.add_track(curve, move |target, value| target.set_field(field, value));
+    }
+
+    // true if a track added with add_field drives the field, so the caller should
+    // not set it as well
+    pub fn drives(&self, field: SurfaceField) -> bool {
+        self.fields.contains(&field)
+    }
+}
+// endregion: timeline
+
+// region: frame interpolation
+// Blends between the two most recent data frames when they arrive slower than the
+// display rate. The renderer keeps the previous and the next frame in two vertex
+// buffers and mixes them with mix_factor(); the interval between frames is
+// estimated from their arrival times. A frame that arrives before the blend has
+// reached the next one starts from the blend shown, see push_frame and blend.
+#[derive(Clone, Copy, Debug)]
+pub struct FrameInterpolator {
+    pub interval: f32,  // estimated seconds between data frames
+    pub smoothing: f32, // weight of the newest gap in the interval estimate
+    last_arrival: Option<Instant>,
+}
+
+impl Default for FrameInterpolator {
+    fn default() -> Self {
+        Self {
+            interval: 0.5,
+            smoothing: 0.3,
+            last_arrival: None,
+        }
+    }
+}
+
+impl FrameInterpolator {
+    // call when a new data frame arrives; returns the mix factor shown until then,
+    // with which blend turns the old previous and next frames into the new previous one
+    pub fn push_frame(&mut self) -> f32 {
+        let shown = self.mix_factor();
+        let now = Instant::now();
+        if let Some(last) = self.last_arrival {
+            let gap = (now - last).as_secs_f32();
+            self.interval += self.smoothing * (gap - self.interval);
+        }
+        self.last_arrival = Some(now);
+        shown
+    }
+
+    // 0 shows the previous frame, 1 the next one
+    pub fn mix_factor(&self) -> f32 {
+        match self.last_arrival {
+            Some(last) if self.interval > 0.0 => {
+                (last.elapsed().as_secs_f32() / self.interval).clamp(0.0, 1.0)
+            }
+            _ => 1.0,
+        }
+    }
+
+    // overwrites previous with its mix towards next at mix factor t, e.g. the vertex
+    // data of two frames of the same size cast to f32
+    pub fn blend(previous: &mut [f32], next: &[f32], t: f32) {
+        for (p, n) in previous.iter_mut().zip(next) {
+            *p += t * (n - *p);
+        }
+    }
+}
+// endregion: frame interpolation
+
+// region: shared clock
+// Animation time shared by several windows, viewports or threads. Clones refer to
+// the same clock, so pausing, changing the speed or scrubbing through one of them
+// moves every view. time() is quantized to frame_rate steps: views whose frames are
+// slightly out of phase still read the same t for the same frame.
+#[derive(Clone, Debug, Default)]
+pub struct SharedClock {
+    inner: Arc<Mutex<ClockState>>,
+}
+
+#[derive(Debug)]
+struct ClockState {
+    anchor: Instant, // wall time at which the clock read `offset`
+    offset: f32,
+    speed: f32,
+    paused: bool,
+    frame_rate: f32, // 0 disables the quantization
+}
+
+impl Default for ClockState {
+    fn default() -> Self {
+        Self {
+            anchor: Instant::now(),
+            offset: 0.0,
+            speed: 1.0,
+            paused: false,
+            frame_rate: 60.0,
+        }
+    }
+}
+
+impl ClockState {
+    fn raw_time(&self) -> f32 {
+        if self.paused {
+            self.offset
+        } else {
+            self.offset + self.speed * self.anchor.elapsed().as_secs_f32()
+        }
+    }
+
+    // restarts the running time from the current value, before changing the rate
+    fn reanchor(&mut self) {
+        self.offset = self.raw_time();
+        self.anchor = Instant::now();
+    }
+}
+
+impl SharedClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, ClockState> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // animation time in seconds
+    pub fn time(&self) -> f32 {
+        let state = self.state();
+        let t = state.raw_time();
+        if state.frame_rate > 0.0 {
+            (t * state.frame_rate).floor() / state.frame_rate
+        } else {
+            t
+        }
+    }
+
+    // jumps to time t; playback continues from there unless paused
+    pub fn set_time(&self, t: f32) {
+        let mut state = self.state();
+        state.offset = t;
+        state.anchor = Instant::now();
+    }
+
+    // moves the time by dt, e.g. from arrow keys
+    pub fn seek(&self, dt: f32) {
+        let mut state = self.state();
+        state.reanchor();
+        state.offset += dt;
+    }
+
+    pub fn pause(&self) {
+        let mut state = self.state();
+        state.reanchor();
+        state.paused = true;
+    }
+
+    pub fn play(&self) {
+        let mut state = self.state();
+        state.anchor = Instant::now();
+        state.paused = false;
+    }
+
+    // returns true if the clock is paused afterwards
+    pub fn toggle(&self) -> bool {
+        if self.is_paused() {
+            self.play();
+            false
+        } else {
+            self.pause();
+            true
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state().paused
+    }
+
+    // animation seconds per wall clock second; negative values run backwards
+    pub fn set_speed(&self, speed: f32) {
+        let mut state = self.state();
+        state.reanchor();
+        state.speed = speed;
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.state().speed
+    }
+
+    pub fn set_frame_rate(&self, frame_rate: f32) {
+        self.state().frame_rate = frame_rate.max(0.0);
+    }
+
+    // true if both refer to the same clock
+    pub fn ptr_eq(&self, other: &SharedClock) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+// endregion: shared clock
@@ -0,0 +1,345 @@
+use super::capabilities;
+use super::error::{Error, Result};
+use super::surface_data::{self as sd, ISurfaceOutput};
+use super::wgpu_simplified as ws;
+
+pub use super::capabilities::Subsystem;
+pub use super::surface_data::{NormalMode, ShadingMode};
+
+// region: stable api
+// The curated part of the crate that follows semver, re-exported at its root:
+// surfaces are configured through builder methods, meshes and GPU contexts are read
+// through getters, and the option structs are #[non_exhaustive], so fields and
+// options can be added in minor releases. Its types and methods take and return no
+// wgpu types, which change with every wgpu release. The modules it is built on
+// (surface_data, wgpu_simplified, ...) change with the examples and are public only
+// with the low_level feature, on by default; turn default features off to build
+// against this layer alone:
+//
+//     let mesh = wgpu_surfaces::ParametricSurface::new("torus")?.resolution(48, 32).build();
+//     println!("{} triangles", mesh.triangle_count());
+
+// colors and look of a generated surface; build it from Default and set fields
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct SurfaceStyle {
+    pub colormap: String,        // any registered colormap name
+    pub wireframe_color: String, // a colormap name or a named color
+    pub scale: f32,
+    pub shading: ShadingMode,
+}
+
+impl Default for SurfaceStyle {
+    fn default() -> Self {
+        Self {
+            colormap: "jet".to_string(),
+            wireframe_color: "white".to_string(),
+            scale: 1.0,
+            shading: ShadingMode::Smooth,
+        }
+    }
+}
+
+// a generated triangle mesh with the wireframe lines over it; every vertex has a
+// position, a normal, a surface color, a wireframe color and a uv
+#[derive(Clone, Default)]
+pub struct Mesh {
+    output: ISurfaceOutput,
+}
+
+impl Mesh {
+    pub fn positions(&self) -> &[[f32; 3]] {
+        &self.output.positions
+    }
+
+    pub fn normals(&self) -> &[[f32; 3]] {
+        &self.output.normals
+    }
+
+    pub fn colors(&self) -> &[[f32; 3]] {
+        &self.output.colors
+    }
+
+    pub fn wireframe_colors(&self) -> &[[f32; 3]] {
+        &self.output.colors2
+    }
+
+    pub fn uvs(&self) -> &[[f32; 2]] {
+        &self.output.uvs
+    }
+
+    // triangle list
+    pub fn indices(&self) -> &[u16] {
+        &self.output.indices
+    }
+
+    // line list
+    pub fn wireframe_indices(&self) -> &[u16] {
+        &self.output.indices2
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.output.positions.len()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.output.indices.len() / 3
+    }
+
+    // see ISurfaceOutput::shade; false if a flat mesh would not fit the u16 indices
+    pub fn shade(&mut self, mode: ShadingMode) -> bool {
+        self.output.shade(mode)
+    }
+
+    #[cfg(feature = "low_level")]
+    pub fn output(&self) -> &ISurfaceOutput {
+        &self.output
+    }
+
+    #[cfg(feature = "low_level")]
+    pub fn into_output(self) -> ISurfaceOutput {
+        self.output
+    }
+}
+
+impl From<ISurfaceOutput> for Mesh {
+    fn from(output: ISurfaceOutput) -> Self {
+        Self { output }
+    }
+}
+
+// height field y = f(x, z, t) over a rectangle
+pub struct SimpleSurface {
+    surface: sd::ISimpleSurface,
+}
+
+// the built-in simple surface types by name, see surface_data::simple_surface_fn
+pub const SIMPLE_SURFACE_NAMES: [&str; 3] = ["sinc", "poles", "peaks"];
+
+impl SimpleSurface {
+    // one of SIMPLE_SURFACE_NAMES
+    pub fn new(name: &str) -> Result<Self> {
+        let surface_type = SIMPLE_SURFACE_NAMES
+            .iter()
+            .position(|n| n.eq_ignore_ascii_case(name))
+            .ok_or_else(|| Error::UnknownSurface(name.to_string()))?;
+        Ok(Self {
+            surface: sd::ISimpleSurface {
+                surface_type: surface_type as u32,
+                ..Default::default()
+            },
+        })
+    }
+
+    // a user function over [-1, 1] x [-1, 1] unless domain() says otherwise
+    pub fn from_fn(f: impl Fn(f32, f32, f32) -> f32 + Send + Sync + 'static) -> Self {
+        Self {
+            surface: sd::ISimpleSurface::from_fn(f),
+        }
+    }
+
+    // [xmin, xmax, zmin, zmax], for user functions
+    pub fn domain(mut self, domain: [f32; 4]) -> Self {
+        self.surface = self.surface.domain(domain);
+        self
+    }
+
+    // grid cells along x and z
+    pub fn resolution(mut self, nx: u16, nz: u16) -> Self {
+        (self.surface.x_resolution, self.surface.z_resolution) = (nx.max(1), nz.max(1));
+        self
+    }
+
+    pub fn style(mut self, style: &SurfaceStyle) -> Self {
+        self.surface.colormap_name = style.colormap.clone();
+        self.surface.wireframe_color = style.wireframe_color.clone();
+        self.surface.scale = style.scale;
+        self.surface.shading = style.shading;
+        self
+    }
+
+    // the animation time t passed to the function
+    pub fn set_time(&mut self, t: f32) {
+        self.surface.t = t;
+    }
+
+    pub fn time(&self) -> f32 {
+        self.surface.t
+    }
+
+    pub fn build(&mut self) -> Mesh {
+        self.surface.new().into()
+    }
+
+    #[cfg(feature = "low_level")]
+    pub fn surface(&mut self) -> &mut sd::ISimpleSurface {
+        &mut self.surface
+    }
+}
+
+// parametric surface [x, y, z] = f(u, v)
+pub struct ParametricSurface {
+    surface: sd::IParametricSurface,
+}
+
+impl ParametricSurface {
+    // a built-in surface type by name, like "klein_bottle" or "torus"; see
+    // parametric_surface_names()
+    pub fn new(name: &str) -> Result<Self> {
+        let surface_type = (0..sd::PARAMETRIC_SURFACE_COUNT)
+            .find(|&t| sd::get_surface_type(t).eq_ignore_ascii_case(name))
+            .ok_or_else(|| Error::UnknownSurface(name.to_string()))?;
+        Ok(Self {
+            surface: sd::IParametricSurface {
+                surface_type,
+                ..Default::default()
+            },
+        })
+    }
+
+    // a user function [x, y, z] = f(u, v, t) over [-1, 1] x [-1, 1] unless domain()
+    // says otherwise
+    pub fn from_fn(f: impl Fn(f32, f32, f32) -> [f32; 3] + Send + Sync + 'static) -> Self {
+        Self {
+            surface: sd::IParametricSurface::from_fn(f),
+        }
+    }
+
+    // [umin, umax, vmin, vmax], for user functions
+    pub fn domain(mut self, domain: [f32; 4]) -> Self {
+        self.surface = self.surface.domain(domain);
+        self
+    }
+
+    // grid cells along u and v
+    pub fn resolution(mut self, nu: u16, nv: u16) -> Self {
+        (self.surface.u_resolution, self.surface.v_resolution) = (nu.max(1), nv.max(1));
+        self
+    }
+
+    pub fn style(mut self, style: &SurfaceStyle) -> Self {
+        self.surface.colormap_name = style.colormap.clone();
+        self.surface.wireframe_color = style.wireframe_color.clone();
+        self.surface.scale = style.scale;
+        self.surface.shading = style.shading;
+        self
+    }
+
+    pub fn normal_mode(mut self, mode: NormalMode) -> Self {
+        self.surface.normal_mode = mode;
+        self
+    }
+
+    // the animation time t passed to a user function
+    pub fn set_time(&mut self, t: f32) {
+        self.surface.t = t;
+    }
+
+    pub fn time(&self) -> f32 {
+        self.surface.t
+    }
+
+    pub fn build(&mut self) -> Mesh {
+        self.surface.new().into()
+    }
+
+    #[cfg(feature = "low_level")]
+    pub fn surface(&mut self) -> &mut sd::IParametricSurface {
+        &mut self.surface
+    }
+}
+
+// the names ParametricSurface::new() accepts
+pub fn parametric_surface_names() -> Vec<String> {
+    (0..sd::PARAMETRIC_SURFACE_COUNT)
+        .map(sd::get_surface_type)
+        .collect()
+}
+
+// an offscreen GPU context: device, queue and a color target to render into
+pub struct Gpu {
+    init: ws::InitWgpuHeadless,
+}
+
+impl Gpu {
+    // sample_count is clamped to what the device supports
+    pub async fn headless(width: u32, height: u32, sample_count: u32) -> Result<Self> {
+        let init = ws::InitWgpuHeadless::init_wgpu(width, height, sample_count).await?;
+        Ok(Self { init })
+    }
+
+    pub fn size(&self) -> [u32; 2] {
+        [self.init.width, self.init.height]
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.init.sample_count
+    }
+
+    pub fn is_enabled(&self, subsystem: Subsystem) -> bool {
+        self.init.capabilities.is_enabled(subsystem)
+    }
+
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            inner: self.init.capabilities.clone(),
+        }
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.init.resize(width, height);
+    }
+
+    // RGBA8 pixels of the color target, after the frame was submitted
+    pub fn read_rgba(&self) -> anyhow::Result<Vec<u8>> {
+        self.init.read_rgba()
+    }
+
+    // the device, queue and target are wgpu types and follow wgpu's releases, so
+    // they are reached through the low level context
+    #[cfg(feature = "low_level")]
+    pub fn init(&self) -> &ws::InitWgpuHeadless {
+        &self.init
+    }
+}
+
+// the optional subsystems the device of a Gpu runs, see capabilities::Capabilities
+#[derive(Clone, Debug, PartialEq)]
+pub struct Capabilities {
+    inner: capabilities::Capabilities,
+}
+
+impl Capabilities {
+    pub fn is_enabled(&self, subsystem: Subsystem) -> bool {
+        self.inner.is_enabled(subsystem)
+    }
+
+    // Error::Unsupported with what is missing when the subsystem is disabled
+    pub fn require(&self, subsystem: Subsystem) -> Result<()> {
+        self.inner.require(subsystem)
+    }
+
+    pub fn disabled(&self) -> Vec<Subsystem> {
+        self.inner.disabled.iter().map(|d| d.subsystem).collect()
+    }
+
+    // the names of the features and downlevel flags the adapter lacks for the
+    // subsystem, like "TIMESTAMP_QUERY"; empty when it is enabled
+    pub fn missing(&self, subsystem: Subsystem) -> Vec<&'static str> {
+        match self.inner.require(subsystem) {
+            Err(Error::Unsupported(disabled)) => disabled.missing(),
+            _ => vec![],
+        }
+    }
+
+    // one line per disabled subsystem
+    pub fn report(&self) -> String {
+        self.inner.report()
+    }
+
+    #[cfg(feature = "low_level")]
+    pub fn inner(&self) -> &capabilities::Capabilities {
+        &self.inner
+    }
+}
+// endregion: stable api
@@ -0,0 +1,948 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use web_time::Instant;
+
+use winit::{
+    application::ApplicationHandler,
+    dpi::PhysicalSize,
+    event::{ElementState, KeyEvent, WindowEvent},
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    keyboard::{Key, KeyCode, NamedKey, PhysicalKey},
+    monitor::MonitorHandle,
+    window::{Window, WindowId},
+};
+
+use bytemuck::cast_slice;
+
+use super::appearance::{DirectionalLight, LIGHT_UNIFORM_SIZE};
+use super::cli::SurfaceArgs;
+use super::error::{Error, KeymapError, Result};
+use super::material::MATERIAL_UNIFORM_SIZE;
+use super::recovery;
+use super::scene::Scene;
+use super::wgpu_simplified::{self as ws, FramePacer, SurfaceChange};
+#[cfg(target_arch = "wasm32")]
+use winit::event_loop::EventLoopProxy;
+
+// region: surface app
+// What an example state implements to run in a window: creation, input, update and
+// render; new() fails if the GPU setup does, and the driver then closes with the
+// error. In the browser the window is a canvas appended to the page, and new() runs
+// on the page's executor instead of blocking. The provided resize() reconfigures the
+// surface and then calls resized() for the views and matrices that depend on the
+// window size. run_app() drives any SurfaceApp, with escape or closing the window to
+// quit and PRESENT_MODE_KEY to cycle the present modes, e.g. to see the frame rate
+// without vsync:
+//
+//     let _ = app::run_app::<State>(&args, "ch02 simple surface");
+pub const PRESENT_MODE_KEY: &str = "y";
+
+pub trait SurfaceApp: Sized + 'static {
+    fn new(window: Arc<Window>, args: &SurfaceArgs) -> impl Future<Output = Result<Self>>;
+
+    fn init(&self) -> &ws::InitWgpu;
+
+    fn init_mut(&mut self) -> &mut ws::InitWgpu;
+
+    // returns true if the event was used, so the driver ignores it
+    fn input(&mut self, event: &WindowEvent) -> bool;
+
+    // dt is the time since rendering started
+    fn update(&mut self, dt: Duration);
+
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError>;
+
+    // called after the surface takes a new, non-zero size
+    fn resized(&mut self, new_size: PhysicalSize<u32>);
+
+    // the scene the state shows, recorded each frame for the recovery file while
+    // --recovery-file is given and restored when the driver recreates the state;
+    // None if the state has no scene to save
+    fn snapshot(&self) -> Option<Scene> {
+        None
+    }
+
+    // called after the surface was reconfigured for new capabilities, e.g. on another
+    // monitor: recreates the MSAA and depth views through resized(). Returns false if
+    // the change needs new pipelines, and the driver then creates the state anew from
+    // its snapshot().
+    fn surface_changed(&mut self, change: SurfaceChange) -> bool {
+        if change.needs_new_pipelines() {
+            return false;
+        }
+        self.resized(self.size());
+        true
+    }
+
+    fn window(&self) -> &Window {
+        &self.init().window
+    }
+
+    fn size(&self) -> PhysicalSize<u32> {
+        self.init().size
+    }
+
+    fn resize(&mut self, new_size: PhysicalSize<u32>) {
+        if new_size.width == 0 || new_size.height == 0 {
+            return;
+        }
+        let init = self.init_mut();
+        init.size = new_size;
+        // the surface needs to be reconfigured every time the window is resized
+        init.config.width = new_size.width;
+        init.config.height = new_size.height;
+        init.surface.configure(&init.device, &init.config);
+        self.resized(new_size);
+    }
+}
+
+// color attachment for the surface view, resolved from msaa_texture_view when the
+// target is multisampled, with the given depth attachment: the main pass of the
+// examples
+pub fn begin_main_pass<'a>(
+    encoder: &'a mut wgpu::CommandEncoder,
+    init: &impl ws::RenderTarget,
+    view: &wgpu::TextureView,
+    msaa_texture_view: &wgpu::TextureView,
+    depth_attachment: wgpu::RenderPassDepthStencilAttachment,
+) -> wgpu::RenderPass<'a> {
+    begin_timed_main_pass(encoder, init, view, msaa_texture_view, depth_attachment, None)
+}
+
+// the main pass timed by a ws::GpuTimer, with its timestamp_writes()
+pub fn begin_timed_main_pass<'a>(
+    encoder: &'a mut wgpu::CommandEncoder,
+    init: &impl ws::RenderTarget,
+    view: &wgpu::TextureView,
+    msaa_texture_view: &wgpu::TextureView,
+    depth_attachment: wgpu::RenderPassDepthStencilAttachment,
+    timestamp_writes: Option<wgpu::RenderPassTimestampWrites>,
+) -> wgpu::RenderPass<'a> {
+    let color_attachment = if init.sample_count() == 1 {
+        ws::create_color_attachment(view)
+    } else {
+        ws::create_msaa_color_attachment(view, msaa_texture_view)
+    };
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Render Pass"),
+        color_attachments: &[Some(color_attachment)],
+        depth_stencil_attachment: Some(depth_attachment),
+        occlusion_query_set: None,
+        timestamp_writes,
+    })
+}
+// endregion: surface app
+
+// region: surface setup
+// The GPU setup the example states share, so a state only supplies its shaders, vertex
+// layout and vertex uniforms. SurfacePipelines holds the shape (triangle list) and
+// wireframe (line list) pipelines over one layout, with the vertex uniforms bound at
+// group 0 and LightUniforms, the light and material of directional_frag.wgsl, at
+// group 1. RenderTargets holds the MSAA and depth views of the main pass:
+//
+//     let lighting = app::LightUniforms::new(&init, &DirectionalLight::default(), material);
+//     let vert = ws::create_bind_group(&init.device, vec![VERTEX], &[uniforms])?;
+//     let pipelines = app::SurfacePipelines::new(&init, &vs, &fs, &layout, vert,
+//         lighting.bind_group(&init.device)?)?;
+//     let targets = app::RenderTargets::new(&init);
+pub struct RenderTargets {
+    pub msaa_texture_view: wgpu::TextureView, // unused with a sample count of 1
+    pub depth_texture_view: wgpu::TextureView,
+    bindable_depth: bool,
+}
+
+impl RenderTargets {
+    pub fn new(init: &impl ws::RenderTarget) -> Self {
+        Self::with_depth(init, false)
+    }
+
+    // with bindable_depth the depth view can also be sampled, see
+    // ws::create_depth_texture_view
+    pub fn with_depth(init: &impl ws::RenderTarget, bindable_depth: bool) -> Self {
+        Self {
+            msaa_texture_view: ws::create_msaa_texture_view(init),
+            depth_texture_view: ws::create_depth_texture_view(init, bindable_depth),
+            bindable_depth,
+        }
+    }
+
+    // called from SurfaceApp::resized() for the new surface size
+    pub fn resize(&mut self, init: &impl ws::RenderTarget) {
+        self.depth_texture_view = ws::create_depth_texture_view(init, self.bindable_depth);
+        if init.sample_count() > 1 {
+            self.msaa_texture_view = ws::create_msaa_texture_view(init);
+        }
+    }
+
+    // the main pass into view with these targets, see begin_main_pass(); a bindable
+    // depth is stored for the passes that sample it
+    pub fn begin_pass<'a>(
+        &self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        init: &impl ws::RenderTarget,
+        view: &wgpu::TextureView,
+    ) -> wgpu::RenderPass<'a> {
+        let depth_attachment = if self.bindable_depth {
+            ws::create_depth_stencil_attachment_store(
+                &self.depth_texture_view,
+                wgpu::StoreOp::Store,
+            )
+        } else {
+            ws::create_depth_stencil_attachment(&self.depth_texture_view)
+        };
+        begin_main_pass(encoder, init, view, &self.msaa_texture_view, depth_attachment)
+    }
+}
+
+pub struct LightUniforms {
+    pub light_buffer: wgpu::Buffer,
+    pub material_buffer: wgpu::Buffer, // ambient, diffuse, specular, shininess
+}
+
+impl LightUniforms {
+    pub fn new(init: &ws::InitWgpu, light: &DirectionalLight, material: [f32; 4]) -> Self {
+        let uniform_buffer = |label, size| {
+            init.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+        let uniforms = Self {
+            light_buffer: uniform_buffer("Light Uniform Buffer", LIGHT_UNIFORM_SIZE),
+            material_buffer: uniform_buffer("Material Uniform Buffer", MATERIAL_UNIFORM_SIZE),
+        };
+        uniforms.set_light(&init.queue, light);
+        init.queue
+            .write_buffer(&uniforms.material_buffer, 0, cast_slice(&material));
+        uniforms
+    }
+
+    pub fn set_light(&self, queue: &wgpu::Queue, light: &DirectionalLight) {
+        queue.write_buffer(&self.light_buffer, 0, cast_slice(&light.uniform_data()));
+    }
+
+    // the specular highlights follow the camera
+    pub fn set_eye_position(&self, queue: &wgpu::Queue, eye_position: [f32; 3]) {
+        queue.write_buffer(&self.light_buffer, 16, cast_slice(&eye_position));
+    }
+
+    pub fn bind_group(
+        &self,
+        device: &wgpu::Device,
+    ) -> Result<(wgpu::BindGroupLayout, wgpu::BindGroup)> {
+        ws::create_bind_group(
+            device,
+            vec![wgpu::ShaderStages::FRAGMENT, wgpu::ShaderStages::FRAGMENT],
+            &[
+                self.light_buffer.as_entire_binding(),
+                self.material_buffer.as_entire_binding(),
+            ],
+        )
+    }
+}
+
+pub struct SurfacePipelines {
+    pub shape: wgpu::RenderPipeline,
+    pub wireframe: wgpu::RenderPipeline,
+    pub layout: wgpu::PipelineLayout, // for other pipelines over the same bind groups
+    pub bind_group_layouts: [wgpu::BindGroupLayout; 2],
+    pub bind_groups: [wgpu::BindGroup; 2], // vertex, fragment
+}
+
+impl SurfacePipelines {
+    pub fn new(
+        init: &impl ws::RenderTarget,
+        vs_shader: &wgpu::ShaderModule,
+        fs_shader: &wgpu::ShaderModule,
+        vertex_buffer_layout: &[wgpu::VertexBufferLayout],
+        (vert_layout, vert_group): (wgpu::BindGroupLayout, wgpu::BindGroup),
+        (frag_layout, frag_group): (wgpu::BindGroupLayout, wgpu::BindGroup),
+    ) -> Result<Self> {
+        let layout = init
+            .device()
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &[&vert_layout, &frag_layout],
+                push_constant_ranges: &[],
+            });
+        let [shape, wireframe] = [
+            wgpu::PrimitiveTopology::TriangleList,
+            wgpu::PrimitiveTopology::LineList,
+        ]
+        .map(|topology| {
+            ws::IRenderPipeline {
+                topology,
+                vs_shader: Some(vs_shader),
+                fs_shader: Some(fs_shader),
+                pipeline_layout: Some(&layout),
+                vertex_buffer_layout,
+                ..Default::default()
+            }
+            .new(init)
+        });
+        Ok(Self {
+            shape: shape?,
+            wireframe: wireframe?,
+            layout,
+            bind_group_layouts: [vert_layout, frag_layout],
+            bind_groups: [vert_group, frag_group],
+        })
+    }
+
+    // sets the shape or wireframe pipeline and both bind groups
+    pub fn bind(&self, render_pass: &mut wgpu::RenderPass, wireframe: bool) {
+        render_pass.set_pipeline(if wireframe { &self.wireframe } else { &self.shape });
+        self.bind_groups(render_pass);
+    }
+
+    // sets only the bind groups, for a pipeline built on layout
+    pub fn bind_groups(&self, render_pass: &mut wgpu::RenderPass) {
+        render_pass.set_bind_group(0, &self.bind_groups[0], &[]);
+        render_pass.set_bind_group(1, &self.bind_groups[1], &[]);
+    }
+}
+// endregion: surface setup
+
+// region: input map
+// Logical actions of the example windows and the keys that trigger them. Each state
+// builds its InputMap from its default bindings, which --keymap then overrides from a
+// TOML file of action names to a key or a list of keys, so the same action can be on
+// the same key in every example:
+//
+//     cycle_plot_type = "Space"
+//     increase_rotation_speed = ["r", "PageUp"]
+//     decrease_rotation_speed = ["f", "PageDown"]
+//
+// Keys are single characters or the names of parse_key(). Input handlers match on
+// the action of a pressed key:
+//
+//     match self.input_map.action(key) {
+//         Some(Action::CyclePlotType) => { .. }
+//         _ => false,
+//     }
+macro_rules! actions {
+    ($($(#[$attr:meta])* $variant:ident => $name:literal,)*) => {
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub enum Action {
+            $($(#[$attr])* $variant,)*
+        }
+
+        impl Action {
+            pub const ALL: [Action; [$($name),*].len()] = [$(Action::$variant),*];
+
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Action::$variant => $name,)*
+                }
+            }
+        }
+    };
+}
+
+actions! {
+    // shared by most examples
+    CyclePlotType => "cycle_plot_type",
+    NextSurfaceType => "next_surface_type",
+    CycleColormapDirection => "cycle_colormap_direction",
+    ToggleRandomShape => "toggle_random_shape",
+    // u is x of the simple surfaces and the rows of the PDE grid, v is z
+    IncreaseUResolution => "increase_u_resolution",
+    DecreaseUResolution => "decrease_u_resolution",
+    IncreaseVResolution => "increase_v_resolution",
+    DecreaseVResolution => "decrease_v_resolution",
+    IncreaseAnimationSpeed => "increase_animation_speed",
+    DecreaseAnimationSpeed => "decrease_animation_speed",
+    IncreaseRotationSpeed => "increase_rotation_speed",
+    DecreaseRotationSpeed => "decrease_rotation_speed",
+    // parameter sliders
+    SelectPreviousParam => "select_previous_param",
+    SelectNextParam => "select_next_param",
+    DecreaseParam => "decrease_param",
+    IncreaseParam => "increase_param",
+    TurnLightLeft => "turn_light_left",
+    TurnLightRight => "turn_light_right",
+    ToggleMorph => "toggle_morph",
+    ExportMesh => "export_mesh",
+    PrintMetrics => "print_metrics",
+    CyclePresentMode => "cycle_present_mode",
+    SaveScene => "save_scene",
+    LoadScene => "load_scene",
+    // simple surface
+    ToggleMaterialSliders => "toggle_material_sliders",
+    CycleColorField => "cycle_color_field",
+    ToggleGpuSurface => "toggle_gpu_surface",
+    ToggleVertexDisplacement => "toggle_vertex_displacement",
+    ToggleHeat => "toggle_heat",
+    CycleLights => "cycle_lights",
+    CycleGizmo => "cycle_gizmo",
+    ToggleSnapping => "toggle_snapping",
+    ToggleGpuTimer => "toggle_gpu_timer",
+    ToggleStats => "toggle_stats",
+    ToggleRecording => "toggle_recording",
+    ToggleProbe => "toggle_probe",
+    // multiple simple surfaces
+    ToggleCulling => "toggle_culling",
+    ToggleLod => "toggle_lod",
+    // pde surface
+    Disturb => "disturb",
+    ResetSimulation => "reset_simulation",
+    CycleDataMode => "cycle_data_mode",
+    ToggleReference => "toggle_reference",
+    // tiled surface, whose animation speed is the flight speed
+    PrintTiles => "print_tiles",
+    RaiseAltitude => "raise_altitude",
+    LowerAltitude => "lower_altitude",
+    // geo terrain
+    CycleProjection => "cycle_projection",
+    IncreaseExaggeration => "increase_exaggeration",
+    DecreaseExaggeration => "decrease_exaggeration",
+    ToggleNormalMap => "toggle_normal_map",
+    ToggleMicroBumps => "toggle_micro_bumps",
+    CycleWireframeStyle => "cycle_wireframe_style",
+    // shadow surface
+    ToggleShadow => "toggle_shadow",
+    ToggleShadowCatcher => "toggle_shadow_catcher",
+    CycleShadowFilter => "cycle_shadow_filter",
+    // telemetry surface
+    TogglePause => "toggle_pause",
+    IncreaseDataRate => "increase_data_rate",
+    DecreaseDataRate => "decrease_data_rate",
+    ToggleScrollAxis => "toggle_scroll_axis",
+    // parametric surface
+    ExportReport => "export_report",
+    ToggleOpacity => "toggle_opacity",
+    NextKnotP => "next_knot_p",
+    NextKnotQ => "next_knot_q",
+    IncreaseTubeRadius => "increase_tube_radius",
+    DecreaseTubeRadius => "decrease_tube_radius",
+    CycleImplicitSurface => "cycle_implicit_surface",
+    CycleNormalOrientation => "cycle_normal_orientation",
+    CycleNormalMode => "cycle_normal_mode",
+    CycleShading => "cycle_shading",
+    ToggleInset => "toggle_inset",
+    ToggleCloseSeams => "toggle_close_seams",
+    ToggleUnfold => "toggle_unfold",
+    ToggleCrossFade => "toggle_cross_fade",
+    // multiple parametric surfaces
+    ToggleDepthView => "toggle_depth_view",
+    ToggleMixed => "toggle_mixed",
+    ToggleAutoNearFar => "toggle_auto_near_far",
+}
+
+impl Action {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|action| action.name() == name)
+    }
+}
+
+const NAMED_KEYS: [(&str, NamedKey); 26] = [
+    ("Space", NamedKey::Space),
+    ("Control", NamedKey::Control),
+    ("Shift", NamedKey::Shift),
+    ("Alt", NamedKey::Alt),
+    ("Enter", NamedKey::Enter),
+    ("Tab", NamedKey::Tab),
+    ("Backspace", NamedKey::Backspace),
+    ("Delete", NamedKey::Delete),
+    ("Insert", NamedKey::Insert),
+    ("Home", NamedKey::Home),
+    ("End", NamedKey::End),
+    ("PageUp", NamedKey::PageUp),
+    ("PageDown", NamedKey::PageDown),
+    ("ArrowUp", NamedKey::ArrowUp),
+    ("ArrowDown", NamedKey::ArrowDown),
+    ("ArrowLeft", NamedKey::ArrowLeft),
+    ("ArrowRight", NamedKey::ArrowRight),
+    ("F1", NamedKey::F1),
+    ("F2", NamedKey::F2),
+    ("F3", NamedKey::F3),
+    ("F4", NamedKey::F4),
+    ("F5", NamedKey::F5),
+    ("F6", NamedKey::F6),
+    ("F7", NamedKey::F7),
+    ("F8", NamedKey::F8),
+    ("F9", NamedKey::F9),
+];
+
+// a single character, or one of Space, Control, Shift, Alt, Enter, Tab, Backspace,
+// Delete, Insert, Home, End, PageUp, PageDown, ArrowUp, ArrowDown, ArrowLeft,
+// ArrowRight and F1 to F9; escape stays the key that quits
+pub fn parse_key(name: &str) -> Option<Key> {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(_), None) => Some(Key::Character(name.into())),
+        _ => NAMED_KEYS
+            .iter()
+            .find(|(key_name, _)| *key_name == name)
+            .map(|&(_, key)| Key::Named(key)),
+    }
+}
+
+pub fn key_name(key: &Key) -> String {
+    match key {
+        Key::Character(c) => c.to_string(),
+        Key::Named(named) => NAMED_KEYS
+            .iter()
+            .find(|(_, key)| key == named)
+            .map_or_else(|| format!("{:?}", named), |(name, _)| name.to_string()),
+        _ => format!("{:?}", key),
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InputMap {
+    bindings: Vec<(Action, Key)>, // in the order of the defaults
+}
+
+impl InputMap {
+    // the default bindings of a state, with keys as parse_key() takes them; an unknown
+    // key name is a mistake in the state and panics
+    pub fn new(defaults: &[(Action, &str)]) -> Self {
+        let bindings = defaults
+            .iter()
+            .map(|&(action, name)| {
+                let key = parse_key(name).unwrap_or_else(|| panic!("unknown key '{}'", name));
+                (action, key)
+            })
+            .collect();
+        Self { bindings }
+    }
+
+    // the defaults with the keymap file of --keymap, if one was given
+    pub fn with_args(defaults: &[(Action, &str)], args: &SurfaceArgs) -> Result<Self> {
+        let mut map = Self::new(defaults);
+        if let Some(path) = &args.keymap {
+            let toml = std::fs::read_to_string(path).map_err(|source| KeymapError::Read {
+                path: path.clone(),
+                source,
+            })?;
+            map.apply_toml(&toml)?;
+        }
+        Ok(map)
+    }
+
+    // the action bound to key, if any
+    pub fn action(&self, key: &Key) -> Option<Action> {
+        self.bindings.iter().find(|(_, k)| k == key).map(|&(action, _)| action)
+    }
+
+    pub fn keys(&self, action: Action) -> impl Iterator<Item = &Key> {
+        self.bindings.iter().filter(move |(a, _)| *a == action).map(|(_, key)| key)
+    }
+
+    pub fn bindings(&self) -> &[(Action, Key)] {
+        &self.bindings
+    }
+
+    // puts action on keys instead of its keys so far; the keys leave the actions they
+    // were bound to. Actions the map has no binding for are not added, so one keymap
+    // can serve every example.
+    pub fn bind(&mut self, action: Action, keys: &[Key]) -> bool {
+        if self.keys(action).next().is_none() {
+            return false;
+        }
+        let mut placed = false;
+        for (a, key) in std::mem::take(&mut self.bindings) {
+            if a == action {
+                if !placed {
+                    let bound = keys.iter().map(|key| (action, key.clone()));
+                    self.bindings.extend(bound);
+                    placed = true;
+                }
+            } else if !keys.contains(&key) {
+                self.bindings.push((a, key));
+            }
+        }
+        true
+    }
+
+    // overrides the bindings from a keymap, a TOML table of action names to a key or an
+    // array of keys; a keymap with an error changes nothing
+    pub fn apply_toml(&mut self, toml: &str) -> Result<()> {
+        let table: std::collections::BTreeMap<String, KeyList> =
+            toml::from_str(toml).map_err(KeymapError::from)?;
+        let mut bindings = vec![];
+        for (name, keys) in table {
+            let action = Action::from_name(&name)
+                .ok_or_else(|| KeymapError::UnknownAction(name.clone()))?;
+            let names = match keys {
+                KeyList::One(name) => vec![name],
+                KeyList::Many(names) => names,
+            };
+            let keys = names
+                .iter()
+                .map(|name| {
+                    parse_key(name).ok_or_else(|| {
+                        Error::from(KeymapError::UnknownKey {
+                            key: name.clone(),
+                            action: action.name(),
+                        })
+                    })
+                })
+                .collect::<Result<Vec<Key>>>()?;
+            bindings.push((action, keys));
+        }
+        for (action, keys) in bindings {
+            self.bind(action, &keys);
+        }
+        Ok(())
+    }
+
+    // one "key: action" line per binding, e.g. for a help text
+    pub fn describe(&self) -> String {
+        let lines: Vec<String> = self
+            .bindings
+            .iter()
+            .map(|(action, key)| format!("{}: {}", key_name(key), action.name()))
+            .collect();
+        lines.join("\n")
+    }
+}
+// endregion: input map
+
+// region: driver
+const DRIVER_KEYS: &[(Action, &str)] = &[(Action::CyclePresentMode, PRESENT_MODE_KEY)];
+
+type Setup<'a, S> = Box<dyn FnOnce(&mut S) + 'a>;
+
+pub struct Application<'a, S: SurfaceApp> {
+    state: Option<S>,
+    args: &'a SurfaceArgs,
+    title: &'a str,
+    setup: Option<Setup<'a, S>>,
+    render_start_time: Option<Instant>,
+    frame_pacer: FramePacer,
+    input_map: InputMap, // the keys of the driver: PRESENT_MODE_KEY unless remapped
+    error: Option<Error>,           // why the state could not be created
+    monitor: Option<MonitorHandle>, // the window's, to notice moves to another one
+    #[cfg(target_arch = "wasm32")]
+    proxy: Option<EventLoopProxy<Result<S>>>, // where new() sends the state back
+}
+
+impl<'a, S: SurfaceApp> Application<'a, S> {
+    pub fn new(args: &'a SurfaceArgs, title: &'a str) -> Self {
+        Self {
+            state: None,
+            args,
+            title,
+            setup: None,
+            render_start_time: None,
+            frame_pacer: FramePacer::default(),
+            // a keymap that does not load fails the state's new() with the reason
+            input_map: InputMap::with_args(DRIVER_KEYS, args)
+                .unwrap_or_else(|_| InputMap::new(DRIVER_KEYS)),
+            error: None,
+            monitor: None,
+            #[cfg(target_arch = "wasm32")]
+            proxy: None,
+        }
+    }
+
+    // runs once on the state right after it is created, e.g. to start a playlist
+    pub fn with_setup(mut self, setup: impl FnOnce(&mut S) + 'a) -> Self {
+        self.setup = Some(Box::new(setup));
+        self
+    }
+
+    // the window the state renders to: a canvas appended to the page in the browser
+    fn create_window(&self, event_loop: &ActiveEventLoop) -> Arc<Window> {
+        let window_attributes = Window::default_attributes().with_title(self.title);
+        #[cfg(target_arch = "wasm32")]
+        let window_attributes = {
+            use winit::platform::web::WindowAttributesExtWebSys;
+            window_attributes.with_append(true)
+        };
+
+        let window = event_loop
+            .create_window(window_attributes)
+            .expect("Failed to create window");
+        Arc::new(window)
+    }
+
+    // the state for the arguments, starting from scene instead of their --scene if
+    // given
+    fn create_state(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        window: Arc<Window>,
+        scene: Option<Scene>,
+    ) {
+        let mut args = self.args.clone();
+        if let Some(scene) = scene {
+            args.seed = Some(scene.seed);
+            args.scene = Some(scene);
+        }
+        // the browser cannot block on the adapter and device requests, so the state
+        // comes back through user_event() once they resolve
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = event_loop;
+            let proxy = self.proxy.clone().expect("run() sets the event loop proxy");
+            wasm_bindgen_futures::spawn_local(async move {
+                let _ = proxy.send_event(S::new(window, &args).await);
+            });
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let state = pollster::block_on(S::new(window, &args));
+            self.created(event_loop, state);
+        }
+    }
+
+    // reconfigures the surface if its capabilities changed, and creates the state
+    // anew when its pipelines no longer match. The new state starts from the scene
+    // of the old one (SurfaceApp::snapshot), so the surface, camera and toggles the
+    // scene keeps survive, and the animation time goes on.
+    fn refresh_surface(&mut self, event_loop: &ActiveEventLoop) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        let change = state.init_mut().refresh_surface();
+        if change.is_empty() || state.surface_changed(change) {
+            return;
+        }
+        log::info!("surface changed ({:?}), recreating the renderer", change);
+        let scene = state.snapshot();
+        if scene.is_none() {
+            log::warn!("the state has no scene to restore, it starts over");
+        }
+        // a window has one surface at a time, so the old state goes first
+        let window = state.init().window.clone();
+        self.state = None;
+        self.create_state(event_loop, window, scene);
+    }
+
+    fn created(&mut self, event_loop: &ActiveEventLoop, state: Result<S>) {
+        let mut state = match state {
+            Ok(state) => state,
+            Err(e) => {
+                // run() does not return in the browser, so the page's console gets it
+                #[cfg(target_arch = "wasm32")]
+                web_sys::console::error_1(&format!("error: {e}").into());
+                self.error = Some(e);
+                event_loop.exit();
+                return;
+            }
+        };
+        let init = state.init();
+        if init.sample_count != self.args.msaa {
+            log::warn!(
+                "--msaa {} is not supported, using {} (at most {})",
+                self.args.msaa,
+                init.sample_count,
+                init.max_sample_count()
+            );
+        }
+        if let Some(setup) = self.setup.take() {
+            setup(&mut state);
+        }
+        if recovery::is_installed() {
+            recovery::record_adapter(&state.init().adapter.get_info());
+        }
+        self.monitor = state.window().current_monitor();
+        self.state = Some(state);
+
+        // a recreated state keeps the time of the first one
+        self.render_start_time.get_or_insert_with(Instant::now);
+    }
+}
+
+impl<S: SurfaceApp> ApplicationHandler<Result<S>> for Application<'_, S> {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.state.is_some() {
+            return;
+        }
+        let window = self.create_window(event_loop);
+        self.create_state(event_loop, window, None);
+    }
+
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, state: Result<S>) {
+        self.created(event_loop, state);
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        _window_id: WindowId,
+        event: WindowEvent,
+    ) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+
+        if state.input(&event) {
+            self.frame_pacer.invalidate();
+            return;
+        }
+
+        match event {
+            WindowEvent::CloseRequested
+            | WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(KeyCode::Escape),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => event_loop.exit(),
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: key,
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } if self.input_map.action(&key) == Some(Action::CyclePresentMode) => {
+                let init = state.init_mut();
+                let preference = init.present_mode.next();
+                let mode = init.set_present_mode(preference);
+                log::info!("present mode: {} ({:?})", preference.name(), mode);
+                self.frame_pacer.invalidate();
+            }
+            WindowEvent::Focused(focused) => {
+                self.frame_pacer.set_focused(focused);
+                self.frame_pacer.invalidate();
+            }
+            WindowEvent::Occluded(occluded) => {
+                self.frame_pacer.set_occluded(occluded);
+                self.frame_pacer.invalidate();
+            }
+            WindowEvent::Resized(physical_size) => {
+                state.resize(physical_size);
+                self.frame_pacer.invalidate();
+            }
+            // another monitor may offer other surface formats or alpha modes
+            WindowEvent::Moved(_) => {
+                let monitor = state.window().current_monitor();
+                if monitor != self.monitor {
+                    self.monitor = monitor;
+                    self.refresh_surface(event_loop);
+                    self.frame_pacer.invalidate();
+                }
+            }
+            WindowEvent::ScaleFactorChanged { .. } => {
+                self.refresh_surface(event_loop);
+                self.frame_pacer.invalidate();
+            }
+            WindowEvent::RedrawRequested => {
+                // the driver asks for no frames while idle, so this redraw was requested
+                // by the state or the system
+                if self.frame_pacer.is_idle() {
+                    self.frame_pacer.invalidate();
+                }
+                // while power saving, nothing is updated or drawn until something changed
+                if !self.frame_pacer.next_frame() {
+                    return;
+                }
+                if !self.frame_pacer.is_power_saving() {
+                    state.window().request_redraw();
+                }
+                let now = Instant::now();
+                state.update(now - self.render_start_time.unwrap_or(now));
+                if recovery::is_installed()
+                    && let Some(scene) = state.snapshot()
+                {
+                    recovery::record(scene);
+                }
+                match state.render() {
+                    Ok(_) => {}
+                    // rebuild the surface if it's lost or outdated, for what it offers now
+                    Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                        let size = state.size();
+                        self.refresh_surface(event_loop);
+                        if let Some(state) = &mut self.state {
+                            state.resize(size);
+                        }
+                    }
+                    Err(wgpu::SurfaceError::OutOfMemory) => {
+                        log::error!("out of memory");
+                        event_loop.exit();
+                    }
+                    // if a frame takes too long to display, warn and move on
+                    Err(wgpu::SurfaceError::Timeout) => log::warn!("surface timeout"),
+                    Err(wgpu::SurfaceError::Other) => log::error!("surface error"),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        // while unfocused, sleep until something changes, then until the next low-rate
+        // frame is due
+        if self.frame_pacer.is_idle() {
+            event_loop.set_control_flow(ControlFlow::Wait);
+            return;
+        }
+        match self.frame_pacer.next_frame_time() {
+            Some(t) if t > Instant::now() => {
+                event_loop.set_control_flow(ControlFlow::WaitUntil(t));
+                return;
+            }
+            _ => event_loop.set_control_flow(ControlFlow::Wait),
+        }
+        if let Some(state) = &self.state {
+            state.window().request_redraw();
+        }
+    }
+}
+
+// opens a window titled title and runs S in it until it is closed
+pub fn run_app<S: SurfaceApp>(args: &SurfaceArgs, title: &str) -> anyhow::Result<()> {
+    run(Application::<S>::new(args, title))
+}
+
+// run_app() with setup applied to the state once it is created
+pub fn run_app_with<S: SurfaceApp>(
+    args: &SurfaceArgs,
+    title: &str,
+    setup: impl FnOnce(&mut S),
+) -> anyhow::Result<()> {
+    run(Application::new(args, title).with_setup(setup))
+}
+
+// logs at the info level unless RUST_LOG says otherwise, so the messages of the
+// library, e.g. the keys' feedback and the frame rate, show without it; wgpu's own
+// info messages are left out
+pub fn init_logger() {
+    let filter = "info,wgpu_core=warn,wgpu_hal=warn,naga=warn";
+    let _ = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(filter))
+        .try_init();
+}
+
+fn run<S: SurfaceApp>(mut app: Application<S>) -> anyhow::Result<()> {
+    init_logger();
+    #[cfg(target_arch = "wasm32")]
+    console_error_panic_hook::set_once();
+    // the browser has no files to save the session to
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(path) = &app.args.recovery_file {
+        let args = std::env::args().collect();
+        recovery::install(recovery::Recovery::new(path, args));
+    }
+    let event_loop = EventLoop::with_user_event().build()?;
+    #[cfg(target_arch = "wasm32")]
+    {
+        app.proxy = Some(event_loop.create_proxy());
+    }
+    event_loop.run_app(&mut app)?;
+    match app.error {
+        Some(e) => Err(e.into()),
+        None => Ok(()),
+    }
+}
+// endregion: driver
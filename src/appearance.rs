@@ -0,0 +1,264 @@
+use std::fmt;
+
+use bytemuck::cast_slice;
+
+use super::colormap::{self, Interpolation};
+use super::error::{Error, Result};
+use super::material::{self, Material};
+
+// region: appearance
+// The parts of a surface's look that live in GPU memory rather than in the pipeline:
+// the directional light and material uniforms of the fragment shaders and a 1D
+// colormap texture. The setters only write buffers and textures, so the material
+// values, the light and the colormap change at runtime without recreating any
+// pipeline; only a material of another kind needs another fragment shader and is
+// rejected. Every change is passed to the hooks, e.g. to show it in a status line:
+//
+//     appearance.hooks.add(|change| println!("{}", change));
+//     appearance.set_light(&queue, DirectionalLight { direction, ..appearance.light() });
+pub const LIGHT_UNIFORM_SIZE: wgpu::BufferAddress = 48;
+pub const COLORMAP_TEXTURE_WIDTH: u32 = 256;
+
+// the light uniforms of the chapter fragment shaders
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DirectionalLight {
+    pub direction: [f32; 3],
+    pub eye_position: [f32; 3],
+    pub specular_color: [f32; 3],
+}
+
+impl Default for DirectionalLight {
+    fn default() -> Self {
+        Self {
+            direction: [-0.5, -0.5, -0.5],
+            eye_position: [4.0, 4.0, 4.0],
+            specular_color: [1.0, 1.0, 1.0],
+        }
+    }
+}
+
+impl DirectionalLight {
+    // contents of the light uniform buffer, each vec3 padded to 16 bytes
+    pub fn uniform_data(&self) -> [f32; 12] {
+        let [d, e, s] = [self.direction, self.eye_position, self.specular_color];
+        [d[0], d[1], d[2], 0.0, e[0], e[1], e[2], 0.0, s[0], s[1], s[2], 0.0]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AppearanceChange {
+    Material(Material),
+    Light(DirectionalLight),
+    Colormap(String),
+}
+
+impl fmt::Display for AppearanceChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Material(material) => {
+                let [a, b, c, d] = material.uniform_data();
+                write!(
+                    f,
+                    "material: {} {:.2} {:.2} {:.2} {:.2}",
+                    material.kind().name(),
+                    a,
+                    b,
+                    c,
+                    d
+                )
+            }
+            Self::Light(light) => {
+                let [x, y, z] = light.direction;
+                write!(f, "light direction: {:.2} {:.2} {:.2}", x, y, z)
+            }
+            Self::Colormap(name) => write!(f, "colormap: {}", name),
+        }
+    }
+}
+
+// callbacks invoked with every change of an Appearance, like picking::ProbeHooks
+#[derive(Default)]
+pub struct AppearanceHooks {
+    #[allow(clippy::type_complexity)]
+    callbacks: Vec<Box<dyn FnMut(&AppearanceChange)>>,
+}
+
+impl AppearanceHooks {
+    pub fn add(&mut self, callback: impl FnMut(&AppearanceChange) + 'static) {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    pub fn clear(&mut self) {
+        self.callbacks.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.callbacks.is_empty()
+    }
+
+    pub fn emit(&mut self, change: &AppearanceChange) {
+        for callback in self.callbacks.iter_mut() {
+            callback(change);
+        }
+    }
+}
+
+pub struct Appearance {
+    pub light_buffer: wgpu::Buffer,
+    pub material_buffer: wgpu::Buffer,
+    pub colormap_texture: wgpu::Texture, // Rgba8UnormSrgb, sampled from 0 to 1
+    pub colormap_view: wgpu::TextureView,
+    pub hooks: AppearanceHooks,
+    light: DirectionalLight,
+    material: Material,
+    colormap: String,
+}
+
+impl Appearance {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        light: DirectionalLight,
+        material: Material,
+        colormap: &str,
+    ) -> Self {
+        let uniform_buffer = |label, size| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+        let light_buffer = uniform_buffer("Light Uniform Buffer", LIGHT_UNIFORM_SIZE);
+        let material_buffer =
+            uniform_buffer("Material Uniform Buffer", material::MATERIAL_UNIFORM_SIZE);
+        let colormap_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Colormap Texture"),
+            size: wgpu::Extent3d {
+                width: COLORMAP_TEXTURE_WIDTH,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D1,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let colormap_view = colormap_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let appearance = Self {
+            light_buffer,
+            material_buffer,
+            colormap_texture,
+            colormap_view,
+            hooks: AppearanceHooks::default(),
+            light,
+            material,
+            colormap: colormap.to_string(),
+        };
+        appearance.write_light(queue);
+        appearance.write_material(queue);
+        appearance.write_colormap(queue);
+        appearance
+    }
+
+    pub fn light(&self) -> DirectionalLight {
+        self.light
+    }
+
+    pub fn material(&self) -> Material {
+        self.material
+    }
+
+    pub fn colormap(&self) -> &str {
+        &self.colormap
+    }
+
+    // light and material buffers for the fragment bind group of the chapter shaders
+    pub fn bind_group(
+        &self,
+        device: &wgpu::Device,
+    ) -> Result<(wgpu::BindGroupLayout, wgpu::BindGroup)> {
+        super::wgpu_simplified::create_bind_group(
+            device,
+            vec![wgpu::ShaderStages::FRAGMENT, wgpu::ShaderStages::FRAGMENT],
+            &[
+                self.light_buffer.as_entire_binding(),
+                self.material_buffer.as_entire_binding(),
+            ],
+        )
+    }
+
+    // follows the camera without notifying the hooks, for every frame
+    pub fn set_eye_position(&mut self, queue: &wgpu::Queue, eye_position: [f32; 3]) {
+        self.light.eye_position = eye_position;
+        self.write_light(queue);
+    }
+
+    pub fn set_light(&mut self, queue: &wgpu::Queue, light: DirectionalLight) {
+        self.light = light;
+        self.write_light(queue);
+        self.hooks.emit(&AppearanceChange::Light(light));
+    }
+
+    // the pipelines shade the kind of the current material, so the new one has to be
+    // of the same kind
+    pub fn set_material(&mut self, queue: &wgpu::Queue, material: Material) -> Result<()> {
+        if material.kind() != self.material.kind() {
+            return Err(Error::MaterialKind {
+                pipeline: self.material.kind(),
+                material: material.kind(),
+            });
+        }
+        self.material = material;
+        self.write_material(queue);
+        self.hooks.emit(&AppearanceChange::Material(material));
+        Ok(())
+    }
+
+    // any name in the colormap registry; unknown names fall back to jet, like the
+    // surfaces do
+    pub fn set_colormap(&mut self, queue: &wgpu::Queue, name: &str) {
+        self.colormap = name.to_string();
+        self.write_colormap(queue);
+        self.hooks.emit(&AppearanceChange::Colormap(self.colormap.clone()));
+    }
+
+    fn write_light(&self, queue: &wgpu::Queue) {
+        queue.write_buffer(&self.light_buffer, 0, cast_slice(&self.light.uniform_data()));
+    }
+
+    fn write_material(&self, queue: &wgpu::Queue) {
+        let data = self.material.uniform_data();
+        queue.write_buffer(&self.material_buffer, 0, cast_slice(&data));
+    }
+
+    fn write_colormap(&self, queue: &wgpu::Queue) {
+        queue.write_texture(
+            self.colormap_texture.as_image_copy(),
+            &colormap_rgba8(&self.colormap, COLORMAP_TEXTURE_WIDTH),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * COLORMAP_TEXTURE_WIDTH),
+                rows_per_image: None,
+            },
+            self.colormap_texture.size(),
+        );
+    }
+}
+
+// width opaque sRGB texels of a registered colormap, from t = 0 to t = 1
+pub fn colormap_rgba8(name: &str, width: u32) -> Vec<u8> {
+    let colormap = colormap::lookup(name, Interpolation::Linear);
+    let n = (width.max(2) - 1) as f32;
+    (0..width.max(2))
+        .flat_map(|k| {
+            let [r, g, b] = colormap.color(0.0, 1.0, k as f32 / n);
+            [r, g, b, 1.0].map(|c| (c * 255.0).round() as u8)
+        })
+        .collect()
+}
+// endregion: appearance
@@ -0,0 +1,402 @@
+use std::time::{Duration, Instant};
+
+use bytemuck::cast_slice;
+use cgmath::Matrix4;
+use wgpu::util::DeviceExt;
+
+use super::surface_data::IParametricSurface;
+use super::wgpu_simplified as ws;
+
+// region: benchmark shader
+const BENCHMARK_SHADER: &str = r#"
+struct Uniforms {
+    vpMat: mat4x4f,
+    modelMat: mat4x4f,
+}
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+
+struct Output {
+    @builtin(position) position: vec4f,
+    @location(0) normal: vec3f,
+    @location(1) color: vec3f,
+}
+
+@vertex
+fn vs_main(
+    @location(0) position: vec3f,
+    @location(1) normal: vec3f,
+    @location(2) color: vec3f,
+    @builtin(instance_index) instance: u32,
+) -> Output {
+    // spread the instances on a 4 x 4 grid so every one of them covers new pixels
+    let offset = vec3f(f32(instance % 4u) - 1.5, f32(instance / 4u % 4u) - 1.5, 0.0);
+    var output: Output;
+    let p = uniforms.modelMat * vec4f(position, 1.0) + vec4f(offset, 0.0);
+    output.position = uniforms.vpMat * p;
+    output.normal = (uniforms.modelMat * vec4f(normal, 0.0)).xyz;
+    output.color = color;
+    return output;
+}
+
+@fragment
+fn fs_main(in: Output) -> @location(0) vec4f {
+    let light = normalize(vec3f(0.5, 0.5, 0.5));
+    let diffuse = abs(dot(normalize(in.normal), light));
+    return vec4f(in.color * (0.2 + 0.8 * diffuse), 1.0);
+}
+"#;
+// endregion: benchmark shader
+
+// region: benchmark
+// Standardized headless workload: a parametric surface drawn as several instances
+// into an offscreen color + depth target, rendered for a fixed number of frames on
+// every adapter the instance can find. Each frame is waited for before the next one,
+// so the frame times include the whole round trip to the GPU.
+#[derive(Clone, Debug)]
+pub struct IBenchmark {
+    pub backends: wgpu::Backends,
+    pub width: u32,
+    pub height: u32,
+    pub frames: u32,
+    pub warmup_frames: u32,
+    pub surface_type: u32,
+    pub resolution: u16,
+    pub instances: u32,
+}
+
+impl Default for IBenchmark {
+    fn default() -> Self {
+        Self {
+            backends: wgpu::Backends::all(),
+            width: 1280,
+            height: 720,
+            frames: 300,
+            warmup_frames: 10,
+            surface_type: 0,
+            resolution: 160,
+            instances: 16,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct IBenchmarkResult {
+    pub adapter: wgpu::AdapterInfo,
+    pub frames: u32,
+    pub elapsed: Result<Duration, String>,
+    pub frame_times: Vec<Duration>, // of the timed frames, empty when the adapter failed
+}
+
+// frame time statistics in milliseconds; percentiles take the nearest rank, the
+// smallest sample with at least that share of the samples at or below it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameTimeStats {
+    pub min: f64,
+    pub mean: f64,
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl FrameTimeStats {
+    pub fn from_samples(samples_ms: &[f64]) -> Option<Self> {
+        if samples_ms.is_empty() {
+            return None;
+        }
+        let mut sorted = samples_ms.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let percentile = |p: f64| {
+            let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+            sorted[rank.clamp(1, sorted.len()) - 1]
+        };
+        Some(Self {
+            min: sorted[0],
+            mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+            p50: percentile(50.0),
+            p95: percentile(95.0),
+            p99: percentile(99.0),
+            max: sorted[sorted.len() - 1],
+        })
+    }
+}
+
+impl IBenchmarkResult {
+    pub fn ms_per_frame(&self) -> Option<f64> {
+        self.elapsed
+            .as_ref()
+            .ok()
+            .map(|elapsed| 1000.0 * elapsed.as_secs_f64() / self.frames.max(1) as f64)
+    }
+
+    pub fn stats(&self) -> Option<FrameTimeStats> {
+        let samples: Vec<f64> = self
+            .frame_times
+            .iter()
+            .map(|t| 1000.0 * t.as_secs_f64())
+            .collect();
+        FrameTimeStats::from_samples(&samples)
+    }
+}
+
+impl IBenchmark {
+    // renders the workload on each adapter in turn; adapters that fail keep their
+    // error message in the result instead of aborting the whole run
+    pub fn run(&self) -> Vec<IBenchmarkResult> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: self.backends,
+            ..Default::default()
+        });
+
+        let mut ps = IParametricSurface {
+            surface_type: self.surface_type,
+            u_resolution: self.resolution,
+            v_resolution: self.resolution,
+            scale: 1.0,
+            ..Default::default()
+        };
+        let data = ps.new();
+        let vertices: Vec<[f32; 9]> = (0..data.positions.len())
+            .map(|i| {
+                let [p, n, c] = [data.positions[i], data.normals[i], data.colors[i]];
+                [p[0], p[1], p[2], n[0], n[1], n[2], c[0], c[1], c[2]]
+            })
+            .collect();
+
+        instance
+            .enumerate_adapters(self.backends)
+            .into_iter()
+            .map(|adapter| {
+                let run = pollster::block_on(self.run_adapter(&adapter, &vertices, &data.indices));
+                let (elapsed, frame_times) = match run {
+                    Ok((elapsed, frame_times)) => (Ok(elapsed), frame_times),
+                    Err(e) => (Err(e.to_string()), vec![]),
+                };
+                IBenchmarkResult {
+                    adapter: adapter.get_info(),
+                    frames: self.frames,
+                    elapsed,
+                    frame_times,
+                }
+            })
+            .collect()
+    }
+
+    async fn run_adapter(
+        &self,
+        adapter: &wgpu::Adapter,
+        vertices: &[[f32; 9]],
+        indices: &[u16],
+    ) -> anyhow::Result<(Duration, Vec<Duration>)> {
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                label: Some("Benchmark Device"),
+                required_features: wgpu::Features::empty(),
+                required_limits: wgpu::Limits::downlevel_defaults()
+                    .using_resolution(adapter.limits()),
+                ..Default::default()
+            })
+            .await?;
+
+        let format = wgpu::TextureFormat::Rgba8Unorm;
+        let size = wgpu::Extent3d {
+            width: self.width,
+            height: self.height,
+            depth_or_array_layers: 1,
+        };
+        let color_view = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("Benchmark Color Texture"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let depth_view = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("Benchmark Depth Texture"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Depth24Plus,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Benchmark Shader"),
+            source: wgpu::ShaderSource::Wgsl(BENCHMARK_SHADER.into()),
+        });
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Benchmark Uniform Buffer"),
+            size: 128,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let (bind_group_layout, bind_group) = ws::create_bind_group(
+            &device,
+            vec![wgpu::ShaderStages::VERTEX],
+            &[uniform_buffer.as_entire_binding()],
+        )?;
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Benchmark Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Benchmark Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<[f32; 9]>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    // pos, norm, col
+                    attributes: &wgpu::vertex_attr_array![
+                        0 => Float32x3, 1 => Float32x3, 2 => Float32x3
+                    ],
+                }],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(format.into())],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth24Plus,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Benchmark Vertex Buffer"),
+            contents: cast_slice(vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Benchmark Index Buffer"),
+            contents: cast_slice(indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let (view_mat, project_mat, _) = ws::create_vp_mat(
+            (0.0, 0.0, 6.0).into(),
+            (0.0, 0.0, 0.0).into(),
+            cgmath::Vector3::unit_y(),
+            self.width as f32 / self.height as f32,
+        );
+        let vp_mat = project_mat * view_mat;
+
+        let mut start = Instant::now();
+        let mut frame_times = Vec::with_capacity(self.frames as usize);
+        for frame in 0..self.warmup_frames + self.frames {
+            if frame == self.warmup_frames {
+                // exclude shader compilation and driver warm-up from the timing
+                device.poll(wgpu::PollType::Wait)?;
+                start = Instant::now();
+            }
+            let frame_start = Instant::now();
+
+            let angle = 0.02 * frame as f32;
+            let model_mat: Matrix4<f32> =
+                ws::create_model_mat([0.0, 0.0, 0.0], [angle, 2.0 * angle, 0.0], [0.8, 0.8, 0.8]);
+            let vp_ref: &[f32; 16] = vp_mat.as_ref();
+            let model_ref: &[f32; 16] = model_mat.as_ref();
+            queue.write_buffer(&uniform_buffer, 0, cast_slice(vp_ref));
+            queue.write_buffer(&uniform_buffer, 64, cast_slice(model_ref));
+
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Benchmark Encoder"),
+            });
+            {
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Benchmark Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &color_view,
+                        depth_slice: None,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: Some(ws::create_depth_stencil_attachment(
+                        &depth_view,
+                    )),
+                    occlusion_query_set: None,
+                    timestamp_writes: None,
+                });
+                render_pass.set_pipeline(&pipeline);
+                render_pass.set_bind_group(0, &bind_group, &[]);
+                render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+                render_pass.draw_indexed(0..indices.len() as u32, 0, 0..self.instances);
+            }
+            queue.submit(std::iter::once(encoder.finish()));
+            device.poll(wgpu::PollType::Wait)?;
+            if frame >= self.warmup_frames {
+                frame_times.push(frame_start.elapsed());
+            }
+        }
+        Ok((start.elapsed(), frame_times))
+    }
+}
+
+// one row per adapter, fastest first, under a header; failed adapters are listed last
+pub fn results_table(results: &[IBenchmarkResult]) -> String {
+    let mut sorted: Vec<&IBenchmarkResult> = results.iter().collect();
+    sorted.sort_by(|a, b| {
+        let key = |r: &IBenchmarkResult| r.ms_per_frame().unwrap_or(f64::MAX);
+        key(a).total_cmp(&key(b))
+    });
+
+    let mut table = format!(
+        "{:<10} {:<14} {:<40} {:>10} {:>8} {:>8} {:>8} {:>8} {:>8}\n",
+        "backend", "device type", "adapter", "ms/frame", "fps", "min", "p50", "p95", "p99"
+    );
+    for result in sorted {
+        let info = &result.adapter;
+        let device_type = format!("{:?}", info.device_type);
+        let row = match (result.ms_per_frame(), result.stats()) {
+            (Some(ms), Some(stats)) => format!(
+                "{:<10} {:<14} {:<40} {:>10.3} {:>8.1} {:>8.3} {:>8.3} {:>8.3} {:>8.3}",
+                info.backend.to_str(),
+                device_type,
+                info.name,
+                ms,
+                1000.0 / ms,
+                stats.min,
+                stats.p50,
+                stats.p95,
+                stats.p99
+            ),
+            _ => format!(
+                "{:<10} {:<14} {:<40} failed: {}",
+                info.backend.to_str(),
+                device_type,
+                info.name,
+                result.elapsed.as_ref().err().map_or("", |e| e.as_str())
+            ),
+        };
+        table.push_str(&row);
+        table.push('\n');
+    }
+    table
+}
+// endregion: benchmark
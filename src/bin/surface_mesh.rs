@@ -0,0 +1,100 @@
+// Generates a built-in surface by name and writes it to mesh files and, optionally, a
+// PNG preview, all without a window, e.g. from build scripts and pipelines; the
+// extension of each output picks its format (obj, ply, gltf, glb or png):
+//
+//     cargo run --features mesh_tool --bin surface_mesh -- \
+//         --resolution 96 torus torus.glb torus.png 800x600
+//
+// Built with the mesh_tool feature. --resolution sets the grid of both the height
+// fields (sinc, poles, peaks) and the parametric surfaces; --colormap,
+// --wireframe-color, --material and --msaa apply as in the examples.
+use wgpu_surfaces::api::{self, SIMPLE_SURFACE_NAMES};
+use wgpu_surfaces::capture;
+use wgpu_surfaces::cli::SurfaceArgs;
+use wgpu_surfaces::export::{self, ExportConvention};
+use wgpu_surfaces::{Gpu, Mesh, ParametricSurface, SimpleSurface, SurfaceStyle};
+
+const USAGE: &str = "SURFACE OUTPUT... [WIDTHxHEIGHT]";
+const PREVIEW_SIZE: (u32, u32) = (1024, 768);
+
+fn main() {
+    let args = SurfaceArgs::default().parse_env(USAGE);
+    env_logger::init();
+    if let Err(e) = run(&args) {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn run(args: &SurfaceArgs) -> anyhow::Result<()> {
+    let [name, outputs @ ..] = &args.positional[..] else {
+        anyhow::bail!(
+            "no surface given; available: {}",
+            surface_names().join(", ")
+        );
+    };
+    let (sizes, outputs): (Vec<&String>, Vec<&String>) =
+        outputs.iter().partition(|arg| parse_size(arg).is_some());
+    if outputs.is_empty() {
+        anyhow::bail!("no output file given for {name}");
+    }
+    let (width, height) = sizes
+        .last()
+        .and_then(|size| parse_size(size))
+        .unwrap_or(PREVIEW_SIZE);
+
+    let mesh = build(name, args)?;
+    for output in outputs {
+        if output.to_lowercase().ends_with(".png") {
+            let gpu = pollster::block_on(Gpu::headless(width, height, args.msaa))?;
+            let rgba = capture::render_preview(gpu.init(), mesh.output(), args.material, true)?;
+            capture::save_png(output, width, height, &rgba)?;
+            println!("saved {output} ({width} x {height})");
+        } else {
+            export::export_mesh_as(mesh.output(), output, &ExportConvention::default())?;
+            println!(
+                "saved {output} ({} vertices, {} triangles)",
+                mesh.vertex_count(),
+                mesh.triangle_count()
+            );
+        }
+    }
+    Ok(())
+}
+
+// a simple surface or, failing that, a parametric surface of the name
+fn build(name: &str, args: &SurfaceArgs) -> anyhow::Result<Mesh> {
+    let mut style = SurfaceStyle::default();
+    style.colormap = args.colormap.clone();
+    style.wireframe_color = args.wireframe_color.clone();
+    if let Ok(surface) = SimpleSurface::new(name) {
+        let mut surface = surface.style(&style);
+        if let Some(n) = args.resolution {
+            surface = surface.resolution(n, n);
+        }
+        return Ok(surface.build());
+    }
+    let Ok(surface) = ParametricSurface::new(name) else {
+        anyhow::bail!(
+            "unknown surface '{name}'; available: {}",
+            surface_names().join(", ")
+        );
+    };
+    let mut surface = surface.style(&style);
+    if let Some(n) = args.resolution {
+        surface = surface.resolution(n, n);
+    }
+    Ok(surface.build())
+}
+
+fn surface_names() -> Vec<String> {
+    let simple = SIMPLE_SURFACE_NAMES.iter().map(|name| name.to_string());
+    simple.chain(api::parametric_surface_names()).collect()
+}
+
+// "800x600"
+fn parse_size(arg: &str) -> Option<(u32, u32)> {
+    let (width, height) = arg.split_once('x')?;
+    let size = (width.parse().ok()?, height.parse().ok()?);
+    (size.0 > 0 && size.1 > 0).then_some(size)
+}
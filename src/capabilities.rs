@@ -0,0 +1,194 @@
+use std::fmt;
+
+use super::error::{Error, Result};
+
+// region: capability negotiation
+// Optional GPU features are requested only where the adapter has them, and the
+// subsystems that depend on a feature the device did not get are switched off up
+// front with a message, instead of failing when their pipelines are created. The
+// matrix of what each subsystem needs:
+//
+//     adapter_sample_counts  TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES (else MSAA 1 and 4)
+//     gpu_surfaces           compute shaders (a downlevel flag, missing on WebGL2)
+//     timestamp_profiling    TIMESTAMP_QUERY
+//     push_constants         PUSH_CONSTANTS
+//     half_float_shaders     SHADER_F16
+//     line_wireframe         POLYGON_MODE_LINE
+//
+// InitWgpu and InitWgpuHeadless negotiate every subsystem and keep the result, so
+// code that depends on one asks first:
+//
+//     init.capabilities.require(Subsystem::GpuSurfaces)?;
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Subsystem {
+    AdapterSampleCounts,
+    GpuSurfaces,
+    TimestampProfiling,
+    PushConstants,
+    HalfFloatShaders,
+    LineWireframe,
+}
+
+impl Subsystem {
+    pub const ALL: [Self; 6] = [
+        Self::AdapterSampleCounts,
+        Self::GpuSurfaces,
+        Self::TimestampProfiling,
+        Self::PushConstants,
+        Self::HalfFloatShaders,
+        Self::LineWireframe,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::AdapterSampleCounts => "adapter_sample_counts",
+            Self::GpuSurfaces => "gpu_surfaces",
+            Self::TimestampProfiling => "timestamp_profiling",
+            Self::PushConstants => "push_constants",
+            Self::HalfFloatShaders => "half_float_shaders",
+            Self::LineWireframe => "line_wireframe",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|subsystem| subsystem.name().eq_ignore_ascii_case(name))
+    }
+}
+
+// the optional device features a subsystem needs; kept out of Subsystem, which the
+// stable api re-exports, as they are wgpu types
+pub fn required_features(subsystem: Subsystem) -> wgpu::Features {
+    match subsystem {
+        Subsystem::AdapterSampleCounts => wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
+        Subsystem::GpuSurfaces => wgpu::Features::empty(),
+        Subsystem::TimestampProfiling => wgpu::Features::TIMESTAMP_QUERY,
+        Subsystem::PushConstants => wgpu::Features::PUSH_CONSTANTS,
+        Subsystem::HalfFloatShaders => wgpu::Features::SHADER_F16,
+        Subsystem::LineWireframe => wgpu::Features::POLYGON_MODE_LINE,
+    }
+}
+
+// the downlevel capabilities it needs, which cannot be requested, only checked
+pub fn required_downlevel_flags(subsystem: Subsystem) -> wgpu::DownlevelFlags {
+    match subsystem {
+        Subsystem::GpuSurfaces => wgpu::DownlevelFlags::COMPUTE_SHADERS,
+        _ => wgpu::DownlevelFlags::empty(),
+    }
+}
+
+// a subsystem that was switched off and what the adapter lacks for it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DisabledSubsystem {
+    pub subsystem: Subsystem,
+    pub missing_features: wgpu::Features,
+    pub missing_flags: wgpu::DownlevelFlags,
+}
+
+impl DisabledSubsystem {
+    // the names of the missing features and downlevel flags, like "TIMESTAMP_QUERY"
+    pub fn missing(&self) -> Vec<&'static str> {
+        self.missing_features
+            .iter_names()
+            .map(|(name, _)| name)
+            .chain(self.missing_flags.iter_names().map(|(name, _)| name))
+            .collect()
+    }
+}
+
+impl fmt::Display for DisabledSubsystem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} disabled: the GPU lacks {}",
+            self.subsystem.name(),
+            self.missing().join(", ")
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Capabilities {
+    pub requested: wgpu::Features, // what the subsystems asked for
+    pub granted: wgpu::Features,   // the part of it the device was created with
+    pub downlevel_flags: wgpu::DownlevelFlags,
+    pub disabled: Vec<DisabledSubsystem>,
+}
+
+impl Capabilities {
+    // which of the wanted subsystems the adapter can run; granted is what to pass as
+    // DeviceDescriptor::required_features
+    pub fn negotiate(
+        wanted: &[Subsystem],
+        adapter_features: wgpu::Features,
+        downlevel_flags: wgpu::DownlevelFlags,
+    ) -> Self {
+        let requested = wanted.iter().fold(wgpu::Features::empty(), |features, s| {
+            features | required_features(*s)
+        });
+        let disabled = wanted
+            .iter()
+            .map(|&subsystem| DisabledSubsystem {
+                subsystem,
+                missing_features: required_features(subsystem) - adapter_features,
+                missing_flags: required_downlevel_flags(subsystem) - downlevel_flags,
+            })
+            .filter(|d| !d.missing_features.is_empty() || !d.missing_flags.is_empty())
+            .collect();
+        Self {
+            requested,
+            granted: requested & adapter_features,
+            downlevel_flags,
+            disabled,
+        }
+    }
+
+    pub fn of_adapter(wanted: &[Subsystem], adapter: &wgpu::Adapter) -> Self {
+        let flags = adapter.get_downlevel_capabilities().flags;
+        Self::negotiate(wanted, adapter.features(), flags)
+    }
+
+    pub fn is_enabled(&self, subsystem: Subsystem) -> bool {
+        self.disabled.iter().all(|d| d.subsystem != subsystem)
+            && self.granted.contains(required_features(subsystem))
+            && self.downlevel_flags.contains(required_downlevel_flags(subsystem))
+    }
+
+    // Error::Unsupported with what is missing when the subsystem is disabled
+    pub fn require(&self, subsystem: Subsystem) -> Result<()> {
+        if self.is_enabled(subsystem) {
+            return Ok(());
+        }
+        Err(Error::Unsupported(DisabledSubsystem {
+            subsystem,
+            missing_features: required_features(subsystem) - self.granted,
+            missing_flags: required_downlevel_flags(subsystem) - self.downlevel_flags,
+        }))
+    }
+
+    // the device limits for the granted features: push constants need room for them
+    pub fn limits(&self, limits: wgpu::Limits, adapter_limits: &wgpu::Limits) -> wgpu::Limits {
+        let mut limits = limits;
+        if self.granted.contains(wgpu::Features::PUSH_CONSTANTS) {
+            limits.max_push_constant_size = adapter_limits.max_push_constant_size.min(128);
+        }
+        limits
+    }
+
+    // one line per disabled subsystem
+    pub fn report(&self) -> String {
+        self.disabled.iter().map(|d| format!("{}\n", d)).collect()
+    }
+
+    // the granted features at info level and a warning per disabled subsystem, whose
+    // features were requested but are missing; called by the device initialization
+    pub fn log(&self) {
+        log::info!("optional GPU features granted: {:?}", self.granted);
+        for disabled in &self.disabled {
+            log::warn!("{}", disabled);
+        }
+    }
+}
+// endregion: capability negotiation
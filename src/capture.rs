@@ -0,0 +1,521 @@
+use std::f32::consts::PI;
+#[cfg(feature = "image")]
+use std::fs::File;
+#[cfg(feature = "image")]
+use std::io::BufWriter;
+#[cfg(feature = "image")]
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use bytemuck::cast_slice;
+use cgmath::{EuclideanSpace, InnerSpace, Matrix4, Point3, SquareMatrix, Vector3};
+use wgpu::util::DeviceExt;
+
+use super::color::{to_linear, to_srgb};
+use super::gizmo::bounds;
+use super::material::MaterialKind;
+use super::surface_data::ISurfaceOutput;
+use super::wgpu_simplified::{
+    IRenderPipeline, InitWgpuHeadless, create_bind_group, create_color_attachment,
+    create_depth_stencil_attachment, create_depth_view, create_msaa_color_attachment,
+    create_msaa_texture_view, create_vp_mat,
+};
+
+#[cfg(feature = "image")]
+use super::export::ExportConvention;
+
+// region: frame capture
+pub struct FrameCapture {
+    pub buffer: wgpu::Buffer,
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+    padded_bytes_per_row: u32,
+}
+
+impl FrameCapture {
+    // creates a readback buffer for a 4-byte-per-pixel color texture of the given size
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let unpadded_bytes_per_row = 4 * width;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Frame Capture Buffer"),
+            size: (padded_bytes_per_row * height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            buffer,
+            width,
+            height,
+            format,
+            padded_bytes_per_row,
+        }
+    }
+
+    // records a copy of the texture into the readback buffer; the texture must have
+    // been created with TextureUsages::COPY_SRC
+    pub fn copy_from_texture(&self, encoder: &mut wgpu::CommandEncoder, texture: &wgpu::Texture) {
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &self.buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(self.padded_bytes_per_row),
+                    rows_per_image: Some(self.height),
+                },
+            },
+            wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    // maps the readback buffer and returns tightly packed RGBA8 pixels; call this after
+    // the encoder holding copy_from_texture has been submitted
+    pub fn read_rgba(&self, device: &wgpu::Device) -> anyhow::Result<Vec<u8>> {
+        let slice = self.buffer.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+        device.poll(wgpu::PollType::Wait)?;
+        rx.recv()??;
+
+        let row_bytes = (4 * self.width) as usize;
+        let mut pixels = Vec::with_capacity(row_bytes * self.height as usize);
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(self.padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..row_bytes]);
+            }
+        }
+        self.buffer.unmap();
+
+        if matches!(
+            self.format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        ) {
+            for px in pixels.chunks_mut(4) {
+                px.swap(0, 2);
+            }
+        }
+        Ok(pixels)
+    }
+}
+// endregion: frame capture
+
+// region: image files
+// the PNG encoders need the image feature
+#[cfg(feature = "image")]
+pub fn encode_png(width: u32, height: u32, rgba: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = vec![];
+    {
+        let mut encoder = png::Encoder::new(&mut bytes, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(rgba)?;
+    }
+    Ok(bytes)
+}
+
+#[cfg(feature = "image")]
+pub fn save_png(
+    path: impl AsRef<Path>,
+    width: u32,
+    height: u32,
+    rgba: &[u8],
+) -> anyhow::Result<()> {
+    let file = File::create(path)?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(rgba)?;
+    Ok(())
+}
+
+// Frames rendered over a transparent clear color hold premultiplied colors wherever
+// they are partly covered: antialiased edges, blended surfaces, a shadow catcher. PNG
+// stores straight alpha, so the colors are divided by alpha, in linear space for sRGB
+// frames as returned by read_rgba(); fully transparent pixels become (0, 0, 0, 0).
+pub fn unpremultiply(rgba: &mut [u8]) {
+    for px in rgba.chunks_exact_mut(4) {
+        let a = px[3] as f32 / 255.0;
+        if a == 0.0 {
+            px.copy_from_slice(&[0; 4]);
+        } else if a < 1.0 {
+            let linear = to_linear(unorm(px)).map(|c| c / a);
+            px[..3].copy_from_slice(&to_unorm8(to_srgb(linear)));
+        }
+    }
+}
+
+// saves the frame with the image orientation expected by the export convention
+#[cfg(feature = "image")]
+pub fn save_png_as(
+    path: impl AsRef<Path>,
+    width: u32,
+    height: u32,
+    rgba: &[u8],
+    convention: &ExportConvention,
+) -> anyhow::Result<()> {
+    if convention.flip_v {
+        let mut flipped = rgba.to_vec();
+        convention.transform_image(width, height, &mut flipped);
+        save_png(path, width, height, &flipped)
+    } else {
+        save_png(path, width, height, rgba)
+    }
+}
+// endregion: image files
+
+// region: jitter accumulation
+// Progressive anti-aliasing of stills: the static scene is rendered many times, each
+// time with the projection shifted by a different sub-pixel offset, and the frames are
+// averaged. Edges, thin wireframe lines and blended surfaces all converge to their
+// coverage-weighted color, which MSAA alone only gives for geometry edges. The offsets
+// follow the Halton (2, 3) sequence, so any prefix of it covers the pixel evenly.
+
+// i-th element of the van der Corput sequence in the given base, in [0, 1)
+fn radical_inverse(mut i: u32, base: u32) -> f32 {
+    let (mut inverse, mut scale) = (0.0, 1.0 / base as f32);
+    while i > 0 {
+        inverse += (i % base) as f32 * scale;
+        i /= base;
+        scale /= base as f32;
+    }
+    inverse
+}
+
+// sub-pixel offsets in pixels, within [-0.5, 0.5); the first one is the pixel center
+pub fn jitter_offsets(count: u32) -> Vec<[f32; 2]> {
+    (0..count)
+        .map(|i| [radical_inverse(i, 2), radical_inverse(i, 3)])
+        .map(|[x, y]| [(x + 0.5).fract() - 0.5, (y + 0.5).fract() - 0.5])
+        .collect()
+}
+
+// shifts the image by offset pixels (x right, y down) when multiplied in front of a
+// projection matrix
+pub fn jitter_mat(offset: [f32; 2], width: u32, height: u32) -> Matrix4<f32> {
+    let dx = 2.0 * offset[0] / width as f32;
+    let dy = -2.0 * offset[1] / height as f32;
+    Matrix4::from_translation(Vector3::new(dx, dy, 0.0))
+}
+
+// the color channels of an RGBA8 pixel in [0, 1]
+fn unorm(px: &[u8]) -> [f32; 3] {
+    [0, 1, 2].map(|c| px[c] as f32 / 255.0)
+}
+
+fn to_unorm8(color: [f32; 3]) -> [u8; 3] {
+    color.map(|c| (255.0 * c.clamp(0.0, 1.0)).round() as u8)
+}
+
+// running sum of sRGB RGBA8 frames; colors are averaged in linear space, so dark and
+// bright sides of an edge mix like light does, alpha is averaged as is
+pub struct Accumulator {
+    pub width: u32,
+    pub height: u32,
+    frames: u32,
+    sum: Vec<f32>,
+}
+
+impl Accumulator {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            frames: 0,
+            sum: vec![0.0; 4 * width as usize * height as usize],
+        }
+    }
+
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    // adds a frame as returned by FrameCapture::read_rgba or InitWgpuHeadless::read_rgba
+    pub fn add(&mut self, rgba: &[u8]) -> anyhow::Result<()> {
+        if rgba.len() != self.sum.len() {
+            anyhow::bail!(
+                "frame has {} bytes, expected {} for {} x {} RGBA",
+                rgba.len(),
+                self.sum.len(),
+                self.width,
+                self.height
+            );
+        }
+        for (px, sum) in rgba.chunks_exact(4).zip(self.sum.chunks_exact_mut(4)) {
+            for (sum, c) in sum.iter_mut().zip(to_linear(unorm(px))) {
+                *sum += c;
+            }
+            sum[3] += px[3] as f32 / 255.0;
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    // average of the frames added so far, as sRGB RGBA8
+    pub fn resolve(&self) -> Vec<u8> {
+        let n = self.frames.max(1) as f32;
+        self.sum
+            .chunks_exact(4)
+            .flat_map(|sum| {
+                let [r, g, b] = to_unorm8(to_srgb([0, 1, 2].map(|c| sum[c] / n)));
+                let a = (255.0 * (sum[3] / n).clamp(0.0, 1.0)).round() as u8;
+                [r, g, b, a]
+            })
+            .collect()
+    }
+}
+// endregion: jitter accumulation
+
+// region: video export
+// Frames of an animation for a video, rendered at a fixed timestep: frame k shows the
+// animation at k / frame_rate seconds however long it took to render, so the frames
+// played back at frame_rate run at the speed of the animation even when the machine
+// renders slower than real time. Each frame, the state animates to time() instead of
+// the wall clock, saves the rendered frame to frame_path() and calls advance().
+#[derive(Clone, Debug, PartialEq)]
+pub struct VideoExport {
+    pub dir: PathBuf,
+    pub frame_rate: f32,
+    pub frames: u32,
+    frame: u32,
+}
+
+impl VideoExport {
+    pub fn new(dir: impl Into<PathBuf>, frame_rate: f32, frames: u32) -> Self {
+        Self {
+            dir: dir.into(),
+            frame_rate: if frame_rate > 0.0 { frame_rate } else { 60.0 },
+            frames,
+            frame: 0,
+        }
+    }
+
+    // frames covering seconds of animation, at least one
+    pub fn for_duration(dir: impl Into<PathBuf>, frame_rate: f32, seconds: f32) -> Self {
+        let mut export = Self::new(dir, frame_rate, 1);
+        export.frames = ((seconds * export.frame_rate).round() as u32).max(1);
+        export
+    }
+
+    pub fn dt(&self) -> f32 {
+        1.0 / self.frame_rate
+    }
+
+    // index of the frame to render next
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.frames
+    }
+
+    // animation time of the frame to render next
+    pub fn time(&self) -> Duration {
+        Duration::from_secs_f64(self.frame as f64 / self.frame_rate as f64)
+    }
+
+    pub fn frame_path(&self) -> PathBuf {
+        self.dir.join(format!("frame_{:05}.png", self.frame))
+    }
+
+    // moves on to the next frame; false once all frames are done
+    pub fn advance(&mut self) -> bool {
+        self.frame = (self.frame + 1).min(self.frames);
+        !self.is_finished()
+    }
+}
+// endregion: video export
+
+// region: mesh preview
+// A still of a mesh shaded like the chapter surfaces, with its wireframe, e.g. for
+// thumbnails from tools and build scripts. The camera looks at the center of the
+// bounding box along the (1, 1, 1) diagonal, from far enough for the bounding sphere
+// to fit the view; returns the RGBA8 pixels of the headless target.
+pub fn render_preview(
+    init: &InitWgpuHeadless,
+    output: &ISurfaceOutput,
+    material: MaterialKind,
+    wireframe: bool,
+) -> anyhow::Result<Vec<u8>> {
+    let device = &init.device;
+    let [min, max] = bounds(output.positions.iter().copied())
+        .ok_or_else(|| anyhow::anyhow!("the mesh has no vertices"))?;
+    let (min, max) = (Vector3::from(min), Vector3::from(max));
+    let center = 0.5 * (min + max);
+    let radius = (0.5 * (max - min).magnitude()).max(1e-3);
+    // the vertical field of view of create_vp_mat is 72 degrees
+    let distance = 1.1 * radius / (0.2 * PI).sin();
+    let eye = Point3::from_vec(center + distance * Vector3::new(1.0, 1.0, 1.0).normalize());
+    let (_, _, vp_mat) = create_vp_mat(
+        eye,
+        Point3::from_vec(center),
+        Vector3::unit_y(),
+        init.width as f32 / init.height as f32,
+    );
+    let identity = Matrix4::<f32>::identity();
+    let mut vert_uniforms: Vec<f32> = vec![];
+    for mat in [vp_mat, identity, identity] {
+        let mat_ref: &[f32; 16] = mat.as_ref();
+        vert_uniforms.extend_from_slice(mat_ref);
+    }
+    let light_uniforms = [
+        -0.5f32, -0.5, -0.5, 0.0, eye.x, eye.y, eye.z, 0.0, 1.0, 1.0, 1.0, 0.0,
+    ];
+    let material_uniforms = material.default_material().uniform_data();
+    let buffer = |label: &str, contents: &[u8], usage: wgpu::BufferUsages| {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents,
+            usage,
+        })
+    };
+    let uniform = wgpu::BufferUsages::UNIFORM;
+    let vert_uniform_buffer = buffer(
+        "Preview Vertex Uniforms",
+        cast_slice(&vert_uniforms),
+        uniform,
+    );
+    let light_uniform_buffer = buffer(
+        "Preview Light Uniforms",
+        cast_slice(&light_uniforms),
+        uniform,
+    );
+    let material_uniform_buffer = buffer(
+        "Preview Material Uniforms",
+        cast_slice(&material_uniforms),
+        uniform,
+    );
+    let (vert_layout, vert_bind_group) = create_bind_group(
+        device,
+        vec![wgpu::ShaderStages::VERTEX],
+        &[vert_uniform_buffer.as_entire_binding()],
+    )?;
+    let (frag_layout, frag_bind_group) = create_bind_group(
+        device,
+        vec![wgpu::ShaderStages::FRAGMENT, wgpu::ShaderStages::FRAGMENT],
+        &[
+            light_uniform_buffer.as_entire_binding(),
+            material_uniform_buffer.as_entire_binding(),
+        ],
+    )?;
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Preview Pipeline Layout"),
+        bind_group_layouts: &[&vert_layout, &frag_layout],
+        push_constant_ranges: &[],
+    });
+    let vs_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Preview Vertex Shader"),
+        source: wgpu::ShaderSource::Wgsl(PREVIEW_VERT_WGSL.into()),
+    });
+    let fs_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(material.fragment_label()),
+        source: wgpu::ShaderSource::Wgsl(material.fragment_source().into()),
+    });
+    // pos, norm, col
+    let vertex_buffer_layout = [wgpu::VertexBufferLayout {
+        array_stride: 36,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3],
+    }];
+
+    // the shape, then the wireframe where the mesh has one
+    let mut draws = vec![(
+        wgpu::PrimitiveTopology::TriangleList,
+        &output.colors,
+        &output.indices,
+    )];
+    if wireframe && output.colors2.len() == output.positions.len() {
+        draws.push((
+            wgpu::PrimitiveTopology::LineList,
+            &output.colors2,
+            &output.indices2,
+        ));
+    }
+    let mut meshes = vec![];
+    for (topology, colors, indices) in draws {
+        if indices.is_empty() {
+            continue;
+        }
+        let vertices: Vec<f32> = (0..output.positions.len())
+            .flat_map(|i| {
+                let color = to_linear(colors.get(i).copied().unwrap_or([1.0; 3]));
+                let normal = output.normals.get(i).copied().unwrap_or([0.0, 1.0, 0.0]);
+                [output.positions[i], normal, color].concat()
+            })
+            .collect();
+        let pipeline = IRenderPipeline {
+            topology,
+            vs_shader: Some(&vs_shader),
+            fs_shader: Some(&fs_shader),
+            pipeline_layout: Some(&pipeline_layout),
+            vertex_buffer_layout: &vertex_buffer_layout,
+            ..Default::default()
+        }
+        .new(init)?;
+        let vertex_buffer = buffer(
+            "Preview Vertex Buffer",
+            cast_slice(&vertices),
+            wgpu::BufferUsages::VERTEX,
+        );
+        let index_buffer = buffer(
+            "Preview Index Buffer",
+            cast_slice(indices),
+            wgpu::BufferUsages::INDEX,
+        );
+        meshes.push((pipeline, vertex_buffer, index_buffer, indices.len() as u32));
+    }
+
+    let msaa_texture_view = create_msaa_texture_view(init);
+    let depth_texture_view = create_depth_view(init);
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Preview Encoder"),
+    });
+    {
+        let color_attachment = if init.sample_count == 1 {
+            create_color_attachment(&init.view)
+        } else {
+            create_msaa_color_attachment(&init.view, &msaa_texture_view)
+        };
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Preview Pass"),
+            color_attachments: &[Some(color_attachment)],
+            depth_stencil_attachment: Some(create_depth_stencil_attachment(&depth_texture_view)),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        render_pass.set_bind_group(0, &vert_bind_group, &[]);
+        render_pass.set_bind_group(1, &frag_bind_group, &[]);
+        for (pipeline, vertex_buffer, index_buffer, count) in meshes.iter() {
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            render_pass.draw_indexed(0..*count, 0, 0..1);
+        }
+    }
+    init.queue.submit(std::iter::once(encoder.finish()));
+    init.read_rgba()
+}
+
+// shader_vert.wgsl of the chapter surfaces
+pub const PREVIEW_VERT_WGSL: &str = include_str!("../ch02/01_simple_surface/shader_vert.wgsl");
+// endregion: mesh preview
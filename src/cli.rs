@@ -0,0 +1,306 @@
+use super::colormap;
+use super::labels::NumberFormat;
+use super::material::MaterialKind;
+use super::scene::Scene;
+use super::theme::Theme;
+use super::wgpu_simplified::PresentModePreference;
+
+// Command line options shared by the chapter binaries, as named flags:
+//   --msaa N               sample count: 1, 2, 4 or 8
+//   --colormap NAME        surface colormap
+//   --wireframe-color NAME wireframe colormap
+//   --resolution N         grid resolution, 2 to 250
+//   --surface-type N       index of the built-in surface
+//   --material NAME        surface shading: phong, pbr or matcap
+//   --present-mode NAME    fifo (vsync), immediate, mailbox or auto_no_vsync
+//   --keymap FILE          TOML file of key bindings, see app::InputMap
+//   --scene FILE           saved scene to start from, see scene::Scene; sets the
+//                          sample count, material and colormaps of the scene
+//   --decimals N           decimals of numeric read-outs, 0 to 9
+//   --decimal-separator C  '.' or ',' in numeric read-outs
+//   --theme NAME           overlay and wireframe theme: standard or high_contrast
+//   --ui-scale X           size factor of the overlays, 0.5 to 4
+//   --seed N               seed of random choices, e.g. random surface changes; the
+//                          seed of --scene unless given
+//   --recovery-file FILE   on a panic, save the scene to FILE and print the command
+//                          that reproduces it, see recovery::Recovery
+// A value can also follow an '=' (--msaa=4). Arguments without "--" are collected
+// in positional, in order, for the options specific to a binary (file paths, modes);
+// everything after a bare "--" is positional as well.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SurfaceArgs {
+    pub msaa: u32,
+    pub colormap: String,
+    pub wireframe_color: String,
+    pub resolution: Option<u16>,
+    pub surface_type: Option<u32>,
+    pub material: MaterialKind,
+    pub present_mode: PresentModePreference,
+    pub keymap: Option<String>,
+    pub scene: Option<Scene>,
+    pub scene_file: Option<String>, // also where the examples save the scene
+    pub number_format: NumberFormat, // colorbar ticks, probes and overlays
+    pub theme: Theme,
+    pub ui_scale: f32,
+    pub seed: Option<u64>, // None for a random one
+    pub recovery_file: Option<String>,
+    pub positional: Vec<String>,
+    surface_types: Option<u32>,
+}
+
+impl Default for SurfaceArgs {
+    fn default() -> Self {
+        Self {
+            msaa: 1,
+            colormap: "jet".to_string(),
+            wireframe_color: "white".to_string(),
+            resolution: None,
+            surface_type: None,
+            material: MaterialKind::default(),
+            present_mode: PresentModePreference::default(),
+            keymap: None,
+            scene: None,
+            scene_file: None,
+            number_format: NumberFormat::default(),
+            theme: Theme::default(),
+            ui_scale: 1.0,
+            seed: None,
+            recovery_file: None,
+            positional: vec![],
+            surface_types: None,
+        }
+    }
+}
+
+impl SurfaceArgs {
+    // default colormap of the binary
+    pub fn colormap(mut self, name: &str) -> Self {
+        self.colormap = name.to_string();
+        self
+    }
+
+    // number of surface types the binary offers; --surface-type is rejected
+    // unless this is set
+    pub fn surface_types(mut self, count: u32) -> Self {
+        self.surface_types = Some(count);
+        self
+    }
+
+    // parses the arguments after the program name on top of the defaults in self
+    pub fn parse(mut self, args: impl IntoIterator<Item = String>) -> anyhow::Result<Self> {
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                self.positional.extend(args.by_ref());
+                break;
+            }
+            let Some(flag) = arg.strip_prefix("--") else {
+                self.positional.push(arg);
+                continue;
+            };
+            let (name, value) = match flag.split_once('=') {
+                Some((name, value)) => (name.to_string(), value.to_string()),
+                None => {
+                    let value = args.next();
+                    let value = value.ok_or_else(|| anyhow::anyhow!("--{} needs a value", flag))?;
+                    (flag.to_string(), value)
+                }
+            };
+            let number = |max: u32| -> anyhow::Result<u32> {
+                value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|v| *v <= max)
+                    .ok_or_else(|| anyhow::anyhow!("invalid value '{}' for --{}", value, name))
+            };
+            match name.as_str() {
+                "msaa" => {
+                    self.msaa = number(8)?;
+                    if ![1, 2, 4, 8].contains(&self.msaa) {
+                        anyhow::bail!("--msaa must be 1, 2, 4 or 8, not {}", self.msaa);
+                    }
+                }
+                "colormap" | "wireframe-color" => {
+                    // registered colormaps count, if the binary registers before parsing
+                    let registry = colormap::registry().read().unwrap();
+                    if !registry.contains(&value) {
+                        anyhow::bail!(
+                            "unknown colormap '{}' for --{}; available: {}",
+                            value,
+                            name,
+                            registry.names().join(", ")
+                        );
+                    }
+                    if name == "colormap" {
+                        self.colormap = value;
+                    } else {
+                        self.wireframe_color = value;
+                    }
+                }
+                "resolution" => {
+                    let resolution = number(250)?;
+                    if resolution < 2 {
+                        anyhow::bail!("--resolution must be between 2 and 250");
+                    }
+                    self.resolution = Some(resolution as u16);
+                }
+                "surface-type" => {
+                    let Some(count) = self.surface_types else {
+                        anyhow::bail!("this program has no --surface-type");
+                    };
+                    let surface_type = number(u32::MAX)?;
+                    if surface_type >= count {
+                        anyhow::bail!("--surface-type must be below {}", count);
+                    }
+                    self.surface_type = Some(surface_type);
+                }
+                "material" => {
+                    self.material = MaterialKind::from_name(&value).ok_or_else(|| {
+                        let names = MaterialKind::ALL.map(|kind| kind.name());
+                        anyhow::anyhow!(
+                            "unknown material '{}'; available: {}",
+                            value,
+                            names.join(", ")
+                        )
+                    })?;
+                }
+                "present-mode" => {
+                    self.present_mode =
+                        PresentModePreference::from_name(&value).ok_or_else(|| {
+                            let names = PresentModePreference::ALL.map(|mode| mode.name());
+                            anyhow::anyhow!(
+                                "unknown present mode '{}'; available: {}",
+                                value,
+                                names.join(", ")
+                            )
+                        })?;
+                }
+                "keymap" => self.keymap = Some(value),
+                "scene" => {
+                    let scene = Scene::load(&value)?;
+                    if ![1, 2, 4, 8].contains(&scene.msaa) {
+                        anyhow::bail!("{}: msaa must be 1, 2, 4 or 8, not {}", value, scene.msaa);
+                    }
+                    self.msaa = scene.msaa;
+                    self.material = scene.material.kind();
+                    self.colormap = scene.colormap.clone();
+                    self.wireframe_color = scene.wireframe_color.clone();
+                    self.seed.get_or_insert(scene.seed);
+                    self.scene = Some(scene);
+                    self.scene_file = Some(value);
+                }
+                "decimals" => self.number_format.decimals = number(9)? as usize,
+                "decimal-separator" => {
+                    self.number_format.decimal_comma = match value.as_str() {
+                        "." => false,
+                        "," => true,
+                        _ => anyhow::bail!("--decimal-separator must be '.' or ','"),
+                    };
+                }
+                "theme" => {
+                    self.theme = Theme::from_name(&value).ok_or_else(|| {
+                        let names = Theme::ALL.map(|theme| theme.name());
+                        anyhow::anyhow!(
+                            "unknown theme '{}'; available: {}",
+                            value,
+                            names.join(", ")
+                        )
+                    })?;
+                }
+                "ui-scale" => {
+                    self.ui_scale = value
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|scale| (0.5..=4.0).contains(scale))
+                        .ok_or_else(|| anyhow::anyhow!("--ui-scale must be between 0.5 and 4"))?;
+                }
+                "seed" => {
+                    let seed = value.parse::<u64>();
+                    let seed = seed.map_err(|_| anyhow::anyhow!("invalid seed '{}'", value))?;
+                    self.seed = Some(seed);
+                }
+                "recovery-file" => self.recovery_file = Some(value),
+                _ => anyhow::bail!("unknown option --{}", name),
+            }
+        }
+        Ok(self)
+    }
+
+    // parses the process arguments; prints the usage and exits on --help or on an
+    // invalid argument. positional_help describes the positional arguments.
+    pub fn parse_env(self, positional_help: &str) -> Self {
+        let mut args = std::env::args();
+        let program = args.next().unwrap_or_default();
+        let program = std::path::Path::new(&program)
+            .file_name()
+            .map_or(program.clone(), |name| name.to_string_lossy().into_owned());
+        let args: Vec<String> = args.collect();
+        let usage = self.usage(&program, positional_help);
+        if args.iter().any(|a| a == "--help" || a == "-h") {
+            println!("{}", usage);
+            std::process::exit(0);
+        }
+        match self.parse(args) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                eprintln!("error: {}\n\n{}", e, usage);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    pub fn usage(&self, program: &str, positional_help: &str) -> String {
+        let mut usage = format!(
+            "usage: {} [options] {}\n\noptions:\n",
+            program, positional_help
+        );
+        usage += &format!(
+            "  --msaa N                sample count: 1, 2, 4 or 8 [{}]\n",
+            self.msaa
+        );
+        usage += &format!(
+            "  --colormap NAME         surface colormap [{}]\n",
+            self.colormap
+        );
+        usage += &format!(
+            "  --wireframe-color NAME  wireframe colormap [{}]\n",
+            self.wireframe_color
+        );
+        usage += "  --resolution N          grid resolution, 2 to 250\n";
+        if let Some(count) = self.surface_types {
+            usage += &format!(
+                "  --surface-type N        built-in surface, 0 to {}\n",
+                count - 1
+            );
+        }
+        usage += &format!(
+            "  --material NAME         phong, pbr or matcap [{}]\n",
+            self.material.name()
+        );
+        usage += &format!(
+            "  --present-mode NAME     fifo, immediate, mailbox or auto_no_vsync [{}]\n",
+            self.present_mode.name()
+        );
+        usage += "  --keymap FILE           TOML file of key bindings\n";
+        usage += "  --scene FILE            saved scene to start from (.ron or .json)\n";
+        usage += &format!(
+            "  --decimals N            decimals of numeric read-outs, 0 to 9 [{}]\n",
+            self.number_format.decimals
+        );
+        usage += "  --decimal-separator C   '.' or ',' in numeric read-outs\n";
+        usage += &format!(
+            "  --theme NAME            standard or high_contrast [{}]\n",
+            self.theme.name()
+        );
+        usage += &format!(
+            "  --ui-scale X            size of the overlays, 0.5 to 4 [{}]\n",
+            self.ui_scale
+        );
+        usage += "  --seed N                seed of random choices\n";
+        usage += "  --recovery-file FILE    on a crash, save the scene to FILE\n";
+        usage += "  -h, --help              print this help\n";
+        let names = colormap::registry().read().unwrap().names();
+        usage += &format!("\ncolormaps: {}", names.join(", "));
+        usage
+    }
+}
@@ -0,0 +1,84 @@
+// region: color spaces
+// The color pipeline: colormaps define display colors, sRGB encoded, which is what
+// ISurfaceOutput holds and what OBJ/PLY export writes. Vertex buffers hold linear
+// colors, converted with to_linear() where the vertices are built, so shading and
+// interpolation work in linear light. InitWgpu renders to an sRGB format, or to an
+// sRGB view of the surface if it offers no sRGB format, and the hardware encodes the
+// linear shader output; output_color() is what a shader has to write to any format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputEncoding {
+    Hardware, // sRGB formats encode linear values on write
+    Shader,   // other 8 and 10 bit unorm formats store what the shader writes
+    Linear,   // float formats keep linear values, e.g. HDR targets
+}
+
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        12.92 * c
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+pub fn to_linear(srgb: [f32; 3]) -> [f32; 3] {
+    srgb.map(srgb_to_linear)
+}
+
+pub fn to_srgb(linear: [f32; 3]) -> [f32; 3] {
+    linear.map(linear_to_srgb)
+}
+
+pub fn output_encoding(format: wgpu::TextureFormat) -> OutputEncoding {
+    use wgpu::TextureFormat as F;
+    match format {
+        _ if format.is_srgb() => OutputEncoding::Hardware,
+        F::Rgba8Unorm | F::Bgra8Unorm | F::Rgb10a2Unorm => OutputEncoding::Shader,
+        _ => OutputEncoding::Linear,
+    }
+}
+
+// the value a shader writes to show the linear color on a target of the format
+pub fn output_color(linear: [f32; 3], format: wgpu::TextureFormat) -> [f32; 3] {
+    match output_encoding(format) {
+        OutputEncoding::Shader => to_srgb(linear),
+        OutputEncoding::Hardware | OutputEncoding::Linear => linear,
+    }
+}
+
+// clear color of a render pass on a target of the format showing the display color
+pub fn clear_color(srgb: [f32; 3], format: wgpu::TextureFormat) -> wgpu::Color {
+    let [r, g, b] = output_color(to_linear(srgb), format).map(f64::from);
+    wgpu::Color { r, g, b, a: 1.0 }
+}
+
+// texture and view format to render to a surface offering the formats: the first sRGB
+// one, else the first format viewed through its sRGB variant where it has one
+pub fn surface_formats(
+    formats: &[wgpu::TextureFormat],
+) -> (wgpu::TextureFormat, wgpu::TextureFormat) {
+    match formats.iter().find(|format| format.is_srgb()) {
+        Some(&format) => (format, format),
+        None => (formats[0], formats[0].add_srgb_suffix()),
+    }
+}
+
+// the conversions for shaders, e.g. to write linear vertex colors from sRGB colormap
+// stops or to encode the output for OutputEncoding::Shader targets
+pub const COLOR_SPACE_WGSL: &str = r#"
+fn srgb_to_linear(c: vec3f) -> vec3f {
+    return select(pow((c + 0.055) / 1.055, vec3f(2.4)), c / 12.92, c <= vec3f(0.04045));
+}
+
+fn linear_to_srgb(c: vec3f) -> vec3f {
+    return select(1.055 * pow(c, vec3f(1.0 / 2.4)) - 0.055, 12.92 * c, c <= vec3f(0.0031308));
+}
+"#;
+// endregion: color spaces
@@ -1,4 +1,7 @@
 #![allow(dead_code)]
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::{LazyLock, RwLock};
 
 pub fn color_lerp(colors: [[f32; 3]; 11], min: f32, max: f32, mut t: f32) -> [f32; 3] {
     if t < min {
@@ -23,343 +26,828 @@ pub fn color_lerp(colors: [[f32; 3]; 11], min: f32, max: f32, mut t: f32) -> [f3
     }
 }
 
+// names of the built-in colormaps, in the order of Builtin
+pub const COLORMAP_NAMES: [&str; 25] = [
+    "hsv", "hot", "cool", "spring", "summer", "autumn", "winter", "bone", "cooper", "greys",
+    "rainbow", "rainbow_soft", "white", "black", "red", "green", "blue", "yellow", "cyan",
+    "fuchsia", "terrain", "ocean", "viridis", "plasma", "jet",
+];
+
+// 11 evenly spaced stops of a built-in or registered colormap; any other name falls
+// back to "jet"
 pub fn colormap_data(colormap_name: &str) -> [[f32; 3]; 11] {
-    let colors = match colormap_name {
-        "hsv" => [
-            [1.0, 0.0, 0.0],
-            [1.0, 0.5, 0.0],
-            [0.97, 1.0, 0.01],
-            [0.0, 0.99, 0.04],
-            [0.0, 0.98, 0.52],
-            [0.0, 0.98, 1.0],
-            [0.01, 0.49, 1.0],
-            [0.03, 0.0, 0.99],
-            [1.0, 0.0, 0.96],
-            [1.0, 0.0, 0.49],
-            [1.0, 0.0, 0.02],
-        ],
-
-        "hot" => [
-            [0.0, 0.0, 0.0],
-            [0.3, 0.0, 0.0],
-            [0.6, 0.0, 0.0],
-            [0.9, 0.0, 0.0],
-            [0.93, 0.27, 0.0],
-            [0.97, 0.55, 0.0],
-            [1.0, 0.82, 0.0],
-            [1.0, 0.87, 0.25],
-            [1.0, 0.91, 0.5],
-            [1.0, 0.96, 0.75],
-            [1.0, 1.0, 1.0],
-        ],
-
-        "cool" => [
-            [0.49, 0.0, 0.7],
-            [0.45, 0.0, 0.85],
-            [0.42, 0.15, 0.89],
-            [0.38, 0.29, 0.93],
-            [0.27, 0.57, 0.91],
-            [0.0, 0.8, 0.77],
-            [0.0, 0.97, 0.57],
-            [0.0, 0.98, 0.46],
-            [0.0, 1.0, 0.35],
-            [0.16, 1.0, 0.03],
-            [0.58, 1.0, 0.0],
-        ],
-
-        "spring" => [
-            [1.0, 0.0, 1.0],
-            [1.0, 0.1, 0.9],
-            [1.0, 0.2, 0.8],
-            [1.0, 0.3, 0.7],
-            [1.0, 0.4, 0.6],
-            [1.0, 0.5, 0.5],
-            [1.0, 0.6, 0.4],
-            [1.0, 0.7, 0.3],
-            [1.0, 0.8, 0.2],
-            [1.0, 0.9, 0.1],
-            [1.0, 1.0, 0.0],
-        ],
-
-        "summer" => [
-            [0.0, 0.5, 0.4],
-            [0.1, 0.55, 0.4],
-            [0.2, 0.6, 0.4],
-            [0.3, 0.65, 0.4],
-            [0.4, 0.7, 0.4],
-            [0.5, 0.75, 0.4],
-            [0.6, 0.8, 0.4],
-            [0.7, 0.85, 0.4],
-            [0.8, 0.9, 0.4],
-            [0.9, 0.95, 0.4],
-            [1.0, 1.0, 0.4],
-        ],
-
-        "autumn" => [
-            [1.0, 0.0, 0.0],
-            [1.0, 0.1, 0.0],
-            [1.0, 0.2, 0.0],
-            [1.0, 0.3, 0.0],
-            [1.0, 0.4, 0.0],
-            [1.0, 0.5, 0.0],
-            [1.0, 0.6, 0.0],
-            [1.0, 0.7, 0.0],
-            [1.0, 0.8, 0.0],
-            [1.0, 0.9, 0.0],
-            [1.0, 1.0, 0.0],
-        ],
-
-        "winter" => [
-            [0.0, 0.0, 1.0],
-            [0.0, 0.1, 0.95],
-            [0.0, 0.2, 0.9],
-            [0.0, 0.3, 0.85],
-            [0.0, 0.4, 0.8],
-            [0.0, 0.5, 0.75],
-            [0.0, 0.6, 0.7],
-            [0.0, 0.7, 0.65],
-            [0.0, 0.8, 0.6],
-            [0.0, 0.9, 0.55],
-            [0.0, 1.0, 0.5],
-        ],
-
-        "bone" => [
-            [0.0, 0.0, 0.0],
-            [0.08, 0.08, 0.11],
-            [0.16, 0.16, 0.23],
-            [0.25, 0.25, 0.34],
-            [0.33, 0.33, 0.45],
-            [0.41, 0.44, 0.54],
-            [0.5, 0.56, 0.62],
-            [0.58, 0.67, 0.7],
-            [0.66, 0.78, 0.78],
-            [0.83, 0.89, 0.89],
-            [1.0, 1.0, 1.0],
-        ],
-
-        "cooper" => [
-            [0.0, 0.0, 0.0],
-            [0.13, 0.08, 0.05],
-            [0.25, 0.16, 0.1],
-            [0.38, 0.24, 0.15],
-            [0.5, 0.31, 0.2],
-            [0.62, 0.39, 0.25],
-            [0.75, 0.47, 0.3],
-            [0.87, 0.55, 0.35],
-            [1.0, 0.63, 0.4],
-            [1.0, 0.71, 0.45],
-            [1.0, 0.78, 0.5],
-        ],
-
-        "greys" => [
-            [0.0, 0.0, 0.0],
-            [0.1, 0.1, 0.1],
-            [0.2, 0.2, 0.2],
-            [0.3, 0.3, 0.3],
-            [0.4, 0.4, 0.4],
-            [0.5, 0.5, 0.5],
-            [0.6, 0.6, 0.6],
-            [0.7, 0.7, 0.7],
-            [0.8, 0.8, 0.8],
-            [0.9, 0.9, 0.9],
-            [1.0, 1.0, 1.0],
-        ],
-
-        "rainbow" => [
-            [0.588, 0.000, 0.353],
-            [0.118, 0.000, 0.698],
-            [0.000, 0.059, 0.914],
-            [0.000, 0.297, 1.000],
-            [0.035, 0.677, 0.918],
-            [0.173, 1.000, 0.588],
-            [0.508, 1.000, 0.118],
-            [0.837, 0.951, 0.000],
-            [1.000, 0.725, 0.000],
-            [1.000, 0.348, 0.000],
-            [1.000, 0.000, 0.000],
-        ],
-
-        "rainbow_soft" => [
-            [0.490, 0.000, 0.702],
-            [0.780, 0.000, 0.706],
-            [1.000, 0.000, 0.475],
-            [1.000, 0.424, 0.000],
-            [0.871, 0.761, 0.000],
-            [0.588, 1.000, 0.000],
-            [0.000, 1.000, 0.216],
-            [0.000, 0.965, 0.588],
-            [0.196, 0.655, 0.871],
-            [0.404, 0.200, 0.922],
-            [0.486, 0.000, 0.729],
-        ],
-
-        "white" => convert_f32([
-            [1, 1, 1],
-            [1, 1, 1],
-            [1, 1, 1],
-            [1, 1, 1],
-            [1, 1, 1],
-            [1, 1, 1],
-            [1, 1, 1],
-            [1, 1, 1],
-            [1, 1, 1],
-            [1, 1, 1],
-            [1, 1, 1],
-        ]),
-
-        "black" => convert_f32([
-            [0, 0, 0],
-            [0, 0, 0],
-            [0, 0, 0],
-            [0, 0, 0],
-            [0, 0, 0],
-            [0, 0, 0],
-            [0, 0, 0],
-            [0, 0, 0],
-            [0, 0, 0],
-            [0, 0, 0],
-            [0, 0, 0],
-        ]),
-
-        "red" => convert_f32([
-            [1, 0, 0],
-            [1, 0, 0],
-            [1, 0, 0],
-            [1, 0, 0],
-            [1, 0, 0],
-            [1, 0, 0],
-            [1, 0, 0],
-            [1, 0, 0],
-            [1, 0, 0],
-            [1, 0, 0],
-            [1, 0, 0],
-        ]),
-
-        "green" => convert_f32([
-            [0, 1, 0],
-            [0, 1, 0],
-            [0, 1, 0],
-            [0, 1, 0],
-            [0, 1, 0],
-            [0, 1, 0],
-            [0, 1, 0],
-            [0, 1, 0],
-            [0, 1, 0],
-            [0, 1, 0],
-            [0, 1, 0],
-        ]),
-
-        "blue" => convert_f32([
-            [0, 0, 1],
-            [0, 0, 1],
-            [0, 0, 1],
-            [0, 0, 1],
-            [0, 0, 1],
-            [0, 0, 1],
-            [0, 0, 1],
-            [0, 0, 1],
-            [0, 0, 1],
-            [0, 0, 1],
-            [0, 0, 1],
-        ]),
-
-        "yellow" => convert_f32([
-            [1, 1, 0],
-            [1, 1, 0],
-            [1, 1, 0],
-            [1, 1, 0],
-            [1, 1, 0],
-            [1, 1, 0],
-            [1, 1, 0],
-            [1, 1, 0],
-            [1, 1, 0],
-            [1, 1, 0],
-            [1, 1, 0],
-        ]),
-
-        "cyan" => convert_f32([
-            [0, 1, 1],
-            [0, 1, 1],
-            [0, 1, 1],
-            [0, 1, 1],
-            [0, 1, 1],
-            [0, 1, 1],
-            [0, 1, 1],
-            [0, 1, 1],
-            [0, 1, 1],
-            [0, 1, 1],
-            [0, 1, 1],
-        ]),
-
-        "fuchsia" => convert_f32([
-            [1, 0, 1],
-            [1, 0, 1],
-            [1, 0, 1],
-            [1, 0, 1],
-            [1, 0, 1],
-            [1, 0, 1],
-            [1, 0, 1],
-            [1, 0, 1],
-            [1, 0, 1],
-            [1, 0, 1],
-            [1, 0, 1],
-        ]),
-
-        "terrain" => [
-            [0.1765, 0.2471, 0.6471],
-            [0.0392, 0.5176, 0.9176],
-            [0.0000, 0.7451, 0.5725],
-            [0.3098, 0.8627, 0.4588],
-            [0.7098, 0.9451, 0.5451],
-            [0.9686, 0.9608, 0.5843],
-            [0.7686, 0.7059, 0.4784],
-            [0.5451, 0.4196, 0.3529],
-            [0.6196, 0.5098, 0.4863],
-            [0.7765, 0.7137, 0.7020],
-            [0.9490, 0.9333, 0.9333],
-        ],
-
-        "ocean" => [
-            [0.0000, 0.4627, 0.0275],
-            [0.0000, 0.3216, 0.1176],
-            [0.0000, 0.1686, 0.2196],
-            [0.0000, 0.0392, 0.3098],
-            [0.0000, 0.0902, 0.3961],
-            [0.0000, 0.2275, 0.4863],
-            [0.0000, 0.3804, 0.5843],
-            [0.0510, 0.5255, 0.6863],
-            [0.3137, 0.6549, 0.7686],
-            [0.5922, 0.7961, 0.8627],
-            [0.9020, 0.9490, 0.9647],
-        ],
-
-        // "jet" as default
-        _ => [
-            [0.0, 0.0, 0.51],
-            [0.0, 0.24, 0.67],
-            [0.01, 0.49, 0.78],
-            [0.01, 0.75, 0.89],
-            [0.02, 1.0, 1.0],
-            [0.51, 1.0, 0.5],
-            [1.0, 1.0, 0.0],
-            [0.99, 0.67, 0.0],
-            [0.99, 0.33, 0.0],
-            [0.98, 0.0, 0.0],
-            [0.5, 0.0, 0.0],
-        ],
-    };
+    if let Some(builtin) = Builtin::from_name(colormap_name) {
+        return builtin.data();
+    }
+    match registry().read().unwrap().get(colormap_name) {
+        Some(colormap) => colormap.resample(),
+        None => Builtin::Jet.data(),
+    }
+}
 
-    colors
+// region: built-in colormaps
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Builtin {
+    Hsv,
+    Hot,
+    Cool,
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+    Bone,
+    Cooper,
+    Greys,
+    Rainbow,
+    RainbowSoft,
+    White,
+    Black,
+    Red,
+    Green,
+    Blue,
+    Yellow,
+    Cyan,
+    Fuchsia,
+    Terrain,
+    Ocean,
+    Viridis,
+    Plasma,
+    Jet,
 }
 
+impl Builtin {
+    pub const ALL: [Builtin; 25] = [
+        Builtin::Hsv,
+        Builtin::Hot,
+        Builtin::Cool,
+        Builtin::Spring,
+        Builtin::Summer,
+        Builtin::Autumn,
+        Builtin::Winter,
+        Builtin::Bone,
+        Builtin::Cooper,
+        Builtin::Greys,
+        Builtin::Rainbow,
+        Builtin::RainbowSoft,
+        Builtin::White,
+        Builtin::Black,
+        Builtin::Red,
+        Builtin::Green,
+        Builtin::Blue,
+        Builtin::Yellow,
+        Builtin::Cyan,
+        Builtin::Fuchsia,
+        Builtin::Terrain,
+        Builtin::Ocean,
+        Builtin::Viridis,
+        Builtin::Plasma,
+        Builtin::Jet,
+    ];
+
+    pub fn name(self) -> &'static str {
+        COLORMAP_NAMES[self as usize]
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|b| b.name() == name)
+    }
+
+    pub fn data(self) -> [[f32; 3]; 11] {
+        match self {
+            Self::Hsv => [
+                [1.0, 0.0, 0.0],
+                [1.0, 0.5, 0.0],
+                [0.97, 1.0, 0.01],
+                [0.0, 0.99, 0.04],
+                [0.0, 0.98, 0.52],
+                [0.0, 0.98, 1.0],
+                [0.01, 0.49, 1.0],
+                [0.03, 0.0, 0.99],
+                [1.0, 0.0, 0.96],
+                [1.0, 0.0, 0.49],
+                [1.0, 0.0, 0.02],
+            ],
+
+            Self::Hot => [
+                [0.0, 0.0, 0.0],
+                [0.3, 0.0, 0.0],
+                [0.6, 0.0, 0.0],
+                [0.9, 0.0, 0.0],
+                [0.93, 0.27, 0.0],
+                [0.97, 0.55, 0.0],
+                [1.0, 0.82, 0.0],
+                [1.0, 0.87, 0.25],
+                [1.0, 0.91, 0.5],
+                [1.0, 0.96, 0.75],
+                [1.0, 1.0, 1.0],
+            ],
+
+            Self::Cool => [
+                [0.49, 0.0, 0.7],
+                [0.45, 0.0, 0.85],
+                [0.42, 0.15, 0.89],
+                [0.38, 0.29, 0.93],
+                [0.27, 0.57, 0.91],
+                [0.0, 0.8, 0.77],
+                [0.0, 0.97, 0.57],
+                [0.0, 0.98, 0.46],
+                [0.0, 1.0, 0.35],
+                [0.16, 1.0, 0.03],
+                [0.58, 1.0, 0.0],
+            ],
+
+            Self::Spring => [
+                [1.0, 0.0, 1.0],
+                [1.0, 0.1, 0.9],
+                [1.0, 0.2, 0.8],
+                [1.0, 0.3, 0.7],
+                [1.0, 0.4, 0.6],
+                [1.0, 0.5, 0.5],
+                [1.0, 0.6, 0.4],
+                [1.0, 0.7, 0.3],
+                [1.0, 0.8, 0.2],
+                [1.0, 0.9, 0.1],
+                [1.0, 1.0, 0.0],
+            ],
+
+            Self::Summer => [
+                [0.0, 0.5, 0.4],
+                [0.1, 0.55, 0.4],
+                [0.2, 0.6, 0.4],
+                [0.3, 0.65, 0.4],
+                [0.4, 0.7, 0.4],
+                [0.5, 0.75, 0.4],
+                [0.6, 0.8, 0.4],
+                [0.7, 0.85, 0.4],
+                [0.8, 0.9, 0.4],
+                [0.9, 0.95, 0.4],
+                [1.0, 1.0, 0.4],
+            ],
+
+            Self::Autumn => [
+                [1.0, 0.0, 0.0],
+                [1.0, 0.1, 0.0],
+                [1.0, 0.2, 0.0],
+                [1.0, 0.3, 0.0],
+                [1.0, 0.4, 0.0],
+                [1.0, 0.5, 0.0],
+                [1.0, 0.6, 0.0],
+                [1.0, 0.7, 0.0],
+                [1.0, 0.8, 0.0],
+                [1.0, 0.9, 0.0],
+                [1.0, 1.0, 0.0],
+            ],
+
+            Self::Winter => [
+                [0.0, 0.0, 1.0],
+                [0.0, 0.1, 0.95],
+                [0.0, 0.2, 0.9],
+                [0.0, 0.3, 0.85],
+                [0.0, 0.4, 0.8],
+                [0.0, 0.5, 0.75],
+                [0.0, 0.6, 0.7],
+                [0.0, 0.7, 0.65],
+                [0.0, 0.8, 0.6],
+                [0.0, 0.9, 0.55],
+                [0.0, 1.0, 0.5],
+            ],
+
+            Self::Bone => [
+                [0.0, 0.0, 0.0],
+                [0.08, 0.08, 0.11],
+                [0.16, 0.16, 0.23],
+                [0.25, 0.25, 0.34],
+                [0.33, 0.33, 0.45],
+                [0.41, 0.44, 0.54],
+                [0.5, 0.56, 0.62],
+                [0.58, 0.67, 0.7],
+                [0.66, 0.78, 0.78],
+                [0.83, 0.89, 0.89],
+                [1.0, 1.0, 1.0],
+            ],
+
+            Self::Cooper => [
+                [0.0, 0.0, 0.0],
+                [0.13, 0.08, 0.05],
+                [0.25, 0.16, 0.1],
+                [0.38, 0.24, 0.15],
+                [0.5, 0.31, 0.2],
+                [0.62, 0.39, 0.25],
+                [0.75, 0.47, 0.3],
+                [0.87, 0.55, 0.35],
+                [1.0, 0.63, 0.4],
+                [1.0, 0.71, 0.45],
+                [1.0, 0.78, 0.5],
+            ],
+
+            Self::Greys => [
+                [0.0, 0.0, 0.0],
+                [0.1, 0.1, 0.1],
+                [0.2, 0.2, 0.2],
+                [0.3, 0.3, 0.3],
+                [0.4, 0.4, 0.4],
+                [0.5, 0.5, 0.5],
+                [0.6, 0.6, 0.6],
+                [0.7, 0.7, 0.7],
+                [0.8, 0.8, 0.8],
+                [0.9, 0.9, 0.9],
+                [1.0, 1.0, 1.0],
+            ],
+
+            Self::Rainbow => [
+                [0.588, 0.000, 0.353],
+                [0.118, 0.000, 0.698],
+                [0.000, 0.059, 0.914],
+                [0.000, 0.297, 1.000],
+                [0.035, 0.677, 0.918],
+                [0.173, 1.000, 0.588],
+                [0.508, 1.000, 0.118],
+                [0.837, 0.951, 0.000],
+                [1.000, 0.725, 0.000],
+                [1.000, 0.348, 0.000],
+                [1.000, 0.000, 0.000],
+            ],
+
+            Self::RainbowSoft => [
+                [0.490, 0.000, 0.702],
+                [0.780, 0.000, 0.706],
+                [1.000, 0.000, 0.475],
+                [1.000, 0.424, 0.000],
+                [0.871, 0.761, 0.000],
+                [0.588, 1.000, 0.000],
+                [0.000, 1.000, 0.216],
+                [0.000, 0.965, 0.588],
+                [0.196, 0.655, 0.871],
+                [0.404, 0.200, 0.922],
+                [0.486, 0.000, 0.729],
+            ],
+
+            Self::White => convert_f32([
+                [1, 1, 1],
+                [1, 1, 1],
+                [1, 1, 1],
+                [1, 1, 1],
+                [1, 1, 1],
+                [1, 1, 1],
+                [1, 1, 1],
+                [1, 1, 1],
+                [1, 1, 1],
+                [1, 1, 1],
+                [1, 1, 1],
+            ]),
+
+            Self::Black => convert_f32([
+                [0, 0, 0],
+                [0, 0, 0],
+                [0, 0, 0],
+                [0, 0, 0],
+                [0, 0, 0],
+                [0, 0, 0],
+                [0, 0, 0],
+                [0, 0, 0],
+                [0, 0, 0],
+                [0, 0, 0],
+                [0, 0, 0],
+            ]),
+
+            Self::Red => convert_f32([
+                [1, 0, 0],
+                [1, 0, 0],
+                [1, 0, 0],
+                [1, 0, 0],
+                [1, 0, 0],
+                [1, 0, 0],
+                [1, 0, 0],
+                [1, 0, 0],
+                [1, 0, 0],
+                [1, 0, 0],
+                [1, 0, 0],
+            ]),
+
+            Self::Green => convert_f32([
+                [0, 1, 0],
+                [0, 1, 0],
+                [0, 1, 0],
+                [0, 1, 0],
+                [0, 1, 0],
+                [0, 1, 0],
+                [0, 1, 0],
+                [0, 1, 0],
+                [0, 1, 0],
+                [0, 1, 0],
+                [0, 1, 0],
+            ]),
+
+            Self::Blue => convert_f32([
+                [0, 0, 1],
+                [0, 0, 1],
+                [0, 0, 1],
+                [0, 0, 1],
+                [0, 0, 1],
+                [0, 0, 1],
+                [0, 0, 1],
+                [0, 0, 1],
+                [0, 0, 1],
+                [0, 0, 1],
+                [0, 0, 1],
+            ]),
+
+            Self::Yellow => convert_f32([
+                [1, 1, 0],
+                [1, 1, 0],
+                [1, 1, 0],
+                [1, 1, 0],
+                [1, 1, 0],
+                [1, 1, 0],
+                [1, 1, 0],
+                [1, 1, 0],
+                [1, 1, 0],
+                [1, 1, 0],
+                [1, 1, 0],
+            ]),
+
+            Self::Cyan => convert_f32([
+                [0, 1, 1],
+                [0, 1, 1],
+                [0, 1, 1],
+                [0, 1, 1],
+                [0, 1, 1],
+                [0, 1, 1],
+                [0, 1, 1],
+                [0, 1, 1],
+                [0, 1, 1],
+                [0, 1, 1],
+                [0, 1, 1],
+            ]),
+
+            Self::Fuchsia => convert_f32([
+                [1, 0, 1],
+                [1, 0, 1],
+                [1, 0, 1],
+                [1, 0, 1],
+                [1, 0, 1],
+                [1, 0, 1],
+                [1, 0, 1],
+                [1, 0, 1],
+                [1, 0, 1],
+                [1, 0, 1],
+                [1, 0, 1],
+            ]),
+
+            Self::Terrain => [
+                [0.1765, 0.2471, 0.6471],
+                [0.0392, 0.5176, 0.9176],
+                [0.0000, 0.7451, 0.5725],
+                [0.3098, 0.8627, 0.4588],
+                [0.7098, 0.9451, 0.5451],
+                [0.9686, 0.9608, 0.5843],
+                [0.7686, 0.7059, 0.4784],
+                [0.5451, 0.4196, 0.3529],
+                [0.6196, 0.5098, 0.4863],
+                [0.7765, 0.7137, 0.7020],
+                [0.9490, 0.9333, 0.9333],
+            ],
+
+            Self::Ocean => [
+                [0.0000, 0.4627, 0.0275],
+                [0.0000, 0.3216, 0.1176],
+                [0.0000, 0.1686, 0.2196],
+                [0.0000, 0.0392, 0.3098],
+                [0.0000, 0.0902, 0.3961],
+                [0.0000, 0.2275, 0.4863],
+                [0.0000, 0.3804, 0.5843],
+                [0.0510, 0.5255, 0.6863],
+                [0.3137, 0.6549, 0.7686],
+                [0.5922, 0.7961, 0.8627],
+                [0.9020, 0.9490, 0.9647],
+            ],
+
+            Self::Viridis => [
+                [0.2670, 0.0049, 0.3294],
+                [0.2826, 0.1409, 0.4575],
+                [0.2539, 0.2653, 0.5300],
+                [0.2068, 0.3718, 0.5531],
+                [0.1636, 0.4711, 0.5581],
+                [0.1276, 0.5669, 0.5506],
+                [0.1347, 0.6586, 0.5176],
+                [0.2669, 0.7488, 0.4406],
+                [0.4775, 0.8214, 0.3182],
+                [0.7414, 0.8734, 0.1496],
+                [0.9932, 0.9062, 0.1439],
+            ],
+
+            Self::Plasma => [
+                [0.0504, 0.0298, 0.5280],
+                [0.2546, 0.0139, 0.6154],
+                [0.4176, 0.0006, 0.6584],
+                [0.5627, 0.0515, 0.6415],
+                [0.6928, 0.1651, 0.5645],
+                [0.7982, 0.2802, 0.4695],
+                [0.8814, 0.3925, 0.3832],
+                [0.9492, 0.5178, 0.2957],
+                [0.9883, 0.6523, 0.2114],
+                [0.9886, 0.8096, 0.1454],
+                [0.9400, 0.9752, 0.1313],
+            ],
+
+            Self::Jet => [
+                [0.0, 0.0, 0.51],
+                [0.0, 0.24, 0.67],
+                [0.01, 0.49, 0.78],
+                [0.01, 0.75, 0.89],
+                [0.02, 1.0, 1.0],
+                [0.51, 1.0, 0.5],
+                [1.0, 1.0, 0.0],
+                [0.99, 0.67, 0.0],
+                [0.99, 0.33, 0.0],
+                [0.98, 0.0, 0.0],
+                [0.5, 0.0, 0.0],
+            ],
+
+        }
+    }
+}
+// endregion: built-in colormaps
+
 fn convert_f32(a: [[i32; 3]; 11]) -> [[f32; 3]; 11] {
     let b: Vec<[f32; 3]> = a
         .iter()
         .map(|&c| [c[0] as f32, c[1] as f32, c[2] as f32])
         .collect();
     let mut arr: [[f32; 3]; 11] = [[0.0; 3]; 11];
-    for i in 0..11 {
-        arr[i] = b[i].clone();
-    }
+    arr.copy_from_slice(&b);
     arr
 }
+
+// region: colormap registry
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Interpolation {
+    #[default]
+    Linear,
+    Cubic, // Catmull-Rom through the stops, smooth where linear shows bands
+}
+
+// any number of evenly spaced stops, at least two
+#[derive(Clone, Debug, PartialEq)]
+pub struct Colormap {
+    pub stops: Vec<[f32; 3]>,
+    pub interpolation: Interpolation,
+}
+
+impl Colormap {
+    // a colormap through the stops, evenly spaced; needs at least 2
+    pub fn new(stops: Vec<[f32; 3]>) -> anyhow::Result<Self> {
+        if stops.len() < 2 {
+            anyhow::bail!("a colormap needs at least 2 stops, got {}", stops.len());
+        }
+        Ok(Self::from_stops(stops))
+    }
+
+    // for stops already known to be at least 2
+    fn from_stops(stops: Vec<[f32; 3]>) -> Self {
+        Self {
+            stops,
+            interpolation: Interpolation::Linear,
+        }
+    }
+
+    pub fn interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    // color of t in [min, max]; values outside get the end colors. With linear
+    // interpolation and 11 stops this is color_lerp. Stops set directly to fewer
+    // than 2 give the one color, or black without any.
+    pub fn color(&self, min: f32, max: f32, t: f32) -> [f32; 3] {
+        match self.stops[..] {
+            [] => return [0.0; 3],
+            [color] => return color,
+            _ => {}
+        }
+        let n = self.stops.len() - 1;
+        let t = if t < min { min } else if t > max { max } else { t };
+        let tn = (t - min) / (max - min);
+        let indx = (n as f32 * tn).floor() as usize;
+        if indx as f32 == n as f32 * tn {
+            return self.stops[indx.min(n)];
+        }
+        let indx = indx.min(n - 1);
+        let s = (tn - indx as f32 / n as f32) * n as f32;
+        let stop = |k: isize| self.stops[k.clamp(0, n as isize) as usize];
+        let (a, b) = (stop(indx as isize), stop(indx as isize + 1));
+        match self.interpolation {
+            Interpolation::Linear => std::array::from_fn(|c| a[c] + (b[c] - a[c]) * s),
+            Interpolation::Cubic => {
+                let (p0, p3) = (stop(indx as isize - 1), stop(indx as isize + 2));
+                std::array::from_fn(|c| {
+                    let v = 0.5
+                        * (2.0 * a[c]
+                            + (b[c] - p0[c]) * s
+                            + (2.0 * p0[c] - 5.0 * a[c] + 4.0 * b[c] - p3[c]) * s * s
+                            + (3.0 * a[c] - p0[c] - 3.0 * b[c] + p3[c]) * s * s * s);
+                    v.clamp(0.0, 1.0)
+                })
+            }
+        }
+    }
+
+    // 11 evenly spaced stops for colormap_data users: colorbars, legends and the GPU
+    pub fn resample(&self) -> [[f32; 3]; 11] {
+        std::array::from_fn(|k| self.color(0.0, 1.0, k as f32 / 10.0))
+    }
+}
+
+// Colormaps by name: the built-ins plus those registered by the program. Surfaces
+// look their colormap_name up in the process-wide registry(), so a registered name
+// works wherever a built-in one does.
+#[derive(Clone, Debug, Default)]
+pub struct Registry {
+    registered: Vec<(String, Colormap)>,
+}
+
+impl Registry {
+    // adds or replaces a colormap; the built-in names cannot be replaced
+    pub fn register(&mut self, name: &str, stops: Vec<[f32; 3]>) -> anyhow::Result<()> {
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            anyhow::bail!("invalid colormap name '{}'", name);
+        }
+        if Builtin::from_name(name).is_some() {
+            anyhow::bail!("'{}' is a built-in colormap", name);
+        }
+        if stops.len() < 2 {
+            anyhow::bail!("colormap '{}' needs at least 2 stops", name);
+        }
+        if stops.iter().flatten().any(|c| !(0.0..=1.0).contains(c)) {
+            anyhow::bail!("colormap '{}' has components outside [0, 1]", name);
+        }
+        let colormap = Colormap::from_stops(stops);
+        match self.registered.iter_mut().find(|(n, _)| n == name) {
+            Some((_, existing)) => *existing = colormap,
+            None => self.registered.push((name.to_string(), colormap)),
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<Colormap> {
+        if let Some(builtin) = Builtin::from_name(name) {
+            return Some(Colormap::from_stops(builtin.data().to_vec()));
+        }
+        self.registered
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, colormap)| colormap.clone())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        Builtin::from_name(name).is_some() || self.registered.iter().any(|(n, _)| n == name)
+    }
+
+    // built-in names first, then the registered ones in order of registration
+    pub fn names(&self) -> Vec<String> {
+        COLORMAP_NAMES
+            .iter()
+            .map(|name| name.to_string())
+            .chain(self.registered.iter().map(|(name, _)| name.clone()))
+            .collect()
+    }
+}
+
+pub fn registry() -> &'static RwLock<Registry> {
+    static REGISTRY: LazyLock<RwLock<Registry>> = LazyLock::new(Default::default);
+    &REGISTRY
+}
+
+// registers a colormap in the process-wide registry
+pub fn register(name: &str, stops: Vec<[f32; 3]>) -> anyhow::Result<()> {
+    registry().write().unwrap().register(name, stops)
+}
+
+// colormap of a name in the process-wide registry; any other name falls back to "jet"
+pub fn lookup(name: &str, interpolation: Interpolation) -> Colormap {
+    registry()
+        .read()
+        .unwrap()
+        .get(name)
+        .unwrap_or_else(|| Colormap::from_stops(Builtin::Jet.data().to_vec()))
+        .interpolation(interpolation)
+}
+// endregion: colormap registry
+
+// region: transfer functions
+// Custom colormaps edited channel by channel: each of red, green, blue and alpha is a
+// curve through control points (position in [0, 1], value in [0, 1]), linear between
+// them and held flat past the ends. An empty alpha curve means opaque. The curves
+// are saved as JSON; rgba8() gives the data of a colormap texture and colormap() the
+// stops to register the curves under a name for the surfaces.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Channel {
+    Red,
+    Green,
+    Blue,
+    Alpha,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ITransferFunction {
+    pub red: Vec<[f32; 2]>,
+    pub green: Vec<[f32; 2]>,
+    pub blue: Vec<[f32; 2]>,
+    pub alpha: Vec<[f32; 2]>,
+}
+
+// value of a curve at t, sorted by position
+fn curve_value(points: &[[f32; 2]], t: f32) -> f32 {
+    let Some(first) = points.first() else {
+        return 1.0;
+    };
+    let last = points[points.len() - 1];
+    if t <= first[0] {
+        return first[1];
+    }
+    if t >= last[0] {
+        return last[1];
+    }
+    let k = points.partition_point(|p| p[0] <= t);
+    let ([x0, y0], [x1, y1]) = (points[k - 1], points[k]);
+    if x1 > x0 { y0 + (y1 - y0) * (t - x0) / (x1 - x0) } else { y1 }
+}
+
+impl ITransferFunction {
+    // the stops of a colormap as control points, evenly spaced, with an opaque alpha
+    pub fn from_colormap(colormap: &Colormap) -> Self {
+        let n = (colormap.stops.len() - 1).max(1) as f32;
+        let curve = |c: usize| -> Vec<[f32; 2]> {
+            let stops = colormap.stops.iter().enumerate();
+            stops.map(|(k, stop)| [k as f32 / n, stop[c]]).collect()
+        };
+        Self { red: curve(0), green: curve(1), blue: curve(2), alpha: vec![] }
+    }
+
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let transfer: ITransferFunction = serde_json::from_str(json)?;
+        transfer.validate()?;
+        Ok(transfer)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    // color channels need a point each; every point lies in [0, 1]^2, sorted by position
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for (name, points) in [
+            ("red", &self.red),
+            ("green", &self.green),
+            ("blue", &self.blue),
+            ("alpha", &self.alpha),
+        ] {
+            if points.is_empty() && name != "alpha" {
+                anyhow::bail!("{} curve has no control points", name);
+            }
+            if points.iter().flatten().any(|v| !(0.0..=1.0).contains(v)) {
+                anyhow::bail!("{} curve has a control point outside [0, 1]", name);
+            }
+            if points.windows(2).any(|w| w[0][0] > w[1][0]) {
+                anyhow::bail!("{} curve control points are not sorted by position", name);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn points(&self, channel: Channel) -> &[[f32; 2]] {
+        match channel {
+            Channel::Red => &self.red,
+            Channel::Green => &self.green,
+            Channel::Blue => &self.blue,
+            Channel::Alpha => &self.alpha,
+        }
+    }
+
+    fn points_mut(&mut self, channel: Channel) -> &mut Vec<[f32; 2]> {
+        match channel {
+            Channel::Red => &mut self.red,
+            Channel::Green => &mut self.green,
+            Channel::Blue => &mut self.blue,
+            Channel::Alpha => &mut self.alpha,
+        }
+    }
+
+    // adds a control point, clamped to [0, 1]^2, and returns its index
+    pub fn insert_point(&mut self, channel: Channel, position: f32, value: f32) -> usize {
+        let point = [position.clamp(0.0, 1.0), value.clamp(0.0, 1.0)];
+        let points = self.points_mut(channel);
+        let k = points.partition_point(|p| p[0] <= point[0]);
+        points.insert(k, point);
+        k
+    }
+
+    // drags a control point; its position stays between its neighbors, so the order
+    // of the points never changes
+    pub fn move_point(&mut self, channel: Channel, index: usize, position: f32, value: f32) {
+        let points = self.points_mut(channel);
+        if index >= points.len() {
+            return;
+        }
+        let low = if index > 0 { points[index - 1][0] } else { 0.0 };
+        let high = points.get(index + 1).map_or(1.0, |p| p[0]);
+        points[index] = [position.clamp(low, high), value.clamp(0.0, 1.0)];
+    }
+
+    // removes a control point; the color curves keep at least one
+    pub fn remove_point(&mut self, channel: Channel, index: usize) -> bool {
+        let keep = if channel == Channel::Alpha { 0 } else { 1 };
+        let points = self.points_mut(channel);
+        if index >= points.len() || points.len() <= keep {
+            return false;
+        }
+        points.remove(index);
+        true
+    }
+
+    // index of the control point nearest to (position, value) within radius, for
+    // picking a point with the mouse
+    pub fn nearest_point(
+        &self,
+        channel: Channel,
+        position: f32,
+        value: f32,
+        radius: f32,
+    ) -> Option<usize> {
+        let distance = |p: &[f32; 2]| (p[0] - position).hypot(p[1] - value);
+        self.points(channel)
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| distance(p) <= radius)
+            .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))
+            .map(|(k, _)| k)
+    }
+
+    pub fn rgba(&self, t: f32) -> [f32; 4] {
+        let t = t.clamp(0.0, 1.0);
+        [&self.red, &self.green, &self.blue, &self.alpha].map(|points| curve_value(points, t))
+    }
+
+    // width texels of a 1D Rgba8Unorm colormap texture, from t = 0 to t = 1
+    pub fn rgba8(&self, width: u32) -> Vec<u8> {
+        let n = (width.max(2) - 1) as f32;
+        (0..width.max(2))
+            .flat_map(|k| self.rgba(k as f32 / n).map(|c| (c * 255.0).round() as u8))
+            .collect()
+    }
+
+    pub fn create_texture(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        width: u32,
+    ) -> wgpu::Texture {
+        let width = width.max(2);
+        let size = wgpu::Extent3d { width, height: 1, depth_or_array_layers: 1 };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Transfer Function Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D1,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        queue.write_texture(
+            texture.as_image_copy(),
+            &self.rgba8(width),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * width),
+                rows_per_image: None,
+            },
+            size,
+        );
+        texture
+    }
+
+    // the color curves sampled at evenly spaced stops (at least 2); alpha is left out,
+    // since surfaces are colored opaque
+    pub fn colormap(&self, stops: usize) -> Colormap {
+        let n = (stops.max(2) - 1) as f32;
+        let stops = (0..=n as usize).map(|k| {
+            let [r, g, b, _] = self.rgba(k as f32 / n);
+            [r, g, b]
+        });
+        Colormap::from_stops(stops.collect())
+    }
+
+    // registers the color curves in the process-wide registry, sampled finely enough
+    // for any control point spacing used in practice
+    pub fn register(&self, name: &str) -> anyhow::Result<()> {
+        register(name, self.colormap(256).stops)
+    }
+}
+// endregion: transfer functions
@@ -0,0 +1,256 @@
+use bytemuck::cast_slice;
+use cgmath::{Matrix4, Vector4};
+
+use super::wgpu_simplified::{InitWgpu, RenderTarget};
+
+// region: near/far auto-tuning
+// Fits the near and far clip planes tightly around the scene bounds so the depth
+// buffer precision is spent where geometry actually is. The result always stays
+// within [min_near, max_far].
+#[derive(Clone, Copy, Debug)]
+pub struct NearFarTuner {
+    pub min_near: f32,
+    pub max_far: f32,
+    pub margin: f32, // relative padding added on both sides of the fitted range
+}
+
+impl Default for NearFarTuner {
+    fn default() -> Self {
+        Self {
+            min_near: 0.1,
+            max_far: 1000.0,
+            margin: 0.05,
+        }
+    }
+}
+
+impl NearFarTuner {
+    // returns [near, far] for an axis-aligned box given in world space
+    pub fn fit(
+        &self,
+        view_mat: &Matrix4<f32>,
+        bounds_min: [f32; 3],
+        bounds_max: [f32; 3],
+    ) -> [f32; 2] {
+        let mut nearest = f32::MAX;
+        let mut farthest = f32::MIN;
+        for i in 0..8 {
+            let pick = |axis: usize| {
+                if i & (1 << axis) == 0 {
+                    bounds_min[axis]
+                } else {
+                    bounds_max[axis]
+                }
+            };
+            let corner = Vector4::new(pick(0), pick(1), pick(2), 1.0);
+            // the camera looks down -z in view space
+            let depth = -(view_mat * corner).z;
+            nearest = nearest.min(depth);
+            farthest = farthest.max(depth);
+        }
+
+        if farthest <= self.min_near {
+            // the whole scene is behind the camera
+            return [self.min_near, self.max_far];
+        }
+
+        let near = (nearest * (1.0 - self.margin)).clamp(self.min_near, self.max_far);
+        let mut far = (farthest * (1.0 + self.margin)).clamp(self.min_near, self.max_far);
+        if far <= near {
+            far = (2.0 * near).min(self.max_far).max(near + 1e-3);
+        }
+        [near, far]
+    }
+}
+// endregion: near/far auto-tuning
+
+// region: linear depth view
+const LINEAR_DEPTH_SHADER: &str = r#"
+struct DepthParams {
+    near: f32,
+    far: f32,
+}
+@group(0) @binding(0) var depthTexture: DEPTH_TEXTURE_TYPE;
+@group(0) @binding(1) var<uniform> params: DepthParams;
+
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> @builtin(position) vec4f {
+    // full-screen triangle
+    let uv = vec2f(f32((i << 1u) & 2u), f32(i & 2u));
+    return vec4f(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4f) -> @location(0) vec4f {
+    let d = textureLoad(depthTexture, vec2i(pos.xy), 0);
+    // undo OPENGL_TO_WGPU_MATRIX, then invert the perspective depth mapping
+    let z = 2.0 * d - 1.0;
+    let n = params.near;
+    let f = params.far;
+    let linear = 2.0 * n * f / (f + n - z * (f - n));
+    let s = clamp((linear - n) / (f - n), 0.0, 1.0);
+    return vec4f(vec3f(1.0 - s), 1.0);
+}
+"#;
+
+// Debug pass that draws the linearized depth buffer as grayscale (white = near plane,
+// black = far plane) over the resolved frame. The depth texture must be created with
+// create_depth_texture_view(init, true) and stored by the surface pass.
+pub struct LinearDepthView {
+    pipeline: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    params_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl LinearDepthView {
+    pub fn new(init: &InitWgpu, depth_view: &wgpu::TextureView) -> Self {
+        let multisampled = init.sample_count > 1;
+        let texture_type = if multisampled {
+            "texture_depth_multisampled_2d"
+        } else {
+            "texture_depth_2d"
+        };
+        let shader = init
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Linear Depth Shader"),
+                source: wgpu::ShaderSource::Wgsl(
+                    LINEAR_DEPTH_SHADER
+                        .replace("DEPTH_TEXTURE_TYPE", texture_type)
+                        .into(),
+                ),
+            });
+
+        let layout = init
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Depth,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+                label: Some("Linear Depth Bind Group Layout"),
+            });
+
+        let pipeline_layout = init
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Linear Depth Pipeline Layout"),
+                bind_group_layouts: &[&layout],
+                push_constant_ranges: &[],
+            });
+
+        let pipeline = init
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Linear Depth Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some("fs_main"),
+                    targets: &[Some(init.color_format().into())],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            });
+
+        let params_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Linear Depth Params Buffer"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = Self::create_bind_group(init, &layout, &params_buffer, depth_view);
+
+        Self {
+            pipeline,
+            layout,
+            params_buffer,
+            bind_group,
+        }
+    }
+
+    fn create_bind_group(
+        init: &InitWgpu,
+        layout: &wgpu::BindGroupLayout,
+        params_buffer: &wgpu::Buffer,
+        depth_view: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        init.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(depth_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: params_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("Linear Depth Bind Group"),
+        })
+    }
+
+    // call after the depth texture has been recreated, e.g. on resize
+    pub fn set_depth_view(&mut self, init: &InitWgpu, depth_view: &wgpu::TextureView) {
+        self.bind_group =
+            Self::create_bind_group(init, &self.layout, &self.params_buffer, depth_view);
+    }
+
+    // near and far must match the projection used to render the depth buffer
+    pub fn set_near_far(&self, init: &InitWgpu, near: f32, far: f32) {
+        init.queue
+            .write_buffer(&self.params_buffer, 0, cast_slice(&[near, far, 0.0, 0.0]));
+    }
+
+    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Linear Depth Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                depth_slice: None,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+// endregion: linear depth view
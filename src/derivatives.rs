@@ -0,0 +1,508 @@
+#![allow(dead_code)]
+use std::f32::consts::PI;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+// region: dual numbers
+// Forward-mode automatic differentiation: a Dual carries a value together with its
+// partial derivatives by u and v, and every operation applies the chain rule. The
+// surface formulas below are those of math_func written over Dual, so evaluating one
+// at (Dual::u(u), Dual::v(v)) gives the exact tangents of the surface, which is what
+// NormalMode::Analytic crosses into a normal:
+//
+//     let [pu, pv] = derivatives::partials(derivatives::torus, u, v);
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Dual {
+    pub value: f32,
+    pub du: f32,
+    pub dv: f32,
+}
+
+// [d/du, d/dv] of [x, y, z]
+pub type Partials = [[f32; 3]; 2];
+
+impl Dual {
+    pub fn constant(value: f32) -> Self {
+        Self {
+            value,
+            du: 0.0,
+            dv: 0.0,
+        }
+    }
+
+    // the u parameter itself
+    pub fn u(value: f32) -> Self {
+        Self {
+            value,
+            du: 1.0,
+            dv: 0.0,
+        }
+    }
+
+    pub fn v(value: f32) -> Self {
+        Self {
+            value,
+            du: 0.0,
+            dv: 1.0,
+        }
+    }
+
+    // f(self) with f(value) = value and f'(value) = slope
+    fn chain(self, value: f32, slope: f32) -> Self {
+        Self {
+            value,
+            du: slope * self.du,
+            dv: slope * self.dv,
+        }
+    }
+
+    pub fn sin(self) -> Self {
+        self.chain(self.value.sin(), self.value.cos())
+    }
+
+    pub fn cos(self) -> Self {
+        self.chain(self.value.cos(), -self.value.sin())
+    }
+
+    pub fn tan(self) -> Self {
+        let t = self.value.tan();
+        self.chain(t, 1.0 + t * t)
+    }
+
+    pub fn atan(self) -> Self {
+        self.chain(self.value.atan(), 1.0 / (1.0 + self.value * self.value))
+    }
+
+    pub fn sinh(self) -> Self {
+        self.chain(self.value.sinh(), self.value.cosh())
+    }
+
+    pub fn cosh(self) -> Self {
+        self.chain(self.value.cosh(), self.value.sinh())
+    }
+
+    pub fn exp(self) -> Self {
+        let e = self.value.exp();
+        self.chain(e, e)
+    }
+
+    pub fn ln(self) -> Self {
+        self.chain(self.value.ln(), 1.0 / self.value)
+    }
+
+    pub fn sqrt(self) -> Self {
+        let s = self.value.sqrt();
+        self.chain(s, 0.5 / s)
+    }
+
+    pub fn powf(self, p: f32) -> Self {
+        self.chain(self.value.powf(p), p * self.value.powf(p - 1.0))
+    }
+}
+
+impl Add for Dual {
+    type Output = Dual;
+    fn add(self, b: Dual) -> Dual {
+        Dual {
+            value: self.value + b.value,
+            du: self.du + b.du,
+            dv: self.dv + b.dv,
+        }
+    }
+}
+
+impl Sub for Dual {
+    type Output = Dual;
+    fn sub(self, b: Dual) -> Dual {
+        self + -b
+    }
+}
+
+impl Mul for Dual {
+    type Output = Dual;
+    fn mul(self, b: Dual) -> Dual {
+        Dual {
+            value: self.value * b.value,
+            du: self.du * b.value + self.value * b.du,
+            dv: self.dv * b.value + self.value * b.dv,
+        }
+    }
+}
+
+impl Div for Dual {
+    type Output = Dual;
+    fn div(self, b: Dual) -> Dual {
+        let d = b.value * b.value;
+        Dual {
+            value: self.value / b.value,
+            du: (self.du * b.value - self.value * b.du) / d,
+            dv: (self.dv * b.value - self.value * b.dv) / d,
+        }
+    }
+}
+
+impl Neg for Dual {
+    type Output = Dual;
+    fn neg(self) -> Dual {
+        Dual {
+            value: -self.value,
+            du: -self.du,
+            dv: -self.dv,
+        }
+    }
+}
+
+// the mixed operations with constants, in both orders
+macro_rules! impl_constant_ops {
+    ($($op:ident $method:ident),*) => {$(
+        impl $op<f32> for Dual {
+            type Output = Dual;
+            fn $method(self, b: f32) -> Dual {
+                self.$method(Dual::constant(b))
+            }
+        }
+
+        impl $op<Dual> for f32 {
+            type Output = Dual;
+            fn $method(self, b: Dual) -> Dual {
+                Dual::constant(self).$method(b)
+            }
+        }
+    )*};
+}
+
+impl_constant_ops!(Add add, Sub sub, Mul mul, Div div);
+
+// the partial derivatives of a surface formula over Dual at (u, v)
+pub fn partials(f: impl Fn(Dual, Dual) -> [Dual; 3], u: f32, v: f32) -> Partials {
+    let p = f(Dual::u(u), Dual::v(v));
+    [p.map(|c| c.du), p.map(|c| c.dv)]
+}
+// endregion: dual numbers
+
+// region: surface derivatives
+// formula over Dual of a parametric surface type without shape parameters; None for
+// the types left to finite differences (boy_surface and costa, which go through
+// complex functions). See IParametricSurface::partials_fn for all types.
+pub fn parametric_surface_derivatives(surface_type: u32) -> Option<fn(Dual, Dual) -> [Dual; 3]> {
+    let f: fn(Dual, Dual) -> [Dual; 3] = match surface_type {
+        0 => klein_bottle,
+        1 => astroid,
+        2 => astroid2,
+        3 => astroidal_torus,
+        4 => bohemian_dome,
+        5 => boy_shape,
+        6 => breather,
+        7 => enneper,
+        8 => figure8,
+        9 => henneberg,
+        10 => kiss,
+        11 => klein_bottle2,
+        12 => klein_bottle3,
+        13 => kuen,
+        14 => minimal,
+        15 => parabolic_cyclide,
+        16 => pear,
+        17 => plucker_conoid,
+        18 => seashell,
+        19 => sievert_enneper,
+        20 => steiner,
+        21 => torus,
+        22 => wellenkugel,
+        25 => cross_cap,
+        27 => scherk,
+        _ => return None,
+    };
+    Some(f)
+}
+
+fn klein_bottle(u: Dual, v: Dual) -> [Dual; 3] {
+    let x = 2.0 / 15.0 * (3.0 + 5.0 * u.cos() * u.sin()) * v.sin();
+
+    let y = -1.0 / 15.0
+        * u.sin()
+        * (3.0 * v.cos()
+            - 3.0 * (u.cos()).powf(2.0) * v.cos()
+            - 48.0 * (u.cos()).powf(4.0) * v.cos()
+            + 48.0 * (u.cos()).powf(6.0) * v.cos()
+            - 60.0 * u.sin()
+            + 5.0 * u.cos() * v.cos() * u.sin()
+            - 5.0 * (u.cos()).powf(3.0) * v.cos() * u.sin()
+            - 80.0 * (u.cos()).powf(5.0) * v.cos() * u.sin()
+            + 80.0 * (u.cos()).powf(7.0) * v.cos() * u.sin());
+
+    let z = -2.0 / 15.0
+        * u.cos()
+        * (3.0 * v.cos() - 30.0 * u.sin() + 90.0 * (u.cos()).powf(4.0) * u.sin()
+            - 60.0 * (u.cos()).powf(6.0) * u.sin()
+            + 5.0 * u.cos() * v.cos() * u.sin());
+
+    [x, y, z]
+}
+
+fn astroid(u: Dual, v: Dual) -> [Dual; 3] {
+    let a = 1.5f32;
+    let x = a * (u.cos()).powf(3.0) * (v.cos()).powf(3.0);
+    let y = a * (u.sin()).powf(3.0);
+    let z = a * (u.sin()).powf(3.0) * (v.cos()).powf(3.0);
+    [x, y, z]
+}
+
+fn astroid2(u: Dual, v: Dual) -> [Dual; 3] {
+    let x = (u.sin()).powf(3.0) * v.cos();
+    let y = (u.cos()).powf(3.0);
+    let z = (u.sin()).powf(3.0) * v.sin();
+    [x, y, z]
+}
+
+fn astroidal_torus(u: Dual, v: Dual) -> [Dual; 3] {
+    let a = 2.0;
+    let b = 1.0;
+    let c = 7854.0f32;
+    let x = (a + b * (u.cos()).powf(3.0) * c.cos() - b * (u.sin()).powf(3.0) * c.sin()) * v.cos();
+    let y = b * (u.cos()).powf(3.0) * c.sin() + b * (u.sin()).powf(3.0) * c.cos();
+    let z = (a + b * (u.cos()).powf(3.0) * c.cos() - b * (u.sin()).powf(3.0) * c.sin()) * v.sin();
+    [x, y, z]
+}
+
+fn bohemian_dome(u: Dual, v: Dual) -> [Dual; 3] {
+    let a = 0.7;
+    let x = a * u.cos();
+    let y = v.cos();
+    let z = a * u.sin() + v.sin();
+    [x, y, z]
+}
+
+fn boy_shape(u: Dual, v: Dual) -> [Dual; 3] {
+    let x = u.cos()
+        * (1.0 / 3.0 * 2.0f32.sqrt() * u.cos() * (2.0 * v).cos() + 2.0 / 3.0 * u.sin() * v.cos())
+        / (1.0 - 2.0f32.sqrt() * u.sin() * u.cos() * (3.0 * v).sin());
+    let y = u.cos() * u.cos() / (1.0 - 2.0f32.sqrt() * u.sin() * u.cos() * (3.0 * v).sin()) - 1.0;
+    let z = u.cos()
+        * (1.0 / 3.0 * 2.0f32.sqrt() * u.cos() * (2.0 * v).sin() - 2.0 / 3.0 * u.sin() * v.sin())
+        / (1.0 - 2.0f32.sqrt() * u.sin() * u.cos() * (3.0 * v).sin());
+    [x, y, z]
+}
+
+fn breather(u: Dual, v: Dual) -> [Dual; 3] {
+    const A: f32 = 0.4; // where 0 < A < 1
+
+    let de = A
+        * ((1.0 - A * A) * ((A * u).cosh()).powf(2.0)
+            + A * A * ((((1.0 - A * A).sqrt() * v).sin()).powf(2.0)));
+
+    let x = -u + (2.0 * (1.0 - A * A) * (A * u).cosh() * (A * u).sinh()) / de;
+
+    let y = (2.0
+        * (1.0 - A * A).sqrt()
+        * (A * u).cosh()
+        * (-((1.0 - A * A).sqrt() * v.cos() * ((1.0 - A * A).sqrt() * v).cos())
+            - v.sin() * ((1.0 - A * A).sqrt() * v).sin()))
+        / de;
+
+    let z = (2.0
+        * (1.0 - A * A).sqrt()
+        * (A * u).cosh()
+        * (-((1.0 - A * A).sqrt() * v.sin() * ((1.0 - A * A).sqrt() * v).cos())
+            + v.cos() * ((1.0 - A * A).sqrt() * v).sin()))
+        / de;
+
+    [x, y, z]
+}
+
+fn enneper(u: Dual, v: Dual) -> [Dual; 3] {
+    let a = 1.0 / 3.0;
+    let x = a * u * (1.0 - u * u / 3.0 + v * v);
+    let y = a * (u * u - v * v);
+    let z = a * v * (1.0 - v * v / 3.0 + u * u);
+    [x, y, z]
+}
+
+fn figure8(u: Dual, v: Dual) -> [Dual; 3] {
+    let a = 2.5f32;
+    let x = (a + (0.5 * u).cos() * v.sin() - (0.5 * u).sin() * (2.0 * v).sin()) * u.cos();
+    let y = (a + (0.5 * u).cos() * v.sin() - (0.5 * u).sin() * (2.0 * v).sin()) * u.sin();
+    let z = (0.5 * u).sin() * v.sin() + (0.5 * u).cos() * (2.0 * v).sin();
+    [x, y, z]
+}
+
+fn henneberg(u: Dual, v: Dual) -> [Dual; 3] {
+    let x = u.sinh() * v.cos() - (3.0 * u).sinh() * (3.0 * v).cos() / 3.0;
+    let y = (2.0 * u).cosh() * (2.0 * v).cos();
+    let z = u.sinh() * v.sin() - (3.0 * u).sinh() * (3.0 * v).sin() / 3.0;
+    [x, y, z]
+}
+
+fn kiss(u: Dual, v: Dual) -> [Dual; 3] {
+    let x = u * u * (1.0 - u).sqrt() * v.cos();
+    let y = u;
+    let z = u * u * (1.0 - u).sqrt() * v.sin();
+    [x, y, z]
+}
+
+fn klein_bottle2(u: Dual, v: Dual) -> [Dual; 3] {
+    let (mut x, mut z) = (Dual::default(), Dual::default());
+    let r = 4.0 * (1.0 - 0.5 * u.cos());
+    if (0.0..=PI).contains(&u.value) {
+        x = 6.0 * u.cos() * (1.0 + u.sin()) + r * u.cos() * v.cos();
+        z = 16.0 * u.sin() + r * u.sin() * v.cos();
+    } else if u.value > PI && u.value <= 2.0 * PI {
+        x = 6.0 * u.cos() * (1.0 + u.sin()) + r * (v + PI).cos();
+        z = 16.0 * u.sin();
+    }
+    let y = r * v.sin();
+    [x, y, z]
+}
+
+fn klein_bottle3(u: Dual, v: Dual) -> [Dual; 3] {
+    let a = 8f32;
+    let n = 3f32;
+    let m = 1f32;
+
+    let x = (a + (0.5 * u * n).cos() * v.sin() - (0.5 * u * n).sin() * (2.0 * v).sin())
+        * (0.5 * u * m).cos();
+    let y = (0.5 * u * n).sin() * v.sin() + (0.5 * u * n).cos() * (2.0 * v).sin();
+    let z = (a + (0.5 * u * n).cos() * v.sin() - (0.5 * u * n).sin() * (2.0 * v).sin())
+        * (0.5 * u * m).sin();
+    [x, y, z]
+}
+
+fn kuen(u: Dual, v: Dual) -> [Dual; 3] {
+    let x = 2.0 * u * v.cos();
+    let y = 2.0 * (3.0 * v).cos();
+    let z = 2.0 * u * v.sin();
+    [x, y, z]
+}
+
+fn minimal(u: Dual, v: Dual) -> [Dual; 3] {
+    let x = u - (2.0 * u).exp() * (2.0 * v).cos() / 2.0;
+    let y = 2.0 * u.exp() * v.cos();
+    let z = -(v + (2.0 * u).exp() * (2.0 * v).sin() / 2.0);
+    [x, y, z]
+}
+
+fn parabolic_cyclide(u: Dual, v: Dual) -> [Dual; 3] {
+    let x = u * (0.5 + v * v) / (1.0 + u * u + v * v);
+    let y = 0.5 * (2.0 * v * v + 0.5 * (1.0 - u * u - v * v)) / (1.0 + u * u + v * v);
+    let z = v * (1.0 + u * u - 0.5) / (1.0 + u * u + v * v);
+    [x, y, z]
+}
+
+fn pear(u: Dual, v: Dual) -> [Dual; 3] {
+    let x = u * (u * (1.0 - u)).sqrt() * v.cos();
+    let y = -u;
+    let z = u * (u * (1.0 - u)).sqrt() * v.sin();
+    [x, y, z]
+}
+
+fn plucker_conoid(u: Dual, v: Dual) -> [Dual; 3] {
+    let x = 2.0 * u * v.cos();
+    let y = 2.0 * (3.0 * v).cos();
+    let z = 2.0 * u * v.sin();
+    [x, y, z]
+}
+
+fn seashell(u: Dual, v: Dual) -> [Dual; 3] {
+    let x = 2.0 * (-1.0 + (u / (6.0 * PI)).exp()) * u.sin() * (((v / 2.0).cos()).powf(2.0));
+
+    let y = 1.0 - (u / (3.0 * PI)).exp() - v.sin() + (u / (6.0 * PI)).exp() * v.sin();
+
+    let z = 2.0 * (1.0 - (u / (6.0 * PI)).exp()) * u.cos() * ((v / 2.0).cos()).powf(2.0);
+
+    [x, y, z]
+}
+
+fn sievert_enneper(u: Dual, v: Dual) -> [Dual; 3] {
+    const A: f32 = 1.0;
+
+    let pu = -u / (1.0 + A).sqrt() + (u.tan() * (1.0 + A).sqrt()).atan();
+    let auv = 2.0 / (1.0 + A - A * v.sin() * v.sin() * u.cos() * u.cos());
+    let ruv = auv * v.sin() * ((1.0 + 1.0 / A) * (1.0 + A * u.sin() * u.sin())).sqrt();
+
+    let x = (((v / 2.0).tan()).ln() + (1.0 + A) * auv * v.cos()) / A.sqrt();
+    let y = ruv * pu.cos();
+    let z = ruv * pu.sin();
+
+    [x, y, z]
+}
+
+fn steiner(u: Dual, v: Dual) -> [Dual; 3] {
+    let x = u.cos() * v.cos() * v.sin();
+    let y = u.cos() * u.sin() * (v.cos()).powf(2.0);
+    let z = u.sin() * v.cos() * v.sin();
+    [x, y, z]
+}
+
+fn torus(u: Dual, v: Dual) -> [Dual; 3] {
+    let x = (1.0 + 0.3 * v.cos()) * u.cos();
+    let y = 0.3 * v.sin();
+    let z = (1.0 + 0.3 * v.cos()) * u.sin();
+    [x, y, z]
+}
+
+fn wellenkugel(u: Dual, v: Dual) -> [Dual; 3] {
+    let x = u * (u.cos()).cos() * v.sin();
+    let y = u * (u.cos()).sin();
+    let z = u * (u.cos()).cos() * v.cos();
+    [x, y, z]
+}
+
+pub fn torus_knot(u: Dual, v: Dual, p: f32, q: f32, tube_radius: f32) -> [Dual; 3] {
+    let r = 1.0 + 0.5 * (q * u).cos();
+    let dr = -0.5 * q * (q * u).sin();
+    let center = [r * (p * u).cos(), 0.5 * (q * u).sin(), r * (p * u).sin()];
+    let tangent = [
+        dr * (p * u).cos() - r * p * (p * u).sin(),
+        0.5 * q * (q * u).cos(),
+        dr * (p * u).sin() + r * p * (p * u).cos(),
+    ];
+
+    // the knot never runs parallel to the y axis, so tangent x y gives a smooth frame
+    let normal = normalize([-tangent[2], Dual::default(), tangent[0]]);
+    let t = normalize(tangent);
+    let binormal = [
+        t[1] * normal[2] - t[2] * normal[1],
+        t[2] * normal[0] - t[0] * normal[2],
+        t[0] * normal[1] - t[1] * normal[0],
+    ];
+
+    let (c, s) = (tube_radius * v.cos(), tube_radius * v.sin());
+    [
+        center[0] + c * normal[0] + s * binormal[0],
+        center[1] + c * normal[1] + s * binormal[1],
+        center[2] + c * normal[2] + s * binormal[2],
+    ]
+}
+
+pub fn klein_bagel(u: Dual, v: Dual, tube_radius: f32) -> [Dual; 3] {
+    let w = (0.5 * u).cos() * v.sin() - (0.5 * u).sin() * (2.0 * v).sin();
+    let x = (1.0 + tube_radius * w) * u.cos();
+    let y = tube_radius * ((0.5 * u).sin() * v.sin() + (0.5 * u).cos() * (2.0 * v).sin());
+    let z = (1.0 + tube_radius * w) * u.sin();
+    [x, y, z]
+}
+
+fn cross_cap(u: Dual, v: Dual) -> [Dual; 3] {
+    let x = u.cos() * (2.0 * v).sin();
+    let y = v.cos() * v.cos() - u.cos() * u.cos() * v.sin() * v.sin();
+    let z = u.sin() * (2.0 * v).sin();
+    [x, y, z]
+}
+
+fn scherk(u: Dual, v: Dual) -> [Dual; 3] {
+    let y = (v.cos() / u.cos()).ln();
+    [u, y, v]
+}
+
+pub fn catenoid_helicoid(u: Dual, v: Dual, alpha: f32) -> [Dual; 3] {
+    let (ca, sa) = (alpha.cos(), alpha.sin());
+    let x = ca * v.sinh() * u.sin() + sa * v.cosh() * u.cos();
+    let z = -ca * v.sinh() * u.cos() + sa * v.cosh() * u.sin();
+    let y = u * ca + v * sa;
+    [x, y, z]
+}
+
+fn normalize(a: [Dual; 3]) -> [Dual; 3] {
+    let len = (a[0] * a[0] + a[1] * a[1] + a[2] * a[2]).sqrt();
+    [a[0] / len, a[1] / len, a[2] / len]
+}
+// endregion: surface derivatives
@@ -0,0 +1,230 @@
+use std::cmp::Ordering;
+
+// region: draw list
+// Draw calls of a frame collected before they are recorded, so they can be ordered
+// to save state changes: opaque draws grouped by pipeline, then by bind group, and
+// blended draws after all opaque ones from far to near, so they blend over what is
+// behind them. The sort is stable, draws with equal keys keep the order they were
+// pushed in. execute() sets a pipeline or bind group only when it changes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DrawKey {
+    pub pipeline: usize,    // index into the pipelines passed to execute()
+    pub bind_group: usize,  // index into the group 0 bind groups passed to execute()
+    pub depth: Option<f32>, // distance from the eye for blended draws, None if opaque
+}
+
+impl DrawKey {
+    pub fn opaque(pipeline: usize, bind_group: usize) -> Self {
+        Self {
+            pipeline,
+            bind_group,
+            depth: None,
+        }
+    }
+
+    pub fn blended(pipeline: usize, bind_group: usize, depth: f32) -> Self {
+        Self {
+            pipeline,
+            bind_group,
+            depth: Some(depth),
+        }
+    }
+
+    // draw order: opaque before blended, opaque by pipeline and bind group, blended
+    // back to front
+    pub fn order(&self, other: &Self) -> Ordering {
+        match (self.depth, other.depth) {
+            (None, None) => {
+                (self.pipeline, self.bind_group).cmp(&(other.pipeline, other.bind_group))
+            }
+            (None, Some(_)) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+            (Some(a), Some(b)) => b.total_cmp(&a),
+        }
+    }
+}
+
+// state changes needed to record draws in a given order; the first pipeline and
+// bind group count as switches too
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DrawStats {
+    pub draws: usize,
+    pub pipeline_switches: usize,
+    pub bind_group_switches: usize,
+}
+
+impl DrawStats {
+    pub fn of<'a>(keys: impl IntoIterator<Item = &'a DrawKey>) -> Self {
+        let mut stats = Self::default();
+        let (mut pipeline, mut bind_group) = (None, None);
+        for key in keys {
+            stats.draws += 1;
+            if pipeline != Some(key.pipeline) {
+                pipeline = Some(key.pipeline);
+                stats.pipeline_switches += 1;
+            }
+            if bind_group != Some(key.bind_group) {
+                bind_group = Some(key.bind_group);
+                stats.bind_group_switches += 1;
+            }
+        }
+        stats
+    }
+
+    pub fn switches(&self) -> usize {
+        self.pipeline_switches + self.bind_group_switches
+    }
+}
+
+impl std::ops::AddAssign for DrawStats {
+    fn add_assign(&mut self, other: Self) {
+        self.draws += other.draws;
+        self.pipeline_switches += other.pipeline_switches;
+        self.bind_group_switches += other.bind_group_switches;
+    }
+}
+
+// stats of the draws in the order they were pushed and after sorting
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SortStats {
+    pub submitted: DrawStats,
+    pub sorted: DrawStats,
+}
+
+impl SortStats {
+    pub fn saved(&self) -> usize {
+        self.submitted
+            .switches()
+            .saturating_sub(self.sorted.switches())
+    }
+}
+
+impl std::ops::AddAssign for SortStats {
+    fn add_assign(&mut self, other: Self) {
+        self.submitted += other.submitted;
+        self.sorted += other.sorted;
+    }
+}
+
+impl std::fmt::Display for SortStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} draws, {} pipeline and {} bind group switches ({} saved by sorting)",
+            self.sorted.draws,
+            self.sorted.pipeline_switches,
+            self.sorted.bind_group_switches,
+            self.saved()
+        )
+    }
+}
+
+pub struct DrawList<T> {
+    items: Vec<(DrawKey, T)>,
+}
+
+impl<T> Default for DrawList<T> {
+    fn default() -> Self {
+        Self { items: Vec::new() }
+    }
+}
+
+impl<T> DrawList<T> {
+    pub fn push(&mut self, key: DrawKey, item: T) {
+        self.items.push((key, item));
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &DrawKey> {
+        self.items.iter().map(|(key, _)| key)
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = &T> {
+        self.items.iter().map(|(_, item)| item)
+    }
+
+    pub fn stats(&self) -> DrawStats {
+        DrawStats::of(self.keys())
+    }
+
+    pub fn sort(&mut self) -> SortStats {
+        let submitted = self.stats();
+        self.items.sort_by(|(a, _), (b, _)| a.order(b));
+        SortStats {
+            submitted,
+            sorted: self.stats(),
+        }
+    }
+}
+
+// one draw call: a non-indexed draw of count vertices, or an indexed one of count
+// indices; blended draws may set the render pass blend constant
+pub struct Draw<'a> {
+    pub vertex_buffer: wgpu::BufferSlice<'a>,
+    pub index_buffer: Option<(wgpu::BufferSlice<'a>, wgpu::IndexFormat)>,
+    pub count: u32,
+    pub blend_constant: Option<wgpu::Color>,
+}
+
+impl<'a> Draw<'a> {
+    pub fn vertices(buffer: &'a wgpu::Buffer, count: u32) -> Self {
+        Self {
+            vertex_buffer: buffer.slice(..),
+            index_buffer: None,
+            count,
+            blend_constant: None,
+        }
+    }
+
+    pub fn indexed(
+        vertices: &'a wgpu::Buffer,
+        indices: &'a wgpu::Buffer,
+        format: wgpu::IndexFormat,
+        count: u32,
+    ) -> Self {
+        Self {
+            index_buffer: Some((indices.slice(..), format)),
+            ..Self::vertices(vertices, count)
+        }
+    }
+}
+
+impl DrawList<Draw<'_>> {
+    pub fn execute(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        pipelines: &[wgpu::RenderPipeline],
+        bind_groups: &[&wgpu::BindGroup],
+    ) {
+        let (mut pipeline, mut bind_group) = (None, None);
+        for (key, draw) in self.items.iter() {
+            if pipeline != Some(key.pipeline) {
+                pipeline = Some(key.pipeline);
+                render_pass.set_pipeline(&pipelines[key.pipeline]);
+            }
+            if bind_group != Some(key.bind_group) {
+                bind_group = Some(key.bind_group);
+                render_pass.set_bind_group(0, bind_groups[key.bind_group], &[]);
+            }
+            if let Some(color) = draw.blend_constant {
+                render_pass.set_blend_constant(color);
+            }
+            render_pass.set_vertex_buffer(0, draw.vertex_buffer);
+            match draw.index_buffer {
+                Some((indices, format)) => {
+                    render_pass.set_index_buffer(indices, format);
+                    render_pass.draw_indexed(0..draw.count, 0, 0..1);
+                }
+                None => render_pass.draw(0..draw.count, 0..1),
+            }
+        }
+    }
+}
+// endregion: draw list
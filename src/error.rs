@@ -0,0 +1,131 @@
+use std::path::PathBuf;
+
+use super::capabilities::DisabledSubsystem;
+use super::material::MaterialKind;
+
+// Errors of the wgpu setup and the pipeline and bind group helpers, so a machine
+// without a compatible GPU or a malformed pipeline gives a message instead of a
+// panic. Converts into anyhow::Error for the examples and the functions that already
+// return anyhow::Result:
+//
+//     let init = ws::InitWgpu::init_wgpu(window, args.msaa, args.present_mode).await?;
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("cannot create a surface for the window: {0}")]
+    CreateSurface(#[from] wgpu::CreateSurfaceError),
+    #[error("no compatible GPU adapter found: {0}")]
+    RequestAdapter(#[from] wgpu::RequestAdapterError),
+    #[error("cannot open the GPU device: {0}")]
+    RequestDevice(#[from] wgpu::RequestDeviceError),
+    #[error("render pipeline '{label}' has no {missing}")]
+    IncompletePipeline {
+        label: String,
+        missing: &'static str,
+    },
+    #[error("bind group '{label}' has {stages} shader stages for {resources} resources")]
+    BindingCount {
+        label: &'static str,
+        stages: usize,
+        resources: usize,
+    },
+    // switching the material kind needs another fragment shader, i.e. a new pipeline
+    #[error("the pipeline shades {} materials, not {}", .pipeline.name(), .material.name())]
+    MaterialKind {
+        pipeline: MaterialKind,
+        material: MaterialKind,
+    },
+    // a subsystem the capability negotiation switched off, see capabilities.rs
+    #[error("{0}")]
+    Unsupported(DisabledSubsystem),
+    // a surface name the api builders do not know
+    #[error("unknown surface type '{0}'")]
+    UnknownSurface(String),
+    // a generated mesh with more vertices than its u16 indices can address
+    #[error("the {0} mesh needs more vertices than u16 indices address, reduce the resolution")]
+    IndexOverflow(&'static str),
+    // a render graph pass using an undeclared attachment, or passes waiting on each other
+    #[error("render graph: {0}")]
+    RenderGraph(String),
+    // a keymap file that does not parse, or names an unknown action or key
+    #[error("keymap: {0}")]
+    Keymap(#[from] KeymapError),
+    // a scene file that cannot be read, written or parsed, see scene.rs
+    #[error("scene: {0}")]
+    Scene(#[from] SceneError),
+    // a wgpu validation error caught in an error scope, with wgpu's description
+    #[error("{context}: {message}")]
+    Validation {
+        context: &'static str,
+        message: String,
+    },
+}
+
+// why a keymap file was rejected, see app::InputMap
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum KeymapError {
+    #[error("cannot read {path}: {source}")]
+    Read {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error(transparent)]
+    Parse(#[from] toml::de::Error),
+    #[error("unknown action '{0}'")]
+    UnknownAction(String),
+    #[error("unknown key '{key}' for {action}")]
+    UnknownKey { key: String, action: &'static str },
+}
+
+// why a scene could not be saved or loaded; File adds the path to a parse error
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum SceneError {
+    #[error("cannot read {}: {source}", .path.display())]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("cannot write {}: {source}", .path.display())]
+    Write {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("{}: {source}", .path.display())]
+    File {
+        path: PathBuf,
+        source: Box<SceneError>,
+    },
+    #[error(transparent)]
+    Ron(#[from] ron::error::SpannedError),
+    #[error(transparent)]
+    RonWrite(#[from] ron::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+// runs create inside a validation error scope and turns a validation error into
+// Error::Validation; on native backends the scope resolves without waiting. The
+// browser resolves it on a later turn of its event loop, so there create runs
+// unscoped and the device's uncaptured error handler reports to the console
+pub fn validated<T>(
+    device: &wgpu::Device,
+    context: &'static str,
+    create: impl FnOnce() -> T,
+) -> Result<T> {
+    if cfg!(target_arch = "wasm32") {
+        return Ok(create());
+    }
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let value = create();
+    match pollster::block_on(device.pop_error_scope()) {
+        Some(error) => Err(Error::Validation {
+            context,
+            message: error.to_string(),
+        }),
+        None => Ok(value),
+    }
+}
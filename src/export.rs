@@ -0,0 +1,500 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use super::color;
+use super::surface_data::ISurfaceOutput;
+
+// region: coordinate conventions
+// The renderer works in a right-handed, Y-up world with counter-clockwise front
+// faces and images stored top row first. Target tools differ on all three, so every
+// export path (screenshots, recorded frames, meshes) runs its data through an
+// ExportConvention; the default leaves everything unchanged.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UpAxis {
+    #[default]
+    YUp,
+    ZUp,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Winding {
+    #[default]
+    Ccw,
+    Cw,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportConvention {
+    pub up_axis: UpAxis,
+    pub flip_v: bool, // images bottom row first, texture coordinates v -> 1 - v
+    pub winding: Winding,
+}
+
+impl ExportConvention {
+    pub fn z_up() -> Self {
+        Self {
+            up_axis: UpAxis::ZUp,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    // rotates Y-up to Z-up about the x axis, keeping the frame right-handed
+    pub fn transform_point(&self, p: [f32; 3]) -> [f32; 3] {
+        match self.up_axis {
+            UpAxis::YUp => p,
+            UpAxis::ZUp => [p[0], -p[2], p[1]],
+        }
+    }
+
+    // a pure rotation, so normals transform like points
+    pub fn transform_normal(&self, n: [f32; 3]) -> [f32; 3] {
+        self.transform_point(n)
+    }
+
+    pub fn transform_uv(&self, uv: [f32; 2]) -> [f32; 2] {
+        if self.flip_v {
+            [uv[0], 1.0 - uv[1]]
+        } else {
+            uv
+        }
+    }
+
+    // reorders triangle-list indices in place for clockwise front faces
+    pub fn transform_triangles<T: Copy>(&self, indices: &mut [T]) {
+        if self.winding == Winding::Cw {
+            for tri in indices.chunks_exact_mut(3) {
+                tri.swap(1, 2);
+            }
+        }
+    }
+
+    // flips tightly packed RGBA8 rows in place when the target expects the
+    // bottom row first
+    pub fn transform_image(&self, width: u32, height: u32, rgba: &mut [u8]) {
+        if !self.flip_v {
+            return;
+        }
+        let row_bytes = 4 * width as usize;
+        let height = height as usize;
+        for row in 0..height / 2 {
+            let (top, bottom) = rgba.split_at_mut((height - 1 - row) * row_bytes);
+            top[row * row_bytes..(row + 1) * row_bytes].swap_with_slice(&mut bottom[..row_bytes]);
+        }
+    }
+}
+// endregion: coordinate conventions
+
+// region: mesh files
+// The triangles of a surface with positions, normals, vertex colors and texture
+// coordinates, for modelling tools such as Blender. OBJ carries the colors with the
+// common "v x y z r g b" extension; PLY is binary little-endian with 8-bit colors. Only
+// the shaded triangles (indices) are written, not the wireframe lines (indices2).
+
+fn triangles(output: &ISurfaceOutput, convention: &ExportConvention) -> Vec<u32> {
+    let mut indices: Vec<u32> = output.indices.iter().map(|&i| i as u32).collect();
+    indices.truncate(indices.len() / 3 * 3);
+    convention.transform_triangles(&mut indices);
+    indices
+}
+
+fn write_f32s(writer: &mut impl Write, values: &[f32]) -> std::io::Result<()> {
+    for v in values {
+        writer.write_all(&v.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+fn color_byte(c: f32) -> u8 {
+    (c.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+pub fn write_obj(
+    mut writer: impl Write,
+    output: &ISurfaceOutput,
+    convention: &ExportConvention,
+) -> anyhow::Result<()> {
+    let count = output.positions.len();
+    let has_normals = output.normals.len() == count;
+    let has_colors = output.colors.len() == count;
+    let has_uvs = output.uvs.len() == count;
+    writeln!(writer, "# wgpu_surfaces surface, {} vertices", count)?;
+    for (i, &p) in output.positions.iter().enumerate() {
+        let [x, y, z] = convention.transform_point(p);
+        if has_colors {
+            let [r, g, b] = output.colors[i];
+            writeln!(writer, "v {x} {y} {z} {r} {g} {b}")?;
+        } else {
+            writeln!(writer, "v {x} {y} {z}")?;
+        }
+    }
+    if has_uvs {
+        for &uv in output.uvs.iter() {
+            let [u, v] = convention.transform_uv(uv);
+            writeln!(writer, "vt {u} {v}")?;
+        }
+    }
+    if has_normals {
+        for &n in output.normals.iter() {
+            let [x, y, z] = convention.transform_normal(n);
+            writeln!(writer, "vn {x} {y} {z}")?;
+        }
+    }
+    // OBJ indices start at 1
+    let corner = |i: u32| match (has_uvs, has_normals) {
+        (true, true) => format!("{0}/{0}/{0}", i + 1),
+        (true, false) => format!("{0}/{0}", i + 1),
+        (false, true) => format!("{0}//{0}", i + 1),
+        (false, false) => format!("{}", i + 1),
+    };
+    for tri in triangles(output, convention).chunks_exact(3) {
+        writeln!(
+            writer,
+            "f {} {} {}",
+            corner(tri[0]),
+            corner(tri[1]),
+            corner(tri[2])
+        )?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn write_ply(
+    mut writer: impl Write,
+    output: &ISurfaceOutput,
+    convention: &ExportConvention,
+) -> anyhow::Result<()> {
+    let count = output.positions.len();
+    let has_normals = output.normals.len() == count;
+    let has_colors = output.colors.len() == count;
+    let has_uvs = output.uvs.len() == count;
+    let indices = triangles(output, convention);
+
+    let mut header = String::from("ply\nformat binary_little_endian 1.0\n");
+    header += "comment wgpu_surfaces surface\n";
+    header += &format!("element vertex {}\n", count);
+    header += "property float x\nproperty float y\nproperty float z\n";
+    if has_normals {
+        header += "property float nx\nproperty float ny\nproperty float nz\n";
+    }
+    if has_colors {
+        header += "property uchar red\nproperty uchar green\nproperty uchar blue\n";
+    }
+    if has_uvs {
+        header += "property float s\nproperty float t\n";
+    }
+    header += &format!("element face {}\n", indices.len() / 3);
+    header += "property list uchar uint vertex_indices\nend_header\n";
+    writer.write_all(header.as_bytes())?;
+
+    for i in 0..count {
+        write_f32s(
+            &mut writer,
+            &convention.transform_point(output.positions[i]),
+        )?;
+        if has_normals {
+            write_f32s(&mut writer, &convention.transform_normal(output.normals[i]))?;
+        }
+        if has_colors {
+            writer.write_all(&output.colors[i].map(color_byte))?;
+        }
+        if has_uvs {
+            write_f32s(&mut writer, &convention.transform_uv(output.uvs[i]))?;
+        }
+    }
+    for tri in indices.chunks_exact(3) {
+        writer.write_all(&[3])?;
+        for i in tri {
+            writer.write_all(&i.to_le_bytes())?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn export_obj(output: &ISurfaceOutput, path: impl AsRef<Path>) -> anyhow::Result<()> {
+    export_obj_as(output, path, &ExportConvention::default())
+}
+
+pub fn export_obj_as(
+    output: &ISurfaceOutput,
+    path: impl AsRef<Path>,
+    convention: &ExportConvention,
+) -> anyhow::Result<()> {
+    write_obj(BufWriter::new(File::create(path)?), output, convention)
+}
+
+pub fn export_ply(output: &ISurfaceOutput, path: impl AsRef<Path>) -> anyhow::Result<()> {
+    export_ply_as(output, path, &ExportConvention::default())
+}
+
+pub fn export_ply_as(
+    output: &ISurfaceOutput,
+    path: impl AsRef<Path>,
+    convention: &ExportConvention,
+) -> anyhow::Result<()> {
+    write_ply(BufWriter::new(File::create(path)?), output, convention)
+}
+// endregion: mesh files
+
+// region: gltf
+// The same triangles as a glTF 2.0 asset, for game engines and web viewers: one mesh
+// of one primitive with POSITION, NORMAL, COLOR_0 and TEXCOORD_0 (those the surface
+// has) and 32-bit indices. write_glb packs the JSON and the binary buffer into one
+// file; write_gltf writes the JSON alone, with the buffer as a base64 data URI. glTF
+// vertex colors are linear and its texture coordinates start at the top left, so the
+// colors go through color::to_linear and v is flipped after the convention.
+const GLTF_ARRAY_BUFFER: u32 = 34962;
+const GLTF_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const GLTF_FLOAT: u32 = 5126;
+const GLTF_UNSIGNED_INT: u32 = 5125;
+
+// the JSON document, without buffers, and the binary buffer it refers to
+fn gltf_parts(output: &ISurfaceOutput, convention: &ExportConvention) -> (Value, Vec<u8>) {
+    let count = output.positions.len();
+    let mut bin: Vec<u8> = vec![];
+    let mut views: Vec<Value> = vec![];
+    let mut accessors: Vec<Value> = vec![];
+    // every view holds 4-byte values, so the offsets stay aligned
+    let mut push = |bytes: &[u8], target: u32, accessor: Value| {
+        let view = views.len();
+        views.push(json!({
+            "buffer": 0,
+            "byteOffset": bin.len(),
+            "byteLength": bytes.len(),
+            "target": target,
+        }));
+        bin.extend_from_slice(bytes);
+        let mut accessor = accessor;
+        accessor["bufferView"] = json!(view);
+        accessors.push(accessor);
+        accessors.len() - 1
+    };
+    let vec3 = |values: &[[f32; 3]]| -> Vec<u8> {
+        values
+            .iter()
+            .flatten()
+            .flat_map(|v| v.to_le_bytes())
+            .collect()
+    };
+
+    let positions: Vec<[f32; 3]> = output
+        .positions
+        .iter()
+        .map(|&p| convention.transform_point(p))
+        .collect();
+    let (mut min, mut max) = ([f32::MAX; 3], [f32::MIN; 3]);
+    for p in positions.iter() {
+        for k in 0..3 {
+            (min[k], max[k]) = (min[k].min(p[k]), max[k].max(p[k]));
+        }
+    }
+    let mut attributes = serde_json::Map::new();
+    let accessor = json!({
+        "componentType": GLTF_FLOAT, "count": count, "type": "VEC3", "min": min, "max": max,
+    });
+    let position = push(&vec3(&positions), GLTF_ARRAY_BUFFER, accessor);
+    attributes.insert("POSITION".into(), json!(position));
+    let float_accessor =
+        |kind: &str| json!({"componentType": GLTF_FLOAT, "count": count, "type": kind});
+    if output.normals.len() == count {
+        let normals: Vec<[f32; 3]> = output
+            .normals
+            .iter()
+            .map(|&n| convention.transform_normal(n))
+            .collect();
+        let normal = push(&vec3(&normals), GLTF_ARRAY_BUFFER, float_accessor("VEC3"));
+        attributes.insert("NORMAL".into(), json!(normal));
+    }
+    if output.colors.len() == count {
+        let colors: Vec<[f32; 3]> = output.colors.iter().map(|&c| color::to_linear(c)).collect();
+        let color = push(&vec3(&colors), GLTF_ARRAY_BUFFER, float_accessor("VEC3"));
+        attributes.insert("COLOR_0".into(), json!(color));
+    }
+    if output.uvs.len() == count {
+        let uvs: Vec<u8> = output
+            .uvs
+            .iter()
+            .flat_map(|&uv| {
+                let [u, v] = convention.transform_uv(uv);
+                [u, 1.0 - v]
+            })
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let uv = push(&uvs, GLTF_ARRAY_BUFFER, float_accessor("VEC2"));
+        attributes.insert("TEXCOORD_0".into(), json!(uv));
+    }
+    let indices = triangles(output, convention);
+    let index_bytes: Vec<u8> = indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+    let accessor = json!({
+        "componentType": GLTF_UNSIGNED_INT, "count": indices.len(), "type": "SCALAR",
+    });
+    let indices = push(&index_bytes, GLTF_ELEMENT_ARRAY_BUFFER, accessor);
+
+    let document = json!({
+        "asset": {"version": "2.0", "generator": "wgpu_surfaces"},
+        "scene": 0,
+        "scenes": [{"nodes": [0]}],
+        "nodes": [{"mesh": 0}],
+        "meshes": [{"primitives": [{"attributes": attributes, "indices": indices, "mode": 4}]}],
+        "buffers": [{"byteLength": bin.len()}],
+        "bufferViews": views,
+        "accessors": accessors,
+    });
+    (document, bin)
+}
+
+pub fn write_glb(
+    mut writer: impl Write,
+    output: &ISurfaceOutput,
+    convention: &ExportConvention,
+) -> anyhow::Result<()> {
+    let (document, mut bin) = gltf_parts(output, convention);
+    // both chunks are padded to 4 bytes, the JSON with spaces and the buffer with zeros
+    let mut json = serde_json::to_vec(&document)?;
+    json.resize(json.len().next_multiple_of(4), b' ');
+    bin.resize(bin.len().next_multiple_of(4), 0);
+    let length = 12 + 8 + json.len() + 8 + bin.len();
+    writer.write_all(b"glTF")?;
+    writer.write_all(&2u32.to_le_bytes())?;
+    writer.write_all(&(length as u32).to_le_bytes())?;
+    for (chunk, kind) in [(&json, b"JSON"), (&bin, b"BIN\0")] {
+        writer.write_all(&(chunk.len() as u32).to_le_bytes())?;
+        writer.write_all(kind)?;
+        writer.write_all(chunk)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn write_gltf(
+    mut writer: impl Write,
+    output: &ISurfaceOutput,
+    convention: &ExportConvention,
+) -> anyhow::Result<()> {
+    let (mut document, bin) = gltf_parts(output, convention);
+    let uri = format!("data:application/octet-stream;base64,{}", base64(&bin));
+    document["buffers"][0]["uri"] = json!(uri);
+    serde_json::to_writer_pretty(&mut writer, &document)?;
+    writer.flush()?;
+    Ok(())
+}
+
+// standard base64 with padding
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for group in bytes.chunks(3) {
+        let b = [0, 1, 2].map(|k| group.get(k).copied().unwrap_or(0) as u32);
+        let n = (b[0] << 16) | (b[1] << 8) | b[2];
+        for k in 0..4 {
+            if k <= group.len() {
+                text.push(ALPHABET[(n >> (18 - 6 * k) & 63) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+pub fn export_glb(output: &ISurfaceOutput, path: impl AsRef<Path>) -> anyhow::Result<()> {
+    export_glb_as(output, path, &ExportConvention::default())
+}
+
+pub fn export_glb_as(
+    output: &ISurfaceOutput,
+    path: impl AsRef<Path>,
+    convention: &ExportConvention,
+) -> anyhow::Result<()> {
+    write_glb(BufWriter::new(File::create(path)?), output, convention)
+}
+
+pub fn export_gltf(output: &ISurfaceOutput, path: impl AsRef<Path>) -> anyhow::Result<()> {
+    export_gltf_as(output, path, &ExportConvention::default())
+}
+
+pub fn export_gltf_as(
+    output: &ISurfaceOutput,
+    path: impl AsRef<Path>,
+    convention: &ExportConvention,
+) -> anyhow::Result<()> {
+    write_gltf(BufWriter::new(File::create(path)?), output, convention)
+}
+// endregion: gltf
+
+// region: mesh formats
+// The mesh file formats by extension, for tools that take the format from the output
+// path: export_mesh_as writes whichever the path names.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MeshFormat {
+    Obj,
+    Ply,
+    Gltf,
+    Glb,
+}
+
+impl MeshFormat {
+    pub const ALL: [Self; 4] = [Self::Obj, Self::Ply, Self::Gltf, Self::Glb];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Obj => "obj",
+            Self::Ply => "ply",
+            Self::Gltf => "gltf",
+            Self::Glb => "glb",
+        }
+    }
+
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.extension().eq_ignore_ascii_case(extension))
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?;
+        Self::from_extension(extension)
+    }
+
+    pub fn write(
+        &self,
+        writer: impl Write,
+        output: &ISurfaceOutput,
+        convention: &ExportConvention,
+    ) -> anyhow::Result<()> {
+        match self {
+            Self::Obj => write_obj(writer, output, convention),
+            Self::Ply => write_ply(writer, output, convention),
+            Self::Gltf => write_gltf(writer, output, convention),
+            Self::Glb => write_glb(writer, output, convention),
+        }
+    }
+}
+
+pub fn export_mesh_as(
+    output: &ISurfaceOutput,
+    path: impl AsRef<Path>,
+    convention: &ExportConvention,
+) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    let format = MeshFormat::from_path(path).ok_or_else(|| {
+        let names = MeshFormat::ALL.map(|format| format.extension());
+        anyhow::anyhow!(
+            "unknown mesh format '{}'; available: {}",
+            path.display(),
+            names.join(", ")
+        )
+    })?;
+    format.write(BufWriter::new(File::create(path)?), output, convention)
+}
+// endregion: mesh formats
@@ -0,0 +1,89 @@
+use super::playlist::{IPlaylist, IScene};
+
+// region: gallery presets
+// Ready-made playlists of composed scenes, e.g. for a demo or a screenshot session:
+//
+//     let playlist = gallery::preset("klein_turntable").unwrap();
+//
+// and from the command line: cargo run --example parametric_surface -- klein_turntable.
+// Every preset loops; the scenes only name built-in colormaps and parametric surface
+// types, so a preset plays on any build of the crate.
+pub const PRESET_NAMES: [&str; 5] = [
+    "klein_turntable",
+    "non_orientable",
+    "minimal_surfaces",
+    "tubes",
+    "shells",
+];
+
+// the surface turning on the spot while the camera rises slowly from the side
+fn turntable(surface_type: u32, caption: &str, colormap: &str, duration: f32) -> IScene {
+    IScene {
+        surface_type,
+        duration,
+        caption: caption.to_string(),
+        camera_start: [0.0, 1.0, 4.5],
+        camera_end: [0.0, 3.0, 3.5],
+        colormap_name: Some(colormap.to_string()),
+        rotation_speed: Some(0.6),
+        ..Default::default()
+    }
+}
+
+// a camera sweep from one side of the still surface to the other
+fn fly_by(surface_type: u32, caption: &str, colormap: &str, duration: f32) -> IScene {
+    IScene {
+        surface_type,
+        duration,
+        caption: caption.to_string(),
+        camera_start: [3.0, 2.0, 3.0],
+        camera_end: [-3.0, 1.5, 3.0],
+        colormap_name: Some(colormap.to_string()),
+        rotation_speed: Some(0.0),
+        ..Default::default()
+    }
+}
+
+pub fn preset(name: &str) -> Option<IPlaylist> {
+    let scenes = match name {
+        "klein_turntable" => vec![turntable(0, "Klein bottle", "viridis", 12.0)],
+        "non_orientable" => vec![
+            turntable(0, "Klein bottle", "viridis", 6.0),
+            turntable(26, "Boy's surface", "plasma", 6.0),
+            turntable(25, "Cross-cap", "cool", 6.0),
+            fly_by(8, "Figure-8 Klein bottle", "jet", 6.0),
+        ],
+        "minimal_surfaces" => vec![
+            fly_by(7, "Enneper surface", "plasma", 5.0),
+            fly_by(28, "Catenoid-helicoid", "viridis", 5.0),
+            turntable(29, "Costa surface", "cool", 6.0),
+            fly_by(27, "Scherk surface", "hot", 5.0),
+            turntable(9, "Henneberg surface", "plasma", 5.0),
+        ],
+        "tubes" => vec![
+            turntable(23, "Torus knot", "hot", 6.0),
+            turntable(24, "Klein bagel", "viridis", 6.0),
+            turntable(21, "Torus", "cool", 5.0),
+        ],
+        "shells" => vec![
+            turntable(18, "Seashell", "hot", 6.0),
+            fly_by(6, "Breather surface", "viridis", 6.0),
+            fly_by(13, "Kuen surface", "plasma", 5.0),
+        ],
+        _ => return None,
+    };
+    Some(IPlaylist {
+        scenes,
+        looping: true,
+        ..Default::default()
+    })
+}
+
+// all presets with their names, in PRESET_NAMES order
+pub fn presets() -> Vec<(&'static str, IPlaylist)> {
+    PRESET_NAMES
+        .iter()
+        .filter_map(|&name| preset(name).map(|playlist| (name, playlist)))
+        .collect()
+}
+// endregion: gallery presets
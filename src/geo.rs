@@ -0,0 +1,269 @@
+use std::f64::consts::PI;
+use std::io::BufRead;
+
+use cgmath::{InnerSpace, Vector3};
+
+use super::colormap;
+use super::surface_data::{ISurfaceOutput, grid_indices};
+
+// WGS84 ellipsoid
+pub const EARTH_RADIUS: f64 = 6_378_137.0;
+pub const EARTH_FLATTENING: f64 = 1.0 / 298.257_223_563;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MapProjection {
+    // plate carrée scaled by the cosine of the center latitude, fine for small areas
+    Equirectangular,
+    // conformal; distances grow with the latitude, so keep away from the poles
+    Mercator,
+    // positions on the ellipsoid, showing the curvature of the earth
+    Globe,
+}
+
+// Elevation grid on geographic coordinates, e.g. a DEM tile. Lat/lon are in degrees
+// and elevations in meters. The mesh keeps true proportions (a vertical exaggeration
+// of 1 is a real-world scale) and is fitted into [-scale, scale] with x pointing
+// east, y up and z south at the center of the grid.
+pub struct GeoTerrain {
+    pub rows: u16,
+    pub cols: u16,
+    pub lats: Vec<f32>, // per grid point, row-major
+    pub lons: Vec<f32>,
+    pub elevations: Vec<f32>,
+    pub projection: MapProjection,
+    pub vertical_exaggeration: f32,
+    pub scale: f32,
+    pub colormap_name: String,
+    pub wireframe_color: String,
+}
+
+impl GeoTerrain {
+    // grid with its own coordinates per point, e.g. from a curvilinear dataset
+    pub fn from_grids(
+        rows: u16,
+        cols: u16,
+        lats: Vec<f32>,
+        lons: Vec<f32>,
+        elevations: Vec<f32>,
+    ) -> Self {
+        let n = rows as usize * cols as usize;
+        assert!(
+            lats.len() == n && lons.len() == n && elevations.len() == n,
+            "a {} x {} grid needs {} coordinates and elevations",
+            rows,
+            cols,
+            n
+        );
+        Self {
+            rows,
+            cols,
+            lats,
+            lons,
+            elevations,
+            projection: MapProjection::Equirectangular,
+            vertical_exaggeration: 1.0,
+            scale: 2.0,
+            colormap_name: "terrain".to_string(),
+            wireframe_color: "white".to_string(),
+        }
+    }
+
+    // regular grid: rows run from lat_range[0] to lat_range[1], columns from
+    // lon_range[0] to lon_range[1]
+    pub fn from_regular(
+        rows: u16,
+        cols: u16,
+        lat_range: [f32; 2],
+        lon_range: [f32; 2],
+        elevations: Vec<f32>,
+    ) -> Self {
+        let t = |k: u16, n: u16| k as f32 / (n.max(2) - 1) as f32;
+        let mut lats = Vec::with_capacity(rows as usize * cols as usize);
+        let mut lons = Vec::with_capacity(rows as usize * cols as usize);
+        for i in 0..rows {
+            for j in 0..cols {
+                lats.push(lat_range[0] + t(i, rows) * (lat_range[1] - lat_range[0]));
+                lons.push(lon_range[0] + t(j, cols) * (lon_range[1] - lon_range[0]));
+            }
+        }
+        Self::from_grids(rows, cols, lats, lons, elevations)
+    }
+
+    // Reads "lon lat elevation" lines as written by gdal2xyz and most GIS exports,
+    // separated by whitespace or commas. The points must form a row-major grid: the
+    // row length is taken from the first change of latitude. Lines that do not start
+    // with three numbers, such as headers, are skipped.
+    pub fn read_xyz(reader: impl BufRead) -> std::io::Result<Self> {
+        let invalid =
+            |message: String| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+        let (mut lats, mut lons, mut elevations) = (vec![], vec![], vec![]);
+        for line in reader.lines() {
+            let line = line?;
+            let values: Vec<f32> = line
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|s| !s.is_empty())
+                .map_while(|s| s.parse::<f32>().ok())
+                .collect();
+            if values.len() >= 3 {
+                lons.push(values[0]);
+                lats.push(values[1]);
+                elevations.push(values[2]);
+            }
+        }
+        let cols = lats
+            .iter()
+            .position(|&lat| lat != lats[0])
+            .unwrap_or(lats.len());
+        if cols < 2 || lats.len() % cols != 0 || lats.len() / cols < 2 {
+            return Err(invalid(format!("{} points do not form a grid", lats.len())));
+        }
+        let rows = lats.len() / cols;
+        if rows * cols > 1 << 16 {
+            return Err(invalid(format!(
+                "a {} x {} grid exceeds the 65536 vertices of a mesh",
+                rows, cols
+            )));
+        }
+        Ok(Self::from_grids(
+            rows as u16,
+            cols as u16,
+            lats,
+            lons,
+            elevations,
+        ))
+    }
+
+    pub fn elevation_range(&self) -> [f32; 2] {
+        let finite = self.elevations.iter().copied().filter(|v| v.is_finite());
+        let (min, max) = finite.fold((f32::MAX, f32::MIN), |(a, b), v| (a.min(v), b.max(v)));
+        if min > max { [0.0, 0.0] } else { [min, max] }
+    }
+
+    // center [lat, lon] of the coordinate bounds, in degrees
+    pub fn center(&self) -> [f32; 2] {
+        let mid = |v: &[f32]| {
+            let (min, max) = v
+                .iter()
+                .fold((f32::MAX, f32::MIN), |(a, b), &x| (a.min(x), b.max(x)));
+            0.5 * (min + max)
+        };
+        [mid(&self.lats), mid(&self.lons)]
+    }
+
+    // position in meters of a point, before centering and scaling
+    pub fn project(&self, lat: f32, lon: f32, elevation: f32) -> [f64; 3] {
+        let [lat0, lon0] = self.center().map(|d| (d as f64).to_radians());
+        let (lat, lon) = ((lat as f64).to_radians(), (lon as f64).to_radians());
+        let h = elevation as f64 * self.vertical_exaggeration as f64;
+        // longitudes wrap around the antimeridian
+        let dlon = (lon - lon0 + PI).rem_euclid(2.0 * PI) - PI;
+        let r = EARTH_RADIUS;
+        match self.projection {
+            MapProjection::Equirectangular => [r * dlon * lat0.cos(), h, -r * (lat - lat0)],
+            MapProjection::Mercator => {
+                let y = |lat: f64| (0.25 * PI + 0.5 * lat.clamp(-1.5, 1.5)).tan().ln();
+                [r * dlon, h, -r * (y(lat) - y(lat0))]
+            }
+            MapProjection::Globe => {
+                // earth centered coordinates with the center meridian in the up/north plane
+                let e2 = EARTH_FLATTENING * (2.0 - EARTH_FLATTENING);
+                let n = r / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+                let p = (n + h) * lat.cos();
+                let (east, north, up) = (
+                    p * dlon.sin(),
+                    (n * (1.0 - e2) + h) * lat.sin(),
+                    p * dlon.cos(),
+                );
+                // rotate about the east axis so the grid center is on top
+                let (s, c) = lat0.sin_cos();
+                [east, up * c + north * s, up * s - north * c]
+            }
+        }
+    }
+
+    // up direction at a point, used to orient the normals
+    fn up(&self, lat: f32, lon: f32) -> Vector3<f64> {
+        if self.projection != MapProjection::Globe {
+            return Vector3::unit_y();
+        }
+        let p0 = Vector3::from(self.project(lat, lon, 0.0));
+        let p1 =
+            Vector3::from(self.project(lat, lon, 1000.0 / self.vertical_exaggeration.max(1e-6)));
+        p1 - p0
+    }
+
+    // generates the mesh; does not touch any GPU state
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(&self) -> ISurfaceOutput {
+        let (rows, cols) = (self.rows as usize, self.cols as usize);
+        assert!(
+            rows >= 2 && cols >= 2 && rows * cols <= 1 << 16,
+            "grid too small or too large"
+        );
+        let elevation = |k: usize| {
+            let v = self.elevations[k];
+            if v.is_finite() { v } else { 0.0 }
+        };
+        let raw: Vec<[f64; 3]> = (0..rows * cols)
+            .map(|k| self.project(self.lats[k], self.lons[k], elevation(k)))
+            .collect();
+
+        // centering and scale come from the datum surface, so changing the
+        // exaggeration does not move the terrain around
+        let datum: Vec<[f64; 3]> = (0..rows * cols)
+            .map(|k| self.project(self.lats[k], self.lons[k], 0.0))
+            .collect();
+        let mut lo = [f64::MAX; 3];
+        let mut hi = [f64::MIN; 3];
+        for p in datum.iter() {
+            for a in 0..3 {
+                lo[a] = lo[a].min(p[a]);
+                hi[a] = hi[a].max(p[a]);
+            }
+        }
+        let extent = (0..3).map(|a| hi[a] - lo[a]).fold(0.0, f64::max).max(1e-9);
+        let factor = 2.0 * self.scale as f64 / extent;
+        let center = [0, 1, 2].map(|a| 0.5 * (lo[a] + hi[a]));
+        let world = |p: &[f64; 3]| {
+            Vector3::new(p[0] - center[0], p[1] - center[1], p[2] - center[2]) * factor
+        };
+        let positions: Vec<Vector3<f64>> = raw.iter().map(world).collect();
+
+        let [emin, emax] = self.elevation_range();
+        let cdata = colormap::colormap_data(&self.colormap_name);
+        let cdata2 = colormap::colormap_data(&self.wireframe_color);
+        let mut data = ISurfaceOutput::default();
+        for i in 0..rows {
+            for j in 0..cols {
+                let k = i * cols + j;
+                let at = |a: usize, b: usize| positions[a * cols + b];
+                let d_row = at((i + 1).min(rows - 1), j) - at(i.saturating_sub(1), j);
+                let d_col = at(i, (j + 1).min(cols - 1)) - at(i, j.saturating_sub(1));
+                let mut normal = d_row.cross(d_col);
+                if normal.dot(self.up(self.lats[k], self.lons[k])) < 0.0 {
+                    normal = -normal;
+                }
+                let normal = if normal.magnitude2() > 0.0 {
+                    normal.normalize()
+                } else {
+                    self.up(self.lats[k], self.lons[k]).normalize()
+                };
+                let p = positions[k];
+                data.positions.push([p.x as f32, p.y as f32, p.z as f32]);
+                data.normals
+                    .push([normal.x as f32, normal.y as f32, normal.z as f32]);
+                let v = elevation(k);
+                data.colors.push(if emax > emin {
+                    colormap::color_lerp(cdata, emin, emax, v)
+                } else {
+                    cdata[0]
+                });
+                data.colors2.push(cdata2[0]);
+                data.uvs
+                    .push([j as f32 / (cols - 1) as f32, i as f32 / (rows - 1) as f32]);
+            }
+        }
+        (data.indices, data.indices2) = grid_indices(self.rows - 1, self.cols - 1);
+        data
+    }
+}
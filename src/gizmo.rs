@@ -0,0 +1,702 @@
+use std::f32::consts::PI;
+
+use bytemuck::cast_slice;
+use cgmath::{
+    InnerSpace, Matrix, Matrix4, Point3, Quaternion, Rad, Rotation3, SquareMatrix, Transform,
+    Vector3,
+};
+use winit::event::{ElementState, KeyEvent, WindowEvent};
+use winit::keyboard::Key;
+
+use super::error::Result;
+use super::picking::Ray;
+use super::surface_data::ISurfaceOutput;
+use super::wgpu_simplified as ws;
+
+// region: gizmo
+// Interactive handles for moving an object with the mouse: three arrows along the
+// world axes to translate it, or three rings around them to rotate it. The gizmo sits
+// at the object position; pick() tells which handle a ray from the cursor hits,
+// begin_drag()/drag()/end_drag() follow a mouse drag on it and model_mat() is the
+// resulting placement. With snapping, positions move on a grid and rotations in
+// fixed angle steps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GizmoMode {
+    Translate,
+    Rotate,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Snapping {
+    pub translate: Option<f32>, // grid spacing in world units
+    pub rotate: Option<f32>,    // angle step in radians
+}
+
+pub const AXIS_COLORS: [[f32; 3]; 3] = [[1.0, 0.2, 0.2], [0.2, 1.0, 0.2], [0.3, 0.4, 1.0]];
+pub const ACTIVE_COLOR: [f32; 3] = [1.0, 1.0, 0.2];
+const RING_SEGMENTS: usize = 48;
+// most vertices line_vertices() returns, for sizing a vertex buffer
+pub const MAX_LINE_VERTICES: usize = 6 * RING_SEGMENTS;
+
+#[derive(Clone, Copy, Debug)]
+struct Drag {
+    axis: usize,
+    start: f32, // axis parameter or ring angle where the drag started
+    position: [f32; 3],
+    rotation: Quaternion<f32>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Gizmo {
+    pub mode: GizmoMode,
+    pub position: [f32; 3],
+    pub rotation: Quaternion<f32>,
+    pub size: f32,      // arrow length and ring radius in world units
+    pub tolerance: f32, // pick distance as a fraction of size
+    pub snap: Snapping,
+    pub hovered: Option<usize>, // axis under the cursor, drawn highlighted
+    drag: Option<Drag>,
+}
+
+impl Default for Gizmo {
+    fn default() -> Self {
+        Self {
+            mode: GizmoMode::Translate,
+            position: [0.0, 0.0, 0.0],
+            rotation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            size: 1.0,
+            tolerance: 0.08,
+            snap: Snapping::default(),
+            hovered: None,
+            drag: None,
+        }
+    }
+}
+
+fn axis(i: usize) -> Vector3<f32> {
+    let mut a = [0.0; 3];
+    a[i] = 1.0;
+    a.into()
+}
+
+// two unit vectors spanning the plane perpendicular to axis i, in cyclic order
+fn ring_basis(i: usize) -> (Vector3<f32>, Vector3<f32>) {
+    (axis((i + 1) % 3), axis((i + 2) % 3))
+}
+
+fn snap_to(value: f32, step: Option<f32>) -> f32 {
+    match step {
+        Some(step) if step > 0.0 => (value / step).round() * step,
+        _ => value,
+    }
+}
+
+// parameters (s along the ray, t along the line) of the closest points of a ray and
+// the line p + t * d; None if they are parallel
+fn closest_on_line(ray: &Ray, p: Vector3<f32>, d: Vector3<f32>) -> Option<(f32, f32)> {
+    let (o, r) = (Vector3::from(ray.origin), Vector3::from(ray.direction));
+    let w = o - p;
+    let (a, b, c) = (r.dot(r), r.dot(d), d.dot(d));
+    let (e, f) = (r.dot(w), d.dot(w));
+    let denom = a * c - b * b;
+    if denom.abs() < 1e-8 {
+        return None;
+    }
+    Some(((b * f - c * e) / denom, (a * f - b * e) / denom))
+}
+
+// distance along the ray to the plane through p with normal n
+fn hit_plane(ray: &Ray, p: Vector3<f32>, n: Vector3<f32>) -> Option<f32> {
+    let denom = Vector3::from(ray.direction).dot(n);
+    if denom.abs() < 1e-6 {
+        return None;
+    }
+    let s = (p - Vector3::from(ray.origin)).dot(n) / denom;
+    (s >= 0.0).then_some(s)
+}
+
+impl Gizmo {
+    pub fn new(mode: GizmoMode, position: [f32; 3], size: f32) -> Self {
+        Self {
+            mode,
+            position,
+            size,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    // the handle being dragged, else the hovered one
+    pub fn active(&self) -> Option<usize> {
+        self.drag.map(|drag| drag.axis).or(self.hovered)
+    }
+
+    pub fn model_mat(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.position.into()) * Matrix4::from(self.rotation)
+    }
+
+    // axis of the nearest handle the ray hits
+    pub fn pick(&self, ray: &Ray) -> Option<usize> {
+        let p = Vector3::from(self.position);
+        let reach = self.tolerance * self.size;
+        let mut best: Option<(f32, usize)> = None;
+        for i in 0..3 {
+            let hit = match self.mode {
+                GizmoMode::Translate => {
+                    let Some((s, t)) = closest_on_line(ray, p, axis(i)) else {
+                        continue;
+                    };
+                    let t = t.clamp(0.0, self.size);
+                    let on_ray = Vector3::from(ray.origin) + s * Vector3::from(ray.direction);
+                    let gap = (on_ray - (p + t * axis(i))).magnitude();
+                    (s >= 0.0 && gap <= reach).then_some(s)
+                }
+                GizmoMode::Rotate => hit_plane(ray, p, axis(i)).filter(|&s| {
+                    let q = Vector3::from(ray.origin) + s * Vector3::from(ray.direction);
+                    ((q - p).magnitude() - self.size).abs() <= reach
+                }),
+            };
+            if let Some(s) = hit
+                && best.is_none_or(|(d, _)| s < d)
+            {
+                best = Some((s, i));
+            }
+        }
+        best.map(|(_, i)| i)
+    }
+
+    // axis parameter or ring angle under the ray for the handle of axis i of a gizmo
+    // at position
+    fn handle_value(&self, ray: &Ray, i: usize, position: [f32; 3]) -> Option<f32> {
+        let p = Vector3::from(position);
+        match self.mode {
+            GizmoMode::Translate => closest_on_line(ray, p, axis(i)).map(|(_, t)| t),
+            GizmoMode::Rotate => {
+                let s = hit_plane(ray, p, axis(i))?;
+                let q = Vector3::from(ray.origin) + s * Vector3::from(ray.direction) - p;
+                let (u, v) = ring_basis(i);
+                Some(q.dot(v).atan2(q.dot(u)))
+            }
+        }
+    }
+
+    // starts a drag if the ray hits a handle
+    pub fn begin_drag(&mut self, ray: &Ray) -> bool {
+        let Some(axis) = self.pick(ray) else {
+            return false;
+        };
+        let Some(start) = self.handle_value(ray, axis, self.position) else {
+            return false;
+        };
+        self.drag = Some(Drag {
+            axis,
+            start,
+            position: self.position,
+            rotation: self.rotation,
+        });
+        true
+    }
+
+    // moves the object with the dragged handle; returns true if the placement changed
+    pub fn drag(&mut self, ray: &Ray) -> bool {
+        let Some(drag) = self.drag else {
+            return false;
+        };
+        let Some(value) = self.handle_value(ray, drag.axis, drag.position) else {
+            return false;
+        };
+        let (position, rotation) = (self.position, self.rotation);
+        match self.mode {
+            GizmoMode::Translate => {
+                let mut moved = drag.position;
+                moved[drag.axis] =
+                    snap_to(moved[drag.axis] + value - drag.start, self.snap.translate);
+                self.position = moved;
+            }
+            GizmoMode::Rotate => {
+                // shortest way around, so crossing the +-PI seam does not flip the object
+                let mut angle = (value - drag.start).rem_euclid(2.0 * PI);
+                if angle > PI {
+                    angle -= 2.0 * PI;
+                }
+                let angle = snap_to(angle, self.snap.rotate);
+                self.rotation =
+                    Quaternion::from_axis_angle(axis(drag.axis), Rad(angle)) * drag.rotation;
+            }
+        }
+        self.position != position || self.rotation != rotation
+    }
+
+    pub fn end_drag(&mut self) {
+        self.drag = None;
+    }
+
+    // line list of the handles in world space: (position, color) pairs, the active
+    // handle highlighted
+    pub fn line_vertices(&self) -> Vec<([f32; 3], [f32; 3])> {
+        let p = Vector3::from(self.position);
+        let mut lines = vec![];
+        for (i, &axis_color) in AXIS_COLORS.iter().enumerate() {
+            let color = if self.active() == Some(i) {
+                ACTIVE_COLOR
+            } else {
+                axis_color
+            };
+            let mut segment = |a: Vector3<f32>, b: Vector3<f32>| {
+                lines.push((a.into(), color));
+                lines.push((b.into(), color));
+            };
+            let (u, v) = ring_basis(i);
+            match self.mode {
+                GizmoMode::Translate => {
+                    let tip = p + self.size * axis(i);
+                    let back = tip - 0.15 * self.size * axis(i);
+                    segment(p, tip);
+                    for side in [u, -u, v, -v] {
+                        segment(tip, back + 0.06 * self.size * side);
+                    }
+                }
+                GizmoMode::Rotate => {
+                    let point = |k: usize| {
+                        let a = 2.0 * PI * k as f32 / RING_SEGMENTS as f32;
+                        p + self.size * (a.cos() * u + a.sin() * v)
+                    };
+                    for k in 0..RING_SEGMENTS {
+                        segment(point(k), point(k + 1));
+                    }
+                }
+            }
+        }
+        lines
+    }
+}
+// endregion: gizmo
+
+// region: guides
+// Reference lines for reading a plot: the XYZ axes from the origin, a grid in the
+// ground plane and the bounding box of the surface. The line builders return world
+// space (position, color) pairs of a line list like Gizmo::line_vertices(); Guides
+// draws them with a pipeline of its own, so any example can add it next to its
+// surface:
+//
+//     guides.input(event)                            // key v cycles the guides
+//     guides.update(&device, &queue, &vp_mat, bounds(positions), &model_mat);
+//     guides.draw(&mut render_pass);                 // in the main render pass
+pub const GRID_COLOR: [f32; 3] = [0.45, 0.45, 0.45];
+pub const BOX_COLOR: [f32; 3] = [0.9, 0.9, 0.9];
+pub const GUIDES_KEY: &str = "v";
+
+// axis aligned box [min, max] around the positions, None if there are none
+pub fn bounds(positions: impl IntoIterator<Item = [f32; 3]>) -> Option<[[f32; 3]; 2]> {
+    let mut positions = positions.into_iter();
+    let first = positions.next()?;
+    Some(positions.fold([first, first], |[min, max], p| {
+        [
+            [min[0].min(p[0]), min[1].min(p[1]), min[2].min(p[2])],
+            [max[0].max(p[0]), max[1].max(p[1]), max[2].max(p[2])],
+        ]
+    }))
+}
+
+// x, y and z axes from origin in AXIS_COLORS
+pub fn axes_lines(origin: [f32; 3], length: f32) -> Vec<([f32; 3], [f32; 3])> {
+    let p = Vector3::from(origin);
+    AXIS_COLORS
+        .iter()
+        .enumerate()
+        .flat_map(|(i, &color)| [(origin, color), ((p + length * axis(i)).into(), color)])
+        .collect()
+}
+
+// square grid in the plane y = center[1], centered on center, with lines every spacing
+// out to at least half_size in x and z
+pub fn grid_lines(center: [f32; 3], half_size: f32, spacing: f32) -> Vec<([f32; 3], [f32; 3])> {
+    if spacing <= 0.0 {
+        return vec![];
+    }
+    let n = (half_size / spacing).ceil().max(1.0) as i32;
+    let h = n as f32 * spacing;
+    let [cx, y, cz] = center;
+    let mut lines = vec![];
+    for k in -n..=n {
+        let d = k as f32 * spacing;
+        lines.push(([cx + d, y, cz - h], GRID_COLOR));
+        lines.push(([cx + d, y, cz + h], GRID_COLOR));
+        lines.push(([cx - h, y, cz + d], GRID_COLOR));
+        lines.push(([cx + h, y, cz + d], GRID_COLOR));
+    }
+    lines
+}
+
+// the 12 edges of the box [min, max] placed by model_mat
+pub fn box_lines(
+    [min, max]: [[f32; 3]; 2],
+    model_mat: &Matrix4<f32>,
+    color: [f32; 3],
+) -> Vec<([f32; 3], [f32; 3])> {
+    let corner = |k: usize| {
+        let p = Point3::new(
+            if k & 1 == 0 { min[0] } else { max[0] },
+            if k & 2 == 0 { min[1] } else { max[1] },
+            if k & 4 == 0 { min[2] } else { max[2] },
+        );
+        model_mat.transform_point(p).into()
+    };
+    // corners k and k | bit differ along one axis
+    let mut lines = vec![];
+    for k in 0..8 {
+        for bit in [1, 2, 4] {
+            if k & bit == 0 {
+                lines.push((corner(k), color));
+                lines.push((corner(k | bit), color));
+            }
+        }
+    }
+    lines
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GuideMode {
+    #[default]
+    Off,
+    AxesGrid,
+    All, // axes, grid and bounding box
+}
+
+impl GuideMode {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Off => Self::AxesGrid,
+            Self::AxesGrid => Self::All,
+            Self::All => Self::Off,
+        }
+    }
+}
+
+const GUIDES_WGSL: &str = r#"
+@group(0) @binding(0) var<uniform> vpMat: mat4x4f;
+
+struct Output {
+    @builtin(position) position: vec4f,
+    @location(0) color: vec4f,
+}
+
+@vertex
+fn vs_main(@location(0) position: vec3f, @location(1) color: vec3f) -> Output {
+    var output: Output;
+    output.position = vpMat * vec4f(position, 1.0);
+    output.color = vec4f(color, 1.0);
+    return output;
+}
+
+@fragment
+fn fs_main(in: Output) -> @location(0) vec4f {
+    return in.color;
+}
+"#;
+
+// Draws world space line lists of (position, color) pairs in a render pass; the
+// pipeline behind Guides and NormalLines.
+pub struct LineRenderer {
+    pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    vertex_buffer: ws::GrowableBuffer,
+    count: u32,
+}
+
+impl LineRenderer {
+    // line list pipeline for the target's color format and sample count, with the
+    // Depth24Plus depth buffer of the example render passes
+    pub fn new(init: &impl ws::RenderTarget) -> Result<Self> {
+        let device = init.device();
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Line Shader"),
+            source: wgpu::ShaderSource::Wgsl(GUIDES_WGSL.into()),
+        });
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Line Uniform Buffer"),
+            size: 64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let (layout, bind_group) = ws::create_bind_group(
+            device,
+            vec![wgpu::ShaderStages::VERTEX],
+            &[uniform_buffer.as_entire_binding()],
+        )?;
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Line Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = ws::IRenderPipeline {
+            shader: Some(&shader),
+            pipeline_layout: Some(&pipeline_layout),
+            vertex_buffer_layout: &[wgpu::VertexBufferLayout {
+                array_stride: 24,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3],
+            }],
+            topology: wgpu::PrimitiveTopology::LineList,
+            ..Default::default()
+        }
+        .new(init)?;
+        let vertex_buffer = ws::GrowableBuffer::new(
+            device,
+            "Line Vertex Buffer",
+            wgpu::BufferUsages::VERTEX,
+            &[0; 24],
+        );
+        Ok(Self {
+            pipeline,
+            uniform_buffer,
+            bind_group,
+            vertex_buffer,
+            count: 0,
+        })
+    }
+
+    pub fn write(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        vp_mat: &Matrix4<f32>,
+        lines: &[([f32; 3], [f32; 3])],
+    ) {
+        self.count = lines.len() as u32;
+        if lines.is_empty() {
+            return;
+        }
+        let vp_ref: &[f32; 16] = vp_mat.as_ref();
+        queue.write_buffer(&self.uniform_buffer, 0, cast_slice(vp_ref));
+        let vertices: Vec<[f32; 6]> = lines
+            .iter()
+            .map(|&([x, y, z], [r, g, b])| [x, y, z, r, g, b])
+            .collect();
+        self.vertex_buffer
+            .write(device, queue, cast_slice(&vertices));
+    }
+
+    // draws the lines of the last write after the other draws, since it sets its own
+    // pipeline and group 0; the render pass needs a Depth24Plus depth attachment
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        if self.count == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice());
+        render_pass.draw(0..self.count, 0..1);
+    }
+}
+
+pub struct Guides {
+    pub mode: GuideMode,
+    pub axis_length: f32,
+    pub grid_center: [f32; 3],
+    pub grid_half_size: f32,
+    pub grid_spacing: f32,
+    renderer: LineRenderer,
+}
+
+impl Guides {
+    pub fn new(init: &impl ws::RenderTarget) -> Result<Self> {
+        Ok(Self {
+            mode: GuideMode::Off,
+            axis_length: 2.0,
+            grid_center: [0.0; 3],
+            grid_half_size: 3.0,
+            grid_spacing: 0.5,
+            renderer: LineRenderer::new(init)?,
+        })
+    }
+
+    // GUIDES_KEY cycles off, axes and grid, axes, grid and bounding box
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Character(c),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } if c.as_str() == GUIDES_KEY => {
+                self.mode = self.mode.next();
+                log::info!("guides: {:?}", self.mode);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // the lines of the current mode; bounds are in model space, placed by model_mat
+    pub fn lines(
+        &self,
+        bounds: Option<[[f32; 3]; 2]>,
+        model_mat: &Matrix4<f32>,
+    ) -> Vec<([f32; 3], [f32; 3])> {
+        let mut lines = vec![];
+        if self.mode == GuideMode::Off {
+            return lines;
+        }
+        lines.extend(grid_lines(
+            self.grid_center,
+            self.grid_half_size,
+            self.grid_spacing,
+        ));
+        lines.extend(axes_lines([0.0; 3], self.axis_length));
+        if let (GuideMode::All, Some(bounds)) = (self.mode, bounds) {
+            lines.extend(box_lines(bounds, model_mat, BOX_COLOR));
+        }
+        lines
+    }
+
+    pub fn update(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        vp_mat: &Matrix4<f32>,
+        bounds: Option<[[f32; 3]; 2]>,
+        model_mat: &Matrix4<f32>,
+    ) {
+        let lines = self.lines(bounds, model_mat);
+        self.write_lines(device, queue, vp_mat, &lines);
+    }
+
+    // uploads any world space line list in place of the guides, e.g. lines() with
+    // other markers such as picking::Crosshair added
+    pub fn write_lines(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        vp_mat: &Matrix4<f32>,
+        lines: &[([f32; 3], [f32; 3])],
+    ) {
+        self.renderer.write(device, queue, vp_mat, lines);
+    }
+
+    // see LineRenderer::draw
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        self.renderer.draw(render_pass);
+    }
+}
+// endregion: guides
+
+// region: normal lines
+// Debug view of the vertex normals: a segment from each vertex along its normal,
+// colored by direction (x, y, z mapped to red, green, blue) unless a color is set.
+pub const NORMALS_KEY: &str = "u";
+
+impl ISurfaceOutput {
+    // world space segments of the given length for the mesh placed by model_mat;
+    // normals are taken through the inverse transpose, so they stay perpendicular
+    // under non-uniform scaling
+    pub fn normal_lines(&self, length: f32, model_mat: &Matrix4<f32>) -> Vec<([f32; 3], [f32; 3])> {
+        let Some(normal_mat) = model_mat.invert().map(|m| m.transpose()) else {
+            return vec![];
+        };
+        self.positions
+            .iter()
+            .zip(&self.normals)
+            .flat_map(|(&p, &n)| {
+                let n = normal_mat.transform_vector(Vector3::from(n));
+                let n = if n.magnitude2() > 0.0 {
+                    n.normalize()
+                } else {
+                    n
+                };
+                let color = [n.x, n.y, n.z].map(|c| 0.5 + 0.5 * c);
+                let p = model_mat.transform_point(Point3::from(p));
+                [(p.into(), color), ((p + length * n).into(), color)]
+            })
+            .collect()
+    }
+}
+
+pub struct NormalLines {
+    pub visible: bool,
+    pub length: f32,
+    pub color: Option<[f32; 3]>, // one color for all segments instead of the direction
+    mesh: ISurfaceOutput,        // positions and normals only
+    renderer: LineRenderer,
+}
+
+impl NormalLines {
+    pub fn new(init: &impl ws::RenderTarget) -> Result<Self> {
+        Ok(Self {
+            visible: false,
+            length: 0.1,
+            color: None,
+            mesh: ISurfaceOutput::default(),
+            renderer: LineRenderer::new(init)?,
+        })
+    }
+
+    // keeps the positions and normals of the surface each time it is regenerated
+    pub fn set_mesh(&mut self, output: &ISurfaceOutput) {
+        self.mesh.positions.clone_from(&output.positions);
+        self.mesh.normals.clone_from(&output.normals);
+    }
+
+    // NORMALS_KEY toggles the normals; + and - change their length while shown
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        let WindowEvent::KeyboardInput {
+            event:
+                KeyEvent {
+                    logical_key: Key::Character(c),
+                    state: ElementState::Pressed,
+                    ..
+                },
+            ..
+        } = event
+        else {
+            return false;
+        };
+        match c.as_str() {
+            NORMALS_KEY => {
+                self.visible = !self.visible;
+                log::info!("normals: {}", if self.visible { "on" } else { "off" });
+            }
+            "+" | "=" if self.visible => {
+                self.length *= 1.25;
+                log::info!("normal length: {:.3}", self.length);
+            }
+            "-" if self.visible => {
+                self.length /= 1.25;
+                log::info!("normal length: {:.3}", self.length);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn update(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        vp_mat: &Matrix4<f32>,
+        model_mat: &Matrix4<f32>,
+    ) {
+        if !self.visible {
+            self.renderer.write(device, queue, vp_mat, &[]);
+            return;
+        }
+        let mut lines = self.mesh.normal_lines(self.length, model_mat);
+        if let Some(color) = self.color {
+            lines.iter_mut().for_each(|line| line.1 = color);
+        }
+        self.renderer.write(device, queue, vp_mat, &lines);
+    }
+
+    // see LineRenderer::draw
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        self.renderer.draw(render_pass);
+    }
+}
+// endregion: normal lines
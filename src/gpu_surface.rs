@@ -0,0 +1,1032 @@
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+use super::color::{self, COLOR_SPACE_WGSL};
+use super::colormap;
+use super::noise;
+use super::surface_data::{self as sd, ISurfaceOutput};
+
+// region: surface shader
+// Compute shaders that evaluate a surface function over a grid and write vertices
+// straight into vertex buffers, so animated surfaces at high resolutions never
+// round-trip through the CPU. Each vertex is 9 floats, position, normal and color,
+// the Vertex layout of the chapter pipelines (Float32x3 at locations 0, 1 and 2).
+// Three dispatches run per update:
+//   cs_reset   clears the bounding box of the surface
+//   cs_bounds  evaluates the grid and shrinks the box with atomics on the float bits
+//   cs_main    normalizes positions as ISimpleSurface/IParametricSurface do and writes
+//              normals and colormap colors, linear as color::to_linear gives them
+// The surface function is WGSL source defining
+//     fn surface_fn(u: f32, v: f32, t: f32) -> vec3f
+// (for simple surfaces u = x, v = z, and y is the height), which may call the
+// functions of the noise library.
+pub const GPU_SURFACE_WGSL: &str = r#"
+struct SurfaceParams {
+    domain: vec4f, // umin, umax, vmin, vmax
+    resolution: vec2u,
+    kind: u32, // 0: simple, 1: parametric
+    colormap_direction: u32,
+    scale: f32,
+    aspect_ratio: f32,
+    t: f32,
+    fixed_range: u32, // 1: colormap_range replaces the data range
+    colormap_range: vec2f,
+    normal_sign: f32, // -1 flips the normals, see surface_data::NormalOrientation
+    colors: array<vec4f, 11>,
+    colors2: array<vec4f, 11>,
+}
+
+@group(0) @binding(0) var<uniform> params: SurfaceParams;
+@group(0) @binding(1) var<storage, read_write> bounds: array<atomic<u32>, 6>;
+@group(0) @binding(2) var<storage, read_write> vertices: array<f32>;
+@group(0) @binding(3) var<storage, read_write> vertices2: array<f32>;
+
+// maps float bits to integers of the same order, so atomicMin/Max work on floats
+fn to_ordered(v: f32) -> u32 {
+    let b = bitcast<u32>(v);
+    return select(b | 0x80000000u, ~b, (b & 0x80000000u) != 0u);
+}
+
+fn from_ordered(b: u32) -> f32 {
+    return bitcast<f32>(select(~b, b & 0x7fffffffu, (b & 0x80000000u) != 0u));
+}
+
+fn is_finite(v: f32) -> bool {
+    return (bitcast<u32>(v) & 0x7f800000u) != 0x7f800000u;
+}
+
+fn grid_step() -> vec2f {
+    let d = params.domain;
+    return vec2f(d.y - d.x, d.w - d.z) / vec2f(params.resolution);
+}
+
+fn grid_point(id: vec2u) -> vec2f {
+    return params.domain.xz + grid_step() * vec2f(id);
+}
+
+fn in_grid(id: vec2u) -> bool {
+    return all(id <= params.resolution);
+}
+
+@compute @workgroup_size(1)
+fn cs_reset() {
+    for (var k = 0u; k < 3u; k++) {
+        atomicStore(&bounds[k], 0xffffffffu);
+        atomicStore(&bounds[k + 3u], 0u);
+    }
+}
+
+@compute @workgroup_size(8, 8)
+fn cs_bounds(@builtin(global_invocation_id) id: vec3u) {
+    if !in_grid(id.xy) {
+        return;
+    }
+    let p = grid_point(id.xy);
+    let pt = surface_fn(p.x, p.y, params.t);
+    for (var k = 0u; k < 3u; k++) {
+        if is_finite(pt[k]) {
+            atomicMin(&bounds[k], to_ordered(pt[k]));
+            atomicMax(&bounds[k + 3u], to_ordered(pt[k]));
+        }
+    }
+}
+
+// y = f(x, z) mapped onto [-scale, scale] horizontally and scale * aspect_ratio
+// vertically, as ISimpleSurface::normalize_data
+fn simple_position(x: f32, z: f32, ymin: f32, ymax: f32) -> vec3f {
+    let d = params.domain;
+    let pt = surface_fn(x, z, params.t);
+    let n = vec3f(
+        -1.0 + 2.0 * (pt.x - d.x) / (d.y - d.x),
+        -1.0 + 2.0 * (pt.y - ymin) / (ymax - ymin),
+        -1.0 + 2.0 * (pt.z - d.z) / (d.w - d.z),
+    );
+    return n * params.scale * vec3f(1.0, params.aspect_ratio, 1.0);
+}
+
+// central differences as surface_data::parametric_normal; w is 0 where the
+// partial derivatives vanish or are parallel
+fn parametric_normal_at(u: f32, v: f32, eps: vec2f) -> vec4f {
+    let nu = surface_fn(u + eps.x, v, params.t) - surface_fn(u - eps.x, v, params.t);
+    let nv = surface_fn(u, v + eps.y, params.t) - surface_fn(u, v - eps.y, params.t);
+    let n = cross(nu, nv);
+    let m = dot(n, n);
+    let valid = m > 1e-12 * dot(nu, nu) * dot(nv, nv) && is_finite(m);
+    return select(vec4f(0.0), vec4f(n * inverseSqrt(m), 1.0), valid);
+}
+
+fn parametric_normal(u: f32, v: f32) -> vec3f {
+    let step = grid_step();
+    let eps = 0.01 * step;
+    let n = parametric_normal_at(u, v, eps);
+    if n.w > 0.0 {
+        return n.xyz;
+    }
+    var sum = vec3f(0.0);
+    for (var k = 0u; k < 4u; k++) {
+        let s = vec2f(select(0.5, -0.5, (k & 1u) != 0u), select(0.5, -0.5, (k & 2u) != 0u));
+        sum += parametric_normal_at(u + s.x * step.x, v + s.y * step.y, eps).xyz;
+    }
+    return select(vec3f(0.0, 1.0, 0.0), normalize(sum), dot(sum, sum) > 1e-12);
+}
+
+// colormap::color_lerp on the surface (which = 0) or wireframe colormap
+fn color_lerp(which: u32, range: vec2f, value: f32) -> vec3f {
+    let tn = (clamp(value, range.x, range.y) - range.x) / (range.y - range.x);
+    let index = min(u32(floor(10.0 * tn)), 9u);
+    let s = 10.0 * tn - f32(index);
+    if which == 0u {
+        return mix(params.colors[index].rgb, params.colors[index + 1u].rgb, s);
+    }
+    return mix(params.colors2[index].rgb, params.colors2[index + 1u].rgb, s);
+}
+
+fn write_vertex(k: u32, position: vec3f, normal: vec3f, srgb: vec3f, srgb2: vec3f) {
+    let color = srgb_to_linear(srgb);
+    let color2 = srgb_to_linear(srgb2);
+    for (var i = 0u; i < 3u; i++) {
+        vertices[k + i] = position[i];
+        vertices[k + 3u + i] = normal[i];
+        vertices[k + 6u + i] = color[i];
+        vertices2[k + i] = position[i];
+        vertices2[k + 3u + i] = normal[i];
+        vertices2[k + 6u + i] = color2[i];
+    }
+}
+
+@compute @workgroup_size(8, 8)
+fn cs_main(@builtin(global_invocation_id) id: vec3u) {
+    if !in_grid(id.xy) {
+        return;
+    }
+    let p = grid_point(id.xy);
+    let lo = vec3f(
+        from_ordered(atomicLoad(&bounds[0])),
+        from_ordered(atomicLoad(&bounds[1])),
+        from_ordered(atomicLoad(&bounds[2])),
+    );
+    let hi = vec3f(
+        from_ordered(atomicLoad(&bounds[3])),
+        from_ordered(atomicLoad(&bounds[4])),
+        from_ordered(atomicLoad(&bounds[5])),
+    );
+    let dir = params.colormap_direction;
+
+    var position: vec3f;
+    var normal: vec3f;
+    var range: vec2f;
+    if params.kind == 0u {
+        let eps = 0.01 * grid_step();
+        position = simple_position(p.x, p.y, lo.y, hi.y);
+        let nx = simple_position(p.x + eps.x, p.y, lo.y, hi.y)
+            - simple_position(p.x - eps.x, p.y, lo.y, hi.y);
+        let nz = simple_position(p.x, p.y + eps.y, lo.y, hi.y)
+            - simple_position(p.x, p.y - eps.y, lo.y, hi.y);
+        normal = normalize(cross(nx, nz));
+        let r = select(params.scale, params.scale * params.aspect_ratio, dir == 1u);
+        range = vec2f(-r, r);
+    } else {
+        // centered in the bounding box and scaled by its longest side
+        let center = 0.5 * (lo + hi);
+        let size = hi - lo;
+        let dist = max(max(size.x, size.y), size.z);
+        position = params.scale * (surface_fn(p.x, p.y, params.t) - center) / dist;
+        normal = parametric_normal(p.x, p.y);
+        range = params.scale * (vec2f(lo[dir], hi[dir]) - center[dir]) / dist;
+    }
+    if params.fixed_range == 1u {
+        range = params.colormap_range;
+    }
+
+    let value = position[dir];
+    let k = 9u * (id.x * (params.resolution.y + 1u) + id.y);
+    write_vertex(k, position, params.normal_sign * normal, color_lerp(0u, range, value), color_lerp(1u, range, value));
+}
+"#;
+
+// WGSL surface_fn of a built-in simple surface type, see surface_data::simple_surface_fn
+pub fn simple_surface_wgsl(surface_type: u32) -> &'static str {
+    match surface_type {
+        0 => {
+            r#"
+fn surface_fn(x: f32, z: f32, t: f32) -> vec3f {
+    let a = 1.01 + sin(t);
+    let r = a * sqrt(x * x + z * z);
+    return vec3f(x, select(sin(r) / r, 1.0, r == 0.0), z);
+}
+"#
+        }
+        1 => {
+            r#"
+fn surface_fn(x: f32, z: f32, t: f32) -> vec3f {
+    let a = 1.5 * sin(t);
+    let y = x * z / (abs((x - a) * (x - a) * (x - a)) + (z - 2.0 * a) * (z - 2.0 * a) + 2.0);
+    return vec3f(x, y, z);
+}
+"#
+        }
+        _ => {
+            r#"
+fn surface_fn(x: f32, z: f32, t: f32) -> vec3f {
+    let a = 1.00001 + sin(t);
+    let b = 1.00001 + sin(1.5 * t);
+    let c = 1.00001 + sin(2.0 * t);
+    let y = 3.0 * (1.0 - x) * (1.0 - x) * exp(-a * (x * x) - a * (z + 1.0) * (z + 1.0))
+        - 10.0 * (x / 5.0 - x * x * x - z * z * z * z * z) * exp(-b * x * x - b * z * z)
+        - 1.0 / 3.0 * exp(-c * (x + 1.0) * (x + 1.0) - c * z * z);
+    return vec3f(z, y, x);
+}
+"#
+        }
+    }
+}
+
+// WGSL surface_fn of the built-in parametric surface types ported so far; others
+// need their own source passed to GpuSurface::new
+pub fn parametric_surface_wgsl(surface_type: u32) -> Option<&'static str> {
+    let source = match surface_type {
+        0 => {
+            r#"
+fn surface_fn(u: f32, v: f32, t: f32) -> vec3f {
+    let c = cos(u);
+    let s = sin(u);
+    let cv = cos(v);
+    let c2 = c * c;
+    let c4 = c2 * c2;
+    let c6 = c4 * c2;
+    let x = 2.0 / 15.0 * (3.0 + 5.0 * c * s) * sin(v);
+    let y = -1.0 / 15.0 * s * (3.0 * cv - 3.0 * c2 * cv - 48.0 * c4 * cv + 48.0 * c6 * cv
+        - 60.0 * s + 5.0 * c * cv * s - 5.0 * c2 * c * cv * s - 80.0 * c4 * c * cv * s
+        + 80.0 * c6 * c * cv * s);
+    let z = -2.0 / 15.0 * c * (3.0 * cv - 30.0 * s + 90.0 * c4 * s - 60.0 * c6 * s
+        + 5.0 * c * cv * s);
+    return vec3f(x, y, z);
+}
+"#
+        }
+        8 => {
+            r#"
+fn surface_fn(u: f32, v: f32, t: f32) -> vec3f {
+    let r = 2.5 + cos(0.5 * u) * sin(v) - sin(0.5 * u) * sin(2.0 * v);
+    let z = sin(0.5 * u) * sin(v) + cos(0.5 * u) * sin(2.0 * v);
+    return vec3f(r * cos(u), r * sin(u), z);
+}
+"#
+        }
+        18 => {
+            r#"
+fn surface_fn(u: f32, v: f32, t: f32) -> vec3f {
+    let pi = 3.14159265;
+    let e = exp(u / (6.0 * pi));
+    let c = cos(v / 2.0);
+    let x = 2.0 * (-1.0 + e) * sin(u) * c * c;
+    let y = 1.0 - exp(u / (3.0 * pi)) - sin(v) + e * sin(v);
+    let z = 2.0 * (1.0 - e) * cos(u) * c * c;
+    return vec3f(x, y, z);
+}
+"#
+        }
+        21 => {
+            r#"
+fn surface_fn(u: f32, v: f32, t: f32) -> vec3f {
+    let r = 1.0 + 0.3 * cos(v);
+    return vec3f(r * cos(u), 0.3 * sin(v), r * sin(u));
+}
+"#
+        }
+        22 => {
+            r#"
+fn surface_fn(u: f32, v: f32, t: f32) -> vec3f {
+    let c = cos(cos(u));
+    return vec3f(u * c * sin(v), u * sin(cos(u)), u * c * cos(v));
+}
+"#
+        }
+        _ => return None,
+    };
+    Some(source)
+}
+// endregion: surface shader
+
+// region: gpu surface
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SurfaceKind {
+    #[default]
+    Simple, // y = f(x, z), normalized like ISimpleSurface
+    Parametric, // (x, y, z) = f(u, v), normalized like IParametricSurface
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct SurfaceUniforms {
+    domain: [f32; 4],
+    resolution: [u32; 2],
+    kind: u32,
+    colormap_direction: u32,
+    scale: f32,
+    aspect_ratio: f32,
+    t: f32,
+    fixed_range: u32,
+    colormap_range: [f32; 2],
+    normal_sign: f32,
+    _padding: f32,
+    colors: [[f32; 4]; 11],
+    colors2: [[f32; 4]; 11],
+}
+
+// A surface generated on the GPU. The public fields mirror those of ISimpleSurface
+// and IParametricSurface and take effect at the next compute() call; the vertex
+// buffers can be bound directly by the chapter pipelines. Indices are Uint32, so
+// the resolution is not limited to 255 x 255 cells like the CPU surfaces.
+pub struct GpuSurface {
+    pub kind: SurfaceKind,
+    pub domain: [f32; 4], // [umin, umax, vmin, vmax], or [xmin, xmax, zmin, zmax]
+    pub scale: f32,
+    pub aspect_ratio: f32,
+    pub colormap_name: String,
+    pub wireframe_color: String,
+    pub colormap_direction: u32, // 0: x-direction, 1: y-direction, 2: z-direction
+    pub colormap_range: Option<[f32; 2]>, // overrides the data range used by the colormap
+    pub t: f32,                  // animation time parameter
+    pub vertex_buffers: [wgpu::Buffer; 2], // surface and wireframe colors
+    pub index_buffers: [wgpu::Buffer; 2], // triangle list and line list
+    pub indices_lens: [u32; 2],
+    resolution: [u32; 2],
+    flip_normals: bool,
+    pipelines: [wgpu::ComputePipeline; 3],
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    uniform_buffer: wgpu::Buffer,
+    bounds_buffer: wgpu::Buffer,
+}
+
+impl GpuSurface {
+    // surface_wgsl defines surface_fn, see GPU_SURFACE_WGSL; resolution is the number
+    // of grid cells along u and v
+    pub fn new(
+        device: &wgpu::Device,
+        kind: SurfaceKind,
+        surface_wgsl: &str,
+        domain: [f32; 4],
+        resolution: [u32; 2],
+    ) -> Self {
+        let resolution = resolution.map(|n| n.max(1));
+        let source = format!(
+            "{}\n{}\n{}",
+            surface_wgsl, COLOR_SPACE_WGSL, GPU_SURFACE_WGSL
+        );
+        let source = noise::with_noise(&source);
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("GPU Surface Shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("GPU Surface Uniform Buffer"),
+            size: std::mem::size_of::<SurfaceUniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bounds_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("GPU Surface Bounds Buffer"),
+            size: 6 * 4,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let (vertex_buffers, index_buffers, indices_lens) = grid_buffers(device, resolution, false);
+        let (bind_group_layout, bind_group) = create_bind_group(
+            device,
+            None,
+            &uniform_buffer,
+            &bounds_buffer,
+            &vertex_buffers,
+        );
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("GPU Surface Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipelines = ["cs_reset", "cs_bounds", "cs_main"].map(|entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                module: &module,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        });
+
+        Self {
+            kind,
+            domain,
+            scale: 1.0,
+            aspect_ratio: 1.0,
+            colormap_name: "jet".to_string(),
+            wireframe_color: "white".to_string(),
+            colormap_direction: 1,
+            colormap_range: None,
+            t: 0.0,
+            vertex_buffers,
+            index_buffers,
+            indices_lens,
+            resolution,
+            flip_normals: false,
+            pipelines,
+            bind_group_layout,
+            bind_group,
+            uniform_buffer,
+            bounds_buffer,
+        }
+    }
+
+    // the built-in surface type of ss with its settings; a custom_fn cannot run on the
+    // GPU, pass WGSL doing the same to new() instead
+    pub fn from_simple(device: &wgpu::Device, ss: &sd::ISimpleSurface) -> Self {
+        let (_, domain, _) = sd::simple_surface_fn(ss.surface_type);
+        let resolution = [ss.x_resolution as u32, ss.z_resolution as u32];
+        let wgsl = simple_surface_wgsl(ss.surface_type);
+        let mut surface = Self::new(device, SurfaceKind::Simple, wgsl, domain, resolution);
+        surface.apply_simple(ss);
+        surface
+    }
+
+    // the built-in surface type of ps with its settings; None if it has no WGSL port,
+    // as for a custom_fn
+    pub fn from_parametric(device: &wgpu::Device, ps: &sd::IParametricSurface) -> Option<Self> {
+        if ps.custom_fn.is_some() {
+            return None;
+        }
+        let wgsl = parametric_surface_wgsl(ps.surface_type)?;
+        let (_, domain) = ps.surface_fn();
+        let resolution = [ps.u_resolution as u32, ps.v_resolution as u32];
+        let mut surface = Self::new(device, SurfaceKind::Parametric, wgsl, domain, resolution);
+        surface.scale = ps.scale;
+        surface.aspect_ratio = ps.aspect_ratio;
+        surface.colormap_name = ps.colormap_name.clone();
+        surface.wireframe_color = ps.wireframe_color.clone();
+        surface.colormap_direction = ps.colormap_direction;
+        surface.colormap_range = ps.colormap_range;
+        surface.set_flip_normals(device, ps.flips_normals());
+        Some(surface)
+    }
+
+    // copies the settings that need no new buffers, e.g. every frame while animating;
+    // the domain and aspect ratio follow the surface type as in ISimpleSurface::new.
+    // A color_field is not evaluated on the GPU, the colors always show the height.
+    pub fn apply_simple(&mut self, ss: &sd::ISimpleSurface) {
+        let (_, domain, aspect_ratio) = sd::simple_surface_fn(ss.surface_type);
+        self.domain = domain;
+        self.aspect_ratio = aspect_ratio;
+        self.scale = ss.scale;
+        self.colormap_name.clone_from(&ss.colormap_name);
+        self.wireframe_color.clone_from(&ss.wireframe_color);
+        self.colormap_direction = ss.colormap_direction;
+        self.colormap_range = ss.colormap_range;
+        self.t = ss.t;
+    }
+
+    pub fn resolution(&self) -> [u32; 2] {
+        self.resolution
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        (self.resolution[0] as usize + 1) * (self.resolution[1] as usize + 1)
+    }
+
+    pub fn index_format(&self) -> wgpu::IndexFormat {
+        wgpu::IndexFormat::Uint32
+    }
+
+    pub fn flips_normals(&self) -> bool {
+        self.flip_normals
+    }
+
+    // negates the normals and reverses the triangle winding, as
+    // ISurfaceOutput::flip_normals; replaces the index buffers when it changes
+    pub fn set_flip_normals(&mut self, device: &wgpu::Device, flip: bool) {
+        if flip == self.flip_normals {
+            return;
+        }
+        for buffer in &self.index_buffers {
+            buffer.destroy();
+        }
+        (self.index_buffers, self.indices_lens) = index_buffers(device, self.resolution, flip);
+        self.flip_normals = flip;
+    }
+
+    // replaces the vertex and index buffers; the shader is kept
+    pub fn set_resolution(&mut self, device: &wgpu::Device, resolution: [u32; 2]) {
+        let resolution = resolution.map(|n| n.max(1));
+        if resolution == self.resolution {
+            return;
+        }
+        for buffer in self.vertex_buffers.iter().chain(&self.index_buffers) {
+            buffer.destroy();
+        }
+        (self.vertex_buffers, self.index_buffers, self.indices_lens) =
+            grid_buffers(device, resolution, self.flip_normals);
+        self.bind_group = create_bind_group(
+            device,
+            Some(&self.bind_group_layout),
+            &self.uniform_buffer,
+            &self.bounds_buffer,
+            &self.vertex_buffers,
+        )
+        .1;
+        self.resolution = resolution;
+    }
+
+    fn uniforms(&self) -> SurfaceUniforms {
+        let rgba = |name: &str| colormap::colormap_data(name).map(|[r, g, b]| [r, g, b, 1.0]);
+        SurfaceUniforms {
+            domain: self.domain,
+            resolution: self.resolution,
+            kind: (self.kind == SurfaceKind::Parametric) as u32,
+            colormap_direction: self.colormap_direction.min(2),
+            scale: self.scale,
+            aspect_ratio: self.aspect_ratio,
+            t: self.t,
+            fixed_range: self.colormap_range.is_some() as u32,
+            colormap_range: self.colormap_range.unwrap_or([0.0, 1.0]),
+            normal_sign: if self.flip_normals { -1.0 } else { 1.0 },
+            _padding: 0.0,
+            colors: rgba(&self.colormap_name),
+            colors2: rgba(&self.wireframe_color),
+        }
+    }
+
+    // records the regeneration of the vertex buffers into encoder, ahead of the
+    // render pass that draws them
+    pub fn compute(&self, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder) {
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&self.uniforms()),
+        );
+        let groups = [self.resolution[0] / 8 + 1, self.resolution[1] / 8 + 1];
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("GPU Surface Pass"),
+            timestamp_writes: None,
+        });
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_pipeline(&self.pipelines[0]);
+        pass.dispatch_workgroups(1, 1, 1);
+        for pipeline in &self.pipelines[1..] {
+            pass.set_pipeline(pipeline);
+            pass.dispatch_workgroups(groups[0], groups[1], 1);
+        }
+    }
+
+    // regenerates the surface and copies it back, e.g. for export or tests. The
+    // indices are only filled while they fit into u16.
+    pub fn read_back(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> ISurfaceOutput {
+        let size = self.vertex_buffers[0].size();
+        let staging = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("GPU Surface Staging Buffer"),
+            size: 2 * size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("GPU Surface Read Back Encoder"),
+        });
+        self.compute(queue, &mut encoder);
+        encoder.copy_buffer_to_buffer(&self.vertex_buffers[0], 0, &staging, 0, size);
+        encoder.copy_buffer_to_buffer(&self.vertex_buffers[1], 0, &staging, size, size);
+        queue.submit(std::iter::once(encoder.finish()));
+
+        staging.slice(..).map_async(wgpu::MapMode::Read, |_| {});
+        let _ = device.poll(wgpu::PollType::Wait);
+        let floats: Vec<f32> = bytemuck::cast_slice(&staging.slice(..).get_mapped_range()).to_vec();
+        let (surface, wireframe) = floats.split_at(floats.len() / 2);
+
+        let [nu, nv] = self.resolution;
+        let mut data = ISurfaceOutput::default();
+        for (k, (v, v2)) in surface
+            .chunks_exact(9)
+            .zip(wireframe.chunks_exact(9))
+            .enumerate()
+        {
+            data.positions.push([v[0], v[1], v[2]]);
+            data.normals.push([v[3], v[4], v[5]]);
+            // back to the display colors ISurfaceOutput holds
+            data.colors.push(color::to_srgb([v[6], v[7], v[8]]));
+            data.colors2.push(color::to_srgb([v2[6], v2[7], v2[8]]));
+            let (i, j) = (k as u32 / (nv + 1), k as u32 % (nv + 1));
+            data.uvs.push([i as f32 / nu as f32, j as f32 / nv as f32]);
+        }
+        if self.vertex_count() <= 1 << 16 {
+            (data.indices, data.indices2) = sd::grid_indices(nu as u16, nv as u16);
+            if self.flip_normals {
+                flip_winding(&mut data.indices);
+            }
+        }
+        data
+    }
+}
+
+fn grid_buffers(
+    device: &wgpu::Device,
+    resolution: [u32; 2],
+    flip: bool,
+) -> ([wgpu::Buffer; 2], [wgpu::Buffer; 2], [u32; 2]) {
+    let vertex_count = (resolution[0] as u64 + 1) * (resolution[1] as u64 + 1);
+    let vertex_buffers =
+        ["GPU Surface Vertex Buffer", "GPU Surface Vertex Buffer 2"].map(|label| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size: 9 * 4 * vertex_count,
+                usage: wgpu::BufferUsages::VERTEX
+                    | wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            })
+        });
+    let (index_buffers, indices_lens) = index_buffers(device, resolution, flip);
+    (vertex_buffers, index_buffers, indices_lens)
+}
+
+fn index_buffers(
+    device: &wgpu::Device,
+    resolution: [u32; 2],
+    flip: bool,
+) -> ([wgpu::Buffer; 2], [u32; 2]) {
+    let (mut indices, indices2) = sd::grid_indices32(resolution[0], resolution[1]);
+    if flip {
+        flip_winding(&mut indices);
+    }
+    let indices_lens = [indices.len() as u32, indices2.len() as u32];
+    let index_buffers = [indices, indices2].map(|indices| {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("GPU Surface Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        })
+    });
+    (index_buffers, indices_lens)
+}
+
+fn flip_winding<T>(indices: &mut [T]) {
+    for tri in indices.chunks_exact_mut(3) {
+        tri.swap(1, 2);
+    }
+}
+
+// binds the uniforms, bounds and both vertex buffers; reuses layout if given
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: Option<&wgpu::BindGroupLayout>,
+    uniform_buffer: &wgpu::Buffer,
+    bounds_buffer: &wgpu::Buffer,
+    vertex_buffers: &[wgpu::Buffer; 2],
+) -> (wgpu::BindGroupLayout, wgpu::BindGroup) {
+    let storage = wgpu::BufferBindingType::Storage { read_only: false };
+    let layout = layout.cloned().unwrap_or_else(|| {
+        super::wgpu_simplified::create_bind_group_layout_storage(
+            device,
+            vec![wgpu::ShaderStages::COMPUTE; 4],
+            vec![wgpu::BufferBindingType::Uniform, storage, storage, storage],
+        )
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("GPU Surface Bind Group"),
+        layout: &layout,
+        entries: &[
+            uniform_buffer.as_entire_binding(),
+            bounds_buffer.as_entire_binding(),
+            vertex_buffers[0].as_entire_binding(),
+            vertex_buffers[1].as_entire_binding(),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(i, resource)| wgpu::BindGroupEntry {
+            binding: i as u32,
+            resource,
+        })
+        .collect::<Vec<_>>(),
+    });
+    (layout, bind_group)
+}
+// endregion: gpu surface
+
+// region: vertex displacement
+// The built-in simple surfaces animated in the vertex shader instead: no vertex buffers
+// at all, the vertex index picks the grid point (i, j) = (k / (nz + 1), k % (nz + 1))
+// and the shader evaluates the surface there at the t of the uniforms, so a frame
+// writes one uniform buffer whatever the resolution. vs_main colors the shape with the
+// surface colormap and vs_wireframe with the wireframe one; both write the outputs of
+// the chapter vertex shaders with the matrices at group 0, so they pair with the
+// chapter fragment shaders, and read the surface at group DISPLACEMENT_GROUP:
+//
+//     render_pass.set_bind_group(DISPLACEMENT_GROUP, &displaced.bind_group, &[]);
+//     render_pass.set_index_buffer(displaced.index_buffers[0].slice(..), Uint32);
+//
+// A vertex cannot see the others, so heights are not normalized by the range of the
+// frame as ISimpleSurface does but by one fitted over a period of t when the surface
+// type changes (fit_height_range); the surface then keeps its scale as it moves.
+// Unlike GpuSurface it needs no compute shaders and runs on WebGL2 as well.
+pub const DISPLACEMENT_GROUP: u32 = 2;
+
+pub const DISPLACEMENT_VERT_WGSL: &str = r#"
+struct Uniforms {
+    vpMat : mat4x4f,
+    modelMat : mat4x4f,
+    normalMat : mat4x4f,
+};
+@group(0) @binding(0) var<uniform> uniforms : Uniforms;
+
+struct DisplacementParams {
+    domain: vec4f, // xmin, xmax, zmin, zmax
+    resolution: vec2u,
+    height_range: vec2f,
+    colormap_range: vec2f,
+    surface_type: u32,
+    colormap_direction: u32,
+    scale: f32,
+    aspect_ratio: f32,
+    t: f32,
+    _padding: f32,
+    colors: array<vec4f, 11>,
+    colors2: array<vec4f, 11>,
+}
+@group(2) @binding(0) var<uniform> params: DisplacementParams;
+
+struct Output {
+    @builtin(position) position : vec4f,
+    @location(0) vPosition : vec4f,
+    @location(1) vNormal : vec4f,
+    @location(2) vColor: vec4f,
+};
+
+fn surface_fn(x: f32, z: f32, t: f32) -> vec3f {
+    switch params.surface_type {
+        case 0u: { return surface_fn0(x, z, t); }
+        case 1u: { return surface_fn1(x, z, t); }
+        default: { return surface_fn2(x, z, t); }
+    }
+}
+
+// as ISimpleSurface::normalize_data, with the fitted height range
+fn displaced(x: f32, z: f32) -> vec3f {
+    let d = params.domain;
+    let h = params.height_range;
+    let pt = surface_fn(x, z, params.t);
+    let n = vec3f(
+        -1.0 + 2.0 * (pt.x - d.x) / (d.y - d.x),
+        -1.0 + 2.0 * (pt.y - h.x) / (h.y - h.x),
+        -1.0 + 2.0 * (pt.z - d.z) / (d.w - d.z),
+    );
+    return n * params.scale * vec3f(1.0, params.aspect_ratio, 1.0);
+}
+
+fn color_lerp(which: u32, value: f32) -> vec3f {
+    let range = params.colormap_range;
+    let tn = (clamp(value, range.x, range.y) - range.x) / (range.y - range.x);
+    let index = min(u32(floor(10.0 * tn)), 9u);
+    let s = 10.0 * tn - f32(index);
+    if which == 0u {
+        return mix(params.colors[index].rgb, params.colors[index + 1u].rgb, s);
+    }
+    return mix(params.colors2[index].rgb, params.colors2[index + 1u].rgb, s);
+}
+
+fn displace(k: u32, which: u32) -> Output {
+    let d = params.domain;
+    let step = vec2f(d.y - d.x, d.w - d.z) / vec2f(params.resolution);
+    let rows = params.resolution.y + 1u;
+    let p = d.xz + step * vec2f(f32(k / rows), f32(k % rows));
+    let eps = 0.01 * step;
+    let position = displaced(p.x, p.y);
+    let nx = displaced(p.x + eps.x, p.y) - displaced(p.x - eps.x, p.y);
+    let nz = displaced(p.x, p.y + eps.y) - displaced(p.x, p.y - eps.y);
+    let normal = normalize(cross(nx, nz));
+    let color = srgb_to_linear(color_lerp(which, position[params.colormap_direction]));
+
+    var output: Output;
+    let mPosition = uniforms.modelMat * vec4f(position, 1.0);
+    output.vPosition = mPosition;
+    output.vNormal = uniforms.normalMat * vec4f(normal, 1.0);
+    output.position = uniforms.vpMat * mPosition;
+    output.vColor = vec4f(color, 1.0);
+    return output;
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) k: u32) -> Output {
+    return displace(k, 0u);
+}
+
+@vertex
+fn vs_wireframe(@builtin(vertex_index) k: u32) -> Output {
+    return displace(k, 1u);
+}
+"#;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct DisplacementUniforms {
+    domain: [f32; 4],
+    resolution: [u32; 2],
+    height_range: [f32; 2],
+    colormap_range: [f32; 2],
+    surface_type: u32,
+    colormap_direction: u32,
+    scale: f32,
+    aspect_ratio: f32,
+    t: f32,
+    _padding: f32,
+    colors: [[f32; 4]; 11],
+    colors2: [[f32; 4]; 11],
+}
+
+// A built-in simple surface displaced in the vertex shader, see DISPLACEMENT_VERT_WGSL.
+// The public fields mirror those of ISimpleSurface and take effect at the next write();
+// the index buffers are Uint32 like those of GpuSurface.
+pub struct DisplacedSurface {
+    pub surface_type: u32,
+    pub domain: [f32; 4], // [xmin, xmax, zmin, zmax]
+    pub scale: f32,
+    pub aspect_ratio: f32,
+    pub colormap_name: String,
+    pub wireframe_color: String,
+    pub colormap_direction: u32, // 0: x-direction, 1: y-direction, 2: z-direction
+    pub colormap_range: Option<[f32; 2]>, // overrides the range of the normalized surface
+    pub t: f32,                  // animation time parameter
+    pub height_range: [f32; 2],  // heights mapped onto [-1, 1] before the scale
+    pub index_buffers: [wgpu::Buffer; 2], // triangle list and line list
+    pub indices_lens: [u32; 2],
+    pub bind_group: wgpu::BindGroup,
+    resolution: [u32; 2],
+    uniform_buffer: wgpu::Buffer,
+}
+
+impl DisplacedSurface {
+    // the built-in surface type of ss with its settings; a custom_fn or color_field is
+    // not evaluated, the surface shows the height of surface_type
+    pub fn from_simple(device: &wgpu::Device, ss: &sd::ISimpleSurface) -> Self {
+        let resolution = [ss.x_resolution as u32, ss.z_resolution as u32].map(|n| n.max(1));
+        let (_, domain, aspect_ratio) = sd::simple_surface_fn(ss.surface_type);
+        let (index_buffers, indices_lens) = index_buffers(device, resolution, false);
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Displaced Surface Uniform Buffer"),
+            size: std::mem::size_of::<DisplacementUniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Displaced Surface Bind Group"),
+            layout: &Self::bind_group_layout(device),
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+        let mut surface = Self {
+            surface_type: ss.surface_type,
+            domain,
+            scale: 1.0,
+            aspect_ratio,
+            colormap_name: "jet".to_string(),
+            wireframe_color: "white".to_string(),
+            colormap_direction: 1,
+            colormap_range: None,
+            t: 0.0,
+            height_range: Self::fit_height_range(ss.surface_type, resolution),
+            index_buffers,
+            indices_lens,
+            bind_group,
+            resolution,
+            uniform_buffer,
+        };
+        surface.apply_simple(device, ss);
+        surface
+    }
+
+    // layout of the bind group at DISPLACEMENT_GROUP, for the pipeline layouts of
+    // vertex_shader(); layouts with the same entries are interchangeable
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Displaced Surface Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        })
+    }
+
+    // vs_main and vs_wireframe with the surface functions of the built-in types
+    pub fn vertex_shader(device: &wgpu::Device) -> wgpu::ShaderModule {
+        let surface_fns = (0..sd::SIMPLE_SURFACE_COUNT)
+            .map(|k| {
+                simple_surface_wgsl(k).replace("fn surface_fn", &format!("fn surface_fn{}", k))
+            })
+            .collect::<String>();
+        let source = format!(
+            "{}\n{}\n{}",
+            surface_fns, COLOR_SPACE_WGSL, DISPLACEMENT_VERT_WGSL
+        );
+        device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Displaced Surface Shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        })
+    }
+
+    // copies the settings of ss, e.g. every frame while animating; replaces the index
+    // buffers when the resolution changed and refits the height range when the
+    // surface type did
+    pub fn apply_simple(&mut self, device: &wgpu::Device, ss: &sd::ISimpleSurface) {
+        let resolution = [ss.x_resolution as u32, ss.z_resolution as u32].map(|n| n.max(1));
+        if resolution != self.resolution {
+            for buffer in &self.index_buffers {
+                buffer.destroy();
+            }
+            (self.index_buffers, self.indices_lens) = index_buffers(device, resolution, false);
+            self.resolution = resolution;
+        }
+        if ss.surface_type != self.surface_type {
+            self.surface_type = ss.surface_type;
+            self.height_range = Self::fit_height_range(ss.surface_type, resolution);
+        }
+        (_, self.domain, self.aspect_ratio) = sd::simple_surface_fn(ss.surface_type);
+        self.scale = ss.scale;
+        self.colormap_name.clone_from(&ss.colormap_name);
+        self.wireframe_color.clone_from(&ss.wireframe_color);
+        self.colormap_direction = ss.colormap_direction;
+        self.colormap_range = ss.colormap_range;
+        self.t = ss.t;
+    }
+
+    // the heights of the surface type over t in [0, 4pi), a period of every built-in
+    // type, sampled on a grid of at most 64 x 64 cells
+    pub fn fit_height_range(surface_type: u32, resolution: [u32; 2]) -> [f32; 2] {
+        const STEPS: u32 = 96;
+        let (f, [xmin, xmax, zmin, zmax], _) = sd::simple_surface_fn(surface_type);
+        let [nx, nz] = resolution.map(|n| n.clamp(1, 64));
+        let mut range = [f32::MAX, f32::MIN];
+        for step in 0..STEPS {
+            let t = 4.0 * std::f32::consts::PI * step as f32 / STEPS as f32;
+            for i in 0..=nx {
+                let x = xmin + (xmax - xmin) * i as f32 / nx as f32;
+                for j in 0..=nz {
+                    let z = zmin + (zmax - zmin) * j as f32 / nz as f32;
+                    let y = f(x, z, t)[1];
+                    range = [range[0].min(y), range[1].max(y)];
+                }
+            }
+        }
+        range
+    }
+
+    pub fn resolution(&self) -> [u32; 2] {
+        self.resolution
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        (self.resolution[0] as usize + 1) * (self.resolution[1] as usize + 1)
+    }
+
+    pub fn index_format(&self) -> wgpu::IndexFormat {
+        wgpu::IndexFormat::Uint32
+    }
+
+    fn uniforms(&self) -> DisplacementUniforms {
+        let rgba = |name: &str| colormap::colormap_data(name).map(|[r, g, b]| [r, g, b, 1.0]);
+        let direction = self.colormap_direction.min(2);
+        let r = match direction {
+            1 => self.scale * self.aspect_ratio,
+            _ => self.scale,
+        };
+        DisplacementUniforms {
+            domain: self.domain,
+            resolution: self.resolution,
+            height_range: self.height_range,
+            colormap_range: self.colormap_range.unwrap_or([-r, r]),
+            surface_type: self.surface_type,
+            colormap_direction: direction,
+            scale: self.scale,
+            aspect_ratio: self.aspect_ratio,
+            t: self.t,
+            _padding: 0.0,
+            colors: rgba(&self.colormap_name),
+            colors2: rgba(&self.wireframe_color),
+        }
+    }
+
+    // the only upload of a frame
+    pub fn write(&self, queue: &wgpu::Queue) {
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&self.uniforms()),
+        );
+    }
+}
+// endregion: vertex displacement
@@ -0,0 +1,126 @@
+use std::io::{BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+
+use super::surface_data::{StreamingGrid, MAX_STREAMING_SIDE};
+
+// Wire format of a message, all integers little-endian:
+//   kind: u8      b'R' = one grid row, b'F' = full frame (row-major)
+//   count: u32    number of values that follow, at most MAX_VALUES
+//   values: count x f32
+//
+// Producers connect over plain TCP. WebSocket is out of scope: a browser producer
+// needs a WebSocket-to-TCP bridge (e.g. websockify) in front of the receiver.
+
+// a full frame of the largest streaming grid
+pub const MAX_VALUES: u32 = MAX_STREAMING_SIDE as u32 * MAX_STREAMING_SIDE as u32;
+
+// messages waiting for the render loop; a producer that gets further ahead is
+// blocked by TCP flow control until the next apply
+pub const QUEUE_LEN: usize = 64;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DataMessage {
+    Row(Vec<f32>),
+    Frame(Vec<f32>),
+}
+
+// region: protocol
+pub fn read_message(reader: &mut impl Read) -> std::io::Result<DataMessage> {
+    let mut head = [0u8; 5];
+    reader.read_exact(&mut head)?;
+    let kind = head[0];
+    if kind != b'R' && kind != b'F' {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("unknown message kind {}", kind),
+        ));
+    }
+    let count = u32::from_le_bytes([head[1], head[2], head[3], head[4]]);
+    if count > MAX_VALUES {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("message with {} values exceeds the limit", count),
+        ));
+    }
+
+    let mut bytes = vec![0u8; 4 * count as usize];
+    reader.read_exact(&mut bytes)?;
+    let values: Vec<f32> = bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect();
+
+    Ok(match kind {
+        b'R' => DataMessage::Row(values),
+        _ => DataMessage::Frame(values),
+    })
+}
+
+pub fn write_message(writer: &mut impl Write, message: &DataMessage) -> std::io::Result<()> {
+    let (kind, values) = match message {
+        DataMessage::Row(values) => (b'R', values),
+        DataMessage::Frame(values) => (b'F', values),
+    };
+    let mut bytes = Vec::with_capacity(5 + 4 * values.len());
+    bytes.push(kind);
+    bytes.extend_from_slice(&(values.len() as u32).to_le_bytes());
+    for v in values {
+        bytes.extend_from_slice(&v.to_le_bytes());
+    }
+    writer.write_all(&bytes)
+}
+// endregion: protocol
+
+// region: data receiver
+// Listens for producer connections and queues their messages until the render
+// loop applies them to a streaming surface.
+pub struct DataReceiver {
+    pub addr: SocketAddr,
+    rx: Receiver<DataMessage>,
+}
+
+impl DataReceiver {
+    pub fn bind(addr: &str) -> anyhow::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let (tx, rx) = mpsc::sync_channel(QUEUE_LEN);
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let tx = tx.clone();
+                std::thread::spawn(move || read_connection(stream, tx));
+            }
+        });
+
+        Ok(Self { addr, rx })
+    }
+
+    pub fn try_recv_all(&self) -> Vec<DataMessage> {
+        self.rx.try_iter().collect()
+    }
+
+    // applies all pending messages to a streaming or telemetry surface; returns true
+    // if it changed
+    pub fn apply(&self, surface: &mut impl StreamingGrid) -> bool {
+        let mut changed = false;
+        for message in self.rx.try_iter() {
+            match message {
+                DataMessage::Row(row) => surface.push_row(&row),
+                DataMessage::Frame(frame) => surface.set_frame(&frame),
+            }
+            changed = true;
+        }
+        changed
+    }
+}
+
+fn read_connection(stream: TcpStream, tx: SyncSender<DataMessage>) {
+    let mut reader = BufReader::new(stream);
+    while let Ok(message) = read_message(&mut reader) {
+        if tx.send(message).is_err() {
+            break;
+        }
+    }
+}
+// endregion: data receiver
@@ -0,0 +1,588 @@
+use cgmath::Matrix4;
+use std::ops::Range;
+
+// region: packed meshes
+// Several meshes in one vertex and one index buffer, e.g. a few different surface
+// types for an instanced grid of varied shapes. Each mesh keeps its own u16 indices;
+// its MeshRange holds where its indices start and the base vertex added to them, so
+// one bind of the two buffers serves all meshes:
+//
+//     let mut pack = IPackedMeshes::default();
+//     let torus = pack.push(&torus_vertices, &torus_indices);
+//     let knot = pack.push(&knot_vertices, &knot_indices);
+//     let batches = InstanceBatches::new(&mesh_of_instance, pack.len());
+//     // ... model matrices written in batches.order, then
+//     batches.draw(&mut render_pass, &pack.ranges);
+//
+// Drawing the instances of every mesh with one draw_indexed stands in for a multi-draw:
+// the draw count is the number of meshes, not the number of instances.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MeshRange {
+    pub base_vertex: i32,
+    pub first_index: u32,
+    pub index_count: u32,
+    pub vertex_count: u32,
+}
+
+impl MeshRange {
+    pub fn indices(&self) -> Range<u32> {
+        self.first_index..self.first_index + self.index_count
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct IPackedMeshes<V> {
+    pub vertices: Vec<V>,
+    pub indices: Vec<u16>,
+    pub ranges: Vec<MeshRange>,
+}
+
+impl<V> Default for IPackedMeshes<V> {
+    fn default() -> Self {
+        Self {
+            vertices: vec![],
+            indices: vec![],
+            ranges: vec![],
+        }
+    }
+}
+
+impl<V: Copy> IPackedMeshes<V> {
+    // appends a mesh and returns its index into ranges
+    pub fn push(&mut self, vertices: &[V], indices: &[u16]) -> usize {
+        self.ranges.push(MeshRange {
+            base_vertex: self.vertices.len() as i32,
+            first_index: self.indices.len() as u32,
+            index_count: indices.len() as u32,
+            vertex_count: vertices.len() as u32,
+        });
+        self.vertices.extend_from_slice(vertices);
+        self.indices.extend_from_slice(indices);
+        self.ranges.len() - 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+        self.ranges.clear();
+    }
+}
+// endregion: packed meshes
+
+// region: instance batches
+// Instances grouped by mesh, so the instances of a mesh are one contiguous instance
+// range. order maps each instance slot to the instance it holds: per-instance data
+// (model matrices in a storage buffer read with instance_index) is written in that
+// order. The grouping is stable, instances of a mesh keep their relative order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InstanceBatches {
+    pub order: Vec<u32>,
+    pub batches: Vec<(usize, Range<u32>)>, // mesh, instance slots
+}
+
+impl InstanceBatches {
+    // mesh_of_instance[i] is the mesh instance i shows; meshes without instances get
+    // no batch
+    pub fn new(mesh_of_instance: &[usize], mesh_count: usize) -> Self {
+        let mut counts = vec![0u32; mesh_count];
+        for &mesh in mesh_of_instance {
+            counts[mesh] += 1;
+        }
+        let mut starts = vec![0u32; mesh_count];
+        let mut batches = vec![];
+        let mut start = 0;
+        for (mesh, &count) in counts.iter().enumerate() {
+            starts[mesh] = start;
+            if count > 0 {
+                batches.push((mesh, start..start + count));
+            }
+            start += count;
+        }
+        let mut order = vec![0; mesh_of_instance.len()];
+        for (instance, &mesh) in mesh_of_instance.iter().enumerate() {
+            order[starts[mesh] as usize] = instance as u32;
+            starts[mesh] += 1;
+        }
+        Self { order, batches }
+    }
+
+    // every instance showing the same single mesh
+    pub fn single(instance_count: u32) -> Self {
+        Self {
+            order: (0..instance_count).collect(),
+            batches: vec![(0, 0..instance_count)],
+        }
+    }
+
+    pub fn instance_count(&self) -> u32 {
+        self.order.len() as u32
+    }
+
+    // only the listed instances, e.g. the visible ones, grouped by mesh_of(instance);
+    // order still holds the original instance indices
+    pub fn subset(instances: &[u32], mesh_of: impl Fn(u32) -> usize, mesh_count: usize) -> Self {
+        let meshes: Vec<usize> = instances.iter().map(|&i| mesh_of(i)).collect();
+        let mut batches = Self::new(&meshes, mesh_count);
+        for slot in batches.order.iter_mut() {
+            *slot = instances[*slot as usize];
+        }
+        batches
+    }
+
+    // per-instance data rearranged into slot order
+    pub fn reorder<T: Copy>(&self, data: &[T]) -> Vec<T> {
+        self.order.iter().map(|&i| data[i as usize]).collect()
+    }
+
+    // one draw_indexed per batch; the pipeline, bind groups and the packed vertex and
+    // index buffers must be set
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass, ranges: &[MeshRange]) {
+        for (mesh, instances) in self.batches.iter() {
+            let range = ranges[*mesh];
+            render_pass.draw_indexed(range.indices(), range.base_vertex, instances.clone());
+        }
+    }
+}
+// endregion: instance batches
+
+// region: level of detail
+// Coarser meshes of one surface for the instances far from the camera. Level 0 is the
+// full resolution; each level starts at its distance in distances and the last one
+// goes on to infinity. The levels are packed as meshes of an IPackedMeshes and the
+// instances bucketed by level, one draw_indexed per level in use:
+//
+//     let lod = LodLevels::halving(30, 4, 10.0);
+//     for resolution in lod.resolutions.iter() {
+//         // ... push the surface generated at this resolution into pack
+//     }
+//     let batches = lod.batches(camera_position, &instance_positions);
+//     // ... model matrices written in batches.order, then
+//     batches.draw(&mut render_pass, &pack.ranges);
+#[derive(Clone, Debug, PartialEq)]
+pub struct LodLevels {
+    pub resolutions: Vec<u16>,
+    pub distances: Vec<f32>, // start of levels 1.., one fewer than resolutions
+}
+
+impl LodLevels {
+    // levels of half the resolution of the previous one, down to 2, each starting at
+    // twice the distance of the previous one
+    pub fn halving(resolution: u16, levels: usize, near: f32) -> Self {
+        let resolutions: Vec<u16> = (0..levels.max(1))
+            .map(|level| (resolution >> level).max(2))
+            .collect();
+        let distances = (0..resolutions.len() - 1)
+            .map(|level| near * (1 << level) as f32)
+            .collect();
+        Self {
+            resolutions,
+            distances,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.resolutions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.resolutions.is_empty()
+    }
+
+    // level of an instance at this distance from the camera
+    pub fn level(&self, distance: f32) -> usize {
+        let level = self.distances.iter().filter(|&&d| distance >= d).count();
+        level.min(self.len().saturating_sub(1))
+    }
+
+    pub fn level_at(&self, camera: [f32; 3], position: [f32; 3]) -> usize {
+        let d = [0, 1, 2].map(|k| position[k] - camera[k]);
+        self.level((d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt())
+    }
+
+    // instances bucketed by the level of their distance from camera
+    pub fn batches(&self, camera: [f32; 3], positions: &[[f32; 3]]) -> InstanceBatches {
+        let levels: Vec<usize> = positions
+            .iter()
+            .map(|&p| self.level_at(camera, p))
+            .collect();
+        InstanceBatches::new(&levels, self.len())
+    }
+
+    // batches of the listed instances only, e.g. those Frustum::visible kept
+    pub fn visible_batches(
+        &self,
+        camera: [f32; 3],
+        positions: &[[f32; 3]],
+        visible: &[u32],
+    ) -> InstanceBatches {
+        InstanceBatches::subset(
+            visible,
+            |i| self.level_at(camera, positions[i as usize]),
+            self.len(),
+        )
+    }
+}
+// endregion: level of detail
+
+// region: frustum culling
+// The six clip planes of a view-projection matrix, to leave out of the instance
+// buffer the instances the camera cannot see. Each instance is tested as a bounding
+// sphere around its position; the visible ones are written compacted, in the order
+// of their batches, and drawn with the visible count:
+//
+//     let visible = Frustum::from_matrix(&vp_mat).visible(&positions, radius);
+//     let batches = lod.visible_batches(camera_position, &positions, &visible);
+//     queue.write_buffer(&model_buffer, 0, cast_slice(&batches.reorder(&model_mats)));
+//     batches.draw(&mut render_pass, &pack.ranges);
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    pub planes: [[f32; 4]; 6], // [a, b, c, d], a x + b y + c z + d >= 0 inside, unit normal
+}
+
+impl Frustum {
+    // planes of a matrix with the 0 to 1 clip depth of wgpu, as made by
+    // create_projection_mat and create_vp_mat
+    pub fn from_matrix(view_project_mat: &Matrix4<f32>) -> Self {
+        let m = view_project_mat;
+        let row = |r: usize| [m.x[r], m.y[r], m.z[r], m.w[r]];
+        let (r0, r1, r2, r3) = (row(0), row(1), row(2), row(3));
+        let add = |a: [f32; 4], b: [f32; 4], sign: f32| [0, 1, 2, 3].map(|k| a[k] + sign * b[k]);
+        let planes = [
+            add(r3, r0, 1.0),
+            add(r3, r0, -1.0),
+            add(r3, r1, 1.0),
+            add(r3, r1, -1.0),
+            r2,
+            add(r3, r2, -1.0),
+        ]
+        .map(|p| {
+            let length = (p[0] * p[0] + p[1] * p[1] + p[2] * p[2]).sqrt();
+            if length > 0.0 {
+                p.map(|c| c / length)
+            } else {
+                p
+            }
+        });
+        Self { planes }
+    }
+
+    // false only when the sphere lies entirely outside one of the planes
+    pub fn intersects_sphere(&self, center: [f32; 3], radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|p| p[0] * center[0] + p[1] * center[1] + p[2] * center[2] + p[3] >= -radius)
+    }
+
+    // indices of the instances whose sphere around their position is in view
+    pub fn visible(&self, positions: &[[f32; 3]], radius: f32) -> Vec<u32> {
+        (0..positions.len() as u32)
+            .filter(|&i| self.intersects_sphere(positions[i as usize], radius))
+            .collect()
+    }
+}
+// endregion: frustum culling
+
+// region: mesh pool
+// First-fit allocator of element ranges in a buffer of capacity elements; freed ranges
+// are merged with free neighbors, so meshes that come and go do not fragment the
+// buffer more than necessary.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RangeAllocator {
+    capacity: u32,
+    free: Vec<Range<u32>>, // sorted, never adjacent
+}
+
+impl RangeAllocator {
+    pub fn new(capacity: u32) -> Self {
+        let mut ranges = Self {
+            capacity: 0,
+            free: vec![],
+        };
+        ranges.grow(capacity);
+        ranges
+    }
+
+    pub fn allocate(&mut self, len: u32) -> Option<Range<u32>> {
+        if len == 0 {
+            return Some(0..0);
+        }
+        let i = self.free.iter().position(|r| r.end - r.start >= len)?;
+        let start = self.free[i].start;
+        self.free[i].start += len;
+        if self.free[i].is_empty() {
+            self.free.remove(i);
+        }
+        Some(start..start + len)
+    }
+
+    pub fn free(&mut self, range: Range<u32>) {
+        if range.is_empty() {
+            return;
+        }
+        let i = self.free.partition_point(|r| r.start < range.start);
+        self.free.insert(i, range);
+        if i + 1 < self.free.len() && self.free[i].end == self.free[i + 1].start {
+            self.free[i].end = self.free.remove(i + 1).end;
+        }
+        if i > 0 && self.free[i - 1].end == self.free[i].start {
+            self.free[i - 1].end = self.free.remove(i).end;
+        }
+    }
+
+    // adds capacity at the end, e.g. after the buffer was reallocated larger
+    pub fn grow(&mut self, capacity: u32) {
+        if capacity > self.capacity {
+            self.free(self.capacity..capacity);
+            self.capacity = capacity;
+        }
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    pub fn free_len(&self) -> u32 {
+        self.free.iter().map(|r| r.end - r.start).sum()
+    }
+}
+
+// a removed mesh's handle stays invalid when its slot is reused
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MeshHandle {
+    slot: usize,
+    generation: u32,
+}
+
+// Many small meshes that come and go, e.g. annotation markers and glyphs, in one large
+// vertex and one index buffer instead of a pair of tiny buffers each. Meshes keep their
+// local u16 indices and are drawn either with a base vertex (bind() once, then
+// draw_indexed with range(), which also works with InstanceBatches::draw) or with the
+// vertex buffer bound at the mesh (vertex_slice()). Full buffers are reallocated twice
+// as large, copying what they hold.
+pub struct MeshPool {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    vertex_stride: u64,
+    vertex_ranges: RangeAllocator,
+    index_ranges: RangeAllocator, // in pairs of indices, for 4-byte aligned writes
+    meshes: Vec<(u32, Option<MeshRange>)>, // generation and range per slot
+    reallocations: u32,
+}
+
+impl MeshPool {
+    // vertex_stride in bytes, a multiple of 4; capacities in vertices and indices
+    pub fn new(
+        device: &wgpu::Device,
+        vertex_stride: u64,
+        vertex_capacity: u32,
+        index_capacity: u32,
+    ) -> Self {
+        let index_pairs = index_capacity.div_ceil(2).max(1);
+        let vertex_capacity = vertex_capacity.max(1);
+        Self {
+            vertex_buffer: Self::create_buffer(
+                device,
+                wgpu::BufferUsages::VERTEX,
+                vertex_capacity as u64 * vertex_stride,
+            ),
+            index_buffer: Self::create_buffer(
+                device,
+                wgpu::BufferUsages::INDEX,
+                index_pairs as u64 * 4,
+            ),
+            vertex_stride,
+            vertex_ranges: RangeAllocator::new(vertex_capacity),
+            index_ranges: RangeAllocator::new(index_pairs),
+            meshes: vec![],
+            reallocations: 0,
+        }
+    }
+
+    fn create_buffer(device: &wgpu::Device, usage: wgpu::BufferUsages, size: u64) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Mesh Pool Buffer"),
+            size,
+            usage: usage | wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        })
+    }
+
+    // stores a mesh, growing the buffers if it does not fit
+    pub fn insert<V: bytemuck::Pod>(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        vertices: &[V],
+        indices: &[u16],
+    ) -> MeshHandle {
+        let vertices: &[u8] = bytemuck::cast_slice(vertices);
+        let vertex_count = (vertices.len() as u64 / self.vertex_stride) as u32;
+        let index_pairs = (indices.len() as u32).div_ceil(2);
+        let vertex_range = self
+            .vertex_ranges
+            .allocate(vertex_count)
+            .unwrap_or_else(|| {
+                self.grow(device, queue, vertex_count, 0);
+                self.vertex_ranges.allocate(vertex_count).unwrap()
+            });
+        let index_range = self.index_ranges.allocate(index_pairs).unwrap_or_else(|| {
+            self.grow(device, queue, 0, index_pairs);
+            self.index_ranges.allocate(index_pairs).unwrap()
+        });
+
+        let vertex_offset = vertex_range.start as u64 * self.vertex_stride;
+        queue.write_buffer(&self.vertex_buffer, vertex_offset, vertices);
+        let mut padded = indices.to_vec();
+        padded.resize(index_pairs as usize * 2, 0);
+        let index_offset = index_range.start as u64 * 4;
+        queue.write_buffer(
+            &self.index_buffer,
+            index_offset,
+            bytemuck::cast_slice(&padded),
+        );
+
+        let range = MeshRange {
+            base_vertex: vertex_range.start as i32,
+            first_index: index_range.start * 2,
+            index_count: indices.len() as u32,
+            vertex_count,
+        };
+        let slot = self.meshes.iter().position(|(_, range)| range.is_none());
+        let slot = slot.unwrap_or_else(|| {
+            self.meshes.push((0, None));
+            self.meshes.len() - 1
+        });
+        self.meshes[slot].1 = Some(range);
+        MeshHandle {
+            slot,
+            generation: self.meshes[slot].0,
+        }
+    }
+
+    // frees the mesh's space; the handle no longer refers to a mesh
+    pub fn remove(&mut self, handle: MeshHandle) {
+        let Some(range) = self.range(handle) else {
+            return;
+        };
+        self.meshes[handle.slot] = (handle.generation + 1, None);
+        let start = range.base_vertex as u32;
+        self.vertex_ranges.free(start..start + range.vertex_count);
+        let first_pair = range.first_index / 2;
+        self.index_ranges
+            .free(first_pair..first_pair + range.index_count.div_ceil(2));
+    }
+
+    // at least double the capacity that ran out, with room for the elements
+    fn grow(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, vertices: u32, pairs: u32) {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Mesh Pool Grow Encoder"),
+        });
+        let mut grow = |buffer: &mut wgpu::Buffer,
+                        ranges: &mut RangeAllocator,
+                        count: u32,
+                        element_size: u64,
+                        usage: wgpu::BufferUsages| {
+            let capacity = (ranges.capacity() * 2).max(ranges.capacity() + count);
+            let new_buffer = Self::create_buffer(device, usage, capacity as u64 * element_size);
+            encoder.copy_buffer_to_buffer(buffer, 0, &new_buffer, 0, buffer.size());
+            *buffer = new_buffer;
+            ranges.grow(capacity);
+        };
+        if vertices > 0 {
+            let usage = wgpu::BufferUsages::VERTEX;
+            let stride = self.vertex_stride;
+            grow(
+                &mut self.vertex_buffer,
+                &mut self.vertex_ranges,
+                vertices,
+                stride,
+                usage,
+            );
+        }
+        if pairs > 0 {
+            let usage = wgpu::BufferUsages::INDEX;
+            grow(
+                &mut self.index_buffer,
+                &mut self.index_ranges,
+                pairs,
+                4,
+                usage,
+            );
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+        self.reallocations += 1;
+    }
+
+    pub fn range(&self, handle: MeshHandle) -> Option<MeshRange> {
+        match self.meshes.get(handle.slot) {
+            Some(&(generation, range)) if generation == handle.generation => range,
+            _ => None,
+        }
+    }
+
+    // ranges of all meshes in the pool, indexed by handle; removed meshes draw nothing
+    pub fn ranges(&self) -> Vec<MeshRange> {
+        self.meshes
+            .iter()
+            .map(|(_, range)| range.unwrap_or_default())
+            .collect()
+    }
+
+    // the mesh's vertices, for set_vertex_buffer; draw it with base vertex 0
+    pub fn vertex_slice(&self, handle: MeshHandle) -> Option<wgpu::BufferSlice<'_>> {
+        let range = self.range(handle)?;
+        let start = range.base_vertex as u64 * self.vertex_stride;
+        let end = start + range.vertex_count.max(1) as u64 * self.vertex_stride;
+        Some(self.vertex_buffer.slice(start..end))
+    }
+
+    // binds the whole vertex and index buffers at slot 0
+    pub fn bind(&self, render_pass: &mut wgpu::RenderPass) {
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+    }
+
+    // draws the mesh with a base vertex; bind() first
+    pub fn draw(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        handle: MeshHandle,
+        instances: Range<u32>,
+    ) {
+        if let Some(range) = self.range(handle) {
+            render_pass.draw_indexed(range.indices(), range.base_vertex, instances);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.meshes
+            .iter()
+            .filter(|(_, range)| range.is_some())
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn vertex_buffer(&self) -> &wgpu::Buffer {
+        &self.vertex_buffer
+    }
+
+    pub fn index_buffer(&self) -> &wgpu::Buffer {
+        &self.index_buffer
+    }
+
+    pub fn reallocations(&self) -> u32 {
+        self.reallocations
+    }
+}
+// endregion: mesh pool
@@ -0,0 +1,85 @@
+// region: number formatting
+// How numeric read-outs are written, the same for colorbar ticks, probes and the
+// stats overlay: a fixed number of decimals, scientific notation for magnitudes at
+// or above scientific_above and for non-zero ones below scientific_below, a decimal
+// comma instead of the point where that is the convention, and an optional unit
+// after a space:
+//
+//     let format = NumberFormat { decimal_comma: true, ..Default::default() };
+//     format.format(1234.5678);            // "1234,568"
+//     format.unit("K").format(0.0002);     // "2,000e-4 K"
+//
+// Setting scientific_above or scientific_below to 0 turns that side of it off.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NumberFormat {
+    pub decimals: usize,
+    pub scientific_above: f32,
+    pub scientific_below: f32,
+    pub decimal_comma: bool,
+    pub unit: &'static str, // empty for none
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self {
+            decimals: 3,
+            scientific_above: 1e5,
+            scientific_below: 1e-3,
+            decimal_comma: false,
+            unit: "",
+        }
+    }
+}
+
+impl NumberFormat {
+    pub fn decimals(mut self, decimals: usize) -> Self {
+        self.decimals = decimals;
+        self
+    }
+
+    pub fn unit(mut self, unit: &'static str) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    pub fn is_scientific(&self, value: f32) -> bool {
+        let magnitude = value.abs();
+        (self.scientific_above > 0.0 && magnitude >= self.scientific_above)
+            || (magnitude > 0.0 && magnitude < self.scientific_below)
+    }
+
+    pub fn format(&self, value: f32) -> String {
+        let mut text = if !value.is_finite() {
+            value.to_string()
+        } else if self.is_scientific(value) {
+            format!("{:.*e}", self.decimals, value)
+        } else {
+            format!("{:.*}", self.decimals, value)
+        };
+        // small negative values rounded to zero read 0.00, not -0.00
+        if text.starts_with('-') && text[1..].chars().all(|c| c == '0' || c == '.') {
+            text.remove(0);
+        }
+        if self.decimal_comma {
+            text = text.replace('.', ",");
+        }
+        if !self.unit.is_empty() {
+            text.push(' ');
+            text.push_str(self.unit);
+        }
+        text
+    }
+
+    // count evenly spaced values from range[0] to range[1] with their labels, e.g.
+    // the ticks of a colorbar from its bottom to its top
+    pub fn ticks(&self, range: [f32; 2], count: usize) -> Vec<(f32, String)> {
+        let n = count.max(2) - 1;
+        (0..=n)
+            .map(|k| {
+                let value = range[0] + (range[1] - range[0]) * k as f32 / n as f32;
+                (value, self.format(value))
+            })
+            .collect()
+    }
+}
+// endregion: number formatting
@@ -1,5 +1,92 @@
-pub mod colormap;
-pub mod math_func;
-pub mod surface_data;
-pub mod vertex_data;
-pub mod wgpu_simplified;
+// The modules the stable api is built on; they change between releases and are
+// public with the low_level feature, which is on by default. Without it they are
+// private, and the parts the api does not use are dead code.
+macro_rules! low_level {
+    ($($(#[$attr:meta])* $name:ident;)*) => {$(
+        $(#[$attr])*
+        #[cfg(feature = "low_level")]
+        pub mod $name;
+        $(#[$attr])*
+        #[cfg(not(feature = "low_level"))]
+        #[allow(dead_code)]
+        mod $name;
+    )*};
+}
+
+// the modules the api does not use, built only with the low_level feature
+macro_rules! low_level_only {
+    ($($(#[$attr:meta])* $name:ident;)*) => {$(
+        $(#[$attr])*
+        #[cfg(feature = "low_level")]
+        pub mod $name;
+    )*};
+}
+
+pub mod api;
+pub mod error;
+
+low_level! {
+    capabilities;
+    capture;
+    color;
+    colormap;
+    derivatives;
+    export;
+    gizmo;
+    material;
+    math_func;
+    metrics;
+    picking;
+    surface_data;
+    wgpu_simplified;
+}
+
+low_level_only! {
+    animation;
+    appearance;
+    app;
+    benchmark;
+    cli;
+    depth;
+    draw_list;
+    gallery;
+    geo;
+    gpu_surface;
+    #[cfg(feature = "network")]
+    ingest;
+    instancing;
+    labels;
+    lighting;
+    mesh;
+    motion;
+    noise;
+    normal_map;
+    overlay;
+    params;
+    pde;
+    playlist;
+    #[cfg(feature = "image")]
+    recorder;
+    recovery;
+    plot;
+    #[cfg(feature = "image")]
+    report;
+    scene;
+    series;
+    shader_hooks;
+    shadow;
+    #[cfg(feature = "stream")]
+    stream;
+    theme;
+    tiles;
+    transition;
+    unfold;
+    vertex_data;
+    wireframe;
+}
+
+pub use api::{
+    Capabilities, Gpu, Mesh, NormalMode, ParametricSurface, ShadingMode, SimpleSurface,
+    Subsystem, SurfaceStyle,
+};
+pub use error::{Error, KeymapError, Result, SceneError};
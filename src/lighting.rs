@@ -0,0 +1,412 @@
+use cgmath::{InnerSpace, Vector3};
+use serde::{Deserialize, Serialize};
+
+use super::error::Result;
+use super::wgpu_simplified as ws;
+
+// region: lights
+// Directional, point and spot lights for the multi-light shading. Directions point the
+// way the light shines, as the lightDirection of the chapter light uniform. Point and
+// spot lights fade with the inverse square of the distance, windowed so they reach
+// zero at range; a range of 0 means no cutoff. Spot lights are full inside inner_angle
+// and dark outside outer_angle (half angles, radians):
+//
+//     let lights = LightSet::new()
+//         .with(Light::directional([-0.5, -0.5, -0.5]).intensity(0.3))
+//         .with(Light::point([2.0, 2.0, 0.0], 8.0).color([1.0, 0.3, 0.2]).intensity(6.0));
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LightKind {
+    #[default]
+    Directional,
+    Point,
+    Spot,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Light {
+    pub kind: LightKind,
+    pub position: [f32; 3],  // point and spot lights
+    pub direction: [f32; 3], // directional and spot lights
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub range: f32,
+    pub inner_angle: f32,
+    pub outer_angle: f32,
+}
+
+impl Default for Light {
+    // the light of the chapters
+    fn default() -> Self {
+        Self {
+            kind: LightKind::Directional,
+            position: [0.0; 3],
+            direction: [-0.5, -0.5, -0.5],
+            color: [1.0; 3],
+            intensity: 1.0,
+            range: 0.0,
+            inner_angle: 0.0,
+            outer_angle: 0.0,
+        }
+    }
+}
+
+// unit vector towards the light and the light arriving from it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LightSample {
+    pub to_light: [f32; 3],
+    pub radiance: [f32; 3],
+}
+
+impl Light {
+    pub fn directional(direction: [f32; 3]) -> Self {
+        Self {
+            direction,
+            ..Default::default()
+        }
+    }
+
+    pub fn point(position: [f32; 3], range: f32) -> Self {
+        Self {
+            kind: LightKind::Point,
+            position,
+            range,
+            ..Default::default()
+        }
+    }
+
+    // a cone of half angle angle, softened over the outer fifth
+    pub fn spot(position: [f32; 3], direction: [f32; 3], angle: f32, range: f32) -> Self {
+        Self {
+            kind: LightKind::Spot,
+            position,
+            direction,
+            range,
+            inner_angle: 0.8 * angle,
+            outer_angle: angle,
+            ..Default::default()
+        }
+    }
+
+    pub fn color(mut self, color: [f32; 3]) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    // the Light struct of LIGHTS_WGSL
+    pub fn gpu_data(&self) -> [[f32; 4]; 4] {
+        let kind = self.kind as u32 as f32;
+        let [px, py, pz] = self.position;
+        let [dx, dy, dz] = self.direction;
+        let [r, g, b] = self.color;
+        let outer = self.outer_angle.max(0.0);
+        let inner = self.inner_angle.clamp(0.0, outer);
+        [
+            [px, py, pz, kind],
+            [dx, dy, dz, self.range],
+            [r, g, b, self.intensity],
+            [inner.cos(), outer.cos(), 0.0, 0.0],
+        ]
+    }
+
+    // CPU mirror of light_sample in LIGHTS_WGSL
+    pub fn sample(&self, position: [f32; 3]) -> LightSample {
+        let radiance = Vector3::from(self.color) * self.intensity;
+        let direction = Vector3::from(self.direction).normalize();
+        if self.kind == LightKind::Directional {
+            return LightSample {
+                to_light: (-direction).into(),
+                radiance: radiance.into(),
+            };
+        }
+        let offset = Vector3::from(self.position) - Vector3::from(position);
+        let distance = offset.magnitude();
+        let to_light = offset / distance.max(1e-6);
+        let mut attenuation = 1.0 / (distance * distance).max(1e-4);
+        if self.range > 0.0 {
+            let window = (1.0 - (distance / self.range).powi(4)).clamp(0.0, 1.0);
+            attenuation *= window * window;
+        }
+        if self.kind == LightKind::Spot {
+            let [cos_inner, cos_outer, _, _] = self.gpu_data()[3];
+            attenuation *= smoothstep(cos_outer, cos_inner, (-to_light).dot(direction));
+        }
+        LightSample {
+            to_light: to_light.into(),
+            radiance: (radiance * attenuation).into(),
+        }
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+// endregion: lights
+
+// region: light set
+// Any number of lights in a storage buffer: a 16-byte header with the light count,
+// then 64 bytes per light. Shaders include LIGHTS_WGSL and loop over
+// lightSet.lights[0..lightSet.count]; the set is bound at group LIGHT_SET_GROUP, after
+// the vertex (0) and fragment (1) uniforms of the chapters:
+//
+//     let binding = LightSetBinding::new(&device, 8);
+//     binding.write(&queue, &lights);
+//     // pipeline layout: [&vert_layout, &frag_layout, &binding.layout]
+//     render_pass.set_bind_group(LIGHT_SET_GROUP, &binding.bind_group, &[]);
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LightSet {
+    pub lights: Vec<Light>,
+}
+
+pub const LIGHT_SET_GROUP: u32 = 2;
+pub const LIGHT_HEADER_SIZE: wgpu::BufferAddress = 16;
+pub const LIGHT_SIZE: wgpu::BufferAddress = 64;
+
+impl LightSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, light: Light) -> Self {
+        self.lights.push(light);
+        self
+    }
+
+    pub fn push(&mut self, light: Light) {
+        self.lights.push(light);
+    }
+
+    pub fn len(&self) -> usize {
+        self.lights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    // bytes of a storage buffer for up to capacity lights
+    pub fn storage_size(capacity: usize) -> wgpu::BufferAddress {
+        LIGHT_HEADER_SIZE + LIGHT_SIZE * capacity.max(1) as wgpu::BufferAddress
+    }
+
+    // header and the first max_lights lights
+    pub fn storage_data(&self, max_lights: usize) -> Vec<u8> {
+        let count = self.lights.len().min(max_lights);
+        let mut data = Vec::with_capacity(Self::storage_size(count) as usize);
+        data.extend_from_slice(bytemuck::cast_slice(&[count as u32, 0, 0, 0]));
+        for light in &self.lights[..count] {
+            data.extend_from_slice(bytemuck::cast_slice(&light.gpu_data()));
+        }
+        data
+    }
+}
+
+// a light storage buffer of fixed capacity with its bind group
+pub struct LightSetBinding {
+    pub buffer: wgpu::Buffer,
+    pub layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+    capacity: usize,
+}
+
+impl LightSetBinding {
+    pub fn new(device: &wgpu::Device, capacity: usize) -> Result<Self> {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Light Set Buffer"),
+            size: LightSet::storage_size(capacity),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let (layout, bind_group) = ws::create_bind_group_storage(
+            device,
+            vec![wgpu::ShaderStages::FRAGMENT],
+            vec![wgpu::BufferBindingType::Storage { read_only: true }],
+            &[buffer.as_entire_binding()],
+        )?;
+        Ok(Self {
+            buffer,
+            layout,
+            bind_group,
+            capacity: capacity.max(1),
+        })
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // uploads the set; lights beyond the capacity are left out. Returns the number of
+    // lights written
+    pub fn write(&self, queue: &wgpu::Queue, lights: &LightSet) -> usize {
+        queue.write_buffer(&self.buffer, 0, &lights.storage_data(self.capacity));
+        lights.len().min(self.capacity)
+    }
+}
+// endregion: light set
+
+// region: rigs
+// Preset light sets, so screenshots do not need hand-tuned light directions. ThreePoint
+// and Studio follow the camera: their lights are placed relative to the view from eye
+// to target, a key light above and to the left of the camera, a fill on the other side
+// and, for ThreePoint, a rim light behind the surface. TopDown lights from straight
+// above like a lab bench and FlatAmbient evenly from the six axis directions, both
+// fixed in world space:
+//
+//     let lights = LightRig::ThreePoint.lights(eye.into(), target.into());
+//     light_set.write(&queue, &lights);
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LightRig {
+    #[default]
+    ThreePoint,
+    Studio,
+    TopDown,
+    FlatAmbient,
+}
+
+impl LightRig {
+    pub const ALL: [Self; 4] = [
+        Self::ThreePoint,
+        Self::Studio,
+        Self::TopDown,
+        Self::FlatAmbient,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ThreePoint => "three_point",
+            Self::Studio => "studio",
+            Self::TopDown => "top_down",
+            Self::FlatAmbient => "flat_ambient",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|rig| rig.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn next(&self) -> Self {
+        Self::ALL[(*self as usize + 1) % Self::ALL.len()]
+    }
+
+    // directional lights for a camera at eye looking at target
+    pub fn lights(&self, eye: [f32; 3], target: [f32; 3]) -> LightSet {
+        // camera right, up and backwards (towards the camera), falling back to looking
+        // down -z for a degenerate view
+        let mut back = Vector3::from(eye) - Vector3::from(target);
+        if back.magnitude2() < 1e-12 {
+            back = Vector3::unit_z();
+        }
+        let back = back.normalize();
+        let mut right = Vector3::unit_y().cross(back);
+        if right.magnitude2() < 1e-12 {
+            right = Vector3::unit_x();
+        }
+        let right = right.normalize();
+        let up = back.cross(right);
+        // a light coming from the camera-space direction [right, up, back]
+        let from = |[x, y, z]: [f32; 3]| -> [f32; 3] {
+            (-(right * x + up * y + back * z).normalize()).into()
+        };
+        let world = |from: [f32; 3]| -> [f32; 3] { (-Vector3::from(from).normalize()).into() };
+        match self {
+            Self::ThreePoint => LightSet::new()
+                .with(Light::directional(from([-0.8, 0.7, 0.9])).color([1.0, 0.96, 0.9]))
+                .with(
+                    Light::directional(from([0.9, 0.2, 0.7]))
+                        .color([0.85, 0.9, 1.0])
+                        .intensity(0.4),
+                )
+                .with(Light::directional(from([0.3, 0.8, -1.0])).intensity(0.7)),
+            Self::Studio => LightSet::new()
+                .with(Light::directional(from([0.0, 0.9, 1.0])).intensity(0.6))
+                .with(Light::directional(from([-1.0, 0.3, 0.3])).intensity(0.35))
+                .with(Light::directional(from([1.0, 0.3, 0.3])).intensity(0.35))
+                .with(Light::directional(world([0.0, 1.0, 0.0])).intensity(0.3)),
+            Self::TopDown => LightSet::new()
+                .with(Light::directional(world([0.0, 1.0, 0.0])).intensity(0.9))
+                .with(Light::directional(from([0.0, 0.0, 1.0])).intensity(0.15)),
+            Self::FlatAmbient => [
+                [1.0, 0.0, 0.0],
+                [-1.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0],
+                [0.0, -1.0, 0.0],
+                [0.0, 0.0, 1.0],
+                [0.0, 0.0, -1.0],
+            ]
+            .into_iter()
+            .fold(LightSet::new(), |lights, direction| {
+                lights.with(Light::directional(world(direction)).intensity(0.35))
+            }),
+        }
+    }
+}
+// endregion: rigs
+
+// region: shaders
+// The light set binding and light_sample(), which turns a light into the direction
+// towards it and the radiance arriving at a world space position.
+pub const LIGHTS_WGSL: &str = r#"
+struct Light {
+    position: vec4f,  // xyz position, w kind: 0 directional, 1 point, 2 spot
+    direction: vec4f, // xyz direction the light shines in, w range
+    color: vec4f,     // rgb color, w intensity
+    cone: vec4f,      // cosines of the inner and outer spot angles
+}
+
+struct LightSet {
+    count: u32,
+    lights: array<Light>,
+}
+@group(2) @binding(0) var<storage, read> lightSet : LightSet;
+
+struct LightSample {
+    toLight: vec3f,
+    radiance: vec3f,
+}
+
+fn light_sample(light: Light, position: vec3f) -> LightSample {
+    let kind = u32(light.position.w);
+    let direction = normalize(light.direction.xyz);
+    let radiance = light.color.rgb * light.color.w;
+    if (kind == 0u) {
+        return LightSample(-direction, radiance);
+    }
+    let offset = light.position.xyz - position;
+    let distance = length(offset);
+    let toLight = offset / max(distance, 1e-6);
+    var attenuation = 1.0 / max(distance * distance, 1e-4);
+    let range = light.direction.w;
+    if (range > 0.0) {
+        let window = clamp(1.0 - pow(distance / range, 4.0), 0.0, 1.0);
+        attenuation *= window * window;
+    }
+    if (kind == 2u) {
+        attenuation *= smoothstep(light.cone.y, light.cone.x, dot(-toLight, direction));
+    }
+    return LightSample(toLight, radiance * attenuation);
+}
+"#;
+
+pub const MULTI_LIGHT_WGSL: &str = include_str!("../ch02/common/multi_light_frag.wgsl");
+
+// the light set functions followed by the given shader source
+pub fn with_lights(shader: &str) -> String {
+    format!("{LIGHTS_WGSL}\n{shader}")
+}
+
+// Blinn-Phong fragment shader lit by every light of the set; same bind group 1 as the
+// Phong material, plus the light set at LIGHT_SET_GROUP
+pub fn multi_light_fragment() -> String {
+    with_lights(MULTI_LIGHT_WGSL)
+}
+// endregion: shaders
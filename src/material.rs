@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+
+// region: materials
+// How the surfaces are shaded: the Blinn-Phong model of the chapters, physically based
+// metal-rough shading (GGX, Smith, Schlick) or a procedural matcap that depends only on
+// the normal as seen from the eye. Each kind has its own fragment shader with the same
+// interface: the light uniforms at group 1 binding 0, a 16-byte material uniform at
+// binding 1 and the hook points of shader_hooks. So all kinds share one bind group
+// layout and pipeline layout, and switching kinds only needs a new fragment shader
+// and new material values:
+//
+//     let material = args.material.default_material();
+//     queue.write_buffer(&material_buffer, 0, cast_slice(&material.uniform_data()));
+//     let fs_source = material.kind().fragment_source();
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MaterialKind {
+    #[default]
+    Phong,
+    Pbr,
+    Matcap,
+}
+
+pub const MATERIAL_UNIFORM_SIZE: wgpu::BufferAddress = 16;
+
+pub const PHONG_WGSL: &str = include_str!("../ch02/common/directional_frag.wgsl");
+pub const PBR_WGSL: &str = include_str!("../ch02/common/pbr_frag.wgsl");
+pub const MATCAP_WGSL: &str = include_str!("../ch02/common/matcap_frag.wgsl");
+
+impl MaterialKind {
+    pub const ALL: [Self; 3] = [Self::Phong, Self::Pbr, Self::Matcap];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Phong => "phong",
+            Self::Pbr => "pbr",
+            Self::Matcap => "matcap",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn next(&self) -> Self {
+        Self::ALL[(*self as usize + 1) % Self::ALL.len()]
+    }
+
+    pub fn fragment_source(&self) -> &'static str {
+        match self {
+            Self::Phong => PHONG_WGSL,
+            Self::Pbr => PBR_WGSL,
+            Self::Matcap => MATCAP_WGSL,
+        }
+    }
+
+    pub fn fragment_label(&self) -> &'static str {
+        match self {
+            Self::Phong => "directional_frag.wgsl",
+            Self::Pbr => "pbr_frag.wgsl",
+            Self::Matcap => "matcap_frag.wgsl",
+        }
+    }
+
+    // the values the chapter surfaces use
+    pub fn default_material(&self) -> Material {
+        match self {
+            Self::Phong => Material::Phong {
+                ambient: 0.1,
+                diffuse: 0.7,
+                specular: 0.4,
+                shininess: 30.0,
+            },
+            Self::Pbr => Material::Pbr {
+                metallic: 0.1,
+                roughness: 0.45,
+                ambient: 0.08,
+                reflectance: 0.5,
+            },
+            Self::Matcap => Material::Matcap {
+                ambient: 0.25,
+                highlight: 0.5,
+                rim: 0.3,
+                sharpness: 24.0,
+            },
+        }
+    }
+}
+
+// material values in the order of the MaterialUniforms of the kind's shader
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Material {
+    Phong {
+        ambient: f32,
+        diffuse: f32,
+        specular: f32,
+        shininess: f32,
+    },
+    Pbr {
+        metallic: f32,  // 0 dielectric, 1 metal
+        roughness: f32, // 0 mirror-like, 1 fully rough
+        ambient: f32,
+        reflectance: f32, // specular reflectance of dielectrics, 0.5 gives F0 = 0.04
+    },
+    Matcap {
+        ambient: f32,   // brightness of the side facing away from the matcap key light
+        highlight: f32, // strength of the baked highlight
+        rim: f32,       // strength of the light along the silhouette
+        sharpness: f32, // exponent of the highlight
+    },
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        MaterialKind::default().default_material()
+    }
+}
+
+impl Material {
+    pub fn kind(&self) -> MaterialKind {
+        match self {
+            Self::Phong { .. } => MaterialKind::Phong,
+            Self::Pbr { .. } => MaterialKind::Pbr,
+            Self::Matcap { .. } => MaterialKind::Matcap,
+        }
+    }
+
+    // a material of the kind from the values of uniform_data(), in the same order
+    pub fn from_uniform_data(kind: MaterialKind, [a, b, c, d]: [f32; 4]) -> Self {
+        match kind {
+            MaterialKind::Phong => Self::Phong {
+                ambient: a,
+                diffuse: b,
+                specular: c,
+                shininess: d,
+            },
+            MaterialKind::Pbr => Self::Pbr {
+                metallic: a,
+                roughness: b,
+                ambient: c,
+                reflectance: d,
+            },
+            MaterialKind::Matcap => Self::Matcap {
+                ambient: a,
+                highlight: b,
+                rim: c,
+                sharpness: d,
+            },
+        }
+    }
+
+    // contents of the material uniform buffer, MATERIAL_UNIFORM_SIZE bytes
+    pub fn uniform_data(&self) -> [f32; 4] {
+        match *self {
+            Self::Phong {
+                ambient,
+                diffuse,
+                specular,
+                shininess,
+            } => [ambient, diffuse, specular, shininess],
+            Self::Pbr {
+                metallic,
+                roughness,
+                ambient,
+                reflectance,
+            } => [
+                metallic.clamp(0.0, 1.0),
+                roughness.clamp(0.0, 1.0),
+                ambient,
+                reflectance.clamp(0.0, 1.0),
+            ],
+            Self::Matcap {
+                ambient,
+                highlight,
+                rim,
+                sharpness,
+            } => [ambient, highlight, rim, sharpness],
+        }
+    }
+}
+// endregion: materials
@@ -96,7 +96,7 @@ pub fn klein_bottle3(u: f32, v: f32) -> [f32; 3] {
 pub fn klein_bottle2(u: f32, v: f32) -> [f32; 3] {
     let (mut x, mut z) = (0f32, 0f32);
     let r = 4.0 * (1.0 - 0.5 * u.cos());
-    if u >= 0.0 && u <= PI {
+    if (0.0..=PI).contains(&u) {
         x = 6.0 * u.cos() * (1.0 + u.sin()) + r * u.cos() * v.cos();
         z = 16.0 * u.sin() + r * u.sin() * v.cos();
     } else if u > PI && u <= 2.0 * PI {
@@ -239,6 +239,245 @@ pub fn steiner(u: f32, v: f32) -> [f32; 3] {
     [x, y, z]
 }
 
+// tube of the given radius around the (p, q) torus knot, which winds p times around
+// the y axis and q times through the hole of a torus with radii 1 and 0.5
+pub fn torus_knot(u: f32, v: f32, p: f32, q: f32, tube_radius: f32) -> [f32; 3] {
+    let r = 1.0 + 0.5 * (q * u).cos();
+    let dr = -0.5 * q * (q * u).sin();
+    let center = [r * (p * u).cos(), 0.5 * (q * u).sin(), r * (p * u).sin()];
+    let tangent = [
+        dr * (p * u).cos() - r * p * (p * u).sin(),
+        0.5 * q * (q * u).cos(),
+        dr * (p * u).sin() + r * p * (p * u).cos(),
+    ];
+
+    // the knot never runs parallel to the y axis, so tangent x y gives a smooth frame
+    let normal = normalize([-tangent[2], 0.0, tangent[0]]);
+    let t = normalize(tangent);
+    let binormal = [
+        t[1] * normal[2] - t[2] * normal[1],
+        t[2] * normal[0] - t[0] * normal[2],
+        t[0] * normal[1] - t[1] * normal[0],
+    ];
+
+    let (c, s) = (tube_radius * v.cos(), tube_radius * v.sin());
+    [
+        center[0] + c * normal[0] + s * binormal[0],
+        center[1] + c * normal[1] + s * binormal[1],
+        center[2] + c * normal[2] + s * binormal[2],
+    ]
+}
+
+// figure-8 immersion of the Klein bottle with a ring of radius 1; the figure-8
+// cross-section has half-width tube_radius
+pub fn klein_bagel(u: f32, v: f32, tube_radius: f32) -> [f32; 3] {
+    let w = (0.5 * u).cos() * v.sin() - (0.5 * u).sin() * (2.0 * v).sin();
+    let x = (1.0 + tube_radius * w) * u.cos();
+    let y = tube_radius * ((0.5 * u).sin() * v.sin() + (0.5 * u).cos() * (2.0 * v).sin());
+    let z = (1.0 + tube_radius * w) * u.sin();
+    [x, y, z]
+}
+
+// cross-cap immersion of the real projective plane
+pub fn cross_cap(u: f32, v: f32) -> [f32; 3] {
+    let x = u.cos() * (2.0 * v).sin();
+    let y = v.cos() * v.cos() - u.cos() * u.cos() * v.sin() * v.sin();
+    let z = u.sin() * (2.0 * v).sin();
+    [x, y, z]
+}
+
+// Bryant-Kusner parametrization of Boy's surface over the unit disk, with
+// u the radius and v the polar angle
+pub fn boy_surface(u: f32, v: f32) -> [f32; 3] {
+    let z = Complex::polar(u, v);
+    let (z3, z4, z6) = (z.powi(3), z.powi(4), z.powi(6));
+    let denom = z6 + z3.scale(5.0f32.sqrt()) - Complex::ONE;
+
+    let g1 = -1.5 * ((z * (Complex::ONE - z4)) / denom).im;
+    let g2 = -1.5 * ((z * (Complex::ONE + z4)) / denom).re;
+    let g3 = ((Complex::ONE + z6) / denom).im - 0.5;
+    let g = g1 * g1 + g2 * g2 + g3 * g3;
+    [g1 / g, g3 / g, g2 / g]
+}
+
+// Scherk's first surface as a graph over (-PI/2, PI/2) x (-PI/2, PI/2)
+pub fn scherk(u: f32, v: f32) -> [f32; 3] {
+    let y = (v.cos() / u.cos()).ln();
+    [u, y, v]
+}
+
+// associate family of minimal surfaces: alpha = 0 gives the helicoid and
+// alpha = PI/2 the catenoid, every alpha in between is an isometric bending
+pub fn catenoid_helicoid(u: f32, v: f32, alpha: f32) -> [f32; 3] {
+    let (ca, sa) = (alpha.cos(), alpha.sin());
+    let x = ca * v.sinh() * u.sin() + sa * v.cosh() * u.cos();
+    let z = -ca * v.sinh() * u.cos() + sa * v.cosh() * u.sin();
+    let y = u * ca + v * sa;
+    [x, y, z]
+}
+
+// Costa's minimal surface from Gray's parametrization with the Weierstrass zeta and
+// p functions of the unit square lattice; (u, v) in (0, 1) x (0, 1), the corners
+// and the points (1/2, 0), (0, 1/2) are the three ends
+pub fn costa(u: f32, v: f32) -> [f32; 3] {
+    let w = Complex::new(u, v);
+    let e1 = weierstrass_p(Complex::new(0.5, 0.0)).re;
+    let zeta_diff =
+        weierstrass_zeta(w - Complex::new(0.5, 0.0)) - weierstrass_zeta(w - Complex::new(0.0, 0.5));
+    let zeta = weierstrass_zeta(w);
+    let i = Complex::new(0.0, 1.0);
+
+    let x = 0.5 * (-zeta.re + PI * u + PI * PI / (4.0 * e1) + PI / (2.0 * e1) * zeta_diff.re);
+    let y = 0.5
+        * (-(i * zeta).re + PI * v + PI * PI / (4.0 * e1) - PI / (2.0 * e1) * (i * zeta_diff).re);
+    let p = weierstrass_p(w);
+    let z = (2.0 * PI).sqrt() / 4.0
+        * ((p - Complex::new(e1, 0.0)).norm() / (p + Complex::new(e1, 0.0)).norm()).ln();
+    [x, z, y]
+}
+
+// Jacobi theta function 1 and its first two derivatives for the square lattice
+// (nome q = exp(-PI))
+fn theta1(v: Complex) -> [Complex; 3] {
+    let q = (-PI).exp();
+    let mut theta = [Complex::ZERO; 3];
+    for n in 0..4 {
+        let k = (2 * n + 1) as f32;
+        let c = 2.0 * (-1.0f32).powi(n) * q.powf((n as f32 + 0.5).powi(2));
+        let (sin, cos) = (v.scale(k).sin(), v.scale(k).cos());
+        theta[0] = theta[0] + sin.scale(c);
+        theta[1] = theta[1] + cos.scale(c * k);
+        theta[2] = theta[2] - sin.scale(c * k * k);
+    }
+    theta
+}
+
+// eta1 = zeta(1/2); for the square lattice it equals PI/2 (Legendre's relation)
+const ETA1: f32 = PI / 2.0;
+
+fn weierstrass_zeta(z: Complex) -> Complex {
+    let [t, t1, _] = theta1(z.scale(PI));
+    z.scale(2.0 * ETA1) + (t1 / t).scale(PI)
+}
+
+fn weierstrass_p(z: Complex) -> Complex {
+    let [t, t1, t2] = theta1(z.scale(PI));
+    Complex::new(-2.0 * ETA1, 0.0) - ((t2 * t - t1 * t1) / (t * t)).scale(PI * PI)
+}
+
+// triply periodic minimal surfaces, approximated by their implicit level sets f = 0
+pub fn gyroid(x: f32, y: f32, z: f32) -> f32 {
+    x.sin() * y.cos() + y.sin() * z.cos() + z.sin() * x.cos()
+}
+
+pub fn schwarz_p(x: f32, y: f32, z: f32) -> f32 {
+    x.cos() + y.cos() + z.cos()
+}
+
+pub fn schwarz_d(x: f32, y: f32, z: f32) -> f32 {
+    x.sin() * y.sin() * z.sin()
+        + x.sin() * y.cos() * z.cos()
+        + x.cos() * y.sin() * z.cos()
+        + x.cos() * y.cos() * z.sin()
+}
+
+#[derive(Clone, Copy)]
+struct Complex {
+    re: f32,
+    im: f32,
+}
+
+impl Complex {
+    const ZERO: Complex = Complex { re: 0.0, im: 0.0 };
+    const ONE: Complex = Complex { re: 1.0, im: 0.0 };
+
+    fn new(re: f32, im: f32) -> Self {
+        Self { re, im }
+    }
+
+    fn polar(r: f32, theta: f32) -> Self {
+        Self {
+            re: r * theta.cos(),
+            im: r * theta.sin(),
+        }
+    }
+
+    fn scale(self, s: f32) -> Self {
+        Self {
+            re: s * self.re,
+            im: s * self.im,
+        }
+    }
+
+    fn powi(self, n: u32) -> Self {
+        (0..n).fold(Complex::ONE, |acc, _| acc * self)
+    }
+
+    fn norm(self) -> f32 {
+        self.re.hypot(self.im)
+    }
+
+    fn sin(self) -> Self {
+        Self {
+            re: self.re.sin() * self.im.cosh(),
+            im: self.re.cos() * self.im.sinh(),
+        }
+    }
+
+    fn cos(self) -> Self {
+        Self {
+            re: self.re.cos() * self.im.cosh(),
+            im: -self.re.sin() * self.im.sinh(),
+        }
+    }
+}
+
+impl std::ops::Add for Complex {
+    type Output = Complex;
+    fn add(self, b: Complex) -> Complex {
+        Complex {
+            re: self.re + b.re,
+            im: self.im + b.im,
+        }
+    }
+}
+
+impl std::ops::Sub for Complex {
+    type Output = Complex;
+    fn sub(self, b: Complex) -> Complex {
+        Complex {
+            re: self.re - b.re,
+            im: self.im - b.im,
+        }
+    }
+}
+
+impl std::ops::Mul for Complex {
+    type Output = Complex;
+    fn mul(self, b: Complex) -> Complex {
+        Complex {
+            re: self.re * b.re - self.im * b.im,
+            im: self.re * b.im + self.im * b.re,
+        }
+    }
+}
+
+impl std::ops::Div for Complex {
+    type Output = Complex;
+    fn div(self, b: Complex) -> Complex {
+        let d = b.re * b.re + b.im * b.im;
+        Complex {
+            re: (self.re * b.re + self.im * b.im) / d,
+            im: (self.im * b.re - self.re * b.im) / d,
+        }
+    }
+}
+
+fn normalize(a: [f32; 3]) -> [f32; 3] {
+    let len = (a[0] * a[0] + a[1] * a[1] + a[2] * a[2]).sqrt();
+    [a[0] / len, a[1] / len, a[2] / len]
+}
+
 pub fn sinc(x: f32, z: f32, t: f32) -> [f32; 3] {
     let a = 1.01 + t.sin();
     let r = a * (x * x + z * z).sqrt();
@@ -0,0 +1,225 @@
+use super::color;
+use super::surface_data::ISurfaceOutput;
+
+// region: vertex attributes
+// The per-vertex attributes a vertex stream carries besides the position. Unlit or
+// colormap-texture pipelines do not need normals or baked colors, and leaving them out
+// shrinks a vertex from 36 bytes to 24 (position and color) or 20 (position and uv).
+// Every attribute keeps its shader location whatever else is present: position 0,
+// normal 1, color 2 and uv 4 (3 holds the previous position of motion::MotionHistory),
+// so a shader reading only position and color runs on any stream that has a color.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct VertexAttributes {
+    pub normal: bool,
+    pub color: bool,
+    pub uv: bool,
+}
+
+impl Default for VertexAttributes {
+    fn default() -> Self {
+        Self::LIT
+    }
+}
+
+impl VertexAttributes {
+    pub const POSITION_LOCATION: u32 = 0;
+    pub const NORMAL_LOCATION: u32 = 1;
+    pub const COLOR_LOCATION: u32 = 2;
+    pub const UV_LOCATION: u32 = 4;
+
+    // position, normal and color: the vertex of the chapter examples
+    pub const LIT: Self = Self {
+        normal: true,
+        color: true,
+        uv: false,
+    };
+    pub const UNLIT: Self = Self {
+        normal: false,
+        color: true,
+        uv: false,
+    };
+    // the color comes from a colormap texture looked up with the uv
+    pub const TEXTURED: Self = Self {
+        normal: false,
+        color: false,
+        uv: true,
+    };
+
+    pub fn floats(&self) -> usize {
+        3 + 3 * self.normal as usize + 3 * self.color as usize + 2 * self.uv as usize
+    }
+
+    pub fn stride(&self) -> wgpu::BufferAddress {
+        (4 * self.floats()) as wgpu::BufferAddress
+    }
+
+    pub fn layout(&self) -> VertexLayout {
+        let mut attributes = vec![];
+        let mut offset = 0;
+        let mut add = |present: bool, location: u32, format: wgpu::VertexFormat| {
+            if present {
+                attributes.push(wgpu::VertexAttribute {
+                    format,
+                    offset,
+                    shader_location: location,
+                });
+                offset += format.size();
+            }
+        };
+        add(true, Self::POSITION_LOCATION, wgpu::VertexFormat::Float32x3);
+        add(
+            self.normal,
+            Self::NORMAL_LOCATION,
+            wgpu::VertexFormat::Float32x3,
+        );
+        add(
+            self.color,
+            Self::COLOR_LOCATION,
+            wgpu::VertexFormat::Float32x3,
+        );
+        add(self.uv, Self::UV_LOCATION, wgpu::VertexFormat::Float32x2);
+        VertexLayout {
+            stride: self.stride(),
+            attributes,
+        }
+    }
+
+    // vertex entry point parameters matching the layout, e.g.
+    // "@location(0) position: vec4f, @location(2) color: vec4f"
+    pub fn wgsl_inputs(&self) -> String {
+        let mut inputs = vec![format!(
+            "@location({}) position: vec4f",
+            Self::POSITION_LOCATION
+        )];
+        if self.normal {
+            inputs.push(format!(
+                "@location({}) normal: vec4f",
+                Self::NORMAL_LOCATION
+            ));
+        }
+        if self.color {
+            inputs.push(format!("@location({}) color: vec4f", Self::COLOR_LOCATION));
+        }
+        if self.uv {
+            inputs.push(format!("@location({}) uv: vec2f", Self::UV_LOCATION));
+        }
+        inputs.join(", ")
+    }
+}
+
+// owns the attribute list a wgpu::VertexBufferLayout borrows
+#[derive(Clone, Debug, PartialEq)]
+pub struct VertexLayout {
+    pub stride: wgpu::BufferAddress,
+    pub attributes: Vec<wgpu::VertexAttribute>,
+}
+
+impl VertexLayout {
+    pub fn buffer_layout(&self) -> wgpu::VertexBufferLayout<'_> {
+        wgpu::VertexBufferLayout {
+            array_stride: self.stride,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &self.attributes,
+        }
+    }
+}
+// endregion: vertex attributes
+
+// region: mesh builder
+// Interleaves the output of a surface into vertex data with the chosen attributes:
+//
+//     let mesh = MeshBuilder::new().normals(false).build(&surface.new());
+//     let layout = mesh.attributes.layout(); // for the pipeline
+//
+// vertices are colored with the surface colors, wire_vertices with the wireframe
+// colors (colors2); without a color attribute both are the same.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MeshBuilder {
+    pub attributes: VertexAttributes,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IMesh {
+    pub attributes: VertexAttributes,
+    pub vertices: Vec<f32>,
+    pub wire_vertices: Vec<f32>,
+    pub indices: Vec<u16>,
+    pub indices2: Vec<u16>,
+}
+
+impl IMesh {
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len() / self.attributes.floats()
+    }
+}
+
+impl MeshBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_attributes(attributes: VertexAttributes) -> Self {
+        Self { attributes }
+    }
+
+    pub fn normals(mut self, normal: bool) -> Self {
+        self.attributes.normal = normal;
+        self
+    }
+
+    pub fn colors(mut self, color: bool) -> Self {
+        self.attributes.color = color;
+        self
+    }
+
+    pub fn uvs(mut self, uv: bool) -> Self {
+        self.attributes.uv = uv;
+        self
+    }
+
+    // one vertex per position; attributes missing from the given slices are zero and
+    // the display colors are written linear (see color.rs)
+    pub fn vertices(
+        &self,
+        positions: &[[f32; 3]],
+        normals: &[[f32; 3]],
+        colors: &[[f32; 3]],
+        uvs: &[[f32; 2]],
+    ) -> Vec<f32> {
+        let a = self.attributes;
+        let mut data = Vec::with_capacity(positions.len() * a.floats());
+        for (i, position) in positions.iter().enumerate() {
+            data.extend_from_slice(position);
+            if a.normal {
+                data.extend_from_slice(normals.get(i).unwrap_or(&[0.0; 3]));
+            }
+            if a.color {
+                data.extend_from_slice(&color::to_linear(*colors.get(i).unwrap_or(&[0.0; 3])));
+            }
+            if a.uv {
+                data.extend_from_slice(uvs.get(i).unwrap_or(&[0.0; 2]));
+            }
+        }
+        data
+    }
+
+    pub fn build(&self, output: &ISurfaceOutput) -> IMesh {
+        let vertices = |colors: &[[f32; 3]]| {
+            self.vertices(&output.positions, &output.normals, colors, &output.uvs)
+        };
+        let surface = vertices(&output.colors);
+        let wire = if self.attributes.color {
+            vertices(&output.colors2)
+        } else {
+            surface.clone()
+        };
+        IMesh {
+            attributes: self.attributes,
+            vertices: surface,
+            wire_vertices: wire,
+            indices: output.indices.clone(),
+            indices2: output.indices2.clone(),
+        }
+    }
+}
+// endregion: mesh builder
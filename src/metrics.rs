@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+
+use super::surface_data::ISurfaceOutput;
+
+// region: mesh metrics
+// Area, enclosed volume, centroid and second moments of a triangle mesh, for checking
+// a discretized surface against analytic values. Grids repeat the vertices along
+// their seams and poles, so vertices at the same position (within a tolerance
+// relative to the mesh size) are welded first; a mesh is closed if every edge is then
+// shared by exactly two triangles. Volume and moments are of the solid a closed mesh
+// bounds, with unit density, whichever way its triangles are wound.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MeshMetrics {
+    pub triangles: usize, // non-degenerate triangles after welding
+    pub area: f32,
+    pub area_centroid: [f32; 3],
+    pub volume: Option<f32>,            // None for an open mesh
+    pub centroid: [f32; 3],             // of the solid if closed, else area_centroid
+    pub inertia: Option<[[f32; 3]; 3]>, // inertia tensor about centroid if closed
+}
+
+// weld distance as a fraction of the bounding box diagonal
+pub const WELD_TOLERANCE: f32 = 1e-5;
+
+impl std::fmt::Display for MeshMetrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let [x, y, z] = self.centroid;
+        write!(f, "{} triangles, area {:.5}", self.triangles, self.area)?;
+        match (self.volume, self.inertia) {
+            (Some(volume), Some(inertia)) => {
+                let [ixx, iyy, izz] = [0, 1, 2].map(|i| inertia[i][i]);
+                write!(
+                    f,
+                    ", volume {volume:.5}, centroid ({x:.4}, {y:.4}, {z:.4}), \
+                     inertia diagonal ({ixx:.5}, {iyy:.5}, {izz:.5})"
+                )
+            }
+            _ => write!(f, ", open, area centroid ({x:.4}, {y:.4}, {z:.4})"),
+        }
+    }
+}
+
+type Vec3 = [f64; 3];
+
+fn sub(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn dot(a: Vec3, b: Vec3) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn to_f32<const N: usize>(v: [f64; N]) -> [f32; N] {
+    v.map(|x| x as f32)
+}
+
+fn to_f64(p: [f32; 3]) -> Vec3 {
+    p.map(|x| x as f64)
+}
+
+impl ISurfaceOutput {
+    // the welded vertex of every vertex, numbered from 0 in order of appearance:
+    // vertices within WELD_TOLERANCE of each other get the same one
+    pub fn weld_ids(&self) -> Vec<usize> {
+        let Some(first) = self.positions.first() else {
+            return vec![];
+        };
+        let (min, max) = self
+            .positions
+            .iter()
+            .fold((*first, *first), |(min, max), p| {
+                (
+                    [0, 1, 2].map(|i| min[i].min(p[i])),
+                    [0, 1, 2].map(|i| max[i].max(p[i])),
+                )
+            });
+        let extent = sub(to_f64(max), to_f64(min));
+        let cell = (dot(extent, extent).sqrt() * WELD_TOLERANCE as f64).max(f64::MIN_POSITIVE);
+        // first position of each welded vertex by grid cell; a position joins a vertex
+        // within cell of it in its own or a neighboring cell
+        let mut cells: HashMap<[i64; 3], Vec<(usize, Vec3)>> = HashMap::new();
+        let mut count = 0;
+        self.positions
+            .iter()
+            .map(|&p| {
+                let p = to_f64(p);
+                let key = p.map(|x| (x / cell).floor() as i64);
+                let near = |q: &Vec3| dot(sub(p, *q), sub(p, *q)) <= cell * cell;
+                for offset in 0..27 {
+                    let d = [offset % 3 - 1, offset / 3 % 3 - 1, offset / 9 - 1];
+                    let neighbor = [0, 1, 2].map(|i| key[i].saturating_add(d[i]));
+                    if let Some(&(id, _)) = cells
+                        .get(&neighbor)
+                        .and_then(|c| c.iter().find(|(_, q)| near(q)))
+                    {
+                        return id;
+                    }
+                }
+                cells.entry(key).or_default().push((count, p));
+                count += 1;
+                count - 1
+            })
+            .collect()
+    }
+
+    // welded vertex ids and corner positions of the triangles, degenerate ones left out
+    fn welded_triangles(&self) -> Vec<([usize; 3], [Vec3; 3])> {
+        if self.positions.is_empty() {
+            return vec![];
+        }
+        let welded = self.weld_ids();
+        self.indices
+            .chunks_exact(3)
+            .map(|t| {
+                let t = [0, 1, 2].map(|k| t[k] as usize);
+                (t.map(|i| welded[i]), t.map(|i| to_f64(self.positions[i])))
+            })
+            .filter(|([a, b, c], _)| a != b && b != c && c != a)
+            .collect()
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        self.metrics().area
+    }
+
+    // enclosed volume, None if the mesh is not closed
+    pub fn volume(&self) -> Option<f32> {
+        self.metrics().volume
+    }
+
+    pub fn centroid(&self) -> [f32; 3] {
+        self.metrics().centroid
+    }
+
+    pub fn metrics(&self) -> MeshMetrics {
+        let triangles = self.welded_triangles();
+        let mut edges = HashMap::new();
+        for (t, _) in &triangles {
+            for k in 0..3 {
+                let (a, b) = (t[k], t[(k + 1) % 3]);
+                *edges.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+            }
+        }
+        let closed = !triangles.is_empty() && edges.values().all(|&n| n == 2);
+
+        let (mut area, mut area_moment) = (0.0, [0.0; 3]);
+        // signed volume, first moment and second moment (covariance) of the tetrahedra
+        // from the origin to each triangle
+        let (mut volume, mut moment, mut covariance) = (0.0, [0.0; 3], [[0.0; 3]; 3]);
+        for (_, [a, b, c]) in &triangles {
+            let n = cross(sub(*b, *a), sub(*c, *a));
+            let triangle_area = 0.5 * dot(n, n).sqrt();
+            area += triangle_area;
+            for i in 0..3 {
+                area_moment[i] += triangle_area * (a[i] + b[i] + c[i]) / 3.0;
+            }
+            let det = dot(*a, cross(*b, *c));
+            volume += det / 6.0;
+            for i in 0..3 {
+                moment[i] += det / 24.0 * (a[i] + b[i] + c[i]);
+            }
+            let s = [a[0] + b[0] + c[0], a[1] + b[1] + c[1], a[2] + b[2] + c[2]];
+            for i in 0..3 {
+                for j in 0..3 {
+                    let pairs = a[i] * a[j] + b[i] * b[j] + c[i] * c[j];
+                    covariance[i][j] += det / 120.0 * (pairs + s[i] * s[j]);
+                }
+            }
+        }
+        let area_centroid = if area > 0.0 {
+            area_moment.map(|m| m / area)
+        } else {
+            [0.0; 3]
+        };
+
+        let mut metrics = MeshMetrics {
+            triangles: triangles.len(),
+            area: area as f32,
+            area_centroid: to_f32(area_centroid),
+            centroid: to_f32(area_centroid),
+            ..Default::default()
+        };
+        if closed && volume.abs() > 0.0 {
+            // inward winding gives negative volumes and moments throughout
+            let sign = volume.signum();
+            let centroid = moment.map(|m| m / volume);
+            let mut inertia = [[0.0f32; 3]; 3];
+            // covariance about the centroid, then I = trace(C) * E - C
+            let c =
+                |i: usize, j: usize| sign * (covariance[i][j] - volume * centroid[i] * centroid[j]);
+            let trace = c(0, 0) + c(1, 1) + c(2, 2);
+            for (i, row) in inertia.iter_mut().enumerate() {
+                for (j, value) in row.iter_mut().enumerate() {
+                    let diagonal = if i == j { trace } else { 0.0 };
+                    *value = (diagonal - c(i, j)) as f32;
+                }
+            }
+            metrics.volume = Some(volume.abs() as f32);
+            metrics.centroid = to_f32(centroid);
+            metrics.inertia = Some(inertia);
+        }
+        metrics
+    }
+}
+// endregion: mesh metrics
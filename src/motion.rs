@@ -0,0 +1,142 @@
+use super::wgpu_simplified::GrowableBuffer;
+
+// region: motion history
+// Positions of the previous frame for effects that depend on how fast the surface
+// moves, e.g. the heat coloring below. push() is called once per frame with the new
+// vertex positions; the history ping-pongs between two position buffers, so previous()
+// always holds the positions of the frame before, ready to be bound as a second vertex
+// buffer next to the current vertices (Float32x3, see VERTEX_ATTRIBUTES). A change of
+// the vertex count, e.g. a new resolution, restarts the history without motion.
+pub struct MotionHistory {
+    buffers: [GrowableBuffer; 2],
+    current: usize,
+    count: usize,
+    last_time: Option<f32>,
+    frame_time: f32,
+}
+
+impl MotionHistory {
+    // previous positions at shader location 3, after position, normal and color
+    pub const VERTEX_ATTRIBUTES: [wgpu::VertexAttribute; 1] =
+        wgpu::vertex_attr_array![3 => Float32x3];
+
+    pub fn new(device: &wgpu::Device) -> Self {
+        let buffer = || {
+            GrowableBuffer::new(
+                device,
+                "Motion History Buffer",
+                wgpu::BufferUsages::VERTEX,
+                &[],
+            )
+        };
+        Self {
+            buffers: [buffer(), buffer()],
+            current: 0,
+            count: 0,
+            last_time: None,
+            frame_time: 0.0,
+        }
+    }
+
+    pub fn vertex_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: 12,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::VERTEX_ATTRIBUTES,
+        }
+    }
+
+    // records the positions of the frame rendered at time (seconds)
+    pub fn push(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        positions: &[[f32; 3]],
+        time: f32,
+    ) {
+        let bytes = bytemuck::cast_slice(positions);
+        if positions.len() != self.count {
+            self.count = positions.len();
+            self.last_time = None;
+            for buffer in self.buffers.iter_mut() {
+                buffer.write(device, queue, bytes);
+            }
+        } else {
+            self.current ^= 1;
+            self.buffers[self.current].write(device, queue, bytes);
+        }
+        self.frame_time = self.last_time.map_or(0.0, |last| time - last);
+        self.last_time = Some(time);
+    }
+
+    // positions of the frame before the last push
+    pub fn previous(&self) -> &GrowableBuffer {
+        &self.buffers[self.current ^ 1]
+    }
+
+    // seconds between the last two pushes, 0 right after a restart
+    pub fn frame_time(&self) -> f32 {
+        self.frame_time
+    }
+
+    // turns a distance moved in one frame into a speed; 0 without a frame time
+    pub fn speed_factor(&self) -> f32 {
+        if self.frame_time > 1e-6 {
+            1.0 / self.frame_time
+        } else {
+            0.0
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.count = 0;
+        self.last_time = None;
+        self.frame_time = 0.0;
+    }
+}
+// endregion: motion history
+
+// region: heat effect
+// Colors a surface by its speed |dP/dt|: still parts keep their color, moving parts
+// glow from red through yellow to white. heat is 1 - exp(-gain * speed), so gain sets
+// the speed at which the glow saturates.
+pub const HEAT_WGSL: &str = r#"
+fn heat_ramp(h: f32) -> vec3f {
+    return clamp(vec3f(3.0 * h, 3.0 * h - 1.0, 3.0 * h - 2.0), vec3f(0.0), vec3f(1.0));
+}
+
+fn heat_color(color: vec3f, speed: f32, gain: f32) -> vec3f {
+    let h = 1.0 - exp(-gain * max(speed, 0.0));
+    return mix(color, heat_ramp(h), h);
+}
+"#;
+
+// the heat functions followed by the given shader source
+pub fn with_heat(shader: &str) -> String {
+    format!("{HEAT_WGSL}\n{shader}")
+}
+
+// CPU mirror of heat_color in HEAT_WGSL
+pub fn heat_color(color: [f32; 3], speed: f32, gain: f32) -> [f32; 3] {
+    let h = 1.0 - (-gain * speed.max(0.0)).exp();
+    let ramp = [3.0 * h, 3.0 * h - 1.0, 3.0 * h - 2.0].map(|c| c.clamp(0.0, 1.0));
+    [0, 1, 2].map(|i| color[i] + (ramp[i] - color[i]) * h)
+}
+
+// per-vertex speeds between two frames of the same mesh
+pub fn vertex_speeds(previous: &[[f32; 3]], current: &[[f32; 3]], frame_time: f32) -> Vec<f32> {
+    previous
+        .iter()
+        .zip(current)
+        .map(|(a, b)| {
+            let d = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+            let distance = (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt();
+            if frame_time > 1e-6 {
+                distance / frame_time
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+// endregion: heat effect
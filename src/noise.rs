@@ -0,0 +1,388 @@
+use std::f32::consts::{FRAC_1_SQRT_2, SQRT_2};
+
+use bytemuck::{Pod, Zeroable};
+
+// region: noise shader
+// Procedural noise for WGSL shaders: value, Perlin (gradient) and simplex noise plus
+// fractal Brownian motion over octaves of Perlin noise. Everything is hash based, so
+// there are no permutation tables to upload; the seed is folded into the hash. Add
+// the library in front of a shader with with_noise() and bind a NoiseParams uniform
+// for the fbm functions:
+//
+//     @group(0) @binding(0) var<uniform> noise: NoiseParams;
+//     ... let height = noise_fbm2(position.xz, noise);
+//
+// All functions return values in about [-1, 1] and are reproduced on the CPU by the
+// functions below, e.g. to place objects on a GPU-generated terrain.
+pub const NOISE_WGSL: &str = r#"
+struct NoiseParams {
+    seed: u32,
+    octaves: u32,
+    frequency: f32,
+    lacunarity: f32,
+    gain: f32,
+    amplitude: f32,
+    offset: vec2f,
+}
+
+// PCG hash (Jarzynski and Olano 2020)
+fn noise_pcg(v: u32) -> u32 {
+    let state = v * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+fn noise_hash2(p: vec2i, seed: u32) -> u32 {
+    return noise_pcg(noise_pcg(noise_pcg(seed) + bitcast<u32>(p.x)) + bitcast<u32>(p.y));
+}
+
+fn noise_hash3(p: vec3i, seed: u32) -> u32 {
+    return noise_pcg(noise_hash2(p.xy, seed) + bitcast<u32>(p.z));
+}
+
+// uniform in [0, 1), from the upper 24 bits so the conversion is exact
+fn noise_unit(h: u32) -> f32 {
+    return f32(h >> 8u) / 16777216.0;
+}
+
+// quintic interpolation weights, continuous up to the second derivative
+fn noise_fade(t: f32) -> f32 {
+    return t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+}
+
+fn noise_mix(a: f32, b: f32, t: f32) -> f32 {
+    return a + (b - a) * t;
+}
+
+// one of eight unit gradients: the axes and the diagonals
+fn noise_grad2(h: u32) -> vec2f {
+    let s = vec2f(select(1.0, -1.0, (h & 1u) != 0u), select(1.0, -1.0, (h & 2u) != 0u));
+    if (h & 4u) != 0u {
+        return s * 0.70710678;
+    }
+    return select(vec2f(s.x, 0.0), vec2f(0.0, s.y), (h & 8u) != 0u);
+}
+
+// dot product with one of the twelve cube edge gradients of improved Perlin noise
+fn noise_grad3(h: u32, d: vec3f) -> f32 {
+    let k = h & 15u;
+    let u = select(d.y, d.x, k < 8u);
+    let v = select(select(d.z, d.x, k == 12u || k == 14u), d.y, k < 4u);
+    return select(-u, u, (k & 1u) == 0u) + select(-v, v, (k & 2u) == 0u);
+}
+
+fn noise_value2(p: vec2f, seed: u32) -> f32 {
+    let c = floor(p);
+    let i = vec2i(c);
+    let f = p - c;
+    let a = noise_unit(noise_hash2(i, seed));
+    let b = noise_unit(noise_hash2(i + vec2i(1, 0), seed));
+    let d = noise_unit(noise_hash2(i + vec2i(0, 1), seed));
+    let e = noise_unit(noise_hash2(i + vec2i(1, 1), seed));
+    let u = noise_fade(f.x);
+    return 2.0 * noise_mix(noise_mix(a, b, u), noise_mix(d, e, u), noise_fade(f.y)) - 1.0;
+}
+
+fn noise_perlin2(p: vec2f, seed: u32) -> f32 {
+    let c = floor(p);
+    let i = vec2i(c);
+    let f = p - c;
+    let a = dot(noise_grad2(noise_hash2(i, seed)), f);
+    let b = dot(noise_grad2(noise_hash2(i + vec2i(1, 0), seed)), f - vec2f(1.0, 0.0));
+    let d = dot(noise_grad2(noise_hash2(i + vec2i(0, 1), seed)), f - vec2f(0.0, 1.0));
+    let e = dot(noise_grad2(noise_hash2(i + vec2i(1, 1), seed)), f - vec2f(1.0, 1.0));
+    let u = noise_fade(f.x);
+    // unit gradients stay within +/- sqrt(1/2)
+    return 1.41421356 * noise_mix(noise_mix(a, b, u), noise_mix(d, e, u), noise_fade(f.y));
+}
+
+fn noise_perlin3(p: vec3f, seed: u32) -> f32 {
+    let c = floor(p);
+    let i = vec3i(c);
+    let f = p - c;
+    var corners: array<f32, 8>;
+    for (var k = 0; k < 8; k++) {
+        let o = vec3i(k & 1, (k >> 1u) & 1, (k >> 2u) & 1);
+        corners[k] = noise_grad3(noise_hash3(i + o, seed), f - vec3f(o));
+    }
+    let u = vec3f(noise_fade(f.x), noise_fade(f.y), noise_fade(f.z));
+    let a = noise_mix(corners[0], corners[1], u.x);
+    let b = noise_mix(corners[2], corners[3], u.x);
+    let d = noise_mix(corners[4], corners[5], u.x);
+    let e = noise_mix(corners[6], corners[7], u.x);
+    return noise_mix(noise_mix(a, b, u.y), noise_mix(d, e, u.y), u.z);
+}
+
+fn noise_simplex_corner(d: vec2f, h: u32) -> f32 {
+    let t = 0.5 - dot(d, d);
+    if t <= 0.0 {
+        return 0.0;
+    }
+    let t2 = t * t;
+    return t2 * t2 * dot(noise_grad2(h), d);
+}
+
+fn noise_simplex2(p: vec2f, seed: u32) -> f32 {
+    let F2 = 0.36602542; // (sqrt(3) - 1) / 2
+    let G2 = 0.21132487; // (3 - sqrt(3)) / 6
+    let c = floor(p + (p.x + p.y) * F2);
+    let i = vec2i(c);
+    let d0 = p - c + (c.x + c.y) * G2;
+    let o = select(vec2i(0, 1), vec2i(1, 0), d0.x > d0.y);
+    let d1 = d0 - vec2f(o) + G2;
+    let d2 = d0 - 1.0 + 2.0 * G2;
+    let n = noise_simplex_corner(d0, noise_hash2(i, seed))
+        + noise_simplex_corner(d1, noise_hash2(i + o, seed))
+        + noise_simplex_corner(d2, noise_hash2(i + vec2i(1, 1), seed));
+    return 99.2 * n;
+}
+
+// octaves of Perlin noise, each with its own seed; normalized by the sum of the
+// octave amplitudes and scaled by params.amplitude
+fn noise_fbm2(p: vec2f, params: NoiseParams) -> f32 {
+    var q = p * params.frequency + params.offset;
+    var amplitude = 1.0;
+    var sum = 0.0;
+    var norm = 0.0;
+    for (var k = 0u; k < params.octaves; k++) {
+        sum += amplitude * noise_perlin2(q, params.seed + k);
+        norm += amplitude;
+        q *= params.lacunarity;
+        amplitude *= params.gain;
+    }
+    return params.amplitude * sum / max(norm, 1e-6);
+}
+
+// like noise_fbm2, z is typically time for animated displacement
+fn noise_fbm3(p: vec3f, params: NoiseParams) -> f32 {
+    var q = p * params.frequency + vec3f(params.offset, 0.0);
+    var amplitude = 1.0;
+    var sum = 0.0;
+    var norm = 0.0;
+    for (var k = 0u; k < params.octaves; k++) {
+        sum += amplitude * noise_perlin3(q, params.seed + k);
+        norm += amplitude;
+        q *= params.lacunarity;
+        amplitude *= params.gain;
+    }
+    return params.amplitude * sum / max(norm, 1e-6);
+}
+"#;
+
+// the noise library followed by the given shader source
+pub fn with_noise(shader: &str) -> String {
+    format!("{NOISE_WGSL}\n{shader}")
+}
+// endregion: noise shader
+
+// region: noise parameters
+// Uniform block matching NoiseParams in NOISE_WGSL (32 bytes).
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+pub struct NoiseParams {
+    pub seed: u32,
+    pub octaves: u32,
+    pub frequency: f32,  // of the first octave
+    pub lacunarity: f32, // frequency factor between octaves
+    pub gain: f32,       // amplitude factor between octaves
+    pub amplitude: f32,
+    pub offset: [f32; 2], // added to the scaled position, e.g. to scroll the noise
+}
+
+impl Default for NoiseParams {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            octaves: 5,
+            frequency: 1.0,
+            lacunarity: 2.0,
+            gain: 0.5,
+            amplitude: 1.0,
+            offset: [0.0, 0.0],
+        }
+    }
+}
+
+impl NoiseParams {
+    pub fn seeded(seed: u32) -> Self {
+        Self {
+            seed,
+            ..Default::default()
+        }
+    }
+
+    // the same name always gives the same noise, e.g. a terrain per level name
+    pub fn named(name: &str) -> Self {
+        Self::seeded(seed_from_str(name))
+    }
+
+    pub fn fbm2(&self, p: [f32; 2]) -> f32 {
+        let q = [0, 1].map(|k| p[k] * self.frequency + self.offset[k]);
+        self.octave_sum(q, perlin2)
+    }
+
+    pub fn fbm3(&self, p: [f32; 3]) -> f32 {
+        let offset = [self.offset[0], self.offset[1], 0.0];
+        let q = [0, 1, 2].map(|k| p[k] * self.frequency + offset[k]);
+        self.octave_sum(q, perlin3)
+    }
+
+    // the same loop as in the shader, so the CPU and GPU sums match
+    fn octave_sum<const N: usize>(
+        &self,
+        mut q: [f32; N],
+        noise: impl Fn([f32; N], u32) -> f32,
+    ) -> f32 {
+        let (mut amplitude, mut sum, mut norm) = (1.0, 0.0, 0.0);
+        for k in 0..self.octaves {
+            sum += amplitude * noise(q, self.seed.wrapping_add(k));
+            norm += amplitude;
+            q = q.map(|v| v * self.lacunarity);
+            amplitude *= self.gain;
+        }
+        self.amplitude * sum / f32::max(norm, 1e-6)
+    }
+}
+
+// FNV-1a hash of a name, as a seed
+pub fn seed_from_str(name: &str) -> u32 {
+    name.bytes()
+        .fold(2166136261u32, |h, b| (h ^ b as u32).wrapping_mul(16777619))
+}
+
+// a different seed on every call, for noise that should vary between runs
+pub fn random_seed() -> u32 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    pcg((nanos as u32) ^ ((nanos >> 32) as u32))
+}
+// endregion: noise parameters
+
+// region: cpu noise
+// CPU versions of the shader functions with the same hashing and arithmetic.
+fn pcg(v: u32) -> u32 {
+    let state = v.wrapping_mul(747796405).wrapping_add(2891336453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+    (word >> 22) ^ word
+}
+
+fn hash2(i: [i32; 2], seed: u32) -> u32 {
+    pcg(pcg(pcg(seed).wrapping_add(i[0] as u32)).wrapping_add(i[1] as u32))
+}
+
+fn hash3(i: [i32; 3], seed: u32) -> u32 {
+    pcg(hash2([i[0], i[1]], seed).wrapping_add(i[2] as u32))
+}
+
+fn unit(h: u32) -> f32 {
+    (h >> 8) as f32 / 16777216.0
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn mix(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+fn grad2(h: u32) -> [f32; 2] {
+    let sign = |bit: u32| if h & bit != 0 { -1.0 } else { 1.0 };
+    let s = [sign(1), sign(2)];
+    if h & 4 != 0 {
+        [s[0] * FRAC_1_SQRT_2, s[1] * FRAC_1_SQRT_2]
+    } else if h & 8 != 0 {
+        [0.0, s[1]]
+    } else {
+        [s[0], 0.0]
+    }
+}
+
+fn grad3(h: u32, d: [f32; 3]) -> f32 {
+    let k = h & 15;
+    let u = if k < 8 { d[0] } else { d[1] };
+    let v = if k < 4 {
+        d[1]
+    } else if k == 12 || k == 14 {
+        d[0]
+    } else {
+        d[2]
+    };
+    (if k & 1 == 0 { u } else { -u }) + (if k & 2 == 0 { v } else { -v })
+}
+
+fn dot2(a: [f32; 2], b: [f32; 2]) -> f32 {
+    a[0] * b[0] + a[1] * b[1]
+}
+
+// lattice cell and the position inside it
+fn cell<const N: usize>(p: [f32; N]) -> ([i32; N], [f32; N]) {
+    (p.map(|v| v.floor() as i32), p.map(|v| v - v.floor()))
+}
+
+pub fn value2(p: [f32; 2], seed: u32) -> f32 {
+    let ([x, z], f) = cell(p);
+    let v = |dx, dz| unit(hash2([x + dx, z + dz], seed));
+    let u = fade(f[0]);
+    2.0 * mix(
+        mix(v(0, 0), v(1, 0), u),
+        mix(v(0, 1), v(1, 1), u),
+        fade(f[1]),
+    ) - 1.0
+}
+
+pub fn perlin2(p: [f32; 2], seed: u32) -> f32 {
+    let ([x, z], f) = cell(p);
+    let g = |dx: i32, dz: i32| {
+        let d = [f[0] - dx as f32, f[1] - dz as f32];
+        dot2(grad2(hash2([x + dx, z + dz], seed)), d)
+    };
+    let u = fade(f[0]);
+    SQRT_2
+        * mix(
+            mix(g(0, 0), g(1, 0), u),
+            mix(g(0, 1), g(1, 1), u),
+            fade(f[1]),
+        )
+}
+
+pub fn perlin3(p: [f32; 3], seed: u32) -> f32 {
+    let (i, f) = cell(p);
+    let corners: Vec<f32> = (0..8)
+        .map(|k| {
+            let o = [k & 1, (k >> 1) & 1, (k >> 2) & 1];
+            let d = [0, 1, 2].map(|a| f[a] - o[a] as f32);
+            grad3(hash3([0, 1, 2].map(|a| i[a] + o[a]), seed), d)
+        })
+        .collect();
+    let u = f.map(fade);
+    let x = |k: usize| mix(corners[k], corners[k + 1], u[0]);
+    mix(mix(x(0), x(2), u[1]), mix(x(4), x(6), u[1]), u[2])
+}
+
+pub fn simplex2(p: [f32; 2], seed: u32) -> f32 {
+    const F2: f32 = 0.36602542; // (sqrt(3) - 1) / 2
+    const G2: f32 = 0.21132487; // (3 - sqrt(3)) / 6
+    let s = (p[0] + p[1]) * F2;
+    let c = [(p[0] + s).floor(), (p[1] + s).floor()];
+    let i = [c[0] as i32, c[1] as i32];
+    let t = (c[0] + c[1]) * G2;
+    let d0 = [p[0] - c[0] + t, p[1] - c[1] + t];
+    let o = if d0[0] > d0[1] { [1, 0] } else { [0, 1] };
+    let d1 = [d0[0] - o[0] as f32 + G2, d0[1] - o[1] as f32 + G2];
+    let d2 = [d0[0] - 1.0 + 2.0 * G2, d0[1] - 1.0 + 2.0 * G2];
+    let corner = |d: [f32; 2], h: u32| {
+        let t = 0.5 - dot2(d, d);
+        if t <= 0.0 {
+            0.0
+        } else {
+            let t2 = t * t;
+            t2 * t2 * dot2(grad2(h), d)
+        }
+    };
+    99.2 * (corner(d0, hash2(i, seed))
+        + corner(d1, hash2([i[0] + o[0], i[1] + o[1]], seed))
+        + corner(d2, hash2([i[0] + 1, i[1] + 1], seed)))
+}
+// endregion: cpu noise
@@ -0,0 +1,556 @@
+use bytemuck::{Pod, Zeroable};
+use cgmath::{InnerSpace, Vector3};
+
+use super::color;
+use super::noise::{self, NoiseParams};
+use super::shader_hooks::ShaderHooks;
+use super::surface_data::ISurfaceOutput;
+
+// region: tangents
+// A normal map stores normals in tangent space: x along the direction in which u grows
+// on the surface, y along v and z along the normal. ISurfaceOutput::tangents gives that
+// frame at each vertex from the uv derivatives of the triangles around it (Lengyel's
+// method): the tangent in xyz, made orthogonal to the normal, and the handedness in w,
+// so that bitangent = w * cross(normal, tangent). Mirrored uvs give w = -1.
+impl ISurfaceOutput {
+    pub fn tangents(&self) -> Vec<[f32; 4]> {
+        let n = self.positions.len();
+        let mut tan = vec![Vector3::new(0.0f32, 0.0, 0.0); n];
+        let mut bitan = vec![Vector3::new(0.0f32, 0.0, 0.0); n];
+        if self.uvs.len() == n {
+            for tri in self.indices.chunks_exact(3) {
+                let [a, b, c] = [0, 1, 2].map(|k| tri[k] as usize);
+                let p0 = Vector3::from(self.positions[a]);
+                let e1 = Vector3::from(self.positions[b]) - p0;
+                let e2 = Vector3::from(self.positions[c]) - p0;
+                let [u0, v0] = self.uvs[a];
+                let (du1, dv1) = (self.uvs[b][0] - u0, self.uvs[b][1] - v0);
+                let (du2, dv2) = (self.uvs[c][0] - u0, self.uvs[c][1] - v0);
+                let det = du1 * dv2 - du2 * dv1;
+                if det.abs() < 1e-12 {
+                    continue; // no uv area, no direction
+                }
+                let t = (e1 * dv2 - e2 * dv1) / det;
+                let bt = (e2 * du1 - e1 * du2) / det;
+                for i in [a, b, c] {
+                    tan[i] += t;
+                    bitan[i] += bt;
+                }
+            }
+        }
+
+        (0..n)
+            .map(|i| {
+                let normal = Vector3::from(self.normals.get(i).copied().unwrap_or([0.0, 1.0, 0.0]));
+                let t = tan[i] - normal * normal.dot(tan[i]);
+                let t = if t.magnitude2() > 1e-12 {
+                    t.normalize()
+                } else {
+                    any_perpendicular(normal)
+                };
+                let w = if normal.cross(t).dot(bitan[i]) < 0.0 {
+                    -1.0
+                } else {
+                    1.0
+                };
+                [t.x, t.y, t.z, w]
+            })
+            .collect()
+    }
+
+    pub fn bitangents(&self) -> Vec<[f32; 3]> {
+        self.tangents()
+            .iter()
+            .zip(self.normals.iter())
+            .map(|(t, &normal)| {
+                let b = Vector3::from(normal).cross(Vector3::new(t[0], t[1], t[2])) * t[3];
+                b.into()
+            })
+            .collect()
+    }
+}
+
+// for vertices without a uv direction, like the poles of a sphere
+fn any_perpendicular(normal: Vector3<f32>) -> Vector3<f32> {
+    let axis = if normal.x.abs() < 0.9 {
+        Vector3::unit_x()
+    } else {
+        Vector3::unit_y()
+    };
+    let t = axis - normal * normal.dot(axis);
+    if t.magnitude2() > 1e-12 {
+        t.normalize()
+    } else {
+        Vector3::unit_x()
+    }
+}
+// endregion: tangents
+
+// region: tangent vertices
+// The chapter vertex (position, normal, color at locations 0 to 2) with the tangent at
+// location 3 and the uv at location 4, the layout the normal-mapping shaders read.
+// Colors are linear, like those of the chapter vertices.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct TangentVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub color: [f32; 3],
+    pub tangent: [f32; 4],
+    pub uv: [f32; 2],
+}
+
+impl TangentVertex {
+    pub const ATTRIBUTES: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+        0 => Float32x3, 1 => Float32x3, 2 => Float32x3, 3 => Float32x4, 4 => Float32x2
+    ];
+
+    pub fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+// surface vertices of a mesh with its tangents; the indices stay those of the mesh
+pub fn tangent_vertices(data: &ISurfaceOutput) -> Vec<TangentVertex> {
+    let tangents = data.tangents();
+    (0..data.positions.len())
+        .map(|i| TangentVertex {
+            position: data.positions[i],
+            normal: data.normals[i],
+            color: color::to_linear(data.colors[i]),
+            tangent: tangents[i],
+            uv: data.uvs.get(i).copied().unwrap_or_default(),
+        })
+        .collect()
+}
+// endregion: tangent vertices
+
+// region: normal maps
+// Tangent-space normal maps as RGBA8 texels, row by row with v growing down the rows:
+// each normal n is stored as 255 * (0.5 * n + 0.5), so a flat texel is (128, 128, 255).
+// normal_map_from_heights takes the slopes of a height field with central differences
+// that wrap around the edges, so a tileable height field gives a tileable map; strength
+// scales the heights, in texels.
+pub fn normal_map_from_heights(width: u32, height: u32, heights: &[f32], strength: f32) -> Vec<u8> {
+    let (w, h) = (width as usize, height as usize);
+    assert_eq!(heights.len(), w * h, "one height per texel");
+    let at = |x: usize, y: usize| heights[(y % h) * w + x % w];
+    let mut rgba = Vec::with_capacity(4 * w * h);
+    for y in 0..h {
+        for x in 0..w {
+            let dx = 0.5 * (at(x + 1, y) - at(x + w - 1, y));
+            let dy = 0.5 * (at(x, y + 1) - at(x, y + h - 1));
+            let n = Vector3::new(-strength * dx, -strength * dy, 1.0).normalize();
+            rgba.extend([n.x, n.y, n.z].map(|c| (127.5 * (c + 1.0)).round() as u8));
+            rgba.push(255);
+        }
+    }
+    rgba
+}
+
+// Heights of fractal noise over a size x size tile that wraps around: four samples
+// shifted by the tile size are blended, each weighted by how far it is from its seam.
+pub fn tileable_heights(size: u32, noise: &NoiseParams) -> Vec<f32> {
+    let s = size as f32;
+    (0..size * size)
+        .map(|k| {
+            let (x, y) = ((k % size) as f32, (k / size) as f32);
+            let (fx, fy) = (x / s, y / s);
+            let f = |dx: f32, dy: f32| noise.fbm2([(x - dx) / s, (y - dy) / s]);
+            (1.0 - fx) * (1.0 - fy) * f(0.0, 0.0)
+                + fx * (1.0 - fy) * f(s, 0.0)
+                + (1.0 - fx) * fy * f(0.0, s)
+                + fx * fy * f(s, s)
+        })
+        .collect()
+}
+
+// a procedural detail map: tileable noise turned into normals
+pub fn detail_normal_map(size: u32, noise: &NoiseParams, strength: f32) -> Vec<u8> {
+    normal_map_from_heights(size, size, &tileable_heights(size, noise), strength)
+}
+
+// A normal map on the GPU with the bind group of NORMAL_MAP_FRAG_WGSL (group 2): the
+// texture repeats, tiling is how many times it does across the uv range of the mesh
+// and strength scales the tilt of the mapped normals, 0 giving the plain mesh normals.
+//
+//     let normal_map = NormalMap::new(device, queue, 256, 256, &detail_normal_map(..));
+//     render_pass.set_bind_group(2, &normal_map.bind_group, &[]);
+pub const NORMAL_MAP_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+pub const NORMAL_MAP_UNIFORM_SIZE: wgpu::BufferAddress = 16;
+
+pub struct NormalMap {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    pub uniform_buffer: wgpu::Buffer, // the parameters below
+    pub layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+    pub strength: f32,
+    pub tiling: f32,
+}
+
+impl NormalMap {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        width: u32,
+        height: u32,
+        rgba: &[u8],
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Normal Map Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: NORMAL_MAP_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Normal Map Sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Normal Map Uniform Buffer"),
+            size: NORMAL_MAP_UNIFORM_SIZE,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let (layout, bind_group) =
+            create_normal_map_bind_group(device, &view, &sampler, &uniform_buffer);
+        let normal_map = Self {
+            texture,
+            view,
+            sampler,
+            uniform_buffer,
+            layout,
+            bind_group,
+            strength: 1.0,
+            tiling: 1.0,
+        };
+        normal_map.write_texture(queue, rgba);
+        normal_map.write(queue);
+        normal_map
+    }
+
+    // the NormalMapUniforms of NORMAL_MAP_FRAG_WGSL
+    pub fn uniform_data(&self) -> [f32; 4] {
+        [self.strength, self.tiling, 0.0, 0.0]
+    }
+
+    // uploads the parameters after strength or tiling changed
+    pub fn write(&self, queue: &wgpu::Queue) {
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&self.uniform_data()),
+        );
+    }
+
+    // replaces the texels; rgba has the size the map was created with
+    pub fn write_texture(&self, queue: &wgpu::Queue, rgba: &[u8]) {
+        queue.write_texture(
+            self.texture.as_image_copy(),
+            rgba,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * self.texture.width()),
+                rows_per_image: None,
+            },
+            self.texture.size(),
+        );
+    }
+}
+
+// binding 0: the normal map, binding 1: its sampler, binding 2: the normal map
+// uniforms; all for the fragment stage
+pub fn create_normal_map_bind_group(
+    device: &wgpu::Device,
+    view: &wgpu::TextureView,
+    sampler: &wgpu::Sampler,
+    uniform_buffer: &wgpu::Buffer,
+) -> (wgpu::BindGroupLayout, wgpu::BindGroup) {
+    let visibility = wgpu::ShaderStages::FRAGMENT;
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Normal Map Bind Group Layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Normal Map Bind Group"),
+        layout: &layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+        ],
+    });
+    (layout, bind_group)
+}
+// endregion: normal maps
+
+// region: micro bumps
+// Procedural detail for close-up views: the fragment shaders tilt the normal by the
+// gradient of two octaves of 3D Perlin noise (noise::NOISE_WGSL) at the world
+// position, so zoomed-in surfaces are not unnaturally smooth, without uvs, tangents
+// or textures. The bumps fade out between fade_near and fade_far from the eye, where
+// they would only alias. MicroBumps is the uniform at binding 0 of its group, and
+// hooks() splices the noise and the perturbation into any chapter fragment shader at
+// its fragment_declarations and fragment_normal hook points:
+//
+//     let fs_source = MicroBumps::hooks(2).apply(MaterialKind::Phong.fragment_source());
+//     queue.write_buffer(&bumps_buffer, 0, cast_slice(&bumps.uniform_data()));
+//
+// A strength of 0 turns them off without another pipeline.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MicroBumps {
+    pub strength: f32,  // tilt of the normal, about the slope of the bumps
+    pub frequency: f32, // bumps per world unit
+    pub fade_near: f32, // full strength up to this distance from the eye
+    pub fade_far: f32,  // none from this distance on
+}
+
+pub const MICRO_BUMPS_UNIFORM_SIZE: wgpu::BufferAddress = 16;
+
+impl Default for MicroBumps {
+    fn default() -> Self {
+        Self {
+            strength: 0.4,
+            frequency: 40.0,
+            fade_near: 0.5,
+            fade_far: 2.0,
+        }
+    }
+}
+
+impl MicroBumps {
+    // the MicroBumpUniforms of hooks()
+    pub fn uniform_data(&self) -> [f32; 4] {
+        [self.strength, self.frequency, self.fade_near, self.fade_far]
+    }
+
+    // the fraction of the strength at a distance from the eye, as the shader has it
+    pub fn fade(&self, distance: f32) -> f32 {
+        let range = (self.fade_far - self.fade_near).max(1e-6);
+        let t = ((distance - self.fade_near) / range).clamp(0.0, 1.0);
+        1.0 - t * t * (3.0 - 2.0 * t)
+    }
+
+    // the shader hooks with the uniform at the given group
+    pub fn hooks(group: u32) -> ShaderHooks {
+        ShaderHooks::default()
+            .snippet("fragment_declarations", &micro_bumps_wgsl(group))
+            .snippet(
+                "fragment_normal",
+                "N = micro_bump_normal(N, in.vPosition.xyz, length(light.eyePosition.xyz - in.vPosition.xyz));",
+            )
+    }
+}
+
+// the noise library, the uniform and micro_bump_normal()
+pub fn micro_bumps_wgsl(group: u32) -> String {
+    format!(
+        r#"{}
+struct MicroBumpUniforms {{
+    strength: f32,
+    frequency: f32,
+    fadeNear: f32,
+    fadeFar: f32,
+}}
+@group({group}) @binding(0) var<uniform> microBumps : MicroBumpUniforms;
+
+// central differences of 3D Perlin noise
+fn micro_bump_gradient(p: vec3f, seed: u32) -> vec3f {{
+    let h = 0.1;
+    let dx = noise_perlin3(p + vec3f(h, 0.0, 0.0), seed) - noise_perlin3(p - vec3f(h, 0.0, 0.0), seed);
+    let dy = noise_perlin3(p + vec3f(0.0, h, 0.0), seed) - noise_perlin3(p - vec3f(0.0, h, 0.0), seed);
+    let dz = noise_perlin3(p + vec3f(0.0, 0.0, h), seed) - noise_perlin3(p - vec3f(0.0, 0.0, h), seed);
+    return vec3f(dx, dy, dz) / (2.0 * h);
+}}
+
+fn micro_bump_normal(N: vec3f, worldPos: vec3f, distance: f32) -> vec3f {{
+    let fade = 1.0 - smoothstep(microBumps.fadeNear, microBumps.fadeFar, distance);
+    if fade <= 0.0 || microBumps.strength == 0.0 {{
+        return N;
+    }}
+    let p = worldPos * microBumps.frequency;
+    var g = micro_bump_gradient(p, 0u) + 0.5 * micro_bump_gradient(2.0 * p, 1u);
+    g -= N * dot(N, g);
+    return normalize(N - microBumps.strength * fade * g);
+}}
+"#,
+        noise::NOISE_WGSL
+    )
+}
+// endregion: micro bumps
+
+// region: shaders
+// The normal-mapping variant of the chapter shaders, for TangentVertex buffers: the
+// same vertex uniforms at group 0 and light and material uniforms at group 1 as
+// shader_vert.wgsl and directional_frag.wgsl, with the normal map at group 2. The
+// tangent follows the model matrix, and the fragment shader tilts the interpolated
+// normal by the mapped one before the Blinn-Phong shading.
+pub const NORMAL_MAP_VERT_WGSL: &str = r#"
+struct Uniforms {
+    vpMat : mat4x4f,
+    modelMat : mat4x4f,
+    normalMat : mat4x4f,
+};
+@binding(0) @group(0) var<uniform> uniforms : Uniforms;
+
+struct Input {
+    @location(0) position: vec4f,
+    @location(1) normal: vec4f,
+    @location(2) color: vec4f,
+    @location(3) tangent: vec4f,
+    @location(4) uv: vec2f,
+}
+
+struct Output {
+    @builtin(position) position : vec4f,
+    @location(0) vPosition : vec4f,
+    @location(1) vNormal : vec4f,
+    @location(2) vColor: vec4f,
+    @location(3) vTangent: vec4f,
+    @location(4) vUv: vec2f,
+};
+
+// @hook vertex_declarations
+
+@vertex
+fn vs_main(in:Input) -> Output {
+    var output: Output;
+    var position = in.position;
+    var normal = in.normal;
+    var color = in.color;
+    // @hook vertex
+    let mPosition = uniforms.modelMat * position;
+    output.vPosition = mPosition;
+    output.vNormal = uniforms.normalMat * normal;
+    output.position = uniforms.vpMat * mPosition;
+    output.vColor = color;
+    output.vTangent = vec4f((uniforms.modelMat * vec4f(in.tangent.xyz, 0.0)).xyz, in.tangent.w);
+    output.vUv = in.uv;
+    return output;
+}
+"#;
+
+pub const NORMAL_MAP_FRAG_WGSL: &str = r#"
+struct LightUniforms {
+    lightDirection : vec4f,
+    eyePosition : vec4f,
+    specularColor : vec4f,
+}
+@group(1) @binding(0) var<uniform> light : LightUniforms;
+
+struct MaterialUniforms {
+    ambient: f32,
+    diffuse: f32,
+    specular: f32,
+    shininess: f32,
+}
+@group(1) @binding(1) var<uniform> material : MaterialUniforms;
+
+struct NormalMapUniforms {
+    strength: f32,
+    tiling: f32,
+}
+@group(2) @binding(0) var normalMap : texture_2d<f32>;
+@group(2) @binding(1) var normalSampler : sampler;
+@group(2) @binding(2) var<uniform> normalParams : NormalMapUniforms;
+
+struct Input {
+    @location(0) vPosition:vec4f,
+    @location(1) vNormal:vec4f,
+    @location(2) vColor: vec4f,
+    @location(3) vTangent: vec4f,
+    @location(4) vUv: vec2f,
+}
+
+fn blinnPhong(N:vec3f, L:vec3f, V:vec3f) -> vec2f{
+    let H = normalize(L + V);
+    var diffuse = material.diffuse * max(dot(N, L), 0.0);
+    diffuse += material.diffuse * max(dot(-N, L), 0.0);
+    var specular = material.specular * pow(max(dot(N, H), 0.0), material.shininess);
+    specular += material.specular * pow(max(dot(-N, H),0.0), material.shininess);
+    return vec2(diffuse, specular);
+}
+
+// the normal of the map in the tangent frame of the fragment
+fn mapped_normal(in: Input) -> vec3f {
+    let N = normalize(in.vNormal.xyz);
+    let T = normalize(in.vTangent.xyz - N * dot(N, in.vTangent.xyz));
+    let B = in.vTangent.w * cross(N, T);
+    var m = textureSample(normalMap, normalSampler, in.vUv * normalParams.tiling).xyz * 2.0 - 1.0;
+    m = vec3f(m.xy * normalParams.strength, m.z);
+    return normalize(m.x * T + m.y * B + m.z * N);
+}
+
+// @hook fragment_declarations
+
+@fragment
+fn fs_main(in:Input) ->  @location(0) vec4f {
+    var N = mapped_normal(in);
+    let L = normalize(-light.lightDirection.xyz);
+    let V = normalize(light.eyePosition.xyz - in.vPosition.xyz);
+    // @hook fragment_normal
+
+    let bp = blinnPhong(N, L, V);
+    let finalColor = in.vColor * (material.ambient + bp[0]) + light.specularColor * bp[1];
+
+    var color = vec4<f32>(finalColor.rgb, 1.0);
+    // @hook fragment
+    return color;
+}
+"#;
+// endregion: shaders
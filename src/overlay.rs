@@ -0,0 +1,364 @@
+use super::labels::NumberFormat;
+use super::theme::Theme;
+use super::wgpu_simplified::{FpsCounter, FrameStats};
+
+// region: stroke text
+// Text as line segments, for labels drawn with a gizmo::LineRenderer without a font
+// texture: digits, upper case letters (lower case ones are drawn upper case) and
+// . , : / - %, each on a 4 x 6 cell with y up from its bottom left corner; other
+// characters are blank. A glyph and the gap after it are as wide as it is tall.
+fn glyph(c: char) -> &'static [[f32; 4]] {
+    match c.to_ascii_uppercase() {
+        'A' => &[
+            [0.0, 0.0, 0.0, 4.0],
+            [0.0, 4.0, 2.0, 6.0],
+            [2.0, 6.0, 4.0, 4.0],
+            [4.0, 4.0, 4.0, 0.0],
+            [0.0, 3.0, 4.0, 3.0],
+        ],
+        'B' => &[
+            [0.0, 0.0, 0.0, 6.0],
+            [0.0, 6.0, 3.0, 6.0],
+            [3.0, 6.0, 4.0, 5.0],
+            [4.0, 5.0, 4.0, 4.0],
+            [4.0, 4.0, 3.0, 3.0],
+            [0.0, 3.0, 3.0, 3.0],
+            [3.0, 3.0, 4.0, 2.0],
+            [4.0, 2.0, 4.0, 1.0],
+            [4.0, 1.0, 3.0, 0.0],
+            [3.0, 0.0, 0.0, 0.0],
+        ],
+        'C' => &[
+            [4.0, 6.0, 0.0, 6.0],
+            [0.0, 6.0, 0.0, 0.0],
+            [0.0, 0.0, 4.0, 0.0],
+        ],
+        'D' => &[
+            [0.0, 0.0, 0.0, 6.0],
+            [0.0, 6.0, 3.0, 6.0],
+            [3.0, 6.0, 4.0, 5.0],
+            [4.0, 5.0, 4.0, 1.0],
+            [4.0, 1.0, 3.0, 0.0],
+            [3.0, 0.0, 0.0, 0.0],
+        ],
+        'E' => &[
+            [4.0, 6.0, 0.0, 6.0],
+            [0.0, 6.0, 0.0, 0.0],
+            [0.0, 0.0, 4.0, 0.0],
+            [0.0, 3.0, 3.0, 3.0],
+        ],
+        'F' => &[
+            [4.0, 6.0, 0.0, 6.0],
+            [0.0, 6.0, 0.0, 0.0],
+            [0.0, 3.0, 3.0, 3.0],
+        ],
+        'G' => &[
+            [4.0, 6.0, 0.0, 6.0],
+            [0.0, 6.0, 0.0, 0.0],
+            [0.0, 0.0, 4.0, 0.0],
+            [4.0, 0.0, 4.0, 3.0],
+            [4.0, 3.0, 2.0, 3.0],
+        ],
+        'H' => &[
+            [0.0, 0.0, 0.0, 6.0],
+            [4.0, 0.0, 4.0, 6.0],
+            [0.0, 3.0, 4.0, 3.0],
+        ],
+        'I' => &[
+            [0.0, 6.0, 4.0, 6.0],
+            [2.0, 6.0, 2.0, 0.0],
+            [0.0, 0.0, 4.0, 0.0],
+        ],
+        'J' => &[
+            [4.0, 6.0, 4.0, 0.0],
+            [4.0, 0.0, 0.0, 0.0],
+            [0.0, 0.0, 0.0, 2.0],
+        ],
+        'K' => &[
+            [0.0, 0.0, 0.0, 6.0],
+            [4.0, 6.0, 0.0, 3.0],
+            [0.0, 3.0, 4.0, 0.0],
+        ],
+        'L' => &[[0.0, 6.0, 0.0, 0.0], [0.0, 0.0, 4.0, 0.0]],
+        'M' => &[
+            [0.0, 0.0, 0.0, 6.0],
+            [0.0, 6.0, 2.0, 3.0],
+            [2.0, 3.0, 4.0, 6.0],
+            [4.0, 6.0, 4.0, 0.0],
+        ],
+        'N' => &[
+            [0.0, 0.0, 0.0, 6.0],
+            [0.0, 6.0, 4.0, 0.0],
+            [4.0, 0.0, 4.0, 6.0],
+        ],
+        'O' => &[
+            [0.0, 0.0, 0.0, 6.0],
+            [0.0, 6.0, 4.0, 6.0],
+            [4.0, 6.0, 4.0, 0.0],
+            [4.0, 0.0, 0.0, 0.0],
+        ],
+        'P' => &[
+            [0.0, 0.0, 0.0, 6.0],
+            [0.0, 6.0, 4.0, 6.0],
+            [4.0, 6.0, 4.0, 3.0],
+            [4.0, 3.0, 0.0, 3.0],
+        ],
+        'Q' => &[
+            [0.0, 0.0, 0.0, 6.0],
+            [0.0, 6.0, 4.0, 6.0],
+            [4.0, 6.0, 4.0, 0.0],
+            [4.0, 0.0, 0.0, 0.0],
+            [2.0, 2.0, 4.0, 0.0],
+        ],
+        'R' => &[
+            [0.0, 0.0, 0.0, 6.0],
+            [0.0, 6.0, 4.0, 6.0],
+            [4.0, 6.0, 4.0, 3.0],
+            [4.0, 3.0, 0.0, 3.0],
+            [2.0, 3.0, 4.0, 0.0],
+        ],
+        'S' | '5' => &[
+            [4.0, 6.0, 0.0, 6.0],
+            [0.0, 6.0, 0.0, 3.0],
+            [0.0, 3.0, 4.0, 3.0],
+            [4.0, 3.0, 4.0, 0.0],
+            [4.0, 0.0, 0.0, 0.0],
+        ],
+        'T' => &[[0.0, 6.0, 4.0, 6.0], [2.0, 6.0, 2.0, 0.0]],
+        'U' => &[
+            [0.0, 6.0, 0.0, 0.0],
+            [0.0, 0.0, 4.0, 0.0],
+            [4.0, 0.0, 4.0, 6.0],
+        ],
+        'V' => &[[0.0, 6.0, 2.0, 0.0], [2.0, 0.0, 4.0, 6.0]],
+        'W' => &[
+            [0.0, 6.0, 1.0, 0.0],
+            [1.0, 0.0, 2.0, 3.0],
+            [2.0, 3.0, 3.0, 0.0],
+            [3.0, 0.0, 4.0, 6.0],
+        ],
+        'X' => &[[0.0, 0.0, 4.0, 6.0], [0.0, 6.0, 4.0, 0.0]],
+        'Y' => &[
+            [0.0, 6.0, 2.0, 3.0],
+            [4.0, 6.0, 2.0, 3.0],
+            [2.0, 3.0, 2.0, 0.0],
+        ],
+        'Z' => &[
+            [0.0, 6.0, 4.0, 6.0],
+            [4.0, 6.0, 0.0, 0.0],
+            [0.0, 0.0, 4.0, 0.0],
+        ],
+        // the zero is slashed, to tell it from the O
+        '0' => &[
+            [0.0, 0.0, 0.0, 6.0],
+            [0.0, 6.0, 4.0, 6.0],
+            [4.0, 6.0, 4.0, 0.0],
+            [4.0, 0.0, 0.0, 0.0],
+            [0.0, 0.0, 4.0, 6.0],
+        ],
+        '1' => &[
+            [2.0, 0.0, 2.0, 6.0],
+            [2.0, 6.0, 1.0, 5.0],
+            [1.0, 0.0, 3.0, 0.0],
+        ],
+        '2' => &[
+            [0.0, 6.0, 4.0, 6.0],
+            [4.0, 6.0, 4.0, 3.0],
+            [4.0, 3.0, 0.0, 3.0],
+            [0.0, 3.0, 0.0, 0.0],
+            [0.0, 0.0, 4.0, 0.0],
+        ],
+        '3' => &[
+            [0.0, 6.0, 4.0, 6.0],
+            [4.0, 6.0, 4.0, 0.0],
+            [4.0, 0.0, 0.0, 0.0],
+            [1.0, 3.0, 4.0, 3.0],
+        ],
+        '4' => &[
+            [0.0, 6.0, 0.0, 3.0],
+            [0.0, 3.0, 4.0, 3.0],
+            [4.0, 6.0, 4.0, 0.0],
+        ],
+        '6' => &[
+            [4.0, 6.0, 0.0, 6.0],
+            [0.0, 6.0, 0.0, 0.0],
+            [0.0, 0.0, 4.0, 0.0],
+            [4.0, 0.0, 4.0, 3.0],
+            [4.0, 3.0, 0.0, 3.0],
+        ],
+        '7' => &[[0.0, 6.0, 4.0, 6.0], [4.0, 6.0, 4.0, 0.0]],
+        '8' => &[
+            [0.0, 0.0, 0.0, 6.0],
+            [0.0, 6.0, 4.0, 6.0],
+            [4.0, 6.0, 4.0, 0.0],
+            [4.0, 0.0, 0.0, 0.0],
+            [0.0, 3.0, 4.0, 3.0],
+        ],
+        '9' => &[
+            [4.0, 3.0, 0.0, 3.0],
+            [0.0, 3.0, 0.0, 6.0],
+            [0.0, 6.0, 4.0, 6.0],
+            [4.0, 6.0, 4.0, 0.0],
+            [4.0, 0.0, 0.0, 0.0],
+        ],
+        '.' => &[[2.0, 0.0, 2.0, 1.0]],
+        ',' => &[[2.0, 1.0, 1.0, -1.0]],
+        ':' => &[[2.0, 1.0, 2.0, 2.0], [2.0, 4.0, 2.0, 5.0]],
+        '/' => &[[0.0, 0.0, 4.0, 6.0]],
+        '-' => &[[1.0, 3.0, 3.0, 3.0]],
+        '%' => &[
+            [0.0, 0.0, 4.0, 6.0],
+            [0.0, 5.0, 1.0, 6.0],
+            [3.0, 0.0, 4.0, 1.0],
+        ],
+        _ => &[],
+    }
+}
+
+// segments of text in pixels with y down, from the top left corner of its first line
+// at origin, with glyphs height pixels tall; '\n' starts a new line, 1.5 heights down
+pub fn text_segments(text: &str, origin: [f32; 2], height: f32) -> Vec<[[f32; 2]; 2]> {
+    let scale = height / 6.0;
+    let mut segments = vec![];
+    for (row, line) in text.lines().enumerate() {
+        let bottom = origin[1] + height + 1.5 * height * row as f32;
+        for (column, c) in line.chars().enumerate() {
+            let left = origin[0] + height * column as f32;
+            segments.extend(glyph(c).iter().map(|&[x0, y0, x1, y1]| {
+                [
+                    [left + scale * x0, bottom - scale * y0],
+                    [left + scale * x1, bottom - scale * y1],
+                ]
+            }));
+        }
+    }
+    segments
+}
+// endregion: stroke text
+
+// region: stats overlay
+// Frame statistics drawn over the scene instead of printed: the frame rate, the last,
+// mean and worst frame times, the vertex and draw call counts, the resolution and,
+// with a ws::GpuTimer, the GPU time of the timed passes as
+// stroke text in the top left corner, above a graph of the last FpsCounter::HISTORY
+// frame times with lines at the 60 and 30 fps budgets. lines() gives them as clip
+// space line list pairs at the near plane, so the depth test keeps them in front of
+// the scene, for a gizmo::LineRenderer written with the identity matrix and drawn
+// last:
+//
+//     self.fps_counter.tick();
+//     let lines = self.stats_overlay.lines(&self.fps_counter, &scene, size);
+//     self.overlay_lines.write(&device, &queue, &Matrix4::identity(), &lines);
+pub const STATS_KEY: &str = "x";
+
+// what the frame drew, counted by the application
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SceneStats {
+    pub vertices: usize,
+    pub draw_calls: u32,
+    pub gpu_ms: Option<f32>, // sum of the passes a GpuTimer read for the frame
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StatsOverlay {
+    pub origin: [f32; 2],     // top left corner, in pixels
+    pub text_height: f32,     // in pixels
+    pub graph_size: [f32; 2], // in pixels
+    pub graph_ms: f32,        // frame time at the top of the graph, longer ones clamp
+    pub color: [f32; 3],
+    pub graph_color: [f32; 3],
+    pub budget_color: [f32; 3],
+    pub number_format: NumberFormat, // of the frame times, with one decimal
+}
+
+impl Default for StatsOverlay {
+    fn default() -> Self {
+        Self::themed(Theme::Standard, 1.0)
+    }
+}
+
+impl StatsOverlay {
+    // the colors and text size of the theme, with every size times the ui scale
+    pub fn themed(theme: Theme, ui_scale: f32) -> Self {
+        let palette = theme.palette();
+        Self {
+            origin: [12.0 * ui_scale; 2],
+            text_height: 12.0 * theme.text_scale() * ui_scale,
+            graph_size: [240.0 * ui_scale, 60.0 * ui_scale],
+            graph_ms: 50.0,
+            color: palette.text,
+            graph_color: palette.graph,
+            budget_color: palette.warning,
+            number_format: NumberFormat::default(),
+        }
+    }
+
+    pub fn text(&self, stats: &FrameStats, scene: &SceneStats, size: [u32; 2]) -> String {
+        let ms = self.number_format.decimals(1);
+        let mut text = format!(
+            "FPS {}\nFRAME {} MS  MEAN {}  MAX {}\nVERTICES {}\nDRAW CALLS {}\n\
+             RESOLUTION {}X{}",
+            stats.fps,
+            ms.format(stats.last_ms),
+            ms.format(stats.mean_ms),
+            ms.format(stats.max_ms),
+            scene.vertices,
+            scene.draw_calls,
+            size[0],
+            size[1]
+        );
+        if let Some(gpu_ms) = scene.gpu_ms {
+            text.push_str(&format!("\nGPU {} MS", self.number_format.decimals(2).format(gpu_ms)));
+        }
+        text
+    }
+
+    // text, graph frame, budget lines and frame time graph, for a target of size pixels
+    pub fn lines(
+        &self,
+        fps: &FpsCounter,
+        scene: &SceneStats,
+        size: [u32; 2],
+    ) -> Vec<([f32; 3], [f32; 3])> {
+        let text = self.text(&fps.stats(), scene, size);
+        let [w, h] = size.map(|n| n.max(1) as f32);
+        let mut lines = vec![];
+        let mut line = |a: [f32; 2], b: [f32; 2], color: [f32; 3]| {
+            lines.push(([2.0 * a[0] / w - 1.0, 1.0 - 2.0 * a[1] / h, 0.0], color));
+            lines.push(([2.0 * b[0] / w - 1.0, 1.0 - 2.0 * b[1] / h, 0.0], color));
+        };
+        for [a, b] in text_segments(&text, self.origin, self.text_height) {
+            line(a, b, self.color);
+        }
+
+        let rows = text.lines().count() as f32;
+        let [gw, gh] = self.graph_size;
+        let x0 = self.origin[0];
+        let y0 = self.origin[1] + 1.5 * self.text_height * rows;
+        let (x1, y1) = (x0 + gw, y0 + gh);
+        let y_of = |ms: f32| y1 - gh * (ms / self.graph_ms).clamp(0.0, 1.0);
+        let dim = self.color.map(|c| 0.45 * c);
+        let corners = [[x0, y0], [x1, y0], [x1, y1], [x0, y1]];
+        for k in 0..4 {
+            line(corners[k], corners[(k + 1) % 4], dim);
+        }
+        for budget in [1000.0 / 60.0, 1000.0 / 30.0] {
+            if budget < self.graph_ms {
+                line([x0, y_of(budget)], [x1, y_of(budget)], self.budget_color);
+            }
+        }
+        // the newest frame at the right edge
+        let step = gw / (FpsCounter::HISTORY - 1) as f32;
+        let start = x1 - step * (fps.frame_times().len() as f32 - 1.0);
+        let points: Vec<[f32; 2]> = fps
+            .frame_times()
+            .enumerate()
+            .map(|(i, ms)| [start + step * i as f32, y_of(ms)])
+            .collect();
+        for pair in points.windows(2) {
+            line(pair[0], pair[1], self.graph_color);
+        }
+        lines
+    }
+}
+// endregion: stats overlay
@@ -0,0 +1,281 @@
+use super::material::{Material, MaterialKind};
+use super::surface_data::{IParametricSurface, ISimpleSurface};
+
+// region: parameter set
+// A numeric surface field exposed as a slider: a UI front end (keybindings, an
+// overlay) only needs the name, range and step and never touches the surface
+// struct directly. The setter returns true if the vertex count changed, i.e. the
+// buffers have to be recreated instead of just rewritten.
+pub struct Param<S> {
+    pub name: String,
+    pub range: [f32; 2],
+    pub step: f32,
+    get: Box<dyn Fn(&S) -> f32>,
+    #[allow(clippy::type_complexity)]
+    set: Box<dyn Fn(&mut S, f32) -> bool>,
+}
+
+impl<S> Param<S> {
+    pub fn get(&self, target: &S) -> f32 {
+        (self.get)(target)
+    }
+
+    // clamps the value into the parameter range before writing it
+    pub fn set(&self, target: &mut S, value: f32) -> bool {
+        (self.set)(target, value.clamp(self.range[0], self.range[1]))
+    }
+
+    // moves the value by the given number of steps
+    pub fn nudge(&self, target: &mut S, steps: f32) -> bool {
+        let value = self.get(target) + steps * self.step;
+        self.set(target, value)
+    }
+
+    // position of the current value within the range, in [0, 1]
+    pub fn fraction(&self, target: &S) -> f32 {
+        let [min, max] = self.range;
+        if max > min {
+            ((self.get(target) - min) / (max - min)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+pub struct ParamSet<S> {
+    pub params: Vec<Param<S>>,
+    pub selected: usize,
+}
+
+impl<S> Default for ParamSet<S> {
+    fn default() -> Self {
+        Self {
+            params: vec![],
+            selected: 0,
+        }
+    }
+}
+
+impl<S> ParamSet<S> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(
+        mut self,
+        name: &str,
+        range: [f32; 2],
+        step: f32,
+        get: impl Fn(&S) -> f32 + 'static,
+        set: impl Fn(&mut S, f32) -> bool + 'static,
+    ) -> Self {
+        self.params.push(Param {
+            name: name.to_string(),
+            range,
+            step,
+            get: Box::new(get),
+            set: Box::new(set),
+        });
+        self
+    }
+
+    pub fn selected(&self) -> Option<&Param<S>> {
+        self.params.get(self.selected)
+    }
+
+    pub fn select_next(&mut self) {
+        if !self.params.is_empty() {
+            self.selected = (self.selected + 1) % self.params.len();
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if !self.params.is_empty() {
+            self.selected = (self.selected + self.params.len() - 1) % self.params.len();
+        }
+    }
+
+    // nudges the selected parameter; returns true if the buffers have to be recreated
+    pub fn nudge_selected(&self, target: &mut S, steps: f32) -> bool {
+        self.selected()
+            .is_some_and(|param| param.nudge(target, steps))
+    }
+
+    // text slider of the selected parameter, e.g. "scale [#####-----] 2.50"
+    pub fn describe(&self, target: &S, width: usize) -> String {
+        match self.selected() {
+            Some(param) => {
+                let filled = (param.fraction(target) * width as f32).round() as usize;
+                format!(
+                    "{} [{}{}] {:.2}",
+                    param.name,
+                    "#".repeat(filled),
+                    "-".repeat(width - filled),
+                    param.get(target)
+                )
+            }
+            None => String::new(),
+        }
+    }
+}
+// endregion: parameter set
+
+// region: surface parameters
+fn set_resolution(field: &mut u16, value: f32) -> bool {
+    let old = *field;
+    *field = value.round() as u16;
+    old != *field
+}
+
+pub fn parametric_params() -> ParamSet<IParametricSurface> {
+    ParamSet::<IParametricSurface>::new()
+        .add(
+            "scale",
+            [0.5, 8.0],
+            0.25,
+            |s| s.scale,
+            |s, v| {
+                s.scale = v;
+                false
+            },
+        )
+        .add(
+            "u resolution",
+            [8.0, 250.0],
+            4.0,
+            |s| s.u_resolution as f32,
+            |s, v| set_resolution(&mut s.u_resolution, v),
+        )
+        .add(
+            "v resolution",
+            [8.0, 250.0],
+            4.0,
+            |s| s.v_resolution as f32,
+            |s, v| set_resolution(&mut s.v_resolution, v),
+        )
+        .add(
+            "knot p",
+            [1.0, 9.0],
+            1.0,
+            |s| s.knot_p as f32,
+            |s, v| {
+                s.knot_p = v.round() as u32;
+                false
+            },
+        )
+        .add(
+            "knot q",
+            [1.0, 9.0],
+            1.0,
+            |s| s.knot_q as f32,
+            |s, v| {
+                s.knot_q = v.round() as u32;
+                false
+            },
+        )
+        .add(
+            "tube radius",
+            [0.05, 0.8],
+            0.05,
+            |s| s.tube_radius,
+            |s, v| {
+                s.tube_radius = v;
+                false
+            },
+        )
+        .add(
+            "deformation",
+            [0.0, 0.5 * std::f32::consts::PI],
+            0.05,
+            |s| s.deformation,
+            |s, v| {
+                s.deformation = v;
+                false
+            },
+        )
+}
+
+pub fn simple_params() -> ParamSet<ISimpleSurface> {
+    ParamSet::<ISimpleSurface>::new()
+        .add(
+            "scale",
+            [0.5, 8.0],
+            0.25,
+            |s| s.scale,
+            |s, v| {
+                s.scale = v;
+                false
+            },
+        )
+        .add(
+            "aspect ratio",
+            [0.1, 2.0],
+            0.05,
+            |s| s.aspect_ratio,
+            |s, v| {
+                s.aspect_ratio = v;
+                false
+            },
+        )
+        .add(
+            "x resolution",
+            [8.0, 250.0],
+            4.0,
+            |s| s.x_resolution as f32,
+            |s, v| set_resolution(&mut s.x_resolution, v),
+        )
+        .add(
+            "z resolution",
+            [8.0, 250.0],
+            4.0,
+            |s| s.z_resolution as f32,
+            |s, v| set_resolution(&mut s.z_resolution, v),
+        )
+}
+// endregion: surface parameters
+
+// region: material parameters
+// sliders for the values of a material of the kind, in uniform_data() order; the
+// values only go to the material uniform buffer (appearance::Appearance::set_material)
+pub fn material_params(kind: MaterialKind) -> ParamSet<Material> {
+    let unit = ([0.0, 1.0], 0.05);
+    let exponent = ([1.0, 128.0], 4.0);
+    let fields = match kind {
+        MaterialKind::Phong => [
+            ("ambient", unit),
+            ("diffuse", unit),
+            ("specular", unit),
+            ("shininess", exponent),
+        ],
+        MaterialKind::Pbr => [
+            ("metallic", unit),
+            ("roughness", unit),
+            ("ambient", unit),
+            ("reflectance", unit),
+        ],
+        MaterialKind::Matcap => [
+            ("ambient", unit),
+            ("highlight", unit),
+            ("rim", unit),
+            ("sharpness", exponent),
+        ],
+    };
+    fields
+        .into_iter()
+        .enumerate()
+        .fold(ParamSet::new(), |params, (k, (name, (range, step)))| {
+            params.add(
+                name,
+                range,
+                step,
+                move |m| m.uniform_data()[k],
+                move |m, v| {
+                    let mut data = m.uniform_data();
+                    data[k] = v;
+                    *m = Material::from_uniform_data(m.kind(), data);
+                    false
+                },
+            )
+        })
+}
+// endregion: material parameters
@@ -0,0 +1,261 @@
+use super::surface_data::IStreamingSurface;
+
+// region: pde solver
+// Explicit finite-difference solver for the 2D heat and wave equations on the
+// square [-1, 1] x [-1, 1], sampled on the same rows x cols grid as a streaming
+// surface. The boundary is held at zero (Dirichlet).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PdeKind {
+    #[default]
+    Heat, // u_t = diffusivity * laplace(u)
+    Wave, // u_tt = wave_speed^2 * laplace(u) - damping * u_t
+}
+
+pub struct PdeSolver {
+    pub kind: PdeKind,
+    pub rows: u16,
+    pub cols: u16,
+    pub diffusivity: f32,
+    pub wave_speed: f32,
+    pub damping: f32,
+    pub max_steps: u32, // upper bound of solver steps per advance call
+    pub time: f32,
+    pub u: Vec<f32>,
+    u_prev: Vec<f32>,
+    laplace: Vec<f32>,
+    accumulator: f32,
+}
+
+impl Default for PdeSolver {
+    fn default() -> Self {
+        Self::new(PdeKind::Heat, 64, 64)
+    }
+}
+
+impl PdeSolver {
+    pub fn new(kind: PdeKind, rows: u16, cols: u16) -> Self {
+        let rows = rows.max(3);
+        let cols = cols.max(3);
+        let n = rows as usize * cols as usize;
+        Self {
+            kind,
+            rows,
+            cols,
+            diffusivity: 0.05,
+            wave_speed: 1.0,
+            damping: 0.2,
+            max_steps: 1000,
+            time: 0.0,
+            u: vec![0.0; n],
+            u_prev: vec![0.0; n],
+            laplace: vec![0.0; n],
+            accumulator: 0.0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.u.iter_mut().for_each(|v| *v = 0.0);
+        self.u_prev.iter_mut().for_each(|v| *v = 0.0);
+        self.time = 0.0;
+        self.accumulator = 0.0;
+    }
+
+    fn spacing(&self) -> [f32; 2] {
+        [2.0 / (self.rows - 1) as f32, 2.0 / (self.cols - 1) as f32]
+    }
+
+    // largest stable step of the explicit scheme, with a 10% safety margin
+    pub fn time_step(&self) -> f32 {
+        let [hx, hz] = self.spacing();
+        let inv = 1.0 / (hx * hx) + 1.0 / (hz * hz);
+        match self.kind {
+            PdeKind::Heat => 0.9 / (2.0 * self.diffusivity.max(1e-6) * inv),
+            PdeKind::Wave => 0.9 / (self.wave_speed.max(1e-6) * inv.sqrt()),
+        }
+    }
+
+    // adds a gaussian bump centered at (x, z) in [-1, 1]; for the wave equation the
+    // bump starts at rest
+    pub fn add_gaussian(&mut self, x: f32, z: f32, amplitude: f32, width: f32) {
+        let w2 = (width * width).max(1e-6);
+        let cols = self.cols as usize;
+        for i in 1..self.rows as usize - 1 {
+            for j in 1..cols - 1 {
+                let px = -1.0 + 2.0 * i as f32 / (self.rows - 1) as f32;
+                let pz = -1.0 + 2.0 * j as f32 / (self.cols - 1) as f32;
+                let r2 = (px - x) * (px - x) + (pz - z) * (pz - z);
+                let bump = amplitude * (-r2 / w2).exp();
+                self.u[i * cols + j] += bump;
+                self.u_prev[i * cols + j] += bump;
+            }
+        }
+    }
+
+    // advances the solution by dt seconds in fixed solver steps; the remainder is
+    // carried over to the next call, so the result does not depend on the frame rate
+    pub fn advance(&mut self, dt: f32) {
+        let h = self.time_step();
+        self.accumulator += dt.max(0.0);
+        let mut steps = 0;
+        while self.accumulator >= h {
+            if steps == self.max_steps {
+                // the simulation cannot keep up, drop the backlog instead of stalling
+                self.accumulator = 0.0;
+                break;
+            }
+            self.step(h);
+            self.accumulator -= h;
+            steps += 1;
+        }
+    }
+
+    // single explicit step of size h; h must not exceed time_step()
+    pub fn step(&mut self, h: f32) {
+        let (rows, cols) = (self.rows as usize, self.cols as usize);
+        let [hx, hz] = self.spacing();
+        let (ax, az) = (1.0 / (hx * hx), 1.0 / (hz * hz));
+        for i in 1..rows - 1 {
+            for j in 1..cols - 1 {
+                let k = i * cols + j;
+                let u = self.u[k];
+                self.laplace[k] = ax * (self.u[k + cols] - 2.0 * u + self.u[k - cols])
+                    + az * (self.u[k + 1] - 2.0 * u + self.u[k - 1]);
+            }
+        }
+
+        for i in 1..rows - 1 {
+            for j in 1..cols - 1 {
+                let k = i * cols + j;
+                let u = self.u[k];
+                match self.kind {
+                    PdeKind::Heat => self.u[k] = u + h * self.diffusivity * self.laplace[k],
+                    PdeKind::Wave => {
+                        let c2 = self.wave_speed * self.wave_speed;
+                        let velocity = (1.0 - self.damping * h).max(0.0) * (u - self.u_prev[k]);
+                        self.u[k] = u + velocity + h * h * c2 * self.laplace[k];
+                        self.u_prev[k] = u;
+                    }
+                }
+            }
+        }
+        self.time += h;
+    }
+
+    // copies the current solution into the height grid of a streaming surface
+    pub fn write_to(&self, surface: &mut IStreamingSurface) {
+        if surface.rows != self.rows || surface.cols != self.cols {
+            surface.resize(self.rows, self.cols);
+        }
+        surface.set_frame(&self.u);
+    }
+}
+// endregion: pde solver
+
+// region: analytic reference
+// A separable eigenmode of the square with the zero boundary,
+// amplitude * sin(m pi (x + 1) / 2) * sin(n pi (z + 1) / 2), whose evolution under
+// both equations is known in closed form: the heat equation decays it by
+// exp(-diffusivity k^2 t) and the damped wave equation, starting at rest, lets it
+// oscillate (or creep back, when overdamped) inside the envelope exp(-damping t / 2).
+// Starting the solver from the mode (PdeSolver::set_mode) and comparing it with
+// reference() checks the discretization; residual() is the difference.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PdeMode {
+    pub m: u32,
+    pub n: u32,
+    pub amplitude: f32,
+}
+
+impl Default for PdeMode {
+    fn default() -> Self {
+        Self {
+            m: 1,
+            n: 1,
+            amplitude: 1.0,
+        }
+    }
+}
+
+impl PdeMode {
+    // the initial shape at (x, z) in [-1, 1]
+    pub fn shape(&self, x: f32, z: f32) -> f32 {
+        use std::f32::consts::FRAC_PI_2;
+        let sx = (self.m as f32 * FRAC_PI_2 * (x + 1.0)).sin();
+        let sz = (self.n as f32 * FRAC_PI_2 * (z + 1.0)).sin();
+        self.amplitude * sx * sz
+    }
+
+    // squared wave number, the eigenvalue of -laplace
+    pub fn wave_number2(&self) -> f32 {
+        use std::f32::consts::FRAC_PI_2;
+        let (km, kn) = (self.m as f32 * FRAC_PI_2, self.n as f32 * FRAC_PI_2);
+        km * km + kn * kn
+    }
+
+    // factor of the shape at time t under the equation and the coefficients of a solver
+    pub fn factor(&self, solver: &PdeSolver, t: f32) -> f32 {
+        let k2 = self.wave_number2();
+        match solver.kind {
+            PdeKind::Heat => (-solver.diffusivity * k2 * t).exp(),
+            PdeKind::Wave => {
+                let half = 0.5 * solver.damping;
+                let c2 = solver.wave_speed * solver.wave_speed;
+                let d = c2 * k2 - half * half;
+                let envelope = (-half * t).exp();
+                if d > 0.0 {
+                    let w = d.sqrt();
+                    envelope * ((w * t).cos() + half / w * (w * t).sin())
+                } else if d < 0.0 {
+                    let w = (-d).sqrt();
+                    envelope * ((w * t).cosh() + half / w * (w * t).sinh())
+                } else {
+                    envelope * (1.0 + half * t)
+                }
+            }
+        }
+    }
+}
+
+impl PdeSolver {
+    // visits the grid points in the order of u
+    fn for_each_point(&self, mut visit: impl FnMut(usize, f32, f32)) {
+        let (rows, cols) = (self.rows as usize, self.cols as usize);
+        let [hx, hz] = self.spacing();
+        for i in 0..rows {
+            for j in 0..cols {
+                visit(i * cols + j, -1.0 + hx * i as f32, -1.0 + hz * j as f32);
+            }
+        }
+    }
+
+    // restarts the simulation from the mode, at rest for the wave equation
+    pub fn set_mode(&mut self, mode: &PdeMode) {
+        self.reset();
+        let (rows, cols) = (self.rows as usize, self.cols as usize);
+        let mut u = std::mem::take(&mut self.u);
+        self.for_each_point(|k, x, z| {
+            let (i, j) = (k / cols, k % cols);
+            // the boundary is held at zero, where the shape only nearly vanishes
+            if i > 0 && i < rows - 1 && j > 0 && j < cols - 1 {
+                u[k] = mode.shape(x, z);
+            }
+        });
+        self.u_prev.copy_from_slice(&u);
+        self.u = u;
+    }
+
+    // the analytic solution at the current time, on the grid of u
+    pub fn reference(&self, mode: &PdeMode) -> Vec<f32> {
+        let factor = mode.factor(self, self.time);
+        let mut values = vec![0.0; self.u.len()];
+        self.for_each_point(|k, x, z| values[k] = factor * mode.shape(x, z));
+        values
+    }
+
+    // computed minus analytic solution at every grid point
+    pub fn residual(&self, mode: &PdeMode) -> Vec<f32> {
+        let reference = self.reference(mode);
+        self.u.iter().zip(reference).map(|(u, r)| u - r).collect()
+    }
+}
+// endregion: analytic reference
@@ -0,0 +1,748 @@
+use std::f32::consts::PI;
+
+use cgmath::{
+    EuclideanSpace, InnerSpace, Matrix, Matrix4, Point3, SquareMatrix, Transform, Vector3, Vector4,
+};
+use winit::window::CursorIcon;
+
+use super::surface_data::{IParametricSurface, ISurfaceOutput};
+use super::wgpu_simplified::RenderTarget;
+
+// region: rays
+#[derive(Clone, Copy, Debug)]
+pub struct Ray {
+    pub origin: [f32; 3],
+    pub direction: [f32; 3], // normalized
+}
+
+impl Ray {
+    // ray through a cursor position given in physical pixels; view_project_mat is the
+    // matrix the vertex shader uses, including OPENGL_TO_WGPU_MATRIX
+    pub fn from_screen(
+        view_project_mat: &Matrix4<f32>,
+        cursor: [f32; 2],
+        size: [f32; 2],
+    ) -> Option<Self> {
+        // wgpu clip space depth runs from 0 (near) to 1 (far)
+        let near = Vector3::from(unproject(view_project_mat, cursor, size, 0.0)?);
+        let far = Vector3::from(unproject(view_project_mat, cursor, size, 1.0)?);
+        Some(Self {
+            origin: near.into(),
+            direction: (far - near).normalize().into(),
+        })
+    }
+
+    // transforms the ray, e.g. into model space with the inverse model matrix
+    pub fn transform(&self, mat: &Matrix4<f32>) -> Self {
+        let o = mat * Vector3::from(self.origin).extend(1.0);
+        let d = mat * Vector3::from(self.direction).extend(0.0);
+        Self {
+            origin: (o.truncate() / o.w).into(),
+            direction: d.truncate().normalize().into(),
+        }
+    }
+}
+// world space point at a cursor position in physical pixels and a depth buffer value
+pub fn unproject(
+    view_project_mat: &Matrix4<f32>,
+    cursor: [f32; 2],
+    size: [f32; 2],
+    depth: f32,
+) -> Option<[f32; 3]> {
+    let inv = view_project_mat.invert()?;
+    let x = 2.0 * cursor[0] / size[0] - 1.0;
+    let y = 1.0 - 2.0 * cursor[1] / size[1];
+    let p = inv * Vector4::new(x, y, depth, 1.0);
+    Some((p.truncate() / p.w).into())
+}
+// endregion: rays
+
+// region: mesh picking
+#[derive(Clone, Copy, Debug)]
+pub struct PickHit {
+    pub triangle: usize, // index of the first vertex index of the hit triangle
+    pub distance: f32,   // along the ray, in the space of the mesh
+    pub position: [f32; 3],
+    pub normal: [f32; 3], // interpolated vertex normal
+    pub barycentric: [f32; 3],
+}
+
+// nearest intersection of the ray with the triangles of the surface (Moller-Trumbore);
+// both sides of a triangle are hit
+pub fn pick_mesh(ray: &Ray, data: &ISurfaceOutput) -> Option<PickHit> {
+    let origin = Vector3::from(ray.origin);
+    let dir = Vector3::from(ray.direction);
+    let mut best: Option<PickHit> = None;
+
+    for (t, tri) in data.indices.chunks_exact(3).enumerate() {
+        let [a, b, c] = [tri[0], tri[1], tri[2]].map(|i| Vector3::from(data.positions[i as usize]));
+        let (e1, e2) = (b - a, c - a);
+        let p = dir.cross(e2);
+        let det = e1.dot(p);
+        if det.abs() < 1e-9 {
+            continue;
+        }
+        let inv_det = 1.0 / det;
+        let s = origin - a;
+        let u = s.dot(p) * inv_det;
+        if !(0.0..=1.0).contains(&u) {
+            continue;
+        }
+        let q = s.cross(e1);
+        let v = dir.dot(q) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            continue;
+        }
+        let distance = e2.dot(q) * inv_det;
+        if distance < 0.0 || best.is_some_and(|hit| hit.distance <= distance) {
+            continue;
+        }
+
+        let w = [1.0 - u - v, u, v];
+        let normal = [tri[0], tri[1], tri[2]]
+            .iter()
+            .zip(w)
+            .map(|(&i, w)| w * Vector3::from(data.normals[i as usize]))
+            .fold(Vector3::new(0.0, 0.0, 0.0), |acc, n| acc + n);
+        let normal = if normal.magnitude2() > 0.0 {
+            normal.normalize()
+        } else {
+            e1.cross(e2).normalize()
+        };
+        best = Some(PickHit {
+            triangle: 3 * t,
+            distance,
+            position: (origin + distance * dir).into(),
+            normal: normal.into(),
+            barycentric: w,
+        });
+    }
+    best
+}
+// endregion: mesh picking
+
+// region: probe hooks
+// What a probe reports about a surface point: the height y at (x, z) and its slope
+// [dy/dx, dy/dz], taken from the normal of a height-field surface.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProbeEvent {
+    pub position: [f32; 3],
+    pub value: f32,
+    pub gradient: [f32; 2],
+}
+
+impl ProbeEvent {
+    pub fn from_hit(hit: &PickHit) -> Self {
+        Self::from_point(hit.position, hit.normal)
+    }
+
+    pub fn from_point(position: [f32; 3], normal: [f32; 3]) -> Self {
+        let n = normal;
+        // a vertical tangent plane has no finite slope
+        let gradient = if n[1].abs() > 1e-6 {
+            [-n[0] / n[1], -n[2] / n[1]]
+        } else {
+            [0.0, 0.0]
+        };
+        Self {
+            position,
+            value: position[1],
+            gradient,
+        }
+    }
+
+    // maps the value within range to a pitch spanning the given number of octaves
+    // above base_hz, a simple starting point for sonification
+    pub fn tone_frequency(&self, range: [f32; 2], base_hz: f32, octaves: f32) -> f32 {
+        let s = if range[1] > range[0] {
+            ((self.value - range[0]) / (range[1] - range[0])).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        base_hz * 2f32.powf(s * octaves)
+    }
+}
+
+// Extension point for sonification, haptics or logging: every callback is invoked
+// with each probe event the viewer emits.
+#[derive(Default)]
+pub struct ProbeHooks {
+    #[allow(clippy::type_complexity)]
+    callbacks: Vec<Box<dyn FnMut(&ProbeEvent)>>,
+}
+
+impl ProbeHooks {
+    pub fn add(&mut self, callback: impl FnMut(&ProbeEvent) + 'static) {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    pub fn clear(&mut self) {
+        self.callbacks.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.callbacks.is_empty()
+    }
+
+    pub fn emit(&mut self, event: &ProbeEvent) {
+        for callback in self.callbacks.iter_mut() {
+            callback(event);
+        }
+    }
+}
+// endregion: probe hooks
+
+// region: crosshair
+// Marker for the surface point under the cursor while probing: a cross or ring lying
+// in the tangent plane at the hit, with a tick along the normal, as world space line
+// list (position, color) pairs for gizmo::Guides::write_lines(). The window shows
+// cursor_icon over the scene in probe mode.
+pub const PROBE_KEY: &str = "j";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarkerShape {
+    Cross,
+    Ring(u32), // segments
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Crosshair {
+    pub shape: MarkerShape,
+    pub size: f32, // half width of the cross, radius of the ring, in world units
+    pub color: [f32; 3],
+    pub normal_length: f32, // 0 leaves out the normal tick
+    pub normal_color: [f32; 3],
+    pub cursor_icon: CursorIcon,
+}
+
+impl Default for Crosshair {
+    fn default() -> Self {
+        Self {
+            shape: MarkerShape::Cross,
+            size: 0.15,
+            color: [1.0, 1.0, 0.2],
+            normal_length: 0.3,
+            normal_color: [0.2, 1.0, 1.0],
+            cursor_icon: CursorIcon::Crosshair,
+        }
+    }
+}
+
+impl Crosshair {
+    pub fn with_shape(mut self, shape: MarkerShape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    pub fn with_size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn with_color(mut self, color: [f32; 3]) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_normal(mut self, length: f32, color: [f32; 3]) -> Self {
+        self.normal_length = length;
+        self.normal_color = color;
+        self
+    }
+
+    pub fn with_cursor_icon(mut self, cursor_icon: CursorIcon) -> Self {
+        self.cursor_icon = cursor_icon;
+        self
+    }
+
+    // the marker at a hit in the model space of the mesh, placed by model_mat; it is
+    // lifted slightly off the surface so the depth test keeps it visible
+    pub fn lines(&self, hit: &PickHit, model_mat: &Matrix4<f32>) -> Vec<([f32; 3], [f32; 3])> {
+        let center = model_mat.transform_point(Point3::from(hit.position));
+        let normal = model_mat
+            .invert()
+            .map(|inv| inv.transpose().transform_vector(Vector3::from(hit.normal)))
+            .filter(|n| n.magnitude2() > 0.0)
+            .map_or(Vector3::unit_y(), |n| n.normalize());
+        // tangent frame, from whichever axis is furthest from the normal
+        let helper = if normal.x.abs() < 0.9 {
+            Vector3::unit_x()
+        } else {
+            Vector3::unit_y()
+        };
+        let u = normal.cross(helper).normalize();
+        let v = normal.cross(u);
+        let center = center.to_vec() + 0.02 * self.size * normal;
+        let at = |offset: Vector3<f32>| -> [f32; 3] { (center + offset).into() };
+
+        let mut lines = match self.shape {
+            MarkerShape::Cross => vec![
+                (at(-self.size * u), self.color),
+                (at(self.size * u), self.color),
+                (at(-self.size * v), self.color),
+                (at(self.size * v), self.color),
+            ],
+            MarkerShape::Ring(segments) => {
+                let segments = segments.max(3);
+                let point = |k: u32| {
+                    let a = 2.0 * PI * k as f32 / segments as f32;
+                    (at(self.size * (a.cos() * u + a.sin() * v)), self.color)
+                };
+                (0..segments)
+                    .flat_map(|k| [point(k), point(k + 1)])
+                    .collect()
+            }
+        };
+        if self.normal_length > 0.0 {
+            lines.push((at(Vector3::new(0.0, 0.0, 0.0)), self.normal_color));
+            lines.push((at(self.normal_length * normal), self.normal_color));
+        }
+        lines
+    }
+}
+// endregion: crosshair
+
+// region: parameter inset
+// The parameter domain of a parametric surface as a 2D inset in a corner of the
+// window, with the (u, v) of a surface point marked in it: uv_of_hit gives the
+// parameters of a picked point, hit_at_uv the surface point of parameters clicked in
+// the inset, to mark with a Crosshair. Both go through the grid cell of the triangle,
+// so they hold for closed seams and flat shading, which keep the triangle order of
+// grid_indices. The lines are in clip space, for a gizmo::LineRenderer written with
+// the identity matrix.
+pub const INSET_KEY: &str = "y";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ParamInset {
+    pub domain: [f32; 4],     // umin, umax, vmin, vmax
+    pub resolution: [u16; 2], // grid cells along u and v
+    pub flipped: bool,        // the mesh went through flip_normals, which swaps corners
+    pub rect: [f32; 4],       // x0, y0, x1, y1 in clip space, u to the right, v up
+    pub grid_lines: u32,      // interior lines along each parameter
+    pub color: [f32; 3],
+    pub marker_color: [f32; 3],
+}
+
+impl Default for ParamInset {
+    fn default() -> Self {
+        Self {
+            domain: [0.0, 1.0, 0.0, 1.0],
+            resolution: [1, 1],
+            flipped: false,
+            rect: [0.5, 0.5, 0.95, 0.95],
+            grid_lines: 3,
+            color: [0.8; 3],
+            marker_color: [1.0, 1.0, 0.2],
+        }
+    }
+}
+
+impl ParamInset {
+    pub fn new(surface: &IParametricSurface) -> Self {
+        let mut inset = Self::default();
+        inset.set_surface(surface);
+        inset
+    }
+
+    // domain, resolution and orientation of the surface, after its new()
+    pub fn set_surface(&mut self, surface: &IParametricSurface) {
+        self.domain = [surface.umin, surface.umax, surface.vmin, surface.vmax];
+        self.resolution = [surface.u_resolution.max(1), surface.v_resolution.max(1)];
+        self.flipped = surface.flips_normals();
+    }
+
+    // grid coordinates of the corners of triangle t: cell (i, j) is split into
+    // (i, j), (i, j + 1), (i + 1, j + 1) and (i + 1, j + 1), (i + 1, j), (i, j)
+    fn triangle_corners(&self, t: usize) -> Option<[[f32; 2]; 3]> {
+        let [nu, nv] = self.resolution.map(|n| n as usize);
+        let cell = t / 2;
+        if cell >= nu * nv {
+            return None;
+        }
+        let (i, j) = ((cell / nv) as f32, (cell % nv) as f32);
+        let mut corners = if t.is_multiple_of(2) {
+            [[i, j], [i, j + 1.0], [i + 1.0, j + 1.0]]
+        } else {
+            [[i + 1.0, j + 1.0], [i + 1.0, j], [i, j]]
+        };
+        if self.flipped {
+            corners.swap(1, 2);
+        }
+        Some(corners)
+    }
+
+    // (u, v) of a point picked on the mesh of the surface
+    pub fn uv_of_hit(&self, hit: &PickHit) -> Option<[f32; 2]> {
+        let corners = self.triangle_corners(hit.triangle / 3)?;
+        let [umin, umax, vmin, vmax] = self.domain;
+        let [nu, nv] = self.resolution.map(|n| n as f32);
+        let w = hit.barycentric;
+        let [i, j] = [0, 1].map(|k| (0..3).map(|c| w[c] * corners[c][k]).sum::<f32>());
+        Some([umin + (umax - umin) * i / nu, vmin + (vmax - vmin) * j / nv])
+    }
+
+    // the point of the mesh at (u, v), clamped to the domain, as a hit at distance 0
+    pub fn hit_at_uv(&self, data: &ISurfaceOutput, [u, v]: [f32; 2]) -> Option<PickHit> {
+        let [umin, umax, vmin, vmax] = self.domain;
+        let [nu, nv] = self.resolution.map(|n| n as f32);
+        let grid = |x: f32, min: f32, max: f32, n: f32| {
+            let g = fraction(x, min, max) * n;
+            let cell = g.floor().min(n - 1.0);
+            (cell, g - cell)
+        };
+        let ((i, a), (j, b)) = (grid(u, umin, umax, nu), grid(v, vmin, vmax, nv));
+        let cell = i as usize * nv as usize + j as usize;
+        // a along u, b along v inside the cell, see triangle_corners
+        let (t, mut w) = if a <= b {
+            (2 * cell, [1.0 - b, b - a, a])
+        } else {
+            (2 * cell + 1, [b, a - b, 1.0 - a])
+        };
+        if self.flipped {
+            w.swap(1, 2);
+        }
+        let tri = data.indices.get(3 * t..3 * t + 3)?;
+        let sum = |values: &[[f32; 3]]| {
+            (0..3).fold(Vector3::new(0.0, 0.0, 0.0), |acc, c| {
+                acc + w[c] * Vector3::from(values[tri[c] as usize])
+            })
+        };
+        let normal = sum(&data.normals);
+        Some(PickHit {
+            triangle: 3 * t,
+            distance: 0.0,
+            position: sum(&data.positions).into(),
+            normal: if normal.magnitude2() > 0.0 {
+                normal.normalize().into()
+            } else {
+                [0.0, 1.0, 0.0]
+            },
+            barycentric: w,
+        })
+    }
+
+    // (u, v) under a cursor in physical pixels, None outside the inset
+    pub fn uv_at_cursor(&self, cursor: [f32; 2], size: [f32; 2]) -> Option<[f32; 2]> {
+        let x = 2.0 * cursor[0] / size[0] - 1.0;
+        let y = 1.0 - 2.0 * cursor[1] / size[1];
+        let [x0, y0, x1, y1] = self.rect;
+        if !(x0..=x1).contains(&x) || !(y0..=y1).contains(&y) {
+            return None;
+        }
+        let [umin, umax, vmin, vmax] = self.domain;
+        Some([
+            umin + (umax - umin) * (x - x0) / (x1 - x0),
+            vmin + (vmax - vmin) * (y - y0) / (y1 - y0),
+        ])
+    }
+
+    // frame, grid and a cross at uv, in clip space at the near plane so the depth
+    // test keeps them in front of the scene
+    pub fn lines(&self, uv: Option<[f32; 2]>) -> Vec<([f32; 3], [f32; 3])> {
+        let [x0, y0, x1, y1] = self.rect;
+        let mut lines = vec![];
+        let mut line = |a: [f32; 2], b: [f32; 2], color: [f32; 3]| {
+            lines.push(([a[0], a[1], 0.0], color));
+            lines.push(([b[0], b[1], 0.0], color));
+        };
+        let dim = self.color.map(|c| 0.45 * c);
+        for k in 1..=self.grid_lines {
+            let f = k as f32 / (self.grid_lines + 1) as f32;
+            let (x, y) = (x0 + (x1 - x0) * f, y0 + (y1 - y0) * f);
+            line([x, y0], [x, y1], dim);
+            line([x0, y], [x1, y], dim);
+        }
+        let corners = [[x0, y0], [x1, y0], [x1, y1], [x0, y1]];
+        for k in 0..4 {
+            line(corners[k], corners[(k + 1) % 4], self.color);
+        }
+        if let Some([u, v]) = uv {
+            let [umin, umax, vmin, vmax] = self.domain;
+            let x = x0 + (x1 - x0) * fraction(u, umin, umax);
+            let y = y0 + (y1 - y0) * fraction(v, vmin, vmax);
+            let size = 0.04 * (x1 - x0).min(y1 - y0).max(0.5);
+            line([x - size, y], [x + size, y], self.marker_color);
+            line([x, y - size], [x, y + size], self.marker_color);
+        }
+        lines
+    }
+}
+// where x lies in [min, max], clamped to 0..1
+fn fraction(x: f32, min: f32, max: f32) -> f32 {
+    if max != min {
+        ((x - min) / (max - min)).clamp(0.0, 1.0)
+    } else {
+        0.5
+    }
+}
+// endregion: parameter inset
+
+// region: depth picking
+const DEPTH_PICK_SHADER: &str = r#"
+@group(0) @binding(0) var depthTexture: DEPTH_TEXTURE_TYPE;
+@group(0) @binding(1) var<uniform> pixels: array<vec4u, 3>;
+@group(0) @binding(2) var<storage, read_write> depths: array<f32, 3>;
+
+@compute @workgroup_size(1)
+fn cs_main() {
+    for (var i = 0u; i < 3u; i++) {
+        depths[i] = textureLoad(depthTexture, vec2i(pixels[i].xy), 0).x;
+    }
+}
+"#;
+
+// A surface point found in the depth buffer: the world position under the cursor,
+// the normal estimated from the depth of the neighboring pixels, facing the camera,
+// and the depth buffer value itself.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DepthHit {
+    pub pixel: [u32; 2],
+    pub depth: f32,
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+}
+
+impl DepthHit {
+    pub fn probe_event(&self) -> ProbeEvent {
+        ProbeEvent::from_point(self.position, self.normal)
+    }
+}
+
+// Picking for any scene, however many meshes or instances it draws and whatever
+// its vertex shaders do: a compute pass reads the depth buffer under the cursor
+// after the surface pass, and the point is unprojected with the view-projection
+// matrix of that pass. The depth texture must be created with
+// create_depth_texture_view(init, true) and stored by the surface pass:
+//
+//     picker.request(&queue, &mut encoder, cursor, size);   // after the surface pass
+//     queue.submit(std::iter::once(encoder.finish()));
+//     if let Some(hit) = picker.read(&device, &view_project_mat)? {
+//         probe_hooks.emit(&hit.probe_event());
+//     }
+pub struct DepthPicker {
+    pipeline: wgpu::ComputePipeline,
+    layout: wgpu::BindGroupLayout,
+    pixel_buffer: wgpu::Buffer,
+    depth_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pending: Option<([[u32; 2]; 3], [f32; 2])>, // requested pixels, target size
+}
+
+impl DepthPicker {
+    pub fn new(init: &impl RenderTarget, depth_view: &wgpu::TextureView) -> Self {
+        let device = init.device();
+        let multisampled = init.sample_count() > 1;
+        // bound as an unfilterable float texture; GL has no textureLoad of depth types
+        let texture_type = if multisampled {
+            "texture_multisampled_2d<f32>"
+        } else {
+            "texture_2d<f32>"
+        };
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Depth Pick Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                DEPTH_PICK_SHADER
+                    .replace("DEPTH_TEXTURE_TYPE", texture_type)
+                    .into(),
+            ),
+        });
+
+        let buffer_entry = |binding, ty| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled,
+                    },
+                    count: None,
+                },
+                buffer_entry(1, wgpu::BufferBindingType::Uniform),
+                buffer_entry(2, wgpu::BufferBindingType::Storage { read_only: false }),
+            ],
+            label: Some("Depth Pick Bind Group Layout"),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Depth Pick Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Depth Pick Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: Some("cs_main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        let buffer = |label, size, usage| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage,
+                mapped_at_creation: false,
+            })
+        };
+        use wgpu::BufferUsages as U;
+        let pixel_buffer = buffer("Depth Pick Pixel Buffer", 48, U::UNIFORM | U::COPY_DST);
+        let depth_buffer = buffer("Depth Pick Buffer", 12, U::STORAGE | U::COPY_SRC);
+        let readback_buffer = buffer("Depth Pick Readback Buffer", 12, U::MAP_READ | U::COPY_DST);
+        let bind_group =
+            Self::create_bind_group(device, &layout, &pixel_buffer, &depth_buffer, depth_view);
+
+        Self {
+            pipeline,
+            layout,
+            pixel_buffer,
+            depth_buffer,
+            readback_buffer,
+            bind_group,
+            pending: None,
+        }
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        pixel_buffer: &wgpu::Buffer,
+        depth_buffer: &wgpu::Buffer,
+        depth_view: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(depth_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: pixel_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: depth_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("Depth Pick Bind Group"),
+        })
+    }
+
+    // call after the depth texture has been recreated, e.g. on resize
+    pub fn set_depth_view(&mut self, init: &impl RenderTarget, depth_view: &wgpu::TextureView) {
+        self.bind_group = Self::create_bind_group(
+            init.device(),
+            &self.layout,
+            &self.pixel_buffer,
+            &self.depth_buffer,
+            depth_view,
+        );
+    }
+
+    // records the read of the depth under the cursor, given in physical pixels of a
+    // target of the given size; false when the cursor is outside the target
+    pub fn request(
+        &mut self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        cursor: [f32; 2],
+        size: [f32; 2],
+    ) -> bool {
+        self.pending = None;
+        let [w, h] = [size[0] as u32, size[1] as u32];
+        if !(0.0..size[0]).contains(&cursor[0])
+            || !(0.0..size[1]).contains(&cursor[1])
+            || w < 2
+            || h < 2
+        {
+            return false;
+        }
+        let [x, y] = [(cursor[0] as u32).min(w - 1), (cursor[1] as u32).min(h - 1)];
+        // the neighbors for the normal, on the other side at the right and bottom edges
+        let dx = if x + 1 < w { x + 1 } else { x - 1 };
+        let dy = if y + 1 < h { y + 1 } else { y - 1 };
+        let pixels = [[x, y], [dx, y], [x, dy]];
+        let data: Vec<u32> = pixels.iter().flat_map(|p| [p[0], p[1], 0, 0]).collect();
+        queue.write_buffer(&self.pixel_buffer, 0, bytemuck::cast_slice(&data));
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Depth Pick Pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &self.bind_group, &[]);
+        compute_pass.dispatch_workgroups(1, 1, 1);
+        drop(compute_pass);
+        encoder.copy_buffer_to_buffer(&self.depth_buffer, 0, &self.readback_buffer, 0, 12);
+        self.pending = Some((pixels, size));
+        true
+    }
+
+    // the surface point of the last request, once its commands have been submitted;
+    // None without a request or when the cursor was over the background
+    pub fn read(
+        &mut self,
+        device: &wgpu::Device,
+        view_project_mat: &Matrix4<f32>,
+    ) -> anyhow::Result<Option<DepthHit>> {
+        let Some((pixels, size)) = self.pending.take() else {
+            return Ok(None);
+        };
+        let slice = self.readback_buffer.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+        device.poll(wgpu::PollType::Wait)?;
+        rx.recv()??;
+        let depths: Vec<f32> = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+        self.readback_buffer.unmap();
+
+        if depths[0] >= 1.0 {
+            return Ok(None);
+        }
+        let point = |k: usize, depth: f32| {
+            let center = [pixels[k][0] as f32 + 0.5, pixels[k][1] as f32 + 0.5];
+            unproject(view_project_mat, center, size, depth).map(Vector3::from)
+        };
+        let (Some(position), Some(near)) = (point(0, depths[0]), point(0, 0.0)) else {
+            return Ok(None);
+        };
+        let view_dir = (position - near).normalize();
+        // across a silhouette a neighbor may lie on the background or another surface;
+        // the normal then falls back to facing the camera
+        let mut normal = -view_dir;
+        if let (Some(px), Some(py)) = (point(1, depths[1]), point(2, depths[2]))
+            && depths[1] < 1.0
+            && depths[2] < 1.0
+        {
+            let n = (px - position).cross(py - position);
+            if n.magnitude2() > 0.0 {
+                let n = n.normalize();
+                normal = if n.dot(view_dir) > 0.0 { -n } else { n };
+            }
+        }
+        Ok(Some(DepthHit {
+            pixel: pixels[0],
+            depth: depths[0],
+            position: position.into(),
+            normal: normal.into(),
+        }))
+    }
+}
+// endregion: depth picking
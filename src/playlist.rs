@@ -0,0 +1,195 @@
+use cgmath::{Point3, Vector3};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+
+use super::animation::Easing;
+use super::capture::VideoExport;
+use super::export::ExportConvention;
+
+// region: playlist script
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IScene {
+    pub surface_type: u32,
+    pub duration: f32, // seconds
+    pub caption: String,
+    pub camera_start: [f32; 3],
+    pub camera_end: [f32; 3],
+    pub look_at: [f32; 3],
+    pub colormap_name: Option<String>,
+    pub rotation_speed: Option<f32>,
+}
+
+impl Default for IScene {
+    fn default() -> Self {
+        Self {
+            surface_type: 0,
+            duration: 5.0,
+            caption: String::new(),
+            camera_start: [2.0, 2.0, 4.0],
+            camera_end: [2.0, 2.0, 4.0],
+            look_at: [0.0, 0.0, 0.0],
+            colormap_name: None,
+            rotation_speed: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IPlaylist {
+    pub scenes: Vec<IScene>,
+    pub looping: bool,
+    pub record_dir: Option<String>, // when set, the playlist is saved as PNG frames
+    pub record_frame_rate: f32,     // frames per second of animation time when recording
+    pub record_frames: Option<u32>, // frames to record, by default one pass of the playlist
+    pub export_convention: ExportConvention, // applied to recorded frames
+}
+
+impl Default for IPlaylist {
+    fn default() -> Self {
+        Self {
+            scenes: vec![],
+            looping: false,
+            record_dir: None,
+            record_frame_rate: 60.0,
+            record_frames: None,
+            export_convention: ExportConvention::default(),
+        }
+    }
+}
+
+impl IPlaylist {
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let playlist: IPlaylist = serde_json::from_str(json)?;
+        if playlist.scenes.is_empty() {
+            anyhow::bail!("playlist contains no scenes");
+        }
+        Ok(playlist)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.scenes.iter().map(|s| s.duration).sum()
+    }
+
+    // fixed timestep recording into record_dir, if set
+    pub fn video_export(&self) -> Option<VideoExport> {
+        let dir = self.record_dir.as_ref()?;
+        Some(match self.record_frames {
+            Some(frames) => VideoExport::new(dir, self.record_frame_rate, frames),
+            None => VideoExport::for_duration(dir, self.record_frame_rate, self.duration()),
+        })
+    }
+}
+// endregion: playlist script
+
+// region: playlist runner
+pub struct PlaylistFrame<'a> {
+    pub scene_index: usize,
+    pub scene: &'a IScene,
+    pub scene_changed: bool,
+    pub progress: f32, // 0..1 within the current scene
+    pub camera_position: Point3<f32>,
+    pub look_at: Point3<f32>,
+    pub up_direction: Vector3<f32>,
+}
+
+// While recording, the playlist runs on the fixed timestep of its VideoExport: states
+// pass the elapsed time through clock() before animating, so every recorded frame is
+// one step further whatever the real frame time.
+pub struct PlaylistRunner {
+    pub playlist: IPlaylist,
+    pub camera_easing: Easing,
+    current: Option<usize>,
+    video: Option<VideoExport>,
+}
+
+impl PlaylistRunner {
+    pub fn new(playlist: IPlaylist) -> Self {
+        Self {
+            video: playlist.video_export(),
+            playlist,
+            camera_easing: Easing::EaseInOut,
+            current: None,
+        }
+    }
+
+    // animation time: the time of the frame being recorded, else the elapsed time
+    pub fn clock(&self, elapsed: Duration) -> Duration {
+        match &self.video {
+            Some(video) if !video.is_finished() => video.time(),
+            _ => elapsed,
+        }
+    }
+
+    pub fn video_export(&self) -> Option<&VideoExport> {
+        self.video.as_ref()
+    }
+
+    // returns the scene state at the elapsed time t, or None once a non-looping
+    // playlist has finished
+    pub fn update(&mut self, t: f32) -> Option<PlaylistFrame<'_>> {
+        let total = self.playlist.duration();
+        if total <= 0.0 || (!self.playlist.looping && t >= total) {
+            return None;
+        }
+        let mut local = if self.playlist.looping {
+            t.rem_euclid(total)
+        } else {
+            t
+        };
+
+        let mut index = 0;
+        for (i, scene) in self.playlist.scenes.iter().enumerate() {
+            index = i;
+            if local < scene.duration {
+                break;
+            }
+            local -= scene.duration;
+        }
+
+        let scene_changed = self.current != Some(index);
+        self.current = Some(index);
+
+        let scene = &self.playlist.scenes[index];
+        let progress = if scene.duration > 0.0 {
+            (local / scene.duration).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        let s = self.camera_easing.ease(progress);
+        let (a, b) = (scene.camera_start, scene.camera_end);
+        let camera_position = Point3::new(
+            a[0] + (b[0] - a[0]) * s,
+            a[1] + (b[1] - a[1]) * s,
+            a[2] + (b[2] - a[2]) * s,
+        );
+
+        Some(PlaylistFrame {
+            scene_index: index,
+            scene,
+            scene_changed,
+            progress,
+            camera_position,
+            look_at: Point3::from(scene.look_at),
+            up_direction: Vector3::unit_y(),
+        })
+    }
+
+    // path of the frame being recorded, moving the recording on to the next frame, or
+    // None if recording is disabled or done
+    pub fn next_frame_path(&mut self) -> Option<std::path::PathBuf> {
+        let video = self.video.as_mut().filter(|video| !video.is_finished())?;
+        let path = video.frame_path();
+        if !video.advance() {
+            log::info!("recorded {} frames to {:?}", video.frames, video.dir);
+        }
+        Some(path)
+    }
+}
+// endregion: playlist runner
@@ -0,0 +1,2144 @@
+use std::sync::{Arc, mpsc};
+use std::thread::JoinHandle;
+
+use bytemuck::{Pod, Zeroable, cast_slice};
+use cgmath::{Matrix4, MetricSpace, Point3, SquareMatrix, Vector4};
+use wgpu::util::DeviceExt;
+use winit::{
+    application::ApplicationHandler,
+    event::{ElementState, KeyEvent, MouseButton, WindowEvent},
+    event_loop::{ActiveEventLoop, EventLoop, EventLoopProxy},
+    keyboard::{Key, NamedKey},
+    window::{Window, WindowId},
+};
+
+use super::animation::SharedClock;
+use super::colormap;
+use super::draw_list::{Draw, DrawKey, DrawList, SortStats};
+use super::error::{Error, Result};
+use super::labels::NumberFormat;
+use super::mesh::{MeshBuilder, VertexAttributes};
+use super::overlay;
+use super::series::{FrameCache, FrameSource};
+use super::surface_data::{self as sd, IStreamingSurface, ISurfaceOutput, MaskMode};
+use super::theme::{Palette, Theme};
+use super::wgpu_simplified as ws;
+
+// region: plot shader
+const PLOT_SHADER: &str = r#"
+struct Uniforms {
+    vpMat: mat4x4f,
+    modelMat: mat4x4f,
+    normalMat: mat4x4f,
+    lightDirection: vec4f,
+    eyePosition: vec4f,
+}
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+
+struct Output {
+    @builtin(position) position: vec4f,
+    @location(0) vPosition: vec4f,
+    @location(1) vNormal: vec4f,
+    @location(2) vColor: vec4f,
+}
+
+@vertex
+fn vs_main(
+    @location(0) position: vec4f,
+    @location(1) normal: vec4f,
+    @location(2) color: vec4f,
+) -> Output {
+    var output: Output;
+    let mPosition = uniforms.modelMat * position;
+    output.vPosition = mPosition;
+    output.vNormal = uniforms.normalMat * normal;
+    output.position = uniforms.vpMat * mPosition;
+    output.vColor = color;
+    return output;
+}
+
+// two-sided Blinn-Phong with the material of the chapter examples
+@fragment
+fn fs_main(in: Output) -> @location(0) vec4f {
+    let N = normalize(in.vNormal.xyz);
+    let L = normalize(-uniforms.lightDirection.xyz);
+    let V = normalize(uniforms.eyePosition.xyz - in.vPosition.xyz);
+    let H = normalize(L + V);
+    let diffuse = 0.7 * (max(dot(N, L), 0.0) + max(dot(-N, L), 0.0));
+    let specular = 0.4 * (pow(max(dot(N, H), 0.0), 30.0) + pow(max(dot(-N, H), 0.0), 30.0));
+    return vec4f(in.vColor.rgb * (0.1 + diffuse) + vec3f(specular), 1.0);
+}
+
+// the colorbar is given in clip space and drawn unlit
+struct ColorbarOutput {
+    @builtin(position) position: vec4f,
+    @location(0) vColor: vec4f,
+}
+
+@vertex
+fn vs_colorbar(@location(0) position: vec4f, @location(2) color: vec4f) -> ColorbarOutput {
+    var output: ColorbarOutput;
+    output.position = position;
+    output.vColor = color;
+    return output;
+}
+
+@fragment
+fn fs_colorbar(in: ColorbarOutput) -> @location(0) vec4f {
+    return vec4f(in.vColor.rgb, 1.0);
+}
+
+// probe lines are given in world space and drawn unlit as well
+@vertex
+fn vs_unlit(@location(0) position: vec4f, @location(2) color: vec4f) -> ColorbarOutput {
+    var output: ColorbarOutput;
+    output.position = uniforms.vpMat * uniforms.modelMat * position;
+    output.vColor = color;
+    return output;
+}
+"#;
+// endregion: plot shader
+
+// region: surface plot
+// High-level entry point for plotting y = f(x, z) without writing a State:
+//
+//     plot_surface(|x, z| (x * x + z * z).sqrt().sin())
+//         .colormap("viridis")
+//         .resolution(256)
+//         .show()?;
+//
+// show() opens a window and blocks until it is closed, show_async() runs the window
+// on its own thread and returns a PlotHandle for live updates. Drag with the left
+// mouse button to orbit, scroll to zoom, space cycles shape/wireframe/both.
+//
+// Measured data can carry a per-sample standard deviation (uncertainty()): it is
+// drawn as semi-transparent surfaces at +/- band_sigmas * sigma, and/or by fading
+// the colors towards gray where sigma is large (color_by_uncertainty()).
+//
+// color_by() takes the colors from a second scalar field with its own normalization,
+// e.g. elevation colored by temperature; the colorbar then belongs to that field.
+//
+// mask() blanks out parts of the data, e.g. ocean cells of an elevation grid; the
+// m key cycles between hiding, dimming and ignoring the mask.
+//
+// plot_animated() plots y = f(x, z, t) and keeps redrawing; t comes from a
+// SharedClock, by default one per window that all its subplots share. p pauses the
+// window clock, the left and right arrow keys step the time back and forth.
+//
+// probe() cuts the surface with the plane x = c or z = c: the intersection curve is
+// drawn on the surface and as a line chart in the top left corner of the viewport.
+// x cycles the probe of the hovered plot between off, x and z; dragging with shift
+// held moves the plane along its axis and prints the cross-section on release.
+//
+// plot_series() shows a sequence of grids, steps_per_second steps per unit of clock
+// time, looping. A slider at the bottom of the viewport shows the current step and
+// scrubs through the series when dragged; the arrow keys move by one step. Frames
+// are loaded on a background thread around the current step, until then the
+// nearest loaded one is drawn.
+//
+// The colorbar has ticks labeled in number_format(), which the probe print-out
+// uses as well, e.g. with a decimal comma or a unit. theme() and ui_scale() set the
+// colors and sizes of the colorbar, its labels and the probe inset (theme::Theme).
+//
+// lighting(false) draws the surface unlit in its colormap colors; its vertices then
+// carry no normals, which saves a third of the vertex memory of large grids.
+//
+// The draws of each subplot are sorted by pipeline before they are recorded, with
+// the blended bands last; d prints the draw calls and state switches of the last frame.
+pub struct SurfacePlot {
+    source: PlotSource,
+    pub x_range: [f32; 2],
+    pub z_range: [f32; 2],
+    pub resolution: u16, // samples per axis
+    pub colormap_name: String,
+    pub wireframe_color: String,
+    pub aspect_ratio: f32, // height of the normalized surface relative to its width
+    pub plot_type: u32,    // 0: shape and wireframe, 1: shape only, 2: wireframe only
+    pub title: String,
+    pub sample_count: u32,
+    sigma: Option<PlotSource>,
+    pub band_sigmas: f32, // offset of the envelope surfaces in sigmas; 0 hides them
+    pub band_opacity: f32,
+    pub color_by_uncertainty: bool,
+    color: Option<PlotSource>,
+    pub color_range: Option<[f32; 2]>, // fixed data range of the color field; auto if None
+    mask: Option<PlotSource>,          // non-zero samples are masked
+    pub mask_mode: MaskMode,
+    pub time: f32, // t at which animated sources are sampled
+    clock: Option<SharedClock>,
+    series: Option<FrameCache>,
+    pub steps_per_second: f32,
+    shown_step: Option<usize>,     // step of the series frame that is drawn
+    value_range: Option<[f32; 2]>, // data range of the loaded series frames
+    pub probe: Option<(ProbeAxis, f32)>, // slicing plane at a data coordinate
+    pub lighting: bool,
+    pub number_format: NumberFormat,
+    pub theme: Theme,
+    pub ui_scale: f32, // size factor of the colorbar, its labels and the inset
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProbeAxis {
+    X, // the plane x = c, the cross-section runs along z
+    Z, // the plane z = c, the cross-section runs along x
+}
+
+// what is plotted: a function sampled on the grid or a grid of values given directly
+enum PlotSource {
+    Function(Box<dyn Fn(f32, f32) -> f32 + Send>),
+    Animated(Box<dyn Fn(f32, f32, f32) -> f32 + Send>),
+    Values {
+        rows: u16,
+        cols: u16,
+        values: Vec<f32>,
+    },
+}
+
+pub fn plot_surface(f: impl Fn(f32, f32) -> f32 + Send + 'static) -> SurfacePlot {
+    SurfacePlot {
+        source: PlotSource::Function(Box::new(f)),
+        x_range: [-1.0, 1.0],
+        z_range: [-1.0, 1.0],
+        resolution: 64,
+        colormap_name: "jet".to_string(),
+        wireframe_color: "white".to_string(),
+        aspect_ratio: 0.5,
+        plot_type: 1,
+        title: "wgpu surfaces plot".to_string(),
+        sample_count: 1,
+        sigma: None,
+        band_sigmas: 1.0,
+        band_opacity: 0.35,
+        color_by_uncertainty: false,
+        color: None,
+        color_range: None,
+        mask: None,
+        mask_mode: MaskMode::Hide,
+        time: 0.0,
+        clock: None,
+        series: None,
+        steps_per_second: 10.0,
+        shown_step: None,
+        value_range: None,
+        probe: None,
+        lighting: true,
+        number_format: NumberFormat::default(),
+        theme: Theme::default(),
+        ui_scale: 1.0,
+    }
+}
+
+// plots y = f(x, z, t), animated by the clock of the window or of the plot
+pub fn plot_animated(f: impl Fn(f32, f32, f32) -> f32 + Send + 'static) -> SurfacePlot {
+    SurfacePlot {
+        source: PlotSource::Animated(Box::new(f)),
+        ..plot_surface(|_, _| 0.0)
+    }
+}
+
+// plots a row-major grid of values (rows along x, at most 256 x 256)
+pub fn plot_values(rows: u16, cols: u16, values: Vec<f32>) -> SurfacePlot {
+    SurfacePlot {
+        source: PlotSource::Values { rows, cols, values },
+        ..plot_surface(|_, _| 0.0)
+    }
+}
+
+// plots a time series of grids, one time step at a time
+pub fn plot_series(source: impl FrameSource + 'static) -> SurfacePlot {
+    let [rows, cols] = source.size();
+    SurfacePlot {
+        source: PlotSource::Values {
+            rows,
+            cols,
+            values: vec![0.0; rows as usize * cols as usize],
+        },
+        series: Some(FrameCache::new(Box::new(source), 32, 8)),
+        ..plot_surface(|_, _| 0.0)
+    }
+}
+
+impl SurfacePlot {
+    pub fn x_range(mut self, min: f32, max: f32) -> Self {
+        self.x_range = [min, max];
+        self
+    }
+
+    pub fn z_range(mut self, min: f32, max: f32) -> Self {
+        self.z_range = [min, max];
+        self
+    }
+
+    // clamped to [2, 256] so the vertex count fits into u16 indices
+    pub fn resolution(mut self, resolution: u16) -> Self {
+        self.resolution = resolution.clamp(2, 256);
+        self
+    }
+
+    pub fn colormap(mut self, colormap_name: &str) -> Self {
+        self.colormap_name = colormap_name.to_string();
+        self
+    }
+
+    pub fn wireframe(mut self, wireframe_color: &str) -> Self {
+        self.wireframe_color = wireframe_color.to_string();
+        self.plot_type = 0;
+        self
+    }
+
+    pub fn aspect_ratio(mut self, aspect_ratio: f32) -> Self {
+        self.aspect_ratio = aspect_ratio;
+        self
+    }
+
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    pub fn sample_count(mut self, sample_count: u32) -> Self {
+        self.sample_count = sample_count;
+        self
+    }
+
+    pub fn lighting(mut self, lighting: bool) -> Self {
+        self.lighting = lighting;
+        self
+    }
+
+    pub fn number_format(mut self, number_format: NumberFormat) -> Self {
+        self.number_format = number_format;
+        self
+    }
+
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn ui_scale(mut self, ui_scale: f32) -> Self {
+        self.ui_scale = ui_scale;
+        self
+    }
+
+    // attributes of the surface vertices: without lighting the normals are left out
+    pub fn vertex_attributes(&self) -> VertexAttributes {
+        if self.lighting {
+            VertexAttributes::LIT
+        } else {
+            VertexAttributes::UNLIT
+        }
+    }
+
+    // standard deviation of the plotted value at (x, z)
+    pub fn uncertainty(mut self, sigma: impl Fn(f32, f32) -> f32 + Send + 'static) -> Self {
+        self.sigma = Some(PlotSource::Function(Box::new(sigma)));
+        self
+    }
+
+    // standard deviations on the same grid as the plotted values
+    pub fn uncertainty_values(mut self, rows: u16, cols: u16, values: Vec<f32>) -> Self {
+        self.sigma = Some(PlotSource::Values { rows, cols, values });
+        self
+    }
+
+    // envelope surfaces at +/- k sigma drawn with the given opacity
+    pub fn band(mut self, k: f32, opacity: f32) -> Self {
+        self.band_sigmas = k;
+        self.band_opacity = opacity.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn color_by_uncertainty(mut self, color_by_uncertainty: bool) -> Self {
+        self.color_by_uncertainty = color_by_uncertainty;
+        self
+    }
+
+    // scalar field that drives the colors instead of the height
+    pub fn color_by(mut self, g: impl Fn(f32, f32) -> f32 + Send + 'static) -> Self {
+        self.color = Some(PlotSource::Function(Box::new(g)));
+        self
+    }
+
+    // color field values on the same grid as the plotted values
+    pub fn color_by_values(mut self, rows: u16, cols: u16, values: Vec<f32>) -> Self {
+        self.color = Some(PlotSource::Values { rows, cols, values });
+        self
+    }
+
+    pub fn color_range(mut self, min: f32, max: f32) -> Self {
+        self.color_range = Some([min, max]);
+        self
+    }
+
+    // grid points where the predicate holds are masked
+    pub fn mask(mut self, masked: impl Fn(f32, f32) -> bool + Send + 'static) -> Self {
+        let f = move |x, z| if masked(x, z) { 1.0 } else { 0.0 };
+        self.mask = Some(PlotSource::Function(Box::new(f)));
+        self
+    }
+
+    // mask flags on the same grid as the plotted values
+    pub fn mask_values(mut self, rows: u16, cols: u16, masked: Vec<bool>) -> Self {
+        let values = masked.iter().map(|&m| if m { 1.0 } else { 0.0 }).collect();
+        self.mask = Some(PlotSource::Values { rows, cols, values });
+        self
+    }
+
+    pub fn mask_mode(mut self, mask_mode: MaskMode) -> Self {
+        self.mask_mode = mask_mode;
+        self
+    }
+
+    // drives this plot from the given clock instead of the one of the window
+    pub fn clock(mut self, clock: &SharedClock) -> Self {
+        self.clock = Some(clock.clone());
+        self
+    }
+
+    pub fn steps_per_second(mut self, steps_per_second: f32) -> Self {
+        self.steps_per_second = steps_per_second.max(1e-3);
+        self
+    }
+
+    pub fn is_animated(&self) -> bool {
+        matches!(self.source, PlotSource::Animated(_)) || self.series.is_some()
+    }
+
+    // number of time steps of a series plot
+    pub fn series_len(&self) -> Option<usize> {
+        self.series.as_ref().map(|series| series.len())
+    }
+
+    // time step of a series plot at time t
+    pub fn step_at(&self, t: f32) -> Option<usize> {
+        let len = self.series_len().filter(|&len| len > 0)?;
+        Some(((t * self.steps_per_second).floor() as i64).rem_euclid(len as i64) as usize)
+    }
+
+    // clock time in the middle of a time step, for scrubbing
+    pub fn time_of_step(&self, step: usize) -> f32 {
+        (step as f32 + 0.5) / self.steps_per_second
+    }
+
+    // the step whose frame is drawn, None before the first frame has been loaded
+    pub fn shown_step(&self) -> Option<usize> {
+        self.shown_step
+    }
+
+    // moves the plot to time t; series plots pick up frames that finished loading
+    // in the meantime. Returns true if the plotted data changed.
+    pub fn set_time(&mut self, t: f32) -> bool {
+        let step = self.step_at(t);
+        let changed = t != self.time;
+        self.time = t;
+        let (Some(series), Some(step)) = (&mut self.series, step) else {
+            return changed && self.is_animated();
+        };
+        let shown = series.update(step);
+        let range_changed = self.value_range != series.value_range();
+        if shown == self.shown_step && !range_changed {
+            return false;
+        }
+        if let Some(frame) = shown.and_then(|s| series.get(s)) {
+            let [rows, cols] = series.size;
+            self.source = PlotSource::Values {
+                rows,
+                cols,
+                values: frame.to_vec(),
+            };
+        }
+        self.shown_step = shown;
+        self.value_range = series.value_range();
+        true
+    }
+
+    // slicing plane at the given x or z coordinate, clamped to the plot range
+    pub fn probe(mut self, axis: ProbeAxis, position: f32) -> Self {
+        self.set_probe(Some((axis, position)));
+        self
+    }
+
+    pub fn set_probe(&mut self, probe: Option<(ProbeAxis, f32)>) {
+        self.probe = probe.map(|(axis, c)| {
+            let [a, b] = self.probe_range(axis);
+            (axis, c.clamp(a.min(b), a.max(b)))
+        });
+    }
+
+    // data range the plane of the given axis moves in
+    pub fn probe_range(&self, axis: ProbeAxis) -> [f32; 2] {
+        match axis {
+            ProbeAxis::X => self.x_range,
+            ProbeAxis::Z => self.z_range,
+        }
+    }
+
+    // fractional grid index of the probe plane and the grid size along its axis
+    fn probe_index(&self, rows: u16, cols: u16) -> Option<(ProbeAxis, f32, u16)> {
+        let (axis, c) = self.probe?;
+        let [a, b] = self.probe_range(axis);
+        let n = match axis {
+            ProbeAxis::X => rows,
+            ProbeAxis::Z => cols,
+        };
+        if n < 2 {
+            return None;
+        }
+        let f = if b != a { (c - a) / (b - a) } else { 0.0 };
+        Some((axis, f.clamp(0.0, 1.0) * (n - 1) as f32, n))
+    }
+
+    // interpolates between the two grid lines next to the probe plane; `at` reads
+    // the grid point with the given row-major index
+    fn interpolate_section<T>(
+        &self,
+        rows: u16,
+        cols: u16,
+        at: impl Fn(usize) -> T,
+        lerp: impl Fn(T, T, f32) -> T,
+    ) -> Option<Vec<T>> {
+        let (axis, f, n) = self.probe_index(rows, cols)?;
+        let i0 = (f.floor() as u16).min(n - 2);
+        let t = f - i0 as f32;
+        let (i0, i1) = (i0 as usize, i0 as usize + 1);
+        let (rows, cols) = (rows as usize, cols as usize);
+        let section = match axis {
+            ProbeAxis::X => (0..cols)
+                .map(|j| lerp(at(i0 * cols + j), at(i1 * cols + j), t))
+                .collect(),
+            ProbeAxis::Z => (0..rows)
+                .map(|i| lerp(at(i * cols + i0), at(i * cols + i1), t))
+                .collect(),
+        };
+        Some(section)
+    }
+
+    // the intersection of the surface with the probe plane in data units, as
+    // (coordinate along the section, value) pairs; None without a probe
+    pub fn cross_section(&self) -> Option<Vec<[f32; 2]>> {
+        let (axis, _) = self.probe?;
+        let (rows, cols, values) = self.sample(&self.source);
+        let section =
+            self.interpolate_section(rows, cols, |k| values[k], |a, b, t| a + (b - a) * t)?;
+        let [a, b] = match axis {
+            ProbeAxis::X => self.z_range,
+            ProbeAxis::Z => self.x_range,
+        };
+        let n = section.len().max(2) - 1;
+        Some(
+            section
+                .into_iter()
+                .enumerate()
+                .map(|(k, v)| [a + (b - a) * k as f32 / n as f32, v])
+                .collect(),
+        )
+    }
+
+    // the intersection curve on the normalized mesh of surface_data()
+    fn probe_curve(&self, data: &ISurfaceOutput) -> Option<Vec<[f32; 3]>> {
+        self.probe?;
+        let (rows, cols, _) = self.sample(&self.source);
+        let lerp = |p: [f32; 3], q: [f32; 3], t: f32| -> [f32; 3] {
+            [0, 1, 2].map(|k| p[k] + (q[k] - p[k]) * t)
+        };
+        self.interpolate_section(rows, cols, |k| data.positions[k], lerp)
+    }
+
+    // data range of the color field, None without one
+    pub fn color_field_range(&self) -> Option<[f32; 2]> {
+        let source = self.color.as_ref()?;
+        if let Some(range) = self.color_range {
+            return Some(range);
+        }
+        let values = self.sample(source).2;
+        let vmin = values.iter().copied().fold(f32::MAX, f32::min);
+        let vmax = values.iter().copied().fold(f32::MIN, f32::max);
+        Some(if vmax > vmin {
+            [vmin, vmax]
+        } else {
+            [vmin, vmin + 1.0]
+        })
+    }
+
+    // data range the colorbar spans: that of the color field, else of the heights
+    pub fn colorbar_range(&self) -> [f32; 2] {
+        if let Some(range) = self.color_field_range() {
+            return range;
+        }
+        let (ss, _) = self.streaming_surface();
+        ss.value_range.unwrap_or_else(|| {
+            let visible = || {
+                (ss.values.iter().enumerate())
+                    .filter(|(k, _)| !ss.is_masked(*k))
+                    .map(|(_, v)| *v)
+            };
+            let vmin = visible().fold(f32::MAX, f32::min);
+            let vmax = visible().fold(f32::MIN, f32::max);
+            if vmax > vmin {
+                [vmin, vmax]
+            } else {
+                [vmin, vmin + 1.0]
+            }
+        })
+    }
+
+    // samples a source on the plot grid; non-finite values become zero
+    fn sample(&self, source: &PlotSource) -> (u16, u16, Vec<f32>) {
+        let grid = |f: &dyn Fn(f32, f32) -> f32| {
+            let n = self.resolution.clamp(2, 256);
+            let [x0, x1] = self.x_range;
+            let [z0, z1] = self.z_range;
+            let step = |a: f32, b: f32, k: u16| a + (b - a) * k as f32 / (n - 1) as f32;
+            let values = (0..n)
+                .flat_map(|i| (0..n).map(move |j| (i, j)))
+                .map(|(i, j)| f(step(x0, x1, i), step(z0, z1, j)))
+                .collect();
+            (n, n, values)
+        };
+        let (rows, cols, values) = match source {
+            PlotSource::Function(f) => grid(f),
+            PlotSource::Animated(f) => grid(&|x, z| f(x, z, self.time)),
+            PlotSource::Values { rows, cols, values } => {
+                ((*rows).min(256), (*cols).min(256), values.clone())
+            }
+        };
+        let values = values
+            .into_iter()
+            .map(|v| if v.is_finite() { v } else { 0.0 })
+            .collect();
+        (rows, cols, values)
+    }
+
+    // the value grid and the matching sigmas (empty without uncertainty); with a band
+    // the normalization is widened so the envelope fits into the same box
+    fn streaming_surface(&self) -> (IStreamingSurface, Vec<f32>) {
+        let mut ss = IStreamingSurface {
+            aspect_ratio: self.aspect_ratio,
+            colormap_name: self.colormap_name.clone(),
+            wireframe_color: self.wireframe_color.clone(),
+            ..Default::default()
+        };
+        let (rows, cols, values) = self.sample(&self.source);
+        ss.resize(rows, cols);
+        ss.set_frame(&values);
+        ss.value_range = self.value_range;
+        if let Some(source) = &self.mask {
+            ss.mask = self.sample(source).2.iter().map(|&v| v != 0.0).collect();
+            ss.mask_mode = self.mask_mode;
+        }
+        let sigmas = match &self.sigma {
+            Some(source) => self.sample(source).2,
+            None => vec![],
+        };
+        if !sigmas.is_empty() && self.band_sigmas > 0.0 {
+            ss.value_range = Some(ss.band_range(&sigmas, self.band_sigmas));
+        }
+        (ss, sigmas)
+    }
+
+    // samples the function on the grid (or takes the given values) and builds the
+    // normalized mesh; non-finite values are drawn as zero
+    pub fn surface_data(&self) -> ISurfaceOutput {
+        let (mut ss, sigmas) = self.streaming_surface();
+        let mut data = ss.new();
+        if let (Some(source), Some([vmin, vmax])) = (&self.color, self.color_field_range()) {
+            let cdata = colormap::colormap_data(&self.colormap_name);
+            let values = self.sample(source).2;
+            for (k, color) in data.colors.iter_mut().enumerate() {
+                let v = values.get(k).copied().unwrap_or(vmin);
+                *color = colormap::color_lerp(cdata, vmin, vmax, v);
+            }
+            ss.dim_masked(&mut data.colors);
+        }
+        if self.color_by_uncertainty {
+            let sigma_max = sigmas.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+            sd::modulate_by_uncertainty(&mut data.colors, &sigmas, sigma_max);
+        }
+        data
+    }
+
+    // upper and lower envelope surfaces, or None without uncertainty or band
+    pub fn band_data(&self) -> Option<[ISurfaceOutput; 2]> {
+        let (mut ss, sigmas) = self.streaming_surface();
+        if sigmas.is_empty() || self.band_sigmas <= 0.0 {
+            return None;
+        }
+        Some([
+            ss.offset_surface(&sigmas, self.band_sigmas),
+            ss.offset_surface(&sigmas, -self.band_sigmas),
+        ])
+    }
+
+    fn apply(&mut self, command: PlotCommand) {
+        match command {
+            PlotCommand::SetFunction(f) => self.source = PlotSource::Function(f),
+            PlotCommand::SetValues { rows, cols, values } => {
+                self.source = PlotSource::Values { rows, cols, values }
+            }
+            PlotCommand::SetUncertaintyValues { rows, cols, values } => {
+                self.sigma = Some(PlotSource::Values { rows, cols, values })
+            }
+            PlotCommand::SetColormap(colormap_name) => self.colormap_name = colormap_name,
+            PlotCommand::Close => {}
+        }
+    }
+
+    // opens a window showing the plot and blocks until it is closed
+    pub fn show(self) -> anyhow::Result<()> {
+        Subplots::from(self).show()
+    }
+
+    // opens the window on a separate thread and returns immediately; the handle
+    // updates the plot from the calling thread. Not available on macOS, where the
+    // event loop has to run on the main thread.
+    pub fn show_async(self) -> anyhow::Result<PlotHandle> {
+        Subplots::from(self).show_async()
+    }
+}
+// endregion: surface plot
+
+// region: subplots
+// A grid of independent plots in one window, addressed by index in row-major
+// order like matplotlib:
+//
+//     subplots(1, 2)
+//         .subplot(0, plot_surface(|x, z| x * z))
+//         .subplot(1, plot_surface(|x, z| x * x - z * z).colormap("cool"))
+//         .show()?;
+//
+// Every viewport has its own camera and colorbar; the mouse acts on the viewport
+// under the cursor. Animated subplots without a clock of their own share the one
+// of the window, so they always show the same t.
+pub struct Subplots {
+    pub rows: usize,
+    pub cols: usize,
+    plots: Vec<SurfacePlot>,
+    pub title: String,
+    pub sample_count: u32,
+    clock: SharedClock,
+}
+
+pub fn subplots(rows: usize, cols: usize) -> Subplots {
+    let (rows, cols) = (rows.max(1), cols.max(1));
+    Subplots {
+        rows,
+        cols,
+        plots: (0..rows * cols).map(|_| plot_surface(|_, _| 0.0)).collect(),
+        title: "wgpu surfaces plot".to_string(),
+        sample_count: 1,
+        clock: SharedClock::new(),
+    }
+}
+
+impl From<SurfacePlot> for Subplots {
+    fn from(plot: SurfacePlot) -> Self {
+        Self {
+            rows: 1,
+            cols: 1,
+            title: plot.title.clone(),
+            sample_count: plot.sample_count,
+            clock: plot.clock.clone().unwrap_or_default(),
+            plots: vec![plot],
+        }
+    }
+}
+
+impl Subplots {
+    // replaces the plot at the given index; panics if the index is outside the grid
+    pub fn subplot(mut self, index: usize, plot: SurfacePlot) -> Self {
+        assert!(
+            index < self.plots.len(),
+            "subplot index {index} out of range for a {} x {} grid",
+            self.rows,
+            self.cols
+        );
+        self.plots[index] = plot;
+        self
+    }
+
+    pub fn get(&self, index: usize) -> Option<&SurfacePlot> {
+        self.plots.get(index)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut SurfacePlot> {
+        self.plots.get_mut(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.plots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.plots.is_empty()
+    }
+
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    pub fn sample_count(mut self, sample_count: u32) -> Self {
+        self.sample_count = sample_count;
+        self
+    }
+
+    // replaces the window clock, e.g. to share it with another window or thread
+    pub fn clock(mut self, clock: &SharedClock) -> Self {
+        self.clock = clock.clone();
+        self
+    }
+
+    // the clock of the window, to pause or scrub all animated subplots at once
+    pub fn shared_clock(&self) -> &SharedClock {
+        &self.clock
+    }
+
+    // the clock that drives the subplot at the given index
+    pub fn clock_of(&self, index: usize) -> &SharedClock {
+        match self.plots.get(index).and_then(|plot| plot.clock.as_ref()) {
+            Some(clock) => clock,
+            None => &self.clock,
+        }
+    }
+
+    // reads the time of every animated subplot from its own clock or the window
+    // clock; returns the indices of the subplots whose data changed
+    pub fn advance(&mut self) -> Vec<usize> {
+        let mut changed = vec![];
+        for (i, plot) in self.plots.iter_mut().enumerate() {
+            if !plot.is_animated() {
+                continue;
+            }
+            let t = plot.clock.as_ref().unwrap_or(&self.clock).time();
+            if plot.set_time(t) {
+                changed.push(i);
+            }
+        }
+        changed
+    }
+
+    pub fn is_animated(&self) -> bool {
+        self.plots.iter().any(|plot| plot.is_animated())
+    }
+
+    // pixel rectangle [x, y, width, height] of a viewport in a window of the given size
+    pub fn viewport(&self, index: usize, size: [u32; 2]) -> [f32; 4] {
+        let (row, col) = (index / self.cols, index % self.cols);
+        let w = size[0] as f32 / self.cols as f32;
+        let h = size[1] as f32 / self.rows as f32;
+        [col as f32 * w, row as f32 * h, w, h]
+    }
+
+    // index of the viewport containing the cursor position
+    pub fn viewport_at(&self, cursor: [f32; 2], size: [u32; 2]) -> Option<usize> {
+        let col = (cursor[0] * self.cols as f32 / size[0] as f32).floor();
+        let row = (cursor[1] * self.rows as f32 / size[1] as f32).floor();
+        if col < 0.0 || row < 0.0 || col >= self.cols as f32 || row >= self.rows as f32 {
+            return None;
+        }
+        Some(row as usize * self.cols + col as usize)
+    }
+
+    // opens a window showing all subplots and blocks until it is closed
+    pub fn show(self) -> anyhow::Result<()> {
+        let event_loop = EventLoop::<PlotEvent>::with_user_event().build()?;
+        self.run(event_loop)
+    }
+
+    // like SurfacePlot::show_async; PlotHandle::subplot addresses the viewports
+    pub fn show_async(self) -> anyhow::Result<PlotHandle> {
+        let (sender, receiver) = mpsc::channel();
+        let thread = std::thread::spawn(move || {
+            let event_loop = match Self::any_thread_event_loop() {
+                Ok(event_loop) => event_loop,
+                Err(e) => {
+                    let message = e.to_string();
+                    let _ = sender.send(Err(e));
+                    return Err(anyhow::anyhow!(message));
+                }
+            };
+            let _ = sender.send(Ok(event_loop.create_proxy()));
+            self.run(event_loop)
+        });
+        match receiver.recv() {
+            Ok(Ok(proxy)) => Ok(PlotHandle {
+                proxy,
+                thread: Some(thread),
+            }),
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(anyhow::anyhow!(
+                "plot thread exited before the window opened"
+            )),
+        }
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    fn any_thread_event_loop() -> anyhow::Result<EventLoop<PlotEvent>> {
+        use winit::platform::x11::EventLoopBuilderExtX11;
+        // sets the same flag for wayland
+        let mut builder: winit::event_loop::EventLoopBuilder<PlotEvent> = EventLoop::with_user_event();
+        Ok(builder.with_any_thread(true).build()?)
+    }
+
+    #[cfg(target_os = "windows")]
+    fn any_thread_event_loop() -> anyhow::Result<EventLoop<PlotEvent>> {
+        use winit::platform::windows::EventLoopBuilderExtWindows;
+        let mut builder: winit::event_loop::EventLoopBuilder<PlotEvent> = EventLoop::with_user_event();
+        Ok(builder.with_any_thread(true).build()?)
+    }
+
+    #[cfg(not(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "windows"
+    )))]
+    fn any_thread_event_loop() -> anyhow::Result<EventLoop<PlotEvent>> {
+        anyhow::bail!("show_async is not supported on this platform, use show instead")
+    }
+
+    fn run(self, event_loop: EventLoop<PlotEvent>) -> anyhow::Result<()> {
+        let mut app = PlotApp {
+            plots: self,
+            state: None,
+            error: None,
+        };
+        event_loop.run_app(&mut app)?;
+        match app.error {
+            Some(e) => Err(e.into()),
+            None => Ok(()),
+        }
+    }
+}
+// endregion: subplots
+
+// region: plot handle
+pub enum PlotCommand {
+    SetFunction(Box<dyn Fn(f32, f32) -> f32 + Send>),
+    SetValues {
+        rows: u16,
+        cols: u16,
+        values: Vec<f32>,
+    }, // row-major, rows along x
+    SetUncertaintyValues {
+        rows: u16,
+        cols: u16,
+        values: Vec<f32>,
+    }, // standard deviations on the same grid
+    SetColormap(String),
+    Close,
+}
+
+// a command for the subplot with the given index
+type PlotEvent = (usize, PlotCommand);
+
+// Returned by show_async. Every method fails once the window has been closed, so a
+// long-running computation can stop pushing data. The setters act on the first
+// subplot, subplot(index) addresses the others.
+pub struct PlotHandle {
+    proxy: EventLoopProxy<PlotEvent>,
+    thread: Option<JoinHandle<anyhow::Result<()>>>,
+}
+
+pub struct SubplotHandle<'a> {
+    proxy: &'a EventLoopProxy<PlotEvent>,
+    index: usize,
+}
+
+impl SubplotHandle<'_> {
+    fn send(&self, command: PlotCommand) -> anyhow::Result<()> {
+        self.proxy
+            .send_event((self.index, command))
+            .map_err(|_| anyhow::anyhow!("plot window is closed"))
+    }
+
+    // replaces the plotted function; it is sampled with the current ranges and resolution
+    pub fn set_function(&self, f: impl Fn(f32, f32) -> f32 + Send + 'static) -> anyhow::Result<()> {
+        self.send(PlotCommand::SetFunction(Box::new(f)))
+    }
+
+    // replaces the plot with a grid of values (at most 256 x 256)
+    pub fn set_values(&self, rows: u16, cols: u16, values: Vec<f32>) -> anyhow::Result<()> {
+        self.send(PlotCommand::SetValues { rows, cols, values })
+    }
+
+    pub fn set_uncertainty_values(
+        &self,
+        rows: u16,
+        cols: u16,
+        values: Vec<f32>,
+    ) -> anyhow::Result<()> {
+        self.send(PlotCommand::SetUncertaintyValues { rows, cols, values })
+    }
+
+    pub fn set_colormap(&self, colormap_name: &str) -> anyhow::Result<()> {
+        self.send(PlotCommand::SetColormap(colormap_name.to_string()))
+    }
+}
+
+impl PlotHandle {
+    // commands for an index outside the grid are ignored
+    pub fn subplot(&self, index: usize) -> SubplotHandle<'_> {
+        SubplotHandle {
+            proxy: &self.proxy,
+            index,
+        }
+    }
+
+    pub fn set_function(&self, f: impl Fn(f32, f32) -> f32 + Send + 'static) -> anyhow::Result<()> {
+        self.subplot(0).set_function(f)
+    }
+
+    pub fn set_values(&self, rows: u16, cols: u16, values: Vec<f32>) -> anyhow::Result<()> {
+        self.subplot(0).set_values(rows, cols, values)
+    }
+
+    pub fn set_uncertainty_values(
+        &self,
+        rows: u16,
+        cols: u16,
+        values: Vec<f32>,
+    ) -> anyhow::Result<()> {
+        self.subplot(0).set_uncertainty_values(rows, cols, values)
+    }
+
+    pub fn set_colormap(&self, colormap_name: &str) -> anyhow::Result<()> {
+        self.subplot(0).set_colormap(colormap_name)
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.thread
+            .as_ref()
+            .is_some_and(|thread| !thread.is_finished())
+    }
+
+    // closes the window and waits for the plot thread
+    pub fn close(mut self) -> anyhow::Result<()> {
+        let _ = self.subplot(0).send(PlotCommand::Close);
+        self.join()
+    }
+
+    // blocks until the user closes the window
+    pub fn wait(mut self) -> anyhow::Result<()> {
+        self.join()
+    }
+
+    fn join(&mut self) -> anyhow::Result<()> {
+        match self.thread.take() {
+            Some(thread) => thread
+                .join()
+                .map_err(|_| anyhow::anyhow!("plot thread panicked"))?,
+            None => Ok(()),
+        }
+    }
+}
+// endregion: plot handle
+
+// region: renderer
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct OverlayVertex {
+    position: [f32; 3],
+    color: [f32; 3],
+}
+
+// extent of the colorbar at a ui scale of 1, in clip space of the viewport; the ui
+// scale widens it to the left
+const COLORBAR_X: [f32; 2] = [0.82, 0.88];
+const COLORBAR_Y: [f32; 2] = [-0.7, 0.7];
+const COLORBAR_TICKS: usize = 5;
+
+fn colorbar_left(ui_scale: f32) -> f32 {
+    let [x0, x1] = COLORBAR_X;
+    x1 - (x1 - x0) * ui_scale
+}
+
+// vertical strip at the right edge of a viewport, in clip space
+fn colorbar_vertices(colormap_name: &str, ui_scale: f32) -> Vec<OverlayVertex> {
+    let cdata = colormap::colormap_data(colormap_name);
+    let ([x0, x1], [y0, y1]) = ([colorbar_left(ui_scale), COLORBAR_X[1]], COLORBAR_Y);
+    let n = cdata.len() - 1;
+    let mut vertices = vec![];
+    for k in 0..n {
+        let ya = y0 + (y1 - y0) * k as f32 / n as f32;
+        let yb = y0 + (y1 - y0) * (k + 1) as f32 / n as f32;
+        let v = |x: f32, y: f32, color: [f32; 3]| OverlayVertex {
+            position: [x, y, 0.0],
+            color,
+        };
+        let (ca, cb) = (cdata[k], cdata[k + 1]);
+        vertices.extend([
+            v(x0, ya, ca),
+            v(x1, ya, ca),
+            v(x1, yb, cb),
+            v(x1, yb, cb),
+            v(x0, yb, cb),
+            v(x0, ya, ca),
+        ]);
+    }
+    vertices
+}
+
+// tick marks on the left of the colorbar with their labels as stroke text, in clip
+// space for the overlay line pipeline; a glyph is as wide as it is tall in clip units
+fn colorbar_tick_vertices(range: [f32; 2], plot: &SurfacePlot) -> Vec<OverlayVertex> {
+    let scale = plot.ui_scale;
+    let palette = plot.theme.palette();
+    let (x0, [y0, y1]) = (colorbar_left(scale), COLORBAR_Y);
+    let height = 0.04 * plot.theme.text_scale() * scale;
+    let mut vertices = vec![];
+    let mut line = |a: [f32; 2], b: [f32; 2], color: [f32; 3]| {
+        vertices.push(line_vertex([a[0], a[1], 0.0], color));
+        vertices.push(line_vertex([b[0], b[1], 0.0], color));
+    };
+    let n = COLORBAR_TICKS - 1;
+    let ticks = plot.number_format.ticks(range, COLORBAR_TICKS);
+    for (k, (_, label)) in ticks.iter().enumerate() {
+        let y = y0 + (y1 - y0) * k as f32 / n as f32;
+        line([x0 - 0.015 * scale, y], [x0, y], palette.frame);
+        // right-aligned, centered on the tick; text_segments has y down
+        let width = height * label.chars().count() as f32;
+        let origin = [x0 - 0.025 * scale - width, -(y + 0.5 * height)];
+        for [a, b] in overlay::text_segments(label, origin, height) {
+            line([a[0], -a[1]], [b[0], -b[1]], palette.text);
+        }
+    }
+    vertices
+}
+
+// extent of the time slider of series plots, in clip space of the viewport
+const SLIDER_X: [f32; 2] = [-0.8, 0.8];
+const SLIDER_Y: [f32; 2] = [-0.9, -0.88];
+
+// horizontal time slider at the bottom of a viewport, in clip space; the handle
+// turns orange while the frame of its step is still loading
+fn slider_vertices(fraction: f32, loading: bool) -> Vec<OverlayVertex> {
+    let [x0, x1] = SLIDER_X;
+    let [y0, y1] = SLIDER_Y;
+    let xh = x0 + (x1 - x0) * fraction.clamp(0.0, 1.0);
+    let quad = |[xa, xb]: [f32; 2], [ya, yb]: [f32; 2], z: f32, color: [f32; 3]| {
+        let v = |x: f32, y: f32| OverlayVertex {
+            position: [x, y, z],
+            color,
+        };
+        [
+            v(xa, ya),
+            v(xb, ya),
+            v(xb, yb),
+            v(xb, yb),
+            v(xa, yb),
+            v(xa, ya),
+        ]
+    };
+    let handle_color = if loading { [1.0, 0.6, 0.1] } else { [1.0; 3] };
+    // the handle is drawn in front of the track
+    [
+        quad([x0, xh], [y0, y1], 0.001, [0.7; 3]),
+        quad([xh, x1], [y0, y1], 0.001, [0.3; 3]),
+        quad(
+            [xh - 0.012, xh + 0.012],
+            [y0 - 0.04, y1 + 0.04],
+            0.0,
+            handle_color,
+        ),
+    ]
+    .concat()
+}
+
+fn line_vertex(position: [f32; 3], color: [f32; 3]) -> OverlayVertex {
+    OverlayVertex { position, color }
+}
+
+// outline of the probe plane and the intersection curve, in world space; the curve
+// is lifted a little so it is not hidden by the surface it lies on
+fn probe_vertices(curve: &[[f32; 3]], height: f32, palette: &Palette) -> Vec<OverlayVertex> {
+    let (first, last) = (curve[0], curve[curve.len() - 1]);
+    let corners = [
+        [first[0], -height, first[2]],
+        [last[0], -height, last[2]],
+        [last[0], height, last[2]],
+        [first[0], height, first[2]],
+    ];
+    let mut vertices = vec![];
+    for k in 0..4 {
+        vertices.push(line_vertex(corners[k], [0.7; 3]));
+        vertices.push(line_vertex(corners[(k + 1) % 4], [0.7; 3]));
+    }
+    for pair in curve.windows(2) {
+        for p in pair {
+            vertices.push(line_vertex([p[0], p[1] + 0.005, p[2]], palette.highlight));
+        }
+    }
+    vertices
+}
+
+// line chart of the cross-section in the top left corner of a viewport, in clip
+// space: the background triangles and the lines (frame, quarter grid, curve); the ui
+// scale grows it from that corner
+fn inset_vertices(
+    section: &[[f32; 2]],
+    palette: &Palette,
+    ui_scale: f32,
+) -> (Vec<OverlayVertex>, Vec<OverlayVertex>) {
+    let (x0, y1) = (-0.95, 0.95);
+    let (x1, y0) = (x0 + 0.6 * ui_scale, y1 - 0.5 * ui_scale);
+    let background = [[x0, y0], [x1, y0], [x1, y1], [x1, y1], [x0, y1], [x0, y0]]
+        .map(|[x, y]| line_vertex([x, y, 0.002], palette.background))
+        .to_vec();
+
+    let mut lines = vec![];
+    let mut line = |a: [f32; 2], b: [f32; 2], color: [f32; 3]| {
+        lines.push(line_vertex([a[0], a[1], 0.001], color));
+        lines.push(line_vertex([b[0], b[1], 0.001], color));
+    };
+    for k in 1..4 {
+        let y = y0 + (y1 - y0) * k as f32 / 4.0;
+        line([x0, y], [x1, y], palette.grid);
+    }
+    let corners = [[x0, y0], [x1, y0], [x1, y1], [x0, y1]];
+    for k in 0..4 {
+        line(corners[k], corners[(k + 1) % 4], palette.frame);
+    }
+
+    let (u0, u1) = (section[0][0], section[section.len() - 1][0]);
+    let vmin = section.iter().map(|p| p[1]).fold(f32::MAX, f32::min);
+    let vmax = section.iter().map(|p| p[1]).fold(f32::MIN, f32::max);
+    let pad = if vmax > vmin {
+        0.05 * (vmax - vmin)
+    } else {
+        1.0
+    };
+    let (v0, v1) = (vmin - pad, vmax + pad);
+    let to_inset = |[u, v]: [f32; 2]| {
+        let fu = if u1 != u0 { (u - u0) / (u1 - u0) } else { 0.5 };
+        [x0 + (x1 - x0) * fu, y0 + (y1 - y0) * (v - v0) / (v1 - v0)]
+    };
+    for pair in section.windows(2) {
+        line(to_inset(pair[0]), to_inset(pair[1]), palette.highlight);
+    }
+    (background, lines)
+}
+
+// GPU resources and view of one subplot
+struct Panel {
+    lighting: bool,
+    vertex_buffers: [wgpu::Buffer; 2], // surface, wireframe
+    index_buffers: [wgpu::Buffer; 2],
+    // what the vertex and index buffers hold, to write only what changes
+    mesh_data: [(Vec<f32>, Vec<u16>); 2],
+    colorbar_buffer: wgpu::Buffer,
+    colorbar_count: u32,
+    tick_buffer: wgpu::Buffer, // colorbar ticks and labels
+    tick_count: u32,
+    // upper and lower envelope with their centroids, sharing the surface indices
+    band_buffers: Vec<(wgpu::Buffer, Point3<f32>)>,
+    band_opacity: f32,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    camera: ws::OrbitCamera,
+    plot_type: u32,
+    slider_buffer: Option<wgpu::Buffer>, // series plots only
+    probe_buffers: Vec<(usize, wgpu::Buffer, u32)>, // pipeline index, vertices, count
+}
+
+impl Panel {
+    fn new(init: &ws::InitWgpu, layout: &wgpu::BindGroupLayout, plot: &SurfacePlot) -> Self {
+        let uniform_buffer = init.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Plot Uniform Buffer"),
+            size: 224,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = init.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+            label: Some("Plot Bind Group"),
+        });
+        let data = plot.surface_data();
+        let (vertex_buffers, index_buffers, mesh_data) = Self::create_buffers(init, plot, &data);
+        let colorbar = colorbar_vertices(&plot.colormap_name, plot.ui_scale);
+        let ticks = colorbar_tick_vertices(plot.colorbar_range(), plot);
+        Self {
+            lighting: plot.lighting,
+            vertex_buffers,
+            index_buffers,
+            mesh_data,
+            colorbar_buffer: Self::create_vertex_buffer(init, "Colorbar Buffer", &colorbar),
+            colorbar_count: colorbar.len() as u32,
+            tick_buffer: Self::create_vertex_buffer(init, "Colorbar Tick Buffer", &ticks),
+            tick_count: ticks.len() as u32,
+            band_buffers: Self::create_band_buffers(init, plot),
+            band_opacity: plot.band_opacity,
+            uniform_buffer,
+            bind_group,
+            camera: ws::OrbitCamera::new((2.0, 2.0, 2.0).into(), (0.0, 0.0, 0.0).into()),
+            plot_type: plot.plot_type,
+            slider_buffer: plot.series_len().map(|_| {
+                Self::create_vertex_buffer(init, "Slider Buffer", &slider_vertices(0.0, true))
+            }),
+            probe_buffers: Self::create_probe_buffers(init, plot, &data),
+        }
+    }
+
+    // the vertex count may change, so the buffers are recreated unless the mesh keeps
+    // its size, e.g. a streaming surface, which only gets the changed bytes written
+    fn set_data(&mut self, init: &ws::InitWgpu, plot: &SurfacePlot) {
+        let data = plot.surface_data();
+        if !self.write_changes(init, plot, &data) {
+            (self.vertex_buffers, self.index_buffers, self.mesh_data) =
+                Self::create_buffers(init, plot, &data);
+        }
+        self.lighting = plot.lighting;
+        let colorbar = colorbar_vertices(&plot.colormap_name, plot.ui_scale);
+        self.colorbar_buffer = Self::create_vertex_buffer(init, "Colorbar Buffer", &colorbar);
+        let ticks = colorbar_tick_vertices(plot.colorbar_range(), plot);
+        self.tick_buffer = Self::create_vertex_buffer(init, "Colorbar Tick Buffer", &ticks);
+        self.tick_count = ticks.len() as u32;
+        self.band_buffers = Self::create_band_buffers(init, plot);
+        self.band_opacity = plot.band_opacity;
+        self.probe_buffers = Self::create_probe_buffers(init, plot, &data);
+    }
+
+    // writes the dirty ranges of the buffers if the data fits them as they are; returns
+    // false if they have to be recreated
+    fn write_changes(
+        &mut self,
+        init: &ws::InitWgpu,
+        plot: &SurfacePlot,
+        data: &ISurfaceOutput,
+    ) -> bool {
+        let attributes = plot.vertex_attributes();
+        let mesh = MeshBuilder::with_attributes(attributes).build(data);
+        let new_data = [
+            (mesh.vertices, data.indices.clone()),
+            (mesh.wire_vertices, data.indices2.clone()),
+        ];
+        let same_size = self
+            .mesh_data
+            .iter()
+            .zip(&new_data)
+            .all(|(old, new)| old.0.len() == new.0.len() && old.1.len() == new.1.len());
+        if plot.lighting != self.lighting || !same_size {
+            return false;
+        }
+        let stride = attributes.stride() as usize;
+        for (i, (vertices, indices)) in new_data.iter().enumerate() {
+            let (old_vertices, old_indices) = &self.mesh_data[i];
+            ws::DirtyRanges::diff(cast_slice(old_vertices), cast_slice(vertices), stride).write(
+                &init.queue,
+                &self.vertex_buffers[i],
+                cast_slice(vertices),
+            );
+            // a triangle at a time
+            ws::DirtyRanges::diff(cast_slice(old_indices), cast_slice(indices), 6).write(
+                &init.queue,
+                &self.index_buffers[i],
+                cast_slice(indices),
+            );
+        }
+        self.mesh_data = new_data;
+        true
+    }
+
+    // only the probe moved, the surface stays
+    fn set_probe(&mut self, init: &ws::InitWgpu, plot: &SurfacePlot) {
+        self.probe_buffers = Self::create_probe_buffers(init, plot, &plot.surface_data());
+    }
+
+    fn create_probe_buffers(
+        init: &ws::InitWgpu,
+        plot: &SurfacePlot,
+        data: &ISurfaceOutput,
+    ) -> Vec<(usize, wgpu::Buffer, u32)> {
+        let (Some(curve), Some(section)) = (plot.probe_curve(data), plot.cross_section()) else {
+            return vec![];
+        };
+        let height = plot.aspect_ratio;
+        let palette = plot.theme.palette();
+        let (background, inset) = inset_vertices(&section, &palette, plot.ui_scale);
+        [
+            (4, probe_vertices(&curve, height, &palette)),
+            (2, background),
+            (3, inset),
+        ]
+        .into_iter()
+        .map(|(pipeline, vertices)| {
+            let buffer = Self::create_vertex_buffer(init, "Probe Buffer", &vertices);
+            (pipeline, buffer, vertices.len() as u32)
+        })
+        .collect()
+    }
+
+    fn create_band_buffers(
+        init: &ws::InitWgpu,
+        plot: &SurfacePlot,
+    ) -> Vec<(wgpu::Buffer, Point3<f32>)> {
+        let builder = MeshBuilder::with_attributes(plot.vertex_attributes());
+        plot.band_data()
+            .into_iter()
+            .flatten()
+            .map(|data| {
+                let vertices = builder.vertices(&data.positions, &data.normals, &data.colors, &[]);
+                let n = data.positions.len().max(1) as f32;
+                let sum = data
+                    .positions
+                    .iter()
+                    .fold([0.0; 3], |s, p| [s[0] + p[0], s[1] + p[1], s[2] + p[2]]);
+                let centroid = Point3::new(sum[0] / n, sum[1] / n, sum[2] / n);
+                let buffer = Self::create_vertex_buffer(init, "Band Vertex Buffer", &vertices);
+                (buffer, centroid)
+            })
+            .collect()
+    }
+
+    fn create_vertex_buffer<T: Pod>(
+        init: &ws::InitWgpu,
+        label: &str,
+        contents: &[T],
+    ) -> wgpu::Buffer {
+        init.device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents: cast_slice(contents),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            })
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn create_buffers(
+        init: &ws::InitWgpu,
+        plot: &SurfacePlot,
+        data: &ISurfaceOutput,
+    ) -> (
+        [wgpu::Buffer; 2],
+        [wgpu::Buffer; 2],
+        [(Vec<f32>, Vec<u16>); 2],
+    ) {
+        let mesh = MeshBuilder::with_attributes(plot.vertex_attributes()).build(data);
+        let index_buffer = |label: &str, contents: &[u16]| {
+            init.device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(label),
+                    contents: cast_slice(contents),
+                    usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+                })
+        };
+        (
+            [
+                Self::create_vertex_buffer(init, "Plot Vertex Buffer", &mesh.vertices),
+                Self::create_vertex_buffer(init, "Plot Vertex Buffer 2", &mesh.wire_vertices),
+            ],
+            [
+                index_buffer("Plot Index Buffer", &data.indices),
+                index_buffer("Plot Index Buffer 2", &data.indices2),
+            ],
+            [
+                (mesh.vertices, data.indices.clone()),
+                (mesh.wire_vertices, data.indices2.clone()),
+            ],
+        )
+    }
+
+    fn view_project_mat(&self, aspect: f32) -> Matrix4<f32> {
+        ws::create_projection_mat(aspect, true) * self.camera.view_mat()
+    }
+
+    fn write_uniforms(&self, init: &ws::InitWgpu, aspect: f32) {
+        let eye = self.camera.eye();
+        let vp_mat = self.view_project_mat(aspect);
+        let identity = Matrix4::<f32>::identity();
+        let vp_ref: &[f32; 16] = vp_mat.as_ref();
+        let identity_ref: &[f32; 16] = identity.as_ref();
+        init.queue
+            .write_buffer(&self.uniform_buffer, 0, cast_slice(vp_ref));
+        init.queue
+            .write_buffer(&self.uniform_buffer, 64, cast_slice(identity_ref));
+        init.queue
+            .write_buffer(&self.uniform_buffer, 128, cast_slice(identity_ref));
+        let light = [-0.5f32, -0.5, -0.5, 0.0, eye.x, eye.y, eye.z, 1.0];
+        init.queue
+            .write_buffer(&self.uniform_buffer, 192, cast_slice(&light));
+    }
+
+    fn write_slider(&self, init: &ws::InitWgpu, plot: &SurfacePlot) {
+        let (Some(buffer), Some(len), Some(step)) = (
+            &self.slider_buffer,
+            plot.series_len(),
+            plot.step_at(plot.time),
+        ) else {
+            return;
+        };
+        let fraction = (step as f32 + 0.5) / len as f32;
+        let vertices = slider_vertices(fraction, plot.shown_step() != Some(step));
+        init.queue.write_buffer(buffer, 0, cast_slice(&vertices));
+    }
+
+    // pipelines: shape, wireframe, colorbar, overlay lines, world lines, transparent shape,
+    // then unlit shape and unlit transparent shape; the draws go through a DrawList,
+    // returns its stats
+    fn draw(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        pipelines: &[wgpu::RenderPipeline],
+    ) -> SortStats {
+        let mut list = DrawList::default();
+        let indexed = |i: usize| {
+            let format = wgpu::IndexFormat::Uint16;
+            let count = self.mesh_data[i].1.len() as u32;
+            Draw::indexed(
+                &self.vertex_buffers[i],
+                &self.index_buffers[i],
+                format,
+                count,
+            )
+        };
+        // unlit surfaces draw their wireframe with the world line pipeline
+        let [shape, wireframe, band] = if self.lighting { [0, 1, 5] } else { [6, 4, 7] };
+        for (i, pipeline) in [shape, wireframe].into_iter().enumerate() {
+            // plot_type 1 skips the wireframe, 2 skips the shape
+            if self.plot_type != 2 - i as u32 {
+                list.push(DrawKey::opaque(pipeline, 0), indexed(i));
+            }
+        }
+        // blended over everything opaque, the farther envelope first
+        let a = self.band_opacity as f64;
+        let eye = self.camera.eye();
+        for (buffer, centroid) in self.band_buffers.iter() {
+            let draw = Draw {
+                vertex_buffer: buffer.slice(..),
+                blend_constant: Some(wgpu::Color {
+                    r: a,
+                    g: a,
+                    b: a,
+                    a,
+                }),
+                ..indexed(0)
+            };
+            list.push(DrawKey::blended(band, 0, eye.distance(*centroid)), draw);
+        }
+        list.push(
+            DrawKey::opaque(2, 0),
+            Draw::vertices(&self.colorbar_buffer, self.colorbar_count),
+        );
+        list.push(
+            DrawKey::opaque(3, 0),
+            Draw::vertices(&self.tick_buffer, self.tick_count),
+        );
+        if let Some(buffer) = &self.slider_buffer {
+            list.push(DrawKey::opaque(2, 0), Draw::vertices(buffer, 18));
+        }
+        for (pipeline, buffer, count) in self.probe_buffers.iter() {
+            list.push(
+                DrawKey::opaque(*pipeline, 0),
+                Draw::vertices(buffer, *count),
+            );
+        }
+        let stats = list.sort();
+        list.execute(render_pass, pipelines, &[&self.bind_group]);
+        stats
+    }
+}
+
+struct PlotState {
+    init: ws::InitWgpu,
+    pipelines: Vec<wgpu::RenderPipeline>,
+    graph: ws::RenderGraph, // the plot pass with its MSAA and depth attachments
+    panels: Vec<Panel>,
+    cursor: [f32; 2],
+    scrubbing: Option<usize>, // series subplot whose slider is being dragged
+    probing: Option<usize>,   // subplot whose probe plane is being dragged
+    shift: bool,
+    draw_stats: SortStats, // of the last frame, all subplots
+}
+
+impl PlotState {
+    async fn new(window: Arc<Window>, plots: &Subplots) -> Result<Self> {
+        let init = ws::InitWgpu::init_wgpu(
+            window,
+            plots.sample_count,
+            ws::PresentModePreference::default(),
+        )
+        .await?;
+        let shader = init
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Plot Shader"),
+                source: wgpu::ShaderSource::Wgsl(PLOT_SHADER.into()),
+            });
+        let bind_group_layout = ws::create_bind_group_layout(
+            &init.device,
+            vec![wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT],
+        );
+        let pipeline_layout = init
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Plot Pipeline Layout"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        // surfaces as configured, overlays always with position and color only
+        let lit = VertexAttributes::LIT.layout();
+        let unlit = VertexAttributes::UNLIT.layout();
+        let lit_layout = [lit.buffer_layout()];
+        let unlit_layout = [unlit.buffer_layout()];
+        let pipelines = [
+            (
+                wgpu::PrimitiveTopology::TriangleList,
+                "vs_main",
+                "fs_main",
+                true,
+            ),
+            (
+                wgpu::PrimitiveTopology::LineList,
+                "vs_main",
+                "fs_main",
+                true,
+            ),
+            (
+                wgpu::PrimitiveTopology::TriangleList,
+                "vs_colorbar",
+                "fs_colorbar",
+                false,
+            ),
+            (
+                wgpu::PrimitiveTopology::LineList,
+                "vs_colorbar",
+                "fs_colorbar",
+                false,
+            ),
+            (
+                wgpu::PrimitiveTopology::LineList,
+                "vs_unlit",
+                "fs_colorbar",
+                false,
+            ),
+        ]
+        .map(|(topology, vs_entry, fs_entry, lighting)| {
+            ws::IRenderPipeline {
+                topology,
+                shader: Some(&shader),
+                pipeline_layout: Some(&pipeline_layout),
+                vertex_buffer_layout: if lighting { &lit_layout } else { &unlit_layout },
+                vs_entry: vs_entry.to_string(),
+                fs_entry: fs_entry.to_string(),
+                ..Default::default()
+            }
+            .new(&init)
+        });
+        // uncertainty bands, blended with the render pass blend constant
+        let band_pipeline = ws::IRenderPipeline {
+            shader: Some(&shader),
+            pipeline_layout: Some(&pipeline_layout),
+            vertex_buffer_layout: &lit_layout,
+            depth_write_enabled: false,
+            blend: Some(ws::CONSTANT_ALPHA_BLENDING),
+            ..Default::default()
+        };
+        // unlit shape and bands of plots without lighting
+        let unlit_pipeline = ws::IRenderPipeline {
+            shader: Some(&shader),
+            pipeline_layout: Some(&pipeline_layout),
+            vertex_buffer_layout: &unlit_layout,
+            vs_entry: "vs_unlit".to_string(),
+            fs_entry: "fs_colorbar".to_string(),
+            ..Default::default()
+        };
+        let unlit_band_pipeline = ws::IRenderPipeline {
+            shader: Some(&shader),
+            pipeline_layout: Some(&pipeline_layout),
+            vertex_buffer_layout: &unlit_layout,
+            vs_entry: "vs_unlit".to_string(),
+            fs_entry: "fs_colorbar".to_string(),
+            depth_write_enabled: false,
+            blend: Some(ws::CONSTANT_ALPHA_BLENDING),
+            ..Default::default()
+        };
+        let extra =
+            [band_pipeline, unlit_pipeline, unlit_band_pipeline].map(|mut ppl| ppl.new(&init));
+        let pipelines = pipelines.into_iter().chain(extra).collect::<Result<_>>()?;
+
+        let mut graph = ws::RenderGraph::default();
+        graph.add_attachment("msaa", ws::AttachmentDesc::color().multisampled());
+        graph.add_attachment("depth", ws::AttachmentDesc::depth().multisampled());
+        graph.add_pass("plot", &[], &[ws::RenderGraph::OUTPUT, "msaa", "depth"]);
+        let panels = plots
+            .plots
+            .iter()
+            .map(|plot| Panel::new(&init, &bind_group_layout, plot))
+            .collect();
+
+        Ok(Self {
+            init,
+            pipelines,
+            graph,
+            panels,
+            cursor: [0.0, 0.0],
+            scrubbing: None,
+            probing: None,
+            shift: false,
+            draw_stats: SortStats::default(),
+        })
+    }
+
+    fn size(&self) -> [u32; 2] {
+        [self.init.config.width, self.init.config.height]
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        if new_size.width > 0 && new_size.height > 0 {
+            self.init.size = new_size;
+            self.init.config.width = new_size.width;
+            self.init.config.height = new_size.height;
+            self.init
+                .surface
+                .configure(&self.init.device, &self.init.config);
+        }
+    }
+
+    // returns true if the event changed the view and a redraw is needed
+    fn input(&mut self, plots: &mut Subplots, event: &WindowEvent) -> bool {
+        let hovered = plots.viewport_at(self.cursor, self.size());
+        match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Named(NamedKey::Space),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => {
+                // the hovered subplot, or all of them if the cursor is outside the window
+                for (i, panel) in self.panels.iter_mut().enumerate() {
+                    if hovered.is_none_or(|h| h == i) {
+                        panel.plot_type = (panel.plot_type + 1) % 3;
+                    }
+                }
+                true
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Character(c),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } if c.as_str() == "m" => {
+                let panels = self.panels.iter_mut();
+                for (i, (plot, panel)) in plots.plots.iter_mut().zip(panels).enumerate() {
+                    if hovered.is_none_or(|h| h == i) {
+                        plot.mask_mode = match plot.mask_mode {
+                            MaskMode::Hide => MaskMode::Dim(0.3),
+                            MaskMode::Dim(_) => MaskMode::Ignore,
+                            MaskMode::Ignore => MaskMode::Hide,
+                        };
+                        panel.set_data(&self.init, plot);
+                    }
+                }
+                true
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Character(c),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } if c.as_str() == "p" && plots.is_animated() => {
+                let paused = plots.clock.toggle();
+                log::info!(
+                    "{} at t = {:.2}",
+                    if paused { "paused" } else { "playing" },
+                    plots.clock.time()
+                );
+                true
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Named(key @ (NamedKey::ArrowLeft | NamedKey::ArrowRight)),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } if plots.is_animated() => {
+                // one time step of the hovered (or first) series, else a quarter second
+                let is_series = |i: &usize| plots.plots[*i].series.is_some();
+                let series = hovered
+                    .filter(is_series)
+                    .or_else(|| (0..plots.len()).find(is_series));
+                let dt = series.map_or(0.25, |i| 1.0 / plots.plots[i].steps_per_second);
+                let clock = plots.clock_of(series.or(hovered).unwrap_or(0));
+                clock.seek(if *key == NamedKey::ArrowLeft { -dt } else { dt });
+                match series.and_then(|i| plots.plots[i].step_at(clock.time())) {
+                    Some(step) => log::info!("step {step}, t = {:.2}", clock.time()),
+                    None => log::info!("t = {:.2}", clock.time()),
+                }
+                true
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Character(c),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } if c.as_str() == "x" => {
+                let panels = self.panels.iter_mut();
+                for (i, (plot, panel)) in plots.plots.iter_mut().zip(panels).enumerate() {
+                    if hovered.is_none_or(|h| h == i) {
+                        let mid = |[a, b]: [f32; 2]| 0.5 * (a + b);
+                        let probe = match plot.probe {
+                            None => Some((ProbeAxis::X, mid(plot.x_range))),
+                            Some((ProbeAxis::X, _)) => Some((ProbeAxis::Z, mid(plot.z_range))),
+                            Some((ProbeAxis::Z, _)) => None,
+                        };
+                        plot.set_probe(probe);
+                        panel.set_probe(&self.init, plot);
+                        log_probe(plot);
+                    }
+                }
+                true
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Character(c),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } if c.as_str() == "d" => {
+                log::info!("last frame: {}", self.draw_stats);
+                false
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.shift = modifiers.state().shift_key();
+                false
+            }
+            // a drag on the time slider scrubs, a shift drag moves the probe plane and
+            // any other drag moves the camera of the subplot it started in
+            WindowEvent::MouseInput { state, button, .. } => {
+                if *state == ElementState::Pressed
+                    && *button == MouseButton::Left
+                    && let Some(i) = hovered
+                {
+                    if self.on_slider(plots, i) {
+                        self.scrubbing = Some(i);
+                        return self.scrub(plots);
+                    }
+                    if self.shift && plots.plots[i].probe.is_some() {
+                        self.probing = Some(i);
+                        return false;
+                    }
+                }
+                if *state == ElementState::Released {
+                    self.scrubbing = None;
+                    if let Some(i) = self.probing.take() {
+                        log_probe(&plots.plots[i]);
+                    }
+                }
+                for (i, panel) in self.panels.iter_mut().enumerate() {
+                    if *state == ElementState::Released || hovered == Some(i) {
+                        panel.camera.input(event);
+                    }
+                }
+                false
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                let cursor = [position.x as f32, position.y as f32];
+                let delta = [cursor[0] - self.cursor[0], cursor[1] - self.cursor[1]];
+                self.cursor = cursor;
+                if let Some(i) = self.probing {
+                    self.move_probe(plots, i, delta);
+                    return true;
+                }
+                // every camera tracks the cursor, only the dragged one moves
+                let mut moved = false;
+                for panel in self.panels.iter_mut() {
+                    moved |= panel.camera.input(event);
+                }
+                self.scrub(plots) || moved
+            }
+            WindowEvent::MouseWheel { .. } => match hovered.and_then(|i| self.panels.get_mut(i)) {
+                Some(panel) => panel.camera.input(event),
+                None => false,
+            },
+            _ => false,
+        }
+    }
+
+    // moves the probe plane of a subplot by the part of the cursor motion that runs
+    // along the on-screen direction of its axis
+    fn move_probe(&mut self, plots: &mut Subplots, index: usize, delta: [f32; 2]) {
+        let [_, _, w, h] = plots.viewport(index, self.size());
+        let plot = &mut plots.plots[index];
+        let Some((axis, c)) = plot.probe else {
+            return;
+        };
+        let [a, b] = plot.probe_range(axis);
+        // the mesh spans [-1, 1] along x and z
+        let world = if b != a {
+            -1.0 + 2.0 * (c - a) / (b - a)
+        } else {
+            0.0
+        };
+        let (origin, direction) = match axis {
+            ProbeAxis::X => ([world, 0.0, 0.0], [1.0, 0.0, 0.0]),
+            ProbeAxis::Z => ([0.0, 0.0, world], [0.0, 0.0, 1.0]),
+        };
+        let vp_mat = self.panels[index].view_project_mat(w / h.max(1.0));
+        let to_screen = |p: [f32; 3]| {
+            let clip = vp_mat * Vector4::new(p[0], p[1], p[2], 1.0);
+            [
+                0.5 * (clip.x / clip.w + 1.0) * w,
+                0.5 * (1.0 - clip.y / clip.w) * h,
+            ]
+        };
+        let step = 0.1;
+        let p0 = to_screen(origin);
+        let p1 = to_screen([0, 1, 2].map(|k| origin[k] + step * direction[k]));
+        let axis_px = [(p1[0] - p0[0]) / step, (p1[1] - p0[1]) / step];
+        let length2 = axis_px[0] * axis_px[0] + axis_px[1] * axis_px[1];
+        if length2 < 1e-6 {
+            // the axis points at the viewer
+            return;
+        }
+        let dw = (delta[0] * axis_px[0] + delta[1] * axis_px[1]) / length2;
+        plot.set_probe(Some((axis, c + 0.5 * dw * (b - a))));
+        self.panels[index].set_probe(&self.init, plot);
+    }
+
+    // cursor position in the clip space of a viewport
+    fn cursor_in(&self, plots: &Subplots, index: usize) -> [f32; 2] {
+        let [x, y, w, h] = plots.viewport(index, self.size());
+        [
+            2.0 * (self.cursor[0] - x) / w.max(1.0) - 1.0,
+            1.0 - 2.0 * (self.cursor[1] - y) / h.max(1.0),
+        ]
+    }
+
+    fn on_slider(&self, plots: &Subplots, index: usize) -> bool {
+        let [cx, cy] = self.cursor_in(plots, index);
+        let [x0, x1] = SLIDER_X;
+        plots.plots[index].series.is_some()
+            && cy < SLIDER_Y[1] + 0.08
+            && cx > x0 - 0.05
+            && cx < x1 + 0.05
+    }
+
+    // moves the clock of the scrubbed subplot to the step under the cursor
+    fn scrub(&self, plots: &Subplots) -> bool {
+        let Some(index) = self.scrubbing else {
+            return false;
+        };
+        let plot = &plots.plots[index];
+        let Some(len) = plot.series_len().filter(|&len| len > 0) else {
+            return false;
+        };
+        let [x0, x1] = SLIDER_X;
+        let fraction = (self.cursor_in(plots, index)[0] - x0) / (x1 - x0);
+        let step = ((fraction * len as f32).floor().max(0.0) as usize).min(len - 1);
+        plots.clock_of(index).set_time(plot.time_of_step(step));
+        true
+    }
+
+    fn render(&mut self, plots: &Subplots) -> Result<(), wgpu::SurfaceError> {
+        let output = self.init.surface.get_current_texture()?;
+        let view = self.init.surface_view(&output.texture);
+        let size = self.size();
+        for (i, panel) in self.panels.iter().enumerate() {
+            let [_, _, w, h] = plots.viewport(i, size);
+            panel.write_uniforms(&self.init, w / h.max(1.0));
+            panel.write_slider(&self.init, &plots.plots[i]);
+        }
+
+        let mut encoder =
+            self.init
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Plot Encoder"),
+                });
+        // the graph resizes its attachments with the window
+        let sample_count = self.init.sample_count;
+        let (panels, pipelines) = (&self.panels, &self.pipelines);
+        let mut draw_stats = SortStats::default();
+        let result = self
+            .graph
+            .execute(&self.init, &mut encoder, &view, |encoder, pass| {
+                let (Some(output), Some(msaa), Some(depth)) = (
+                    pass.view(ws::RenderGraph::OUTPUT),
+                    pass.view("msaa"),
+                    pass.view("depth"),
+                ) else {
+                    return;
+                };
+                let color_attachment = if sample_count == 1 {
+                    ws::create_color_attachment(output)
+                } else {
+                    ws::create_msaa_color_attachment(output, msaa)
+                };
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Plot Pass"),
+                    color_attachments: &[Some(color_attachment)],
+                    depth_stencil_attachment: Some(ws::create_depth_stencil_attachment(depth)),
+                    occlusion_query_set: None,
+                    timestamp_writes: None,
+                });
+                // each subplot has its own viewport, so the draws are sorted per subplot
+                for (i, panel) in panels.iter().enumerate() {
+                    let [x, y, w, h] = plots.viewport(i, size);
+                    render_pass.set_viewport(x, y, w, h, 0.0, 1.0);
+                    draw_stats += panel.draw(&mut render_pass, pipelines);
+                }
+            });
+        if let Err(e) = result {
+            log::error!("{}", e);
+        }
+        self.draw_stats = draw_stats;
+        self.init.queue.submit(std::iter::once(encoder.finish()));
+        output.present();
+        Ok(())
+    }
+}
+
+// position and extremes of the cross-section, as the inset has no labels
+fn log_probe(plot: &SurfacePlot) {
+    let (Some((axis, c)), Some(section)) = (plot.probe, plot.cross_section()) else {
+        log::info!("probe off");
+        return;
+    };
+    let (name, along) = match axis {
+        ProbeAxis::X => ("x", "z"),
+        ProbeAxis::Z => ("z", "x"),
+    };
+    let min = section
+        .iter()
+        .copied()
+        .fold([0.0, f32::MAX], |m, p| if p[1] < m[1] { p } else { m });
+    let max = section
+        .iter()
+        .copied()
+        .fold([0.0, f32::MIN], |m, p| if p[1] > m[1] { p } else { m });
+    let f = &plot.number_format;
+    log::info!(
+        "probe {name} = {}: min {} at {along} = {}, max {} at {along} = {}",
+        f.format(c),
+        f.format(min[1]),
+        f.format(min[0]),
+        f.format(max[1]),
+        f.format(max[0])
+    );
+}
+
+struct PlotApp {
+    plots: Subplots,
+    state: Option<PlotState>,
+    error: Option<Error>, // why the window could not be set up
+}
+
+impl ApplicationHandler<PlotEvent> for PlotApp {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.state.is_some() {
+            return;
+        }
+        let window_attributes = Window::default_attributes().with_title(&self.plots.title);
+        let window = event_loop
+            .create_window(window_attributes)
+            .expect("Failed to create window");
+        match pollster::block_on(PlotState::new(window.into(), &self.plots)) {
+            Ok(state) => self.state = Some(state),
+            Err(e) => {
+                self.error = Some(e);
+                event_loop.exit();
+            }
+        }
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        _window_id: WindowId,
+        event: WindowEvent,
+    ) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        if state.input(&mut self.plots, &event) {
+            state.init.window.request_redraw();
+            return;
+        }
+        match event {
+            WindowEvent::CloseRequested
+            | WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Named(NamedKey::Escape),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => event_loop.exit(),
+            WindowEvent::Resized(size) => {
+                state.resize(size);
+                state.init.window.request_redraw();
+            }
+            // static plots are only drawn when something changed, animated ones
+            // keep requesting frames
+            WindowEvent::RedrawRequested => {
+                for i in self.plots.advance() {
+                    state.panels[i].set_data(&state.init, &self.plots.plots[i]);
+                }
+                if self.plots.is_animated() {
+                    state.init.window.request_redraw();
+                }
+                match state.render(&self.plots) {
+                    Ok(_) => {}
+                    Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                        state.resize(state.init.size);
+                        state.init.window.request_redraw();
+                    }
+                    Err(wgpu::SurfaceError::OutOfMemory) => {
+                        log::error!("out of memory");
+                        event_loop.exit();
+                    }
+                    Err(e) => log::warn!("surface error: {e}"),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, (index, command): PlotEvent) {
+        if let PlotCommand::Close = command {
+            event_loop.exit();
+            return;
+        }
+        let Some(plot) = self.plots.plots.get_mut(index) else {
+            return;
+        };
+        plot.apply(command);
+        if let Some(state) = &mut self.state
+            && let Some(panel) = state.panels.get_mut(index)
+        {
+            panel.set_data(&state.init, plot);
+            state.init.window.request_redraw();
+        }
+    }
+}
+// endregion: renderer
@@ -0,0 +1,260 @@
+use std::path::Path;
+use std::time::Duration;
+
+use web_time::Instant;
+
+use super::capture::{self, FrameCapture};
+
+// region: recorder
+// Records a clip of the window while it animates: between start() and stop() (or
+// max_frames), copy_frame() copies the rendered texture of every frame that is due at
+// frame_rate into a readback buffer, and read_frame() keeps its pixels in memory once
+// the frame is submitted. save() writes the clip as an animated GIF, whose frame
+// delays follow the times the frames were captured at, or as a numbered PNG sequence
+// in a directory, to assemble into a video:
+//
+//     self.recorder.copy_frame(&self.init.device, &mut encoder, &output.texture, format);
+//     self.init.queue.submit(std::iter::once(encoder.finish()));
+//     if self.recorder.read_frame(&self.init.device)? {
+//         self.recorder.save("recording.gif")?;
+//     }
+pub const RECORD_KEY: &str = "z";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RecordFormat {
+    #[default]
+    Gif,
+    Frames, // frame_00000.png, frame_00001.png, ...
+}
+
+impl RecordFormat {
+    pub const ALL: [Self; 2] = [Self::Gif, Self::Frames];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Gif => "gif",
+            Self::Frames => "frames",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|format| format.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn next(&self) -> Self {
+        Self::ALL[(*self as usize + 1) % Self::ALL.len()]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedFrame {
+    pub rgba: Vec<u8>,
+    pub time: Duration, // since the recording started
+}
+
+pub struct Recorder {
+    pub format: RecordFormat,
+    pub frame_rate: f32, // at most this many frames per second are kept
+    pub max_frames: u32,
+    width: u32,
+    height: u32,
+    frames: Vec<RecordedFrame>,
+    start: Option<Instant>, // set while recording
+    capture: Option<FrameCapture>,
+    pending: Option<Duration>, // time of the frame copied but not read back yet
+}
+
+impl Recorder {
+    pub fn new(format: RecordFormat, frame_rate: f32, max_frames: u32) -> Self {
+        Self {
+            format,
+            frame_rate: if frame_rate > 0.0 { frame_rate } else { 30.0 },
+            max_frames: max_frames.max(1),
+            width: 0,
+            height: 0,
+            frames: vec![],
+            start: None,
+            capture: None,
+            pending: None,
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.start.is_some()
+    }
+
+    // drops the previous clip and records a new one
+    pub fn start(&mut self) {
+        self.frames.clear();
+        self.pending = None;
+        self.start = Some(Instant::now());
+    }
+
+    // stops recording and returns the number of frames of the clip
+    pub fn stop(&mut self) -> usize {
+        self.start = None;
+        self.pending = None;
+        self.frames.len()
+    }
+
+    // starts or stops; true if it is recording now
+    pub fn toggle(&mut self) -> bool {
+        if self.is_recording() {
+            self.stop();
+        } else {
+            self.start();
+        }
+        self.is_recording()
+    }
+
+    pub fn frames(&self) -> &[RecordedFrame] {
+        &self.frames
+    }
+
+    pub fn size(&self) -> [u32; 2] {
+        [self.width, self.height]
+    }
+
+    // keeps a frame of the clip; the first one sets the size, and frames of another
+    // size (the window was resized) are rejected. Recording stops at max_frames, and
+    // the return value is true for the frame that completed the clip.
+    pub fn add_frame(
+        &mut self,
+        width: u32,
+        height: u32,
+        rgba: Vec<u8>,
+        time: Duration,
+    ) -> anyhow::Result<bool> {
+        if !self.is_recording() {
+            return Ok(false);
+        }
+        if rgba.len() != (4 * width * height) as usize {
+            anyhow::bail!("expected {}x{} RGBA8 pixels, got {} bytes", width, height, rgba.len());
+        }
+        if self.frames.is_empty() {
+            (self.width, self.height) = (width, height);
+        } else if [width, height] != self.size() {
+            anyhow::bail!(
+                "frame of {}x{} in a {}x{} recording",
+                width,
+                height,
+                self.width,
+                self.height
+            );
+        }
+        self.frames.push(RecordedFrame { rgba, time });
+        if self.frames.len() >= self.max_frames as usize {
+            self.stop();
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    // records a copy of the rendered texture when a frame is due; the texture must
+    // have been created with TextureUsages::COPY_SRC (the surface's config.usage tells)
+    pub fn copy_frame(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        texture: &wgpu::Texture,
+        format: wgpu::TextureFormat,
+    ) {
+        let Some(start) = self.start else {
+            return;
+        };
+        let time = start.elapsed();
+        if let Some(last) = self.frames.last()
+            && (time - last.time).as_secs_f32() < 1.0 / self.frame_rate
+        {
+            return;
+        }
+        let (width, height) = (texture.width(), texture.height());
+        if self
+            .capture
+            .as_ref()
+            .is_none_or(|c| c.width != width || c.height != height || c.format != format)
+        {
+            self.capture = Some(FrameCapture::new(device, width, height, format));
+        }
+        if let Some(capture) = &self.capture {
+            capture.copy_from_texture(encoder, texture);
+            self.pending = Some(time);
+        }
+    }
+
+    // reads back the frame copied by copy_frame() after its encoder was submitted;
+    // true once the clip is complete
+    pub fn read_frame(&mut self, device: &wgpu::Device) -> anyhow::Result<bool> {
+        let (Some(time), Some(capture)) = (self.pending.take(), &self.capture) else {
+            return Ok(false);
+        };
+        let rgba = capture.read_rgba(device)?;
+        let (width, height) = (capture.width, capture.height);
+        self.add_frame(width, height, rgba, time)
+    }
+
+    // the clip as an animated GIF that loops forever
+    pub fn encode_gif(&self) -> anyhow::Result<Vec<u8>> {
+        if self.frames.is_empty() {
+            anyhow::bail!("no frames recorded");
+        }
+        let (Ok(width), Ok(height)) = (u16::try_from(self.width), u16::try_from(self.height))
+        else {
+            anyhow::bail!("{}x{} is too large for a GIF", self.width, self.height);
+        };
+        let times: Vec<Duration> = self.frames.iter().map(|f| f.time).collect();
+        let delays = gif_delays(&times, self.frame_rate);
+
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = gif::Encoder::new(&mut bytes, width, height, &[])?;
+            encoder.set_repeat(gif::Repeat::Infinite)?;
+            for (frame, delay) in self.frames.iter().zip(delays) {
+                let mut rgba = frame.rgba.clone();
+                // speed 10 is the quantizer's default trade of quality for time
+                let mut gif_frame = gif::Frame::from_rgba_speed(width, height, &mut rgba, 10);
+                gif_frame.delay = delay;
+                encoder.write_frame(&gif_frame)?;
+            }
+        }
+        Ok(bytes)
+    }
+
+    // writes the clip to path: a GIF file, or a directory of PNG frames
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        match self.format {
+            RecordFormat::Gif => {
+                let bytes = self.encode_gif()?;
+                std::fs::write(path, bytes)?;
+            }
+            RecordFormat::Frames => {
+                std::fs::create_dir_all(path)?;
+                for (i, frame) in self.frames.iter().enumerate() {
+                    let png = capture::encode_png(self.width, self.height, &frame.rgba)?;
+                    std::fs::write(path.join(format!("frame_{:05}.png", i)), png)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// GIF delays in hundredths of a second between frames captured at times; the last
+// frame gets the nominal frame time. Browsers slow down delays below 2, so they are
+// at least 2.
+pub fn gif_delays(times: &[Duration], frame_rate: f32) -> Vec<u16> {
+    let nominal = Duration::from_secs_f32(1.0 / frame_rate);
+    times
+        .iter()
+        .enumerate()
+        .map(|(i, &time)| {
+            let next = times.get(i + 1).map_or(time + nominal, |&t| t);
+            let centis = (next.saturating_sub(time).as_secs_f64() * 100.0).round();
+            (centis as u16).max(2)
+        })
+        .collect()
+}
+// endregion: recorder
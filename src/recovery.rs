@@ -0,0 +1,150 @@
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock, TryLockError};
+
+use super::scene::Scene;
+
+// region: crash recovery
+// Opt-in panic hook for crashes that are hard to reproduce, e.g. a driver bug on one
+// user's GPU. Once it is installed, the driver (app::run_app) records the scene of the
+// state after every update (SurfaceApp::snapshot), and a panic writes the last one to
+// the recovery file, a scene file like any other, then prints the adapter and the
+// command line that starts from it. --recovery-file FILE (cli::SurfaceArgs) installs
+// it; by hand:
+//
+//     recovery::install(Recovery::new("crash_scene.ron", std::env::args().collect()));
+//     ..
+//     recovery::record(state.scene());
+//
+// The scene keeps the seed of the state's random generator, which --scene FILE
+// applies at startup, so random choices come out the same in the rerun.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Session {
+    pub scene: Option<Scene>,
+    pub adapter: String, // name and backend of the GPU
+    pub frame: u64,      // frames recorded so far
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recovery {
+    pub path: PathBuf,
+    pub args: Vec<String>, // of the process, the program first
+}
+
+static RECOVERY: OnceLock<Recovery> = OnceLock::new();
+static SESSION: Mutex<Session> = Mutex::new(Session {
+    scene: None,
+    adapter: String::new(),
+    frame: 0,
+});
+
+impl Recovery {
+    pub fn new(path: impl Into<PathBuf>, args: Vec<String>) -> Self {
+        Self {
+            path: path.into(),
+            args,
+        }
+    }
+
+    // the arguments of the crashed run, starting from the recovery file instead of
+    // its own --scene and --seed
+    pub fn repro_command(&self) -> String {
+        const DROPPED: [&str; 3] = ["--scene", "--seed", "--recovery-file"];
+        let mut command = vec![];
+        let mut args = self.args.iter();
+        command.extend(args.next().cloned());
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                break;
+            }
+            if DROPPED.contains(&arg.as_str()) {
+                args.next();
+            } else if !DROPPED
+                .iter()
+                .any(|flag| arg.starts_with(&format!("{}=", flag)))
+            {
+                command.push(arg.clone());
+            }
+        }
+        command.push("--scene".to_string());
+        command.push(self.path.display().to_string());
+        // positional arguments after a bare "--" stay last
+        if let Some(i) = self.args.iter().skip(1).position(|arg| arg == "--") {
+            command.extend(self.args[i + 1..].iter().cloned());
+        }
+        command
+            .iter()
+            .map(|arg| match arg.contains(char::is_whitespace) {
+                true => format!("{:?}", arg),
+                false => arg.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    // writes the scene of the session to the recovery file and returns the
+    // instructions for the bug report
+    pub fn save(&self, session: &Session) -> String {
+        let path = self.path.display();
+        let Some(scene) = &session.scene else {
+            return format!(
+                "no scene was recorded before the crash (frame {}, adapter: {})",
+                session.frame, session.adapter
+            );
+        };
+        if let Err(e) = scene.save(&self.path) {
+            return format!("could not save the session to {}: {}", path, e);
+        }
+        format!(
+            "the session at frame {} was saved to {} (adapter: {}); to reproduce \
+             the crash, run\n    {}\nand attach {} to the bug report",
+            session.frame,
+            path,
+            session.adapter,
+            self.repro_command(),
+            path
+        )
+    }
+}
+
+// installs the panic hook, after the hook already set, e.g. the one printing the
+// message and backtrace; false if it is installed already
+pub fn install(recovery: Recovery) -> bool {
+    if RECOVERY.set(recovery).is_err() {
+        return false;
+    }
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        previous(info);
+        if let Some(recovery) = RECOVERY.get() {
+            eprintln!("{}", recovery.save(&session()));
+        }
+    }));
+    true
+}
+
+pub fn is_installed() -> bool {
+    RECOVERY.get().is_some()
+}
+
+// the scene shown now, saved if the process panics before the next one
+pub fn record(scene: Scene) {
+    let mut session = SESSION.lock().unwrap_or_else(|e| e.into_inner());
+    session.scene = Some(scene);
+    session.frame += 1;
+}
+
+pub fn record_adapter(info: &wgpu::AdapterInfo) {
+    let mut session = SESSION.lock().unwrap_or_else(|e| e.into_inner());
+    session.adapter = format!("{} ({:?})", info.name, info.backend);
+}
+
+// the recorded session; in the hook the lock may be held by the panicking thread, and
+// the session is then left out rather than waited for
+pub fn session() -> Session {
+    match SESSION.try_lock() {
+        Ok(session) => session.clone(),
+        Err(TryLockError::Poisoned(e)) => e.into_inner().clone(),
+        Err(TryLockError::WouldBlock) => Session::default(),
+    }
+}
+// endregion: crash recovery
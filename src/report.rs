@@ -0,0 +1,267 @@
+use std::fmt::Write;
+use std::path::Path;
+
+use super::capture;
+use super::colormap;
+use super::surface_data::{IParametricSurface, ISimpleSurface};
+
+// region: report data
+pub struct IReportImage {
+    pub caption: String,
+    pub png: Vec<u8>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ICameraInfo {
+    pub position: [f32; 3],
+    pub look_at: [f32; 3],
+    pub up: [f32; 3],
+    pub fovy_degrees: f32,
+}
+
+pub struct IReport {
+    pub title: String,
+    pub images: Vec<IReportImage>,
+    pub parameters: Vec<(String, String)>,
+    pub colormap_name: String,
+    pub colormap_range: [f32; 2],
+    pub camera: Option<ICameraInfo>,
+}
+
+impl Default for IReport {
+    fn default() -> Self {
+        Self {
+            title: "wgpu surfaces report".to_string(),
+            images: vec![],
+            parameters: vec![],
+            colormap_name: "jet".to_string(),
+            colormap_range: [-1.0, 1.0],
+            camera: None,
+        }
+    }
+}
+
+impl IReport {
+    pub fn add_png(&mut self, caption: &str, png: Vec<u8>) {
+        self.images.push(IReportImage {
+            caption: caption.to_string(),
+            png,
+        });
+    }
+
+    pub fn add_image_rgba(
+        &mut self,
+        caption: &str,
+        width: u32,
+        height: u32,
+        rgba: &[u8],
+    ) -> anyhow::Result<()> {
+        let png = capture::encode_png(width, height, rgba)?;
+        self.add_png(caption, png);
+        Ok(())
+    }
+
+    pub fn parameter(&mut self, name: &str, value: impl ToString) {
+        self.parameters.push((name.to_string(), value.to_string()));
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        std::fs::write(path, self.to_html())?;
+        Ok(())
+    }
+
+    // renders a single HTML file with all images embedded as data URIs
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        let _ = writeln!(
+            html,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+             <style>\nbody {{ font-family: sans-serif; margin: 2em; }}\n\
+             table {{ border-collapse: collapse; }}\n\
+             td, th {{ border: 1px solid #ccc; padding: 4px 10px; text-align: left; }}\n\
+             figure {{ display: inline-block; margin: 0 1em 1em 0; }}\n\
+             .legend {{ width: 400px; height: 20px; border: 1px solid #888; }}\n\
+             .legend-labels {{ width: 400px; display: flex; justify-content: space-between; }}\n\
+             </style>\n</head>\n<body>\n<h1>{}</h1>",
+            escape(&self.title),
+            escape(&self.title)
+        );
+
+        if !self.images.is_empty() {
+            html.push_str("<h2>Renders</h2>\n");
+            for image in self.images.iter() {
+                let _ = writeln!(
+                    html,
+                    "<figure><img src=\"data:image/png;base64,{}\" alt=\"{}\">\
+                     <figcaption>{}</figcaption></figure>",
+                    base64(&image.png),
+                    escape(&image.caption),
+                    escape(&image.caption)
+                );
+            }
+        }
+
+        html.push_str("<h2>Colormap</h2>\n");
+        let _ = writeln!(
+            html,
+            "<p>{}</p>\n<div class=\"legend\" style=\"background: {}\"></div>\n\
+             <div class=\"legend-labels\"><span>{}</span><span>{}</span><span>{}</span></div>",
+            escape(&self.colormap_name),
+            legend_gradient(&self.colormap_name),
+            self.colormap_range[0],
+            0.5 * (self.colormap_range[0] + self.colormap_range[1]),
+            self.colormap_range[1]
+        );
+
+        if !self.parameters.is_empty() {
+            html.push_str("<h2>Surface parameters</h2>\n<table>\n");
+            for (name, value) in self.parameters.iter() {
+                let _ = writeln!(
+                    html,
+                    "<tr><th>{}</th><td>{}</td></tr>",
+                    escape(name),
+                    escape(value)
+                );
+            }
+            html.push_str("</table>\n");
+        }
+
+        if let Some(camera) = &self.camera {
+            html.push_str("<h2>Camera</h2>\n<table>\n");
+            let rows = [
+                ("position", format!("{:?}", camera.position)),
+                ("look at", format!("{:?}", camera.look_at)),
+                ("up", format!("{:?}", camera.up)),
+                ("vertical fov (deg)", format!("{}", camera.fovy_degrees)),
+            ];
+            for (name, value) in rows.iter() {
+                let _ = writeln!(html, "<tr><th>{}</th><td>{}</td></tr>", name, value);
+            }
+            html.push_str("</table>\n");
+        }
+
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+// endregion: report data
+
+// region: surface parameters
+pub fn parametric_surface_report(ps: &IParametricSurface) -> IReport {
+    let mut report = IReport {
+        colormap_name: ps.colormap_name.clone(),
+        // the normalized surface fits into [-scale/2, scale/2] in every direction
+        colormap_range: ps
+            .colormap_range
+            .unwrap_or([-0.5 * ps.scale, 0.5 * ps.scale]),
+        ..Default::default()
+    };
+    let surface_name = super::surface_data::get_surface_type(ps.surface_type);
+    report.parameter(
+        "surface type",
+        format!("{} ({})", ps.surface_type, surface_name),
+    );
+    report.parameter("u range", format!("[{}, {}]", ps.umin, ps.umax));
+    report.parameter("v range", format!("[{}, {}]", ps.vmin, ps.vmax));
+    report.parameter(
+        "resolution",
+        format!("{} x {}", ps.u_resolution, ps.v_resolution),
+    );
+    report.parameter("scale", ps.scale);
+    // the shape parameters only the surfaces that read them list
+    match surface_name.as_str() {
+        "torus_knot" => {
+            report.parameter("knot (p, q)", format!("({}, {})", ps.knot_p, ps.knot_q));
+            report.parameter("tube radius", ps.tube_radius);
+        }
+        "klein_bagel" => report.parameter("tube radius", ps.tube_radius),
+        "catenoid_helicoid" => report.parameter("deformation", ps.deformation),
+        _ => {}
+    }
+    report.parameter("colormap direction", ps.colormap_direction);
+    report.parameter("wireframe color", &ps.wireframe_color);
+    report
+}
+
+pub fn simple_surface_report(ss: &ISimpleSurface) -> IReport {
+    let range = if ss.colormap_direction == 1 {
+        ss.scale * ss.aspect_ratio
+    } else {
+        ss.scale
+    };
+    let mut report = IReport {
+        colormap_name: ss.colormap_name.clone(),
+        colormap_range: ss.colormap_range.unwrap_or([-range, range]),
+        ..Default::default()
+    };
+    report.parameter("surface type", ss.surface_type);
+    report.parameter("x range", format!("[{}, {}]", ss.xmin, ss.xmax));
+    report.parameter("z range", format!("[{}, {}]", ss.zmin, ss.zmax));
+    report.parameter(
+        "resolution",
+        format!("{} x {}", ss.x_resolution, ss.z_resolution),
+    );
+    report.parameter("scale", ss.scale);
+    report.parameter("aspect ratio", ss.aspect_ratio);
+    report.parameter("t", ss.t);
+    report.parameter("colormap direction", ss.colormap_direction);
+    report.parameter("wireframe color", &ss.wireframe_color);
+    report
+}
+// endregion: surface parameters
+
+// region: utility
+fn legend_gradient(colormap_name: &str) -> String {
+    let cdata = colormap::colormap_data(colormap_name);
+    // the stops spread evenly from 0% to 100% whatever their count
+    let last = (cdata.len() - 1) as f32;
+    let stops: Vec<String> = cdata
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            format!(
+                "rgb({}, {}, {}) {}%",
+                (255.0 * c[0]).round(),
+                (255.0 * c[1]).round(),
+                (255.0 * c[2]).round(),
+                100.0 * i as f32 / last
+            )
+        })
+        .collect();
+    format!("linear-gradient(to right, {})", stops.join(", "))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        out.push(TABLE[(n >> 18) as usize & 63] as char);
+        out.push(TABLE[(n >> 12) as usize & 63] as char);
+        out.push(if chunk.len() > 1 {
+            TABLE[(n >> 6) as usize & 63] as char
+        } else {
+            '='
+        });
+        out.push(if chunk.len() > 2 {
+            TABLE[n as usize & 63] as char
+        } else {
+            '='
+        });
+    }
+    out
+}
+// endregion: utility
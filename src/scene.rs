@@ -0,0 +1,121 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::error::{Result, SceneError};
+use super::material::Material;
+use super::wgpu_simplified::OrbitCamera;
+
+// region: scene files
+// The settings that reproduce a visualization: the surface (type, grid resolution,
+// scale and colormaps), the camera pose, the material, the MSAA sample count, the
+// plot type and the seed of random choices. States fill one from their fields and
+// apply a loaded one the same way. The sample count, the material kind and the seed
+// are fixed once the state is built, so those take effect when the scene is given
+// at startup (--scene FILE, see cli::SurfaceArgs). Paths ending in .ron are written
+// and read as RON, others as JSON:
+//
+//     let mut scene = Scene { plot_type: self.plot_type, ..Default::default() };
+//     scene.set_camera(&self.camera);
+//     scene.save("scene.ron")?;
+//     ..
+//     let scene = Scene::load("scene.ron")?;
+//     self.camera = scene.camera();
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scene {
+    pub surface_type: u32,
+    pub resolution: [u16; 2], // grid cells along x and z, or u and v
+    pub scale: f32,
+    pub colormap: String,
+    pub wireframe_color: String,
+    pub eye: [f32; 3],
+    pub target: [f32; 3],
+    pub material: Material,
+    pub msaa: u32,
+    pub plot_type: u32, // 0: shape and wireframe, 1: shape only, 2: wireframe only
+    pub seed: u64,
+}
+
+// where the examples save and load without --scene
+pub const SCENE_FILE: &str = "scene.ron";
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self {
+            surface_type: 0,
+            resolution: [64, 64],
+            scale: 1.0,
+            colormap: "jet".to_string(),
+            wireframe_color: "white".to_string(),
+            eye: [4.0, 4.0, 4.0],
+            target: [0.0, 0.0, 0.0],
+            material: Material::default(),
+            msaa: 1,
+            plot_type: 0,
+            seed: 0,
+        }
+    }
+}
+
+impl Scene {
+    pub fn camera(&self) -> OrbitCamera {
+        OrbitCamera::new(self.eye.into(), self.target.into())
+    }
+
+    pub fn set_camera(&mut self, camera: &OrbitCamera) {
+        self.eye = camera.eye().into();
+        self.target = camera.target.into();
+    }
+
+    pub fn to_ron(&self) -> Result<String> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default());
+        Ok(text.map_err(SceneError::from)?)
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self> {
+        Ok(ron::from_str(text).map_err(SceneError::from)?)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self).map_err(SceneError::from)?)
+    }
+
+    pub fn from_json(text: &str) -> Result<Self> {
+        Ok(serde_json::from_str(text).map_err(SceneError::from)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let text = if is_ron(path) {
+            self.to_ron()?
+        } else {
+            self.to_json()?
+        };
+        let path = path.to_path_buf();
+        Ok(std::fs::write(&path, text).map_err(|source| SceneError::Write { path, source })?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|source| SceneError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let scene = if is_ron(path) {
+            ron::from_str(&text).map_err(SceneError::from)
+        } else {
+            serde_json::from_str(&text).map_err(SceneError::from)
+        };
+        Ok(scene.map_err(|e| SceneError::File {
+            path: path.to_path_buf(),
+            source: Box::new(e),
+        })?)
+    }
+}
+
+fn is_ron(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("ron"))
+}
+// endregion: scene files
@@ -0,0 +1,282 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+// region: frame sources
+// A time series of grids of the same shape, e.g. the output of a simulation saved
+// every few steps. Frames are row-major like IStreamingSurface values (rows along
+// x) and are read on demand, so a long series never has to fit into memory.
+pub trait FrameSource: Send {
+    // [rows, cols] of every frame
+    fn size(&self) -> [u16; 2];
+
+    // number of time steps
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn frame(&self, step: usize) -> std::io::Result<Vec<f32>>;
+}
+
+fn out_of_range(step: usize, len: usize) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        format!("time step {} out of range for {} frames", step, len),
+    )
+}
+
+// frames held in memory
+pub struct FrameList {
+    pub rows: u16,
+    pub cols: u16,
+    pub frames: Vec<Vec<f32>>,
+}
+
+impl FrameSource for FrameList {
+    fn size(&self) -> [u16; 2] {
+        [self.rows, self.cols]
+    }
+
+    fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    fn frame(&self, step: usize) -> std::io::Result<Vec<f32>> {
+        self.frames
+            .get(step)
+            .cloned()
+            .ok_or_else(|| out_of_range(step, self.frames.len()))
+    }
+}
+
+// procedural series, evaluated per time step and grid point
+pub struct FnFrames {
+    pub rows: u16,
+    pub cols: u16,
+    pub steps: usize,
+    pub f: Box<dyn Fn(usize, u16, u16) -> f32 + Send>,
+}
+
+impl FrameSource for FnFrames {
+    fn size(&self) -> [u16; 2] {
+        [self.rows, self.cols]
+    }
+
+    fn len(&self) -> usize {
+        self.steps
+    }
+
+    fn frame(&self, step: usize) -> std::io::Result<Vec<f32>> {
+        if step >= self.steps {
+            return Err(out_of_range(step, self.steps));
+        }
+        Ok((0..self.rows)
+            .flat_map(|i| (0..self.cols).map(move |j| (i, j)))
+            .map(|(i, j)| (self.f)(step, i, j))
+            .collect())
+    }
+}
+
+// Raw little-endian f32 frames stored back to back without a header, as written by
+// most simulation codes with a plain binary dump per step. The number of frames
+// follows from the file size.
+pub struct RawFramesSource {
+    pub rows: u16,
+    pub cols: u16,
+    steps: usize,
+    file: Mutex<File>,
+}
+
+impl RawFramesSource {
+    pub fn open(path: impl AsRef<Path>, rows: u16, cols: u16) -> std::io::Result<Self> {
+        let file = File::open(path)?;
+        let frame_bytes = 4 * rows as u64 * cols as u64;
+        let steps = file.metadata()?.len() / frame_bytes.max(1);
+        if steps == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("file is smaller than one {} x {} f32 frame", rows, cols),
+            ));
+        }
+        Ok(Self {
+            rows,
+            cols,
+            steps: steps as usize,
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl FrameSource for RawFramesSource {
+    fn size(&self) -> [u16; 2] {
+        [self.rows, self.cols]
+    }
+
+    fn len(&self) -> usize {
+        self.steps
+    }
+
+    fn frame(&self, step: usize) -> std::io::Result<Vec<f32>> {
+        if step >= self.steps {
+            return Err(out_of_range(step, self.steps));
+        }
+        let count = self.rows as usize * self.cols as usize;
+        let mut bytes = vec![0u8; 4 * count];
+        let mut file = self.file.lock().unwrap();
+        file.seek(SeekFrom::Start((4 * count * step) as u64))?;
+        file.read_exact(&mut bytes)?;
+        Ok(bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect())
+    }
+}
+// endregion: frame sources
+
+// region: frame cache
+// Frames around the current time step, loaded on a background thread so scrubbing
+// and playback never wait for the disk. update() asks for the current step first
+// and then for its neighbors, alternating ahead and behind; frames farthest from the
+// current step are evicted first. Steps wrap around, as playback loops.
+pub struct FrameCache {
+    pub size: [u16; 2],
+    pub capacity: usize, // frames kept resident, at least 2 * preload + 1
+    pub preload: usize,  // steps loaded ahead of and behind the current one
+    len: usize,
+    frames: HashMap<usize, Vec<f32>>,
+    value_range: Option<[f32; 2]>,
+    requests: Sender<Vec<usize>>,
+    results: Receiver<(usize, std::io::Result<Vec<f32>>)>,
+    requested_for: Option<usize>,
+}
+
+impl FrameCache {
+    pub fn new(source: Box<dyn FrameSource>, capacity: usize, preload: usize) -> Self {
+        let (size, len) = (source.size(), source.len());
+        let (requests, request_receiver) = mpsc::channel::<Vec<usize>>();
+        let (result_sender, results) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut queue = VecDeque::new();
+            loop {
+                // newer request lists replace the older ones, so scrubbing quickly
+                // does not leave a backlog of frames nobody looks at any more
+                let next = if queue.is_empty() {
+                    request_receiver.recv().ok()
+                } else {
+                    request_receiver.try_iter().last()
+                };
+                match next {
+                    Some(steps) => queue = steps.into(),
+                    None if queue.is_empty() => return,
+                    None => {}
+                }
+                if let Some(step) = queue.pop_front()
+                    && result_sender.send((step, source.frame(step))).is_err()
+                {
+                    return;
+                }
+            }
+        });
+        Self {
+            size,
+            capacity: capacity.max(2 * preload + 1),
+            preload,
+            len,
+            frames: HashMap::new(),
+            value_range: None,
+            requests,
+            results,
+            requested_for: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn contains(&self, step: usize) -> bool {
+        self.frames.contains_key(&step)
+    }
+
+    pub fn get(&self, step: usize) -> Option<&[f32]> {
+        self.frames.get(&step).map(|frame| frame.as_slice())
+    }
+
+    // resident steps in ascending order
+    pub fn resident(&self) -> Vec<usize> {
+        let mut steps: Vec<usize> = self.frames.keys().copied().collect();
+        steps.sort();
+        steps
+    }
+
+    // data range of all frames loaded so far, for a normalization that does not
+    // jump between time steps
+    pub fn value_range(&self) -> Option<[f32; 2]> {
+        self.value_range
+    }
+
+    // distance between two steps on the looping timeline
+    fn distance(&self, a: usize, b: usize) -> usize {
+        let d = a.abs_diff(b);
+        d.min(self.len - d)
+    }
+
+    // takes the frames loaded since the last call, requests the ones around step
+    // and returns the resident step closest to it (the step itself once loaded)
+    pub fn update(&mut self, step: usize) -> Option<usize> {
+        if self.len == 0 {
+            return None;
+        }
+        let step = step % self.len;
+        for (loaded, frame) in self.results.try_iter() {
+            let Ok(frame) = frame else {
+                continue;
+            };
+            for &v in frame.iter().filter(|v| v.is_finite()) {
+                let [vmin, vmax] = self.value_range.get_or_insert([v, v]);
+                *vmin = vmin.min(v);
+                *vmax = vmax.max(v);
+            }
+            self.frames.insert(loaded, frame);
+        }
+
+        if self.requested_for != Some(step) {
+            self.requested_for = Some(step);
+            let mut wanted = vec![step];
+            for k in 1..=self.preload.min(self.len / 2) {
+                wanted.push((step + k) % self.len);
+                wanted.push((step + self.len - k) % self.len);
+            }
+            wanted.dedup();
+            wanted.retain(|s| !self.frames.contains_key(s));
+            let _ = self.requests.send(wanted);
+        }
+
+        if self.frames.len() > self.capacity {
+            let mut by_distance: Vec<(usize, usize)> = self
+                .frames
+                .keys()
+                .map(|&s| (self.distance(s, step), s))
+                .collect();
+            by_distance.sort();
+            for (_, s) in by_distance.into_iter().skip(self.capacity) {
+                self.frames.remove(&s);
+            }
+        }
+
+        self.frames
+            .keys()
+            .copied()
+            .min_by_key(|&s| (self.distance(s, step), s))
+    }
+}
+// endregion: frame cache
@@ -0,0 +1,138 @@
+use std::path::Path;
+
+// region: shader hooks
+// User WGSL snippets spliced into the standard surface shaders when their pipelines
+// are built, so a custom effect does not need a fork of the whole shader file. A hook
+// point is a comment line "// @hook NAME" in the shader source; the snippets for NAME
+// are inserted below it with its indentation. The chapter shaders provide:
+//   vertex_declarations    module scope of the vertex shaders (helper functions, consts)
+//   vertex                 start of vs_main; position, normal and color are mutable
+//                          vec4f copies of the vertex attributes in model space
+//   fragment_declarations  module scope of the fragment shaders (material module)
+//   fragment_normal        in fs_main before the lighting; N is the mutable unit
+//                          normal in world space and V the unit vector to the eye
+//   fragment               end of fs_main; color is the mutable lit vec4f output, and
+//                          in (vPosition, vNormal, vColor), N, L and V are in scope
+// e.g. contour lines every 0.2 units of world height:
+//     ShaderHooks::default().snippet("fragment", "if fract(in.vPosition.y * 5.0) < 0.05 {
+//         color = vec4f(0.0, 0.0, 0.0, 1.0);
+//     }")
+pub const HOOK_MARKER: &str = "// @hook ";
+
+// names of the hook points of a shader source, in order
+pub fn hook_points(source: &str) -> Vec<&str> {
+    source
+        .lines()
+        .filter_map(|line| line.trim_start().strip_prefix(HOOK_MARKER))
+        .map(str::trim)
+        .collect()
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShaderHooks {
+    snippets: Vec<(String, String)>, // hook point, WGSL code
+}
+
+impl ShaderHooks {
+    // adds code at a hook point; several snippets at the same point keep their order
+    pub fn snippet(mut self, hook: &str, code: &str) -> Self {
+        self.snippets.push((hook.to_string(), code.to_string()));
+        self
+    }
+
+    // hook=path arguments of the chapter binaries, e.g. fragment=contours.wgsl
+    pub fn from_args(args: &[String]) -> anyhow::Result<Self> {
+        let mut hooks = Self::default();
+        for arg in args {
+            let Some((hook, path)) = arg.split_once('=') else {
+                anyhow::bail!("expected hook=file.wgsl, not '{}'", arg);
+            };
+            hooks = hooks.snippet(hook, &read_snippet(path)?);
+        }
+        Ok(hooks)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snippets.is_empty()
+    }
+
+    // the hook points the snippets are meant for
+    pub fn hooks(&self) -> Vec<&str> {
+        self.snippets
+            .iter()
+            .map(|(hook, _)| hook.as_str())
+            .collect()
+    }
+
+    // fails for snippets whose hook point none of the sources has, which would
+    // otherwise be dropped silently
+    pub fn check(&self, sources: &[&str]) -> anyhow::Result<()> {
+        let available: Vec<&str> = sources.iter().flat_map(|s| hook_points(s)).collect();
+        for hook in self.hooks() {
+            if !available.contains(&hook) {
+                anyhow::bail!(
+                    "unknown shader hook '{}'; available: {}",
+                    hook,
+                    available.join(", ")
+                );
+            }
+        }
+        Ok(())
+    }
+
+    // source with the snippets for its hook points inserted; snippets for other
+    // hook points are ignored, so one set of hooks serves both shader stages
+    pub fn apply(&self, source: &str) -> String {
+        let mut out = String::with_capacity(source.len());
+        for line in source.lines() {
+            out += line;
+            out.push('\n');
+            let trimmed = line.trim_start();
+            let Some(hook) = trimmed.strip_prefix(HOOK_MARKER) else {
+                continue;
+            };
+            let indent = &line[..line.len() - trimmed.len()];
+            for (_, code) in self.snippets.iter().filter(|(h, _)| h == hook.trim()) {
+                for code_line in code.lines() {
+                    if !code_line.trim().is_empty() {
+                        out += indent;
+                        out += code_line;
+                    }
+                    out.push('\n');
+                }
+            }
+        }
+        out
+    }
+
+    // applies the hooks and compiles the result; a snippet that does not compile is
+    // reported as an error instead of the device's uncaptured error panic
+    pub fn create_shader_module(
+        &self,
+        device: &wgpu::Device,
+        label: &str,
+        source: &str,
+    ) -> anyhow::Result<wgpu::ShaderModule> {
+        let descriptor = wgpu::ShaderModuleDescriptor {
+            label: Some(label),
+            source: wgpu::ShaderSource::Wgsl(self.apply(source).into()),
+        };
+        // error scopes cannot be waited on in the browser (see error::validated)
+        if cfg!(target_arch = "wasm32") {
+            return Ok(device.create_shader_module(descriptor));
+        }
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let module = device.create_shader_module(descriptor);
+        match pollster::block_on(device.pop_error_scope()) {
+            Some(e) => anyhow::bail!("shader {} with hooks {:?}: {}", label, self.hooks(), e),
+            None => Ok(module),
+        }
+    }
+}
+
+fn read_snippet(path: impl AsRef<Path>) -> anyhow::Result<String> {
+    let path = path.as_ref();
+    std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("cannot read shader hook {}: {}", path.display(), e))
+}
+// endregion: shader hooks
@@ -0,0 +1,305 @@
+use cgmath::{InnerSpace, Matrix4, Point3, Vector3};
+
+use super::wgpu_simplified as ws;
+
+// region: light space
+// View-projection matrix of a directional light: an orthographic box along direction
+// that just holds the bounding sphere (center, radius) of the shadow casters and
+// receivers. A tighter sphere gives sharper shadows.
+pub fn light_space_mat(direction: [f32; 3], center: [f32; 3], radius: f32) -> Matrix4<f32> {
+    let direction = Vector3::from(direction).normalize();
+    let center = Point3::from(center);
+    let eye = center - 2.0 * radius * direction;
+    let up = if direction.y.abs() > 0.99 {
+        Vector3::unit_z()
+    } else {
+        Vector3::unit_y()
+    };
+    let view_mat = Matrix4::look_at_rh(eye, center, up);
+    ws::create_ortho_mat(-radius, radius, -radius, radius, radius, 3.0 * radius) * view_mat
+}
+// endregion: light space
+
+// region: shadow map
+// A depth texture rendered from the light, sampled with a comparison sampler while
+// shading. Each frame:
+//
+//     shadow_map.write(&queue, &light_mat);
+//     {
+//         let mut pass = shadow_map.begin_pass(&mut encoder);
+//         // shadow pipeline (create_shadow_pipeline), casters drawn with lightMat * modelMat
+//     }
+//     // main pass: shadow_map.bind_group at the group given to shadow_wgsl
+//
+// bias moves the compared depth towards the light against shadow acne, darkness is
+// how much light a fully shadowed point loses and pcf_radius the spacing in texels of
+// the 3x3 filter that softens the shadow edges.
+pub const SHADOW_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth24Plus;
+pub const SHADOW_UNIFORM_SIZE: wgpu::BufferAddress = 80;
+
+pub struct ShadowMap {
+    pub size: u32,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    pub uniform_buffer: wgpu::Buffer, // light-space matrix and the parameters below
+    pub layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+    pub bias: f32,
+    pub darkness: f32,
+    pub pcf_radius: f32,
+}
+
+impl ShadowMap {
+    pub fn new(init: &impl ws::RenderTarget, size: u32) -> Self {
+        let device = init.device();
+        let view = ws::create_shadow_texture_view(init, size, size);
+        let sampler = ws::create_depth_sampler(device, Some(wgpu::CompareFunction::LessEqual));
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Shadow Uniform Buffer"),
+            size: SHADOW_UNIFORM_SIZE,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let (layout, bind_group) =
+            create_shadow_bind_group(device, &view, &sampler, &uniform_buffer);
+        Self {
+            size,
+            view,
+            sampler,
+            uniform_buffer,
+            layout,
+            bind_group,
+            bias: 0.002,
+            darkness: 0.6,
+            pcf_radius: 1.0,
+        }
+    }
+
+    // the ShadowUniforms of shadow_wgsl
+    pub fn uniform_data(&self, light_mat: &Matrix4<f32>) -> [f32; 20] {
+        let mut data = [0.0; 20];
+        let mat: &[f32; 16] = light_mat.as_ref();
+        data[..16].copy_from_slice(mat);
+        data[16..].copy_from_slice(&[
+            self.bias,
+            self.darkness.clamp(0.0, 1.0),
+            1.0 / self.size.max(1) as f32,
+            self.pcf_radius,
+        ]);
+        data
+    }
+
+    pub fn write(&self, queue: &wgpu::Queue, light_mat: &Matrix4<f32>) {
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&self.uniform_data(light_mat)),
+        );
+    }
+
+    // depth-only pass into the shadow map, cleared to the far plane
+    pub fn begin_pass<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder) -> wgpu::RenderPass<'a> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Shadow Pass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(ws::create_depth_stencil_attachment_store(
+                &self.view,
+                wgpu::StoreOp::Store,
+            )),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        })
+    }
+}
+
+// binding 0: the shadow map (texture_depth_2d), binding 1: the comparison sampler,
+// binding 2: the shadow uniforms; all for the fragment stage
+pub fn create_shadow_bind_group(
+    device: &wgpu::Device,
+    view: &wgpu::TextureView,
+    sampler: &wgpu::Sampler,
+    uniform_buffer: &wgpu::Buffer,
+) -> (wgpu::BindGroupLayout, wgpu::BindGroup) {
+    let visibility = wgpu::ShaderStages::FRAGMENT;
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Shadow Bind Group Layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Depth,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Shadow Bind Group"),
+        layout: &layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+        ],
+    });
+    (layout, bind_group)
+}
+
+// Depth-only pipeline of the shadow pass: no fragment stage and a slope-scaled depth
+// bias. Only the position (location 0) of the vertex buffers is read by
+// SHADOW_DEPTH_WGSL, so the vertex buffers of the main pass can be drawn as they are.
+pub fn create_shadow_pipeline(
+    device: &wgpu::Device,
+    vs_shader: &wgpu::ShaderModule,
+    pipeline_layout: &wgpu::PipelineLayout,
+    vertex_buffer_layout: &[wgpu::VertexBufferLayout],
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Shadow Pipeline"),
+        layout: Some(pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: vs_shader,
+            entry_point: Some("vs_main"),
+            buffers: vertex_buffer_layout,
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: None,
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: SHADOW_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::LessEqual,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState {
+                constant: 2,
+                slope_scale: 2.0,
+                clamp: 0.0,
+            },
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    })
+}
+// endregion: shadow map
+
+// region: shadow catcher
+// A ground plane that shows only the shadow it receives, for figures composited over
+// other backgrounds: shadow_catcher_wgsl() outputs black with the shadow strength,
+// 1 - shadow_factor(), as alpha, so the plane itself never shows. Draw it after the
+// opaque geometry, blended and without depth writes:
+//
+//     ws::IRenderPipeline {
+//         fs_shader: Some(&catcher_shader), // shadow_catcher_wgsl(2)
+//         blend: Some(shadow::SHADOW_CATCHER_BLENDING),
+//         depth_write_enabled: false,
+//         ..
+//     }
+//
+// Over a frame cleared to a transparent color the shadow ends up in the alpha channel.
+pub const SHADOW_CATCHER_BLENDING: wgpu::BlendState =
+    wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING;
+// endregion: shadow catcher
+
+// region: shaders
+// vertex shader of the shadow pass: one uniform buffer per caster at group 0 with the
+// light-space matrix and the model matrix of the caster
+pub const SHADOW_DEPTH_WGSL: &str = r#"
+struct ShadowPassUniforms {
+    lightMat: mat4x4f,
+    modelMat: mat4x4f,
+}
+@group(0) @binding(0) var<uniform> shadowPass : ShadowPassUniforms;
+
+@vertex
+fn vs_main(@location(0) position: vec4f) -> @builtin(position) vec4f {
+    return shadowPass.lightMat * shadowPass.modelMat * position;
+}
+"#;
+
+// Declarations of the shadow bind group at the given group and shadow_factor(), which
+// is 1 where a world space position is lit and 1 - darkness where it is in shadow.
+// Positions outside the light box count as lit.
+pub fn shadow_wgsl(group: u32) -> String {
+    format!(
+        r#"
+struct ShadowUniforms {{
+    lightMat: mat4x4f,
+    params: vec4f, // bias, darkness, texel size, pcf radius
+}}
+@group({group}) @binding(0) var shadowMap : texture_depth_2d;
+@group({group}) @binding(1) var shadowSampler : sampler_comparison;
+@group({group}) @binding(2) var<uniform> shadow : ShadowUniforms;
+
+fn shadow_factor(worldPos: vec3f) -> f32 {{
+    let p = shadow.lightMat * vec4f(worldPos, 1.0);
+    let ndc = p.xyz / p.w;
+    let uv = vec2f(0.5 * ndc.x + 0.5, 0.5 - 0.5 * ndc.y);
+    let depth = ndc.z - shadow.params.x;
+    let step = shadow.params.z * shadow.params.w;
+    var lit = 0.0;
+    for (var x = -1; x <= 1; x++) {{
+        for (var y = -1; y <= 1; y++) {{
+            let offset = vec2f(f32(x), f32(y)) * step;
+            lit += textureSampleCompareLevel(shadowMap, shadowSampler, uv + offset, depth);
+        }}
+    }}
+    lit /= 9.0;
+    let outside = any(uv < vec2f(0.0)) || any(uv > vec2f(1.0)) || depth > 1.0;
+    return select(mix(1.0 - shadow.params.y, 1.0, lit), 1.0, outside);
+}}
+"#
+    )
+}
+
+// fragment shader of the shadow catcher; reads the world position at location 0, the
+// vPosition of the chapter vertex shaders
+pub const SHADOW_CATCHER_WGSL: &str = r#"
+struct ShadowCatcherInput {
+    @location(0) vPosition: vec4f,
+}
+
+@fragment
+fn fs_main(in: ShadowCatcherInput) -> @location(0) vec4f {
+    return vec4f(0.0, 0.0, 0.0, 1.0 - shadow_factor(in.vPosition.xyz));
+}
+"#;
+
+// SHADOW_CATCHER_WGSL with the shadow bind group at group
+pub fn shadow_catcher_wgsl(group: u32) -> String {
+    with_shadow(group, SHADOW_CATCHER_WGSL)
+}
+
+// the shadow functions at group followed by the given shader source
+pub fn with_shadow(group: u32, shader: &str) -> String {
+    format!("{}\n{shader}", shadow_wgsl(group))
+}
+// endregion: shaders
@@ -0,0 +1,126 @@
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::wgpu_simplified::InitWgpuHeadless;
+
+const BOUNDARY: &str = "frame";
+
+// region: jpeg encoding
+pub fn encode_jpeg(rgba: &[u8], width: u32, height: u32, quality: u8) -> anyhow::Result<Vec<u8>> {
+    if width > u16::MAX as u32 || height > u16::MAX as u32 {
+        anyhow::bail!("frame size {}x{} exceeds the JPEG limit", width, height);
+    }
+    let mut jpeg = vec![];
+    let encoder = jpeg_encoder::Encoder::new(&mut jpeg, quality);
+    encoder.encode(
+        rgba,
+        width as u16,
+        height as u16,
+        jpeg_encoder::ColorType::Rgba,
+    )?;
+    Ok(jpeg)
+}
+// endregion: jpeg encoding
+
+// region: mjpeg server
+// Serves published frames as an MJPEG stream (multipart/x-mixed-replace) that any
+// browser can display by opening http://<addr>/. A headless renderer publishes each
+// frame after submitting it, as the headless_render example does with serve=ADDR:
+//
+//     let server = FrameServer::bind("127.0.0.1:8080")?;
+//     loop {
+//         init.queue.submit(..);
+//         server.publish_target(&init)?;
+//     }
+pub struct FrameServer {
+    pub addr: SocketAddr,
+    pub quality: u8,
+    clients: Arc<Mutex<Vec<TcpStream>>>,
+}
+
+impl FrameServer {
+    pub fn bind(addr: &str) -> anyhow::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let clients: Arc<Mutex<Vec<TcpStream>>> = Arc::new(Mutex::new(vec![]));
+
+        // each handshake runs on its own thread, so a viewer that connects without
+        // sending its request does not hold up the next one
+        let accepted = clients.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let accepted = accepted.clone();
+                std::thread::spawn(move || {
+                    if let Ok(stream) = open_stream(stream) {
+                        accepted.lock().unwrap().push(stream);
+                    }
+                });
+            }
+        });
+
+        Ok(Self {
+            addr,
+            quality: 80,
+            clients,
+        })
+    }
+
+    pub fn client_count(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+
+    // encodes and sends an RGBA8 frame to all connected clients; clients whose
+    // connection failed are dropped. The clients are taken out of the list while
+    // they are written, so new viewers are accepted in the meantime
+    pub fn publish(&self, rgba: &[u8], width: u32, height: u32) -> anyhow::Result<()> {
+        if self.client_count() == 0 {
+            return Ok(());
+        }
+
+        let jpeg = encode_jpeg(rgba, width, height, self.quality)?;
+        let header = format!(
+            "--{}\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+            BOUNDARY,
+            jpeg.len()
+        );
+        let mut clients = std::mem::take(&mut *self.clients.lock().unwrap());
+        clients.retain_mut(|client| {
+            client.write_all(header.as_bytes()).is_ok()
+                && client.write_all(&jpeg).is_ok()
+                && client.write_all(b"\r\n").is_ok()
+        });
+        self.clients.lock().unwrap().append(&mut clients);
+        Ok(())
+    }
+
+    // publishes the target of a headless frame once it is submitted; the target is
+    // only read back while a client is connected
+    pub fn publish_target(&self, init: &InitWgpuHeadless) -> anyhow::Result<()> {
+        if self.client_count() == 0 {
+            return Ok(());
+        }
+        let rgba = init.read_rgba()?;
+        self.publish(&rgba, init.width, init.height)
+    }
+}
+
+fn open_stream(mut stream: TcpStream) -> std::io::Result<TcpStream> {
+    // consume the request head; the same stream is served for every path
+    stream.set_read_timeout(Some(Duration::from_millis(500)))?;
+    let mut request = [0u8; 1024];
+    let _ = stream.read(&mut request)?;
+
+    // a stalled viewer must not block the render loop for long
+    stream.set_write_timeout(Some(Duration::from_millis(200)))?;
+    stream.set_nodelay(true)?;
+    write!(
+        stream,
+        "HTTP/1.0 200 OK\r\nCache-Control: no-cache\r\nConnection: close\r\n\
+         Content-Type: multipart/x-mixed-replace; boundary={}\r\n\r\n",
+        BOUNDARY
+    )?;
+    Ok(stream)
+}
+// endregion: mjpeg server
@@ -1,11 +1,19 @@
 #![allow(dead_code)]
 use super::colormap;
+use super::derivatives::{self, Partials};
+use super::error::{Error, Result};
 use super::math_func as mf;
 use cgmath::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::f32::consts::PI;
+use std::ops::Range;
+use std::sync::Arc;
 
-#[derive(Default)]
+// user surface functions, see ISimpleSurface::from_fn and IParametricSurface::from_fn
+pub type SimpleFn = Arc<dyn Fn(f32, f32, f32) -> f32 + Send + Sync>;
+pub type ParametricFn = Arc<dyn Fn(f32, f32, f32) -> [f32; 3] + Send + Sync>;
+
+#[derive(Clone, Default)]
 pub struct ISurfaceOutput {
     pub positions: Vec<[f32; 3]>,
     pub normals: Vec<[f32; 3]>,
@@ -31,7 +39,26 @@ pub struct IParametricSurface {
     pub colormap_name: String,
     pub wireframe_color: String,
     pub colormap_direction: u32, // 0: x-direction, 1: y-direction, 2: z-direction
+    pub colormap_range: Option<[f32; 2]>, // overrides the data range used by the colormap
+    pub colormap_interpolation: colormap::Interpolation,
     pub uv_lens: [f32; 2],
+    pub knot_p: u32,      // torus knot: windings around the axis
+    pub knot_q: u32,      // torus knot: windings through the hole
+    pub tube_radius: f32, // torus knot tube and klein bagel cross-section size
+    pub deformation: f32, // catenoid-helicoid bending: 0 = helicoid, PI/2 = catenoid
+    // overrides default_normal_orientation of the surface type
+    pub normal_orientation: Option<NormalOrientation>,
+    pub normal_mode: NormalMode,
+    pub shading: ShadingMode, // Flat needs 3 vertices per triangle, see MAX_VERTICES
+    // welds the last u and v rows to the first where the surface closes, see Seam
+    pub close_seams: bool,
+    // overrides default_seams of the surface type
+    pub seams: Option<[Seam; 2]>,
+
+    // user function [x, y, z] = f(u, v, t) replacing surface_type, over the current
+    // domain; t is its animation time parameter
+    pub custom_fn: Option<ParametricFn>,
+    pub t: f32,
 }
 
 fn surface_type_map() -> HashMap<u32, String> {
@@ -59,6 +86,13 @@ fn surface_type_map() -> HashMap<u32, String> {
     surface_type.insert(20, String::from("steiner"));
     surface_type.insert(21, String::from("torus"));
     surface_type.insert(22, String::from("wellenkugel"));
+    surface_type.insert(23, String::from("torus_knot"));
+    surface_type.insert(24, String::from("klein_bagel"));
+    surface_type.insert(25, String::from("cross_cap"));
+    surface_type.insert(26, String::from("boy_surface"));
+    surface_type.insert(27, String::from("scherk"));
+    surface_type.insert(28, String::from("catenoid_helicoid"));
+    surface_type.insert(29, String::from("costa"));
     surface_type
 }
 
@@ -83,7 +117,20 @@ impl Default for IParametricSurface {
             colormap_name: "jet".to_string(),
             wireframe_color: "white".to_string(),
             colormap_direction: 1,
+            colormap_range: None,
+            colormap_interpolation: colormap::Interpolation::Linear,
             uv_lens: [1.0, 1.0],
+            knot_p: 2,
+            knot_q: 3,
+            tube_radius: 0.3,
+            deformation: 0.5 * PI,
+            normal_orientation: None,
+            normal_mode: NormalMode::Analytic,
+            shading: ShadingMode::Smooth,
+            close_seams: false,
+            seams: None,
+            custom_fn: None,
+            t: 0.0,
         }
     }
 }
@@ -97,81 +144,299 @@ impl Default for IParametricSurface {
     None
 }*/
 
+// number of built-in parametric surface types
+pub const PARAMETRIC_SURFACE_COUNT: u32 = 30;
+
+// math function and [umin, umax, vmin, vmax] domain of a parametric surface type
+// without shape parameters; see IParametricSurface::surface_fn for all types
+#[allow(clippy::type_complexity)]
+pub fn parametric_surface_fn(surface_type: u32) -> (fn(f32, f32) -> [f32; 3], [f32; 4]) {
+    match surface_type {
+        1 => (mf::astroid, [0.0, 2.0 * PI, 0.0, 2.0 * PI]),
+        2 => (mf::astroid2, [0.0, 2.0 * PI, 0.0, 2.0 * PI]),
+        3 => (mf::astroidal_torus, [-PI, PI, 0.0, 5.0]),
+        4 => (mf::bohemian_dome, [0.0, 2.0 * PI, 0.0, 2.0 * PI]),
+        5 => (mf::boy_shape, [0.0, PI, 0.0, PI]),
+        6 => (mf::breather, [-14.0, 14.0, -12.0 * PI, 12.0 * PI]),
+        7 => (mf::enneper, [-3.3, 3.3, -3.3, 3.3]),
+        8 => (mf::figure8, [0.0, 4.0 * PI, 0.0, 2.0 * PI]),
+        9 => (mf::henneberg, [0.0, 1.0, 0.0, 2.0 * PI]),
+        10 => (mf::kiss, [-0.99999, 0.99999, 0.0, 2.0 * PI]),
+        11 => (mf::klein_bottle2, [0.0, 2.0 * PI, 0.0, 2.0 * PI]),
+        12 => (mf::klein_bottle3, [0.0, 4.0 * PI, 0.0, 2.0 * PI]),
+        13 => (mf::kuen, [-4.5, 4.5, -5.0, 5.0]),
+        14 => (mf::minimal, [-3.0, 1.0, -3.0 * PI, 3.0 * PI]),
+        15 => (mf::parabolic_cyclide, [-5.0, 5.0, -5.0, 5.0]),
+        16 => (mf::pear, [0.0, 1.0, 0.0, 2.0 * PI]),
+        17 => (mf::plucker_conoid, [-2.0, 2.0, 0.0, 2.0 * PI]),
+        18 => (mf::seashell, [0.0, 6.0 * PI, 0.0, 2.0 * PI]),
+        19 => (
+            mf::sievert_enneper,
+            [-PI / 2.1, PI / 2.1, 0.001, PI / 1.001],
+        ),
+        20 => (mf::steiner, [0.0, 1.999999 * PI, 0.0, 0.999999 * PI]),
+        21 => (mf::torus, [0.0, 2.0 * PI, 0.0, 2.0 * PI]),
+        22 => (mf::wellenkugel, [0.0, 14.5, 0.0, 5.2]),
+        25 => (mf::cross_cap, [0.0, 2.0 * PI, 0.0, 0.5 * PI]),
+        26 => (mf::boy_surface, [0.0, 1.0, 0.0, 2.0 * PI]),
+        27 => (mf::scherk, [-1.45, 1.45, -1.45, 1.45]),
+        // the ends are cut off where the surface leaves the unit square lattice cell
+        29 => (mf::costa, [0.1, 0.9, 0.1, 0.9]),
+        _ => (mf::klein_bottle, [0.0, PI, 0.0, 2.0 * PI]),
+    }
+}
+
+// triangle-list and line-list indices of a grid with nu x nv cells, (nu + 1) x (nv + 1)
+// vertices stored row by row
+pub fn grid_indices(nu: u16, nv: u16) -> (Vec<u16>, Vec<u16>) {
+    let (indices, indices2) = grid_indices32(nu as u32, nv as u32);
+    let narrow = |indices: Vec<u32>| indices.into_iter().map(|i| i as u16).collect();
+    (narrow(indices), narrow(indices2))
+}
+
+// grid_indices for meshes with more than 65536 vertices, drawn with Uint32 indices
+pub fn grid_indices32(nu: u32, nv: u32) -> (Vec<u32>, Vec<u32>) {
+    let mut indices: Vec<u32> = vec![];
+    let mut indices2: Vec<u32> = vec![];
+    let vertices_per_row = nv + 1;
+
+    for i in 0..nu {
+        for j in 0..nv {
+            let idx0 = j + i * vertices_per_row;
+            let idx1 = j + 1 + i * vertices_per_row;
+            let idx2 = j + 1 + (i + 1) * vertices_per_row;
+            let idx3 = j + (i + 1) * vertices_per_row;
+
+            let values: Vec<u32> = vec![idx0, idx1, idx2, idx2, idx3, idx0];
+            indices.extend(values);
+
+            let values2: Vec<u32> = vec![idx0, idx1, idx0, idx3];
+            indices2.extend(values2);
+            if i == nu - 1 || j == nv - 1 {
+                let edge_values: Vec<u32> = vec![idx1, idx2, idx2, idx3];
+                indices2.extend(edge_values);
+            }
+        }
+    }
+    (indices, indices2)
+}
+
+// unit normal from central differences. At singular points (poles, cusps) the
+// partial derivatives vanish or become parallel, so the normals of nearby parameter
+// values are averaged instead.
+fn parametric_normal(
+    f: &dyn Fn(f32, f32) -> [f32; 3],
+    u: f32,
+    v: f32,
+    du: f32,
+    dv: f32,
+) -> [f32; 3] {
+    let (epsu, epsv) = (0.01 * du, 0.01 * dv);
+    let normal_at = |u: f32, v: f32| -> Option<Vector3<f32>> {
+        let nu = Vector3::from(f(u + epsu, v)) - Vector3::from(f(u - epsu, v));
+        let nv = Vector3::from(f(u, v + epsv)) - Vector3::from(f(u, v - epsv));
+        let n = nu.cross(nv);
+        let valid = n.magnitude2() > 1e-12 * nu.magnitude2() * nv.magnitude2();
+        (valid && n.magnitude2().is_finite()).then(|| n.normalize())
+    };
+
+    if let Some(n) = normal_at(u, v) {
+        return n.into();
+    }
+    let sum = [(0.5, 0.5), (0.5, -0.5), (-0.5, 0.5), (-0.5, -0.5)]
+        .iter()
+        .filter_map(|(su, sv)| normal_at(u + su * du, v + sv * dv))
+        .fold(Vector3::zero(), |acc, n| acc + n);
+    if sum.magnitude2() > 1e-12 {
+        sum.normalize().into()
+    } else {
+        [0.0, 1.0, 0.0]
+    }
+}
+
+// How parametric_surface_data computes the normals. Central differences blur the
+// tangents over a hundredth of a grid cell, which shows as shading seams where the
+// curvature is high, as on the Klein bottles; Analytic crosses the exact partial
+// derivatives of the surface formula instead. Surface types without derivatives (see
+// IParametricSurface::partials_fn), custom functions and the singular points, where
+// the tangents vanish, still use central differences.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum NormalMode {
+    FiniteDifference,
+    #[default]
+    Analytic,
+}
+
+impl NormalMode {
+    pub const ALL: [Self; 2] = [Self::FiniteDifference, Self::Analytic];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::FiniteDifference => "finite_difference",
+            Self::Analytic => "analytic",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn next(&self) -> Self {
+        Self::ALL[(*self as usize + 1) % Self::ALL.len()]
+    }
+}
+
+// unit normal from the partial derivatives, or from central differences where they
+// give none
+fn analytic_normal(
+    partials: &dyn Fn(f32, f32) -> Partials,
+    f: &dyn Fn(f32, f32) -> [f32; 3],
+    u: f32,
+    v: f32,
+    du: f32,
+    dv: f32,
+) -> [f32; 3] {
+    // unit tangents first: near cusps they get small enough for their cross product
+    // to lose precision
+    let [pu, pv] = partials(u, v).map(|p| Vector3::from(p).normalize());
+    let n = pu.cross(pv);
+    if n.magnitude2() > 1e-12 && n.magnitude2().is_finite() {
+        n.normalize().into()
+    } else {
+        parametric_normal(f, u, v, du, dv)
+    }
+}
+
 impl IParametricSurface {
+    // surface of a user function [x, y, z] = f(u, v, t) over the domain set with
+    // domain(), [-1, 1] x [-1, 1] by default
+    pub fn from_fn(f: impl Fn(f32, f32, f32) -> [f32; 3] + Send + Sync + 'static) -> Self {
+        Self {
+            custom_fn: Some(Arc::new(f)),
+            ..Default::default()
+        }
+    }
+
+    // [umin, umax, vmin, vmax]; kept by new() only for a custom_fn, the built-in surface
+    // types have their own domains
+    pub fn domain(mut self, [umin, umax, vmin, vmax]: [f32; 4]) -> Self {
+        (self.umin, self.umax, self.vmin, self.vmax) = (umin, umax, vmin, vmax);
+        self
+    }
+
+    #[allow(clippy::new_ret_no_self, clippy::wrong_self_convention)]
     pub fn new(&mut self) -> ISurfaceOutput {
-        if self.surface_type == 1 {
-            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 2.0 * PI, 0.0, 2.0 * PI);
-            self.parametric_surface_data(&mf::astroid)
-        } else if self.surface_type == 2 {
-            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 2.0 * PI, 0.0, 2.0 * PI);
-            self.parametric_surface_data(&mf::astroid2)
-        } else if self.surface_type == 3 {
-            (self.umin, self.umax, self.vmin, self.vmax) = (-PI, PI, 0.0, 5.0);
-            self.parametric_surface_data(&mf::astroidal_torus)
-        } else if self.surface_type == 4 {
-            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 2.0 * PI, 0.0, 2.0 * PI);
-            self.parametric_surface_data(&mf::bohemian_dome)
-        } else if self.surface_type == 5 {
-            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, PI, 0.0, PI);
-            self.parametric_surface_data(&mf::boy_shape)
-        } else if self.surface_type == 6 {
-            (self.umin, self.umax, self.vmin, self.vmax) = (-14.0, 14.0, -12.0 * PI, 12.0 * PI);
-            self.parametric_surface_data(&mf::breather)
-        } else if self.surface_type == 7 {
-            (self.umin, self.umax, self.vmin, self.vmax) = (-3.3, 3.3, -3.3, 3.3);
-            self.parametric_surface_data(&mf::enneper)
-        } else if self.surface_type == 8 {
-            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 4.0 * PI, 0.0, 2.0 * PI);
-            self.parametric_surface_data(&mf::figure8)
-        } else if self.surface_type == 9 {
-            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 1.0, 0.0, 2.0 * PI);
-            self.parametric_surface_data(&mf::henneberg)
-        } else if self.surface_type == 10 {
-            (self.umin, self.umax, self.vmin, self.vmax) = (-0.99999, 0.99999, 0.0, 2.0 * PI);
-            self.parametric_surface_data(&mf::kiss)
-        } else if self.surface_type == 11 {
-            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 2.0 * PI, 0.0, 2.0 * PI);
-            self.parametric_surface_data(&mf::klein_bottle2)
-        } else if self.surface_type == 12 {
-            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 4.0 * PI, 0.0, 2.0 * PI);
-            self.parametric_surface_data(&mf::klein_bottle3)
-        } else if self.surface_type == 13 {
-            (self.umin, self.umax, self.vmin, self.vmax) = (-4.5, 4.5, -5.0, 5.0);
-            self.parametric_surface_data(&mf::kuen)
-        } else if self.surface_type == 14 {
-            (self.umin, self.umax, self.vmin, self.vmax) = (-3.0, 1.0, -3.0 * PI, 3.0 * PI);
-            self.parametric_surface_data(&mf::minimal)
-        } else if self.surface_type == 15 {
-            (self.umin, self.umax, self.vmin, self.vmax) = (-5.0, 5.0, -5.0, 5.0);
-            self.parametric_surface_data(&mf::parabolic_cyclide)
-        } else if self.surface_type == 16 {
-            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 1.0, 0.0, 2.0 * PI);
-            self.parametric_surface_data(&mf::pear)
-        } else if self.surface_type == 17 {
-            (self.umin, self.umax, self.vmin, self.vmax) = (-2.0, 2.0, 0.0, 2.0 * PI);
-            self.parametric_surface_data(&mf::plucker_conoid)
-        } else if self.surface_type == 18 {
-            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 6.0 * PI, 0.0, 2.0 * PI);
-            self.parametric_surface_data(&mf::seashell)
-        } else if self.surface_type == 19 {
-            (self.umin, self.umax, self.vmin, self.vmax) = (-PI / 2.1, PI / 2.1, 0.001, PI / 1.001);
-            self.parametric_surface_data(&mf::sievert_enneper)
-        } else if self.surface_type == 20 {
-            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 1.999999 * PI, 0.0, 0.999999 * PI);
-            self.parametric_surface_data(&mf::steiner)
-        } else if self.surface_type == 21 {
-            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 2.0 * PI, 0.0, 2.0 * PI);
-            self.parametric_surface_data(&mf::torus)
-        } else if self.surface_type == 22 {
-            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, 14.5, 0.0, 5.2);
-            self.parametric_surface_data(&mf::wellenkugel)
-        } else {
-            (self.umin, self.umax, self.vmin, self.vmax) = (0.0, PI, 0.0, 2.0 * PI);
-            self.parametric_surface_data(&mf::klein_bottle)
+        let (f, [umin, umax, vmin, vmax]) = self.surface_fn();
+        (self.umin, self.umax, self.vmin, self.vmax) = (umin, umax, vmin, vmax);
+        let mut output = self.parametric_surface_data(&f);
+        if self.flips_normals() {
+            output.flip_normals();
+        }
+        if self.close_seams {
+            output.close_seams(self.u_resolution, self.v_resolution, self.seams());
+        }
+        if self.shading == ShadingMode::Flat {
+            output.shade(ShadingMode::Flat);
+        }
+        output
+    }
+
+    // the normal orientation new() applies: the override or the surface type's default
+    pub fn orientation(&self) -> NormalOrientation {
+        match (self.normal_orientation, &self.custom_fn) {
+            (Some(orientation), _) => orientation,
+            (None, Some(_)) => NormalOrientation::Auto,
+            (None, None) => default_normal_orientation(self.surface_type),
+        }
+    }
+
+    // the seams new() closes: the override or the surface type's default
+    pub fn seams(&self) -> [Seam; 2] {
+        match (self.seams, &self.custom_fn) {
+            (Some(seams), _) => seams,
+            (None, Some(_)) => [Seam::Open; 2],
+            (None, None) => default_seams(self.surface_type),
+        }
+    }
+
+    // whether new() flips the generated normals. Auto scores a coarse mesh of fixed
+    // resolution, so the decision does not change with u_resolution and v_resolution.
+    pub fn flips_normals(&self) -> bool {
+        match self.orientation() {
+            NormalOrientation::Auto => {
+                let (f, domain) = self.surface_fn();
+                let mesh = orientation_mesh(&f, domain, ORIENTATION_RESOLUTION);
+                orientation_score(&mesh) < -ORIENTATION_THRESHOLD
+            }
+            NormalOrientation::Keep => false,
+            NormalOrientation::Flip => true,
+        }
+    }
+
+    // math function and domain of the current surface type, with the shape
+    // parameters of the struct applied, or of the custom_fn at time t
+    #[allow(clippy::type_complexity)]
+    pub fn surface_fn(&self) -> (Box<dyn Fn(f32, f32) -> [f32; 3]>, [f32; 4]) {
+        if let Some(f) = self.custom_fn.clone() {
+            let t = self.t;
+            let domain = [self.umin, self.umax, self.vmin, self.vmax];
+            return (Box::new(move |u, v| f(u, v, t)), domain);
+        }
+        let (p, q, tube_radius) = (self.knot_p as f32, self.knot_q as f32, self.tube_radius);
+        let alpha = self.deformation;
+        match self.surface_type {
+            23 => (
+                Box::new(move |u, v| mf::torus_knot(u, v, p, q, tube_radius)),
+                [0.0, 2.0 * PI, 0.0, 2.0 * PI],
+            ),
+            24 => (
+                Box::new(move |u, v| mf::klein_bagel(u, v, tube_radius)),
+                [0.0, 2.0 * PI, 0.0, 2.0 * PI],
+            ),
+            28 => (
+                Box::new(move |u, v| mf::catenoid_helicoid(u, v, alpha)),
+                [-PI, PI, -1.2, 1.2],
+            ),
+            _ => {
+                let (f, domain) = parametric_surface_fn(self.surface_type);
+                (Box::new(f), domain)
+            }
         }
     }
 
-    fn parametric_surface_data(&mut self, f: &dyn Fn(f32, f32) -> [f32; 3]) -> ISurfaceOutput {
+    // exact [d/du, d/dv] of the current surface type with its shape parameters, None
+    // for a custom_fn and the types without a derivative formula
+    pub fn partials_fn(&self) -> Option<Box<dyn Fn(f32, f32) -> Partials>> {
+        if self.custom_fn.is_some() {
+            return None;
+        }
+        let (p, q, tube_radius) = (self.knot_p as f32, self.knot_q as f32, self.tube_radius);
+        let alpha = self.deformation;
+        match self.surface_type {
+            23 => Some(Box::new(move |u, v| {
+                derivatives::partials(
+                    |u, v| derivatives::torus_knot(u, v, p, q, tube_radius),
+                    u,
+                    v,
+                )
+            })),
+            24 => Some(Box::new(move |u, v| {
+                derivatives::partials(|u, v| derivatives::klein_bagel(u, v, tube_radius), u, v)
+            })),
+            28 => Some(Box::new(move |u, v| {
+                derivatives::partials(|u, v| derivatives::catenoid_helicoid(u, v, alpha), u, v)
+            })),
+            _ => {
+                let f = derivatives::parametric_surface_derivatives(self.surface_type)?;
+                Some(Box::new(move |u, v| derivatives::partials(f, u, v)))
+            }
+        }
+    }
+
+    // generates the mesh over the current domain; does not touch any GPU state
+    pub fn parametric_surface_data(&self, f: &dyn Fn(f32, f32) -> [f32; 3]) -> ISurfaceOutput {
         let mut positions: Vec<[f32; 3]> = vec![];
         let mut normals: Vec<[f32; 3]> = vec![];
         let mut colors: Vec<[f32; 3]> = vec![];
@@ -180,12 +445,18 @@ impl IParametricSurface {
 
         let du = (self.umax - self.umin) / self.u_resolution as f32;
         let dv = (self.vmax - self.vmin) / self.v_resolution as f32;
-        let (epsu, epsv) = (0.01 * du, 0.01 * dv);
         //let (mut p0, mut p1, mut p2, mut p3): (Vector3<f32>, Vector3<f32>, Vector3<f32>, Vector3<f32>);
 
-        let (min_val, max_val, pts) = self.parametric_surface_range(f);
-        let cdata = colormap::colormap_data(&self.colormap_name);
-        let cdata2 = colormap::colormap_data(&self.wireframe_color);
+        let (mut min_val, mut max_val, pts) = self.parametric_surface_range(f);
+        if let Some(range) = self.colormap_range {
+            (min_val, max_val) = (range[0], range[1]);
+        }
+        let cmap = colormap::lookup(&self.colormap_name, self.colormap_interpolation);
+        let cmap2 = colormap::lookup(&self.wireframe_color, self.colormap_interpolation);
+        let partials = match self.normal_mode {
+            NormalMode::Analytic => self.partials_fn(),
+            NormalMode::FiniteDifference => None,
+        };
 
         for i in 0..=self.u_resolution {
             let u = self.umin + du * i as f32;
@@ -211,20 +482,18 @@ impl IParametricSurface {
                 }
                 let normal = p2.cross(p3).normalize();*/
 
-                let nu = Vector3::from(f(u + epsu, v)) - Vector3::from(f(u - epsu, v));
-                let nv = Vector3::from(f(u, v + epsv)) - Vector3::from(f(u, v - epsv));
-                let normal = nu.cross(nv).normalize();
-                normals.push(normal.into());
+                normals.push(match &partials {
+                    Some(partials) => analytic_normal(partials, f, u, v, du, dv),
+                    None => parametric_normal(f, u, v, du, dv),
+                });
 
                 // colormap
-                let color = colormap::color_lerp(
-                    cdata,
+                let color = cmap.color(
                     min_val,
                     max_val,
                     pts[i as usize][j as usize][self.colormap_direction as usize],
                 );
-                let color2 = colormap::color_lerp(
-                    cdata2,
+                let color2 = cmap2.color(
                     min_val,
                     max_val,
                     pts[i as usize][j as usize][self.colormap_direction as usize],
@@ -241,28 +510,7 @@ impl IParametricSurface {
         }
 
         // calculate indices
-        let mut indices: Vec<u16> = vec![];
-        let mut indices2: Vec<u16> = vec![];
-        let vertices_per_row = self.v_resolution + 1;
-
-        for i in 0..self.u_resolution {
-            for j in 0..self.v_resolution {
-                let idx0 = j + i * vertices_per_row;
-                let idx1 = j + 1 + i * vertices_per_row;
-                let idx2 = j + 1 + (i + 1) * vertices_per_row;
-                let idx3 = j + (i + 1) * vertices_per_row;
-
-                let values: Vec<u16> = vec![idx0, idx1, idx2, idx2, idx3, idx0];
-                indices.extend(values);
-
-                let values2: Vec<u16> = vec![idx0, idx1, idx0, idx3];
-                indices2.extend(values2);
-                if i == self.u_resolution - 1 || j == self.v_resolution - 1 {
-                    let edge_values: Vec<u16> = vec![idx1, idx2, idx2, idx3];
-                    indices2.extend(edge_values);
-                }
-            }
-        }
+        let (indices, indices2) = grid_indices(self.u_resolution, self.v_resolution);
 
         ISurfaceOutput {
             positions,
@@ -276,7 +524,7 @@ impl IParametricSurface {
     }
 
     fn parametric_surface_range(
-        &mut self,
+        &self,
         f: &dyn Fn(f32, f32) -> [f32; 3],
     ) -> (f32, f32, Vec<Vec<[f32; 3]>>) {
         let du = (self.umax - self.umin) / self.u_resolution as f32;
@@ -322,6 +570,412 @@ impl IParametricSurface {
 }
 // endregion: parametric surface
 
+// region: seams
+// How the last row of the parametric grid meets the first one in u or v. Open leaves
+// the two rows apart. Periodic welds vertex j of the last row to vertex j of the first;
+// Twisted(offset) welds it to the vertex mirrored in the other parameter about offset,
+// a fraction of its range: j to offset * n - j, wrapping around when the other
+// parameter is closed as well, which is how the Klein bottles close.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Seam {
+    #[default]
+    Open,
+    Periodic,
+    Twisted(f32),
+}
+
+// [u, v] seams of the built-in surfaces over their default domains. Rows that collapse
+// to a point (poles, cusps) are left as they are.
+pub fn default_seams(surface_type: u32) -> [Seam; 2] {
+    use Seam::*;
+    match surface_type {
+        0 | 11 => [Twisted(0.5), Periodic],
+        1 | 4 | 8 | 12 | 20 | 21 | 23 => [Periodic, Periodic],
+        2 | 9 | 10 | 16 | 17 | 18 | 26 => [Open, Periodic],
+        3 | 25 | 28 => [Periodic, Open],
+        5 => [Periodic, Twisted(1.0)],
+        24 => [Twisted(1.0), Periodic],
+        _ => [Open, Open],
+    }
+}
+
+// fraction of the surface size within which the welded vertices must meet
+pub const SEAM_TOLERANCE: f32 = 1e-3;
+
+impl ISurfaceOutput {
+    // Welds the seams of a mesh laid out like grid_indices(nu, nv): each vertex of the
+    // last u or v row is dropped and the triangles and lines use the first-row vertex it
+    // meets instead, so the surface has no duplicate vertices and no normal or shading
+    // break along the seam. The kept vertices keep their uvs, so a texture jumps back at
+    // the seam. A seam whose vertices do not meet, e.g. a twist between grid lines or a
+    // domain changed by hand, stays open; returns which of [u, v] were closed.
+    pub fn close_seams(&mut self, nu: u16, nv: u16, seams: [Seam; 2]) -> [bool; 2] {
+        let (nu, nv) = (nu as usize, nv as usize);
+        if self.positions.len() != (nu + 1) * (nv + 1) || nu == 0 || nv == 0 {
+            return [false; 2];
+        }
+        let index = |i: usize, j: usize| i * (nv + 1) + j;
+        // k mirrored about offset * n, or None when it falls off an open grid
+        let mirror = |k: usize, offset: f32, n: usize, wraps: bool| {
+            let m = (offset * n as f32).round() as i64 - k as i64;
+            if wraps {
+                Some(m.rem_euclid(n as i64) as usize)
+            } else {
+                (0..=n as i64).contains(&m).then_some(m as usize)
+            }
+        };
+        // (dropped, kept) vertex pairs of each seam
+        let pairs = |axis: usize| -> Option<Vec<(usize, usize)>> {
+            let (n, other) = if axis == 0 {
+                (nv, seams[1])
+            } else {
+                (nu, seams[0])
+            };
+            let wraps = other != Seam::Open;
+            (0..=n)
+                .map(|k| {
+                    let target = match seams[axis] {
+                        Seam::Open => return None,
+                        Seam::Periodic => k,
+                        Seam::Twisted(offset) => mirror(k, offset, n, wraps)?,
+                    };
+                    Some(if axis == 0 {
+                        (index(nu, k), index(0, target))
+                    } else {
+                        (index(k, nv), index(target, 0))
+                    })
+                })
+                .collect()
+        };
+
+        let (min, max) = self.positions.iter().fold(
+            (Vector3::from([f32::MAX; 3]), Vector3::from([f32::MIN; 3])),
+            |(min, max), p| {
+                let p = Vector3::from(*p);
+                (
+                    Vector3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)),
+                    Vector3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)),
+                )
+            },
+        );
+        let tolerance = SEAM_TOLERANCE * (max - min).magnitude();
+        let meets = |(a, b): &(usize, usize)| {
+            (Vector3::from(self.positions[*a]) - Vector3::from(self.positions[*b])).magnitude()
+                <= tolerance
+        };
+        let mut closed = [false; 2];
+        let mut target: Vec<usize> = (0..self.positions.len()).collect();
+        for (axis, seam_closed) in closed.iter_mut().enumerate() {
+            if let Some(pairs) = pairs(axis).filter(|pairs| pairs.iter().all(meets)) {
+                for (dropped, kept) in pairs {
+                    target[dropped] = kept;
+                }
+                *seam_closed = true;
+            }
+        }
+        if closed == [false; 2] {
+            return closed;
+        }
+        // a corner may be welded to a vertex that is dropped by the other seam
+        for k in 0..target.len() {
+            for _ in 0..4 {
+                target[k] = target[target[k]];
+            }
+        }
+
+        let mut remap = vec![0u16; target.len()];
+        let mut kept = 0;
+        for k in 0..target.len() {
+            if target[k] == k {
+                remap[k] = kept;
+                kept += 1;
+            }
+        }
+        fn keep<T>(values: &mut Vec<T>, target: &[usize]) {
+            let mut k = 0;
+            values.retain(|_| {
+                k += 1;
+                target[k - 1] == k - 1
+            });
+        }
+        keep(&mut self.positions, &target);
+        keep(&mut self.normals, &target);
+        keep(&mut self.colors, &target);
+        keep(&mut self.colors2, &target);
+        keep(&mut self.uvs, &target);
+        for i in self.indices.iter_mut() {
+            *i = remap[target[*i as usize]];
+        }
+        // the lines of the last row now lie on those of the first
+        let mut lines = HashSet::new();
+        let mut indices2 = Vec::with_capacity(self.indices2.len());
+        for line in self.indices2.chunks_exact(2) {
+            let [a, b] = [0, 1].map(|k| remap[target[line[k] as usize]]);
+            if a != b && lines.insert((a.min(b), a.max(b))) {
+                indices2.extend([a, b]);
+            }
+        }
+        self.indices2 = indices2;
+        closed
+    }
+}
+// endregion: seams
+
+// region: normal orientation
+// Whether the normals of a generated surface are made to face outwards. Auto flips the
+// normals and the triangle winding when orientation_score finds them facing inwards;
+// Keep leaves the mesh as generated and Flip always flips it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum NormalOrientation {
+    #[default]
+    Auto,
+    Keep,
+    Flip,
+}
+
+// One-sided surfaces (Klein bottles, Boy's surface, cross-cap, Roman surface) have no
+// outside, so the check would flip them at random; they keep their normals.
+pub fn default_normal_orientation(surface_type: u32) -> NormalOrientation {
+    match surface_type {
+        0 | 5 | 8 | 11 | 12 | 20 | 24 | 25 | 26 => NormalOrientation::Keep,
+        _ => NormalOrientation::Auto,
+    }
+}
+
+// fraction below which the score counts as undecided, e.g. for saddles
+pub const ORIENTATION_THRESHOLD: f32 = 0.1;
+pub const ORIENTATION_RESOLUTION: u16 = 48;
+
+// positions, normals and indices of f over domain on an n x n grid
+fn orientation_mesh(
+    f: &dyn Fn(f32, f32) -> [f32; 3],
+    [umin, umax, vmin, vmax]: [f32; 4],
+    n: u16,
+) -> ISurfaceOutput {
+    let (du, dv) = ((umax - umin) / n as f32, (vmax - vmin) / n as f32);
+    let mut output = ISurfaceOutput::default();
+    for i in 0..=n {
+        let u = umin + du * i as f32;
+        for j in 0..=n {
+            let v = vmin + dv * j as f32;
+            output.positions.push(f(u, v));
+            output.normals.push(parametric_normal(f, u, v, du, dv));
+        }
+    }
+    output.indices = grid_indices(n, n).0;
+    output
+}
+
+// Signed volume of the mesh seen from its centroid, as the flux of the position through
+// the triangles, each triangle facing the side of its vertex normals, divided by the
+// unsigned total: +1 when all normals face away from the centroid, -1 when all face
+// towards it and near 0 when they cancel out, as for open saddle-like surfaces.
+pub fn orientation_score(output: &ISurfaceOutput) -> f32 {
+    let n = output.positions.len().max(1) as f32;
+    let centroid = output
+        .positions
+        .iter()
+        .fold(Vector3::zero(), |acc, p| acc + Vector3::from(*p))
+        / n;
+    let (mut signed, mut total) = (0.0, 0.0);
+    for tri in output.indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|k| Vector3::from(output.positions[tri[k] as usize]));
+        let area_normal = (b - a).cross(c - a);
+        let vertex_normals = [0, 1, 2].iter().fold(Vector3::zero(), |acc, &k| {
+            acc + Vector3::from(output.normals[tri[k] as usize])
+        });
+        let side = area_normal.dot(vertex_normals).signum();
+        let flux = area_normal.dot((a + b + c) / 3.0 - centroid);
+        if flux.is_finite() {
+            signed += side * flux;
+            total += flux.abs();
+        }
+    }
+    if total > 0.0 { signed / total } else { 0.0 }
+}
+
+impl ISurfaceOutput {
+    // negates the normals and reverses the winding of the triangles
+    pub fn flip_normals(&mut self) {
+        for n in self.normals.iter_mut() {
+            *n = n.map(|c| -c);
+        }
+        for tri in self.indices.chunks_exact_mut(3) {
+            tri.swap(1, 2);
+        }
+    }
+
+    // applies the orientation; returns true if the mesh was flipped
+    pub fn orient(&mut self, orientation: NormalOrientation) -> bool {
+        let flip = match orientation {
+            NormalOrientation::Auto => orientation_score(self) < -ORIENTATION_THRESHOLD,
+            NormalOrientation::Keep => false,
+            NormalOrientation::Flip => true,
+        };
+        if flip {
+            self.flip_normals();
+        }
+        flip
+    }
+}
+// endregion: normal orientation
+
+// region: shading
+// Smooth shading interpolates the normals across the triangles, which needs vertices
+// shared by the triangles around them, and the generated grids are built that way.
+// Flat shading gives every triangle its own three vertices with the face normal, for
+// the faceted look of a low-resolution surface. The surfaces apply their shading field
+// in new(), and shade() converts any mesh, e.g. a flat one back to smooth:
+//
+//     let mut ps = IParametricSurface { shading: ShadingMode::Flat, ..Default::default() };
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ShadingMode {
+    #[default]
+    Smooth,
+    Flat,
+}
+
+impl ShadingMode {
+    pub const ALL: [Self; 2] = [Self::Smooth, Self::Flat];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Smooth => "smooth",
+            Self::Flat => "flat",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn next(&self) -> Self {
+        Self::ALL[(*self as usize + 1) % Self::ALL.len()]
+    }
+}
+
+// vertices the u16 indices can address
+pub const MAX_VERTICES: usize = u16::MAX as usize + 1;
+
+impl ISurfaceOutput {
+    // true if the mesh has the shading now; a mesh whose flat version would have more
+    // than MAX_VERTICES stays as it is
+    pub fn shade(&mut self, mode: ShadingMode) -> bool {
+        match mode {
+            ShadingMode::Smooth => *self = self.smooth_shaded(),
+            ShadingMode::Flat => match self.flat_shaded() {
+                Some(flat) => *self = flat,
+                None => return false,
+            },
+        }
+        true
+    }
+
+    // Welds the vertices at the same position (see weld_ids), like the corners a flat
+    // mesh repeats or the seams of a grid, and gives each welded vertex the average of
+    // their normals and the color and uv of the first of them. Where the normals cancel
+    // out, as along the seam of a one-sided surface, the vertices stay apart.
+    pub fn smooth_shaded(&self) -> ISurfaceOutput {
+        let ids = self.weld_ids();
+        let count = ids.iter().max().map_or(0, |id| id + 1);
+        let mut sums = vec![(Vector3::zero(), 0); count];
+        for (i, &id) in ids.iter().enumerate() {
+            let normal = self.normals.get(i).copied().unwrap_or_default();
+            sums[id] = (sums[id].0 + Vector3::from(normal), sums[id].1 + 1);
+        }
+        let welds = |id: usize| sums[id].0.magnitude() > 0.5 * sums[id].1 as f32;
+
+        let mut output = ISurfaceOutput::default();
+        let mut welded: HashMap<usize, u16> = HashMap::new();
+        let remap: Vec<u16> = ids
+            .iter()
+            .enumerate()
+            .map(|(i, &id)| {
+                if let (true, Some(&k)) = (welds(id), welded.get(&id)) {
+                    return k;
+                }
+                let k = output.positions.len() as u16;
+                output.push_vertex(self, i);
+                if welds(id) {
+                    output.normals[k as usize] = sums[id].0.normalize().into();
+                    welded.insert(id, k);
+                }
+                k
+            })
+            .collect();
+        output.indices = self.indices.iter().map(|&i| remap[i as usize]).collect();
+        output.indices2 = self.indices2.iter().map(|&i| remap[i as usize]).collect();
+        output
+    }
+
+    // every triangle with its own three vertices and its face normal, turned to the
+    // side of its vertex normals; None if that takes more than MAX_VERTICES. The
+    // wireframe lines keep their vertex positions and colors.
+    pub fn flat_shaded(&self) -> Option<ISurfaceOutput> {
+        if self.indices.len() > MAX_VERTICES {
+            return None;
+        }
+        let mut output = ISurfaceOutput::default();
+        let mut first_copy: Vec<Option<u16>> = vec![None; self.positions.len()];
+        for tri in self.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|k| Vector3::from(self.positions[tri[k] as usize]));
+            let side = tri.iter().fold(Vector3::zero(), |acc, &i| {
+                acc + Vector3::from(self.normals.get(i as usize).copied().unwrap_or_default())
+            });
+            let mut normal = (b - a).cross(c - a);
+            if normal.dot(side) < 0.0 {
+                normal = -normal;
+            }
+            // degenerate triangles take the direction of their vertex normals
+            let normal = [normal, side]
+                .into_iter()
+                .find(|n| n.magnitude2() > 0.0 && n.magnitude2().is_finite())
+                .map_or([0.0, 1.0, 0.0], |n| n.normalize().into());
+            for &i in tri {
+                let k = output.positions.len() as u16;
+                output.push_vertex(self, i as usize);
+                output.normals[k as usize] = normal;
+                first_copy[i as usize].get_or_insert(k);
+                output.indices.push(k);
+            }
+        }
+        for &i in &self.indices2 {
+            let k = match first_copy[i as usize] {
+                Some(k) => k,
+                // a line to a vertex of no triangle keeps that vertex
+                None if output.positions.len() < MAX_VERTICES => {
+                    let k = output.positions.len() as u16;
+                    output.push_vertex(self, i as usize);
+                    first_copy[i as usize] = Some(k);
+                    k
+                }
+                None => return None,
+            };
+            output.indices2.push(k);
+        }
+        Some(output)
+    }
+
+    // appends vertex i of source with the attributes source has
+    fn push_vertex(&mut self, source: &ISurfaceOutput, i: usize) {
+        self.positions.push(source.positions[i]);
+        self.normals
+            .push(source.normals.get(i).copied().unwrap_or([0.0, 1.0, 0.0]));
+        if let Some(&color) = source.colors.get(i) {
+            self.colors.push(color);
+        }
+        if let Some(&color) = source.colors2.get(i) {
+            self.colors2.push(color);
+        }
+        if let Some(&uv) = source.uvs.get(i) {
+            self.uvs.push(uv);
+        }
+    }
+}
+// endregion: shading
+
 // region: simple surface
 pub struct ISimpleSurface {
     pub surface_type: u32,
@@ -336,8 +990,14 @@ pub struct ISimpleSurface {
     pub colormap_name: String,
     pub wireframe_color: String,
     pub colormap_direction: u32, // 0: x-direction, 1: y-direction, 2: z-direction
-    pub t: f32,                  // animation time parameter
+    pub colormap_range: Option<[f32; 2]>, // overrides the data range used by the colormap
+    pub colormap_interpolation: colormap::Interpolation,
+    pub t: f32, // animation time parameter
     pub uv_lens: [f32; 2],
+    pub color_field: Option<u32>, // surface type whose height drives the color; own height if None
+    pub color_field_range: Option<[f32; 2]>, // fixed data range of the color field; auto if None
+    pub custom_fn: Option<SimpleFn>, // replaces surface_type, over the current domain
+    pub shading: ShadingMode,     // Flat needs 3 vertices per triangle, see MAX_VERTICES
 }
 
 impl Default for ISimpleSurface {
@@ -355,30 +1015,131 @@ impl Default for ISimpleSurface {
             colormap_name: "jet".to_string(),
             wireframe_color: "white".to_string(),
             colormap_direction: 1,
+            colormap_range: None,
+            colormap_interpolation: colormap::Interpolation::Linear,
             t: 0.0,
             uv_lens: [1.0, 1.0],
+            color_field: None,
+            color_field_range: None,
+            custom_fn: None,
+            shading: ShadingMode::Smooth,
         }
     }
 }
 
+// number of built-in simple surface types
+pub const SIMPLE_SURFACE_COUNT: u32 = 3;
+
+// math function, [xmin, xmax, zmin, zmax] domain and aspect ratio of a simple surface type
+#[allow(clippy::type_complexity)]
+pub fn simple_surface_fn(surface_type: u32) -> (fn(f32, f32, f32) -> [f32; 3], [f32; 4], f32) {
+    match surface_type {
+        0 => (mf::sinc, [-8.0, 8.0, -8.0, 8.0], 0.5),
+        1 => (mf::poles, [-8.0, 8.0, -8.0, 8.0], 0.6),
+        _ => (mf::peaks, [-3.0, 3.0, -3.0, 3.0], 0.9),
+    }
+}
+
 impl ISimpleSurface {
+    // surface of a user function y = f(x, z, t) over the domain set with domain(),
+    // [-1, 1] x [-1, 1] by default; aspect_ratio scales its height
+    pub fn from_fn(f: impl Fn(f32, f32, f32) -> f32 + Send + Sync + 'static) -> Self {
+        Self {
+            custom_fn: Some(Arc::new(f)),
+            ..Default::default()
+        }
+    }
+
+    // [xmin, xmax, zmin, zmax]; kept by new() only for a custom_fn, the built-in surface
+    // types have their own domains
+    pub fn domain(mut self, [xmin, xmax, zmin, zmax]: [f32; 4]) -> Self {
+        (self.xmin, self.xmax, self.zmin, self.zmax) = (xmin, xmax, zmin, zmax);
+        self
+    }
+
+    #[allow(clippy::new_ret_no_self, clippy::wrong_self_convention)]
     pub fn new(&mut self) -> ISurfaceOutput {
-        if self.surface_type == 0 {
-            (self.xmin, self.xmax, self.zmin, self.zmax) = (-8.0, 8.0, -8.0, 8.0);
-            self.aspect_ratio = 0.5;
-            self.simple_surface_data(&mf::sinc)
-        } else if self.surface_type == 1 {
-            (self.xmin, self.xmax, self.zmin, self.zmax) = (-8.0, 8.0, -8.0, 8.0);
-            self.aspect_ratio = 0.6;
-            self.simple_surface_data(&mf::poles)
+        let mut data = match self.custom_fn.clone() {
+            Some(f) => self.simple_surface_data(&move |x, z, t| [x, f(x, z, t), z]),
+            None => {
+                let (f, [xmin, xmax, zmin, zmax], aspect_ratio) =
+                    simple_surface_fn(self.surface_type);
+                (self.xmin, self.xmax, self.zmin, self.zmax) = (xmin, xmax, zmin, zmax);
+                self.aspect_ratio = aspect_ratio;
+                self.simple_surface_data(&f)
+            }
+        };
+        if let Some(field) = self.color_field {
+            self.apply_color_field(&mut data, &self.color_field_fn(field));
+        }
+        if self.shading == ShadingMode::Flat {
+            data.shade(ShadingMode::Flat);
+        }
+        data
+    }
+
+    // height of another simple surface type as a scalar field over this domain; its
+    // own domain is stretched onto the current one
+    pub fn color_field_fn(&self, surface_type: u32) -> impl Fn(f32, f32, f32) -> f32 {
+        let (g, [gxmin, gxmax, gzmin, gzmax], _) = simple_surface_fn(surface_type);
+        let (xmin, xmax, zmin, zmax) = (self.xmin, self.xmax, self.zmin, self.zmax);
+        move |x, z, t| {
+            let gx = gxmin + (gxmax - gxmin) * (x - xmin) / (xmax - xmin);
+            let gz = gzmin + (gzmax - gzmin) * (z - zmin) / (zmax - zmin);
+            g(gx, gz, t)[1]
+        }
+    }
+
+    // data range of the color field, i.e. the labels of its colorbar
+    pub fn color_field_range(&self, g: &dyn Fn(f32, f32, f32) -> f32) -> [f32; 2] {
+        if let Some(range) = self.color_field_range {
+            return range;
+        }
+        let (mut vmin, mut vmax) = (f32::MAX, f32::MIN);
+        self.for_each_grid_point(|x, z| {
+            let v = g(x, z, self.t);
+            (vmin, vmax) = (vmin.min(v), vmax.max(v));
+        });
+        // a constant field gets a non-empty range so color_lerp stays finite
+        if vmax > vmin {
+            [vmin, vmax]
         } else {
-            (self.xmin, self.xmax, self.zmin, self.zmax) = (-3.0, 3.0, -3.0, 3.0);
-            self.aspect_ratio = 0.9;
-            self.simple_surface_data(&mf::peaks)
+            [vmin, vmin + 1.0]
+        }
+    }
+
+    // colors the surface by a second scalar field normalized on its own, so height and
+    // color can show different quantities; the wireframe keeps the height colors
+    pub fn apply_color_field(
+        &self,
+        data: &mut ISurfaceOutput,
+        g: &dyn Fn(f32, f32, f32) -> f32,
+    ) -> [f32; 2] {
+        let [vmin, vmax] = self.color_field_range(g);
+        let cmap = colormap::lookup(&self.colormap_name, self.colormap_interpolation);
+        let mut k = 0;
+        self.for_each_grid_point(|x, z| {
+            if let Some(color) = data.colors.get_mut(k) {
+                *color = cmap.color(vmin, vmax, g(x, z, self.t));
+            }
+            k += 1;
+        });
+        [vmin, vmax]
+    }
+
+    // visits the grid in vertex order
+    fn for_each_grid_point(&self, mut visit: impl FnMut(f32, f32)) {
+        let dx = (self.xmax - self.xmin) / self.x_resolution as f32;
+        let dz = (self.zmax - self.zmin) / self.z_resolution as f32;
+        for i in 0..=self.x_resolution {
+            for j in 0..=self.z_resolution {
+                visit(self.xmin + dx * i as f32, self.zmin + dz * j as f32);
+            }
         }
     }
 
-    fn simple_surface_data(&mut self, f: &dyn Fn(f32, f32, f32) -> [f32; 3]) -> ISurfaceOutput {
+    // generates the mesh over the current domain; does not touch any GPU state
+    pub fn simple_surface_data(&self, f: &dyn Fn(f32, f32, f32) -> [f32; 3]) -> ISurfaceOutput {
         let mut positions: Vec<[f32; 3]> = vec![];
         let mut normals: Vec<[f32; 3]> = vec![];
         let mut colors: Vec<[f32; 3]> = vec![];
@@ -390,8 +1151,8 @@ impl ISimpleSurface {
         let (epsx, epsz) = (0.01 * dx, 0.01 * dz);
 
         let (ymin, ymax) = self.yrange(f);
-        let cdata = colormap::colormap_data(&self.colormap_name);
-        let cdata2 = colormap::colormap_data(&self.wireframe_color);
+        let cmap = colormap::lookup(&self.colormap_name, self.colormap_interpolation);
+        let cmap2 = colormap::lookup(&self.wireframe_color, self.colormap_interpolation);
 
         for i in 0..=self.x_resolution {
             let x = self.xmin + dx * i as f32;
@@ -414,18 +1175,9 @@ impl ISimpleSurface {
                 } else {
                     self.scale
                 };
-                let color = colormap::color_lerp(
-                    cdata,
-                    -range,
-                    range,
-                    pos[self.colormap_direction as usize],
-                );
-                let color2 = colormap::color_lerp(
-                    cdata2,
-                    -range,
-                    range,
-                    pos[self.colormap_direction as usize],
-                );
+                let [cmin, cmax] = self.colormap_range.unwrap_or([-range, range]);
+                let color = cmap.color(cmin, cmax, pos[self.colormap_direction as usize]);
+                let color2 = cmap2.color(cmin, cmax, pos[self.colormap_direction as usize]);
                 colors.push(color);
                 colors2.push(color2);
 
@@ -438,28 +1190,7 @@ impl ISimpleSurface {
         }
 
         // calculate indices
-        let mut indices: Vec<u16> = vec![];
-        let mut indices2: Vec<u16> = vec![];
-        let vertices_per_row = self.z_resolution + 1;
-
-        for i in 0..self.x_resolution {
-            for j in 0..self.z_resolution {
-                let idx0 = j + i * vertices_per_row;
-                let idx1 = j + 1 + i * vertices_per_row;
-                let idx2 = j + 1 + (i + 1) * vertices_per_row;
-                let idx3 = j + (i + 1) * vertices_per_row;
-
-                let values: Vec<u16> = vec![idx0, idx1, idx2, idx2, idx3, idx0];
-                indices.extend(values);
-
-                let values2: Vec<u16> = vec![idx0, idx1, idx0, idx3];
-                indices2.extend(values2);
-                if i == self.x_resolution - 1 || j == self.z_resolution - 1 {
-                    let edge_values: Vec<u16> = vec![idx1, idx2, idx2, idx3];
-                    indices2.extend(edge_values);
-                }
-            }
-        }
+        let (indices, indices2) = grid_indices(self.x_resolution, self.z_resolution);
 
         ISurfaceOutput {
             positions,
@@ -472,15 +1203,15 @@ impl ISimpleSurface {
         }
     }
 
-    fn normalize_data(&mut self, point: [f32; 3], ymin: f32, ymax: f32) -> [f32; 3] {
-        let mut pt = point.clone();
+    fn normalize_data(&self, point: [f32; 3], ymin: f32, ymax: f32) -> [f32; 3] {
+        let mut pt = point;
         pt[0] = (-1.0 + 2.0 * (pt[0] - self.xmin) / (self.xmax - self.xmin)) * self.scale;
         pt[1] = (-1.0 + 2.0 * (pt[1] - ymin) / (ymax - ymin)) * self.scale * self.aspect_ratio;
         pt[2] = (-1.0 + 2.0 * (pt[2] - self.zmin) / (self.zmax - self.zmin)) * self.scale;
         pt
     }
 
-    fn yrange(&mut self, f: &dyn Fn(f32, f32, f32) -> [f32; 3]) -> (f32, f32) {
+    fn yrange(&self, f: &dyn Fn(f32, f32, f32) -> [f32; 3]) -> (f32, f32) {
         let dx = (self.xmax - self.xmin) / self.x_resolution as f32;
         let dz = (self.zmax - self.zmin) / self.z_resolution as f32;
         let mut ymin = f32::MAX;
@@ -499,3 +1230,1077 @@ impl ISimpleSurface {
     }
 }
 // endregion: simple surface
+
+// region: streaming surface
+// how masked grid values are drawn
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MaskMode {
+    Hide,     // triangles and wireframe lines touching a masked value are skipped
+    Dim(f32), // masked vertices keep their geometry, colors are scaled by the factor
+    Ignore,   // the mask is kept but has no effect
+}
+
+// largest grid side of a streaming surface: 256 x 256 vertices fill the u16 indices
+pub const MAX_STREAMING_SIDE: u16 = 256;
+
+// Grids that take rows and frames of values as they arrive, e.g. from an
+// ingest::DataReceiver: IStreamingSurface overwrites its grid, ITelemetrySurface
+// scrolls a history
+pub trait StreamingGrid {
+    // appends a row, dropping the oldest one
+    fn push_row(&mut self, row: &[f32]);
+    // replaces the values of the whole grid, row-major
+    fn set_frame(&mut self, frame: &[f32]);
+    // sides clamped to 2..=MAX_STREAMING_SIDE
+    fn resize(&mut self, rows: u16, cols: u16);
+}
+
+// height grid that is filled from external data, either row by row (scrolling,
+// e.g. a spectrogram or telemetry history) or a full frame at a time
+pub struct IStreamingSurface {
+    pub rows: u16, // grid size along x, set with resize()
+    pub cols: u16, // grid size along z, set with resize()
+    pub values: Vec<f32>,
+    pub scale: f32,
+    pub aspect_ratio: f32,
+    pub colormap_name: String,
+    pub wireframe_color: String,
+    pub colormap_direction: u32, // 0: x-direction, 1: y-direction, 2: z-direction
+    pub colormap_range: Option<[f32; 2]>, // overrides the data range used by the colormap
+    pub value_range: Option<[f32; 2]>, // fixed data range mapped to the height; auto if None
+    pub uv_lens: [f32; 2],
+    pub mask: Vec<bool>, // one flag per grid value, true = masked; missing flags are unmasked
+    pub mask_mode: MaskMode,
+}
+
+impl Default for IStreamingSurface {
+    fn default() -> Self {
+        Self {
+            rows: 64,
+            cols: 64,
+            values: vec![0.0; 64 * 64],
+            scale: 1.0,
+            aspect_ratio: 0.5,
+            colormap_name: "jet".to_string(),
+            wireframe_color: "white".to_string(),
+            colormap_direction: 1,
+            colormap_range: None,
+            value_range: None,
+            uv_lens: [1.0, 1.0],
+            mask: vec![],
+            mask_mode: MaskMode::Hide,
+        }
+    }
+}
+
+impl IStreamingSurface {
+    // both sides are clamped to 2..=MAX_STREAMING_SIDE so the vertices fit u16 indices
+    pub fn resize(&mut self, rows: u16, cols: u16) {
+        self.rows = rows.clamp(2, MAX_STREAMING_SIDE);
+        self.cols = cols.clamp(2, MAX_STREAMING_SIDE);
+        self.values = vec![0.0; self.rows as usize * self.cols as usize];
+    }
+
+    // scrolls the grid by one row and appends the new row at the end
+    pub fn push_row(&mut self, row: &[f32]) {
+        let cols = self.cols as usize;
+        self.values.drain(..cols);
+        self.values
+            .extend((0..cols).map(|j| row.get(j).copied().unwrap_or(0.0)));
+    }
+
+    // replaces the whole grid; missing values are set to zero
+    pub fn set_frame(&mut self, frame: &[f32]) {
+        for (i, v) in self.values.iter_mut().enumerate() {
+            *v = frame.get(i).copied().unwrap_or(0.0);
+        }
+    }
+
+    pub fn value(&self, i: u16, j: u16) -> f32 {
+        self.values[i as usize * self.cols as usize + j as usize]
+    }
+
+    // whether the mask applies to the value with the given index
+    pub fn is_masked(&self, index: usize) -> bool {
+        self.mask_mode != MaskMode::Ignore && self.mask.get(index).copied().unwrap_or(false)
+    }
+
+    // values that are not masked out, e.g. for ranges and statistics
+    fn visible_values(&self) -> impl Iterator<Item = f32> + '_ {
+        self.values
+            .iter()
+            .enumerate()
+            .filter(|(k, _)| !self.is_masked(*k))
+            .map(|(_, v)| *v)
+    }
+
+    #[allow(clippy::new_ret_no_self, clippy::wrong_self_convention)]
+    pub fn new(&mut self) -> ISurfaceOutput {
+        let (rows, cols) = (self.rows, self.cols);
+        // masked values do not take part in the normalization
+        let [vmin, vmax] = self.value_range.unwrap_or_else(|| {
+            let vmin = self.visible_values().fold(f32::MAX, f32::min);
+            let vmax = self.visible_values().fold(f32::MIN, f32::max);
+            [vmin, vmax]
+        });
+        let vdist = if vmax > vmin { vmax - vmin } else { 1.0 };
+
+        let position = |i: u16, j: u16| -> [f32; 3] {
+            let x = -1.0 + 2.0 * i as f32 / (rows - 1) as f32;
+            let y = -1.0 + 2.0 * (self.value(i, j) - vmin) / vdist;
+            let z = -1.0 + 2.0 * j as f32 / (cols - 1) as f32;
+            [
+                x * self.scale,
+                y.clamp(-1.0, 1.0) * self.scale * self.aspect_ratio,
+                z * self.scale,
+            ]
+        };
+
+        let mut positions: Vec<[f32; 3]> = vec![];
+        let mut normals: Vec<[f32; 3]> = vec![];
+        let mut colors: Vec<[f32; 3]> = vec![];
+        let mut colors2: Vec<[f32; 3]> = vec![];
+        let mut uvs: Vec<[f32; 2]> = vec![];
+
+        let cdata = colormap::colormap_data(&self.colormap_name);
+        let cdata2 = colormap::colormap_data(&self.wireframe_color);
+        let range = if self.colormap_direction == 1 {
+            self.scale * self.aspect_ratio
+        } else {
+            self.scale
+        };
+        let [cmin, cmax] = self.colormap_range.unwrap_or([-range, range]);
+
+        for i in 0..rows {
+            for j in 0..cols {
+                let pos = position(i, j);
+                positions.push(pos);
+
+                // calculate normals from the neighboring grid points
+                let nx = Vector3::from(position((i + 1).min(rows - 1), j))
+                    - Vector3::from(position(i.saturating_sub(1), j));
+                let nz = Vector3::from(position(i, (j + 1).min(cols - 1)))
+                    - Vector3::from(position(i, j.saturating_sub(1)));
+                let normal = nx.cross(nz).normalize();
+                normals.push(normal.into());
+
+                // colormap
+                let c = pos[self.colormap_direction as usize];
+                colors.push(colormap::color_lerp(cdata, cmin, cmax, c));
+                colors2.push(colormap::color_lerp(cdata2, cmin, cmax, c));
+
+                // uvs
+                uvs.push([
+                    self.uv_lens[0] * i as f32 / (rows - 1) as f32,
+                    self.uv_lens[1] * j as f32 / (cols - 1) as f32,
+                ]);
+            }
+        }
+
+        // calculate indices
+        let (mut indices, mut indices2) = grid_indices(rows - 1, cols - 1);
+        if let MaskMode::Hide = self.mask_mode {
+            indices = self.mask_indices(&indices, 3);
+            indices2 = self.mask_indices(&indices2, 2);
+        }
+
+        let mut output = ISurfaceOutput {
+            positions,
+            normals,
+            colors,
+            colors2,
+            uvs,
+            indices,
+            indices2,
+        };
+        self.dim_masked(&mut output.colors);
+        self.dim_masked(&mut output.colors2);
+        output
+    }
+
+    // drops the primitives (3 indices for triangles, 2 for lines) that touch a masked
+    // value; lets a renderer switch the mask on and off by swapping index buffers only
+    pub fn mask_indices(&self, indices: &[u16], primitive_size: usize) -> Vec<u16> {
+        indices
+            .chunks_exact(primitive_size)
+            .filter(|p| p.iter().all(|&k| !self.is_masked(k as usize)))
+            .flatten()
+            .copied()
+            .collect()
+    }
+
+    // darkens the colors of masked vertices in MaskMode::Dim
+    pub fn dim_masked(&self, colors: &mut [[f32; 3]]) {
+        if let MaskMode::Dim(factor) = self.mask_mode {
+            for (k, color) in colors.iter_mut().enumerate() {
+                if self.is_masked(k) {
+                    *color = color.map(|c| c * factor);
+                }
+            }
+        }
+    }
+
+    // colors the surface by one value per grid point instead of the height, e.g. the
+    // difference to a reference solution, mapped over range; the wireframe keeps the
+    // height colors
+    pub fn color_by_values(&self, data: &mut ISurfaceOutput, values: &[f32], range: [f32; 2]) {
+        let cdata = colormap::colormap_data(&self.colormap_name);
+        for (color, v) in data.colors.iter_mut().zip(values) {
+            *color = colormap::color_lerp(cdata, range[0], range[1], *v);
+        }
+        self.dim_masked(&mut data.colors);
+    }
+
+    // data range that holds the mean values as well as the envelope values +/- k * sigma;
+    // set it as value_range so the mean and the band surfaces share one normalization
+    pub fn band_range(&self, sigmas: &[f32], k: f32) -> [f32; 2] {
+        let mut range = [f32::MAX, f32::MIN];
+        for (i, v) in self.values.iter().enumerate() {
+            if self.is_masked(i) {
+                continue;
+            }
+            let d = k * sigmas.get(i).copied().unwrap_or(0.0).abs();
+            range = [range[0].min(v - d), range[1].max(v + d)];
+        }
+        range
+    }
+
+    // envelope surface at values + k * sigma (k < 0 for the lower one); the values
+    // themselves are left unchanged
+    pub fn offset_surface(&mut self, sigmas: &[f32], k: f32) -> ISurfaceOutput {
+        let mean = self.values.clone();
+        for (i, v) in self.values.iter_mut().enumerate() {
+            *v += k * sigmas.get(i).copied().unwrap_or(0.0).abs();
+        }
+        let output = self.new();
+        self.values = mean;
+        output
+    }
+}
+
+impl StreamingGrid for IStreamingSurface {
+    fn push_row(&mut self, row: &[f32]) {
+        IStreamingSurface::push_row(self, row);
+    }
+
+    fn set_frame(&mut self, frame: &[f32]) {
+        IStreamingSurface::set_frame(self, frame);
+    }
+
+    fn resize(&mut self, rows: u16, cols: u16) {
+        IStreamingSurface::resize(self, rows, cols);
+    }
+}
+
+// fades colors towards gray where the uncertainty is large: sigma_max and above
+// are fully gray, sigma 0 keeps the colormap color
+pub fn modulate_by_uncertainty(colors: &mut [[f32; 3]], sigmas: &[f32], sigma_max: f32) {
+    if sigma_max <= 0.0 {
+        return;
+    }
+    for (color, sigma) in colors.iter_mut().zip(sigmas) {
+        let t = (sigma.abs() / sigma_max).clamp(0.0, 1.0);
+        let gray = (color[0] + color[1] + color[2]) / 3.0;
+        *color = color.map(|c| c + t * (gray - c));
+    }
+}
+// endregion: streaming surface
+
+// region: heightmap surface
+// largest grid side of a heightmap surface: 256 x 256 vertices fill the u16 indices
+pub const MAX_HEIGHTMAP_SIDE: usize = 256;
+
+// Height grid read from a file: measurements on a regular grid as CSV, or the gray
+// levels of a PNG heightmap. Rows of the file run along x and columns along z, as in
+// IStreamingSurface, which draws it; empty or non-numeric CSV cells are missing and
+// left out of the surface. Grids larger than MAX_HEIGHTMAP_SIDE on a side are
+// resampled down to fit, keeping their aspect:
+//
+//     let mut heightmap = IHeightmapSurface::load("measurements.csv")?;
+//     heightmap.colormap_name = "hot".to_string();
+//     let output = heightmap.new();
+pub struct IHeightmapSurface {
+    pub rows: u16,
+    pub cols: u16,
+    pub values: Vec<f32>,   // row-major
+    pub missing: Vec<bool>, // one flag per value, true where the file had no number
+    pub scale: f32,
+    pub aspect_ratio: f32,
+    pub colormap_name: String,
+    pub wireframe_color: String,
+    pub colormap_direction: u32, // 0: x-direction, 1: y-direction, 2: z-direction
+    pub colormap_range: Option<[f32; 2]>,
+    pub value_range: Option<[f32; 2]>, // fixed data range mapped to the height; auto if None
+    pub uv_lens: [f32; 2],
+}
+
+impl IHeightmapSurface {
+    // a row-major grid of values, NaN where a value is missing
+    pub fn from_grid(rows: usize, cols: usize, values: &[f32]) -> anyhow::Result<Self> {
+        if rows < 2 || cols < 2 || values.len() != rows * cols {
+            anyhow::bail!(
+                "a heightmap needs at least 2 x 2 values, got {} for {} x {}",
+                values.len(),
+                rows,
+                cols
+            );
+        }
+        let (rows, cols, values) = fit_grid(rows, cols, values);
+        let streaming = IStreamingSurface::default();
+        Ok(Self {
+            rows: rows as u16,
+            cols: cols as u16,
+            missing: values.iter().map(|v| !v.is_finite()).collect(),
+            values,
+            scale: streaming.scale,
+            aspect_ratio: streaming.aspect_ratio,
+            colormap_name: streaming.colormap_name,
+            wireframe_color: streaming.wireframe_color,
+            colormap_direction: streaming.colormap_direction,
+            colormap_range: None,
+            value_range: None,
+            uv_lens: streaming.uv_lens,
+        })
+    }
+
+    // one grid row per line, the values separated by commas, semicolons, tabs or
+    // spaces; blank lines, '#' comments and a header line without numbers are skipped
+    pub fn parse_csv(text: &str) -> anyhow::Result<Self> {
+        let mut grid: Vec<Vec<f32>> = vec![];
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let cells: Vec<&str> = if line.contains([',', ';', '\t']) {
+                line.split([',', ';', '\t']).map(str::trim).collect()
+            } else {
+                line.split_whitespace().collect()
+            };
+            let row: Vec<f32> = cells
+                .iter()
+                .map(|cell| cell.parse::<f32>().unwrap_or(f32::NAN))
+                .collect();
+            if grid.is_empty() && row.iter().all(|v| v.is_nan()) {
+                continue; // header
+            }
+            if let Some(first) = grid.first()
+                && first.len() != row.len()
+            {
+                anyhow::bail!(
+                    "line {} has {} values, the first row has {}",
+                    n + 1,
+                    row.len(),
+                    first.len()
+                );
+            }
+            grid.push(row);
+        }
+        let cols = grid.first().map_or(0, Vec::len);
+        Self::from_grid(grid.len(), cols, &grid.concat())
+    }
+
+    pub fn load_csv(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        Self::parse_csv(&std::fs::read_to_string(path)?)
+    }
+
+    // gray levels (or the luminance of color images) scaled to [0, 1]; image rows run
+    // along x, so the surface shows the image as seen from above; fully transparent
+    // pixels are missing
+    #[cfg(feature = "image")]
+    pub fn load_png(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        let mut decoder = png::Decoder::new(file);
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let mut reader = decoder.read_info()?;
+        let size = reader.output_buffer_size();
+        let mut buf = vec![0; size.ok_or_else(|| anyhow::anyhow!("image too large"))?];
+        let info = reader.next_frame(&mut buf)?;
+        let bytes = &buf[..info.buffer_size()];
+
+        let samples: Vec<f32> = match info.bit_depth {
+            png::BitDepth::Sixteen => bytes
+                .chunks_exact(2)
+                .map(|b| u16::from_be_bytes([b[0], b[1]]) as f32 / 65535.0)
+                .collect(),
+            _ => bytes.iter().map(|&b| b as f32 / 255.0).collect(),
+        };
+        let channels = info.color_type.samples();
+        let values: Vec<f32> = samples
+            .chunks_exact(channels)
+            .map(|px| {
+                let alpha = match info.color_type {
+                    png::ColorType::GrayscaleAlpha | png::ColorType::Rgba => px[channels - 1],
+                    _ => 1.0,
+                };
+                let level = match info.color_type {
+                    png::ColorType::Rgb | png::ColorType::Rgba => {
+                        0.2126 * px[0] + 0.7152 * px[1] + 0.0722 * px[2]
+                    }
+                    _ => px[0],
+                };
+                if alpha > 0.0 { level } else { f32::NAN }
+            })
+            .collect();
+        Self::from_grid(info.height as usize, info.width as usize, &values)
+    }
+
+    // load_png() for .png files, load_csv() for anything else
+    pub fn load(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let is_png = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+        if is_png {
+            #[cfg(feature = "image")]
+            return Self::load_png(path);
+            #[cfg(not(feature = "image"))]
+            anyhow::bail!("{}: PNG heightmaps need the image feature", path.display());
+        }
+        Self::load_csv(path)
+    }
+
+    // the range of the values that are not missing
+    pub fn data_range(&self) -> [f32; 2] {
+        self.values
+            .iter()
+            .zip(&self.missing)
+            .filter(|(_, missing)| !**missing)
+            .fold([f32::MAX, f32::MIN], |[min, max], (&v, _)| {
+                [min.min(v), max.max(v)]
+            })
+    }
+
+    // the values with the missing ones at the bottom of the range, so the normals of
+    // their neighbors stay finite; their triangles are hidden anyway
+    pub fn filled_values(&self) -> Vec<f32> {
+        let [min, _] = self.data_range();
+        let fill = if min <= f32::MAX { min } else { 0.0 };
+        self.values
+            .iter()
+            .zip(&self.missing)
+            .map(|(&v, &missing)| if missing { fill } else { v })
+            .collect()
+    }
+
+    #[allow(clippy::new_ret_no_self, clippy::wrong_self_convention)]
+    pub fn new(&mut self) -> ISurfaceOutput {
+        let mut surface = IStreamingSurface {
+            rows: self.rows,
+            cols: self.cols,
+            values: self.filled_values(),
+            scale: self.scale,
+            aspect_ratio: self.aspect_ratio,
+            colormap_name: self.colormap_name.clone(),
+            wireframe_color: self.wireframe_color.clone(),
+            colormap_direction: self.colormap_direction,
+            colormap_range: self.colormap_range,
+            value_range: self.value_range,
+            uv_lens: self.uv_lens,
+            mask: self.missing.clone(),
+            mask_mode: MaskMode::Hide,
+        };
+        surface.new()
+    }
+}
+
+// the grid bilinearly resampled to at most MAX_HEIGHTMAP_SIDE on a side; a sample
+// next to a missing (NaN) value is missing too
+fn fit_grid(rows: usize, cols: usize, values: &[f32]) -> (usize, usize, Vec<f32>) {
+    let side = rows.max(cols);
+    if side <= MAX_HEIGHTMAP_SIDE {
+        return (rows, cols, values.to_vec());
+    }
+    let shrink = |n: usize| (n * MAX_HEIGHTMAP_SIDE / side).max(2);
+    let (new_rows, new_cols) = (shrink(rows), shrink(cols));
+    let mut resampled = Vec::with_capacity(new_rows * new_cols);
+    for i in 0..new_rows {
+        let x = i as f32 * (rows - 1) as f32 / (new_rows - 1) as f32;
+        let i0 = (x as usize).min(rows - 2);
+        let fx = x - i0 as f32;
+        for j in 0..new_cols {
+            let z = j as f32 * (cols - 1) as f32 / (new_cols - 1) as f32;
+            let j0 = (z as usize).min(cols - 2);
+            let fz = z - j0 as f32;
+            let v = |di: usize, dj: usize| values[(i0 + di) * cols + j0 + dj];
+            let top = v(0, 0) + (v(0, 1) - v(0, 0)) * fz;
+            let bottom = v(1, 0) + (v(1, 1) - v(1, 0)) * fz;
+            resampled.push(top + (bottom - top) * fx);
+        }
+    }
+    (new_rows, new_cols, resampled)
+}
+// endregion: heightmap surface
+
+// region: telemetry surface
+// axis of a telemetry surface along which the history scrolls
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScrollAxis {
+    #[default]
+    X, // pushed rows run along x, the samples of a row along z
+    Z, // pushed columns run along z, the samples along x
+}
+
+// Scrolling history of sensor rows for realtime plots. The rows are kept in a ring
+// of `history` slots, one slot per row of vertices, so pushing a row rewrites the
+// vertices of its slot and the normals of its two neighbors instead of regenerating
+// the grid: update() refreshes those in the last output and returns the vertex
+// ranges to upload, and the scrolling itself is the translation scroll_offset(),
+// applied with the model matrix. The seam between the newest and the oldest slot
+// is left out by drawing two index ranges:
+//
+//     surface.push_row(&samples);
+//     for range in surface.update(&mut output) {
+//         dirty.mark_elements(range, std::mem::size_of::<Vertex>());
+//     }
+//     let (triangles, lines) = surface.draw_ranges();
+//
+// The vertices are laid out again (update() returns the whole grid) every
+// `history` rows, and whenever a row leaves the data range the vertices were
+// normalized with; a fixed grid.value_range avoids the latter. The ring is the
+// grid of an IStreamingSurface, one grid row per slot, whose style fields apply;
+// changes of the public fields take effect at the next new().
+pub struct ITelemetrySurface {
+    pub grid: IStreamingSurface, // slot-major ring of history rows of samples values
+    pub scroll_axis: ScrollAxis,
+    pushed: u64,      // rows pushed, counting the zero rows the ring starts with
+    base: u64,        // row at the start of the time axis in the uploaded vertices
+    range: [f32; 2],  // data range of the uploaded vertices
+    dirty: Vec<u16>,  // slots whose vertices changed since the last update
+    relayout: bool,
+}
+
+impl Default for ITelemetrySurface {
+    fn default() -> Self {
+        Self::with_size(128, 64)
+    }
+}
+
+impl ITelemetrySurface {
+    // a ring of history rows of samples values each, all zero; both are clamped as
+    // by IStreamingSurface::resize
+    pub fn with_size(history: u16, samples: u16) -> Self {
+        let mut surface = Self {
+            grid: IStreamingSurface::default(),
+            scroll_axis: ScrollAxis::X,
+            pushed: 0,
+            base: 0,
+            range: [0.0, 0.0],
+            dirty: vec![],
+            relayout: true,
+        };
+        surface.resize(history, samples);
+        surface
+    }
+
+    // an empty ring of the new size, laid out again at the next update()
+    pub fn resize(&mut self, history: u16, samples: u16) {
+        self.grid.resize(history, samples);
+        self.pushed = self.grid.rows as u64;
+        self.base = 0;
+        self.dirty.clear();
+        self.relayout = true;
+    }
+
+    pub fn history(&self) -> u16 {
+        self.grid.rows
+    }
+
+    pub fn samples(&self) -> u16 {
+        self.grid.cols
+    }
+
+    // rows pushed since the surface was created
+    pub fn rows_pushed(&self) -> u64 {
+        self.pushed - self.history() as u64
+    }
+
+    // appends a row, dropping the oldest one; missing samples and samples that are
+    // not finite are zero
+    pub fn push_row(&mut self, row: &[f32]) {
+        let (h, s) = (self.history() as u64, self.samples() as usize);
+        let slot = (self.pushed % h) as usize;
+        for (j, v) in self.grid.values[slot * s..(slot + 1) * s].iter_mut().enumerate() {
+            *v = row.get(j).copied().filter(|v| v.is_finite()).unwrap_or(0.0);
+        }
+        self.pushed += 1;
+
+        // the new row, the previous newest row and the new oldest row, whose
+        // normals lost a neighbor
+        let newest = slot as u64;
+        for k in [newest, (newest + h - 1) % h, (newest + 1) % h] {
+            if !self.dirty.contains(&(k as u16)) {
+                self.dirty.push(k as u16);
+            }
+        }
+        if self.pushed - self.base > 2 * h - 1 {
+            self.relayout = true;
+        }
+        let [vmin, vmax] = self.range;
+        let row = &self.grid.values[slot * s..(slot + 1) * s];
+        if self.grid.value_range.is_none() && row.iter().any(|&v| v < vmin || v > vmax) {
+            self.relayout = true;
+        }
+    }
+
+    // the row pushed age rows ago, 0 for the newest
+    pub fn row(&self, age: u16) -> &[f32] {
+        let h = self.history() as u64;
+        let slot = ((self.pushed - 1 - (age as u64).min(h - 1)) % h) as usize;
+        let s = self.samples() as usize;
+        &self.grid.values[slot * s..(slot + 1) * s]
+    }
+
+    // translation that scrolls the uploaded vertices so the newest row is at the
+    // end of the time axis, for the model matrix
+    pub fn scroll_offset(&self) -> [f32; 3] {
+        let h = self.history() as u64;
+        let step = 2.0 * self.grid.scale / (h - 1) as f32;
+        let shift = -((self.pushed - h - self.base) as f32) * step;
+        match self.scroll_axis {
+            ScrollAxis::X => [shift, 0.0, 0.0],
+            ScrollAxis::Z => [0.0, 0.0, shift],
+        }
+    }
+
+    // index ranges of indices and indices2 to draw, without the seam
+    pub fn draw_ranges(&self) -> ([Range<u32>; 2], [Range<u32>; 2]) {
+        let (h, s) = (self.history() as u32, self.samples() as u32);
+        let newest = ((self.pushed - 1) % h as u64) as u32;
+        let (triangles, lines) = (6 * (s - 1), 4 * s - 2);
+        (
+            [
+                0..newest * triangles,
+                (newest + 1) * triangles..h * triangles,
+            ],
+            // the seam strip starts with the sample lines of the newest row
+            [
+                0..newest * lines + 2 * (s - 1),
+                (newest + 1) * lines..h * lines,
+            ],
+        )
+    }
+
+    // lays out all the vertices again, with the newest row at the end of the time axis
+    #[allow(clippy::new_ret_no_self, clippy::wrong_self_convention)]
+    pub fn new(&mut self) -> ISurfaceOutput {
+        let h = self.history() as u64;
+        self.base = self.pushed - h;
+        self.range = self.grid.value_range.unwrap_or_else(|| {
+            let vmin = self.grid.values.iter().copied().fold(f32::MAX, f32::min);
+            let vmax = self.grid.values.iter().copied().fold(f32::MIN, f32::max);
+            [vmin, vmax]
+        });
+        self.dirty.clear();
+        self.relayout = false;
+
+        let n = self.grid.values.len();
+        let mut output = ISurfaceOutput {
+            positions: vec![[0.0; 3]; n],
+            normals: vec![[0.0; 3]; n],
+            colors: vec![[0.0; 3]; n],
+            colors2: vec![[0.0; 3]; n],
+            uvs: vec![[0.0; 2]; n],
+            ..Default::default()
+        };
+        for slot in 0..self.history() {
+            self.write_slot(&mut output, slot);
+        }
+        (output.indices, output.indices2) = self.ring_indices();
+        output
+    }
+
+    // refreshes the vertices of the rows pushed since the last call in an output of
+    // new() and returns the vertex ranges that changed; the indices never do
+    pub fn update(&mut self, output: &mut ISurfaceOutput) -> Vec<Range<usize>> {
+        if self.relayout || output.positions.len() != self.grid.values.len() {
+            *output = self.new();
+            let all = 0..self.grid.values.len();
+            return vec![all];
+        }
+        let s = self.samples() as usize;
+        let mut dirty = std::mem::take(&mut self.dirty);
+        dirty.sort_unstable();
+        for &slot in dirty.iter() {
+            self.write_slot(output, slot);
+        }
+        dirty
+            .iter()
+            .map(|&slot| slot as usize * s..(slot as usize + 1) * s)
+            .collect()
+    }
+
+    // row number held by a slot
+    fn slot_row(&self, slot: u16) -> u64 {
+        let h = self.history() as u64;
+        let oldest = self.pushed - h;
+        oldest + (slot as u64 + h - oldest % h) % h
+    }
+
+    fn position(&self, row: u64, j: u16) -> [f32; 3] {
+        let (h, s) = (self.history() as u64, self.samples() as usize);
+        let [vmin, vmax] = self.range;
+        let vdist = if vmax > vmin { vmax - vmin } else { 1.0 };
+        let value = self.grid.values[(row % h) as usize * s + j as usize];
+        let t = -1.0 + 2.0 * (row - self.base) as f32 / (h - 1) as f32;
+        let u = -1.0 + 2.0 * j as f32 / (s - 1) as f32;
+        let y = (-1.0 + 2.0 * (value - vmin) / vdist).clamp(-1.0, 1.0);
+        let y = y * self.grid.scale * self.grid.aspect_ratio;
+        match self.scroll_axis {
+            ScrollAxis::X => [t * self.grid.scale, y, u * self.grid.scale],
+            ScrollAxis::Z => [u * self.grid.scale, y, t * self.grid.scale],
+        }
+    }
+
+    fn write_slot(&self, output: &mut ISurfaceOutput, slot: u16) {
+        let (h, s) = (self.history() as u64, self.samples());
+        let row = self.slot_row(slot);
+        let (oldest, newest) = (self.pushed - h, self.pushed - 1);
+        let cdata = colormap::colormap_data(&self.grid.colormap_name);
+        let cdata2 = colormap::colormap_data(&self.grid.wireframe_color);
+        let range = if self.grid.colormap_direction == 1 {
+            self.grid.scale * self.grid.aspect_ratio
+        } else {
+            self.grid.scale
+        };
+        let [cmin, cmax] = self.grid.colormap_range.unwrap_or([-range, range]);
+
+        for j in 0..s {
+            let k = slot as usize * s as usize + j as usize;
+            let pos = self.position(row, j);
+            // differences to the neighbors along time and along the samples
+            let dt = Vector3::from(self.position((row + 1).min(newest), j))
+                - Vector3::from(self.position(row.saturating_sub(1).max(oldest), j));
+            let ds = Vector3::from(self.position(row, (j + 1).min(s - 1)))
+                - Vector3::from(self.position(row, j.saturating_sub(1)));
+            let normal = match self.scroll_axis {
+                ScrollAxis::X => dt.cross(ds),
+                ScrollAxis::Z => ds.cross(dt),
+            };
+            output.positions[k] = pos;
+            output.normals[k] = normal.normalize().into();
+            let c = pos[self.grid.colormap_direction as usize];
+            output.colors[k] = colormap::color_lerp(cdata, cmin, cmax, c);
+            output.colors2[k] = colormap::color_lerp(cdata2, cmin, cmax, c);
+            output.uvs[k] = [
+                (row - self.base) as f32 / (h - 1) as f32,
+                j as f32 / (s - 1) as f32,
+            ];
+        }
+    }
+
+    // strip k joins slot k to the next slot, the last back to the first: the triangles
+    // of grid_indices over one row more than the ring, wrapped, then lines with the
+    // sample lines of slot k first so the seam strip can keep them
+    fn ring_indices(&self) -> (Vec<u16>, Vec<u16>) {
+        let (h, s) = (self.history(), self.samples());
+        let n = h as u32 * s as u32;
+        let (triangles, _) = grid_indices32(h as u32, s as u32 - 1);
+        let mut indices: Vec<u16> = triangles.into_iter().map(|k| (k % n) as u16).collect();
+        if self.scroll_axis == ScrollAxis::Z {
+            indices.chunks_exact_mut(6).for_each(|quad| quad.reverse());
+        }
+        let mut indices2: Vec<u16> = vec![];
+        for k in 0..h {
+            let (row, next) = (k * s, (k + 1) % h * s);
+            indices2.extend((0..s - 1).flat_map(|j| [row + j, row + j + 1]));
+            indices2.extend((0..s).flat_map(|j| [row + j, next + j]));
+        }
+        (indices, indices2)
+    }
+}
+
+impl StreamingGrid for ITelemetrySurface {
+    fn push_row(&mut self, row: &[f32]) {
+        ITelemetrySurface::push_row(self, row);
+    }
+
+    // pushes the rows of a row-major frame, the oldest first
+    fn set_frame(&mut self, frame: &[f32]) {
+        let s = self.samples() as usize;
+        for k in 0..self.history() as usize {
+            self.push_row(frame.get(k * s..).unwrap_or(&[]));
+        }
+    }
+
+    fn resize(&mut self, rows: u16, cols: u16) {
+        ITelemetrySurface::resize(self, rows, cols);
+    }
+}
+// endregion: telemetry surface
+
+// region: implicit surface
+// iso-surface f(x, y, z) = iso_level extracted from a cubic grid by marching
+// tetrahedra; vertices are shared along grid edges, so the mesh is welded
+pub struct IImplicitSurface {
+    pub surface_type: u32,
+    pub surface_type_map: HashMap<u32, String>,
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+    pub resolution: u16, // grid cells per axis
+    pub iso_level: f32,
+    pub scale: f32,
+    pub colormap_name: String,
+    pub wireframe_color: String,
+    pub colormap_direction: u32, // 0: x-direction, 1: y-direction, 2: z-direction
+    pub colormap_range: Option<[f32; 2]>, // overrides the data range used by the colormap
+}
+
+// number of built-in implicit surface types
+pub const IMPLICIT_SURFACE_COUNT: u32 = 3;
+
+fn implicit_type_map() -> HashMap<u32, String> {
+    let mut surface_type = HashMap::new();
+    surface_type.insert(0, String::from("gyroid"));
+    surface_type.insert(1, String::from("schwarz_p"));
+    surface_type.insert(2, String::from("schwarz_d"));
+    surface_type
+}
+
+pub fn implicit_surface_fn(surface_type: u32) -> fn(f32, f32, f32) -> f32 {
+    match surface_type {
+        1 => mf::schwarz_p,
+        2 => mf::schwarz_d,
+        _ => mf::gyroid,
+    }
+}
+
+impl Default for IImplicitSurface {
+    fn default() -> Self {
+        Self {
+            surface_type: 0,
+            surface_type_map: implicit_type_map(),
+            min: [-PI, -PI, -PI],
+            max: [PI, PI, PI],
+            resolution: 32,
+            iso_level: 0.0,
+            scale: 1.0,
+            colormap_name: "jet".to_string(),
+            wireframe_color: "white".to_string(),
+            colormap_direction: 1,
+            colormap_range: None,
+        }
+    }
+}
+
+// Kuhn decomposition of a cube into six tetrahedra around the diagonal from corner 0
+// to corner 6; neighboring cubes split their shared faces the same way
+const CUBE_CORNERS: [[usize; 3]; 8] = [
+    [0, 0, 0],
+    [1, 0, 0],
+    [1, 1, 0],
+    [0, 1, 0],
+    [0, 0, 1],
+    [1, 0, 1],
+    [1, 1, 1],
+    [0, 1, 1],
+];
+const CUBE_TETRAHEDRA: [[usize; 4]; 6] = [
+    [0, 1, 2, 6],
+    [0, 2, 3, 6],
+    [0, 3, 7, 6],
+    [0, 7, 4, 6],
+    [0, 4, 5, 6],
+    [0, 5, 1, 6],
+];
+
+impl IImplicitSurface {
+    #[allow(clippy::new_ret_no_self, clippy::wrong_self_convention)]
+    pub fn new(&mut self) -> Result<ISurfaceOutput> {
+        self.implicit_surface_data(&implicit_surface_fn(self.surface_type))
+    }
+
+    // extracts the mesh; fails with Error::IndexOverflow if the vertex count would
+    // exceed the u16 index range
+    pub fn implicit_surface_data(
+        &self,
+        f: &dyn Fn(f32, f32, f32) -> f32,
+    ) -> Result<ISurfaceOutput> {
+        let n = self.resolution.max(1) as usize;
+        let step = [0, 1, 2].map(|k| (self.max[k] - self.min[k]) / n as f32);
+        let grid_index = |i: usize, j: usize, k: usize| (k * (n + 1) + j) * (n + 1) + i;
+        let grid_point = |idx: usize| -> [f32; 3] {
+            let (i, j, k) = (
+                idx % (n + 1),
+                idx / (n + 1) % (n + 1),
+                idx / ((n + 1) * (n + 1)),
+            );
+            [
+                self.min[0] + step[0] * i as f32,
+                self.min[1] + step[1] * j as f32,
+                self.min[2] + step[2] * k as f32,
+            ]
+        };
+
+        let mut values = vec![0.0f32; (n + 1) * (n + 1) * (n + 1)];
+        for (idx, value) in values.iter_mut().enumerate() {
+            let p = grid_point(idx);
+            *value = f(p[0], p[1], p[2]) - self.iso_level;
+        }
+
+        let h = 0.1 * step[0].min(step[1]).min(step[2]);
+        let gradient = |p: [f32; 3]| -> Vector3<f32> {
+            Vector3::new(
+                f(p[0] + h, p[1], p[2]) - f(p[0] - h, p[1], p[2]),
+                f(p[0], p[1] + h, p[2]) - f(p[0], p[1] - h, p[2]),
+                f(p[0], p[1], p[2] + h) - f(p[0], p[1], p[2] - h),
+            )
+        };
+
+        let mut raw_positions: Vec<[f32; 3]> = vec![];
+        let mut edge_vertices: HashMap<(usize, usize), u16> = HashMap::new();
+        let mut indices: Vec<u16> = vec![];
+
+        let mut edge_vertex = |a: usize, b: usize, positions: &mut Vec<[f32; 3]>| -> Option<u16> {
+            let key = (a.min(b), a.max(b));
+            if let Some(&idx) = edge_vertices.get(&key) {
+                return Some(idx);
+            }
+            if positions.len() >= u16::MAX as usize {
+                return None;
+            }
+            let (pa, pb) = (grid_point(a), grid_point(b));
+            let (va, vb) = (values[a], values[b]);
+            let s = if va != vb { va / (va - vb) } else { 0.5 };
+            positions.push([
+                pa[0] + s * (pb[0] - pa[0]),
+                pa[1] + s * (pb[1] - pa[1]),
+                pa[2] + s * (pb[2] - pa[2]),
+            ]);
+            let idx = (positions.len() - 1) as u16;
+            edge_vertices.insert(key, idx);
+            Some(idx)
+        };
+
+        for k in 0..n {
+            for j in 0..n {
+                for i in 0..n {
+                    let corners = CUBE_CORNERS.map(|c| grid_index(i + c[0], j + c[1], k + c[2]));
+                    for tet in CUBE_TETRAHEDRA.iter() {
+                        let v = tet.map(|t| corners[t]);
+                        let inside: Vec<usize> =
+                            v.iter().copied().filter(|&c| values[c] < 0.0).collect();
+                        let outside: Vec<usize> =
+                            v.iter().copied().filter(|&c| values[c] >= 0.0).collect();
+
+                        let triangles: Vec<[(usize, usize); 3]> = match inside.len() {
+                            1 => vec![[
+                                (inside[0], outside[0]),
+                                (inside[0], outside[1]),
+                                (inside[0], outside[2]),
+                            ]],
+                            3 => vec![[
+                                (outside[0], inside[0]),
+                                (outside[0], inside[1]),
+                                (outside[0], inside[2]),
+                            ]],
+                            2 => vec![
+                                [
+                                    (inside[0], outside[0]),
+                                    (inside[0], outside[1]),
+                                    (inside[1], outside[1]),
+                                ],
+                                [
+                                    (inside[0], outside[0]),
+                                    (inside[1], outside[1]),
+                                    (inside[1], outside[0]),
+                                ],
+                            ],
+                            _ => vec![],
+                        };
+
+                        for tri in triangles {
+                            let mut idx = [0u16; 3];
+                            for (m, &(a, b)) in tri.iter().enumerate() {
+                                match edge_vertex(a, b, &mut raw_positions) {
+                                    Some(id) => idx[m] = id,
+                                    None => return Err(Error::IndexOverflow("implicit surface")),
+                                }
+                            }
+                            if idx[0] == idx[1] || idx[1] == idx[2] || idx[2] == idx[0] {
+                                continue;
+                            }
+
+                            // wind the triangle counter-clockwise when seen from the
+                            // outside; the edge midpoints give the same orientation as the
+                            // interpolated vertices but never degenerate, so neighboring
+                            // tetrahedra always agree
+                            let [m0, m1, m2] = tri.map(|(a, b)| {
+                                0.5 * (Vector3::from(grid_point(a)) + Vector3::from(grid_point(b)))
+                            });
+                            let centroid = |corners: &[usize]| {
+                                corners
+                                    .iter()
+                                    .map(|&c| Vector3::from(grid_point(c)))
+                                    .fold(Vector3::new(0.0, 0.0, 0.0), |acc, p| acc + p)
+                                    / corners.len() as f32
+                            };
+                            let outward = centroid(&outside) - centroid(&inside);
+                            if (m1 - m0).cross(m2 - m0).dot(outward) < 0.0 {
+                                idx.swap(1, 2);
+                            }
+                            indices.extend(idx);
+                        }
+                    }
+                }
+            }
+        }
+        // normalize into [-scale/2, scale/2] like the parametric surfaces
+        let dist = (0..3)
+            .map(|k| self.max[k] - self.min[k])
+            .fold(f32::MIN, f32::max);
+        let center = [0, 1, 2].map(|k| 0.5 * (self.min[k] + self.max[k]));
+        let cdata = colormap::colormap_data(&self.colormap_name);
+        let cdata2 = colormap::colormap_data(&self.wireframe_color);
+        let [cmin, cmax] = self
+            .colormap_range
+            .unwrap_or([-0.5 * self.scale, 0.5 * self.scale]);
+
+        let mut positions: Vec<[f32; 3]> = vec![];
+        let mut normals: Vec<[f32; 3]> = vec![];
+        let mut colors: Vec<[f32; 3]> = vec![];
+        let mut colors2: Vec<[f32; 3]> = vec![];
+        let mut uvs: Vec<[f32; 2]> = vec![];
+        for p in raw_positions.iter() {
+            let pos = [0, 1, 2].map(|k| self.scale * (p[k] - center[k]) / dist);
+            positions.push(pos);
+
+            let g = gradient(*p);
+            normals.push(if g.magnitude2() > 0.0 {
+                g.normalize().into()
+            } else {
+                [0.0, 1.0, 0.0]
+            });
+
+            let c = pos[self.colormap_direction as usize];
+            colors.push(colormap::color_lerp(cdata, cmin, cmax, c));
+            colors2.push(colormap::color_lerp(cdata2, cmin, cmax, c));
+            uvs.push([
+                (p[0] - self.min[0]) / (self.max[0] - self.min[0]),
+                (p[2] - self.min[2]) / (self.max[2] - self.min[2]),
+            ]);
+        }
+
+        // wireframe: every triangle edge once
+        let mut edges = std::collections::HashSet::new();
+        let mut indices2: Vec<u16> = vec![];
+        for tri in indices.chunks_exact(3) {
+            for (a, b) in [(tri[0], tri[1]), (tri[1], tri[2]), (tri[2], tri[0])] {
+                if edges.insert((a.min(b), a.max(b))) {
+                    indices2.extend([a, b]);
+                }
+            }
+        }
+
+        Ok(ISurfaceOutput {
+            positions,
+            normals,
+            colors,
+            colors2,
+            uvs,
+            indices,
+            indices2,
+        })
+    }
+}
+// endregion: implicit surface
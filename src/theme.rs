@@ -0,0 +1,89 @@
+// region: themes
+// Display theme of the overlays and wireframes. HighContrast keeps them readable
+// when projected or for low-vision users: bold, saturated overlay colors over a black
+// inset background, text half as large again and wireframes twice as thick where
+// their width can be set (wireframe::barycentric_wireframe_wgsl). The ui scale of
+// SurfaceArgs (--ui-scale) multiplies the size of every overlay element on top of
+// the theme, e.g.
+//
+//     let stats_overlay = StatsOverlay::themed(args.theme, args.ui_scale);
+//     plot_surface(f).theme(args.theme).ui_scale(args.ui_scale).show()?;
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Theme {
+    #[default]
+    Standard,
+    HighContrast,
+}
+
+// colors of the overlay elements
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Palette {
+    pub text: [f32; 3],
+    pub frame: [f32; 3], // outlines and tick marks
+    pub grid: [f32; 3],
+    pub graph: [f32; 3],
+    pub highlight: [f32; 3], // probe curves
+    pub warning: [f32; 3],   // budgets and limits
+    pub background: [f32; 3],
+}
+
+impl Theme {
+    pub const ALL: [Self; 2] = [Self::Standard, Self::HighContrast];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Standard => "standard",
+            Self::HighContrast => "high_contrast",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|theme| theme.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn next(&self) -> Self {
+        Self::ALL[(*self as usize + 1) % Self::ALL.len()]
+    }
+
+    pub fn palette(&self) -> Palette {
+        match self {
+            Self::Standard => Palette {
+                text: [0.9; 3],
+                frame: [0.8; 3],
+                grid: [0.35; 3],
+                graph: [0.2, 1.0, 0.3],
+                highlight: [1.0, 0.85, 0.2],
+                warning: [1.0, 0.5, 0.1],
+                background: [0.12; 3],
+            },
+            Self::HighContrast => Palette {
+                text: [1.0; 3],
+                frame: [1.0; 3],
+                grid: [0.6; 3],
+                graph: [0.0, 1.0, 0.0],
+                highlight: [1.0, 1.0, 0.0],
+                warning: [1.0, 0.2, 0.0],
+                background: [0.0; 3],
+            },
+        }
+    }
+
+    // factor of the text height, before the ui scale
+    pub fn text_scale(&self) -> f32 {
+        match self {
+            Self::Standard => 1.0,
+            Self::HighContrast => 1.5,
+        }
+    }
+
+    // width in pixels of wireframes drawn by the fragment shader
+    pub fn wireframe_width(&self) -> f32 {
+        match self {
+            Self::Standard => 1.5,
+            Self::HighContrast => 3.0,
+        }
+    }
+}
+// endregion: themes
@@ -0,0 +1,530 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Mutex;
+
+use cgmath::{InnerSpace, Matrix4, Point3, Vector3, Vector4};
+
+use super::colormap;
+use super::surface_data::{ISurfaceOutput, grid_indices};
+
+// region: tile sources
+// Height data that is read on demand, one tile at a time, so the whole grid never
+// has to fit into memory. Grid point (i, j) lies at row i along x and column j
+// along z.
+pub trait TileSource {
+    // [rows, cols] of grid points
+    fn size(&self) -> [u32; 2];
+
+    fn value(&self, i: u32, j: u32) -> std::io::Result<f32>;
+
+    // rows x cols values starting at (i0, j0) with the given stride, row-major;
+    // coordinates outside the grid are clamped to its border
+    fn read(
+        &self,
+        i0: i64,
+        j0: i64,
+        rows: u32,
+        cols: u32,
+        stride: u32,
+    ) -> std::io::Result<Vec<f32>> {
+        let [n, m] = self.size();
+        let mut values = Vec::with_capacity(rows as usize * cols as usize);
+        for a in 0..rows as i64 {
+            let i = (i0 + a * stride as i64).clamp(0, n as i64 - 1) as u32;
+            for b in 0..cols as i64 {
+                let j = (j0 + b * stride as i64).clamp(0, m as i64 - 1) as u32;
+                values.push(self.value(i, j)?);
+            }
+        }
+        Ok(values)
+    }
+}
+
+// grid held in memory, row-major
+pub struct GridSource {
+    pub rows: u32,
+    pub cols: u32,
+    pub values: Vec<f32>,
+}
+
+impl TileSource for GridSource {
+    fn size(&self) -> [u32; 2] {
+        [self.rows, self.cols]
+    }
+
+    fn value(&self, i: u32, j: u32) -> std::io::Result<f32> {
+        Ok(self.values[i as usize * self.cols as usize + j as usize])
+    }
+}
+
+// procedural grid, evaluated per grid point
+pub struct FnSource {
+    pub rows: u32,
+    pub cols: u32,
+    pub f: Box<dyn Fn(u32, u32) -> f32>,
+}
+
+impl TileSource for FnSource {
+    fn size(&self) -> [u32; 2] {
+        [self.rows, self.cols]
+    }
+
+    fn value(&self, i: u32, j: u32) -> std::io::Result<f32> {
+        Ok((self.f)(i, j))
+    }
+}
+
+// Raw little-endian f32 grid on disk, row-major without a header (the layout most
+// DEM tools can export). Only the samples a tile needs are read.
+pub struct RawFileSource {
+    pub rows: u32,
+    pub cols: u32,
+    file: Mutex<File>,
+}
+
+impl RawFileSource {
+    pub fn open(path: impl AsRef<Path>, rows: u32, cols: u32) -> std::io::Result<Self> {
+        let file = File::open(path)?;
+        let expected = 4 * rows as u64 * cols as u64;
+        if file.metadata()?.len() < expected {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("file is smaller than a {} x {} f32 grid", rows, cols),
+            ));
+        }
+        Ok(Self {
+            rows,
+            cols,
+            file: Mutex::new(file),
+        })
+    }
+
+    fn read_span(file: &mut File, offset: u64, count: usize) -> std::io::Result<Vec<f32>> {
+        let mut bytes = vec![0u8; 4 * count];
+        file.seek(SeekFrom::Start(4 * offset))?;
+        file.read_exact(&mut bytes)?;
+        Ok(bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect())
+    }
+}
+
+impl TileSource for RawFileSource {
+    fn size(&self) -> [u32; 2] {
+        [self.rows, self.cols]
+    }
+
+    fn value(&self, i: u32, j: u32) -> std::io::Result<f32> {
+        let mut file = self.file.lock().unwrap();
+        let offset = i as u64 * self.cols as u64 + j as u64;
+        Ok(Self::read_span(&mut file, offset, 1)?[0])
+    }
+
+    // reads each needed row in one go for fine strides; coarse strides read the
+    // samples one by one instead of pulling in whole rows
+    fn read(
+        &self,
+        i0: i64,
+        j0: i64,
+        rows: u32,
+        cols: u32,
+        stride: u32,
+    ) -> std::io::Result<Vec<f32>> {
+        let (n, m) = (self.rows as i64, self.cols as i64);
+        let columns: Vec<i64> = (0..cols as i64)
+            .map(|b| (j0 + b * stride as i64).clamp(0, m - 1))
+            .collect();
+        let (first, last) = (columns[0], columns[columns.len() - 1]);
+        let mut file = self.file.lock().unwrap();
+        let mut values = Vec::with_capacity(rows as usize * cols as usize);
+        for a in 0..rows as i64 {
+            let i = (i0 + a * stride as i64).clamp(0, n - 1);
+            if stride <= 16 {
+                let span = Self::read_span(
+                    &mut file,
+                    (i * m + first) as u64,
+                    (last - first + 1) as usize,
+                )?;
+                values.extend(columns.iter().map(|&j| span[(j - first) as usize]));
+            } else {
+                for &j in columns.iter() {
+                    values.push(Self::read_span(&mut file, (i * m + j) as u64, 1)?[0]);
+                }
+            }
+        }
+        Ok(values)
+    }
+}
+// endregion: tile sources
+
+// region: tiled surface
+// A node of the tile quadtree: level 0 tiles sample every grid point, each level
+// above doubles the stride and the covered area.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TileKey {
+    pub level: u32,
+    pub x: u32, // tile index along the rows
+    pub z: u32, // tile index along the columns
+}
+
+impl TileKey {
+    pub fn parent(&self) -> TileKey {
+        TileKey {
+            level: self.level + 1,
+            x: self.x / 2,
+            z: self.z / 2,
+        }
+    }
+
+    pub fn is_ancestor_of(&self, other: &TileKey) -> bool {
+        self.level > other.level
+            && other.x >> (self.level - other.level) == self.x
+            && other.z >> (self.level - other.level) == self.z
+    }
+}
+
+// Out-of-core renderer support for grids far larger than GPU memory: select() picks
+// the tiles for the current view (frustum culling plus distance based level of
+// detail), tile_data() builds the mesh of a single tile from the source, and
+// TileCache keeps the GPU buffers of recently used tiles. Neighboring tiles share
+// their border samples; the cracks between tiles of different levels are hidden by
+// skirts hanging down from the tile edges.
+pub struct TiledSurface {
+    pub source: Box<dyn TileSource>,
+    pub tile_size: u32, // cells per tile edge, at most 250 so a tile with skirts fits u16 indices
+    pub scale: f32,     // the longer side of the grid spans [-scale, scale]
+    pub aspect_ratio: f32,
+    pub value_range: [f32; 2], // data range mapped to [-scale, scale] * aspect_ratio
+    pub lod_factor: f32,       // a tile is split while the eye is closer than lod_factor x its size
+    pub skirt_depth: f32,      // skirt length as a fraction of the height range
+    pub colormap_name: String,
+    pub wireframe_color: String,
+}
+
+impl TiledSurface {
+    // estimates the value range from a coarse sampling of the source
+    pub fn from_source(source: Box<dyn TileSource>) -> std::io::Result<Self> {
+        let [rows, cols] = source.size();
+        let stride = (rows.max(cols) / 256).max(1);
+        let samples = source.read(0, 0, rows.div_ceil(stride), cols.div_ceil(stride), stride)?;
+        let vmin = samples.iter().copied().fold(f32::MAX, f32::min);
+        let vmax = samples.iter().copied().fold(f32::MIN, f32::max);
+        Ok(Self {
+            source,
+            tile_size: 128,
+            scale: 1.0,
+            aspect_ratio: 0.1,
+            value_range: if vmax > vmin {
+                [vmin, vmax]
+            } else {
+                [vmin, vmin + 1.0]
+            },
+            lod_factor: 2.0,
+            skirt_depth: 0.05,
+            colormap_name: "terrain".to_string(),
+            wireframe_color: "white".to_string(),
+        })
+    }
+
+    fn tile_cells(&self) -> u32 {
+        self.tile_size.clamp(2, 250)
+    }
+
+    // world distance between neighboring grid points
+    pub fn cell_size(&self) -> f32 {
+        let [rows, cols] = self.source.size();
+        2.0 * self.scale / (rows.max(cols).max(2) - 1) as f32
+    }
+
+    // level of the single tile that covers the whole grid
+    pub fn root_level(&self) -> u32 {
+        let [rows, cols] = self.source.size();
+        let cells = rows.max(cols).max(2) - 1;
+        let mut level = 0;
+        while self.tile_cells() << level < cells {
+            level += 1;
+        }
+        level
+    }
+
+    pub fn root(&self) -> TileKey {
+        TileKey {
+            level: self.root_level(),
+            x: 0,
+            z: 0,
+        }
+    }
+
+    // world position of grid point (i, j) at height value v
+    fn world(&self, i: f32, j: f32, v: f32) -> [f32; 3] {
+        let [rows, cols] = self.source.size();
+        let h = self.cell_size();
+        let [vmin, vmax] = self.value_range;
+        let y = -1.0 + 2.0 * (v - vmin) / (vmax - vmin);
+        [
+            (i - 0.5 * (rows - 1) as f32) * h,
+            y.clamp(-1.0, 1.0) * self.scale * self.aspect_ratio,
+            (j - 0.5 * (cols - 1) as f32) * h,
+        ]
+    }
+
+    // first grid point, stride and cell counts [along x, along z] of a tile; None if
+    // the tile lies outside the grid
+    fn tile_extent(&self, key: &TileKey) -> Option<([u32; 2], u32, [u32; 2])> {
+        let [rows, cols] = self.source.size();
+        let stride = 1u32.checked_shl(key.level)?;
+        let span = self.tile_cells().checked_mul(stride)?;
+        let (i0, j0) = (key.x.checked_mul(span)?, key.z.checked_mul(span)?);
+        if i0 >= rows - 1 || j0 >= cols - 1 {
+            return None;
+        }
+        let cells = |start: u32, n: u32| ((n - 1 - start).div_ceil(stride)).min(self.tile_cells());
+        Some(([i0, j0], stride, [cells(i0, rows), cells(j0, cols)]))
+    }
+
+    // world space bounding box of a tile
+    pub fn tile_bounds(&self, key: &TileKey) -> Option<[[f32; 3]; 2]> {
+        let [rows, cols] = self.source.size();
+        let ([i0, j0], stride, [ni, nj]) = self.tile_extent(key)?;
+        let i1 = (i0 + ni * stride).min(rows - 1);
+        let j1 = (j0 + nj * stride).min(cols - 1);
+        let [vmin, vmax] = self.value_range;
+        let lo = self.world(i0 as f32, j0 as f32, vmin);
+        let hi = self.world(i1 as f32, j1 as f32, vmax);
+        // the skirts hang below the lowest sample
+        let skirt = self.skirt_depth * 2.0 * self.scale * self.aspect_ratio;
+        Some([[lo[0], lo[1] - skirt, lo[2]], hi])
+    }
+
+    // tiles to draw for the given eye position and view-projection matrix, coarse
+    // tiles first; they cover the visible part of the grid without overlapping
+    pub fn select(&self, eye: Point3<f32>, view_project_mat: &Matrix4<f32>) -> Vec<TileKey> {
+        let mut selected = vec![];
+        let mut stack = vec![self.root()];
+        while let Some(key) = stack.pop() {
+            let Some(bounds) = self.tile_bounds(&key) else {
+                continue;
+            };
+            if !in_frustum(view_project_mat, &bounds) {
+                continue;
+            }
+            let size = (self.tile_cells() << key.level) as f32 * self.cell_size();
+            if key.level > 0 && distance_to_box(eye, &bounds) < self.lod_factor * size {
+                for (dx, dz) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    stack.push(TileKey {
+                        level: key.level - 1,
+                        x: 2 * key.x + dx,
+                        z: 2 * key.z + dz,
+                    });
+                }
+            } else {
+                selected.push(key);
+            }
+        }
+        selected.sort_by(|a, b| b.cmp(a));
+        selected
+    }
+
+    // mesh of one tile in world coordinates, including its skirts; the wireframe
+    // indices leave the skirts out
+    pub fn tile_data(&self, key: &TileKey) -> std::io::Result<ISurfaceOutput> {
+        let Some(([i0, j0], stride, [ni, nj])) = self.tile_extent(key) else {
+            return Ok(ISurfaceOutput::default());
+        };
+        let [rows, cols] = self.source.size();
+        // one extra sample on each side gives the border normals of both neighbors
+        let (pr, pc) = (ni + 3, nj + 3);
+        let s = stride as i64;
+        let padded = self
+            .source
+            .read(i0 as i64 - s, j0 as i64 - s, pr, pc, stride)?;
+        let grid_i = |a: i64| (i0 as i64 + a * s).clamp(0, rows as i64 - 1) as f32;
+        let grid_j = |b: i64| (j0 as i64 + b * s).clamp(0, cols as i64 - 1) as f32;
+        let point = |a: i64, b: i64| {
+            let v = padded[((a + 1) * pc as i64 + b + 1) as usize];
+            Vector3::from(self.world(grid_i(a), grid_j(b), v))
+        };
+
+        let cdata = colormap::colormap_data(&self.colormap_name);
+        let cdata2 = colormap::colormap_data(&self.wireframe_color);
+        let range = self.scale * self.aspect_ratio;
+        let mut data = ISurfaceOutput::default();
+        for a in 0..=ni as i64 {
+            for b in 0..=nj as i64 {
+                let p = point(a, b);
+                let nx = point(a + 1, b) - point(a - 1, b);
+                let nz = point(a, b + 1) - point(a, b - 1);
+                data.positions.push(p.into());
+                data.normals.push(nz.cross(nx).normalize().into());
+                data.colors
+                    .push(colormap::color_lerp(cdata, -range, range, p.y));
+                data.colors2
+                    .push(colormap::color_lerp(cdata2, -range, range, p.y));
+                data.uvs
+                    .push([grid_i(a) / (rows - 1) as f32, grid_j(b) / (cols - 1) as f32]);
+            }
+        }
+        (data.indices, data.indices2) = grid_indices(ni as u16, nj as u16);
+
+        // skirts: every border vertex gets a copy moved down, joined by a strip
+        let vertex = |a: u32, b: u32| (a * (nj + 1) + b) as u16;
+        let edges: [Vec<u16>; 4] = [
+            (0..=nj).map(|b| vertex(0, b)).collect(),
+            (0..=nj).map(|b| vertex(ni, b)).collect(),
+            (0..=ni).map(|a| vertex(a, 0)).collect(),
+            (0..=ni).map(|a| vertex(a, nj)).collect(),
+        ];
+        let drop = self.skirt_depth * 2.0 * range;
+        for edge in edges.iter() {
+            let first = data.positions.len() as u16;
+            for &k in edge.iter() {
+                let [x, y, z] = data.positions[k as usize];
+                data.positions.push([x, y - drop, z]);
+                data.normals.push(data.normals[k as usize]);
+                data.colors.push(data.colors[k as usize]);
+                data.colors2.push(data.colors2[k as usize]);
+                data.uvs.push(data.uvs[k as usize]);
+            }
+            for (e, pair) in edge.windows(2).enumerate() {
+                let (s0, s1) = (first + e as u16, first + e as u16 + 1);
+                data.indices.extend([pair[0], pair[1], s1, s1, s0, pair[0]]);
+            }
+        }
+        Ok(data)
+    }
+}
+
+// true unless all corners of the box lie outside one of the clip planes
+fn in_frustum(view_project_mat: &Matrix4<f32>, bounds: &[[f32; 3]; 2]) -> bool {
+    let [lo, hi] = bounds;
+    let corners: Vec<Vector4<f32>> = (0..8)
+        .map(|c| {
+            let x = if c & 1 == 0 { lo[0] } else { hi[0] };
+            let y = if c & 2 == 0 { lo[1] } else { hi[1] };
+            let z = if c & 4 == 0 { lo[2] } else { hi[2] };
+            view_project_mat * Vector4::new(x, y, z, 1.0)
+        })
+        .collect();
+    let outside = |plane: fn(&Vector4<f32>) -> bool| corners.iter().all(plane);
+    !(outside(|p| p.x < -p.w)
+        || outside(|p| p.x > p.w)
+        || outside(|p| p.y < -p.w)
+        || outside(|p| p.y > p.w)
+        || outside(|p| p.z < 0.0)
+        || outside(|p| p.z > p.w))
+}
+
+fn distance_to_box(p: Point3<f32>, bounds: &[[f32; 3]; 2]) -> f32 {
+    let [lo, hi] = bounds;
+    let d = Vector3::new(
+        (lo[0] - p.x).max(p.x - hi[0]).max(0.0),
+        (lo[1] - p.y).max(p.y - hi[1]).max(0.0),
+        (lo[2] - p.z).max(p.z - hi[2]).max(0.0),
+    );
+    d.magnitude()
+}
+// endregion: tiled surface
+
+// region: tile cache
+// Resident tiles (typically their GPU buffers), evicted least recently used first.
+// While a selected tile is still waiting for its turn to load, its nearest resident
+// ancestor is drawn in its place.
+pub struct TileCache<T> {
+    pub capacity: usize,    // tiles kept resident, never fewer than the drawn ones
+    pub load_budget: usize, // tiles loaded per update, so a fast camera does not stall a frame
+    tiles: HashMap<TileKey, (T, u64)>,
+    frame: u64,
+}
+
+impl<T> TileCache<T> {
+    pub fn new(capacity: usize, load_budget: usize) -> Self {
+        Self {
+            capacity,
+            load_budget: load_budget.max(1),
+            tiles: HashMap::new(),
+            frame: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    pub fn contains(&self, key: &TileKey) -> bool {
+        self.tiles.contains_key(key)
+    }
+
+    pub fn get(&self, key: &TileKey) -> Option<&T> {
+        self.tiles.get(key).map(|(tile, _)| tile)
+    }
+
+    // loads missing selected tiles within the budget (coarse ones first, as select()
+    // orders them), evicts the least recently used tiles above capacity and returns
+    // the tiles to draw this frame
+    pub fn update<E>(
+        &mut self,
+        selected: &[TileKey],
+        root: TileKey,
+        mut load: impl FnMut(&TileKey) -> Result<T, E>,
+    ) -> Result<Vec<TileKey>, E> {
+        self.frame += 1;
+        // the root is the fallback of every tile
+        if !self.tiles.contains_key(&root) {
+            let tile = load(&root)?;
+            self.tiles.insert(root, (tile, self.frame));
+        }
+        let mut loads = 0;
+        for key in selected.iter() {
+            if loads < self.load_budget && !self.tiles.contains_key(key) {
+                let tile = load(key)?;
+                self.tiles.insert(*key, (tile, self.frame));
+                loads += 1;
+            }
+        }
+
+        // selected tiles, or their nearest resident ancestor
+        let mut drawn: HashSet<TileKey> = HashSet::new();
+        for key in selected.iter() {
+            let mut k = *key;
+            while !self.tiles.contains_key(&k) && k.level < root.level {
+                k = k.parent();
+            }
+            drawn.insert(if self.tiles.contains_key(&k) { k } else { root });
+        }
+        // an ancestor stand-in covers its descendants
+        let fallbacks: Vec<TileKey> = drawn.iter().copied().collect();
+        drawn.retain(|k| !fallbacks.iter().any(|f| f.is_ancestor_of(k)));
+
+        for key in drawn.iter().chain([&root]) {
+            if let Some(entry) = self.tiles.get_mut(key) {
+                entry.1 = self.frame;
+            }
+        }
+        if self.tiles.len() > self.capacity {
+            let mut by_age: Vec<(u64, TileKey)> = self
+                .tiles
+                .iter()
+                .map(|(k, (_, used))| (*used, *k))
+                .collect();
+            by_age.sort();
+            let excess = self.tiles.len() - self.capacity;
+            for (used, key) in by_age.into_iter().take(excess) {
+                if used < self.frame {
+                    self.tiles.remove(&key);
+                }
+            }
+        }
+
+        let mut drawn: Vec<TileKey> = drawn.into_iter().collect();
+        drawn.sort_by(|a, b| b.cmp(a));
+        Ok(drawn)
+    }
+}
+// endregion: tile cache
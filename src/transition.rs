@@ -0,0 +1,115 @@
+use super::animation::Easing;
+use super::wgpu_simplified::GrowableBuffer;
+
+// region: cross-fade
+// A cross-fade from the surface shown to the next one, e.g. when the surface type
+// changes: the outgoing mesh is copied into buffers of the cross-fade before the new
+// one is written over it, and for seconds both are drawn with the transparent
+// pipeline (ws::CONSTANT_ALPHA_BLENDING), the outgoing one fading out while the new
+// one fades in. The vertex and index buffers of the shown mesh need COPY_SRC usage:
+//
+//     fade.start(&device, &queue, &vertex_buffer, &index_buffer, index_count);
+//     vertex_buffer.write(&device, &queue, cast_slice(&new_vertices));
+//     ..
+//     fade.advance(dt);
+//     if fade.is_fading() {
+//         let [outgoing, incoming] = fade.opacities();
+//         render_pass.set_blend_constant(..outgoing..);
+//         fade.draw_outgoing(&mut render_pass, &transparent_pipeline, index_format);
+//         render_pass.set_blend_constant(..incoming..);
+//         // the new mesh with the transparent pipeline
+//     }
+pub struct CrossFade {
+    pub seconds: f32,
+    pub easing: Easing,
+    vertex_buffer: GrowableBuffer,
+    index_buffer: GrowableBuffer,
+    index_count: u32,
+    progress: Option<f32>, // 0 to 1 while fading, linear in time
+}
+
+impl CrossFade {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let buffer = |label, usage| GrowableBuffer::new(device, label, usage, &[]);
+        Self {
+            seconds: 0.5,
+            easing: Easing::EaseInOut,
+            vertex_buffer: buffer("Cross-fade Vertex Buffer", wgpu::BufferUsages::VERTEX),
+            index_buffer: buffer("Cross-fade Index Buffer", wgpu::BufferUsages::INDEX),
+            index_count: 0,
+            progress: None,
+        }
+    }
+
+    // keeps the mesh of the buffers as the outgoing one and starts fading it out; call
+    // it before the buffers get the new mesh. A fade in progress starts over from the
+    // mesh shown last.
+    pub fn start(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        vertex_buffer: &GrowableBuffer,
+        index_buffer: &GrowableBuffer,
+        index_count: u32,
+    ) {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Cross-fade Copy Encoder"),
+        });
+        self.vertex_buffer
+            .copy_from(device, &mut encoder, vertex_buffer);
+        self.index_buffer
+            .copy_from(device, &mut encoder, index_buffer);
+        // submitted now, so the copy runs before writes queued for the new mesh
+        queue.submit(std::iter::once(encoder.finish()));
+        self.index_count = index_count;
+        self.progress = Some(0.0);
+    }
+
+    pub fn stop(&mut self) {
+        self.progress = None;
+    }
+
+    // moves the fade on by dt seconds, and ends it once the new mesh is fully shown
+    pub fn advance(&mut self, dt: f32) {
+        if let Some(progress) = self.progress {
+            let step = if self.seconds > 0.0 {
+                dt / self.seconds
+            } else {
+                1.0
+            };
+            self.progress = Some(progress + step).filter(|&p| p < 1.0);
+        }
+    }
+
+    pub fn is_fading(&self) -> bool {
+        self.progress.is_some()
+    }
+
+    // eased opacities of the outgoing and the new mesh; [0, 1] when not fading
+    pub fn opacities(&self) -> [f32; 2] {
+        let t = self.progress.map_or(1.0, |p| self.easing.ease(p));
+        [1.0 - t, t]
+    }
+
+    // the outgoing mesh, with the caller's bind groups and a pipeline of its vertex
+    // layout that blends by the blend constant
+    pub fn draw_outgoing(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        pipeline: &wgpu::RenderPipeline,
+        index_format: wgpu::IndexFormat,
+    ) {
+        if !self.is_fading() || self.index_count == 0 {
+            return;
+        }
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice());
+        render_pass.set_index_buffer(self.index_buffer.slice(), index_format);
+        render_pass.draw_indexed(0..self.index_count, 0, 0..1);
+    }
+
+    pub fn outgoing_buffers(&self) -> (&GrowableBuffer, &GrowableBuffer, u32) {
+        (&self.vertex_buffer, &self.index_buffer, self.index_count)
+    }
+}
+// endregion: cross-fade
@@ -0,0 +1,208 @@
+use bytemuck::{Pod, Zeroable};
+use cgmath::Vector3;
+
+use super::animation::Easing;
+use super::color;
+use super::surface_data::{IParametricSurface, ISurfaceOutput};
+
+// region: flattening
+// The parameter rectangle of a parametric surface as a second set of positions, so
+// the surface can be unfolded onto it: each vertex goes to its (u, v) in the xy
+// plane, u along x and v along y, with the aspect ratio of the domain and the longer
+// side as long as the surface is wide (scale). The (u, v) are taken from the uvs, so
+// a mesh with closed seams has its seam triangles stretched across the rectangle.
+impl IParametricSurface {
+    pub fn flat_positions(&self, data: &ISurfaceOutput) -> Vec<[f32; 3]> {
+        let (w, h) = (self.umax - self.umin, self.vmax - self.vmin);
+        let side = w.abs().max(h.abs()).max(f32::EPSILON);
+        let (sx, sy) = (self.scale * w / side, self.scale * h / side);
+        let lens = self.uv_lens.map(|l| if l != 0.0 { l } else { 1.0 });
+        data.uvs
+            .iter()
+            .map(|uv| {
+                [
+                    sx * (uv[0] / lens[0] - 0.5),
+                    sy * (uv[1] / lens[1] - 0.5),
+                    0.0,
+                ]
+            })
+            .collect()
+    }
+}
+
+// normal of the flattened mesh: +z or -z, whichever its triangles wind around, so the
+// lighting of the rectangle matches the side of the surface the normals were on
+pub fn flat_normal(data: &ISurfaceOutput, flat: &[[f32; 3]]) -> [f32; 3] {
+    let area = data.indices.chunks_exact(3).fold(0.0, |area, tri| {
+        let [a, b, c] = [0, 1, 2].map(|k| Vector3::from(flat[tri[k] as usize]));
+        area + (b - a).cross(c - a).z
+    });
+    if area < 0.0 {
+        [0.0, 0.0, -1.0]
+    } else {
+        [0.0, 0.0, 1.0]
+    }
+}
+// endregion: flattening
+
+// region: unfold vertices
+// The chapter vertex (position, normal, color at locations 0 to 2) with the flattened
+// position and normal at locations 3 and 4; UNFOLD_VERT_WGSL blends between the two.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct UnfoldVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub color: [f32; 3],
+    pub flat_position: [f32; 3],
+    pub flat_normal: [f32; 3],
+}
+
+impl UnfoldVertex {
+    pub const ATTRIBUTES: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+        0 => Float32x3, 1 => Float32x3, 2 => Float32x3, 3 => Float32x3, 4 => Float32x3
+    ];
+
+    pub fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+// vertices of both endpoints of the unfolding, with the surface colors or, for the
+// wireframe, the wireframe colors; the indices stay those of the mesh
+pub fn unfold_vertices(
+    data: &ISurfaceOutput,
+    flat: &[[f32; 3]],
+    wireframe: bool,
+) -> Vec<UnfoldVertex> {
+    let flat_normal = flat_normal(data, flat);
+    let colors = if wireframe {
+        &data.colors2
+    } else {
+        &data.colors
+    };
+    (0..data.positions.len())
+        .map(|i| UnfoldVertex {
+            position: data.positions[i],
+            normal: data.normals[i],
+            color: color::to_linear(colors[i]),
+            flat_position: flat[i],
+            flat_normal,
+        })
+        .collect()
+}
+// endregion: unfold vertices
+
+// region: unfold animation
+// Progress of the unfolding, toggled between the surface (0) and the parameter
+// rectangle (1) and eased over seconds; blend() is what the shader gets.
+pub const UNFOLD_UNIFORM_SIZE: u64 = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Unfold {
+    pub progress: f32, // 0 to 1, linear in time
+    pub target: f32,   // 0 or 1
+    pub seconds: f32,
+    pub easing: Easing,
+}
+
+impl Default for Unfold {
+    fn default() -> Self {
+        Self {
+            progress: 0.0,
+            target: 0.0,
+            seconds: 1.5,
+            easing: Easing::EaseInOut,
+        }
+    }
+}
+
+impl Unfold {
+    // turns around towards the other end, from where it is
+    pub fn toggle(&mut self) {
+        self.target = 1.0 - self.target;
+    }
+
+    // moves towards the target; returns false once there
+    pub fn advance(&mut self, dt: f32) -> bool {
+        if self.progress == self.target {
+            return false;
+        }
+        let step = if self.seconds > 0.0 {
+            dt / self.seconds
+        } else {
+            1.0
+        };
+        self.progress = if self.target > self.progress {
+            (self.progress + step).min(self.target)
+        } else {
+            (self.progress - step).max(self.target)
+        };
+        true
+    }
+
+    pub fn blend(&self) -> f32 {
+        self.easing.ease(self.progress)
+    }
+
+    // neither folded nor on its way back, so the plain surface pipelines do
+    pub fn is_folded(&self) -> bool {
+        self.progress == 0.0 && self.target == 0.0
+    }
+
+    pub fn uniform_data(&self) -> [f32; 4] {
+        [self.blend(), 0.0, 0.0, 0.0]
+    }
+}
+// endregion: unfold animation
+
+// region: shaders
+// Vertex shader of the unfolding, with the chapter vertex uniforms at group 0 binding
+// 0 and the Unfold uniform at binding 1; position and normal are blended before the
+// vertex hook, so the chapter fragment shaders work unchanged.
+pub const UNFOLD_VERT_WGSL: &str = r#"
+struct Uniforms {
+    vpMat : mat4x4f,
+    modelMat : mat4x4f,
+    normalMat : mat4x4f,
+};
+@binding(0) @group(0) var<uniform> uniforms : Uniforms;
+@binding(1) @group(0) var<uniform> unfold : vec4f; // x: 0 surface, 1 parameter rectangle
+
+struct Input {
+    @location(0) position: vec4f,
+    @location(1) normal: vec4f,
+    @location(2) color: vec4f,
+    @location(3) flatPosition: vec4f,
+    @location(4) flatNormal: vec4f,
+}
+
+struct Output {
+    @builtin(position) position : vec4f,
+    @location(0) vPosition : vec4f,
+    @location(1) vNormal : vec4f,
+    @location(2) vColor: vec4f,
+};
+
+// @hook vertex_declarations
+
+@vertex
+fn vs_main(in:Input) -> Output {
+    var output: Output;
+    var position = mix(in.position, in.flatPosition, unfold.x);
+    var normal = mix(in.normal, in.flatNormal, unfold.x);
+    var color = in.color;
+    // @hook vertex
+    let mPosition = uniforms.modelMat * position;
+    output.vPosition = mPosition;
+    output.vNormal = uniforms.normalMat * normal;
+    output.position = uniforms.vpMat * mPosition;
+    output.vColor = color;
+    return output;
+}
+"#;
+// endregion: shaders
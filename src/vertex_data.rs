@@ -7,6 +7,7 @@ pub fn torus_position(r_torus: f32, r_tube: f32, u: Deg<f32>, v: Deg<f32>) -> [f
     [x, y, z]
 }
 
+#[allow(clippy::type_complexity)]
 pub fn create_torus_data(
     r_torus: f32,
     r_tube: f32,
@@ -122,6 +123,7 @@ fn sphere_position(r: f32, theta: Deg<f32>, phi: Deg<f32>) -> [f32; 3] {
     [x, y, z]
 }
 
+#[allow(clippy::type_complexity)]
 pub fn create_sphere_data(
     r: f32,
     u: u16,
@@ -153,10 +155,10 @@ pub fn create_sphere_data(
 
     for i in 0..u {
         for j in 0..v {
-            let idx0 = j + i * (v as u16 + 1);
-            let idx1 = j + 1 + i * (v as u16 + 1);
-            let idx2 = j + 1 + (i + 1) * (v as u16 + 1);
-            let idx3 = j + (i + 1) * (v as u16 + 1);
+            let idx0 = j + i * (v + 1);
+            let idx1 = j + 1 + i * (v + 1);
+            let idx2 = j + 1 + (i + 1) * (v + 1);
+            let idx3 = j + (i + 1) * (v + 1);
 
             let values: Vec<u16> = vec![idx0, idx1, idx2, idx2, idx3, idx0];
             indices.extend(values);
@@ -169,6 +171,7 @@ pub fn create_sphere_data(
     (positions, normals, uvs, indices, indices2)
 }
 
+#[allow(clippy::type_complexity)]
 pub fn create_cube_data(
     side: f32,
 ) -> (
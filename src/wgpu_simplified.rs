@@ -1,11 +1,31 @@
-use cgmath::{ortho, perspective, Matrix4, Point3, Rad, Vector3};
+use cgmath::{ortho, perspective, InnerSpace, Matrix4, Point3, Rad, Vector3};
 use std::collections::VecDeque; // HashMap
 use std::f32::consts::PI;
+use std::ops::Range;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::Duration;
+use web_time::Instant;
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
 use winit::window::Window;
 
+use super::capabilities::{Capabilities, Subsystem};
+use super::capture::FrameCapture;
+use super::color;
+use super::error::{self, Error, Result};
+
 // region: wgpu initialization
+// the default limits, or the WebGL2 ones for adapters that fall short of them (WebGL2
+// in the browser, older GL drivers), raised to the adapter's texture size so large
+// windows still fit
+pub fn required_limits(adapter_limits: &wgpu::Limits) -> wgpu::Limits {
+    let limits = wgpu::Limits::default();
+    if limits.check_limits(adapter_limits) {
+        limits
+    } else {
+        wgpu::Limits::downlevel_webgl2_defaults().using_resolution(adapter_limits.clone())
+    }
+}
+
 pub struct InitWgpu {
     pub surface: wgpu::Surface<'static>,
     pub adapter: wgpu::Adapter,
@@ -13,20 +33,94 @@ pub struct InitWgpu {
     pub queue: wgpu::Queue,
     pub config: wgpu::SurfaceConfiguration,
     pub size: winit::dpi::PhysicalSize<u32>,
-    pub sample_count: u32,
+    pub sample_count: u32, // the requested count clamped to what the device supports
     pub window: Arc<Window>,
+    pub present_mode: PresentModePreference, // config.present_mode is what it resolved to
+    pub capabilities: Capabilities,          // the optional features the device got
+    requested_sample_count: u32,
+    supported_sample_counts: Vec<u32>,
+    supported_present_modes: Vec<wgpu::PresentMode>,
 }
 
-impl InitWgpu {
-    pub async fn init_wgpu(window: Arc<Window>, sample_count: u32) -> Self {
+// What InitWgpu::refresh_surface() changed, as [old, new] pairs
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SurfaceChange {
+    pub color_format: Option<[wgpu::TextureFormat; 2]>,
+    pub alpha_mode: Option<[wgpu::CompositeAlphaMode; 2]>,
+    pub sample_count: Option<[u32; 2]>,
+}
 
-        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+impl SurfaceChange {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    // pipelines are created for a color format and sample count
+    pub fn needs_new_pipelines(&self) -> bool {
+        self.color_format.is_some() || self.sample_count.is_some()
+    }
+}
+
+// Configuration of a surface with the capabilities. Without a current configuration:
+// an sRGB format, or an sRGB view of the surface, so the output is encoded the same on
+// every platform (see color.rs), and the first alpha mode. With one, its format and
+// alpha mode stay while the capabilities still offer them, so pipelines created for
+// it remain valid.
+pub fn surface_config(
+    caps: &wgpu::SurfaceCapabilities,
+    current: Option<&wgpu::SurfaceConfiguration>,
+    [width, height]: [u32; 2],
+    present_mode: PresentModePreference,
+) -> wgpu::SurfaceConfiguration {
+    let (format, view_format) = match current {
+        Some(config) if caps.formats.contains(&config.format) => {
+            let view_format = config.view_formats.first().copied().unwrap_or(config.format);
+            (config.format, view_format)
+        }
+        _ => color::surface_formats(&caps.formats),
+    };
+    let alpha_mode = match current {
+        Some(config) if caps.alpha_modes.contains(&config.alpha_mode) => config.alpha_mode,
+        _ => caps.alpha_modes[0],
+    };
+
+    // allow reading back frames (screenshots, recording) when the surface supports it
+    let mut usage = wgpu::TextureUsages::RENDER_ATTACHMENT;
+    if caps.usages.contains(wgpu::TextureUsages::COPY_SRC) {
+        usage |= wgpu::TextureUsages::COPY_SRC;
+    }
+
+    // Defines how a Surface creates a SurfaceTexture.
+    wgpu::SurfaceConfiguration {
+        usage,
+        format,
+        width,
+        height,
+        present_mode: present_mode.resolve(&caps.present_modes),
+        alpha_mode,
+        desired_maximum_frame_latency: 2,
+        view_formats: if view_format != format { vec![view_format] } else { vec![] },
+    }
+}
+
+impl InitWgpu {
+    pub async fn init_wgpu(
+        window: Arc<Window>,
+        sample_count: u32,
+        present_mode: PresentModePreference,
+    ) -> Result<Self> {
+        let instance_descriptor = wgpu::InstanceDescriptor {
             backends: wgpu::Backends::all(),
             ..Default::default()
-        });
+        };
+        // browsers without WebGPU get the WebGL2 backend
+        #[cfg(target_arch = "wasm32")]
+        let instance = wgpu::util::new_instance_with_webgpu_detection(&instance_descriptor).await;
+        #[cfg(not(target_arch = "wasm32"))]
+        let instance = wgpu::Instance::new(&instance_descriptor);
 
         // Surface
-        let surface = instance.create_surface(window.clone()).unwrap();
+        let surface = instance.create_surface(window.clone())?;
 
         // Adapter:
         let adapter = instance
@@ -34,44 +128,44 @@ impl InitWgpu {
                 power_preference: wgpu::PowerPreference::default(),
                 compatible_surface: Some(&surface),
                 force_fallback_adapter: false,
-                ..Default::default()
             })
-            .await
-            .unwrap();
+            .await?;
 
-        // Logical Device and Queue
+        // Logical Device and Queue, with the optional features the adapter offers
+        let capabilities = Capabilities::of_adapter(&Subsystem::ALL, &adapter);
+        capabilities.log();
+        let adapter_limits = adapter.limits();
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: None,
-                    required_features: wgpu::Features::empty(),
-                    required_limits: wgpu::Limits::default(),
+                    required_features: capabilities.granted,
+                    required_limits: capabilities
+                        .limits(required_limits(&adapter_limits), &adapter_limits),
                     ..Default::default()
                 },
             )
-            .await
-            .unwrap();
+            .await?;
 
+        // a canvas has no size until the page lays it out
         let size = window.inner_size();
+        let size = winit::dpi::PhysicalSize::new(size.width.max(1), size.height.max(1));
 
         let surface_caps = surface.get_capabilities(&adapter);
-        let format = surface_caps.formats[0];
-
-        // Defines how a Surface creates a SurfaceTexture.
-        let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format,
-            width: size.width,
-            height: size.height,
-            present_mode: wgpu::PresentMode::Fifo,
-            alpha_mode: surface_caps.alpha_modes[0],
-            desired_maximum_frame_latency: 2,
-            view_formats: vec![],
-        };
-
+        let config = surface_config(&surface_caps, None, [size.width, size.height], present_mode);
         surface.configure(&device, &config);
 
-        Self {
+        let view_format = config
+            .view_formats
+            .first()
+            .copied()
+            .unwrap_or(config.format);
+        let formats = [view_format, wgpu::TextureFormat::Depth24Plus];
+        let supported_sample_counts = supported_sample_counts(&adapter, &device, &formats);
+        let requested_sample_count = sample_count;
+        let sample_count = clamp_sample_count(&supported_sample_counts, sample_count);
+
+        Ok(Self {
             surface,
             adapter,
             device,
@@ -79,9 +173,332 @@ impl InitWgpu {
             config,
             size,
             sample_count,
-            window: window,
+            window,
+            present_mode,
+            capabilities,
+            requested_sample_count,
+            supported_sample_counts,
+            supported_present_modes: surface_caps.present_modes,
+        })
+    }
+
+    // Reads the surface capabilities again and reconfigures the surface for them,
+    // e.g. after the window moved to a monitor with other formats or alpha modes, and
+    // returns what changed. The format and alpha mode are kept while the surface still
+    // offers them, so usually nothing does; a new color_format() or sample_count needs
+    // new pipelines, and any change new MSAA and depth views. A surface without
+    // formats (lost, or the window minimized) is left alone.
+    pub fn refresh_surface(&mut self) -> SurfaceChange {
+        let caps = self.surface.get_capabilities(&self.adapter);
+        if caps.formats.is_empty() || caps.alpha_modes.is_empty() {
+            return SurfaceChange::default();
+        }
+        let (old_format, old_alpha_mode) = (self.color_format(), self.config.alpha_mode);
+        let old_sample_count = self.sample_count;
+        let size = [self.config.width, self.config.height];
+        self.config = surface_config(&caps, Some(&self.config), size, self.present_mode);
+        self.supported_present_modes = caps.present_modes;
+
+        let formats = [self.color_format(), wgpu::TextureFormat::Depth24Plus];
+        self.supported_sample_counts =
+            supported_sample_counts(&self.adapter, &self.device, &formats);
+        self.sample_count =
+            clamp_sample_count(&self.supported_sample_counts, self.requested_sample_count);
+        self.surface.configure(&self.device, &self.config);
+
+        SurfaceChange {
+            color_format: (old_format != self.color_format())
+                .then_some([old_format, self.color_format()]),
+            alpha_mode: (old_alpha_mode != self.config.alpha_mode)
+                .then_some([old_alpha_mode, self.config.alpha_mode]),
+            sample_count: (old_sample_count != self.sample_count)
+                .then_some([old_sample_count, self.sample_count]),
+        }
+    }
+
+    // reconfigures the surface for another present mode, e.g. to measure uncapped
+    // frame rates; returns the mode the preference resolved to on this surface
+    pub fn set_present_mode(&mut self, present_mode: PresentModePreference) -> wgpu::PresentMode {
+        self.present_mode = present_mode;
+        self.config.present_mode = present_mode.resolve(&self.supported_present_modes);
+        self.surface.configure(&self.device, &self.config);
+        self.config.present_mode
+    }
+
+    // the largest sample count the surface and depth formats support on the device
+    pub fn max_sample_count(&self) -> u32 {
+        self.supported_sample_counts.last().copied().unwrap_or(1)
+    }
+
+    // view of a surface texture in color_format(), the format pipelines render to
+    pub fn surface_view(&self, texture: &wgpu::Texture) -> wgpu::TextureView {
+        texture.create_view(&wgpu::TextureViewDescriptor {
+            format: Some(self.color_format()),
+            ..Default::default()
+        })
+    }
+}
+
+// How frames are presented. Fifo waits for vertical sync and is the only mode every
+// surface supports; Immediate presents at once and may tear; Mailbox replaces the
+// queued frame without tearing; AutoNoVsync takes whichever uncapped mode there is.
+// A mode the surface does not support falls back along resolve():
+//
+//     Immediate    Immediate, Mailbox, Fifo
+//     Mailbox      Mailbox, Fifo
+//     AutoNoVsync  Mailbox, Immediate, Fifo
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum PresentModePreference {
+    Immediate,
+    Mailbox,
+    #[default]
+    Fifo,
+    AutoNoVsync,
+}
+
+impl PresentModePreference {
+    pub const ALL: [Self; 4] = [
+        Self::Immediate,
+        Self::Mailbox,
+        Self::Fifo,
+        Self::AutoNoVsync,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Immediate => "immediate",
+            Self::Mailbox => "mailbox",
+            Self::Fifo => "fifo",
+            Self::AutoNoVsync => "auto_no_vsync",
         }
     }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn next(&self) -> Self {
+        Self::ALL[(*self as usize + 1) % Self::ALL.len()]
+    }
+
+    // the first mode of the fallback order the surface supports, Fifo if none is
+    pub fn resolve(&self, supported: &[wgpu::PresentMode]) -> wgpu::PresentMode {
+        use wgpu::PresentMode as P;
+        let order: &[P] = match self {
+            Self::Immediate => &[P::Immediate, P::Mailbox],
+            Self::Mailbox => &[P::Mailbox],
+            Self::Fifo => &[],
+            Self::AutoNoVsync => &[P::Mailbox, P::Immediate],
+        };
+        order
+            .iter()
+            .copied()
+            .find(|mode| supported.contains(mode))
+            .unwrap_or(P::Fifo)
+    }
+}
+
+// Offscreen counterpart of InitWgpu for rendering without a window, e.g. on CI or
+// for batch figure generation: the device is created without a surface and frames
+// are rendered into `view`, an RGBA texture that read_rgba() copies back. Falls
+// back to a software adapter when no GPU is available.
+pub struct InitWgpuHeadless {
+    pub adapter: wgpu::Adapter,
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+    pub format: wgpu::TextureFormat,
+    pub width: u32,
+    pub height: u32,
+    pub sample_count: u32,
+    pub capabilities: Capabilities,
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+}
+
+impl InitWgpuHeadless {
+    pub async fn init_wgpu(width: u32, height: u32, sample_count: u32) -> Result<Self> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            ..Default::default()
+        });
+
+        let mut options = wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            compatible_surface: None,
+            force_fallback_adapter: false,
+        };
+        let adapter = match instance.request_adapter(&options).await {
+            Ok(adapter) => adapter,
+            Err(_) => {
+                options.force_fallback_adapter = true;
+                instance.request_adapter(&options).await?
+            }
+        };
+
+        let capabilities = Capabilities::of_adapter(&Subsystem::ALL, &adapter);
+        capabilities.log();
+        let adapter_limits = adapter.limits();
+        let limits = wgpu::Limits::downlevel_defaults().using_resolution(adapter_limits.clone());
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                label: Some("Headless Device"),
+                required_features: capabilities.granted,
+                required_limits: capabilities.limits(limits, &adapter_limits),
+                ..Default::default()
+            })
+            .await?;
+
+        let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+        let formats = [format, wgpu::TextureFormat::Depth24Plus];
+        let supported = supported_sample_counts(&adapter, &device, &formats);
+        let sample_count = clamp_sample_count(&supported, sample_count);
+        let (width, height) = (width.max(1), height.max(1));
+        let texture = Self::create_target(&device, format, width, height);
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Ok(Self {
+            adapter,
+            device,
+            queue,
+            format,
+            width,
+            height,
+            sample_count,
+            capabilities,
+            texture,
+            view,
+        })
+    }
+
+    fn create_target(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Headless Target Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        })
+    }
+
+    // recreates the target texture; depth and MSAA views have to be recreated as well
+    pub fn resize(&mut self, width: u32, height: u32) {
+        (self.width, self.height) = (width.max(1), height.max(1));
+        self.texture = Self::create_target(&self.device, self.format, self.width, self.height);
+        self.view = self.texture.create_view(&wgpu::TextureViewDescriptor::default());
+    }
+
+    // copies the target texture back and returns tightly packed RGBA8 pixels; call
+    // this after the frame has been submitted
+    pub fn read_rgba(&self) -> anyhow::Result<Vec<u8>> {
+        let capture = FrameCapture::new(&self.device, self.width, self.height, self.format);
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Headless Readback Encoder"),
+            });
+        capture.copy_from_texture(&mut encoder, &self.texture);
+        self.queue.submit(std::iter::once(encoder.finish()));
+        capture.read_rgba(&self.device)
+    }
+}
+
+// Without this feature a device only allows sample counts 1 and 4, whatever the adapter
+// supports; with it, the counts supported_sample_counts() finds for the formats. The
+// capabilities request it for Subsystem::AdapterSampleCounts.
+pub const SAMPLE_COUNT_FEATURES: wgpu::Features =
+    wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES;
+
+// the sample counts render targets of all the formats can use on the device, ascending
+pub fn supported_sample_counts(
+    adapter: &wgpu::Adapter,
+    device: &wgpu::Device,
+    formats: &[wgpu::TextureFormat],
+) -> Vec<u32> {
+    let adapter_specific = device.features().contains(SAMPLE_COUNT_FEATURES);
+    [1, 2, 4, 8, 16]
+        .into_iter()
+        .filter(|&count| {
+            formats.iter().all(|&format| {
+                let flags = if adapter_specific {
+                    adapter.get_texture_format_features(format).flags
+                } else {
+                    format.guaranteed_format_features(device.features()).flags
+                };
+                count == 1 || flags.sample_count_supported(count)
+            })
+        })
+        .collect()
+}
+
+// the largest supported count not above the requested one, e.g. 8 becomes 4 where the
+// adapter only allows 4; a request for 0 gives 1
+pub fn clamp_sample_count(supported: &[u32], requested: u32) -> u32 {
+    supported
+        .iter()
+        .copied()
+        .filter(|&count| count <= requested.max(1))
+        .max()
+        .unwrap_or(1)
+}
+
+// What the pipeline and attachment helpers below need from a wgpu setup, so they
+// work with a window (InitWgpu) and offscreen (InitWgpuHeadless) alike.
+pub trait RenderTarget {
+    fn device(&self) -> &wgpu::Device;
+    fn color_format(&self) -> wgpu::TextureFormat;
+    fn target_size(&self) -> [u32; 2];
+    fn sample_count(&self) -> u32;
+}
+
+impl RenderTarget for InitWgpu {
+    fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    fn color_format(&self) -> wgpu::TextureFormat {
+        self.config.view_formats.first().copied().unwrap_or(self.config.format)
+    }
+
+    fn target_size(&self) -> [u32; 2] {
+        [self.config.width, self.config.height]
+    }
+
+    fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+}
+
+impl RenderTarget for InitWgpuHeadless {
+    fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    fn color_format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    fn target_size(&self) -> [u32; 2] {
+        [self.width, self.height]
+    }
+
+    fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
 }
 // endregion: wgpu initialization
 
@@ -96,6 +513,8 @@ pub struct IRenderPipeline<'a> {
     pub strip_index_format: Option<wgpu::IndexFormat>,
     pub cull_mode: Option<wgpu::Face>,
     pub is_depth_stencil: bool,
+    pub depth_write_enabled: bool, // false for transparent geometry
+    pub blend: Option<wgpu::BlendState>,
     pub vs_entry: String,
     pub fs_entry: String,
 }
@@ -112,6 +531,8 @@ impl Default for IRenderPipeline<'_> {
             strip_index_format: None,
             cull_mode: None,
             is_depth_stencil: true,
+            depth_write_enabled: true,
+            blend: None,
             vs_entry: String::from("vs_main"),
             fs_entry: String::from("fs_main"),
         }
@@ -119,37 +540,54 @@ impl Default for IRenderPipeline<'_> {
 }
 
 impl IRenderPipeline<'_> {
-    pub fn new(&mut self, init: &InitWgpu) -> wgpu::RenderPipeline {
+    // fails without a pipeline layout or shaders, or when wgpu rejects the pipeline,
+    // e.g. for a vertex layout the shader does not match
+    #[allow(clippy::new_ret_no_self, clippy::wrong_self_convention)]
+    pub fn new(&mut self, init: &impl RenderTarget) -> Result<wgpu::RenderPipeline> {
         if self.shader.is_some() {
             self.vs_shader = self.shader;
             self.fs_shader = self.shader;
         }
+        let missing = |missing| Error::IncompletePipeline {
+            label: format!("{}/{}", self.vs_entry, self.fs_entry),
+            missing,
+        };
+        let layout = self
+            .pipeline_layout
+            .ok_or_else(|| missing("pipeline layout"))?;
+        let vs_shader = self.vs_shader.ok_or_else(|| missing("vertex shader"))?;
+        let fs_shader = self.fs_shader.ok_or_else(|| missing("fragment shader"))?;
 
         let mut depth_stencil: Option<wgpu::DepthStencilState> = None;
         if self.is_depth_stencil {
             depth_stencil = Some(wgpu::DepthStencilState {
                 format: wgpu::TextureFormat::Depth24Plus,
-                depth_write_enabled: true,
+                depth_write_enabled: self.depth_write_enabled,
                 depth_compare: wgpu::CompareFunction::LessEqual,
                 stencil: wgpu::StencilState::default(),
                 bias: wgpu::DepthBiasState::default(),
             });
         }
 
-        init.device
-            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        let device = init.device();
+        error::validated(device, "render pipeline", || {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
                 label: Some("Render Pipeline"),
-                layout: Some(&self.pipeline_layout.unwrap()),
+                layout: Some(layout),
                 vertex: wgpu::VertexState {
-                    module: &self.vs_shader.as_ref().unwrap(),
+                    module: vs_shader,
                     entry_point: Some(&self.vs_entry),
-                    buffers: &self.vertex_buffer_layout,
+                    buffers: self.vertex_buffer_layout,
                     compilation_options: wgpu::PipelineCompilationOptions::default(),
                 },
                 fragment: Some(wgpu::FragmentState {
-                    module: &self.fs_shader.as_ref().unwrap(),
+                    module: fs_shader,
                     entry_point: Some(&self.fs_entry),
-                    targets: &[Some(init.config.format.into())],
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: init.color_format(),
+                        blend: self.blend,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
                     compilation_options: wgpu::PipelineCompilationOptions::default(),
                 }),
                 primitive: wgpu::PrimitiveState {
@@ -159,14 +597,26 @@ impl IRenderPipeline<'_> {
                 },
                 depth_stencil,
                 multisample: wgpu::MultisampleState {
-                    count: init.sample_count,
+                    count: init.sample_count(),
                     ..Default::default()
                 },
                 multiview: None,
                 cache: None,
             })
+        })
     }
 }
+// blends the fragment color with the frame using the render pass blend constant as
+// opacity, so transparency needs no shader change: call
+// render_pass.set_blend_constant(wgpu::Color { r: a, g: a, b: a, a }) before drawing
+pub const CONSTANT_ALPHA_BLENDING: wgpu::BlendState = wgpu::BlendState {
+    color: wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::Constant,
+        dst_factor: wgpu::BlendFactor::OneMinusConstant,
+        operation: wgpu::BlendOperation::Add,
+    },
+    alpha: wgpu::BlendComponent::OVER,
+};
 // endregion: pipelines
 
 // region: views and attachments
@@ -184,17 +634,18 @@ pub fn create_color_attachment<'a>(
     }
 }
 
-pub fn create_msaa_texture_view(init: &InitWgpu) -> wgpu::TextureView {
-    let msaa_texture = init.device.create_texture(&wgpu::TextureDescriptor {
+pub fn create_msaa_texture_view(init: &impl RenderTarget) -> wgpu::TextureView {
+    let [width, height] = init.target_size();
+    let msaa_texture = init.device().create_texture(&wgpu::TextureDescriptor {
         size: wgpu::Extent3d {
-            width: init.config.width,
-            height: init.config.height,
+            width,
+            height,
             depth_or_array_layers: 1,
         },
         mip_level_count: 1,
-        sample_count: init.sample_count,
+        sample_count: init.sample_count(),
         dimension: wgpu::TextureDimension::D2,
-        format: init.config.format,
+        format: init.color_format(),
         usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
         label: None,
         view_formats: &[],
@@ -220,19 +671,32 @@ pub fn create_msaa_color_attachment<'a>(
     }
 }
 
-pub fn create_depth_view(init: &InitWgpu) -> wgpu::TextureView {
-    let depth_texture = init.device.create_texture(&wgpu::TextureDescriptor {
+pub fn create_depth_view(init: &impl RenderTarget) -> wgpu::TextureView {
+    create_depth_texture_view(init, false)
+}
+
+// creates the depth texture; with bindable = true it can also be sampled in shaders
+// (soft particles, depth-aware overlays), which requires storing the depth pass result
+// with create_depth_stencil_attachment_store
+pub fn create_depth_texture_view(init: &impl RenderTarget, bindable: bool) -> wgpu::TextureView {
+    let mut usage = wgpu::TextureUsages::RENDER_ATTACHMENT;
+    if bindable {
+        usage |= wgpu::TextureUsages::TEXTURE_BINDING;
+    }
+
+    let [width, height] = init.target_size();
+    let depth_texture = init.device().create_texture(&wgpu::TextureDescriptor {
         size: wgpu::Extent3d {
-            width: init.config.width,
-            height: init.config.height,
+            width,
+            height,
             depth_or_array_layers: 1,
         },
         mip_level_count: 1,
-        sample_count: init.sample_count,
+        sample_count: init.sample_count(),
         dimension: wgpu::TextureDimension::D2,
         format: wgpu::TextureFormat::Depth24Plus,
-        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-        label: None,
+        usage,
+        label: Some("Depth Texture"),
         view_formats: &[],
     });
 
@@ -241,111 +705,853 @@ pub fn create_depth_view(init: &InitWgpu) -> wgpu::TextureView {
 
 pub fn create_depth_stencil_attachment<'a>(
     depth_view: &'a wgpu::TextureView,
+) -> wgpu::RenderPassDepthStencilAttachment<'a> {
+    create_depth_stencil_attachment_store(depth_view, wgpu::StoreOp::Discard)
+}
+
+pub fn create_depth_stencil_attachment_store<'a>(
+    depth_view: &'a wgpu::TextureView,
+    store: wgpu::StoreOp,
 ) -> wgpu::RenderPassDepthStencilAttachment<'a> {
     wgpu::RenderPassDepthStencilAttachment {
         view: depth_view,
         depth_ops: Some(wgpu::Operations {
             load: wgpu::LoadOp::Clear(1.0),
-            store: wgpu::StoreOp::Discard,
+            store,
         }),
         stencil_ops: None,
     }
 }
 
-pub fn create_shadow_texture_view(init: &InitWgpu, width: u32, height: u32) -> wgpu::TextureView {
-    let shadow_depth_texture = init.device.create_texture(&wgpu::TextureDescriptor {
+// compare = Some(..) gives a comparison sampler (textureSampleCompare, shadow-style tests),
+// None a non-filtering sampler for reading raw depth values
+pub fn create_depth_sampler(
+    device: &wgpu::Device,
+    compare: Option<wgpu::CompareFunction>,
+) -> wgpu::Sampler {
+    let filter = if compare.is_some() {
+        wgpu::FilterMode::Linear
+    } else {
+        wgpu::FilterMode::Nearest
+    };
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("Depth Sampler"),
+        address_mode_u: wgpu::AddressMode::ClampToEdge,
+        address_mode_v: wgpu::AddressMode::ClampToEdge,
+        address_mode_w: wgpu::AddressMode::ClampToEdge,
+        mag_filter: filter,
+        min_filter: filter,
+        mipmap_filter: wgpu::FilterMode::Nearest,
+        compare,
+        ..Default::default()
+    })
+}
+
+// binding 0: depth texture (texture_depth_2d or texture_depth_multisampled_2d),
+// binding 1: the depth sampler (single-sampled textures only)
+pub fn create_depth_bind_group(
+    device: &wgpu::Device,
+    shader_stages: wgpu::ShaderStages,
+    depth_view: &wgpu::TextureView,
+    sampler: &wgpu::Sampler,
+    sample_count: u32,
+    comparison: bool,
+) -> (wgpu::BindGroupLayout, wgpu::BindGroup) {
+    let multisampled = sample_count > 1;
+    let mut layout_entries = vec![wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: shader_stages,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Depth,
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled,
+        },
+        count: None,
+    }];
+    let mut entries = vec![wgpu::BindGroupEntry {
+        binding: 0,
+        resource: wgpu::BindingResource::TextureView(depth_view),
+    }];
+
+    if !multisampled {
+        let sampler_type = if comparison {
+            wgpu::SamplerBindingType::Comparison
+        } else {
+            wgpu::SamplerBindingType::NonFiltering
+        };
+        layout_entries.push(wgpu::BindGroupLayoutEntry {
+            binding: 1,
+            visibility: shader_stages,
+            ty: wgpu::BindingType::Sampler(sampler_type),
+            count: None,
+        });
+        entries.push(wgpu::BindGroupEntry {
+            binding: 1,
+            resource: wgpu::BindingResource::Sampler(sampler),
+        });
+    }
+
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &layout_entries,
+        label: Some("Depth Bind Group Layout"),
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: &layout,
+        entries: &entries,
+        label: Some("Depth Bind Group"),
+    });
+
+    (layout, bind_group)
+}
+
+// depth texture of a shadow map, see shadow::ShadowMap; always single-sampled, as
+// textureSampleCompare cannot read a multisampled texture
+pub fn create_shadow_texture_view(
+    init: &impl RenderTarget,
+    width: u32,
+    height: u32,
+) -> wgpu::TextureView {
+    let shadow_depth_texture = init.device().create_texture(&wgpu::TextureDescriptor {
         size: wgpu::Extent3d {
             width,
             height,
             depth_or_array_layers: 1,
         },
         mip_level_count: 1,
-        sample_count: init.sample_count,
+        sample_count: 1,
         dimension: wgpu::TextureDimension::D2,
         format: wgpu::TextureFormat::Depth24Plus,
         usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-        label: None,
+        label: Some("Shadow Depth Texture"),
         view_formats: &[],
     });
 
     shadow_depth_texture.create_view(&wgpu::TextureViewDescriptor::default())
 }
-// endregion: views and attachments
-
-// region: tranformation
-pub const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
-    1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.5, 0.0, 0.0, 0.0, 0.5, 1.0,
-);
 
-pub fn create_model_mat(
-    translation: [f32; 3],
-    rotation: [f32; 3],
-    scaling: [f32; 3],
-) -> Matrix4<f32> {
-    // create transformation matrices
-    let trans_mat =
-        Matrix4::from_translation(Vector3::new(translation[0], translation[1], translation[2]));
-    let rotate_mat_x = Matrix4::from_angle_x(Rad(rotation[0]));
-    let rotate_mat_y = Matrix4::from_angle_y(Rad(rotation[1]));
-    let rotate_mat_z = Matrix4::from_angle_z(Rad(rotation[2]));
-    let scale_mat = Matrix4::from_nonuniform_scale(scaling[0], scaling[1], scaling[2]);
+// A texture with several layers rendered one pass at a time: the six faces of a
+// cubemap, in the +x, -x, +y, -y, +z, -z order of cube views, or the layers of a
+// texture array, e.g. one per eye. view samples all layers (texture_cube or
+// texture_2d_array), layer_views are the single-layer attachments and depth_view a
+// depth buffer shared by the layer passes. It is single-sampled and implements
+// RenderTarget, so pipelines for the layer passes come from IRenderPipeline::new.
+pub struct LayeredTarget {
+    pub device: wgpu::Device,
+    pub format: wgpu::TextureFormat,
+    pub size: [u32; 2],
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub layer_views: Vec<wgpu::TextureView>,
+    pub depth_view: wgpu::TextureView,
+}
 
-    // combine all transformation matrices together to form a final transform matrix: model matrix
-    let model_mat = trans_mat * rotate_mat_z * rotate_mat_y * rotate_mat_x * scale_mat;
+// cubemap with square faces of size texels
+pub fn create_cube_target(
+    device: &wgpu::Device,
+    size: u32,
+    format: wgpu::TextureFormat,
+) -> LayeredTarget {
+    create_layered_target(device, [size, size], 6, format, wgpu::TextureViewDimension::Cube)
+}
 
-    // return final model matrix
-    model_mat
+pub fn create_array_target(
+    device: &wgpu::Device,
+    size: [u32; 2],
+    layers: u32,
+    format: wgpu::TextureFormat,
+) -> LayeredTarget {
+    create_layered_target(device, size, layers, format, wgpu::TextureViewDimension::D2Array)
 }
 
-pub fn create_view_mat(
-    camera_position: Point3<f32>,
-    look_direction: Point3<f32>,
-    up_direction: Vector3<f32>,
-) -> Matrix4<f32> {
-    Matrix4::look_at_rh(camera_position, look_direction, up_direction)
+fn create_layered_target(
+    device: &wgpu::Device,
+    [width, height]: [u32; 2],
+    layers: u32,
+    format: wgpu::TextureFormat,
+    dimension: wgpu::TextureViewDimension,
+) -> LayeredTarget {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: layers,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+            | wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::COPY_SRC,
+        label: Some("Layered Texture"),
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor {
+        dimension: Some(dimension),
+        array_layer_count: Some(layers),
+        ..Default::default()
+    });
+    let layer_views = (0..layers)
+        .map(|layer| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                dimension: Some(wgpu::TextureViewDimension::D2),
+                base_array_layer: layer,
+                array_layer_count: Some(1),
+                ..Default::default()
+            })
+        })
+        .collect();
+    let depth_view = device
+        .create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Depth24Plus,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            label: Some("Layered Depth Texture"),
+            view_formats: &[],
+        })
+        .create_view(&wgpu::TextureViewDescriptor::default());
+
+    LayeredTarget {
+        device: device.clone(),
+        format,
+        size: [width, height],
+        texture,
+        view,
+        layer_views,
+        depth_view,
+    }
 }
 
-pub fn create_projection_mat(aspect: f32, is_perspective: bool) -> Matrix4<f32> {
-    let project_mat: Matrix4<f32>;
-    if is_perspective {
-        project_mat = OPENGL_TO_WGPU_MATRIX * perspective(Rad(2.0 * PI / 5.0), aspect, 0.1, 1000.0);
-    } else {
-        project_mat = OPENGL_TO_WGPU_MATRIX * ortho(-4.0, 4.0, -3.0, 3.0, -1.0, 6.0);
+impl LayeredTarget {
+    pub fn layers(&self) -> u32 {
+        self.layer_views.len() as u32
+    }
+
+    // pass into one layer, cleared to clear_color, with the shared depth buffer
+    pub fn begin_layer_pass<'a>(
+        &'a self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        layer: u32,
+        clear_color: wgpu::Color,
+    ) -> wgpu::RenderPass<'a> {
+        let mut color_attachment = create_color_attachment(&self.layer_views[layer as usize]);
+        color_attachment.ops.load = wgpu::LoadOp::Clear(clear_color);
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Layer Pass"),
+            color_attachments: &[Some(color_attachment)],
+            depth_stencil_attachment: Some(create_depth_stencil_attachment(&self.depth_view)),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        })
     }
-    project_mat
 }
 
-pub fn create_vp_mat(
-    camera_position: Point3<f32>,
-    look_direction: Point3<f32>,
-    up_direction: Vector3<f32>,
-    aspect: f32,
-) -> (Matrix4<f32>, Matrix4<f32>, Matrix4<f32>) {
-    // construct view matrix
-    let view_mat = Matrix4::look_at_rh(camera_position, look_direction, up_direction);
+impl RenderTarget for LayeredTarget {
+    fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
 
-    // construct projection matrix
-    let project_mat = OPENGL_TO_WGPU_MATRIX * perspective(Rad(2.0 * PI / 5.0), aspect, 0.1, 1000.0);
+    fn color_format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
 
-    // contruct view-projection matrix
-    let vp_mat = project_mat * view_mat;
+    fn target_size(&self) -> [u32; 2] {
+        self.size
+    }
 
-    // return various matrices
-    (view_mat, project_mat, vp_mat)
+    fn sample_count(&self) -> u32 {
+        1
+    }
 }
+// endregion: views and attachments
 
-pub fn create_ortho_mat(
-    left: f32,
-    right: f32,
-    bottom: f32,
-    top: f32,
-    near: f32,
-    far: f32,
-) -> Matrix4<f32> {
-    OPENGL_TO_WGPU_MATRIX * ortho(left, right, bottom, top, near, far)
+// region: render hooks
+// render targets of the current frame, handed to user-defined passes
+pub struct FrameTargets<'a> {
+    pub view: &'a wgpu::TextureView, // resolved surface texture view
+    pub msaa_view: Option<&'a wgpu::TextureView>,
+    pub depth_view: &'a wgpu::TextureView,
+    pub format: wgpu::TextureFormat,
+    pub width: u32,
+    pub height: u32,
+    pub sample_count: u32,
+}
+
+impl<'a> FrameTargets<'a> {
+    pub fn new(
+        init: &impl RenderTarget,
+        view: &'a wgpu::TextureView,
+        msaa_view: &'a wgpu::TextureView,
+        depth_view: &'a wgpu::TextureView,
+    ) -> Self {
+        let [width, height] = init.target_size();
+        Self {
+            view,
+            msaa_view: if init.sample_count() > 1 {
+                Some(msaa_view)
+            } else {
+                None
+            },
+            depth_view,
+            format: init.color_format(),
+            width,
+            height,
+            sample_count: init.sample_count(),
+        }
+    }
+
+    // color attachment that keeps what has been drawn so far; passes running after the
+    // surface pass draw on the resolved view with sample count 1
+    pub fn load_color_attachment(&self) -> wgpu::RenderPassColorAttachment<'a> {
+        wgpu::RenderPassColorAttachment {
+            view: self.view,
+            depth_slice: None,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Load,
+                store: wgpu::StoreOp::Store,
+            },
+        }
+    }
+}
+
+pub type PassHook = dyn FnMut(&mut wgpu::CommandEncoder, &FrameTargets);
+
+// user-defined passes recorded before and after the surface pass of a frame. Pre passes
+// run before the surface pass clears the frame, so they suit offscreen or compute work;
+// post passes draw on top of the finished frame (UI, extra geometry).
+#[derive(Default)]
+pub struct RenderHooks {
+    pre_passes: Vec<Box<PassHook>>,
+    post_passes: Vec<Box<PassHook>>,
+}
+
+impl RenderHooks {
+    pub fn add_pre_pass(
+        &mut self,
+        hook: impl FnMut(&mut wgpu::CommandEncoder, &FrameTargets) + 'static,
+    ) {
+        self.pre_passes.push(Box::new(hook));
+    }
+
+    pub fn add_post_pass(
+        &mut self,
+        hook: impl FnMut(&mut wgpu::CommandEncoder, &FrameTargets) + 'static,
+    ) {
+        self.post_passes.push(Box::new(hook));
+    }
+
+    pub fn clear(&mut self) {
+        self.pre_passes.clear();
+        self.post_passes.clear();
+    }
+
+    pub fn run_pre_passes(&mut self, encoder: &mut wgpu::CommandEncoder, targets: &FrameTargets) {
+        for hook in self.pre_passes.iter_mut() {
+            hook(encoder, targets);
+        }
+    }
+
+    pub fn run_post_passes(&mut self, encoder: &mut wgpu::CommandEncoder, targets: &FrameTargets) {
+        for hook in self.post_passes.iter_mut() {
+            hook(encoder, targets);
+        }
+    }
+}
+// endregion: render hooks
+
+// region: render graph
+// Passes that declare the attachments they read and write, run in an order that
+// satisfies them: the writers of an attachment run in the order they were added and
+// its readers after all of them (a pass that reads and writes one, like an overlay
+// drawn over the frame, counts as a writer). The graph creates the attachment
+// textures for the render target and recreates them when its size, color format or
+// sample count changes. OUTPUT is the view given to execute(), usually the surface
+// texture of the frame. One callback runs every pass, dispatching on its name:
+//
+//     graph.add_attachment("shadow", AttachmentDesc::depth().fixed_size(2048, 2048));
+//     graph.add_attachment("depth", AttachmentDesc::depth().multisampled());
+//     graph.add_pass("main", &["shadow"], &[RenderGraph::OUTPUT, "depth"]);
+//     graph.add_pass("shadow", &[], &["shadow"]);
+//     graph.execute(&init, &mut encoder, &view, |encoder, pass| match pass.name {
+//         "shadow" => .., // runs before main, which samples pass.view("shadow")
+//         _ => ..,
+//     })?;
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AttachmentDesc {
+    pub format: Option<wgpu::TextureFormat>, // None: the color format of the target
+    pub multisampled: bool,                  // with the sample count of the target
+    pub size: Option<[u32; 2]>,              // None: the size of the target
+    pub usage: wgpu::TextureUsages,          // besides RENDER_ATTACHMENT
+}
+
+impl Default for AttachmentDesc {
+    fn default() -> Self {
+        Self::color()
+    }
+}
+
+impl AttachmentDesc {
+    pub fn color() -> Self {
+        Self {
+            format: None,
+            multisampled: false,
+            size: None,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING,
+        }
+    }
+
+    pub fn depth() -> Self {
+        Self {
+            format: Some(wgpu::TextureFormat::Depth24Plus),
+            ..Self::color()
+        }
+    }
+
+    pub fn multisampled(self) -> Self {
+        Self {
+            multisampled: true,
+            ..self
+        }
+    }
+
+    pub fn fixed_size(self, width: u32, height: u32) -> Self {
+        Self {
+            size: Some([width, height]),
+            ..self
+        }
+    }
+}
+
+struct GraphAttachment {
+    name: String,
+    desc: AttachmentDesc,
+    // the texture with the size, format and sample count it was created for
+    texture: Option<(wgpu::Texture, wgpu::TextureView, ([u32; 3], wgpu::TextureFormat))>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GraphPass {
+    pub name: String,
+    pub reads: Vec<String>,
+    pub writes: Vec<String>,
+}
+
+// what the callback of execute() gets for a pass
+pub struct PassContext<'a> {
+    pub name: &'a str,
+    pub pass: &'a GraphPass,
+    graph: &'a RenderGraph,
+    output: &'a wgpu::TextureView,
+}
+
+impl PassContext<'_> {
+    // the view of an attachment of the graph, or OUTPUT
+    pub fn view(&self, name: &str) -> Option<&wgpu::TextureView> {
+        match name {
+            RenderGraph::OUTPUT => Some(self.output),
+            _ => self.graph.view(name),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct RenderGraph {
+    attachments: Vec<GraphAttachment>,
+    passes: Vec<GraphPass>,
+    allocations: u32,
+}
+
+impl RenderGraph {
+    pub const OUTPUT: &'static str = "output";
+
+    // declares an attachment, or changes the description of a declared one
+    pub fn add_attachment(&mut self, name: &str, desc: AttachmentDesc) {
+        match self.attachments.iter_mut().find(|a| a.name == name) {
+            Some(attachment) => attachment.desc = desc,
+            None => self.attachments.push(GraphAttachment {
+                name: name.to_string(),
+                desc,
+                texture: None,
+            }),
+        }
+    }
+
+    pub fn add_pass(&mut self, name: &str, reads: &[&str], writes: &[&str]) {
+        self.passes.push(GraphPass {
+            name: name.to_string(),
+            reads: reads.iter().map(|s| s.to_string()).collect(),
+            writes: writes.iter().map(|s| s.to_string()).collect(),
+        });
+    }
+
+    pub fn remove_pass(&mut self, name: &str) {
+        self.passes.retain(|pass| pass.name != name);
+    }
+
+    pub fn passes(&self) -> &[GraphPass] {
+        &self.passes
+    }
+
+    // the view of an attachment once execute() created it
+    pub fn view(&self, name: &str) -> Option<&wgpu::TextureView> {
+        let attachment = self.attachments.iter().find(|a| a.name == name)?;
+        attachment.texture.as_ref().map(|(_, view, _)| view)
+    }
+
+    pub fn texture(&self, name: &str) -> Option<&wgpu::Texture> {
+        let attachment = self.attachments.iter().find(|a| a.name == name)?;
+        attachment.texture.as_ref().map(|(texture, ..)| texture)
+    }
+
+    // how many attachment textures were created so far
+    pub fn allocations(&self) -> u32 {
+        self.allocations
+    }
+
+    // the pass indices in the order they run; the earliest added pass goes first
+    // whenever several could
+    pub fn order(&self) -> Result<Vec<usize>> {
+        let known =
+            |name: &str| name == Self::OUTPUT || self.attachments.iter().any(|a| a.name == name);
+        for pass in self.passes.iter() {
+            if let Some(name) = pass.reads.iter().chain(&pass.writes).find(|n| !known(n)) {
+                return Err(Error::RenderGraph(format!(
+                    "pass '{}' uses the undeclared attachment '{}'",
+                    pass.name, name
+                )));
+            }
+        }
+
+        // after[i]: the passes that have to run before pass i
+        let n = self.passes.len();
+        let mut after = vec![vec![]; n];
+        for (i, pass) in self.passes.iter().enumerate() {
+            for (j, other) in self.passes.iter().enumerate() {
+                let before = other.writes.iter().any(|name| {
+                    (pass.reads.contains(name) && !pass.writes.contains(name))
+                        || (j < i && pass.writes.contains(name))
+                });
+                if i != j && before {
+                    after[i].push(j);
+                }
+            }
+        }
+        let mut order = Vec::with_capacity(n);
+        let mut done = vec![false; n];
+        while order.len() < n {
+            let Some(i) = (0..n).find(|&i| !done[i] && after[i].iter().all(|&j| done[j])) else {
+                let waiting: Vec<&str> = (0..n)
+                    .filter(|&i| !done[i])
+                    .map(|i| self.passes[i].name.as_str())
+                    .collect();
+                return Err(Error::RenderGraph(format!(
+                    "the passes {} wait on each other",
+                    waiting.join(", ")
+                )));
+            };
+            done[i] = true;
+            order.push(i);
+        }
+        Ok(order)
+    }
+
+    // creates the attachment textures the target does not fit anymore
+    fn allocate(&mut self, target: &impl RenderTarget) {
+        let [width, height] = target.target_size();
+        for attachment in self.attachments.iter_mut() {
+            let desc = attachment.desc;
+            let [w, h] = desc.size.unwrap_or([width, height]).map(|x| x.max(1));
+            let format = desc.format.unwrap_or(target.color_format());
+            let samples = if desc.multisampled { target.sample_count() } else { 1 };
+            let key = ([w, h, samples], format);
+            if attachment.texture.as_ref().is_some_and(|(.., k)| *k == key) {
+                continue;
+            }
+            let texture = target.device().create_texture(&wgpu::TextureDescriptor {
+                label: Some(&attachment.name),
+                size: wgpu::Extent3d {
+                    width: w,
+                    height: h,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: samples,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | desc.usage,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            attachment.texture = Some((texture, view, key));
+            self.allocations += 1;
+        }
+    }
+
+    // runs the passes in order() with the attachments of the target
+    pub fn execute(
+        &mut self,
+        target: &impl RenderTarget,
+        encoder: &mut wgpu::CommandEncoder,
+        output: &wgpu::TextureView,
+        mut run: impl FnMut(&mut wgpu::CommandEncoder, &PassContext),
+    ) -> Result<()> {
+        let order = self.order()?;
+        self.allocate(target);
+        for i in order {
+            let pass = &self.passes[i];
+            let context = PassContext {
+                name: &pass.name,
+                pass,
+                graph: self,
+                output,
+            };
+            run(encoder, &context);
+        }
+        Ok(())
+    }
+}
+// endregion: render graph
+
+// region: tranformation
+pub const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
+    1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.5, 0.0, 0.0, 0.0, 0.5, 1.0,
+);
+
+pub fn create_model_mat(
+    translation: [f32; 3],
+    rotation: [f32; 3],
+    scaling: [f32; 3],
+) -> Matrix4<f32> {
+    // create transformation matrices
+    let trans_mat =
+        Matrix4::from_translation(Vector3::new(translation[0], translation[1], translation[2]));
+    let rotate_mat_x = Matrix4::from_angle_x(Rad(rotation[0]));
+    let rotate_mat_y = Matrix4::from_angle_y(Rad(rotation[1]));
+    let rotate_mat_z = Matrix4::from_angle_z(Rad(rotation[2]));
+    let scale_mat = Matrix4::from_nonuniform_scale(scaling[0], scaling[1], scaling[2]);
+
+    // combine all transformation matrices together to form a final transform matrix: model matrix
+    let model_mat = trans_mat * rotate_mat_z * rotate_mat_y * rotate_mat_x * scale_mat;
+
+    // return final model matrix
+    model_mat
+}
+
+pub fn create_view_mat(
+    camera_position: Point3<f32>,
+    look_direction: Point3<f32>,
+    up_direction: Vector3<f32>,
+) -> Matrix4<f32> {
+    Matrix4::look_at_rh(camera_position, look_direction, up_direction)
+}
+
+pub fn create_projection_mat(aspect: f32, is_perspective: bool) -> Matrix4<f32> {
+    if is_perspective {
+        OPENGL_TO_WGPU_MATRIX * perspective(Rad(2.0 * PI / 5.0), aspect, 0.1, 1000.0)
+    } else {
+        OPENGL_TO_WGPU_MATRIX * ortho(-4.0, 4.0, -3.0, 3.0, -1.0, 6.0)
+    }
+}
+
+// perspective projection with explicit clip planes, e.g. from depth::NearFarTuner
+pub fn create_perspective_mat(aspect: f32, near: f32, far: f32) -> Matrix4<f32> {
+    OPENGL_TO_WGPU_MATRIX * perspective(Rad(2.0 * PI / 5.0), aspect, near, far)
+}
+
+pub fn create_vp_mat(
+    camera_position: Point3<f32>,
+    look_direction: Point3<f32>,
+    up_direction: Vector3<f32>,
+    aspect: f32,
+) -> (Matrix4<f32>, Matrix4<f32>, Matrix4<f32>) {
+    // construct view matrix
+    let view_mat = Matrix4::look_at_rh(camera_position, look_direction, up_direction);
+
+    // construct projection matrix
+    let project_mat = OPENGL_TO_WGPU_MATRIX * perspective(Rad(2.0 * PI / 5.0), aspect, 0.1, 1000.0);
+
+    // contruct view-projection matrix
+    let vp_mat = project_mat * view_mat;
+
+    // return various matrices
+    (view_mat, project_mat, vp_mat)
+}
+
+pub fn create_ortho_mat(
+    left: f32,
+    right: f32,
+    bottom: f32,
+    top: f32,
+    near: f32,
+    far: f32,
+) -> Matrix4<f32> {
+    OPENGL_TO_WGPU_MATRIX * ortho(left, right, bottom, top, near, far)
+}
+
+// view matrices from position through the six faces of a cubemap, in layer order
+pub fn create_cube_view_mats(position: Point3<f32>) -> [Matrix4<f32>; 6] {
+    let faces = [
+        (Vector3::unit_x(), -Vector3::unit_y()),
+        (-Vector3::unit_x(), -Vector3::unit_y()),
+        (Vector3::unit_y(), Vector3::unit_z()),
+        (-Vector3::unit_y(), -Vector3::unit_z()),
+        (Vector3::unit_z(), -Vector3::unit_y()),
+        (-Vector3::unit_z(), -Vector3::unit_y()),
+    ];
+    faces.map(|(direction, up)| Matrix4::look_at_rh(position, position + direction, up))
+}
+
+// square 90 degree projection of the cube faces. The cube view up vectors put +y at the
+// bottom, as cubemaps are laid out with the first row at the bottom; flipping y here
+// puts it on the first row of the wgpu texture, which also turns the winding of front
+// faces to clockwise
+pub fn create_cube_projection_mat(near: f32, far: f32) -> Matrix4<f32> {
+    Matrix4::from_nonuniform_scale(1.0, -1.0, 1.0)
+        * OPENGL_TO_WGPU_MATRIX
+        * perspective(Rad(0.5 * PI), 1.0, near, far)
+}
+
+// left and right eye view matrices for stereo layers, eye_separation apart along the
+// camera right vector
+pub fn create_stereo_view_mats(
+    camera_position: Point3<f32>,
+    look_direction: Point3<f32>,
+    up_direction: Vector3<f32>,
+    eye_separation: f32,
+) -> [Matrix4<f32>; 2] {
+    let right = (look_direction - camera_position).cross(up_direction).normalize();
+    [-0.5, 0.5].map(|side| {
+        let offset = side * eye_separation * right;
+        Matrix4::look_at_rh(camera_position + offset, look_direction + offset, up_direction)
+    })
 }
 // endregion: tranformation
 
+// region: camera
+// Orbit camera around a target point: dragging with the left mouse button rotates,
+// dragging with the right or middle button pans and the wheel zooms. Feed it the
+// window events and take view_mat() and eye() from it each frame.
+#[derive(Clone, Copy, Debug)]
+pub struct OrbitCamera {
+    pub target: Point3<f32>,
+    pub yaw: f32,   // around the y axis, 0 looks from +z
+    pub pitch: f32, // above the xz plane, kept within (-PI/2, PI/2)
+    pub distance: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    pub rotate_speed: f32, // radians per pixel
+    pub zoom_speed: f32,   // distance factor per wheel step
+    button: Option<MouseButton>,
+    cursor: Option<[f32; 2]>,
+}
+
+impl Default for OrbitCamera {
+    fn default() -> Self {
+        Self::new((4.0, 4.0, 4.0).into(), (0.0, 0.0, 0.0).into())
+    }
+}
+
+impl OrbitCamera {
+    pub fn new(eye: Point3<f32>, target: Point3<f32>) -> Self {
+        let offset = eye - target;
+        let distance = offset.magnitude().max(1e-3);
+        Self {
+            target,
+            yaw: offset.x.atan2(offset.z),
+            pitch: (offset.y / distance).clamp(-1.0, 1.0).asin(),
+            distance,
+            min_distance: 0.1 * distance,
+            max_distance: 20.0 * distance,
+            rotate_speed: 0.01,
+            zoom_speed: 0.9,
+            button: None,
+            cursor: None,
+        }
+    }
+
+    pub fn eye(&self) -> Point3<f32> {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        self.target
+            + self.distance * Vector3::new(cos_pitch * sin_yaw, sin_pitch, cos_pitch * cos_yaw)
+    }
+
+    pub fn view_mat(&self) -> Matrix4<f32> {
+        create_view_mat(self.eye(), self.target, Vector3::unit_y())
+    }
+
+    // dx, dy in pixels; dragging right turns the scene right, dragging down tilts it down
+    pub fn rotate(&mut self, dx: f32, dy: f32) {
+        self.yaw -= self.rotate_speed * dx;
+        self.pitch = (self.pitch + self.rotate_speed * dy).clamp(-0.49 * PI, 0.49 * PI);
+    }
+
+    // positive steps move closer
+    pub fn zoom(&mut self, steps: f32) {
+        self.distance = (self.distance * self.zoom_speed.powf(steps))
+            .clamp(self.min_distance, self.max_distance);
+    }
+
+    // moves the target in the view plane; dx, dy in pixels, scaled with the distance
+    // so the scene follows the cursor at any zoom
+    pub fn pan(&mut self, dx: f32, dy: f32) {
+        let forward = (self.target - self.eye()).normalize();
+        let right = forward.cross(Vector3::unit_y()).normalize();
+        let up = right.cross(forward);
+        let scale = 0.1 * self.rotate_speed * self.distance;
+        self.target += scale * (up * dy - right * dx);
+    }
+
+    // returns true if the event moved the camera; button presses and cursor moves
+    // without a drag are left for other handlers, e.g. picking
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::MouseInput { state, button, .. } => {
+                self.button = match state {
+                    ElementState::Pressed => Some(*button),
+                    ElementState::Released => None,
+                };
+                false
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                let cursor = [position.x as f32, position.y as f32];
+                let last = self.cursor.replace(cursor);
+                let (Some(last), Some(button)) = (last, self.button) else {
+                    return false;
+                };
+                let (dx, dy) = (cursor[0] - last[0], cursor[1] - last[1]);
+                match button {
+                    MouseButton::Left => self.rotate(dx, dy),
+                    MouseButton::Right | MouseButton::Middle => self.pan(dx, dy),
+                    _ => return false,
+                }
+                true
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor = None;
+                false
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.zoom(match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(p) => p.y as f32 / 40.0,
+                });
+                true
+            }
+            _ => false,
+        }
+    }
+}
+// endregion: camera
+
 // region: bind groups
 pub fn create_bind_group_layout_storage(
     device: &wgpu::Device,
@@ -378,7 +1584,14 @@ pub fn create_bind_group_storage(
     shader_stages: Vec<wgpu::ShaderStages>,
     binding_types: Vec<wgpu::BufferBindingType>,
     resources: &[wgpu::BindingResource<'_>],
-) -> (wgpu::BindGroupLayout, wgpu::BindGroup) {
+) -> Result<(wgpu::BindGroupLayout, wgpu::BindGroup)> {
+    if shader_stages.len() != resources.len() {
+        return Err(Error::BindingCount {
+            label: "Bind Group",
+            stages: shader_stages.len(),
+            resources: resources.len(),
+        });
+    }
     let entries: Vec<_> = resources
         .iter()
         .enumerate()
@@ -389,13 +1602,15 @@ pub fn create_bind_group_storage(
         .collect();
 
     let layout = create_bind_group_layout_storage(device, shader_stages, binding_types);
-    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-        layout: &layout,
-        entries: &entries,
-        label: Some("Bind Group"),
-    });
+    let bind_group = error::validated(device, "bind group", || {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &entries,
+            label: Some("Bind Group"),
+        })
+    })?;
 
-    (layout, bind_group)
+    Ok((layout, bind_group))
 }
 
 pub fn create_bind_group_layout(
@@ -404,10 +1619,10 @@ pub fn create_bind_group_layout(
 ) -> wgpu::BindGroupLayout {
     let mut entries = vec![];
 
-    for i in 0..shader_stages.len() {
+    for (i, stage) in shader_stages.iter().enumerate() {
         entries.push(wgpu::BindGroupLayoutEntry {
             binding: i as u32,
-            visibility: shader_stages[i],
+            visibility: *stage,
             ty: wgpu::BindingType::Buffer {
                 ty: wgpu::BufferBindingType::Uniform,
                 has_dynamic_offset: false,
@@ -427,7 +1642,14 @@ pub fn create_bind_group(
     device: &wgpu::Device,
     shader_stages: Vec<wgpu::ShaderStages>,
     resources: &[wgpu::BindingResource<'_>],
-) -> (wgpu::BindGroupLayout, wgpu::BindGroup) {
+) -> Result<(wgpu::BindGroupLayout, wgpu::BindGroup)> {
+    if shader_stages.len() != resources.len() {
+        return Err(Error::BindingCount {
+            label: "Uniform Bind Group",
+            stages: shader_stages.len(),
+            resources: resources.len(),
+        });
+    }
     let entries: Vec<_> = resources
         .iter()
         .enumerate()
@@ -438,21 +1660,484 @@ pub fn create_bind_group(
         .collect();
 
     let layout = create_bind_group_layout(device, shader_stages);
-    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-        layout: &layout,
-        entries: &entries,
-        label: Some("Uniform Bind Group"),
-    });
+    let bind_group = error::validated(device, "bind group", || {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &entries,
+            label: Some("Uniform Bind Group"),
+        })
+    })?;
 
-    (layout, bind_group)
+    Ok((layout, bind_group))
 }
 // endregion: bind groups
 
+// region: growable buffers
+// Vertex or index buffer for contents whose size changes, e.g. with the surface
+// resolution. write() uploads through queue.write_buffer while the data fits and only
+// reallocates when it does not, then with headroom, so changing the resolution back
+// and forth does not destroy and recreate buffers every time.
+pub struct GrowableBuffer {
+    buffer: wgpu::Buffer,
+    label: String,
+    usage: wgpu::BufferUsages,
+    len: u64,          // bytes written, padded to COPY_BUFFER_ALIGNMENT
+    pub headroom: f32, // extra capacity on reallocation, as a fraction of the data size
+    reallocations: u32,
+}
+
+impl GrowableBuffer {
+    // usage gets COPY_DST added; the initial contents are allocated with headroom too
+    pub fn new(
+        device: &wgpu::Device,
+        label: &str,
+        usage: wgpu::BufferUsages,
+        contents: &[u8],
+    ) -> Self {
+        let usage = usage | wgpu::BufferUsages::COPY_DST;
+        let headroom = 0.5;
+        let len = Self::padded_len(contents.len());
+        let capacity = Self::capacity_for(len, headroom);
+        let buffer = Self::allocate(device, label, usage, capacity, true);
+        buffer.slice(..).get_mapped_range_mut()[..contents.len()].copy_from_slice(contents);
+        buffer.unmap();
+        Self {
+            buffer,
+            label: label.to_string(),
+            usage,
+            len,
+            headroom,
+            reallocations: 0,
+        }
+    }
+
+    fn padded_len(len: usize) -> u64 {
+        (len as u64).next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT)
+    }
+
+    fn capacity_for(len: u64, headroom: f32) -> u64 {
+        let capacity = len + (len as f64 * headroom.max(0.0) as f64) as u64;
+        Self::padded_len(capacity as usize).max(wgpu::COPY_BUFFER_ALIGNMENT)
+    }
+
+    fn allocate(
+        device: &wgpu::Device,
+        label: &str,
+        usage: wgpu::BufferUsages,
+        size: u64,
+        mapped: bool,
+    ) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size,
+            usage,
+            mapped_at_creation: mapped,
+        })
+    }
+
+    // replaces the contents; returns true if the buffer had to be reallocated, which
+    // invalidates bind groups holding it
+    pub fn write(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, contents: &[u8]) -> bool {
+        let len = Self::padded_len(contents.len());
+        let reallocate = len > self.buffer.size();
+        if reallocate {
+            self.buffer.destroy();
+            let capacity = Self::capacity_for(len, self.headroom);
+            self.buffer = Self::allocate(device, &self.label, self.usage, capacity, false);
+            self.reallocations += 1;
+        }
+        if len as usize == contents.len() {
+            queue.write_buffer(&self.buffer, 0, contents);
+        } else {
+            // write_buffer needs a multiple of 4 bytes, e.g. an odd number of u16 indices
+            let mut padded = contents.to_vec();
+            padded.resize(len as usize, 0);
+            queue.write_buffer(&self.buffer, 0, &padded);
+        }
+        self.len = len;
+        reallocate
+    }
+
+    // write() for contents that mostly match what the buffer holds: only the dirty
+    // ranges are uploaded, unless the size changed; the ranges are cleared either way
+    pub fn write_dirty(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        contents: &[u8],
+        dirty: &mut DirtyRanges,
+    ) -> bool {
+        if Self::padded_len(contents.len()) != self.len {
+            dirty.clear();
+            return self.write(device, queue, contents);
+        }
+        dirty.write(queue, &self.buffer, contents);
+        false
+    }
+
+    // records a copy of what source holds, e.g. to keep a mesh while its buffer is
+    // rewritten; source needs COPY_SRC usage. Returns true on reallocation, like write()
+    pub fn copy_from(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &GrowableBuffer,
+    ) -> bool {
+        let reallocate = source.len > self.buffer.size();
+        if reallocate {
+            self.buffer.destroy();
+            let capacity = Self::capacity_for(source.len, self.headroom);
+            self.buffer = Self::allocate(device, &self.label, self.usage, capacity, false);
+            self.reallocations += 1;
+        }
+        if source.len > 0 {
+            encoder.copy_buffer_to_buffer(&source.buffer, 0, &self.buffer, 0, source.len);
+        }
+        self.len = source.len;
+        reallocate
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    // the written part of the buffer, for set_vertex_buffer and set_index_buffer
+    pub fn slice(&self) -> wgpu::BufferSlice<'_> {
+        self.buffer.slice(..self.len.max(wgpu::COPY_BUFFER_ALIGNMENT))
+    }
+
+    // bytes written
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> u64 {
+        self.buffer.size()
+    }
+
+    pub fn reallocations(&self) -> u32 {
+        self.reallocations
+    }
+}
+// endregion: growable buffers
+
+// region: dirty ranges
+// Byte ranges of a buffer that changed since the last upload, sorted and coalesced, for
+// sparse updates such as a streaming surface where only a few rows change per frame:
+// write() issues one write_buffer per range instead of uploading the whole buffer.
+// Ranges are widened to COPY_BUFFER_ALIGNMENT; ranges less than merge_gap bytes apart
+// are merged, trading a few unchanged bytes for fewer writes.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DirtyRanges {
+    ranges: Vec<Range<u64>>,
+    pub merge_gap: u64,
+}
+
+impl DirtyRanges {
+    pub fn with_merge_gap(mut self, merge_gap: u64) -> Self {
+        self.merge_gap = merge_gap;
+        self
+    }
+
+    // the ranges where new differs from old, compared stride bytes (one vertex) at a
+    // time; bytes past the end of old are dirty
+    pub fn diff(old: &[u8], new: &[u8], stride: usize) -> Self {
+        let mut dirty = Self::default();
+        dirty.mark_diff(old, new, stride);
+        dirty
+    }
+
+    pub fn mark_diff(&mut self, old: &[u8], new: &[u8], stride: usize) {
+        let stride = stride.max(1);
+        let mut run: Option<usize> = None;
+        for (i, chunk) in new.chunks(stride).enumerate() {
+            let start = i * stride;
+            let changed = old.get(start..start + chunk.len()) != Some(chunk);
+            match (changed, run) {
+                (true, None) => run = Some(start),
+                (false, Some(run_start)) => {
+                    self.mark(run_start as u64..start as u64);
+                    run = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(run_start) = run {
+            self.mark(run_start as u64..new.len() as u64);
+        }
+    }
+
+    pub fn mark(&mut self, range: Range<u64>) {
+        if range.is_empty() {
+            return;
+        }
+        let align = wgpu::COPY_BUFFER_ALIGNMENT;
+        let range = range.start / align * align..range.end.next_multiple_of(align);
+        let at = self.ranges.partition_point(|r| r.start < range.start);
+        self.ranges.insert(at, range);
+        // coalesce the new range with its neighbors
+        let gap = self.merge_gap;
+        let mut merged: Vec<Range<u64>> = Vec::with_capacity(self.ranges.len());
+        for r in self.ranges.drain(..) {
+            match merged.last_mut() {
+                Some(last) if r.start <= last.end + gap => last.end = last.end.max(r.end),
+                _ => merged.push(r),
+            }
+        }
+        self.ranges = merged;
+    }
+
+    // marks elements of stride bytes, e.g. a few rows of vertices
+    pub fn mark_elements(&mut self, elements: Range<usize>, stride: usize) {
+        self.mark((elements.start * stride) as u64..(elements.end * stride) as u64);
+    }
+
+    pub fn ranges(&self) -> &[Range<u64>] {
+        &self.ranges
+    }
+
+    // bytes write() uploads
+    pub fn bytes(&self) -> u64 {
+        self.ranges.iter().map(|r| r.end - r.start).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.ranges.clear();
+    }
+
+    // writes the dirty parts of contents at the same offsets of buffer, then clears
+    pub fn write(&mut self, queue: &wgpu::Queue, buffer: &wgpu::Buffer, contents: &[u8]) {
+        let len = contents.len() as u64;
+        for r in self.ranges.drain(..) {
+            let end = r.end.min(len);
+            if r.start >= end {
+                continue;
+            }
+            let bytes = &contents[r.start as usize..end as usize];
+            if (bytes.len() as u64).is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT) {
+                queue.write_buffer(buffer, r.start, bytes);
+            } else {
+                // the last range of contents whose length is not a multiple of 4
+                let mut padded = bytes.to_vec();
+                padded.resize(bytes.len().next_multiple_of(4), 0);
+                queue.write_buffer(buffer, r.start, &padded);
+            }
+        }
+    }
+}
+// endregion: dirty ranges
+
+// region: gpu timer
+// GPU time of render passes, from timestamp queries written at the start and end of
+// each timed pass. They need Features::TIMESTAMP_QUERY, which the capabilities request
+// for Subsystem::TimestampProfiling; without it the timer is disabled and
+// timestamp_writes() gives None, so the passes simply run untimed:
+//
+//     let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+//         timestamp_writes: timer.timestamp_writes("shape_only"),
+//         ..
+//     });
+//     ..
+//     timer.resolve(&mut encoder);
+//     queue.submit(std::iter::once(encoder.finish()));
+//     let times = timer.read(&device)?;
+//
+// read() waits for the GPU, so the timer stalls every frame it times.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PassTime {
+    pub label: String,
+    pub ms: f64,
+}
+
+impl std::fmt::Display for PassTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}: {:.3} ms", self.label, self.ms)
+    }
+}
+
+struct TimerQueries {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+}
+
+pub struct GpuTimer {
+    pub max_passes: u32, // timed passes per frame; further passes run untimed
+    queries: Option<TimerQueries>,
+    period: f32,         // nanoseconds per timestamp tick
+    labels: Vec<String>, // the passes timed in the current frame
+    resolved: u32,       // the passes resolve() copied for read()
+    totals: Vec<(String, f64, u32)>, // summed times and counts since the last print
+    last_print_time: Instant,
+}
+
+impl GpuTimer {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, max_passes: u32) -> Self {
+        let max_passes = max_passes.max(1);
+        let queries = device
+            .features()
+            .contains(wgpu::Features::TIMESTAMP_QUERY)
+            .then(|| {
+                let size = 16 * max_passes as u64;
+                let buffer = |label, usage| {
+                    device.create_buffer(&wgpu::BufferDescriptor {
+                        label: Some(label),
+                        size,
+                        usage,
+                        mapped_at_creation: false,
+                    })
+                };
+                TimerQueries {
+                    query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                        label: Some("Gpu Timer Queries"),
+                        ty: wgpu::QueryType::Timestamp,
+                        count: 2 * max_passes,
+                    }),
+                    resolve_buffer: buffer(
+                        "Gpu Timer Resolve Buffer",
+                        wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                    ),
+                    readback_buffer: buffer(
+                        "Gpu Timer Readback Buffer",
+                        wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                    ),
+                }
+            });
+        Self {
+            max_passes,
+            queries,
+            period: queue.get_timestamp_period(),
+            labels: vec![],
+            resolved: 0,
+            totals: vec![],
+            last_print_time: Instant::now(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.queries.is_some()
+    }
+
+    // the timestamp writes of the next pass, which is reported as label; None when the
+    // timer is disabled or the frame already timed max_passes passes
+    pub fn timestamp_writes(&mut self, label: &str) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        let queries = self.queries.as_ref()?;
+        let index = self.labels.len() as u32;
+        if index >= self.max_passes {
+            return None;
+        }
+        self.labels.push(label.to_string());
+        Some(wgpu::RenderPassTimestampWrites {
+            query_set: &queries.query_set,
+            beginning_of_pass_write_index: Some(2 * index),
+            end_of_pass_write_index: Some(2 * index + 1),
+        })
+    }
+
+    // copies the timestamps of the frame's passes for read(); call it after the passes
+    pub fn resolve(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let (Some(queries), count) = (&self.queries, self.labels.len() as u32) else {
+            return;
+        };
+        if count == 0 {
+            return;
+        }
+        encoder.resolve_query_set(&queries.query_set, 0..2 * count, &queries.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &queries.resolve_buffer,
+            0,
+            &queries.readback_buffer,
+            0,
+            16 * count as u64,
+        );
+        self.resolved = count;
+    }
+
+    // the times of the passes resolved in the submitted frame, in the order they were
+    // timed, and starts the next frame
+    pub fn read(&mut self, device: &wgpu::Device) -> anyhow::Result<Vec<PassTime>> {
+        let labels = std::mem::take(&mut self.labels);
+        let (Some(queries), count) = (&self.queries, std::mem::take(&mut self.resolved)) else {
+            return Ok(vec![]);
+        };
+        if count == 0 {
+            return Ok(vec![]);
+        }
+        let slice = queries.readback_buffer.slice(..16 * count as u64);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+        device.poll(wgpu::PollType::Wait)?;
+        rx.recv()??;
+        let ticks: Vec<u64> = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+        queries.readback_buffer.unmap();
+
+        let times: Vec<PassTime> = labels
+            .into_iter()
+            .zip(ticks.chunks_exact(2))
+            .map(|(label, t)| PassTime {
+                label,
+                ms: t[1].wrapping_sub(t[0]) as f64 * self.period as f64 / 1e6,
+            })
+            .collect();
+        for time in times.iter() {
+            match self.totals.iter_mut().find(|(label, ..)| *label == time.label) {
+                Some((_, sum, n)) => (*sum, *n) = (*sum + time.ms, *n + 1),
+                None => self.totals.push((time.label.clone(), time.ms, 1)),
+            }
+        }
+        Ok(times)
+    }
+
+    // the mean time of each label since the last call, and clears them
+    pub fn take_averages(&mut self) -> Vec<PassTime> {
+        self.totals
+            .drain(..)
+            .map(|(label, sum, n)| PassTime {
+                label,
+                ms: sum / n as f64,
+            })
+            .collect()
+    }
+
+    // logs the mean pass times every interval seconds, like FpsCounter::log_fps
+    pub fn log_times(&mut self, interval: u64) {
+        let now = Instant::now();
+        if now - self.last_print_time >= Duration::from_secs(interval) {
+            for time in self.take_averages() {
+                log::info!("GPU {}", time);
+            }
+            self.last_print_time = now;
+        }
+    }
+}
+// endregion: gpu timer
+
 // region: utility
 
+// frame rate of the last second and the frame times in milliseconds of the last
+// FpsCounter::HISTORY frames
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameStats {
+    pub fps: usize,
+    pub last_ms: f32,
+    pub mean_ms: f32,
+    pub max_ms: f32,
+}
+
 #[derive(Debug)]
 pub struct FpsCounter {
     last_second_frames: VecDeque<Instant>,
+    frame_times: VecDeque<f32>, // milliseconds, oldest first
     last_print_time: Instant,
 }
 
@@ -463,34 +2148,147 @@ impl Default for FpsCounter {
 }
 
 impl FpsCounter {
+    pub const HISTORY: usize = 120;
+
     // Creates a new FpsCounter.
     pub fn new() -> Self {
         Self {
             last_second_frames: VecDeque::with_capacity(128),
+            frame_times: VecDeque::with_capacity(Self::HISTORY),
             last_print_time: Instant::now(),
         }
     }
 
-    // updates the fps counter and print fps.
-    pub fn print_fps(&mut self, interval: u64) {
-        let now = Instant::now();
-        let a_second_ago = now - Duration::from_secs(1);
+    // counts a frame rendered now, for the metrics without printing them
+    pub fn tick(&mut self) {
+        self.tick_at(Instant::now());
+    }
+
+    pub fn tick_at(&mut self, now: Instant) {
+        if let Some(&last) = self.last_second_frames.back() {
+            if self.frame_times.len() == Self::HISTORY {
+                self.frame_times.pop_front();
+            }
+            self.frame_times
+                .push_back(now.saturating_duration_since(last).as_secs_f32() * 1000.0);
+        }
 
+        let a_second_ago = now - Duration::from_secs(1);
         while self
             .last_second_frames
             .front()
-            .map_or(false, |t| *t < a_second_ago)
+            .is_some_and(|t| *t < a_second_ago)
         {
             self.last_second_frames.pop_front();
         }
         self.last_second_frames.push_back(now);
+    }
+
+    // frames counted in the last second
+    pub fn fps(&self) -> usize {
+        self.last_second_frames.len()
+    }
+
+    // milliseconds between the frames counted, oldest first
+    pub fn frame_times(&self) -> impl ExactSizeIterator<Item = f32> + '_ {
+        self.frame_times.iter().copied()
+    }
+
+    pub fn stats(&self) -> FrameStats {
+        let n = self.frame_times.len();
+        FrameStats {
+            fps: self.fps(),
+            last_ms: self.frame_times.back().copied().unwrap_or(0.0),
+            mean_ms: if n > 0 {
+                self.frame_times.iter().sum::<f32>() / n as f32
+            } else {
+                0.0
+            },
+            max_ms: self.frame_times.iter().copied().fold(0.0, f32::max),
+        }
+    }
+
+    // updates the fps counter and logs the fps every interval seconds; the
+    // overlay::StatsOverlay shows it on screen instead
+    pub fn log_fps(&mut self, interval: u64) {
+        let now = Instant::now();
+        self.tick_at(now);
 
         // Check if the interval seconds have passed since the last print time
         if now - self.last_print_time >= Duration::from_secs(interval) {
-            let fps = self.last_second_frames.len();
-            println!("FPS: {}", fps);
+            log::info!("FPS: {}", self.fps());
             self.last_print_time = now;
         }
     }
 }
+
+// Drops the frame rate while the window is unfocused or occluded so long-running
+// visualizations do not keep the GPU busy in the background. While power saving, a
+// frame is only due after something changed (invalidate: input, a resize or an
+// explicit redraw), and then at most idle_fps times a second; the application
+// should only update and redraw when next_frame returns true.
+#[derive(Debug)]
+pub struct FramePacer {
+    pub idle_fps: u32,
+    focused: bool,
+    occluded: bool,
+    dirty: bool,
+    last_frame: Instant,
+}
+
+impl Default for FramePacer {
+    fn default() -> Self {
+        Self {
+            idle_fps: 5,
+            focused: true,
+            occluded: false,
+            dirty: true,
+            last_frame: Instant::now(),
+        }
+    }
+}
+
+impl FramePacer {
+    pub fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+    }
+
+    pub fn set_occluded(&mut self, occluded: bool) {
+        self.occluded = occluded;
+    }
+
+    pub fn is_power_saving(&self) -> bool {
+        !self.focused || self.occluded
+    }
+
+    // something changed that the next frame has to show
+    pub fn invalidate(&mut self) {
+        self.dirty = true;
+    }
+
+    // power saving with nothing changed since the last frame: no frame is due
+    pub fn is_idle(&self) -> bool {
+        self.is_power_saving() && !self.dirty
+    }
+
+    fn frame_interval(&self) -> Duration {
+        Duration::from_secs_f32(1.0 / self.idle_fps.max(1) as f32)
+    }
+
+    // time of the next frame while power saving; None means render continuously
+    pub fn next_frame_time(&self) -> Option<Instant> {
+        self.is_power_saving().then(|| self.last_frame + self.frame_interval())
+    }
+
+    // returns true if a frame is due and marks it as rendered
+    pub fn next_frame(&mut self) -> bool {
+        let now = Instant::now();
+        if self.is_idle() || self.next_frame_time().is_some_and(|t| now < t) {
+            return false;
+        }
+        self.last_frame = now;
+        self.dirty = false;
+        true
+    }
+}
 // endregion: utility
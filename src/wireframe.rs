@@ -0,0 +1,226 @@
+use bytemuck::{Pod, Zeroable};
+use std::collections::HashSet;
+
+use super::color;
+use super::surface_data::ISurfaceOutput;
+
+// region: wireframe style
+// How the wireframe is drawn. Lines is the line list of the chapters: a second vertex
+// and index buffer (indices2) with its own LineList pipeline and draw call.
+// Barycentric draws surface and wireframe in one pass: every triangle gets its own
+// three vertices with barycentric coordinates, and the fragment shader blends in the
+// wireframe color near the edges, anti-aliased with fwidth and of the same width in
+// pixels at any distance. Only the edges of the line list are drawn, so the diagonals
+// of the grid cells stay hidden, as with Lines. The vertices are not shared, so the
+// buffer holds 3 vertices per triangle and is drawn without an index buffer, which
+// also lifts the u16 index limit:
+//
+//     let vertices = wireframe::barycentric_vertices(&output);
+//     let mut ppl = ws::IRenderPipeline {
+//         vertex_buffer_layout: &[BarycentricVertex::layout()],
+//         fs_entry: WireframeStyle::Barycentric.fs_entry(wire_only).to_string(),
+//         ..
+//     };
+//     render_pass.draw(0..vertices.len() as u32, 0..1);
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WireframeStyle {
+    #[default]
+    Lines,
+    Barycentric,
+}
+
+impl WireframeStyle {
+    pub const ALL: [Self; 2] = [Self::Lines, Self::Barycentric];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Lines => "lines",
+            Self::Barycentric => "barycentric",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|style| style.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn next(&self) -> Self {
+        Self::ALL[(*self as usize + 1) % Self::ALL.len()]
+    }
+
+    // fragment entry point of barycentric_wireframe_wgsl: the surface with its
+    // wireframe, or the wireframe alone
+    pub fn fs_entry(&self, wire_only: bool) -> &'static str {
+        if wire_only { "fs_wire" } else { "fs_main" }
+    }
+}
+// endregion: wireframe style
+
+// region: barycentric vertices
+// The chapter vertex (position, normal, color at locations 0 to 2) with the wireframe
+// color at location 3 and the barycentric coordinates at location 4. Colors are
+// linear, like those of the chapter vertices.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct BarycentricVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub color: [f32; 3],
+    pub wire_color: [f32; 3],
+    pub barycentric: [f32; 3],
+}
+
+impl BarycentricVertex {
+    pub const ATTRIBUTES: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+        0 => Float32x3, 1 => Float32x3, 2 => Float32x3, 3 => Float32x3, 4 => Float32x3
+    ];
+
+    pub fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+// Three vertices per triangle of indices, to draw as a non-indexed triangle list. The
+// coordinate of a vertex is 1 for itself and 0 for the other two, so a coordinate
+// goes to 0 along the opposite edge; for the edges not in the line list indices2 it
+// is 1 at all three vertices, which keeps it away from 0 and the edge undrawn.
+pub fn barycentric_vertices(data: &ISurfaceOutput) -> Vec<BarycentricVertex> {
+    let edges: HashSet<(u16, u16)> = data
+        .indices2
+        .chunks_exact(2)
+        .map(|line| (line[0].min(line[1]), line[0].max(line[1])))
+        .collect();
+    let mut vertices = Vec::with_capacity(data.indices.len());
+    for tri in data.indices.chunks_exact(3) {
+        // k: the vertex opposite the edge
+        let hidden = [0, 1, 2].map(|k| {
+            let (a, b) = (tri[(k + 1) % 3], tri[(k + 2) % 3]);
+            !edges.contains(&(a.min(b), a.max(b)))
+        });
+        for (corner, &index) in tri.iter().enumerate() {
+            let i = index as usize;
+            let barycentric = [0, 1, 2].map(|k| if k == corner || hidden[k] { 1.0 } else { 0.0 });
+            vertices.push(BarycentricVertex {
+                position: data.positions[i],
+                normal: data.normals[i],
+                color: color::to_linear(data.colors[i]),
+                wire_color: color::to_linear(data.colors2[i]),
+                barycentric,
+            });
+        }
+    }
+    vertices
+}
+// endregion: barycentric vertices
+
+// region: shaders
+// Vertex and fragment shader of the Barycentric style, with the vertex uniforms at
+// group 0 and the light and material uniforms at group 1 like shader_vert.wgsl and
+// directional_frag.wgsl, so it fits the pipeline layout of the chapters. line_width is
+// in pixels. fs_main lights the surface color blended with the wireframe color at the
+// edges; fs_wire discards the inside of the triangles and returns the edge coverage as
+// alpha, for a pipeline with alpha blending.
+pub fn barycentric_wireframe_wgsl(line_width: f32) -> String {
+    format!(
+        r#"
+const lineWidth = {line_width:?};
+
+struct Uniforms {{
+    vpMat : mat4x4f,
+    modelMat : mat4x4f,
+    normalMat : mat4x4f,
+}};
+@binding(0) @group(0) var<uniform> uniforms : Uniforms;
+
+struct LightUniforms {{
+    lightDirection : vec4f,
+    eyePosition : vec4f,
+    specularColor : vec4f,
+}}
+@group(1) @binding(0) var<uniform> light : LightUniforms;
+
+struct MaterialUniforms {{
+    ambient: f32,
+    diffuse: f32,
+    specular: f32,
+    shininess: f32,
+}}
+@group(1) @binding(1) var<uniform> material : MaterialUniforms;
+
+struct Input {{
+    @location(0) position: vec4f,
+    @location(1) normal: vec4f,
+    @location(2) color: vec4f,
+    @location(3) wireColor: vec4f,
+    @location(4) barycentric: vec3f,
+}}
+
+struct Output {{
+    @builtin(position) position : vec4f,
+    @location(0) vPosition : vec4f,
+    @location(1) vNormal : vec4f,
+    @location(2) vColor: vec4f,
+    @location(3) vWireColor: vec4f,
+    @location(4) vBarycentric: vec3f,
+}};
+
+@vertex
+fn vs_main(in:Input) -> Output {{
+    var output: Output;
+    let mPosition = uniforms.modelMat * in.position;
+    output.vPosition = mPosition;
+    output.vNormal = uniforms.normalMat * in.normal;
+    output.position = uniforms.vpMat * mPosition;
+    output.vColor = in.color;
+    output.vWireColor = in.wireColor;
+    output.vBarycentric = in.barycentric;
+    return output;
+}}
+
+fn blinnPhong(N:vec3f, L:vec3f, V:vec3f) -> vec2f{{
+    let H = normalize(L + V);
+    var diffuse = material.diffuse * max(dot(N, L), 0.0);
+    diffuse += material.diffuse * max(dot(-N, L), 0.0);
+    var specular = material.specular * pow(max(dot(N, H), 0.0), material.shininess);
+    specular += material.specular * pow(max(dot(-N, H),0.0), material.shininess);
+    return vec2(diffuse, specular);
+}}
+
+fn shade(in: Output, base: vec4f) -> vec3f {{
+    let N = normalize(in.vNormal.xyz);
+    let L = normalize(-light.lightDirection.xyz);
+    let V = normalize(light.eyePosition.xyz - in.vPosition.xyz);
+    let bp = blinnPhong(N, L, V);
+    return (base * (material.ambient + bp[0]) + light.specularColor * bp[1]).rgb;
+}}
+
+// 1 on the drawn edges, 0 inside the triangle, a one pixel ramp in between
+fn edge_coverage(barycentric: vec3f) -> f32 {{
+    let d = fwidth(barycentric);
+    let a = smoothstep(d * (0.5 * lineWidth - 0.5), d * (0.5 * lineWidth + 0.5), barycentric);
+    return 1.0 - min(min(a.x, a.y), a.z);
+}}
+
+@fragment
+fn fs_main(in:Output) -> @location(0) vec4f {{
+    let edge = edge_coverage(in.vBarycentric);
+    return vec4f(shade(in, mix(in.vColor, in.vWireColor, edge)), 1.0);
+}}
+
+@fragment
+fn fs_wire(in:Output) -> @location(0) vec4f {{
+    let edge = edge_coverage(in.vBarycentric);
+    if (edge <= 0.0) {{
+        discard;
+    }}
+    return vec4f(shade(in, in.vWireColor), edge);
+}}
+"#
+    )
+}
+// endregion: shaders
@@ -0,0 +1,220 @@
+#![cfg(feature = "low_level")]
+
+use wgpu_surfaces::animation::{
+    Animatable, AnimationCurve, Easing, FrameInterpolator, SharedClock, SurfaceField, Timeline,
+};
+use wgpu_surfaces::surface_data::{IParametricSurface, ISimpleSurface};
+
+fn close(a: f32, b: f32) -> bool {
+    (a - b).abs() < 1e-5
+}
+
+#[test]
+fn curve_holds_the_end_values_and_eases_between_keys() {
+    let curve = AnimationCurve::new()
+        .key(3.0, 10.0, Easing::Linear)
+        .key(1.0, 0.0, Easing::Linear)
+        .key(5.0, 0.0, Easing::EaseInOut);
+    assert_eq!(curve.duration(), 5.0);
+    // before the first and after the last key
+    assert_eq!(curve.sample(-2.0), 0.0);
+    assert_eq!(curve.sample(1.0), 0.0);
+    assert_eq!(curve.sample(7.0), 0.0);
+    // between keys, with the easing of the key the segment ends at
+    assert!(close(curve.sample(2.5), 7.5), "{}", curve.sample(2.5));
+    assert_eq!(curve.sample(3.0), 10.0);
+    assert!(close(curve.sample(4.0), 5.0), "{}", curve.sample(4.0));
+    assert!(curve.sample(3.5) > 7.5);
+
+    assert_eq!(AnimationCurve::new().sample(1.0), 0.0);
+}
+
+#[test]
+fn looping_curve_wraps_negative_and_late_times() {
+    let curve = AnimationCurve::new()
+        .key(1.0, 0.0, Easing::Linear)
+        .key(3.0, 10.0, Easing::Linear)
+        .looping(true);
+    assert!(close(curve.sample(1.5), 2.5));
+    assert!(close(curve.sample(-0.5), 2.5), "{}", curve.sample(-0.5));
+    assert!(close(curve.sample(5.5), 2.5), "{}", curve.sample(5.5));
+    assert!(close(curve.sample(4.0), 5.0), "{}", curve.sample(4.0));
+}
+
+#[test]
+fn step_easing_holds_until_the_next_key() {
+    assert_eq!(Easing::Step.ease(0.0), 0.0);
+    assert_eq!(Easing::Step.ease(0.99), 0.0);
+    assert_eq!(Easing::Step.ease(1.0), 1.0);
+
+    let curve = AnimationCurve::new()
+        .key(0.0, 30.0, Easing::Linear)
+        .key(2.0, 12.0, Easing::Step);
+    assert_eq!(curve.sample(0.5), 30.0);
+    assert_eq!(curve.sample(1.99), 30.0);
+    assert_eq!(curve.sample(2.0), 12.0);
+}
+
+#[test]
+fn timeline_loops_its_tracks_and_reports_new_resolutions() {
+    let mut timeline = Timeline::new();
+    timeline.looping = true;
+    timeline.add_field(
+        SurfaceField::Scale,
+        AnimationCurve::new()
+            .key(0.0, 0.0, Easing::Linear)
+            .key(2.0, 2.0, Easing::Linear),
+    );
+    timeline.add_field(
+        SurfaceField::UResolution,
+        AnimationCurve::new()
+            .key(0.0, 10.0, Easing::Linear)
+            .key(1.0, 20.0, Easing::Step),
+    );
+    assert_eq!(timeline.duration(), 2.0);
+    assert!(timeline.drives(SurfaceField::Scale));
+    assert!(!timeline.drives(SurfaceField::T));
+
+    let mut surface = ISimpleSurface::default();
+    // the first sample changes the grid, the same one again leaves it
+    assert!(timeline.apply(&mut surface, 0.5));
+    assert_eq!(surface.x_resolution, 10);
+    assert!(!timeline.apply(&mut surface, 0.5));
+    assert!(close(surface.scale, 0.5));
+
+    // past the end and before the start wrap around the longest track
+    assert!(timeline.apply(&mut surface, 3.5));
+    assert_eq!(surface.x_resolution, 20);
+    assert!(close(surface.scale, 1.5), "{}", surface.scale);
+    assert!(timeline.apply(&mut surface, -1.5));
+    assert_eq!(surface.x_resolution, 10);
+    assert!(close(surface.scale, 0.5), "{}", surface.scale);
+}
+
+#[test]
+fn set_field_asks_for_new_buffers_only_when_the_grid_changes() {
+    let mut surface = ISimpleSurface::default();
+    assert!(!surface.set_field(SurfaceField::Scale, 2.0));
+    assert!(!surface.set_field(SurfaceField::T, 0.25));
+    assert!(!surface.set_field(SurfaceField::ColormapMax, 0.5));
+    assert_eq!((surface.scale, surface.t), (2.0, 0.25));
+    assert_eq!(surface.colormap_range, Some([-1.0, 0.5]));
+
+    let x_resolution = surface.x_resolution;
+    assert!(!surface.set_field(SurfaceField::UResolution, x_resolution as f32 + 0.2));
+    assert!(surface.set_field(SurfaceField::VResolution, 12.0));
+    assert_eq!(surface.z_resolution, 12);
+
+    // resolutions stay within a grid the u16 indices can address
+    assert!(surface.set_field(SurfaceField::UResolution, 1.0e6));
+    assert_eq!(surface.x_resolution, 255);
+    assert!(!surface.set_field(SurfaceField::UResolution, 300.0));
+    assert!(surface.set_field(SurfaceField::UResolution, 0.0));
+    assert_eq!(surface.x_resolution, 2);
+
+    let mut parametric = IParametricSurface::default();
+    assert!(!parametric.set_field(SurfaceField::TubeRadius, 0.3));
+    assert_eq!(parametric.tube_radius, 0.3);
+    assert!(parametric.set_field(SurfaceField::VResolution, 1.0e6));
+    assert_eq!(parametric.v_resolution, 255);
+}
+
+#[test]
+fn frame_interpolator_mixes_towards_next_frame() {
+    let mut interpolator = FrameInterpolator::default();
+    // nothing to blend before the first frame
+    assert_eq!(interpolator.mix_factor(), 1.0);
+
+    interpolator.push_frame();
+    assert!(interpolator.mix_factor() < 0.5);
+
+    interpolator.interval = 0.01;
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    assert_eq!(interpolator.mix_factor(), 1.0);
+}
+
+#[test]
+fn frame_interpolator_rises_monotonically_over_one_interval() {
+    let mut interpolator = FrameInterpolator::default();
+    assert_eq!(interpolator.mix_factor(), 1.0);
+    // nothing was shown blended before the first frame
+    assert_eq!(interpolator.push_frame(), 1.0);
+
+    interpolator.interval = 0.05;
+    let start = std::time::Instant::now();
+    let mut last = interpolator.mix_factor();
+    while start.elapsed().as_secs_f32() < 0.07 {
+        let mix = interpolator.mix_factor();
+        assert!(mix >= last && mix <= 1.0, "{} after {}", mix, last);
+        last = mix;
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+    // a full interval later the next frame is shown
+    assert!(last > 0.5, "{}", last);
+    assert_eq!(interpolator.mix_factor(), 1.0);
+}
+
+#[test]
+fn frame_interpolator_starts_an_early_frame_from_the_blend_shown() {
+    let mut interpolator = FrameInterpolator::default();
+    interpolator.interval = 10.0;
+    interpolator.push_frame();
+    // the next frame arrives 20 ms into a 10 s interval, with the previous one still shown
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    let shown = interpolator.push_frame();
+    assert!(shown > 0.0 && shown < 0.1, "{}", shown);
+
+    let mut previous = vec![0.0, 1.0, -2.0];
+    let next = [1.0, 1.0, 2.0];
+    FrameInterpolator::blend(&mut previous, &next, 0.25);
+    assert_eq!(previous, [0.25, 1.0, -1.0]);
+    FrameInterpolator::blend(&mut previous, &next, 1.0);
+    assert_eq!(previous, next);
+}
+
+#[test]
+fn frame_interpolator_estimates_interval() {
+    let mut interpolator = FrameInterpolator::default();
+    interpolator.smoothing = 1.0;
+    interpolator.push_frame();
+    std::thread::sleep(std::time::Duration::from_millis(30));
+    interpolator.push_frame();
+    assert!(interpolator.interval > 0.02 && interpolator.interval < 0.5);
+}
+
+#[test]
+fn shared_clock_clones_see_the_same_time() {
+    let clock = SharedClock::new();
+    let view = clock.clone();
+    assert!(view.ptr_eq(&clock));
+    assert!(!SharedClock::new().ptr_eq(&clock));
+
+    clock.pause();
+    clock.set_time(2.0);
+    assert_eq!(view.time(), 2.0);
+    view.seek(-0.5);
+    assert_eq!(clock.time(), 1.5);
+
+    // paused clocks stand still
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    assert_eq!(view.time(), 1.5);
+    assert!(!view.toggle());
+    std::thread::sleep(std::time::Duration::from_millis(40));
+    assert!(clock.time() > 1.5);
+}
+
+#[test]
+fn shared_clock_speed_and_frame_rate() {
+    let clock = SharedClock::new();
+    clock.set_frame_rate(4.0);
+    clock.pause();
+    clock.set_time(1.3);
+    // quantized to quarter seconds
+    assert_eq!(clock.time(), 1.25);
+
+    clock.set_speed(-1.0);
+    clock.play();
+    std::thread::sleep(std::time::Duration::from_millis(300));
+    assert!(clock.time() < 1.25);
+    assert_eq!(clock.speed(), -1.0);
+}
@@ -0,0 +1,97 @@
+use wgpu_surfaces::api::{self, SIMPLE_SURFACE_NAMES};
+use wgpu_surfaces::{
+    Error, Gpu, ParametricSurface, ShadingMode, SimpleSurface, Subsystem, SurfaceStyle,
+};
+
+mod common;
+
+#[test]
+fn builders_generate_meshes_by_name() {
+    let mut style = SurfaceStyle::default();
+    style.colormap = "viridis".to_string();
+    style.shading = ShadingMode::Flat;
+    let mesh = ParametricSurface::new("Torus")
+        .unwrap()
+        .resolution(8, 6)
+        .style(&style)
+        .build();
+    assert_eq!(mesh.triangle_count(), 8 * 6 * 2);
+    assert_eq!(mesh.vertex_count(), 3 * mesh.triangle_count());
+    for attribute in [mesh.normals(), mesh.colors(), mesh.wireframe_colors()] {
+        assert_eq!(attribute.len(), mesh.vertex_count());
+    }
+    assert!(!mesh.wireframe_indices().is_empty());
+
+    for name in api::parametric_surface_names() {
+        assert!(ParametricSurface::new(&name).is_ok(), "{name}");
+    }
+    for name in SIMPLE_SURFACE_NAMES {
+        let mesh = SimpleSurface::new(name).unwrap().resolution(4, 4).build();
+        assert_eq!(mesh.vertex_count(), 25);
+    }
+    match SimpleSurface::new("saddle") {
+        Err(Error::UnknownSurface(name)) => assert_eq!(name, "saddle"),
+        _ => panic!("expected Error::UnknownSurface"),
+    }
+}
+
+#[test]
+fn user_functions_follow_the_time_parameter() {
+    let mut wave = SimpleSurface::from_fn(|x, z, t| (x + z + t).sin())
+        .domain([0.0, 1.0, 0.0, 1.0])
+        .resolution(3, 3);
+    let before = wave.build();
+    wave.set_time(0.5);
+    assert_eq!(wave.time(), 0.5);
+    let after = wave.build();
+    assert_eq!(before.vertex_count(), after.vertex_count());
+    assert_ne!(before.colors(), after.colors());
+}
+
+#[test]
+fn the_headless_gpu_is_read_through_getters() {
+    let Some(mut gpu) = common::gpu(pollster::block_on(Gpu::headless(16, 8, 1))) else {
+        return;
+    };
+    assert_eq!(gpu.size(), [16, 8]);
+    gpu.resize(4, 4);
+    assert_eq!(gpu.size(), [4, 4]);
+
+    let caps = gpu.capabilities();
+    for subsystem in Subsystem::ALL {
+        let enabled = gpu.is_enabled(subsystem);
+        assert_eq!(caps.is_enabled(subsystem), enabled);
+        assert_eq!(caps.require(subsystem).is_ok(), enabled);
+        assert_eq!(caps.disabled().contains(&subsystem), !enabled);
+        assert_eq!(caps.missing(subsystem).is_empty(), enabled);
+    }
+    assert_eq!(caps.report().lines().count(), caps.disabled().len());
+}
+
+#[cfg(feature = "low_level")]
+#[test]
+fn the_headless_gpu_renders_through_the_low_level_context() {
+    let Some(gpu) = common::gpu(pollster::block_on(Gpu::headless(4, 4, 1))) else {
+        return;
+    };
+    let init = gpu.init();
+    let mut encoder = init
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: &init.view,
+            depth_slice: None,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::RED),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        ..Default::default()
+    });
+    init.queue.submit(std::iter::once(encoder.finish()));
+    let rgba = gpu.read_rgba().unwrap();
+    assert_eq!(rgba.len(), 4 * 4 * 4);
+    assert_eq!(&rgba[..4], &[255, 0, 0, 255]);
+}
@@ -0,0 +1,242 @@
+#![cfg(feature = "low_level")]
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wgpu_surfaces::app::{self, Action, InputMap};
+use wgpu_surfaces::cli::SurfaceArgs;
+use wgpu_surfaces::wgpu_simplified as ws;
+use wgpu_surfaces::{Error, KeymapError};
+use winit::keyboard::{Key, NamedKey};
+
+mod common;
+
+const KEYS: &[(Action, &str)] = &[
+    (Action::CyclePlotType, "Space"),
+    (Action::IncreaseRotationSpeed, "r"),
+    (Action::DecreaseRotationSpeed, "f"),
+    (Action::ToggleCrossFade, "x"),
+];
+
+fn key(name: &str) -> Key {
+    app::parse_key(name).unwrap()
+}
+
+#[test]
+fn actions_and_keys_have_names() {
+    for action in Action::ALL {
+        assert_eq!(Action::from_name(action.name()), Some(action));
+    }
+    assert_eq!(
+        Action::from_name("cycle_plot_type"),
+        Some(Action::CyclePlotType)
+    );
+    assert_eq!(Action::from_name("CyclePlotType"), None);
+
+    assert_eq!(key("q"), Key::Character("q".into()));
+    assert_eq!(key("PageUp"), Key::Named(NamedKey::PageUp));
+    assert_eq!(app::parse_key("Escape"), None);
+    assert_eq!(app::parse_key(""), None);
+    for name in ["Space", "ArrowLeft", "F3", "q", "+"] {
+        assert_eq!(app::key_name(&key(name)), name);
+    }
+}
+
+#[test]
+fn keymaps_override_the_default_bindings() {
+    let mut map = InputMap::new(KEYS);
+    assert_eq!(map.action(&key("r")), Some(Action::IncreaseRotationSpeed));
+    assert_eq!(map.action(&key("q")), None);
+
+    // a key moves to the action that takes it, actions the map lacks are left out
+    map.apply_toml(
+        r#"
+        increase_rotation_speed = ["x", "PageUp"]
+        decrease_rotation_speed = "PageDown"
+        toggle_culling = "c"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(map.action(&key("x")), Some(Action::IncreaseRotationSpeed));
+    assert_eq!(
+        map.action(&key("PageUp")),
+        Some(Action::IncreaseRotationSpeed)
+    );
+    assert_eq!(map.action(&key("r")), None);
+    assert_eq!(map.action(&key("f")), None);
+    assert_eq!(map.keys(Action::ToggleCrossFade).count(), 0);
+    assert_eq!(map.action(&key("c")), None);
+    assert_eq!(
+        map.describe(),
+        "Space: cycle_plot_type\nx: increase_rotation_speed\n\
+         PageUp: increase_rotation_speed\nPageDown: decrease_rotation_speed"
+    );
+
+    for toml in [
+        "spin_faster = \"r\"",
+        "cycle_plot_type = \"Escape\"",
+        "cycle_plot_type = 3",
+        "cycle_plot_type = ",
+        "cycle_plot_type = \"Tab\"\nspin_faster = \"r\"",
+    ] {
+        let mut map = InputMap::new(KEYS);
+        assert!(map.apply_toml(toml).is_err(), "{} was accepted", toml);
+        assert_eq!(map, InputMap::new(KEYS), "{} changed the map", toml);
+    }
+
+    // the error says which part of the keymap was wrong
+    let mut map = InputMap::new(KEYS);
+    let error = map.apply_toml("spin_faster = \"r\"").unwrap_err();
+    let Error::Keymap(KeymapError::UnknownAction(name)) = error else {
+        panic!("{:?} is not an unknown action", error);
+    };
+    assert_eq!(name, "spin_faster");
+    let error = map.apply_toml("cycle_plot_type = \"Escape\"").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "keymap: unknown key 'Escape' for cycle_plot_type"
+    );
+    let error = map.apply_toml("cycle_plot_type = ").unwrap_err();
+    assert!(matches!(error, Error::Keymap(KeymapError::Parse(_))));
+}
+
+#[test]
+fn keymap_files_come_from_the_arguments() {
+    let path = std::env::temp_dir().join("wgpu_surfaces_keymap.toml");
+    std::fs::write(&path, "cycle_plot_type = \"Tab\"\n").unwrap();
+    let args = SurfaceArgs::default()
+        .parse(["--keymap".to_string(), path.display().to_string()])
+        .unwrap();
+    let map = InputMap::with_args(KEYS, &args).unwrap();
+    assert_eq!(map.action(&key("Tab")), Some(Action::CyclePlotType));
+    std::fs::remove_file(&path).unwrap();
+    let error = InputMap::with_args(KEYS, &args).unwrap_err();
+    assert!(matches!(error, Error::Keymap(KeymapError::Read { .. })));
+    let source = std::error::Error::source(&error).unwrap();
+    assert!(source.downcast_ref::<KeymapError>().is_some());
+    assert_eq!(
+        InputMap::with_args(KEYS, &SurfaceArgs::default()).unwrap(),
+        InputMap::new(KEYS)
+    );
+}
+
+// an empty pass that clears the frame to color
+fn clear_pass(encoder: &mut wgpu::CommandEncoder, targets: &ws::FrameTargets, color: wgpu::Color) {
+    let mut attachment = targets.load_color_attachment();
+    attachment.ops.load = wgpu::LoadOp::Clear(color);
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: None,
+        color_attachments: &[Some(attachment)],
+        depth_stencil_attachment: None,
+        occlusion_query_set: None,
+        timestamp_writes: None,
+    });
+}
+
+#[test]
+fn render_hooks_run_in_order_around_the_surface_pass() {
+    let Some(init) = common::headless(2, 1, 1) else {
+        return;
+    };
+    let calls = Rc::new(RefCell::new(Vec::new()));
+    let mut hooks = ws::RenderHooks::default();
+    let hooks_added = [
+        ("pre 1", false, wgpu::Color::RED),
+        ("post 1", true, wgpu::Color::BLUE),
+        ("pre 2", false, wgpu::Color::RED),
+        ("post 2", true, wgpu::Color::GREEN),
+    ];
+    for (name, post, color) in hooks_added {
+        let calls = calls.clone();
+        let hook = move |encoder: &mut wgpu::CommandEncoder, targets: &ws::FrameTargets| {
+            assert_eq!([targets.width, targets.height], [2, 1]);
+            assert!(targets.msaa_view.is_none());
+            calls.borrow_mut().push(name);
+            clear_pass(encoder, targets, color);
+        };
+        if post {
+            hooks.add_post_pass(hook);
+        } else {
+            hooks.add_pre_pass(hook);
+        }
+    }
+
+    let render_targets = app::RenderTargets::new(&init);
+    let mut encoder = init
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    let targets = ws::FrameTargets::new(
+        &init,
+        &init.view,
+        &render_targets.msaa_texture_view,
+        &render_targets.depth_texture_view,
+    );
+    hooks.run_pre_passes(&mut encoder, &targets);
+    calls.borrow_mut().push("surface");
+    drop(render_targets.begin_pass(&mut encoder, &init, &init.view));
+    hooks.run_post_passes(&mut encoder, &targets);
+    init.queue.submit(std::iter::once(encoder.finish()));
+
+    assert_eq!(
+        *calls.borrow(),
+        ["pre 1", "pre 2", "surface", "post 1", "post 2"]
+    );
+    // the surface pass clears the pre passes away, the last post pass stays
+    assert_eq!(init.read_rgba().unwrap(), [0, 255, 0, 255, 0, 255, 0, 255]);
+
+    // cleared hooks are not called again
+    hooks.clear();
+    let mut encoder = init
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    hooks.run_pre_passes(&mut encoder, &targets);
+    hooks.run_post_passes(&mut encoder, &targets);
+    assert_eq!(calls.borrow().len(), 5);
+}
+
+#[test]
+fn frame_pacer_renders_continuously_while_focused() {
+    let mut pacer = ws::FramePacer::default();
+    assert!(!pacer.is_power_saving());
+    assert_eq!(pacer.next_frame_time(), None);
+    assert!(pacer.next_frame());
+    assert!(pacer.next_frame());
+    assert!(!pacer.is_idle());
+}
+
+#[test]
+fn frame_pacer_saves_power_while_unfocused_or_occluded() {
+    let mut pacer = ws::FramePacer::default();
+    pacer.set_occluded(true);
+    assert!(pacer.is_power_saving());
+    pacer.set_occluded(false);
+    pacer.set_focused(false);
+    assert!(pacer.is_power_saving());
+    pacer.idle_fps = 10;
+    let interval = std::time::Duration::from_millis(120);
+
+    // the pending first frame once the interval is over, then nothing until
+    // something changes
+    std::thread::sleep(interval);
+    assert!(pacer.next_frame());
+    let due = pacer.next_frame_time().unwrap();
+    assert!(due > std::time::Instant::now());
+    assert!(pacer.is_idle());
+    std::thread::sleep(interval);
+    assert!(!pacer.next_frame());
+
+    // a change is drawn, at most idle_fps times a second
+    pacer.invalidate();
+    assert!(!pacer.is_idle());
+    assert!(pacer.next_frame());
+    pacer.invalidate();
+    assert!(!pacer.next_frame());
+    std::thread::sleep(interval);
+    assert!(pacer.next_frame());
+    assert!(pacer.is_idle());
+
+    // back in focus, every frame is due again
+    pacer.set_focused(true);
+    assert_eq!(pacer.next_frame_time(), None);
+    assert!(pacer.next_frame() && pacer.next_frame());
+}
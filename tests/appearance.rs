@@ -0,0 +1,94 @@
+#![cfg(feature = "low_level")]
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wgpu_surfaces::appearance::{self, Appearance, AppearanceChange, DirectionalLight};
+use wgpu_surfaces::colormap::{self, Interpolation};
+use wgpu_surfaces::material::{Material, MaterialKind};
+use wgpu_surfaces::params;
+
+mod common;
+
+#[test]
+fn material_sliders_round_trip_the_uniform_values() {
+    for kind in MaterialKind::ALL {
+        let material = kind.default_material();
+        let data = material.uniform_data();
+        assert_eq!(Material::from_uniform_data(kind, data), material);
+
+        let mut params = params::material_params(kind);
+        assert_eq!(params.params.len(), 4);
+        let mut tuned = material;
+        params.select_next();
+        assert!(!params.nudge_selected(&mut tuned, 2.0));
+        assert_eq!(tuned.kind(), kind);
+        let tuned_data = tuned.uniform_data();
+        assert!((tuned_data[1] - (data[1] + 0.1).min(1.0)).abs() < 1e-6);
+        assert_eq!([tuned_data[0], tuned_data[2], tuned_data[3]], [data[0], data[2], data[3]]);
+    }
+}
+
+#[test]
+fn light_and_colormap_data_have_the_gpu_layout() {
+    let light = DirectionalLight {
+        direction: [1.0, 2.0, 3.0],
+        eye_position: [4.0, 5.0, 6.0],
+        specular_color: [7.0, 8.0, 9.0],
+    };
+    let data = light.uniform_data();
+    assert_eq!(std::mem::size_of_val(&data) as u64, appearance::LIGHT_UNIFORM_SIZE);
+    assert_eq!(data, [1.0, 2.0, 3.0, 0.0, 4.0, 5.0, 6.0, 0.0, 7.0, 8.0, 9.0, 0.0]);
+
+    let texels = appearance::colormap_rgba8("hot", 4);
+    let hot = colormap::lookup("hot", Interpolation::Linear);
+    let first = hot.color(0.0, 1.0, 0.0).map(|c| (c * 255.0).round() as u8);
+    assert_eq!(texels.len(), 16);
+    assert_eq!(texels[..4], [first[0], first[1], first[2], 255]);
+}
+
+#[test]
+fn changes_write_buffers_and_notify_the_hooks() {
+    let Some(init) = common::headless(4, 4, 1) else {
+        return;
+    };
+    let phong = MaterialKind::Phong.default_material();
+    let mut appearance =
+        Appearance::new(&init.device, &init.queue, DirectionalLight::default(), phong, "jet");
+    let changes = Rc::new(RefCell::new(vec![]));
+    let seen = changes.clone();
+    appearance.hooks.add(move |change| seen.borrow_mut().push(change.clone()));
+    assert!(appearance.bind_group(&init.device).is_ok());
+
+    let light = DirectionalLight {
+        direction: [0.0, -1.0, 0.0],
+        ..appearance.light()
+    };
+    appearance.set_light(&init.queue, light);
+    appearance.set_eye_position(&init.queue, [1.0, 2.0, 3.0]);
+    assert_eq!(appearance.light().eye_position, [1.0, 2.0, 3.0]);
+
+    let shiny = Material::Phong {
+        ambient: 0.1,
+        diffuse: 0.7,
+        specular: 0.9,
+        shininess: 90.0,
+    };
+    appearance.set_material(&init.queue, shiny).unwrap();
+    // another kind needs another fragment shader
+    let pbr = MaterialKind::Pbr.default_material();
+    assert!(appearance.set_material(&init.queue, pbr).is_err());
+    assert_eq!(appearance.material(), shiny);
+    appearance.set_colormap(&init.queue, "cool");
+    init.device.poll(wgpu::PollType::Wait).unwrap();
+
+    assert_eq!(
+        *changes.borrow(),
+        [
+            AppearanceChange::Light(light),
+            AppearanceChange::Material(shiny),
+            AppearanceChange::Colormap("cool".to_string()),
+        ]
+    );
+    assert_eq!(changes.borrow()[2].to_string(), "colormap: cool");
+}
@@ -0,0 +1,101 @@
+#![cfg(feature = "low_level")]
+
+use std::time::Duration;
+
+use wgpu_surfaces::benchmark::{self, FrameTimeStats, IBenchmarkResult};
+
+fn result(name: &str, elapsed: Result<u64, &str>) -> IBenchmarkResult {
+    IBenchmarkResult {
+        adapter: wgpu::AdapterInfo {
+            name: name.into(),
+            vendor: 0,
+            device: 0,
+            device_type: wgpu::DeviceType::Cpu,
+            driver: String::new(),
+            driver_info: String::new(),
+            backend: wgpu::Backend::Noop,
+        },
+        frames: 2,
+        elapsed: elapsed.map(Duration::from_millis).map_err(String::from),
+        frame_times: match elapsed {
+            Ok(ms) => vec![Duration::from_millis(ms / 2); 2],
+            Err(_) => vec![],
+        },
+    }
+}
+
+#[test]
+fn frame_stats_of_fixed_samples() {
+    // 1 to 100 ms in shuffled order
+    let samples: Vec<f64> = (0..100).map(|i| ((i * 37) % 100 + 1) as f64).collect();
+    let stats = FrameTimeStats::from_samples(&samples).unwrap();
+    assert_eq!(
+        stats,
+        FrameTimeStats {
+            min: 1.0,
+            mean: 50.5,
+            p50: 50.0,
+            p95: 95.0,
+            p99: 99.0,
+            max: 100.0,
+        }
+    );
+
+    // with few samples the percentiles take the nearest rank above
+    let stats = FrameTimeStats::from_samples(&[4.0, 1.0, 3.0, 2.0]).unwrap();
+    assert_eq!([stats.min, stats.mean, stats.max], [1.0, 2.5, 4.0]);
+    assert_eq!([stats.p50, stats.p95, stats.p99], [2.0, 4.0, 4.0]);
+
+    let stats = FrameTimeStats::from_samples(&[7.0]).unwrap();
+    assert_eq!([stats.min, stats.p50, stats.p99, stats.max], [7.0; 4]);
+    assert_eq!(FrameTimeStats::from_samples(&[]), None);
+}
+
+#[test]
+fn benchmark_result_reports_milliseconds() {
+    let frame_times = [2, 4, 6, 8].map(Duration::from_millis).to_vec();
+    let result = IBenchmarkResult {
+        adapter: wgpu::AdapterInfo {
+            name: "test".into(),
+            vendor: 0,
+            device: 0,
+            device_type: wgpu::DeviceType::Cpu,
+            driver: String::new(),
+            driver_info: String::new(),
+            backend: wgpu::Backend::Noop,
+        },
+        frames: 4,
+        elapsed: Ok(Duration::from_millis(20)),
+        frame_times,
+    };
+    assert_eq!(result.ms_per_frame(), Some(5.0));
+    let stats = result.stats().unwrap();
+    assert_eq!(
+        [stats.min, stats.mean, stats.p50, stats.max],
+        [2.0, 5.0, 4.0, 8.0]
+    );
+
+    let failed = IBenchmarkResult {
+        elapsed: Err("no device".into()),
+        frame_times: vec![],
+        ..result
+    };
+    assert_eq!(failed.ms_per_frame(), None);
+    assert_eq!(failed.stats(), None);
+}
+
+#[test]
+fn results_table_lists_the_fastest_adapter_first() {
+    let results = [
+        result("slow", Ok(40)),
+        result("broken", Err("no device")),
+        result("fast", Ok(10)),
+    ];
+    let table = benchmark::results_table(&results);
+    let rows: Vec<&str> = table.lines().collect();
+    assert_eq!(rows.len(), 4);
+    assert!(rows[0].contains("ms/frame") && rows[0].contains("p99"));
+    assert!(rows[1].contains("fast") && rows[1].contains("5.000"), "{}", rows[1]);
+    assert!(rows[2].contains("slow") && rows[2].contains("20.000"), "{}", rows[2]);
+    assert!(rows[3].contains("broken") && rows[3].ends_with("failed: no device"));
+}
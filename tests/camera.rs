@@ -0,0 +1,45 @@
+#![cfg(feature = "low_level")]
+
+use cgmath::{InnerSpace, Point3};
+use wgpu_surfaces::wgpu_simplified as ws;
+
+fn close(a: Point3<f32>, b: Point3<f32>) -> bool {
+    (a - b).magnitude() < 1e-4
+}
+
+#[test]
+fn orbit_camera_starts_at_the_given_eye() {
+    let eye = Point3::new(2.0, 3.0, -1.0);
+    let target = Point3::new(0.5, 0.0, 0.5);
+    let camera = ws::OrbitCamera::new(eye, target);
+    assert!(close(camera.eye(), eye));
+    let view_mat = ws::create_view_mat(eye, target, cgmath::Vector3::unit_y());
+    let diff = camera.view_mat() - view_mat;
+    assert!(
+        diff.x.magnitude() + diff.y.magnitude() + diff.z.magnitude() + diff.w.magnitude() < 1e-4
+    );
+}
+
+#[test]
+fn orbit_camera_rotates_zooms_and_pans() {
+    let mut camera = ws::OrbitCamera::new((4.0, 4.0, 4.0).into(), (0.0, 0.0, 0.0).into());
+    let distance = camera.distance;
+
+    // rotating keeps the distance and never flips over the poles
+    camera.rotate(150.0, 1000.0);
+    assert!((camera.eye() - camera.target).magnitude() - distance < 1e-4);
+    assert!(camera.eye().y < distance && camera.pitch < std::f32::consts::FRAC_PI_2);
+
+    camera.zoom(1.0);
+    assert!(camera.distance < distance);
+    camera.zoom(-1000.0);
+    assert_eq!(camera.distance, camera.max_distance);
+
+    // panning moves eye and target together, across the view direction
+    let (eye, target) = (camera.eye(), camera.target);
+    camera.pan(30.0, -20.0);
+    let shift = camera.target - target;
+    assert!(shift.magnitude() > 0.0);
+    assert!(close(camera.eye(), eye + shift));
+    assert!(shift.dot(target - eye).abs() < 1e-3);
+}
@@ -0,0 +1,71 @@
+#![cfg(feature = "low_level")]
+
+use wgpu_surfaces::Error;
+use wgpu_surfaces::capabilities::{Capabilities, Subsystem};
+
+mod common;
+
+#[test]
+fn missing_features_disable_their_subsystems() {
+    // a WebGL2-like adapter: no compute shaders, no optional features but MSAA counts
+    let features = wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES;
+    let flags = wgpu::DownlevelFlags::empty();
+    let caps = Capabilities::negotiate(&Subsystem::ALL, features, flags);
+    assert_eq!(caps.granted, features);
+    assert!(caps.requested.contains(wgpu::Features::TIMESTAMP_QUERY));
+    assert!(caps.is_enabled(Subsystem::AdapterSampleCounts));
+    for subsystem in [Subsystem::GpuSurfaces, Subsystem::TimestampProfiling] {
+        assert!(!caps.is_enabled(subsystem));
+    }
+    assert_eq!(caps.disabled.len(), Subsystem::ALL.len() - 1);
+    assert!(
+        caps.report()
+            .contains("gpu_surfaces disabled: the GPU lacks COMPUTE_SHADERS")
+    );
+
+    match caps.require(Subsystem::TimestampProfiling) {
+        Err(Error::Unsupported(disabled)) => {
+            assert_eq!(disabled.missing_features, wgpu::Features::TIMESTAMP_QUERY);
+            assert_eq!(
+                disabled.to_string(),
+                "timestamp_profiling disabled: the GPU lacks TIMESTAMP_QUERY"
+            );
+        }
+        other => panic!("expected Error::Unsupported, got {:?}", other),
+    }
+
+    // features no subsystem asked for are not granted
+    let caps = Capabilities::negotiate(&[], wgpu::Features::all(), wgpu::DownlevelFlags::all());
+    assert!(caps.granted.is_empty() && caps.disabled.is_empty());
+    assert!(!caps.is_enabled(Subsystem::PushConstants));
+    assert!(caps.is_enabled(Subsystem::GpuSurfaces)); // needs a flag, not a feature
+    assert_eq!(
+        Subsystem::from_name("Line_Wireframe"),
+        Some(Subsystem::LineWireframe)
+    );
+}
+
+#[test]
+fn the_device_gets_the_granted_features() {
+    let Some(init) = common::headless(1, 1, 1) else {
+        return;
+    };
+    let caps = &init.capabilities;
+    assert!(init.device.features().contains(caps.granted));
+    assert!(
+        caps.granted
+            .contains(caps.requested & init.adapter.features())
+    );
+    for subsystem in Subsystem::ALL {
+        let disabled = caps.disabled.iter().any(|d| d.subsystem == subsystem);
+        assert_eq!(
+            caps.is_enabled(subsystem),
+            !disabled,
+            "{}",
+            subsystem.name()
+        );
+    }
+    if caps.is_enabled(Subsystem::PushConstants) {
+        assert!(init.device.limits().max_push_constant_size > 0);
+    }
+}
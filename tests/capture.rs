@@ -0,0 +1,139 @@
+#![cfg(feature = "low_level")]
+
+use std::path::Path;
+use std::time::Duration;
+
+use cgmath::Vector4;
+use wgpu_surfaces::capture::{self, Accumulator, VideoExport, jitter_mat, jitter_offsets};
+use wgpu_surfaces::material::MaterialKind;
+use wgpu_surfaces::playlist::{IPlaylist, IScene, PlaylistRunner};
+use wgpu_surfaces::surface_data::IParametricSurface;
+
+mod common;
+
+#[test]
+fn jitter_offsets_cover_the_pixel() {
+    let offsets = jitter_offsets(16);
+    assert_eq!(offsets[0], [0.0, 0.0]);
+    assert!(offsets.iter().flatten().all(|c| (-0.5..0.5).contains(c)));
+    // every quadrant of the pixel gets samples
+    for (sx, sy) in [(1.0, 1.0), (1.0, -1.0), (-1.0, 1.0), (-1.0, -1.0)] {
+        assert!(offsets.iter().any(|[x, y]| x * sx > 0.0 && y * sy > 0.0));
+    }
+}
+
+#[test]
+fn jitter_mat_shifts_by_pixels() {
+    // a point in clip space with w = 2 moves by the offset in pixels after division by w
+    let p = jitter_mat([0.5, 0.25], 100, 50) * Vector4::new(0.0, 0.0, 0.5, 2.0);
+    assert!((p.x / p.w * 50.0 - 0.5).abs() < 1e-6);
+    assert!((p.y / p.w * 25.0 + 0.25).abs() < 1e-6);
+    assert_eq!(p.z, 0.5);
+}
+
+#[test]
+fn accumulation_averages_in_linear_space() {
+    let mut acc = Accumulator::new(2, 1);
+    acc.add(&[0, 0, 0, 255, 10, 20, 30, 0]).unwrap();
+    acc.add(&[255, 255, 255, 255, 10, 20, 30, 255]).unwrap();
+    assert_eq!(acc.frames(), 2);
+    assert!(acc.add(&[0; 4]).is_err());
+
+    let rgba = acc.resolve();
+    // half of white in linear light is sRGB 188, not 128
+    assert_eq!(&rgba[..4], &[188, 188, 188, 255]);
+    // identical colors stay as they are, alpha is averaged linearly
+    assert_eq!(&rgba[4..], &[10, 20, 30, 128]);
+}
+
+#[test]
+fn video_export_steps_a_fixed_dt_per_frame() {
+    let mut video = VideoExport::for_duration("frames", 30.0, 2.0);
+    assert_eq!((video.frames, video.dt()), (60, 1.0 / 30.0));
+    assert_eq!(
+        video.frame_path(),
+        Path::new("frames").join("frame_00000.png")
+    );
+    for _ in 0..45 {
+        assert!(video.advance());
+    }
+    assert_eq!(video.time(), Duration::from_secs_f64(1.5));
+    while video.advance() {}
+    assert!(video.is_finished());
+    assert_eq!(video.frame(), 60);
+    assert_eq!(VideoExport::for_duration("frames", 0.0, 0.0).frames, 1);
+}
+
+#[test]
+fn recorded_playlists_run_on_the_frame_clock() {
+    let playlist = IPlaylist {
+        scenes: vec![IScene {
+            duration: 0.05,
+            ..Default::default()
+        }],
+        record_dir: Some("frames".to_string()),
+        record_frame_rate: 40.0,
+        ..Default::default()
+    };
+    let mut runner = PlaylistRunner::new(playlist.clone());
+    let wall = Duration::from_secs(3);
+    let mut times = vec![];
+    while let Some(path) = {
+        times.push(runner.clock(wall));
+        runner.next_frame_path()
+    } {
+        assert!(path.starts_with("frames"));
+    }
+    // two frames 25 ms apart, then the wall clock again
+    let ms = |t: &Duration| t.as_millis();
+    assert_eq!(times.iter().map(ms).collect::<Vec<_>>(), [0, 25, 3000]);
+
+    let unrecorded = IPlaylist {
+        record_dir: None,
+        ..playlist
+    };
+    let mut runner = PlaylistRunner::new(unrecorded);
+    assert_eq!(runner.clock(wall), wall);
+    assert!(runner.next_frame_path().is_none());
+}
+
+#[test]
+fn unpremultiply_restores_straight_alpha() {
+    // half covered white over transparent black resolves to the sRGB of linear 0.5
+    let mut rgba = vec![188, 188, 188, 128, 255, 0, 0, 255, 7, 7, 7, 0];
+    capture::unpremultiply(&mut rgba);
+    assert!(rgba[..3].iter().all(|&c| c >= 254), "{:?}", rgba);
+    assert_eq!(rgba[3], 128);
+    assert_eq!(rgba[4..], [255, 0, 0, 255, 0, 0, 0, 0]);
+}
+
+#[test]
+fn previews_frame_the_mesh() {
+    let Some(init) = common::headless(64, 48, 1) else {
+        return;
+    };
+    let mut output = IParametricSurface {
+        surface_type: 21,
+        u_resolution: 16,
+        v_resolution: 12,
+        ..Default::default()
+    }
+    .new();
+    // far from the origin, and still in view
+    for p in output.positions.iter_mut() {
+        p[0] += 50.0;
+    }
+    let rgba = capture::render_preview(&init, &output, MaterialKind::Phong, true).unwrap();
+    assert_eq!(rgba.len(), 4 * 64 * 48);
+    let background = [rgba[0], rgba[1], rgba[2]];
+    let covered = rgba
+        .chunks_exact(4)
+        .filter(|px| px[..3] != background)
+        .count();
+    assert!(covered > 64 * 48 / 10, "{covered}");
+    // the bounding sphere fits, so the corners stay empty
+    assert_eq!(&rgba[4 * (64 * 48 - 1)..][..3], &background);
+
+    output.positions.clear();
+    assert!(capture::render_preview(&init, &output, MaterialKind::Phong, false).is_err());
+}
@@ -0,0 +1,101 @@
+#![cfg(feature = "low_level")]
+
+use wgpu::PresentMode;
+use wgpu_surfaces::cli::SurfaceArgs;
+use wgpu_surfaces::theme::Theme;
+use wgpu_surfaces::wgpu_simplified::PresentModePreference;
+
+fn parse(defaults: SurfaceArgs, args: &str) -> anyhow::Result<SurfaceArgs> {
+    defaults.parse(args.split_whitespace().map(String::from))
+}
+
+#[test]
+fn parses_named_flags_and_positional_arguments() {
+    let args = parse(
+        SurfaceArgs::default().surface_types(3),
+        "--msaa 4 grid --colormap=hot --wireframe-color black --resolution 100 --surface-type 2 -- --x",
+    )
+    .unwrap();
+    assert_eq!(args.msaa, 4);
+    assert_eq!(args.colormap, "hot");
+    assert_eq!(args.wireframe_color, "black");
+    assert_eq!(args.resolution, Some(100));
+    assert_eq!(args.surface_type, Some(2));
+    assert_eq!(args.positional, vec!["grid", "--x"]);
+
+    let args = parse(SurfaceArgs::default(), "--decimals 1 --decimal-separator ,").unwrap();
+    assert_eq!(args.number_format.format(2.25), "2,2");
+    let args = parse(
+        SurfaceArgs::default(),
+        "--theme high_contrast --ui-scale 1.5",
+    )
+    .unwrap();
+    assert_eq!((args.theme, args.ui_scale), (Theme::HighContrast, 1.5));
+    let args = parse(
+        SurfaceArgs::default(),
+        "--seed 42 --recovery-file crash.ron",
+    )
+    .unwrap();
+    assert_eq!(args.seed, Some(42));
+    assert_eq!(args.recovery_file.as_deref(), Some("crash.ron"));
+
+    let defaults = parse(SurfaceArgs::default().colormap("terrain"), "").unwrap();
+    assert_eq!((defaults.msaa, defaults.colormap.as_str()), (1, "terrain"));
+    assert_eq!((defaults.resolution, defaults.surface_type), (None, None));
+}
+
+#[test]
+fn rejects_invalid_arguments() {
+    for args in [
+        "--msaa 3",
+        "--msaa four",
+        "--colormap nope",
+        "--decimals 10",
+        "--decimal-separator ;",
+        "--theme dark",
+        "--ui-scale 0.1",
+        "--seed -1",
+        "--resolution 1",
+        "--resolution 251",
+        "--surface-type 3",
+        "--unknown 1",
+        "--colormap",
+    ] {
+        let parsed = parse(SurfaceArgs::default().surface_types(3), args);
+        assert!(parsed.is_err(), "{} was accepted", args);
+    }
+    // binaries without a choice of surfaces do not take --surface-type
+    assert!(parse(SurfaceArgs::default(), "--surface-type 0").is_err());
+
+    let usage = SurfaceArgs::default().usage("simple_surface", "");
+    assert!(usage.contains("--wireframe-color") && !usage.contains("--surface-type"));
+}
+
+#[test]
+fn present_modes_parse_and_fall_back_to_supported_ones() {
+    let args = parse(SurfaceArgs::default(), "--present-mode Mailbox").unwrap();
+    assert_eq!(args.present_mode, PresentModePreference::Mailbox);
+    assert_eq!(
+        SurfaceArgs::default().present_mode,
+        PresentModePreference::Fifo
+    );
+    assert!(parse(SurfaceArgs::default(), "--present-mode none").is_err());
+
+    let fifo_only = [PresentMode::Fifo];
+    let immediate = [PresentMode::Fifo, PresentMode::Immediate];
+    for preference in PresentModePreference::ALL {
+        assert_eq!(preference.resolve(&fifo_only), PresentMode::Fifo);
+    }
+    assert_eq!(
+        PresentModePreference::AutoNoVsync.resolve(&immediate),
+        PresentMode::Immediate
+    );
+    assert_eq!(
+        PresentModePreference::Mailbox.resolve(&immediate),
+        PresentMode::Fifo
+    );
+    assert_eq!(
+        PresentModePreference::AutoNoVsync.next(),
+        PresentModePreference::Immediate
+    );
+}
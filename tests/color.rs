@@ -0,0 +1,99 @@
+#![cfg(feature = "low_level")]
+
+use wgpu_surfaces::color::{self, OutputEncoding};
+use wgpu_surfaces::wgpu_simplified::{self as ws, RenderTarget};
+
+mod common;
+
+#[test]
+fn srgb_and_linear_round_trip() {
+    assert!((color::srgb_to_linear(0.5) - 0.214).abs() < 1e-3);
+    assert!((color::linear_to_srgb(0.214) - 0.5).abs() < 1e-3);
+    for i in 0..=100 {
+        let c = i as f32 / 100.0;
+        assert!((color::linear_to_srgb(color::srgb_to_linear(c)) - c).abs() < 1e-5);
+    }
+    assert_eq!(color::to_linear([0.0, 1.0, 0.0]), [0.0, 1.0, 0.0]);
+}
+
+#[test]
+fn output_depends_on_the_target_format() {
+    use wgpu::TextureFormat as F;
+    assert_eq!(
+        color::output_encoding(F::Rgba8UnormSrgb),
+        OutputEncoding::Hardware
+    );
+    assert_eq!(
+        color::output_encoding(F::Bgra8Unorm),
+        OutputEncoding::Shader
+    );
+    assert_eq!(
+        color::output_encoding(F::Rgba16Float),
+        OutputEncoding::Linear
+    );
+
+    let linear = color::to_linear([0.5, 0.25, 1.0]);
+    assert_eq!(color::output_color(linear, F::Bgra8UnormSrgb), linear);
+    assert_eq!(color::output_color(linear, F::Rgba16Float), linear);
+    let encoded = color::output_color(linear, F::Rgba8Unorm);
+    assert!(
+        encoded
+            .iter()
+            .zip([0.5, 0.25, 1.0])
+            .all(|(a, b)| (a - b).abs() < 1e-5)
+    );
+
+    let clear = color::clear_color([0.5, 0.5, 0.5], F::Bgra8UnormSrgb);
+    assert!((clear.r - 0.214).abs() < 1e-3 && clear.a == 1.0);
+    assert!((color::clear_color([0.5, 0.5, 0.5], F::Bgra8Unorm).g - 0.5).abs() < 1e-5);
+}
+
+#[test]
+fn surfaces_are_rendered_through_an_srgb_format() {
+    use wgpu::TextureFormat as F;
+    let formats = [F::Bgra8Unorm, F::Bgra8UnormSrgb, F::Rgba16Float];
+    assert_eq!(
+        color::surface_formats(&formats),
+        (F::Bgra8UnormSrgb, F::Bgra8UnormSrgb)
+    );
+    // without an sRGB format, the first one is viewed as sRGB
+    let formats = [F::Rgba8Unorm, F::Rgba16Float];
+    assert_eq!(
+        color::surface_formats(&formats),
+        (F::Rgba8Unorm, F::Rgba8UnormSrgb)
+    );
+    let formats = [F::Rgba16Float];
+    assert_eq!(
+        color::surface_formats(&formats),
+        (F::Rgba16Float, F::Rgba16Float)
+    );
+}
+
+#[test]
+fn linear_clear_reads_back_as_the_display_color() {
+    let Some(init) = common::headless(2, 2, 1) else {
+        return;
+    };
+    let mut encoder = init
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    {
+        let mut attachment = ws::create_color_attachment(&init.view);
+        let clear = color::clear_color([0.5, 0.5, 0.5], init.color_format());
+        attachment.ops.load = wgpu::LoadOp::Clear(clear);
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(attachment)],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+    }
+    init.queue.submit(std::iter::once(encoder.finish()));
+
+    let rgba = init.read_rgba().unwrap();
+    assert!(
+        rgba.chunks(4)
+            .all(|px| px[..3].iter().all(|&c| c.abs_diff(128) <= 1))
+    );
+}
@@ -0,0 +1,165 @@
+#![cfg(feature = "low_level")]
+
+use wgpu_surfaces::cli::SurfaceArgs;
+use wgpu_surfaces::colormap::{
+    self, Builtin, Channel, Colormap, ITransferFunction, Interpolation, Registry,
+};
+use wgpu_surfaces::surface_data::ISimpleSurface;
+
+#[test]
+fn builtins_are_listed_by_name() {
+    assert_eq!(Builtin::ALL.len(), colormap::COLORMAP_NAMES.len());
+    for builtin in Builtin::ALL {
+        assert_eq!(Builtin::from_name(builtin.name()), Some(builtin));
+        assert_eq!(colormap::colormap_data(builtin.name()), builtin.data());
+    }
+    assert_eq!(Builtin::Viridis.name(), "viridis");
+    assert_eq!(Builtin::from_name("no_such_map"), None);
+    assert_eq!(colormap::colormap_data("no_such_map"), Builtin::Jet.data());
+}
+
+#[test]
+fn interpolation_follows_the_stops() {
+    let data = Builtin::Terrain.data();
+    let linear = Colormap::new(data.to_vec()).unwrap();
+    let cubic = linear.clone().interpolation(Interpolation::Cubic);
+    for k in 0..=40 {
+        let t = -1.0 + 0.05 * k as f32;
+        let expected = colormap::color_lerp(data, -1.0, 1.0, t);
+        let color = linear.color(-1.0, 1.0, t);
+        assert!((0..3).all(|c| (color[c] - expected[c]).abs() < 1e-5));
+        assert!(
+            cubic
+                .color(-1.0, 1.0, t)
+                .iter()
+                .all(|c| (0.0..=1.0).contains(c))
+        );
+    }
+    // both pass through the stops and clamp outside the range
+    assert_eq!(cubic.color(0.0, 1.0, 0.3), data[3]);
+    assert_eq!(cubic.color(0.0, 1.0, 5.0), data[10]);
+    assert_ne!(cubic.color(0.0, 1.0, 0.35), linear.color(0.0, 1.0, 0.35));
+
+    // any number of stops
+    let ramp = Colormap::new(vec![[0.0; 3], [1.0; 3]]).unwrap();
+    assert_eq!(ramp.color(0.0, 2.0, 0.5), [0.25; 3]);
+    assert_eq!(ramp.resample()[4], [0.4; 3]);
+}
+
+#[test]
+fn colormaps_need_two_stops() {
+    assert!(Colormap::new(vec![]).is_err());
+    assert!(Colormap::new(vec![[0.5; 3]]).is_err());
+
+    // stops set directly still give a color
+    let mut colormap = Colormap::new(vec![[0.0; 3], [1.0; 3]]).unwrap();
+    colormap.stops = vec![[0.5, 0.25, 1.0]];
+    assert_eq!(colormap.color(0.0, 1.0, 0.7), [0.5, 0.25, 1.0]);
+    assert_eq!(colormap.resample(), [[0.5, 0.25, 1.0]; 11]);
+    colormap.stops.clear();
+    colormap.interpolation = Interpolation::Cubic;
+    assert_eq!(colormap.color(0.0, 1.0, 0.7), [0.0; 3]);
+}
+
+#[test]
+fn registry_validates_and_replaces() {
+    let mut registry = Registry::default();
+    assert!(registry.register("jet", vec![[0.0; 3], [1.0; 3]]).is_err());
+    assert!(registry.register("single", vec![[0.0; 3]]).is_err());
+    assert!(
+        registry
+            .register("bright", vec![[0.0; 3], [2.0; 3]])
+            .is_err()
+    );
+    assert!(
+        registry
+            .register("two words", vec![[0.0; 3], [1.0; 3]])
+            .is_err()
+    );
+
+    registry.register("ramp", vec![[0.0; 3], [1.0; 3]]).unwrap();
+    registry.register("ramp", vec![[1.0; 3], [0.0; 3]]).unwrap();
+    assert_eq!(registry.get("ramp").unwrap().stops[0], [1.0; 3]);
+    assert!(registry.contains("ramp") && registry.contains("plasma"));
+    assert_eq!(registry.names().last().unwrap(), "ramp");
+    assert_eq!(registry.names().len(), colormap::COLORMAP_NAMES.len() + 1);
+}
+
+#[test]
+fn registered_colormaps_color_surfaces() {
+    colormap::register("test_gray", vec![[0.0; 3], [1.0; 3]]).unwrap();
+    let args = SurfaceArgs::default()
+        .parse(["--colormap", "test_gray"].map(String::from))
+        .unwrap();
+    let mut ss = ISimpleSurface {
+        colormap_name: args.colormap,
+        colormap_range: Some([-1.0, 1.0]),
+        x_resolution: 4,
+        z_resolution: 4,
+        ..Default::default()
+    };
+    let data = ss.new();
+    for (position, color) in data.positions.iter().zip(&data.colors) {
+        let gray = 0.5 * (position[1] + 1.0);
+        assert!(color.iter().all(|c| (c - gray).abs() < 1e-5));
+    }
+    assert_eq!(colormap::colormap_data("test_gray")[10], [1.0; 3]);
+
+    ss.colormap_interpolation = Interpolation::Cubic;
+    assert_ne!(ss.new().colors, data.colors);
+}
+
+#[test]
+fn transfer_function_curves_are_edited_per_channel() {
+    let jet = colormap::lookup("jet", Interpolation::Linear);
+    let mut transfer = ITransferFunction::from_colormap(&jet);
+    assert_eq!(transfer.colormap(11), jet);
+    assert_eq!(transfer.rgba(0.5)[3], 1.0);
+
+    // a ramp in alpha and a red peak in the middle
+    transfer.insert_point(Channel::Alpha, 1.0, 1.0);
+    assert_eq!(transfer.insert_point(Channel::Alpha, 0.0, 0.0), 0);
+    assert_eq!(transfer.rgba(0.25)[3], 0.25);
+    let k = transfer.insert_point(Channel::Red, 0.55, 1.0);
+    assert_eq!(
+        transfer.nearest_point(Channel::Red, 0.56, 0.98, 0.05),
+        Some(k)
+    );
+    // dragging stops at the neighbors
+    transfer.move_point(Channel::Red, k, 0.9, 2.0);
+    assert_eq!(transfer.points(Channel::Red)[k], [0.6, 1.0]);
+
+    let rgba = transfer.rgba8(5);
+    assert_eq!(rgba.len(), 20);
+    assert_eq!(
+        rgba[8..12],
+        transfer.rgba(0.5).map(|c| (c * 255.0).round() as u8)
+    );
+
+    transfer.red.truncate(1);
+    assert!(!transfer.remove_point(Channel::Red, 0));
+    assert!(transfer.remove_point(Channel::Alpha, 0));
+    assert!(transfer.remove_point(Channel::Alpha, 0));
+    assert_eq!(transfer.rgba(0.3)[3], 1.0);
+}
+
+#[test]
+fn transfer_functions_are_saved_and_registered() {
+    let ramp = Colormap::new(vec![[0.0; 3], [1.0; 3]]).unwrap();
+    let mut transfer = ITransferFunction::from_colormap(&ramp);
+    transfer.insert_point(Channel::Green, 0.5, 0.0);
+    let path = std::env::temp_dir().join("wgpu_surfaces_transfer_function.json");
+    transfer.save(&path).unwrap();
+    assert_eq!(ITransferFunction::load(&path).unwrap(), transfer);
+    std::fs::remove_file(&path).unwrap();
+
+    let unsorted = r#"{"red": [[0.5, 0.0], [0.2, 1.0]], "green": [[0, 0]], "blue": [[0, 0]]}"#;
+    assert!(ITransferFunction::from_json(unsorted).is_err());
+    assert!(ITransferFunction::from_json(r#"{"red": [[0, 0]]}"#).is_err());
+
+    transfer.register("green_dip").unwrap();
+    let colormap = colormap::lookup("green_dip", Interpolation::Linear);
+    assert_eq!(colormap.stops.len(), 256);
+    let middle = colormap.color(0.0, 1.0, 0.5);
+    assert!((middle[0] - 0.5).abs() < 0.01 && middle[1] < 0.01);
+}
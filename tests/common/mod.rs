@@ -0,0 +1,44 @@
+// Headless target for the GPU tests:
+//
+//     mod common;
+//
+//     let Some(init) = common::headless(2, 1, 1) else {
+//         return;
+//     };
+//
+// Without an adapter, not even the software fallback, the test fails rather than
+// passing without having run. On machines known to have none, set SKIP_GPU_TESTS
+// (e.g. WGPU_SURFACES_SKIP_GPU=1 cargo test) to skip them with a note instead.
+// Tests that get their device some other way, like api::Gpu::headless, pass the
+// result through common::gpu.
+
+// each test binary uses only one of the two helpers
+#![allow(dead_code)]
+
+#[cfg(feature = "low_level")]
+use wgpu_surfaces::wgpu_simplified as ws;
+
+pub const SKIP_GPU_TESTS: &str = "WGPU_SURFACES_SKIP_GPU";
+
+#[cfg(feature = "low_level")]
+pub fn headless(width: u32, height: u32, sample_count: u32) -> Option<ws::InitWgpuHeadless> {
+    gpu(pollster::block_on(ws::InitWgpuHeadless::init_wgpu(
+        width,
+        height,
+        sample_count,
+    )))
+}
+
+pub fn gpu<T>(result: wgpu_surfaces::Result<T>) -> Option<T> {
+    match result {
+        Ok(gpu) => Some(gpu),
+        Err(e) if std::env::var_os(SKIP_GPU_TESTS).is_some() => {
+            eprintln!("skipping a GPU test, no adapter: {}", e);
+            None
+        }
+        Err(e) => panic!(
+            "no GPU adapter: {}; set {} to skip the GPU tests",
+            e, SKIP_GPU_TESTS
+        ),
+    }
+}
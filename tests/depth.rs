@@ -0,0 +1,254 @@
+#![cfg(feature = "low_level")]
+
+use cgmath::{Matrix4, Point3, SquareMatrix, Vector3};
+use wgpu_surfaces::app::RenderTargets;
+use wgpu_surfaces::depth::NearFarTuner;
+use wgpu_surfaces::wgpu_simplified as ws;
+
+mod common;
+
+fn close(a: [f32; 2], b: [f32; 2]) -> bool {
+    (a[0] - b[0]).abs() < 1e-4 && (a[1] - b[1]).abs() < 1e-4
+}
+
+fn view_from(eye: [f32; 3]) -> Matrix4<f32> {
+    ws::create_view_mat(
+        Point3::from(eye),
+        Point3::new(0.0, 0.0, 0.0),
+        Vector3::unit_y(),
+    )
+}
+
+#[test]
+fn near_far_fits_a_box_in_front_of_the_camera() {
+    let tuner = NearFarTuner::default();
+    // the unit box seen from 5 units away spans depths 4 to 6, padded by the margin
+    let near_far = tuner.fit(&view_from([0.0, 0.0, 5.0]), [-1.0; 3], [1.0; 3]);
+    assert!(close(near_far, [4.0 * 0.95, 6.0 * 1.05]), "{:?}", near_far);
+
+    // without a margin the planes touch the nearest and farthest corners
+    let tight = NearFarTuner {
+        margin: 0.0,
+        ..Default::default()
+    };
+    let near_far = tight.fit(&Matrix4::identity(), [-2.0, -1.0, -10.0], [2.0, 1.0, -5.0]);
+    assert!(close(near_far, [5.0, 10.0]), "{:?}", near_far);
+}
+
+#[test]
+fn near_far_keeps_near_positive_with_the_camera_inside_the_box() {
+    let tuner = NearFarTuner::default();
+    let near_far = tuner.fit(&view_from([0.0, 0.0, 0.5]), [-1.0; 3], [1.0; 3]);
+    assert_eq!(near_far[0], tuner.min_near);
+    assert!(near_far[0] > 0.0);
+    assert!((near_far[1] - 1.5 * 1.05).abs() < 1e-4, "{:?}", near_far);
+}
+
+#[test]
+fn near_far_handles_degenerate_and_empty_boxes() {
+    let tuner = NearFarTuner::default();
+    let view_mat = view_from([0.0, 0.0, 5.0]);
+
+    // a single point still gives a non-empty range around it
+    let near_far = tuner.fit(&view_mat, [0.0; 3], [0.0; 3]);
+    assert!(close(near_far, [5.0 * 0.95, 5.0 * 1.05]), "{:?}", near_far);
+
+    // a point at the camera or behind it falls back to the full range
+    let full = [tuner.min_near, tuner.max_far];
+    assert_eq!(tuner.fit(&view_mat, [0.0, 0.0, 5.0], [0.0, 0.0, 5.0]), full);
+    assert_eq!(tuner.fit(&view_mat, [0.0, 0.0, 8.0], [1.0, 1.0, 9.0]), full);
+
+    // an empty box, min above max as an unfilled bounds accumulator leaves it, stays in range
+    let near_far = tuner.fit(&view_mat, [f32::MAX; 3], [f32::MIN; 3]);
+    assert!(near_far.iter().all(|d| d.is_finite()));
+    assert!(tuner.min_near <= near_far[0] && near_far[0] < near_far[1]);
+    assert!(near_far[1] <= tuner.max_far);
+}
+
+// the left half of the target at depth 0.25, with or without a color target
+const DEPTH_SHADER: &str = r#"
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> @builtin(position) vec4f {
+    var corners = array(
+        vec2f(-1.0, -1.0), vec2f(0.0, -1.0), vec2f(0.0, 1.0),
+        vec2f(-1.0, -1.0), vec2f(0.0, 1.0), vec2f(-1.0, 1.0),
+    );
+    return vec4f(corners[i], 0.25, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4f {
+    return vec4f(0.0, 0.0, 1.0, 1.0);
+}
+"#;
+
+// a full-screen triangle that compares the depth read through the bind group with
+// 0.2 in red and 0.3 in green, 1 where the reference is at most the stored depth
+const SAMPLE_SHADER: &str = r#"
+@group(0) @binding(0) var depthTexture: texture_depth_2d;
+@group(0) @binding(1) var depthSampler: sampler_comparison;
+
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> @builtin(position) vec4f {
+    let uv = vec2f(f32((i << 1u) & 2u), f32(i & 2u));
+    return vec4f(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) pos: vec4f) -> @location(0) vec4f {
+    let uv = pos.xy / vec2f(textureDimensions(depthTexture));
+    let below = textureSampleCompare(depthTexture, depthSampler, uv, 0.2);
+    let above = textureSampleCompare(depthTexture, depthSampler, uv, 0.3);
+    return vec4f(below, above, 0.0, 1.0);
+}
+"#;
+
+#[test]
+fn depth_bind_group_reads_the_stored_depth() {
+    let Some(rgba) = render_sampled_depth(false) else {
+        return;
+    };
+    // 0.25 where the quad was drawn lies between the two references, the cleared 1.0
+    // beside it above both
+    assert_eq!(rgba[..2], [255, 0], "{:?}", rgba);
+    assert_eq!(rgba[4..6], [255, 255], "{:?}", rgba);
+}
+
+#[test]
+fn render_targets_store_a_bindable_depth() {
+    let Some(rgba) = render_sampled_depth(true) else {
+        return;
+    };
+    assert_eq!(rgba[..2], [255, 0], "{:?}", rgba);
+    assert_eq!(rgba[4..6], [255, 255], "{:?}", rgba);
+}
+
+// draws DEPTH_SHADER into a bindable depth texture, stores it, then samples it with
+// create_depth_bind_group in a second pass and reads the two pixels back; the GL
+// backend cannot load or plainly sample depth textures, so the depth is read through
+// a comparison sampler. With main_pass the depth is drawn in the main pass of
+// RenderTargets::with_depth instead of a depth-only pass.
+fn render_sampled_depth(main_pass: bool) -> Option<Vec<u8>> {
+    let init = common::headless(2, 1, 1)?;
+    let device = &init.device;
+    let targets = RenderTargets::with_depth(&init, true);
+    let depth_view = &targets.depth_texture_view;
+
+    let depth_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(DEPTH_SHADER.into()),
+    });
+    let color_targets = [Some(init.format.into())];
+    let depth_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: None,
+        layout: None,
+        vertex: wgpu::VertexState {
+            module: &depth_shader,
+            entry_point: Some("vs_main"),
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        fragment: main_pass.then_some(wgpu::FragmentState {
+            module: &depth_shader,
+            entry_point: Some("fs_main"),
+            targets: &color_targets,
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: wgpu::TextureFormat::Depth24Plus,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    });
+
+    // the non-comparison pair has to match its layout as well
+    let raw_sampler = ws::create_depth_sampler(device, None);
+    ws::create_depth_bind_group(
+        device,
+        wgpu::ShaderStages::FRAGMENT,
+        depth_view,
+        &raw_sampler,
+        init.sample_count,
+        false,
+    );
+    let sampler = ws::create_depth_sampler(device, Some(wgpu::CompareFunction::LessEqual));
+    let (layout, bind_group) = ws::create_depth_bind_group(
+        device,
+        wgpu::ShaderStages::FRAGMENT,
+        depth_view,
+        &sampler,
+        init.sample_count,
+        true,
+    );
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(SAMPLE_SHADER.into()),
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[&layout],
+        push_constant_ranges: &[],
+    });
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: None,
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(init.format.into())],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    });
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    {
+        let mut depth_pass = if main_pass {
+            targets.begin_pass(&mut encoder, &init, &init.view)
+        } else {
+            let depth_attachment =
+                ws::create_depth_stencil_attachment_store(depth_view, wgpu::StoreOp::Store);
+            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[],
+                depth_stencil_attachment: Some(depth_attachment),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            })
+        };
+        depth_pass.set_pipeline(&depth_pipeline);
+        depth_pass.draw(0..6, 0..1);
+    }
+    {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(ws::create_color_attachment(&init.view))],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        render_pass.set_pipeline(&pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+    init.queue.submit(std::iter::once(encoder.finish()));
+
+    Some(init.read_rgba().unwrap())
+}
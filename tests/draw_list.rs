@@ -0,0 +1,64 @@
+#![cfg(feature = "low_level")]
+
+use wgpu_surfaces::draw_list::{DrawKey, DrawList, DrawStats};
+
+#[test]
+fn sorting_groups_opaque_draws_and_keeps_their_order() {
+    let mut list = DrawList::default();
+    let keys = [
+        (DrawKey::opaque(1, 0), "wireframe a"),
+        (DrawKey::opaque(0, 1), "shape b"),
+        (DrawKey::blended(5, 0, 2.0), "near band"),
+        (DrawKey::opaque(0, 0), "shape a"),
+        (DrawKey::opaque(1, 0), "wireframe a again"),
+        (DrawKey::blended(5, 1, 3.0), "far band"),
+        (DrawKey::opaque(0, 1), "shape b again"),
+    ];
+    for (key, name) in keys {
+        list.push(key, name);
+    }
+    let stats = list.sort();
+
+    let order: Vec<&str> = list.items().copied().collect();
+    assert_eq!(
+        order,
+        [
+            "shape a",
+            "shape b",
+            "shape b again",
+            "wireframe a",
+            "wireframe a again",
+            "far band",
+            "near band",
+        ]
+    );
+    assert_eq!(
+        stats.submitted,
+        DrawStats {
+            draws: 7,
+            pipeline_switches: 7,
+            bind_group_switches: 4,
+        }
+    );
+    assert_eq!(
+        stats.sorted,
+        DrawStats {
+            draws: 7,
+            pipeline_switches: 3,
+            bind_group_switches: 5,
+        }
+    );
+    assert_eq!(stats.saved(), 3);
+}
+
+#[test]
+fn sorted_lists_save_nothing() {
+    let mut list = DrawList::default();
+    for pipeline in [0, 0, 1, 2, 2] {
+        list.push(DrawKey::opaque(pipeline, 0), ());
+    }
+    let stats = list.sort();
+    assert_eq!(stats.submitted, stats.sorted);
+    assert_eq!(stats.saved(), 0);
+    assert_eq!(stats.sorted.switches(), 4);
+}
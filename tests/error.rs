@@ -0,0 +1,82 @@
+#![cfg(feature = "low_level")]
+
+use wgpu_surfaces::Error;
+use wgpu_surfaces::wgpu_simplified as ws;
+
+mod common;
+
+const SHADER: &str = "
+@vertex fn vs_main(@location(0) p: vec3f) -> @builtin(position) vec4f {
+    return vec4f(p, 1.0);
+}
+@fragment fn fs_main() -> @location(0) vec4f {
+    return vec4f(1.0);
+}
+";
+
+#[test]
+fn pipeline_and_bind_group_mistakes_are_errors() {
+    let Some(init) = common::headless(1, 1, 1) else {
+        return;
+    };
+    let device = &init.device;
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: 64,
+        usage: wgpu::BufferUsages::UNIFORM,
+        mapped_at_creation: false,
+    });
+    let result = ws::create_bind_group(
+        device,
+        vec![wgpu::ShaderStages::VERTEX, wgpu::ShaderStages::FRAGMENT],
+        &[buffer.as_entire_binding()],
+    );
+    assert!(matches!(
+        result,
+        Err(Error::BindingCount {
+            stages: 2,
+            resources: 1,
+            ..
+        })
+    ));
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+    });
+    let missing = ws::IRenderPipeline {
+        shader: Some(&shader),
+        ..Default::default()
+    }
+    .new(&init);
+    assert!(matches!(
+        missing,
+        Err(Error::IncompletePipeline {
+            missing: "pipeline layout",
+            ..
+        })
+    ));
+
+    // the shader reads location 0, the vertex buffer layout has no attributes
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[],
+        push_constant_ranges: &[],
+    });
+    let mut pipeline = ws::IRenderPipeline {
+        shader: Some(&shader),
+        pipeline_layout: Some(&layout),
+        ..Default::default()
+    };
+    let invalid = pipeline.new(&init);
+    assert!(matches!(invalid, Err(Error::Validation { .. })));
+    let error = anyhow::Error::from(invalid.unwrap_err());
+    assert!(error.to_string().starts_with("render pipeline: "));
+
+    pipeline.vertex_buffer_layout = &[wgpu::VertexBufferLayout {
+        array_stride: 12,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &wgpu::vertex_attr_array![0 => Float32x3],
+    }];
+    assert!(pipeline.new(&init).is_ok());
+}
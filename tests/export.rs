@@ -0,0 +1,176 @@
+#![cfg(feature = "low_level")]
+
+use wgpu_surfaces::export::{self, ExportConvention, MeshFormat, Winding};
+use wgpu_surfaces::surface_data::ISurfaceOutput;
+
+// one quad of two triangles
+fn quad() -> ISurfaceOutput {
+    ISurfaceOutput {
+        positions: vec![
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [1.0, 0.0, 1.0],
+            [0.0, 0.0, 1.0],
+        ],
+        normals: vec![[0.0, 1.0, 0.0]; 4],
+        colors: vec![[1.0, 0.5, 0.0]; 4],
+        colors2: vec![],
+        uvs: vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]],
+        indices: vec![0, 2, 1, 0, 3, 2],
+        indices2: vec![0, 1, 1, 2],
+    }
+}
+
+#[test]
+fn obj_has_colored_vertices_normals_and_one_based_faces() {
+    let mut bytes = vec![];
+    export::write_obj(&mut bytes, &quad(), &ExportConvention::default()).unwrap();
+    let text = String::from_utf8(bytes).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.iter().filter(|l| l.starts_with("v ")).count(), 4);
+    assert_eq!(lines.iter().filter(|l| l.starts_with("vt ")).count(), 4);
+    assert_eq!(lines.iter().filter(|l| l.starts_with("vn ")).count(), 4);
+    assert!(lines.contains(&"v 1 0 1 1 0.5 0"));
+    let faces: Vec<&&str> = lines.iter().filter(|l| l.starts_with("f ")).collect();
+    assert_eq!(faces, [&"f 1/1/1 3/3/3 2/2/2", &"f 1/1/1 4/4/4 3/3/3"]);
+}
+
+#[test]
+fn obj_follows_the_export_convention() {
+    let convention = ExportConvention {
+        winding: Winding::Cw,
+        ..ExportConvention::z_up()
+    };
+    let mut output = quad();
+    output.uvs.clear();
+    let mut bytes = vec![];
+    export::write_obj(&mut bytes, &output, &convention).unwrap();
+    let text = String::from_utf8(bytes).unwrap();
+    assert!(text.contains("v 1 -1 0 1 0.5 0\n"));
+    let normal: Vec<f32> = text.lines().find(|l| l.starts_with("vn ")).unwrap()[3..]
+        .split(' ')
+        .map(|c| c.parse().unwrap())
+        .collect();
+    assert_eq!(normal, [0.0, 0.0, 1.0]);
+    assert!(text.contains("f 1//1 2//2 3//3\n"));
+    assert!(!text.contains("vt "));
+}
+
+#[test]
+fn ply_header_matches_binary_body() {
+    let mut bytes = vec![];
+    export::write_ply(&mut bytes, &quad(), &ExportConvention::default()).unwrap();
+    let end = b"end_header\n";
+    let split = bytes.windows(end.len()).position(|w| w == end).unwrap() + end.len();
+    let header = std::str::from_utf8(&bytes[..split]).unwrap();
+    assert!(header.starts_with("ply\nformat binary_little_endian 1.0\n"));
+    assert!(header.contains("element vertex 4\n"));
+    assert!(header.contains("property uchar red\n"));
+    assert!(header.contains("element face 2\n"));
+
+    // per vertex: position, normal, uv floats and three color bytes; per face: a count
+    // byte and three indices
+    let body = &bytes[split..];
+    assert_eq!(body.len(), 4 * (8 * 4 + 3) + 2 * (1 + 3 * 4));
+    let first = &body[..35];
+    assert_eq!(&first[24..27], &[255, 128, 0]);
+    let faces = &body[4 * 35..];
+    assert_eq!(faces[0], 3);
+    assert_eq!(u32::from_le_bytes(faces[5..9].try_into().unwrap()), 2);
+}
+
+#[test]
+fn files_are_written_to_disk() {
+    let dir = std::env::temp_dir();
+    let obj = dir.join(format!("export_test_{}.obj", std::process::id()));
+    let ply = dir.join(format!("export_test_{}.ply", std::process::id()));
+    export::export_obj(&quad(), &obj).unwrap();
+    export::export_ply(&quad(), &ply).unwrap();
+    assert!(std::fs::read_to_string(&obj).unwrap().contains("f "));
+    assert!(std::fs::read(&ply).unwrap().starts_with(b"ply\n"));
+    let _ = std::fs::remove_file(obj);
+    let _ = std::fs::remove_file(ply);
+}
+
+#[test]
+fn glb_chunks_hold_the_gltf_document_and_buffer() {
+    let mut bytes = vec![];
+    export::write_glb(&mut bytes, &quad(), &ExportConvention::default()).unwrap();
+    let word = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as usize;
+    assert_eq!(&bytes[..4], b"glTF");
+    assert_eq!((word(4), word(8)), (2, bytes.len()));
+    let json_len = word(12);
+    assert_eq!(&bytes[16..20], b"JSON");
+    assert_eq!(json_len % 4, 0);
+    let document: serde_json::Value = serde_json::from_slice(&bytes[20..20 + json_len]).unwrap();
+    let bin = &bytes[20 + json_len + 8..];
+    assert_eq!(&bytes[20 + json_len + 4..20 + json_len + 8], b"BIN\0");
+
+    let primitive = &document["meshes"][0]["primitives"][0];
+    for attribute in ["POSITION", "NORMAL", "COLOR_0", "TEXCOORD_0"] {
+        assert!(primitive["attributes"][attribute].is_u64(), "{attribute}");
+    }
+    let position = &document["accessors"][0];
+    assert_eq!(position["count"], 4);
+    assert_eq!(position["max"], serde_json::json!([1.0, 0.0, 1.0]));
+    // six 32-bit indices at the end of the buffer
+    let indices = &document["accessors"][primitive["indices"].as_u64().unwrap() as usize];
+    let view = &document["bufferViews"][indices["bufferView"].as_u64().unwrap() as usize];
+    let offset = view["byteOffset"].as_u64().unwrap() as usize;
+    let index = |k: usize| u32::from_le_bytes(bin[offset + 4 * k..][..4].try_into().unwrap());
+    assert_eq!((0..6).map(index).collect::<Vec<_>>(), [0, 2, 1, 0, 3, 2]);
+
+    // colors are linear and v runs down from the top
+    let first = |attribute: &str, n: usize| -> Vec<f32> {
+        let accessor = primitive["attributes"][attribute].as_u64().unwrap() as usize;
+        let view = document["accessors"][accessor]["bufferView"]
+            .as_u64()
+            .unwrap() as usize;
+        let offset = document["bufferViews"][view]["byteOffset"]
+            .as_u64()
+            .unwrap() as usize;
+        (0..n)
+            .map(|k| f32::from_le_bytes(bin[offset + 4 * k..][..4].try_into().unwrap()))
+            .collect()
+    };
+    let color = first("COLOR_0", 3);
+    assert_eq!((color[0], color[2]), (1.0, 0.0));
+    assert!((color[1] - 0.214).abs() < 1e-3);
+    assert_eq!(first("TEXCOORD_0", 2), [0.0, 1.0]);
+    assert_eq!(document["buffers"][0]["byteLength"], bin.len());
+}
+
+#[test]
+fn gltf_embeds_the_buffer_as_a_data_uri() {
+    let mut bytes = vec![];
+    export::write_gltf(&mut bytes, &quad(), &ExportConvention::default()).unwrap();
+    let document: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let uri = document["buffers"][0]["uri"].as_str().unwrap();
+    let data = uri
+        .strip_prefix("data:application/octet-stream;base64,")
+        .unwrap();
+    assert_eq!(data.len() % 4, 0);
+    // 4 vertices of 11 floats and 6 indices
+    assert_eq!(document["buffers"][0]["byteLength"], 4 * (4 * 11 + 6));
+    assert_eq!(data.len(), 200_usize.div_ceil(3) * 4);
+
+    let mut glb = vec![];
+    export::write_glb(&mut glb, &quad(), &ExportConvention::default()).unwrap();
+    let text = String::from_utf8_lossy(&glb);
+    assert!(!text.contains("\"uri\""));
+}
+
+#[test]
+fn mesh_formats_follow_the_extension() {
+    assert_eq!(MeshFormat::from_path("a/b.GLB"), Some(MeshFormat::Glb));
+    assert_eq!(MeshFormat::from_path("mesh.obj"), Some(MeshFormat::Obj));
+    assert_eq!(MeshFormat::from_path("mesh.stl"), None);
+    assert_eq!(MeshFormat::from_path("mesh"), None);
+    for format in MeshFormat::ALL {
+        assert_eq!(MeshFormat::from_extension(format.extension()), Some(format));
+    }
+    let path = std::env::temp_dir().join(format!("export_test_{}.stl", std::process::id()));
+    let error = export::export_mesh_as(&quad(), &path, &ExportConvention::default());
+    assert!(error.unwrap_err().to_string().contains("glb"));
+    assert!(!path.exists());
+}
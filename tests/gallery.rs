@@ -0,0 +1,60 @@
+#![cfg(feature = "low_level")]
+
+use wgpu_surfaces::colormap;
+use wgpu_surfaces::gallery::{self, PRESET_NAMES};
+use wgpu_surfaces::playlist::{IPlaylist, PlaylistRunner};
+use wgpu_surfaces::surface_data as sd;
+
+#[test]
+fn every_preset_is_available() {
+    let presets = gallery::presets();
+    assert_eq!(presets.len(), PRESET_NAMES.len());
+    assert!(gallery::preset("no such preset").is_none());
+    for (name, playlist) in presets {
+        assert!(!playlist.scenes.is_empty(), "{name}");
+        assert!(playlist.looping, "{name}");
+    }
+}
+
+// the presets double as integration tests: every scene builds its surface, finds its
+// colormap and survives a JSON round trip and a full playback
+#[test]
+fn preset_scenes_build_and_play() {
+    let registry = colormap::registry().read().unwrap();
+    for (name, playlist) in gallery::presets() {
+        for scene in playlist.scenes.iter() {
+            assert!(scene.surface_type < sd::PARAMETRIC_SURFACE_COUNT, "{name}");
+            let colormap_name = scene.colormap_name.as_deref().unwrap();
+            assert!(registry.contains(colormap_name), "{name}: {colormap_name}");
+
+            let data = sd::IParametricSurface {
+                surface_type: scene.surface_type,
+                colormap_name: colormap_name.to_string(),
+                u_resolution: 16,
+                v_resolution: 16,
+                ..Default::default()
+            }
+            .new();
+            assert!(
+                data.positions.iter().flatten().all(|c| c.is_finite()),
+                "{name}: {}",
+                scene.caption
+            );
+        }
+
+        let json = serde_json::to_string(&playlist).unwrap();
+        let reloaded = IPlaylist::from_json(&json).unwrap();
+        assert_eq!(reloaded.scenes.len(), playlist.scenes.len());
+
+        let duration = playlist.duration();
+        let count = playlist.scenes.len();
+        let mut runner = PlaylistRunner::new(playlist);
+        let mut seen = vec![false; count];
+        let mut t = 0.0;
+        while t < duration {
+            seen[runner.update(t).unwrap().scene_index] = true;
+            t += 0.5;
+        }
+        assert!(seen.iter().all(|&s| s), "{name}");
+    }
+}
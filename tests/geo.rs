@@ -0,0 +1,77 @@
+#![cfg(feature = "low_level")]
+
+use wgpu_surfaces::geo::{GeoTerrain, MapProjection};
+
+// 1 x 1 degree around 45N with a 1000 m bump in the middle
+fn bump(projection: MapProjection, exaggeration: f32) -> GeoTerrain {
+    let n = 21u16;
+    let elevations = (0..n as usize * n as usize)
+        .map(|k| {
+            if k == (n as usize * n as usize) / 2 {
+                1000.0
+            } else {
+                0.0
+            }
+        })
+        .collect();
+    let mut terrain = GeoTerrain::from_regular(n, n, [44.5, 45.5], [9.5, 10.5], elevations);
+    terrain.projection = projection;
+    terrain.vertical_exaggeration = exaggeration;
+    terrain
+}
+
+#[test]
+fn map_projections_keep_true_proportions() {
+    let terrain = bump(MapProjection::Equirectangular, 1.0);
+    let data = terrain.new();
+    let xs: Vec<f32> = data.positions.iter().map(|p| p[0]).collect();
+    let zs: Vec<f32> = data.positions.iter().map(|p| p[2]).collect();
+    let width =
+        xs.iter().cloned().fold(f32::MIN, f32::max) - xs.iter().cloned().fold(f32::MAX, f32::min);
+    let depth =
+        zs.iter().cloned().fold(f32::MIN, f32::max) - zs.iter().cloned().fold(f32::MAX, f32::min);
+    // a degree of longitude at 45N is cos(45) of a degree of latitude
+    assert!((depth - 2.0 * terrain.scale).abs() < 1e-4);
+    assert!((width / depth - 45f32.to_radians().cos()).abs() < 1e-3);
+    // north is -z: the first row (44.5N) lies south of the last one
+    assert!(data.positions[0][2] > data.positions[data.positions.len() - 1][2]);
+
+    // 1000 m over 111 km of latitude, doubled by the exaggeration
+    let peak = data.positions[data.positions.len() / 2][1];
+    assert!((peak / depth - 1000.0 / 111_000.0).abs() < 2e-4);
+    let doubled = bump(MapProjection::Equirectangular, 2.0).new();
+    assert!((doubled.positions[data.positions.len() / 2][1] - 2.0 * peak).abs() < 1e-5);
+    assert!(data.normals[0][1] > 0.999);
+
+    let mercator = bump(MapProjection::Mercator, 1.0).new();
+    assert!(
+        mercator
+            .positions
+            .iter()
+            .all(|p| p.iter().all(|v| v.is_finite()))
+    );
+}
+
+#[test]
+fn globe_curves_the_flat_ground() {
+    let data = bump(MapProjection::Globe, 1.0).new();
+    let n = 21;
+    let (corner, edge_mid) = (data.positions[0], data.positions[10]);
+    // the ground drops away from the center and the normals lean outwards
+    assert!(data.positions[n * 10 + 9][1] > edge_mid[1] && edge_mid[1] > corner[1]);
+    assert!(data.normals[0][0] * corner[0] > 0.0 && data.normals[0][2] * corner[2] > 0.0);
+    assert!(data.normals.iter().all(|n| n[1] > 0.99));
+}
+
+#[test]
+fn reads_xyz_grids() {
+    let text = "lon,lat,elevation\n10,45,1\n11,45,2\n12,45,3\n10,46,4\n11,46,5\n12,46,6\n";
+    let terrain = GeoTerrain::read_xyz(text.as_bytes()).unwrap();
+    assert_eq!((terrain.rows, terrain.cols), (2, 3));
+    assert_eq!(terrain.lats[3], 46.0);
+    assert_eq!(terrain.elevation_range(), [1.0, 6.0]);
+    assert_eq!(terrain.center(), [45.5, 11.0]);
+
+    let broken = "10 45 1\n11 45 2\n12 45 3\n10 46 4\n";
+    assert!(GeoTerrain::read_xyz(broken.as_bytes()).is_err());
+}
@@ -0,0 +1,155 @@
+#![cfg(feature = "low_level")]
+
+use std::f32::consts::PI;
+
+use cgmath::{InnerSpace, Matrix4, Quaternion, Rad, Rotation3, SquareMatrix, Vector3};
+use wgpu_surfaces::gizmo::{self, Gizmo, GizmoMode, GuideMode, Guides};
+use wgpu_surfaces::picking::Ray;
+use wgpu_surfaces::surface_data::ISurfaceOutput;
+
+mod common;
+
+// ray from far along +z looking down -z through (x, y)
+fn ray_at(x: f32, y: f32) -> Ray {
+    Ray {
+        origin: [x, y, 10.0],
+        direction: [0.0, 0.0, -1.0],
+    }
+}
+
+#[test]
+fn arrows_are_picked_and_dragged_along_their_axis() {
+    let mut gizmo = Gizmo::new(GizmoMode::Translate, [0.0, 0.0, 0.0], 1.0);
+    assert_eq!(gizmo.pick(&ray_at(0.5, 0.02)), Some(0));
+    assert_eq!(gizmo.pick(&ray_at(0.01, 0.6)), Some(1));
+    assert_eq!(gizmo.pick(&ray_at(0.5, 0.5)), None);
+    assert_eq!(gizmo.pick(&ray_at(1.5, 0.0)), None);
+
+    assert!(gizmo.begin_drag(&ray_at(0.5, 0.0)));
+    // only the x component follows the cursor
+    assert!(gizmo.drag(&ray_at(1.3, 0.4)));
+    let [x, y, z] = gizmo.position;
+    assert!((x - 0.8).abs() < 1e-5 && y == 0.0 && z == 0.0);
+
+    gizmo.snap.translate = Some(0.25);
+    gizmo.drag(&ray_at(1.3, 0.4));
+    assert!((gizmo.position[0] - 0.75).abs() < 1e-5);
+    gizmo.end_drag();
+    assert!(!gizmo.is_dragging());
+    assert!(!gizmo.drag(&ray_at(2.0, 0.0)));
+}
+
+#[test]
+fn rings_rotate_around_their_axis_with_snapping() {
+    let mut gizmo = Gizmo::new(GizmoMode::Rotate, [0.0, 0.0, 0.0], 1.0);
+    gizmo.snap.rotate = Some(PI / 12.0);
+    // the z ring faces the ray; start at angle 0 and drag a bit past a quarter turn
+    assert_eq!(gizmo.pick(&ray_at(1.0, 0.0)), Some(2));
+    assert_eq!(gizmo.pick(&ray_at(0.5, 0.0)), None);
+    assert!(gizmo.begin_drag(&ray_at(1.0, 0.0)));
+    let a = 0.5 * PI + 0.05;
+    gizmo.drag(&ray_at(a.cos(), a.sin()));
+
+    let expected = Quaternion::from_axis_angle(Vector3::unit_z(), Rad(0.5 * PI));
+    let turned = gizmo.rotation * Vector3::unit_x();
+    assert!((turned - expected * Vector3::unit_x()).x.abs() < 1e-5);
+    assert!((turned.y - 1.0).abs() < 1e-5);
+    assert_eq!(gizmo.position, [0.0, 0.0, 0.0]);
+}
+
+#[test]
+fn handles_are_line_lists_highlighting_the_active_axis() {
+    let mut gizmo = Gizmo::new(GizmoMode::Translate, [1.0, 2.0, 3.0], 2.0);
+    gizmo.hovered = Some(1);
+    for mode in [GizmoMode::Translate, GizmoMode::Rotate] {
+        gizmo.mode = mode;
+        let lines = gizmo.line_vertices();
+        assert_eq!(lines.len() % 2, 0);
+        assert!(lines.len() <= wgpu_surfaces::gizmo::MAX_LINE_VERTICES);
+        assert!(
+            lines
+                .iter()
+                .any(|(_, c)| *c == wgpu_surfaces::gizmo::ACTIVE_COLOR)
+        );
+    }
+}
+
+#[test]
+fn guides_cover_axes_grid_and_bounding_box() {
+    assert_eq!(gizmo::bounds([]), None);
+    let bounds = gizmo::bounds([[1.0, -2.0, 0.5], [-1.0, 3.0, 0.0], [0.0, 0.0, 2.0]]);
+    assert_eq!(bounds, Some([[-1.0, -2.0, 0.0], [1.0, 3.0, 2.0]]));
+
+    let axes = gizmo::axes_lines([1.0, 0.0, 0.0], 2.0);
+    assert_eq!(axes.len(), 6);
+    assert_eq!(axes[1], ([3.0, 0.0, 0.0], gizmo::AXIS_COLORS[0]));
+    assert_eq!(axes[5].0, [1.0, 0.0, 2.0]);
+
+    // 2 * 3 + 1 lines each way, rounded out to whole cells
+    let grid = gizmo::grid_lines([0.0, -1.0, 0.0], 2.5, 1.0);
+    assert_eq!(grid.len(), 2 * 2 * 7);
+    assert!(grid.iter().all(|(p, _)| p[1] == -1.0 && p[0].abs() <= 3.0));
+    assert!(gizmo::grid_lines([0.0; 3], 1.0, 0.0).is_empty());
+
+    // 12 edges, each along one axis, moved by the model matrix
+    let shift = Matrix4::from_translation(Vector3::new(0.0, 10.0, 0.0));
+    let edges = gizmo::box_lines([[0.0; 3], [1.0, 2.0, 3.0]], &shift, gizmo::BOX_COLOR);
+    assert_eq!(edges.len(), 24);
+    for pair in edges.chunks(2) {
+        let (a, b) = (pair[0].0, pair[1].0);
+        assert_eq!((0..3).filter(|&i| a[i] != b[i]).count(), 1);
+        assert!(a[1] >= 10.0 && b[1] <= 12.0);
+    }
+
+    let mut mode = GuideMode::default();
+    assert_eq!(mode, GuideMode::Off);
+    for expected in [GuideMode::AxesGrid, GuideMode::All, GuideMode::Off] {
+        mode = mode.next();
+        assert_eq!(mode, expected);
+    }
+}
+
+#[test]
+fn guides_lines_follow_the_mode() {
+    let Some(init) = common::headless(1, 1, 1) else {
+        return;
+    };
+    init.device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let mut guides = Guides::new(&init).unwrap();
+    let error = pollster::block_on(init.device.pop_error_scope());
+    assert!(error.is_none(), "{:?}", error);
+
+    let bounds = Some([[-1.0; 3], [1.0; 3]]);
+    let identity = Matrix4::identity();
+    assert!(guides.lines(bounds, &identity).is_empty());
+    guides.mode = GuideMode::AxesGrid;
+    let without_box = guides.lines(bounds, &identity).len();
+    assert_eq!(guides.lines(None, &identity).len(), without_box);
+    guides.mode = GuideMode::All;
+    assert_eq!(guides.lines(bounds, &identity).len(), without_box + 24);
+    assert_eq!(guides.lines(None, &identity).len(), without_box);
+}
+
+#[test]
+fn normal_lines_stay_perpendicular_under_scaling() {
+    // the plane x + y = 1 with its unit normal
+    let n = [0.5f32.sqrt(), 0.5f32.sqrt(), 0.0];
+    let output = ISurfaceOutput {
+        positions: vec![[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 1.0, 1.0]],
+        normals: vec![n; 3],
+        indices: vec![0, 1, 2],
+        ..Default::default()
+    };
+    let model_mat = Matrix4::from_nonuniform_scale(2.0, 1.0, 1.0);
+    let lines = output.normal_lines(0.5, &model_mat);
+    assert_eq!(lines.len(), 6);
+    assert_eq!(lines[0].0, [2.0, 0.0, 0.0]);
+    // the scaled plane x / 2 + y = 1 has the normal (1, 2, 0) / sqrt(5)
+    let segment = Vector3::from(lines[1].0) - Vector3::from(lines[0].0);
+    let expected = Vector3::new(1.0, 2.0, 0.0) / 5f32.sqrt() * 0.5;
+    assert!((segment - expected).magnitude() < 1e-5);
+    // colored by direction, 0.5 + 0.5 * normal
+    let color = Vector3::from(lines[0].1);
+    assert!((color - (expected + Vector3::new(0.5, 0.5, 0.5))).magnitude() < 1e-5);
+    assert_eq!(lines[0].1, lines[1].1);
+}
@@ -0,0 +1,222 @@
+#![cfg(feature = "low_level")]
+
+use wgpu::util::DeviceExt;
+use wgpu_surfaces::capabilities::Subsystem;
+use wgpu_surfaces::colormap;
+use wgpu_surfaces::gpu_surface::{DISPLACEMENT_GROUP, DisplacedSurface, GpuSurface, SurfaceKind};
+use wgpu_surfaces::surface_data::{self as sd, ISurfaceOutput};
+use wgpu_surfaces::wgpu_simplified as ws;
+
+mod common;
+
+// headless device with compute shaders, None where there is none to test with
+fn compute_device() -> Option<ws::InitWgpuHeadless> {
+    let init = common::headless(1, 1, 1)?;
+    let enabled = init.capabilities.is_enabled(Subsystem::GpuSurfaces);
+    enabled.then_some(init)
+}
+
+fn assert_close(gpu: &[[f32; 3]], cpu: &[[f32; 3]], tolerance: f32, what: &str) {
+    assert_eq!(gpu.len(), cpu.len());
+    for (k, (g, c)) in gpu.iter().zip(cpu).enumerate() {
+        let error = (0..3).map(|i| (g[i] - c[i]).abs()).fold(0.0, f32::max);
+        assert!(error < tolerance, "{what} {k}: gpu {g:?}, cpu {c:?}");
+    }
+}
+
+fn assert_matches(gpu: &ISurfaceOutput, cpu: &ISurfaceOutput) {
+    assert_close(&gpu.positions, &cpu.positions, 1e-3, "position");
+    assert_close(&gpu.normals, &cpu.normals, 2e-2, "normal");
+    assert_close(&gpu.colors, &cpu.colors, 1e-2, "color");
+    assert_close(&gpu.colors2, &cpu.colors2, 1e-2, "wireframe color");
+    assert_eq!(gpu.indices, cpu.indices);
+    assert_eq!(gpu.indices2, cpu.indices2);
+}
+
+#[test]
+fn gpu_surfaces_match_cpu_surfaces() {
+    let Some(init) = compute_device() else {
+        return;
+    };
+    for surface_type in 0..sd::SIMPLE_SURFACE_COUNT {
+        let mut ss = sd::ISimpleSurface {
+            surface_type,
+            x_resolution: 24,
+            z_resolution: 17,
+            scale: 2.0,
+            colormap_name: "hot".to_string(),
+            t: 0.7,
+            ..Default::default()
+        };
+        let gpu = GpuSurface::from_simple(&init.device, &ss);
+        assert_matches(&gpu.read_back(&init.device, &init.queue), &ss.new());
+    }
+
+    for surface_type in [0, 21] {
+        let mut ps = sd::IParametricSurface {
+            surface_type,
+            u_resolution: 20,
+            v_resolution: 30,
+            colormap_direction: 0,
+            ..Default::default()
+        };
+        let gpu = GpuSurface::from_parametric(&init.device, &ps).unwrap();
+        assert_matches(&gpu.read_back(&init.device, &init.queue), &ps.new());
+    }
+    let unported = sd::IParametricSurface {
+        surface_type: 29,
+        ..Default::default()
+    };
+    assert!(GpuSurface::from_parametric(&init.device, &unported).is_none());
+}
+
+#[test]
+fn gpu_surface_exceeds_u16_indices() {
+    let Some(init) = compute_device() else {
+        return;
+    };
+    let wgsl = "fn surface_fn(u: f32, v: f32, t: f32) -> vec3f { return vec3f(u, u * v, v); }";
+    let domain = [-1.0, 1.0, -1.0, 1.0];
+    let mut gpu = GpuSurface::new(&init.device, SurfaceKind::Simple, wgsl, domain, [8, 8]);
+    assert_eq!(gpu.indices_lens, [8 * 8 * 6, 8 * 8 * 4 + 2 * 8 * 4 - 4]);
+
+    gpu.set_resolution(&init.device, [300, 299]);
+    assert_eq!(gpu.vertex_count(), 301 * 300);
+    assert_eq!(gpu.index_format(), wgpu::IndexFormat::Uint32);
+    let data = gpu.read_back(&init.device, &init.queue);
+    assert_eq!(data.positions.len(), 301 * 300);
+    assert!(data.indices.is_empty());
+    // the saddle spans the full height range, corners first
+    assert_close(&data.positions[..1], &[[-1.0, 1.0, -1.0]], 1e-5, "corner");
+    let last = data.positions.len() - 1;
+    assert_close(&data.positions[last..], &[[1.0, 1.0, 1.0]], 1e-5, "corner");
+}
+
+#[test]
+fn displaced_height_range_covers_the_animation() {
+    for surface_type in 0..sd::SIMPLE_SURFACE_COUNT {
+        let [ymin, ymax] = DisplacedSurface::fit_height_range(surface_type, [30, 30]);
+        assert!(ymin < ymax);
+        // times between the fitted samples, on the finer grid of the CPU surface
+        let (f, [xmin, xmax, zmin, zmax], _) = sd::simple_surface_fn(surface_type);
+        let margin = 0.02 * (ymax - ymin);
+        for t in [0.37, 2.9, 5.55, 11.1] {
+            for i in 0..=60 {
+                for j in 0..=60 {
+                    let x = xmin + (xmax - xmin) * i as f32 / 60.0;
+                    let z = zmin + (zmax - zmin) * j as f32 / 60.0;
+                    let y = f(x, z, t)[1];
+                    assert!(
+                        y > ymin - margin && y < ymax + margin,
+                        "type {surface_type}"
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn displaced_surface_draws_without_vertex_buffers() {
+    let Some(init) = common::headless(32, 32, 1) else {
+        return;
+    };
+    let device = &init.device;
+    let ss = sd::ISimpleSurface {
+        surface_type: 2,
+        x_resolution: 40,
+        z_resolution: 40,
+        colormap_name: "hot".to_string(),
+        colormap_direction: 0,
+        t: 1.3,
+        ..Default::default()
+    };
+    let displaced = DisplacedSurface::from_simple(device, &ss);
+    assert_eq!(displaced.vertex_count(), 41 * 41);
+    assert_eq!(displaced.indices_lens[0], 40 * 40 * 6);
+    displaced.write(&init.queue);
+
+    // top view: x to the right, z up, the height only into the depth
+    #[rustfmt::skip]
+    let matrices: [f32; 48] = [
+        1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.1, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.5, 1.0,
+        1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
+        1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
+    ];
+    let matrix_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: None,
+        contents: bytemuck::cast_slice(&matrices),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+    let (matrix_layout, matrix_bind_group) = ws::create_bind_group(
+        device,
+        vec![wgpu::ShaderStages::VERTEX],
+        &[matrix_buffer.as_entire_binding()],
+    )
+    .unwrap();
+    let empty_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: None,
+        entries: &[],
+    });
+    let empty_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &empty_layout,
+        entries: &[],
+    });
+    let displacement_layout = DisplacedSurface::bind_group_layout(device);
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[&matrix_layout, &empty_layout, &displacement_layout],
+        push_constant_ranges: &[],
+    });
+    let vs_shader = DisplacedSurface::vertex_shader(device);
+    let fs_source = "@fragment
+        fn fs_main(@location(2) color: vec4f) -> @location(0) vec4f { return color; }";
+    let fs_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(fs_source.into()),
+    });
+    let pipeline = ws::IRenderPipeline {
+        vs_shader: Some(&vs_shader),
+        fs_shader: Some(&fs_shader),
+        pipeline_layout: Some(&pipeline_layout),
+        is_depth_stencil: false,
+        ..Default::default()
+    }
+    .new(&init)
+    .unwrap();
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(ws::create_color_attachment(&init.view))],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &matrix_bind_group, &[]);
+        pass.set_bind_group(1, &empty_bind_group, &[]);
+        pass.set_bind_group(DISPLACEMENT_GROUP, &displaced.bind_group, &[]);
+        pass.set_index_buffer(
+            displaced.index_buffers[0].slice(..),
+            displaced.index_format(),
+        );
+        pass.draw_indexed(0..displaced.indices_lens[0], 0, 0..1);
+    }
+    init.queue.submit(std::iter::once(encoder.finish()));
+
+    // the surface covers the view, colored along x by the colormap
+    let rgba = init.read_rgba().unwrap();
+    let colors = colormap::colormap_data("hot");
+    for (k, pixel) in rgba.chunks(4).enumerate() {
+        let x = -1.0 + (2 * (k % 32) + 1) as f32 / 32.0;
+        let expected = colormap::color_lerp(colors, -1.0, 1.0, x);
+        for c in 0..3 {
+            let error = (pixel[c] as f32 / 255.0 - expected[c]).abs();
+            assert!(error < 0.02, "pixel {k}: {pixel:?}, expected {expected:?}");
+        }
+    }
+}
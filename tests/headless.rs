@@ -0,0 +1,665 @@
+#![cfg(feature = "low_level")]
+
+use cgmath::{InnerSpace, Matrix4, Point3, SquareMatrix, Transform, Vector3};
+use wgpu::util::DeviceExt;
+use wgpu_surfaces::app;
+use wgpu_surfaces::wgpu_simplified::{self as ws, RenderTarget};
+
+mod common;
+
+#[test]
+fn headless_target_clears_and_reads_back() {
+    // a machine without any adapter, not even a software one, fails here, see common::headless
+    let Some(init) = common::headless(5, 3, 1) else {
+        return;
+    };
+    assert_eq!(init.target_size(), [5, 3]);
+    assert_eq!(init.color_format(), wgpu::TextureFormat::Rgba8UnormSrgb);
+
+    let mut encoder = init
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    {
+        let mut attachment = ws::create_color_attachment(&init.view);
+        attachment.ops.load = wgpu::LoadOp::Clear(wgpu::Color::RED);
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(attachment)],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+    }
+    init.queue.submit(std::iter::once(encoder.finish()));
+
+    let rgba = init.read_rgba().unwrap();
+    assert_eq!(rgba.len(), 5 * 3 * 4);
+    assert!(rgba.chunks(4).all(|px| px == [255, 0, 0, 255]));
+}
+
+#[test]
+fn growable_buffer_reallocates_only_when_outgrown() {
+    let Some(init) = common::headless(1, 1, 1) else {
+        return;
+    };
+    let (device, queue) = (&init.device, &init.queue);
+    let indices: Vec<u16> = (0..11).collect();
+    let usage = wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_SRC;
+    let mut buffer =
+        ws::GrowableBuffer::new(device, "Test", usage, bytemuck::cast_slice(&indices[..6]));
+    assert_eq!((buffer.len(), buffer.capacity()), (12, 20));
+
+    // smaller and odd-sized data fits, padded to 4 bytes
+    assert!(!buffer.write(device, queue, bytemuck::cast_slice(&indices[..3])));
+    assert_eq!(buffer.len(), 8);
+    assert!(!buffer.write(device, queue, bytemuck::cast_slice(&indices[..10])));
+    assert!(buffer.write(device, queue, bytemuck::cast_slice(&indices)));
+    assert_eq!((buffer.len(), buffer.capacity()), (24, 36));
+    assert_eq!(buffer.reallocations(), 1);
+
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: 24,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.copy_buffer_to_buffer(buffer.buffer(), 0, &readback, 0, 24);
+    queue.submit(std::iter::once(encoder.finish()));
+    readback.slice(..).map_async(wgpu::MapMode::Read, |_| {});
+    device.poll(wgpu::PollType::Wait).unwrap();
+    let bytes = readback.slice(..).get_mapped_range();
+    let read: &[u16] = bytemuck::cast_slice(&bytes);
+    assert_eq!(&read[..11], &indices[..]);
+}
+
+#[test]
+fn dirty_ranges_are_aligned_and_coalesced() {
+    let mut dirty = ws::DirtyRanges::default();
+    dirty.mark(10..13);
+    dirty.mark(40..48);
+    dirty.mark(14..20);
+    assert_eq!(dirty.ranges(), [8..20, 40..48]);
+    dirty.mark(20..24);
+    assert_eq!(dirty.ranges(), [8..24, 40..48]);
+    assert_eq!(dirty.bytes(), 24);
+
+    let mut merged = ws::DirtyRanges::default().with_merge_gap(16);
+    merged.mark(8..24);
+    merged.mark(40..48);
+    assert_eq!((merged.ranges()[0].clone(), merged.bytes()), (8..48, 40));
+
+    // two rows of 4 vertices of 12 bytes change out of 8 rows
+    let old = vec![0u8; 8 * 4 * 12];
+    let mut new = old.clone();
+    new[2 * 48 + 5] = 1;
+    new[3 * 48 + 40] = 1;
+    new[6 * 48..7 * 48].fill(2);
+    let dirty = ws::DirtyRanges::diff(&old, &new, 12);
+    assert_eq!(dirty.ranges(), [96..108, 180..192, 288..336]);
+    assert!(ws::DirtyRanges::diff(&old, &old, 12).is_empty());
+    // growing data is dirty past the old end
+    let grown = ws::DirtyRanges::diff(&old[..48], &old[..96], 12);
+    assert_eq!((grown.ranges()[0].clone(), grown.bytes()), (48..96, 48));
+}
+
+#[test]
+fn dirty_writes_update_only_the_changed_ranges() {
+    let Some(init) = common::headless(1, 1, 1) else {
+        return;
+    };
+    let (device, queue) = (&init.device, &init.queue);
+    let old: Vec<f32> = (0..64).map(|i| i as f32).collect();
+    let usage = wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_SRC;
+    let mut buffer = ws::GrowableBuffer::new(device, "Test", usage, bytemuck::cast_slice(&old));
+
+    let mut new = old.clone();
+    new[5] = -1.0;
+    new[60] = -2.0;
+    let mut dirty =
+        ws::DirtyRanges::diff(bytemuck::cast_slice(&old), bytemuck::cast_slice(&new), 12);
+    assert_eq!(dirty.bytes(), 24);
+    // bytes outside the dirty ranges are not uploaded, even where the contents differ
+    let mut contents = new.clone();
+    contents[30] = -3.0;
+    assert!(!buffer.write_dirty(device, queue, bytemuck::cast_slice(&contents), &mut dirty));
+    assert!(dirty.is_empty());
+
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: 256,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.copy_buffer_to_buffer(buffer.buffer(), 0, &readback, 0, 256);
+    queue.submit(std::iter::once(encoder.finish()));
+    readback.slice(..).map_async(wgpu::MapMode::Read, |_| {});
+    device.poll(wgpu::PollType::Wait).unwrap();
+    let bytes = readback.slice(..).get_mapped_range();
+    let read: &[f32] = bytemuck::cast_slice(&bytes);
+    assert_eq!(read, new);
+}
+
+#[test]
+fn stereo_eyes_are_apart_along_the_camera_right() {
+    let [left, right] = ws::create_stereo_view_mats(
+        Point3::new(0.0, 0.0, 5.0),
+        Point3::new(0.0, 0.0, 0.0),
+        Vector3::unit_y(),
+        0.2,
+    );
+    // the eyes sit at x = -0.1 and 0.1, looking down -z
+    let eye = |view: Matrix4<f32>| {
+        view.invert()
+            .unwrap()
+            .transform_point(Point3::new(0.0, 0.0, 0.0))
+    };
+    assert!((eye(left) - Point3::new(-0.1, 0.0, 5.0)).magnitude() < 1e-5);
+    assert!((eye(right) - Point3::new(0.1, 0.0, 5.0)).magnitude() < 1e-5);
+    let ahead = right.transform_vector(-Vector3::unit_z());
+    assert!((ahead - -Vector3::unit_z()).magnitude() < 1e-5);
+}
+
+const CUBE_FACE_SHADER: &str = r#"
+@group(0) @binding(0) var<uniform> invVpMat: mat4x4f;
+const SIZE = 16.0;
+
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> @builtin(position) vec4f {
+    let p = vec2f(f32(i & 1u) * 4.0 - 1.0, f32(i >> 1u) * 4.0 - 1.0);
+    return vec4f(p, 0.5, 1.0);
+}
+
+// the world direction through each texel
+@fragment
+fn fs_main(@builtin(position) position: vec4f) -> @location(0) vec4f {
+    let ndc = vec2f(2.0 * position.x / SIZE - 1.0, 1.0 - 2.0 * position.y / SIZE);
+    let world = invVpMat * vec4f(ndc, 0.5, 1.0);
+    return vec4f(normalize(world.xyz / world.w), 1.0);
+}
+"#;
+
+const CUBE_SAMPLE_SHADER: &str = r#"
+@group(0) @binding(0) var cube: texture_cube<f32>;
+@group(0) @binding(1) var cubeSampler: sampler;
+
+@vertex
+fn vs_main(@builtin(vertex_index) i: u32) -> @builtin(position) vec4f {
+    let p = vec2f(f32(i & 1u) * 4.0 - 1.0, f32(i >> 1u) * 4.0 - 1.0);
+    return vec4f(p, 0.5, 1.0);
+}
+
+// green where the cubemap gives back the direction it was sampled at, red elsewhere
+@fragment
+fn fs_main(@builtin(position) position: vec4f) -> @location(0) vec4f {
+    var directions = array<vec3f, 6>(
+        vec3f(1.0, 0.3, -0.5),
+        vec3f(-1.0, -0.4, 0.2),
+        vec3f(0.3, 1.0, 0.6),
+        vec3f(-0.5, -1.0, 0.1),
+        vec3f(0.4, -0.2, 1.0),
+        vec3f(-0.3, 0.5, -1.0),
+    );
+    let d = normalize(directions[u32(position.x)]);
+    let sampled = textureSampleLevel(cube, cubeSampler, d, 0.0).xyz;
+    let hit = dot(normalize(sampled), d) > 0.99;
+    return select(vec4f(1.0, 0.0, 0.0, 1.0), vec4f(0.0, 1.0, 0.0, 1.0), hit);
+}
+"#;
+
+#[test]
+fn cube_faces_render_what_cube_sampling_expects() {
+    let Some(init) = common::headless(6, 1, 1) else {
+        return;
+    };
+    let device = &init.device;
+    let cube = ws::create_cube_target(device, 16, wgpu::TextureFormat::Rgba16Float);
+    assert_eq!((cube.layers(), cube.target_size()), (6, [16, 16]));
+    let array = ws::create_array_target(device, [8, 4], 2, wgpu::TextureFormat::Rgba8Unorm);
+    assert_eq!((array.layers(), array.sample_count()), (2, 1));
+
+    let project_mat = ws::create_cube_projection_mat(0.1, 10.0);
+    let face_bind_groups: Vec<_> = ws::create_cube_view_mats(Point3::new(0.0, 0.0, 0.0))
+        .iter()
+        .map(|view_mat| {
+            let inv_vp_mat = (project_mat * view_mat).invert().unwrap();
+            let inv_vp_ref: &[f32; 16] = inv_vp_mat.as_ref();
+            let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: bytemuck::cast_slice(inv_vp_ref),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+            ws::create_bind_group(
+                device,
+                vec![wgpu::ShaderStages::FRAGMENT],
+                &[buffer.as_entire_binding()],
+            )
+            .unwrap()
+        })
+        .collect();
+    let pipeline_layout = |layout: &wgpu::BindGroupLayout| {
+        device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[layout],
+            push_constant_ranges: &[],
+        })
+    };
+    let face_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(CUBE_FACE_SHADER.into()),
+    });
+    let face_pipeline = ws::IRenderPipeline {
+        shader: Some(&face_shader),
+        pipeline_layout: Some(&pipeline_layout(&face_bind_groups[0].0)),
+        ..Default::default()
+    }
+    .new(&cube)
+    .unwrap();
+
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+    let sample_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: None,
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::Cube,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+    });
+    let sample_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &sample_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&cube.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&sampler),
+            },
+        ],
+    });
+    let sample_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(CUBE_SAMPLE_SHADER.into()),
+    });
+    let sample_pipeline = ws::IRenderPipeline {
+        shader: Some(&sample_shader),
+        pipeline_layout: Some(&pipeline_layout(&sample_layout)),
+        is_depth_stencil: false,
+        ..Default::default()
+    }
+    .new(&init)
+    .unwrap();
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    for (layer, (_, bind_group)) in face_bind_groups.iter().enumerate() {
+        let mut pass = cube.begin_layer_pass(&mut encoder, layer as u32, wgpu::Color::BLACK);
+        pass.set_pipeline(&face_pipeline);
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+    {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(ws::create_color_attachment(&init.view))],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&sample_pipeline);
+        pass.set_bind_group(0, &sample_bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+    init.queue.submit(std::iter::once(encoder.finish()));
+
+    let rgba = init.read_rgba().unwrap();
+    let pixels: Vec<&[u8]> = rgba.chunks(4).collect();
+    assert_eq!(pixels, [[0, 255, 0, 255]; 6]);
+}
+
+#[test]
+fn sample_counts_are_clamped_to_the_supported_ones() {
+    assert_eq!(ws::clamp_sample_count(&[1, 4], 8), 4);
+    assert_eq!(ws::clamp_sample_count(&[1, 2, 4, 8], 8), 8);
+    assert_eq!(ws::clamp_sample_count(&[1, 4], 2), 1);
+    assert_eq!(ws::clamp_sample_count(&[1, 4], 0), 1);
+
+    // 16 is beyond most adapters; the target still renders with what is used instead
+    let Some(init) = common::headless(4, 4, 16) else {
+        return;
+    };
+    let formats = [init.format, wgpu::TextureFormat::Depth24Plus];
+    let supported = ws::supported_sample_counts(&init.adapter, &init.device, &formats);
+    assert!(supported.contains(&1) && supported.contains(&init.sample_count));
+    let msaa_texture_view = ws::create_msaa_texture_view(&init);
+    let depth_texture_view = ws::create_depth_view(&init);
+    let mut encoder = init
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    app::begin_main_pass(
+        &mut encoder,
+        &init,
+        &init.view,
+        &msaa_texture_view,
+        ws::create_depth_stencil_attachment(&depth_texture_view),
+    );
+    init.queue.submit(std::iter::once(encoder.finish()));
+    assert_eq!(init.read_rgba().unwrap().len(), 4 * 4 * 4);
+}
+
+#[test]
+fn main_pass_resolves_multisampled_targets() {
+    for sample_count in [1, 4] {
+        let Some(init) = common::headless(4, 4, sample_count) else {
+            return;
+        };
+        // start from red, so the cleared black shows the pass reached the target
+        let mut encoder = init
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut attachment = ws::create_color_attachment(&init.view);
+            attachment.ops.load = wgpu::LoadOp::Clear(wgpu::Color::RED);
+            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(attachment)],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+        }
+        let msaa_texture_view = ws::create_msaa_texture_view(&init);
+        let depth_texture_view = ws::create_depth_view(&init);
+        app::begin_main_pass(
+            &mut encoder,
+            &init,
+            &init.view,
+            &msaa_texture_view,
+            ws::create_depth_stencil_attachment(&depth_texture_view),
+        );
+        init.queue.submit(std::iter::once(encoder.finish()));
+
+        let rgba = init.read_rgba().unwrap();
+        assert!(rgba.chunks(4).all(|px| px == [0, 0, 0, 255]));
+    }
+}
+
+#[test]
+fn webgl2_adapters_get_the_webgl2_limits() {
+    let webgl2 = wgpu::Limits::downlevel_webgl2_defaults();
+    assert_eq!(
+        ws::required_limits(&wgpu::Limits::default()),
+        wgpu::Limits::default()
+    );
+    let limits = ws::required_limits(&webgl2);
+    assert_eq!(limits.max_storage_buffers_per_shader_stage, 0);
+    assert_eq!(
+        limits.max_texture_dimension_2d,
+        webgl2.max_texture_dimension_2d
+    );
+    assert!(limits.check_limits(&webgl2));
+}
+
+#[test]
+fn surface_config_keeps_what_the_new_capabilities_still_offer() {
+    use wgpu::{CompositeAlphaMode as Alpha, TextureFormat as F};
+    let caps = |formats: Vec<F>, alpha_modes: Vec<Alpha>| wgpu::SurfaceCapabilities {
+        formats,
+        present_modes: vec![wgpu::PresentMode::Fifo],
+        alpha_modes,
+        usages: wgpu::TextureUsages::RENDER_ATTACHMENT,
+    };
+    let fifo = ws::PresentModePreference::Fifo;
+    let first = caps(
+        vec![F::Bgra8Unorm, F::Rgba8Unorm],
+        vec![Alpha::Opaque, Alpha::PreMultiplied],
+    );
+    let config = ws::surface_config(&first, None, [640, 480], fifo);
+    assert_eq!(
+        (config.format, config.view_formats.clone()),
+        (F::Bgra8Unorm, vec![F::Bgra8UnormSrgb])
+    );
+    assert_eq!(config.alpha_mode, Alpha::Opaque);
+    assert_eq!(config.usage, wgpu::TextureUsages::RENDER_ATTACHMENT);
+
+    // a monitor offering the same format first in another order keeps the configuration
+    let mut current = config.clone();
+    current.alpha_mode = Alpha::PreMultiplied;
+    let reordered = caps(
+        vec![F::Rgba8UnormSrgb, F::Bgra8Unorm],
+        vec![Alpha::PreMultiplied],
+    );
+    let config = ws::surface_config(&reordered, Some(&current), [640, 480], fifo);
+    assert_eq!(
+        (config.format, config.view_formats),
+        (F::Bgra8Unorm, vec![F::Bgra8UnormSrgb])
+    );
+    assert_eq!(config.alpha_mode, Alpha::PreMultiplied);
+
+    // one without it gets a new format and alpha mode
+    let other = caps(vec![F::Rgba16Float, F::Rgba8UnormSrgb], vec![Alpha::Opaque]);
+    let config = ws::surface_config(&other, Some(&current), [800, 600], fifo);
+    assert_eq!(
+        (config.format, config.view_formats),
+        (F::Rgba8UnormSrgb, vec![])
+    );
+    assert_eq!(
+        (config.alpha_mode, config.width, config.height),
+        (Alpha::Opaque, 800, 600)
+    );
+
+    let change = ws::SurfaceChange {
+        alpha_mode: Some([Alpha::PreMultiplied, Alpha::Opaque]),
+        ..Default::default()
+    };
+    assert!(!change.is_empty() && !change.needs_new_pipelines());
+    let change = ws::SurfaceChange {
+        color_format: Some([F::Bgra8UnormSrgb, F::Rgba8UnormSrgb]),
+        ..change
+    };
+    assert!(change.needs_new_pipelines());
+    assert!(ws::SurfaceChange::default().is_empty());
+}
+
+#[test]
+fn gpu_timer_reports_the_timed_passes() {
+    let Some(init) = common::headless(4, 4, 1) else {
+        return;
+    };
+    let mut timer = ws::GpuTimer::new(&init.device, &init.queue, 2);
+    let timing = init
+        .device
+        .features()
+        .contains(wgpu::Features::TIMESTAMP_QUERY);
+    assert_eq!(timer.is_enabled(), timing);
+
+    let mut encoder = init
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    let depth_texture_view = ws::create_depth_view(&init);
+    for label in ["shape_only", "wireframe_only", "both"] {
+        let timestamp_writes = timer.timestamp_writes(label);
+        // passes beyond max_passes run untimed
+        assert_eq!(timestamp_writes.is_some(), timing && label != "both");
+        app::begin_timed_main_pass(
+            &mut encoder,
+            &init,
+            &init.view,
+            &init.view,
+            ws::create_depth_stencil_attachment(&depth_texture_view),
+            timestamp_writes,
+        );
+    }
+    timer.resolve(&mut encoder);
+    init.queue.submit(std::iter::once(encoder.finish()));
+
+    let times = timer.read(&init.device).unwrap();
+    if !timing {
+        assert!(times.is_empty());
+        return;
+    }
+    let labels: Vec<&str> = times.iter().map(|t| t.label.as_str()).collect();
+    assert_eq!(labels, ["shape_only", "wireframe_only"]);
+    assert!(times.iter().all(|t| t.ms >= 0.0 && t.ms < 1000.0));
+    assert!(timer.read(&init.device).unwrap().is_empty());
+
+    let averages = timer.take_averages();
+    assert_eq!(averages, times);
+    assert!(timer.take_averages().is_empty());
+}
+
+#[test]
+fn render_graph_orders_passes_by_their_attachments() {
+    let mut graph = ws::RenderGraph::default();
+    graph.add_attachment("shadow", ws::AttachmentDesc::depth().fixed_size(64, 64));
+    graph.add_attachment("depth", ws::AttachmentDesc::depth().multisampled());
+    // the overlay reads and writes the output, so it stays after the main pass
+    graph.add_pass("main", &["shadow"], &[ws::RenderGraph::OUTPUT, "depth"]);
+    graph.add_pass(
+        "overlay",
+        &[ws::RenderGraph::OUTPUT],
+        &[ws::RenderGraph::OUTPUT],
+    );
+    graph.add_pass("shadow", &[], &["shadow"]);
+    let names = |graph: &ws::RenderGraph| -> Vec<String> {
+        let order = graph.order().unwrap();
+        order
+            .iter()
+            .map(|&i| graph.passes()[i].name.clone())
+            .collect()
+    };
+    assert_eq!(names(&graph), ["shadow", "main", "overlay"]);
+
+    // a capture reading the finished frame runs after every pass drawing on it
+    graph.add_pass("capture", &[ws::RenderGraph::OUTPUT], &[]);
+    graph.add_pass("hud", &[], &[ws::RenderGraph::OUTPUT]);
+    assert_eq!(
+        names(&graph),
+        ["shadow", "main", "overlay", "hud", "capture"]
+    );
+
+    graph.add_pass("blur", &["bloom"], &[ws::RenderGraph::OUTPUT]);
+    assert!(matches!(
+        graph.order(),
+        Err(wgpu_surfaces::Error::RenderGraph(_))
+    ));
+    graph.remove_pass("blur");
+
+    // two passes reading what the other writes
+    graph.add_attachment("a", ws::AttachmentDesc::color());
+    graph.add_attachment("b", ws::AttachmentDesc::color());
+    graph.add_pass("ab", &["a"], &["b"]);
+    graph.add_pass("ba", &["b"], &["a"]);
+    let error = graph.order().unwrap_err().to_string();
+    assert!(error.contains("ab, ba"), "{error}");
+}
+
+#[test]
+fn render_graph_creates_and_resizes_its_attachments() {
+    let Some(mut init) = common::headless(4, 4, 1) else {
+        return;
+    };
+    let mut graph = ws::RenderGraph::default();
+    graph.add_attachment("depth", ws::AttachmentDesc::depth());
+    graph.add_attachment("shadow", ws::AttachmentDesc::depth().fixed_size(16, 8));
+    graph.add_pass("main", &["shadow"], &[ws::RenderGraph::OUTPUT, "depth"]);
+    graph.add_pass("shadow", &[], &["shadow"]);
+    assert!(graph.view("depth").is_none());
+
+    let frame = |init: &ws::InitWgpuHeadless, graph: &mut ws::RenderGraph| {
+        let mut encoder = init
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        let mut ran = vec![];
+        graph
+            .execute(init, &mut encoder, &init.view, |encoder, pass| {
+                ran.push(pass.name.to_string());
+                if pass.name == "main" {
+                    let mut attachment =
+                        ws::create_color_attachment(pass.view(ws::RenderGraph::OUTPUT).unwrap());
+                    attachment.ops.load = wgpu::LoadOp::Clear(wgpu::Color::RED);
+                    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: None,
+                        color_attachments: &[Some(attachment)],
+                        depth_stencil_attachment: Some(ws::create_depth_stencil_attachment(
+                            pass.view("depth").unwrap(),
+                        )),
+                        occlusion_query_set: None,
+                        timestamp_writes: None,
+                    });
+                }
+            })
+            .unwrap();
+        init.queue.submit(std::iter::once(encoder.finish()));
+        ran
+    };
+    assert_eq!(frame(&init, &mut graph), ["shadow", "main"]);
+    assert!(
+        init.read_rgba()
+            .unwrap()
+            .chunks(4)
+            .all(|px| px == [255, 0, 0, 255])
+    );
+    assert_eq!(graph.allocations(), 2);
+    let size = |graph: &ws::RenderGraph, name| {
+        let texture = graph.texture(name).unwrap();
+        [texture.width(), texture.height()]
+    };
+    assert_eq!(size(&graph, "shadow"), [16, 8]);
+
+    // the next frame reuses them, a new target size recreates the sized ones
+    frame(&init, &mut graph);
+    assert_eq!(graph.allocations(), 2);
+    init.resize(8, 6);
+    frame(&init, &mut graph);
+    assert_eq!(graph.allocations(), 3);
+    assert_eq!(size(&graph, "depth"), [8, 6]);
+    assert_eq!(size(&graph, "shadow"), [16, 8]);
+}
+
+#[test]
+fn growable_buffers_copy_what_another_holds() {
+    let Some(init) = common::headless(1, 1, 1) else {
+        return;
+    };
+    let (device, queue) = (&init.device, &init.queue);
+    let usage = wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_SRC;
+    let data: Vec<f32> = (0..12).map(|k| k as f32).collect();
+    let source = ws::GrowableBuffer::new(device, "Source", usage, bytemuck::cast_slice(&data));
+    let mut copy = ws::GrowableBuffer::new(device, "Copy", usage, &[]);
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    assert!(copy.copy_from(device, &mut encoder, &source));
+    assert_eq!(copy.len(), 48);
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: 48,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    encoder.copy_buffer_to_buffer(copy.buffer(), 0, &readback, 0, 48);
+    queue.submit(std::iter::once(encoder.finish()));
+    readback.slice(..).map_async(wgpu::MapMode::Read, |_| {});
+    device.poll(wgpu::PollType::Wait).unwrap();
+    let bytes = readback.slice(..).get_mapped_range();
+    assert_eq!(bytemuck::cast_slice::<u8, f32>(&bytes), &data[..]);
+}
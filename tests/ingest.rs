@@ -0,0 +1,118 @@
+#![cfg(all(feature = "low_level", feature = "network"))]
+
+use std::io::ErrorKind;
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+use wgpu_surfaces::ingest::{self, DataMessage, DataReceiver};
+use wgpu_surfaces::surface_data::{IStreamingSurface, ITelemetrySurface};
+
+fn encode(message: &DataMessage) -> Vec<u8> {
+    let mut bytes = vec![];
+    ingest::write_message(&mut bytes, message).unwrap();
+    bytes
+}
+
+#[test]
+fn messages_round_trip() {
+    let row = DataMessage::Row(vec![1.0, -2.5, f32::MAX]);
+    let frame = DataMessage::Frame(vec![0.5; 12]);
+    let mut bytes = encode(&row);
+    assert_eq!(&bytes[..5], &[b'R', 3, 0, 0, 0]);
+    bytes.extend(encode(&frame));
+
+    let mut reader = bytes.as_slice();
+    assert_eq!(ingest::read_message(&mut reader).unwrap(), row);
+    assert_eq!(ingest::read_message(&mut reader).unwrap(), frame);
+    let end = ingest::read_message(&mut reader).unwrap_err();
+    assert_eq!(end.kind(), ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn oversized_counts_are_rejected_before_reading() {
+    let mut bytes = vec![b'F'];
+    bytes.extend((ingest::MAX_VALUES + 1).to_le_bytes());
+    let error = ingest::read_message(&mut bytes.as_slice()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+}
+
+#[test]
+fn unknown_kinds_are_rejected() {
+    let mut bytes = encode(&DataMessage::Row(vec![1.0]));
+    bytes[0] = b'X';
+    let error = ingest::read_message(&mut bytes.as_slice()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+    // before the payload is read, so a bogus count is not allocated
+    let mut bytes = vec![b'X'];
+    bytes.extend(ingest::MAX_VALUES.to_le_bytes());
+    let error = ingest::read_message(&mut bytes.as_slice()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+}
+
+#[test]
+fn the_largest_streaming_frame_fits_the_limit() {
+    let side = wgpu_surfaces::surface_data::MAX_STREAMING_SIDE as usize;
+    let frame = DataMessage::Frame(vec![0.5; side * side]);
+    let bytes = encode(&frame);
+    assert_eq!(ingest::read_message(&mut bytes.as_slice()).unwrap(), frame);
+}
+
+#[test]
+fn truncated_payloads_are_errors() {
+    let bytes = encode(&DataMessage::Frame(vec![1.0, 2.0, 3.0]));
+    for len in [3, 5, bytes.len() - 1] {
+        let error = ingest::read_message(&mut &bytes[..len]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof, "{len} bytes");
+    }
+}
+
+#[test]
+fn receivers_feed_streaming_and_telemetry_surfaces() {
+    let receiver = DataReceiver::bind("127.0.0.1:0").unwrap();
+    let mut producer = TcpStream::connect(receiver.addr).unwrap();
+    let mut telemetry = ITelemetrySurface::with_size(4, 2);
+    ingest::write_message(&mut producer, &DataMessage::Row(vec![1.0, 2.0])).unwrap();
+    let start = Instant::now();
+    while !receiver.apply(&mut telemetry) {
+        assert!(start.elapsed() < Duration::from_secs(5), "no message");
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(telemetry.row(0), &[1.0, 2.0]);
+
+    let mut streaming = IStreamingSurface::default();
+    streaming.resize(2, 2);
+    let frame = DataMessage::Frame(vec![1.0, 2.0, 3.0, 4.0]);
+    ingest::write_message(&mut producer, &frame).unwrap();
+    while !receiver.apply(&mut streaming) {
+        assert!(start.elapsed() < Duration::from_secs(5), "no message");
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(streaming.values, vec![1.0, 2.0, 3.0, 4.0]);
+}
+
+#[test]
+fn producers_ahead_of_the_queue_wait_instead_of_losing_rows() {
+    let receiver = DataReceiver::bind("127.0.0.1:0").unwrap();
+    let mut producer = TcpStream::connect(receiver.addr).unwrap();
+    let count = 3 * ingest::QUEUE_LEN;
+    for i in 0..count {
+        ingest::write_message(&mut producer, &DataMessage::Row(vec![i as f32])).unwrap();
+    }
+
+    let mut received = vec![];
+    let start = Instant::now();
+    while received.len() < count {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "{} rows",
+            received.len()
+        );
+        received.extend(receiver.try_recv_all());
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    let expected: Vec<_> = (0..count)
+        .map(|i| DataMessage::Row(vec![i as f32]))
+        .collect();
+    assert_eq!(received, expected);
+}
@@ -0,0 +1,356 @@
+#![cfg(feature = "low_level")]
+
+use wgpu::util::DeviceExt;
+use wgpu_surfaces::instancing::{
+    Frustum, IPackedMeshes, InstanceBatches, LodLevels, MeshPool, MeshRange, RangeAllocator,
+};
+use wgpu_surfaces::wgpu_simplified as ws;
+
+mod common;
+
+#[test]
+fn packed_meshes_keep_local_indices_with_offsets() {
+    let mut pack = IPackedMeshes::default();
+    assert_eq!(pack.push(&[1u8, 2, 3], &[0, 1, 2]), 0);
+    assert_eq!(pack.push(&[4u8, 5, 6, 7], &[0, 1, 2, 2, 3, 0]), 1);
+    assert_eq!(pack.vertices, [1, 2, 3, 4, 5, 6, 7]);
+    assert_eq!(pack.indices, [0, 1, 2, 0, 1, 2, 2, 3, 0]);
+    assert_eq!(
+        pack.ranges[1],
+        MeshRange {
+            base_vertex: 3,
+            first_index: 3,
+            index_count: 6,
+            vertex_count: 4,
+        }
+    );
+    assert_eq!(pack.ranges[1].indices(), 3..9);
+    pack.clear();
+    assert!(pack.is_empty());
+}
+
+#[test]
+fn instances_are_grouped_by_mesh_in_stable_order() {
+    let batches = InstanceBatches::new(&[2, 0, 2, 0, 0], 3);
+    assert_eq!(batches.order, [1, 3, 4, 0, 2]);
+    // mesh 1 has no instances and no draw
+    assert_eq!(batches.batches, [(0, 0..3), (2, 3..5)]);
+    assert_eq!(
+        batches.reorder(&['a', 'b', 'c', 'd', 'e']),
+        ['b', 'd', 'e', 'a', 'c']
+    );
+    assert_eq!(batches.instance_count(), 5);
+
+    let single = InstanceBatches::single(4);
+    assert_eq!(single.order, [0, 1, 2, 3]);
+    assert_eq!(single.batches, [(0, 0..4)]);
+}
+
+#[test]
+fn lod_levels_bucket_instances_by_distance() {
+    let lod = LodLevels::halving(30, 4, 10.0);
+    assert_eq!(lod.resolutions, [30, 15, 7, 3]);
+    assert_eq!(lod.distances, [10.0, 20.0, 40.0]);
+    assert_eq!(
+        [0.0, 9.9, 10.0, 25.0, 40.0, 1e6].map(|d| lod.level(d)),
+        [0, 0, 1, 2, 3, 3]
+    );
+    // the coarsest grids keep at least two cells a side
+    assert_eq!(LodLevels::halving(8, 4, 1.0).resolutions, [8, 4, 2, 2]);
+    assert_eq!(LodLevels::halving(8, 0, 1.0).len(), 1);
+
+    let positions = [
+        [0.0, 0.0, 50.0],
+        [0.0, 0.0, 1.0],
+        [12.0, 0.0, 0.0],
+        [0.0, 0.0, 2.0],
+    ];
+    let batches = lod.batches([0.0; 3], &positions);
+    assert_eq!(batches.batches, [(0, 0..2), (1, 2..3), (3, 3..4)]);
+    assert_eq!(batches.order, [1, 3, 2, 0]);
+}
+
+#[test]
+fn frustum_culling_keeps_the_instances_in_view() {
+    // looking down -z from the origin, 0.1 to 1000 deep
+    let (_, _, vp_mat) = ws::create_vp_mat(
+        (0.0, 0.0, 0.0).into(),
+        (0.0, 0.0, -1.0).into(),
+        cgmath::Vector3::unit_y(),
+        1.0,
+    );
+    let frustum = Frustum::from_matrix(&vp_mat);
+    for plane in frustum.planes {
+        let length = (plane[0] * plane[0] + plane[1] * plane[1] + plane[2] * plane[2]).sqrt();
+        assert!((length - 1.0).abs() < 1e-4);
+    }
+    assert!(frustum.intersects_sphere([0.0, 0.0, -10.0], 0.1));
+    assert!(!frustum.intersects_sphere([0.0, 0.0, 10.0], 1.0)); // behind
+    assert!(!frustum.intersects_sphere([0.0, 0.0, -1100.0], 1.0)); // beyond the far plane
+    assert!(!frustum.intersects_sphere([100.0, 0.0, -10.0], 1.0)); // off to the side
+    // the field of view is 72 degrees, so x = 7.27 at z = -10 is on the right plane
+    assert!(frustum.intersects_sphere([8.0, 0.0, -10.0], 1.0));
+    assert!(!frustum.intersects_sphere([9.0, 0.0, -10.0], 1.0));
+
+    let positions = [
+        [0.0, 0.0, 10.0],
+        [0.0, 0.0, -50.0],
+        [0.0, 0.0, -5.0],
+        [50.0, 0.0, -5.0],
+    ];
+    let visible = frustum.visible(&positions, 1.0);
+    assert_eq!(visible, [1, 2]);
+    // compacted: the visible instances only, bucketed by level
+    let lod = LodLevels::halving(30, 4, 10.0);
+    let batches = lod.visible_batches([0.0; 3], &positions, &visible);
+    assert_eq!(batches.batches, [(0, 0..1), (3, 1..2)]);
+    assert_eq!(batches.order, [2, 1]);
+    assert_eq!(batches.reorder(&['a', 'b', 'c', 'd']), ['c', 'b']);
+    assert_eq!(
+        InstanceBatches::subset(&visible, |_| 0, 1).batches,
+        [(0, 0..2)]
+    );
+}
+
+const SHADER: &str = r#"
+@group(0) @binding(0) var<storage> offsets: array<f32>;
+
+struct Output {
+    @builtin(position) position: vec4f,
+    @location(0) color: vec4f,
+}
+
+@vertex
+fn vs_main(
+    @builtin(instance_index) idx: u32,
+    @location(0) position: vec2f,
+    @location(1) color: vec3f,
+) -> Output {
+    var output: Output;
+    output.position = vec4f(position.x + offsets[idx], position.y, 0.0, 1.0);
+    output.color = vec4f(color, 1.0);
+    return output;
+}
+
+@fragment
+fn fs_main(in: Output) -> @location(0) vec4f {
+    return in.color;
+}
+"#;
+
+// a quad of width 0.5 at the left edge of clip space, in one color
+fn quad(color: [f32; 3]) -> Vec<[f32; 5]> {
+    [[-1.0, -1.0], [-0.5, -1.0], [-0.5, 1.0], [-1.0, 1.0]]
+        .map(|[x, y]| [x, y, color[0], color[1], color[2]])
+        .to_vec()
+}
+
+// draws SHADER quads into the headless target, offset by the storage buffer at group 0
+fn quad_pipeline(device: &wgpu::Device, layout: &wgpu::BindGroupLayout) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[layout],
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: None,
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            buffers: &[wgpu::VertexBufferLayout {
+                array_stride: 20,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x3],
+            }],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(wgpu::TextureFormat::Rgba8UnormSrgb.into())],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    })
+}
+
+#[test]
+fn batches_draw_each_instance_with_its_mesh() {
+    let Some(init) = common::headless(4, 1, 1) else {
+        return;
+    };
+    let device = &init.device;
+    let mut pack = IPackedMeshes::default();
+    pack.push(&quad([1.0, 0.0, 0.0]), &[0, 1, 2, 2, 3, 0]);
+    // the second mesh lists its vertices in another order to make the indices differ
+    let green = quad([0.0, 1.0, 0.0]);
+    pack.push(
+        &[green[2], green[3], green[0], green[1]],
+        &[2, 3, 0, 0, 1, 2],
+    );
+
+    // instance k covers pixel k
+    let mesh_of_instance = [1, 0, 1, 0];
+    let batches = InstanceBatches::new(&mesh_of_instance, pack.len());
+    let offsets = batches.reorder(&[0.0f32, 0.5, 1.0, 1.5]);
+
+    let buffer = |contents: &[u8], usage| {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents,
+            usage,
+        })
+    };
+    let vertex_buffer = buffer(
+        bytemuck::cast_slice(&pack.vertices),
+        wgpu::BufferUsages::VERTEX,
+    );
+    let index_buffer = buffer(
+        bytemuck::cast_slice(&pack.indices),
+        wgpu::BufferUsages::INDEX,
+    );
+    let offset_buffer = buffer(bytemuck::cast_slice(&offsets), wgpu::BufferUsages::STORAGE);
+    let (layout, bind_group) = ws::create_bind_group_storage(
+        device,
+        vec![wgpu::ShaderStages::VERTEX],
+        vec![wgpu::BufferBindingType::Storage { read_only: true }],
+        &[offset_buffer.as_entire_binding()],
+    )
+    .unwrap();
+    let pipeline = quad_pipeline(device, &layout);
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(ws::create_color_attachment(&init.view))],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        render_pass.set_pipeline(&pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        batches.draw(&mut render_pass, &pack.ranges);
+    }
+    init.queue.submit(std::iter::once(encoder.finish()));
+
+    let rgba = init.read_rgba().unwrap();
+    let pixels: Vec<&[u8]> = rgba.chunks(4).collect();
+    assert_eq!(
+        pixels,
+        [
+            [0, 255, 0, 255],
+            [255, 0, 0, 255],
+            [0, 255, 0, 255],
+            [255, 0, 0, 255]
+        ]
+    );
+}
+
+#[test]
+fn range_allocator_reuses_and_merges_freed_ranges() {
+    let mut ranges = RangeAllocator::new(10);
+    let a = ranges.allocate(4).unwrap();
+    let b = ranges.allocate(3).unwrap();
+    let c = ranges.allocate(3).unwrap();
+    assert_eq!((a.clone(), b.clone(), c.clone()), (0..4, 4..7, 7..10));
+    assert_eq!(ranges.allocate(1), None);
+
+    ranges.free(a);
+    ranges.free(c);
+    assert_eq!(ranges.allocate(5), None);
+    // freeing b joins all three into one free range
+    ranges.free(b);
+    assert_eq!(ranges.allocate(10), Some(0..10));
+    ranges.free(0..10);
+    ranges.grow(16);
+    assert_eq!((ranges.capacity(), ranges.free_len()), (16, 16));
+    assert_eq!(ranges.allocate(16), Some(0..16));
+}
+
+#[test]
+fn pooled_meshes_draw_from_shared_buffers() {
+    let Some(init) = common::headless(4, 1, 1) else {
+        return;
+    };
+    let (device, queue) = (&init.device, &init.queue);
+    // room for one quad, so the pool has to grow
+    let mut pool = MeshPool::new(device, 20, 4, 6);
+    let red = pool.insert(device, queue, &quad([1.0, 0.0, 0.0]), &[0, 1, 2, 2, 3, 0]);
+    let blue = pool.insert(device, queue, &quad([0.0, 0.0, 1.0]), &[0, 1, 2]);
+    let green = quad([0.0, 1.0, 0.0]);
+    let green = pool.insert(
+        device,
+        queue,
+        &[green[2], green[3], green[0], green[1]],
+        &[2, 3, 0, 0, 1, 2],
+    );
+    assert!(pool.reallocations() > 0);
+    // the freed space is reused
+    pool.remove(blue);
+    let white = pool.insert(device, queue, &quad([1.0, 1.0, 1.0]), &[0, 1, 2, 2, 3, 0]);
+    assert_eq!(pool.range(white).unwrap().base_vertex, 4);
+    assert_eq!((pool.len(), pool.range(blue)), (3, None));
+
+    let offsets = [0.0f32, 0.5, 1.0, 1.5];
+    let offset_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: None,
+        contents: bytemuck::cast_slice(&offsets),
+        usage: wgpu::BufferUsages::STORAGE,
+    });
+    let (layout, bind_group) = ws::create_bind_group_storage(
+        device,
+        vec![wgpu::ShaderStages::VERTEX],
+        vec![wgpu::BufferBindingType::Storage { read_only: true }],
+        &[offset_buffer.as_entire_binding()],
+    )
+    .unwrap();
+    let pipeline = quad_pipeline(device, &layout);
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(ws::create_color_attachment(&init.view))],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        render_pass.set_pipeline(&pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        // base-vertex draws, then the last quad with the vertex buffer bound at it
+        pool.bind(&mut render_pass);
+        pool.draw(&mut render_pass, green, 0..1);
+        pool.draw(&mut render_pass, red, 1..2);
+        pool.draw(&mut render_pass, blue, 2..3);
+        render_pass.set_vertex_buffer(0, pool.vertex_slice(white).unwrap());
+        let range = pool.range(white).unwrap();
+        render_pass.draw_indexed(range.indices(), 0, 3..4);
+    }
+    queue.submit(std::iter::once(encoder.finish()));
+
+    let rgba = init.read_rgba().unwrap();
+    let pixels: Vec<&[u8]> = rgba.chunks(4).collect();
+    assert_eq!(
+        pixels,
+        [
+            [0, 255, 0, 255],
+            [255, 0, 0, 255],
+            [0, 0, 0, 255],
+            [255, 255, 255, 255]
+        ]
+    );
+}
@@ -0,0 +1,46 @@
+#![cfg(feature = "low_level")]
+
+use wgpu_surfaces::labels::NumberFormat;
+use wgpu_surfaces::overlay;
+
+#[test]
+fn numbers_are_written_in_the_configured_notation() {
+    let format = NumberFormat::default();
+    assert_eq!(format.format(1234.5678), "1234.568");
+    assert_eq!(format.format(0.0), "0.000");
+    // rounded to zero, without a sign
+    assert_eq!(format.decimals(2).format(-0.004), "0.00");
+    // scientific at the thresholds, in both directions
+    assert_eq!(format.format(123456.0), "1.235e5");
+    assert_eq!(format.decimals(1).format(-0.00012), "-1.2e-4");
+    let fixed = NumberFormat {
+        scientific_above: 0.0,
+        scientific_below: 0.0,
+        ..format
+    };
+    assert_eq!(fixed.decimals(1).format(123456.0), "123456.0");
+    assert_eq!(fixed.format(0.0002), "0.000");
+    assert_eq!(format.format(f32::NAN), "NaN");
+}
+
+#[test]
+fn decimal_commas_and_units_carry_into_every_label() {
+    let format = NumberFormat {
+        decimal_comma: true,
+        ..Default::default()
+    }
+    .decimals(2)
+    .unit("K");
+    assert_eq!(format.format(273.15), "273,15 K");
+    assert_eq!(format.format(0.0002), "2,00e-4 K");
+
+    let ticks = format.ticks([0.0, 1.0], 5);
+    let labels: Vec<&str> = ticks.iter().map(|(_, label)| label.as_str()).collect();
+    assert_eq!(labels, ["0,00 K", "0,25 K", "0,50 K", "0,75 K", "1,00 K"]);
+    assert_eq!(ticks[4].0, 1.0);
+    assert_eq!(format.ticks([0.0, 1.0], 0).len(), 2);
+
+    // the stroke text draws the comma, a stroke below the baseline
+    let comma = overlay::text_segments(",", [0.0, 0.0], 6.0);
+    assert_eq!(comma, vec![[[2.0, 5.0], [1.0, 7.0]]]);
+}
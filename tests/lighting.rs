@@ -0,0 +1,133 @@
+#![cfg(feature = "low_level")]
+
+use wgpu_surfaces::lighting::{self, Light, LightKind, LightRig, LightSet, LightSetBinding};
+use wgpu_surfaces::material::MaterialKind;
+use wgpu_surfaces::shader_hooks::{self, ShaderHooks};
+use wgpu_surfaces::wgpu_simplified as ws;
+
+mod common;
+
+const VERTEX: &str = include_str!("../ch02/01_simple_surface/shader_vert.wgsl");
+
+fn close(a: [f32; 3], b: [f32; 3]) -> bool {
+    (0..3).all(|i| (a[i] - b[i]).abs() < 1e-4)
+}
+
+#[test]
+fn storage_data_has_a_count_header_and_64_bytes_per_light() {
+    let lights = LightSet::new()
+        .with(Light::default())
+        .with(Light::point([1.0, 2.0, 3.0], 5.0).intensity(2.0))
+        .with(Light::spot([0.0; 3], [0.0, -1.0, 0.0], 0.5, 0.0));
+    let data = lights.storage_data(8);
+    assert_eq!(data.len() as u64, 16 + 3 * 64);
+    let floats: &[f32] = bytemuck::cast_slice(&data[16..]);
+    assert_eq!(bytemuck::cast_slice::<u8, u32>(&data[..4]), [3]);
+    // the point light: position and kind, direction and range, color and intensity
+    assert_eq!(floats[16..20], [1.0, 2.0, 3.0, 1.0]);
+    assert_eq!(floats[23], 5.0);
+    assert_eq!(floats[27], 2.0);
+    // spot cone cosines, inner first
+    assert_eq!(floats[47], 0.0);
+    assert_eq!(floats[44..46], [0.4f32.cos(), 0.5f32.cos()]);
+
+    // lights beyond the capacity are left out
+    let data = lights.storage_data(1);
+    assert_eq!(data.len() as u64, LightSet::storage_size(1));
+    assert_eq!(bytemuck::cast_slice::<u8, u32>(&data[..4]), [1]);
+    let json = serde_json::to_string(&lights).unwrap();
+    assert_eq!(serde_json::from_str::<LightSet>(&json).unwrap(), lights);
+}
+
+#[test]
+fn samples_fade_with_distance_and_outside_the_cone() {
+    let sun = Light::directional([0.0, -2.0, 0.0]).color([1.0, 0.5, 0.0]);
+    let sample = sun.sample([5.0, 5.0, 5.0]);
+    assert!(close(sample.to_light, [0.0, 1.0, 0.0]));
+    assert!(close(sample.radiance, [1.0, 0.5, 0.0]));
+
+    // inverse square without a range, zero at the range with one
+    let bulb = Light::point([0.0, 2.0, 0.0], 0.0).intensity(4.0);
+    assert!(close(bulb.sample([0.0; 3]).radiance, [1.0; 3]));
+    assert!(close(bulb.sample([0.0; 3]).to_light, [0.0, 1.0, 0.0]));
+    let bulb = Light { range: 2.0, ..bulb };
+    assert!(close(bulb.sample([0.0; 3]).radiance, [0.0; 3]));
+    assert!(bulb.sample([0.0, 1.0, 0.0]).radiance[0] > 3.0);
+
+    let spot = Light::spot([0.0, 2.0, 0.0], [0.0, -1.0, 0.0], 0.5, 0.0);
+    assert_eq!(spot.kind, LightKind::Spot);
+    assert!(close(spot.sample([0.0; 3]).radiance, [0.25; 3]));
+    assert!(close(spot.sample([2.0, 0.0, 0.0]).radiance, [0.0; 3]));
+}
+
+#[test]
+fn multi_light_shader_builds_with_the_light_set_bound() {
+    assert_eq!(
+        shader_hooks::hook_points(lighting::MULTI_LIGHT_WGSL),
+        shader_hooks::hook_points(MaterialKind::Phong.fragment_source())
+    );
+    let Some(init) = common::headless(1, 1, 1) else {
+        return;
+    };
+    let device = &init.device;
+    let binding = LightSetBinding::new(device, 4).unwrap();
+    let rig = LightSet::new()
+        .with(Light::default())
+        .with(Light::point([0.0, 2.0, 0.0], 4.0));
+    assert_eq!(binding.write(&init.queue, &rig), 2);
+    let vert_layout = ws::create_bind_group_layout(device, vec![wgpu::ShaderStages::VERTEX]);
+    let frag_layout = ws::create_bind_group_layout(
+        device,
+        vec![wgpu::ShaderStages::FRAGMENT, wgpu::ShaderStages::FRAGMENT],
+    );
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[&vert_layout, &frag_layout, &binding.layout],
+        push_constant_ranges: &[],
+    });
+    let hooks = ShaderHooks::default();
+    let vs_shader = hooks
+        .create_shader_module(device, "vertex", VERTEX)
+        .unwrap();
+    let fs_shader = hooks
+        .create_shader_module(device, "lights", &lighting::multi_light_fragment())
+        .unwrap();
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    ws::IRenderPipeline {
+        vs_shader: Some(&vs_shader),
+        fs_shader: Some(&fs_shader),
+        pipeline_layout: Some(&pipeline_layout),
+        vertex_buffer_layout: &[wgpu::VertexBufferLayout {
+            array_stride: 36,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3],
+        }],
+        ..Default::default()
+    }
+    .new(&init)
+    .unwrap();
+    let error = pollster::block_on(device.pop_error_scope());
+    assert!(error.is_none(), "{:?}", error);
+}
+
+#[test]
+fn light_rigs_follow_the_camera_unless_fixed_in_world_space() {
+    for rig in LightRig::ALL {
+        assert_eq!(LightRig::from_name(rig.name()), Some(rig));
+        assert!(!rig.lights([0.0, 0.0, 5.0], [0.0; 3]).lights.is_empty());
+    }
+    assert_eq!(LightRig::FlatAmbient.next(), LightRig::ThreePoint);
+    assert_eq!(LightRig::from_name("Studio"), Some(LightRig::Studio));
+
+    // the key light shines towards the surface from the camera side
+    let key = LightRig::ThreePoint.lights([0.0, 0.0, 5.0], [0.0; 3]).lights[0];
+    assert!(key.direction[2] < 0.0 && key.direction[1] < 0.0);
+    let key = LightRig::ThreePoint.lights([5.0, 0.0, 0.0], [0.0; 3]).lights[0];
+    assert!(key.direction[0] < 0.0);
+
+    // top down lighting does not move with the camera, even looking straight down
+    let a = LightRig::TopDown.lights([0.0, 5.0, 0.0], [0.0; 3]).lights[0];
+    let b = LightRig::TopDown.lights([3.0, 1.0, 2.0], [0.0; 3]).lights[0];
+    assert!(close(a.direction, [0.0, -1.0, 0.0]));
+    assert_eq!(a, b);
+}
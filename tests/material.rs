@@ -0,0 +1,95 @@
+#![cfg(feature = "low_level")]
+
+use wgpu_surfaces::cli::SurfaceArgs;
+use wgpu_surfaces::material::{self, Material, MaterialKind};
+use wgpu_surfaces::shader_hooks::{self, ShaderHooks};
+use wgpu_surfaces::wgpu_simplified as ws;
+
+mod common;
+
+const VERTEX: &str = include_str!("../ch02/01_simple_surface/shader_vert.wgsl");
+
+#[test]
+fn kinds_have_names_and_default_values() {
+    for kind in MaterialKind::ALL {
+        assert_eq!(MaterialKind::from_name(kind.name()), Some(kind));
+        assert_eq!(kind.default_material().kind(), kind);
+        // every material shader offers the same hook points
+        assert_eq!(
+            shader_hooks::hook_points(kind.fragment_source()),
+            ["fragment_declarations", "fragment_normal", "fragment"]
+        );
+    }
+    assert_eq!(MaterialKind::from_name("PBR"), Some(MaterialKind::Pbr));
+    assert_eq!(MaterialKind::from_name("toon"), None);
+    assert_eq!(MaterialKind::Matcap.next(), MaterialKind::Phong);
+    // the chapter values
+    assert_eq!(Material::default().uniform_data(), [0.1, 0.7, 0.4, 30.0]);
+
+    let rough = Material::Pbr {
+        metallic: 2.0,
+        roughness: -1.0,
+        ambient: 0.1,
+        reflectance: 0.5,
+    };
+    assert_eq!(rough.uniform_data(), [1.0, 0.0, 0.1, 0.5]);
+    assert_eq!(
+        std::mem::size_of_val(&rough.uniform_data()) as u64,
+        material::MATERIAL_UNIFORM_SIZE
+    );
+}
+
+#[test]
+fn material_is_a_command_line_option() {
+    let args = SurfaceArgs::default().parse(["--material".to_string(), "pbr".to_string()]);
+    assert_eq!(args.unwrap().material, MaterialKind::Pbr);
+    assert!(
+        SurfaceArgs::default()
+            .parse(["--material=glass".to_string()])
+            .is_err()
+    );
+    assert_eq!(SurfaceArgs::default().material, MaterialKind::Phong);
+}
+
+#[test]
+fn every_kind_builds_a_pipeline_with_the_chapter_layout() {
+    let Some(init) = common::headless(1, 1, 1) else {
+        return;
+    };
+    let device = &init.device;
+    let vert_layout = ws::create_bind_group_layout(device, vec![wgpu::ShaderStages::VERTEX]);
+    let frag_layout = ws::create_bind_group_layout(
+        device,
+        vec![wgpu::ShaderStages::FRAGMENT, wgpu::ShaderStages::FRAGMENT],
+    );
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[&vert_layout, &frag_layout],
+        push_constant_ranges: &[],
+    });
+    let hooks = ShaderHooks::default();
+    let vs_shader = hooks
+        .create_shader_module(device, "vertex", VERTEX)
+        .unwrap();
+    for kind in MaterialKind::ALL {
+        let fs_shader = hooks
+            .create_shader_module(device, kind.fragment_label(), kind.fragment_source())
+            .unwrap();
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        ws::IRenderPipeline {
+            vs_shader: Some(&vs_shader),
+            fs_shader: Some(&fs_shader),
+            pipeline_layout: Some(&pipeline_layout),
+            vertex_buffer_layout: &[wgpu::VertexBufferLayout {
+                array_stride: 36,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3],
+            }],
+            ..Default::default()
+        }
+        .new(&init)
+        .unwrap();
+        let error = pollster::block_on(device.pop_error_scope());
+        assert!(error.is_none(), "{}: {:?}", kind.name(), error);
+    }
+}
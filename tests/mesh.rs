@@ -0,0 +1,70 @@
+#![cfg(feature = "low_level")]
+
+use wgpu_surfaces::mesh::{MeshBuilder, VertexAttributes};
+use wgpu_surfaces::surface_data::ISurfaceOutput;
+
+fn triangle() -> ISurfaceOutput {
+    ISurfaceOutput {
+        positions: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+        normals: vec![[0.0, 0.0, 1.0]; 3],
+        colors: vec![[1.0, 0.0, 0.0]; 3],
+        colors2: vec![[1.0, 1.0, 1.0]; 3],
+        uvs: vec![[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]],
+        indices: vec![0, 1, 2],
+        indices2: vec![0, 1, 1, 2, 2, 0],
+    }
+}
+
+#[test]
+fn layouts_keep_shader_locations_and_pack_offsets() {
+    let lit = VertexAttributes::LIT.layout();
+    assert_eq!(lit.stride, 36);
+    let placed: Vec<(u32, u64)> = lit
+        .attributes
+        .iter()
+        .map(|a| (a.shader_location, a.offset))
+        .collect();
+    assert_eq!(placed, [(0, 0), (1, 12), (2, 24)]);
+
+    let unlit = VertexAttributes::UNLIT.layout();
+    assert_eq!(unlit.stride, 24);
+    assert_eq!(unlit.attributes[1].shader_location, 2);
+    assert_eq!(unlit.attributes[1].offset, 12);
+
+    let textured = VertexAttributes::TEXTURED.layout();
+    assert_eq!(textured.stride, 20);
+    assert_eq!(textured.attributes[1].shader_location, 4);
+    assert_eq!(textured.attributes[1].format, wgpu::VertexFormat::Float32x2);
+    assert_eq!(
+        VertexAttributes::UNLIT.wgsl_inputs(),
+        "@location(0) position: vec4f, @location(2) color: vec4f"
+    );
+}
+
+#[test]
+fn builder_interleaves_only_the_chosen_attributes() {
+    let lit = MeshBuilder::new().build(&triangle());
+    assert_eq!(lit.vertex_count(), 3);
+    assert_eq!(
+        lit.vertices[..9],
+        [0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0]
+    );
+    assert_eq!(lit.wire_vertices[6..9], [1.0, 1.0, 1.0]);
+
+    let unlit = MeshBuilder::new().normals(false).build(&triangle());
+    assert_eq!(unlit.vertices.len(), 3 * 6);
+    assert_eq!(unlit.vertices[6..12], [1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+
+    let textured = MeshBuilder::with_attributes(VertexAttributes::TEXTURED).build(&triangle());
+    assert_eq!(textured.vertices[10..15], [0.0, 1.0, 0.0, 0.0, 1.0]);
+    // without colors the wireframe shares the surface vertices
+    assert_eq!(textured.wire_vertices, textured.vertices);
+    assert_eq!(textured.indices2.len(), 6);
+}
+
+#[test]
+fn missing_attribute_data_is_zero_filled() {
+    let builder = MeshBuilder::with_attributes(VertexAttributes::UNLIT).uvs(true);
+    let data = builder.vertices(&[[1.0, 2.0, 3.0]], &[], &[], &[]);
+    assert_eq!(data, [1.0, 2.0, 3.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+}
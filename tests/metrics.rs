@@ -0,0 +1,126 @@
+#![cfg(feature = "low_level")]
+
+use std::f32::consts::PI;
+
+use wgpu_surfaces::surface_data::{self as sd, IParametricSurface, ISurfaceOutput};
+
+fn mesh(positions: Vec<[f32; 3]>, indices: Vec<u16>) -> ISurfaceOutput {
+    ISurfaceOutput {
+        positions,
+        indices,
+        ..Default::default()
+    }
+}
+
+// the box [0, 1] x [0, 2] x [0, 3] as 12 triangles, each face with its own vertices
+fn cuboid() -> ISurfaceOutput {
+    let corner = |k: usize| {
+        [
+            (k & 1) as f32,
+            2.0 * (k >> 1 & 1) as f32,
+            3.0 * (k >> 2 & 1) as f32,
+        ]
+    };
+    let faces = [
+        [0, 2, 3, 1],
+        [4, 5, 7, 6],
+        [0, 1, 5, 4],
+        [2, 6, 7, 3],
+        [0, 4, 6, 2],
+        [1, 3, 7, 5],
+    ];
+    let mut positions = vec![];
+    let mut indices = vec![];
+    for face in faces {
+        let base = positions.len() as u16;
+        positions.extend(face.map(corner));
+        indices.extend([0, 1, 2, 2, 3, 0].map(|k| base + k));
+    }
+    mesh(positions, indices)
+}
+
+// uv sphere with repeated seam and pole vertices, like the parametric grids
+fn sphere(radius: f32, n: u16) -> ISurfaceOutput {
+    let mut positions = vec![];
+    for i in 0..=n {
+        for j in 0..=n {
+            let (u, v) = (2.0 * PI * i as f32 / n as f32, PI * j as f32 / n as f32);
+            positions.push([
+                radius * v.sin() * u.cos(),
+                radius * v.cos() + 1.0,
+                radius * v.sin() * u.sin(),
+            ]);
+        }
+    }
+    mesh(positions, sd::grid_indices(n, n).0)
+}
+
+fn close(a: f32, b: f32, tolerance: f32) -> bool {
+    (a - b).abs() <= tolerance * b.abs().max(1.0)
+}
+
+#[test]
+fn cuboid_metrics_are_exact() {
+    let box_mesh = cuboid();
+    let metrics = box_mesh.metrics();
+    assert_eq!(metrics.triangles, 12);
+    assert!(close(metrics.area, 22.0, 1e-6));
+    assert!(close(metrics.volume.unwrap(), 6.0, 1e-6));
+    let [x, y, z] = metrics.centroid;
+    assert!(close(x, 0.5, 1e-6) && close(y, 1.0, 1e-6) && close(z, 1.5, 1e-6));
+    // m (b^2 + c^2) / 12 about the x axis, no products of inertia
+    let inertia = metrics.inertia.unwrap();
+    assert!(close(inertia[0][0], 6.0 * (4.0 + 9.0) / 12.0, 1e-5));
+    assert!(close(inertia[1][1], 6.0 * (1.0 + 9.0) / 12.0, 1e-5));
+    assert!(close(inertia[2][2], 6.0 * (1.0 + 4.0) / 12.0, 1e-5));
+    assert!(inertia[0][1].abs() < 1e-5 && inertia[1][2].abs() < 1e-5);
+
+    // the same with inward winding
+    let mut inward = cuboid();
+    for t in inward.indices.chunks_exact_mut(3) {
+        t.swap(1, 2);
+    }
+    assert_eq!(inward.metrics(), metrics);
+
+    // one face missing: open, no volume
+    let mut open = cuboid();
+    open.indices.truncate(30);
+    assert_eq!(open.volume(), None);
+    assert!(close(open.surface_area(), 16.0, 1e-6));
+    assert!(open.metrics().to_string().contains("open"));
+}
+
+#[test]
+fn sphere_grid_closes_across_seam_and_poles() {
+    let r = 2.0;
+    let metrics = sphere(r, 96).metrics();
+    assert!(close(metrics.area, 4.0 * PI * r * r, 2e-3));
+    let volume = metrics.volume.expect("closed sphere");
+    assert!(close(volume, 4.0 / 3.0 * PI * r * r * r, 3e-3));
+    let [x, y, z] = metrics.centroid;
+    assert!(x.abs() < 1e-4 && (y - 1.0).abs() < 1e-4 && z.abs() < 1e-4);
+    let inertia = metrics.inertia.unwrap();
+    for (i, row) in inertia.iter().enumerate() {
+        assert!(close(row[i], 0.4 * volume * r * r, 5e-3));
+    }
+
+    // a height field is open, its centroid is the area centroid
+    let mut plane = sd::ISimpleSurface::from_fn(|x, _, _| 0.1 * x);
+    let metrics = plane.new().metrics();
+    assert_eq!(metrics.volume, None);
+    assert_eq!(metrics.centroid, metrics.area_centroid);
+}
+
+#[test]
+fn torus_area_and_volume_match_the_analytic_ratio() {
+    // A^3 / V^2 = 16 pi^2 R / r does not depend on the normalizing scale
+    let output = IParametricSurface {
+        surface_type: 21,
+        ..Default::default()
+    }
+    .new();
+    let metrics = output.metrics();
+    let volume = metrics.volume.expect("closed torus");
+    let ratio = metrics.area.powi(3) / (volume * volume);
+    assert!(close(ratio, 16.0 * PI * PI / 0.3, 1e-2), "{ratio}");
+}
@@ -0,0 +1,56 @@
+#![cfg(feature = "low_level")]
+
+use wgpu_surfaces::motion::{self, MotionHistory};
+
+mod common;
+
+#[test]
+fn heat_color_keeps_still_vertices_and_saturates_fast_ones() {
+    let color = [0.2, 0.4, 0.6];
+    assert_eq!(motion::heat_color(color, 0.0, 2.0), color);
+    let hot = motion::heat_color(color, 100.0, 2.0);
+    assert!(hot.iter().all(|c| (c - 1.0).abs() < 1e-4));
+    // warmer colors first: red rises before green and blue
+    let warm = motion::heat_color([0.0; 3], 0.2, 1.0);
+    assert!(warm[0] > 0.0 && warm[1] == 0.0 && warm[2] == 0.0);
+}
+
+#[test]
+fn vertex_speeds_are_distances_over_frame_time() {
+    let previous = [[0.0, 0.0, 0.0], [1.0, 1.0, 1.0]];
+    let current = [[3.0, 4.0, 0.0], [1.0, 1.0, 1.0]];
+    assert_eq!(
+        motion::vertex_speeds(&previous, &current, 0.5),
+        vec![10.0, 0.0]
+    );
+    assert_eq!(
+        motion::vertex_speeds(&previous, &current, 0.0),
+        vec![0.0, 0.0]
+    );
+    assert!(motion::with_heat("// body").contains("fn heat_color"));
+}
+
+#[test]
+fn motion_history_ping_pongs_and_restarts_on_new_vertex_count() {
+    let Some(init) = common::headless(1, 1, 1) else {
+        return;
+    };
+    let (device, queue) = (&init.device, &init.queue);
+    let mut history = MotionHistory::new(device);
+
+    history.push(device, queue, &[[0.0; 3]; 4], 1.0);
+    assert_eq!(history.speed_factor(), 0.0);
+    assert_eq!(history.previous().len(), 48);
+
+    history.push(device, queue, &[[1.0; 3]; 4], 1.25);
+    assert!((history.frame_time() - 0.25).abs() < 1e-6);
+    assert!((history.speed_factor() - 4.0).abs() < 1e-4);
+
+    history.push(device, queue, &[[1.0; 3]; 6], 1.5);
+    assert_eq!(history.frame_time(), 0.0);
+    assert_eq!(history.previous().len(), 72);
+
+    history.clear();
+    history.push(device, queue, &[[1.0; 3]; 6], 2.0);
+    assert_eq!(history.speed_factor(), 0.0);
+}
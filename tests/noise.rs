@@ -0,0 +1,184 @@
+#![cfg(feature = "low_level")]
+
+use wgpu::util::DeviceExt;
+use wgpu_surfaces::noise::{self, NoiseParams};
+use wgpu_surfaces::wgpu_simplified as ws;
+
+mod common;
+
+fn grid() -> Vec<[f32; 2]> {
+    (0..256)
+        .map(|k| [(k % 16) as f32 * 0.37 - 2.9, (k / 16) as f32 * 0.29 - 2.1])
+        .collect()
+}
+
+#[test]
+fn noise_is_seeded_and_bounded() {
+    for p in grid() {
+        assert_eq!(noise::perlin2(p, 7), noise::perlin2(p, 7));
+        for v in [
+            noise::value2(p, 7),
+            noise::perlin2(p, 7),
+            noise::simplex2(p, 7),
+            noise::perlin3([p[0], p[1], 0.4], 7),
+        ] {
+            assert!((-1.0..=1.0).contains(&v));
+        }
+    }
+    let differs = grid()
+        .into_iter()
+        .filter(|&p| noise::perlin2(p, 1) != noise::perlin2(p, 2))
+        .count();
+    assert!(differs > 200);
+
+    // gradient noise vanishes on the lattice and varies smoothly in between
+    assert_eq!(noise::perlin2([3.0, -2.0], 5), 0.0);
+    let a = noise::perlin2([0.5, 0.5], 5);
+    assert!((noise::perlin2([0.501, 0.5], 5) - a).abs() < 0.01);
+
+    assert_eq!(NoiseParams::named("terrain"), NoiseParams::named("terrain"));
+    assert_ne!(noise::seed_from_str("a"), noise::seed_from_str("b"));
+}
+
+#[test]
+fn fbm_sums_octaves() {
+    let one = NoiseParams {
+        octaves: 1,
+        frequency: 2.0,
+        amplitude: 3.0,
+        ..NoiseParams::seeded(9)
+    };
+    assert_eq!(one.fbm2([0.3, 0.7]), 3.0 * noise::perlin2([0.6, 1.4], 9));
+    let params = NoiseParams::seeded(9);
+    for p in grid() {
+        assert!(params.fbm2(p).abs() <= 1.0);
+        assert!(params.fbm3([p[0], 0.5, p[1]]).abs() <= 1.0);
+    }
+}
+
+// the shader library evaluated in a compute pass gives the CPU values
+#[test]
+fn gpu_noise_matches_cpu() {
+    let Some(init) = common::headless(1, 1, 1) else {
+        return;
+    };
+    let flags = init.adapter.get_downlevel_capabilities().flags;
+    if !flags.contains(wgpu::DownlevelFlags::COMPUTE_SHADERS) {
+        return;
+    }
+    let shader = noise::with_noise(
+        r#"
+@group(0) @binding(0) var<uniform> params: NoiseParams;
+@group(0) @binding(1) var<storage, read> points: array<vec2f>;
+@group(0) @binding(2) var<storage, read_write> values: array<f32>;
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3u) {
+    let k = id.x;
+    if k >= arrayLength(&points) {
+        return;
+    }
+    let p = points[k];
+    values[6u * k] = noise_value2(p, params.seed);
+    values[6u * k + 1u] = noise_perlin2(p, params.seed);
+    values[6u * k + 2u] = noise_simplex2(p, params.seed);
+    values[6u * k + 3u] = noise_perlin3(vec3f(p, 0.4), params.seed);
+    values[6u * k + 4u] = noise_fbm2(p, params);
+    values[6u * k + 5u] = noise_fbm3(vec3f(p, 0.4), params);
+}
+"#,
+    );
+    let params = NoiseParams {
+        offset: [0.25, -0.5],
+        ..NoiseParams::seeded(12345)
+    };
+    let points = grid();
+    let device = &init.device;
+    let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(shader.into()),
+    });
+    let buffer = |contents: &[u8], usage| {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents,
+            usage,
+        })
+    };
+    let params_buffer = buffer(bytemuck::bytes_of(&params), wgpu::BufferUsages::UNIFORM);
+    let points_buffer = buffer(bytemuck::cast_slice(&points), wgpu::BufferUsages::STORAGE);
+    let size = (points.len() * 6 * 4) as u64;
+    let values_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let (layout, bind_group) = ws::create_bind_group_storage(
+        device,
+        vec![wgpu::ShaderStages::COMPUTE; 3],
+        vec![
+            wgpu::BufferBindingType::Uniform,
+            wgpu::BufferBindingType::Storage { read_only: true },
+            wgpu::BufferBindingType::Storage { read_only: false },
+        ],
+        &[
+            params_buffer.as_entire_binding(),
+            points_buffer.as_entire_binding(),
+            values_buffer.as_entire_binding(),
+        ],
+    )
+    .unwrap();
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[&layout],
+        push_constant_ranges: &[],
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: None,
+        layout: Some(&pipeline_layout),
+        module: &module,
+        entry_point: Some("cs_main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups((points.len() as u32).div_ceil(64), 1, 1);
+    }
+    encoder.copy_buffer_to_buffer(&values_buffer, 0, &readback, 0, size);
+    init.queue.submit(std::iter::once(encoder.finish()));
+    readback.slice(..).map_async(wgpu::MapMode::Read, |_| {});
+    device.poll(wgpu::PollType::Wait).unwrap();
+    let gpu: Vec<f32> = bytemuck::cast_slice(&readback.slice(..).get_mapped_range()).to_vec();
+
+    let seed = params.seed;
+    for (k, &p) in points.iter().enumerate() {
+        let p3 = [p[0], p[1], 0.4];
+        let cpu = [
+            noise::value2(p, seed),
+            noise::perlin2(p, seed),
+            noise::simplex2(p, seed),
+            noise::perlin3(p3, seed),
+            params.fbm2(p),
+            params.fbm3(p3),
+        ];
+        for (i, v) in cpu.into_iter().enumerate() {
+            let g = gpu[6 * k + i];
+            assert!(
+                (g - v).abs() < 1e-4,
+                "function {i} at {p:?}: gpu {g}, cpu {v}"
+            );
+        }
+    }
+}
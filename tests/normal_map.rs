@@ -0,0 +1,204 @@
+#![cfg(feature = "low_level")]
+
+use cgmath::{InnerSpace, Vector3};
+use wgpu_surfaces::material::MaterialKind;
+use wgpu_surfaces::noise::NoiseParams;
+use wgpu_surfaces::normal_map::{self, MicroBumps, NormalMap, TangentVertex};
+use wgpu_surfaces::shader_hooks::ShaderHooks;
+use wgpu_surfaces::surface_data::{IParametricSurface, ISurfaceOutput};
+use wgpu_surfaces::wgpu_simplified as ws;
+
+mod common;
+
+// a unit quad in the xz plane facing up, with u along x and v along v_sign * z
+fn quad(v_sign: f32) -> ISurfaceOutput {
+    let positions = vec![
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [1.0, 0.0, 1.0],
+        [0.0, 0.0, 1.0],
+    ];
+    ISurfaceOutput {
+        uvs: positions.iter().map(|p| [p[0], v_sign * p[2]]).collect(),
+        normals: vec![[0.0, 1.0, 0.0]; 4],
+        colors: vec![[1.0; 3]; 4],
+        colors2: vec![[1.0; 3]; 4],
+        positions,
+        indices: vec![0, 2, 1, 0, 3, 2],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn tangents_follow_the_uv_directions() {
+    let plane = quad(1.0);
+    for (t, b) in plane.tangents().iter().zip(plane.bitangents()) {
+        assert_eq!(t[..3], [1.0, 0.0, 0.0]);
+        assert!((Vector3::from(b) - Vector3::unit_z()).magnitude() < 1e-6);
+    }
+
+    // v running the other way flips the handedness, not the tangent
+    let mirrored = quad(-1.0);
+    let (t, t2) = (plane.tangents()[0], mirrored.tangents()[0]);
+    assert_eq!(t[..3], t2[..3]);
+    assert_eq!(t[3], -t2[3]);
+    assert_eq!(mirrored.bitangents()[0], [0.0, 0.0, -1.0]);
+
+    // without uvs every vertex still gets a unit tangent across its normal
+    let no_uvs = ISurfaceOutput {
+        uvs: vec![],
+        ..quad(1.0)
+    };
+    for t in no_uvs.tangents() {
+        assert!((Vector3::new(t[0], t[1], t[2]).magnitude() - 1.0).abs() < 1e-6);
+        assert_eq!(t[1], 0.0);
+    }
+}
+
+#[test]
+fn surface_tangents_are_unit_and_across_the_normals() {
+    let torus = IParametricSurface {
+        surface_type: 21,
+        ..Default::default()
+    }
+    .new();
+    let vertices = normal_map::tangent_vertices(&torus);
+    assert_eq!(vertices.len(), torus.positions.len());
+    for v in vertices.iter() {
+        let t = Vector3::new(v.tangent[0], v.tangent[1], v.tangent[2]);
+        assert!((t.magnitude() - 1.0).abs() < 1e-4);
+        assert!(t.dot(Vector3::from(v.normal)).abs() < 1e-4);
+        assert!(v.tangent[3].abs() == 1.0);
+    }
+    assert_eq!(std::mem::size_of::<TangentVertex>(), 60);
+    assert_eq!(TangentVertex::layout().array_stride, 60);
+}
+
+#[test]
+fn height_fields_become_tangent_space_normals() {
+    let flat = normal_map::normal_map_from_heights(4, 4, &[0.3; 16], 5.0);
+    assert!(flat.chunks(4).all(|texel| texel == [128, 128, 255, 255]));
+
+    // heights growing along x tilt the normals towards -x; the wrap-around keeps the
+    // edge texels defined
+    let ramp: Vec<f32> = (0..16).map(|k| (k % 4) as f32).collect();
+    let rgba = normal_map::normal_map_from_heights(4, 4, &ramp, 1.0);
+    assert!(rgba[4 * 5] < 128 && rgba[4 * 5 + 1] == 128);
+    assert!(rgba[4 * 4] > 128); // the wrapping edge falls towards x = 0
+
+    let noise = NoiseParams::default();
+    let detail = normal_map::detail_normal_map(32, &noise, 4.0);
+    assert_eq!(detail.len(), 4 * 32 * 32);
+    assert!(
+        detail
+            .chunks(4)
+            .all(|texel| texel[2] >= 128 && texel[3] == 255)
+    );
+}
+
+#[test]
+fn normal_map_shaders_build_a_pipeline() {
+    let Some(init) = common::headless(1, 1, 1) else {
+        return;
+    };
+    let device = &init.device;
+    let rgba = normal_map::detail_normal_map(16, &NoiseParams::default(), 2.0);
+    let mut map = NormalMap::new(device, &init.queue, 16, 16, &rgba);
+    map.tiling = 4.0;
+    map.write(&init.queue);
+    assert_eq!(map.uniform_data(), [1.0, 4.0, 0.0, 0.0]);
+
+    let vert_layout = ws::create_bind_group_layout(device, vec![wgpu::ShaderStages::VERTEX]);
+    let frag_layout = ws::create_bind_group_layout(
+        device,
+        vec![wgpu::ShaderStages::FRAGMENT, wgpu::ShaderStages::FRAGMENT],
+    );
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[&vert_layout, &frag_layout, &map.layout],
+        push_constant_ranges: &[],
+    });
+    let hooks = ShaderHooks::default();
+    let vs_shader = hooks
+        .create_shader_module(device, "vertex", normal_map::NORMAL_MAP_VERT_WGSL)
+        .unwrap();
+    let fs_shader = hooks
+        .create_shader_module(device, "fragment", normal_map::NORMAL_MAP_FRAG_WGSL)
+        .unwrap();
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    ws::IRenderPipeline {
+        vs_shader: Some(&vs_shader),
+        fs_shader: Some(&fs_shader),
+        pipeline_layout: Some(&pipeline_layout),
+        vertex_buffer_layout: &[TangentVertex::layout()],
+        ..Default::default()
+    }
+    .new(&init)
+    .unwrap();
+    let error = pollster::block_on(device.pop_error_scope());
+    assert!(error.is_none(), "{:?}", error);
+}
+
+#[test]
+fn micro_bumps_fade_with_the_distance() {
+    let bumps = MicroBumps::default();
+    assert_eq!(bumps.fade(0.0), 1.0);
+    assert_eq!(bumps.fade(bumps.fade_near), 1.0);
+    assert_eq!(bumps.fade(bumps.fade_far), 0.0);
+    assert!((bumps.fade(0.5 * (bumps.fade_near + bumps.fade_far)) - 0.5).abs() < 1e-6);
+    assert_eq!(bumps.uniform_data()[0], bumps.strength);
+    assert_eq!(
+        std::mem::size_of_val(&bumps.uniform_data()) as u64,
+        normal_map::MICRO_BUMPS_UNIFORM_SIZE
+    );
+}
+
+#[test]
+fn micro_bumps_fit_every_material_shader() {
+    let hooks = MicroBumps::hooks(2);
+    let sources: Vec<&str> = MaterialKind::ALL
+        .iter()
+        .map(|kind| kind.fragment_source())
+        .collect();
+    hooks.check(&sources).unwrap();
+    let Some(init) = common::headless(1, 1, 1) else {
+        return;
+    };
+    let device = &init.device;
+    let layouts = [
+        vec![wgpu::ShaderStages::VERTEX],
+        vec![wgpu::ShaderStages::FRAGMENT, wgpu::ShaderStages::FRAGMENT],
+        vec![wgpu::ShaderStages::FRAGMENT],
+    ]
+    .map(|stages| ws::create_bind_group_layout(device, stages));
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[&layouts[0], &layouts[1], &layouts[2]],
+        push_constant_ranges: &[],
+    });
+    let vs_shader = ShaderHooks::default()
+        .create_shader_module(
+            device,
+            "vertex",
+            include_str!("../ch02/01_simple_surface/shader_vert.wgsl"),
+        )
+        .unwrap();
+    for kind in MaterialKind::ALL {
+        let fs_shader = hooks
+            .create_shader_module(device, kind.fragment_label(), kind.fragment_source())
+            .unwrap();
+        ws::IRenderPipeline {
+            vs_shader: Some(&vs_shader),
+            fs_shader: Some(&fs_shader),
+            pipeline_layout: Some(&pipeline_layout),
+            vertex_buffer_layout: &[wgpu::VertexBufferLayout {
+                array_stride: 36,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3],
+            }],
+            ..Default::default()
+        }
+        .new(&init)
+        .unwrap();
+    }
+}
@@ -0,0 +1,92 @@
+#![cfg(feature = "low_level")]
+
+use std::time::Duration;
+
+use web_time::Instant;
+use wgpu_surfaces::overlay::{self, SceneStats, StatsOverlay};
+use wgpu_surfaces::wgpu_simplified::FpsCounter;
+
+#[test]
+fn text_is_laid_out_in_glyph_cells() {
+    // the 1 is a stem, a flag and a foot; y grows down from the top of the line
+    let one = overlay::text_segments("1", [10.0, 20.0], 12.0);
+    assert_eq!(one.len(), 3);
+    assert_eq!(one[0], [[14.0, 32.0], [14.0, 20.0]]);
+
+    // unknown characters are blank but take their cell, lower case is upper case
+    let text = overlay::text_segments("a?1", [10.0, 20.0], 12.0);
+    assert_eq!(
+        text.len(),
+        overlay::text_segments("A", [0.0; 2], 12.0).len() + 3
+    );
+    assert_eq!(text.last().unwrap()[0][0], 10.0 + 24.0 + 2.0);
+    let lines = overlay::text_segments("1\n1", [10.0, 20.0], 12.0);
+    assert_eq!(lines[3], [[14.0, 50.0], [14.0, 38.0]]);
+}
+
+#[test]
+fn fps_counters_keep_the_recent_frame_times() {
+    let mut fps = FpsCounter::new();
+    let start = Instant::now();
+    for ms in [0, 10, 30, 70] {
+        fps.tick_at(start + Duration::from_millis(ms));
+    }
+    let times: Vec<f32> = fps.frame_times().collect();
+    assert_eq!(times.len(), 3);
+    assert!((times[2] - 40.0).abs() < 1e-3);
+    let stats = fps.stats();
+    assert_eq!(stats.fps, 4);
+    assert!((stats.mean_ms - 70.0 / 3.0).abs() < 1e-3);
+    assert!((stats.max_ms - 40.0).abs() < 1e-3 && stats.last_ms == stats.max_ms);
+
+    // the frame rate counts the last second, the times the last HISTORY frames
+    for k in 1..=2 * FpsCounter::HISTORY as u64 {
+        fps.tick_at(start + Duration::from_millis(70 + 20 * k));
+    }
+    assert_eq!(fps.stats().fps, 51); // the frame a second ago still counts
+    assert_eq!(fps.frame_times().len(), FpsCounter::HISTORY);
+    assert!(fps.frame_times().all(|ms| (ms - 20.0).abs() < 1e-3));
+}
+
+#[test]
+fn the_overlay_stays_in_front_of_the_scene() {
+    let mut fps = FpsCounter::new();
+    let start = Instant::now();
+    for (k, ms) in [0, 10, 30, 130].into_iter().enumerate() {
+        fps.tick_at(start + Duration::from_millis(ms + 5 * k as u64));
+    }
+    let stats_overlay = StatsOverlay::default();
+    let mut scene = SceneStats {
+        vertices: 1234,
+        draw_calls: 2,
+        gpu_ms: None,
+    };
+    let text = stats_overlay.text(&fps.stats(), &scene, [800, 600]);
+    assert!(text.contains("VERTICES 1234") && text.contains("RESOLUTION 800X600"));
+    assert!(!text.contains("GPU"));
+    scene.gpu_ms = Some(1.234);
+    let text = stats_overlay.text(&fps.stats(), &scene, [800, 600]);
+    assert!(text.ends_with("\nGPU 1.23 MS"), "{}", text);
+    scene.gpu_ms = None;
+
+    let lines = stats_overlay.lines(&fps, &scene, [800, 600]);
+    assert!(lines.len().is_multiple_of(2));
+    assert!(
+        lines
+            .iter()
+            .all(|(p, _)| p[0].abs() <= 1.0 && p[1].abs() <= 1.0 && p[2] == 0.0)
+    );
+    // two segments between the three frame times, the 100 ms one clamped to the top
+    let graph: Vec<[f32; 3]> = lines
+        .iter()
+        .filter(|(_, color)| *color == stats_overlay.graph_color)
+        .map(|(p, _)| *p)
+        .collect();
+    assert_eq!(graph.len(), 4);
+    let budgets = lines
+        .iter()
+        .filter(|(_, color)| *color == stats_overlay.budget_color);
+    assert_eq!(budgets.count(), 4);
+    assert!(graph[3][1] > graph[0][1]);
+    assert_eq!(graph[3][0], 2.0 * (12.0 + 240.0) / 800.0 - 1.0);
+}
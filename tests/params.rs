@@ -0,0 +1,56 @@
+#![cfg(feature = "low_level")]
+
+use wgpu_surfaces::params::{self, ParamSet};
+use wgpu_surfaces::surface_data::IParametricSurface;
+
+#[test]
+fn nudge_clamps_to_range() {
+    let set = params::parametric_params();
+    let mut ps = IParametricSurface::default();
+    // scale is the first parameter
+    assert!(!set.nudge_selected(&mut ps, 100.0));
+    assert_eq!(ps.scale, 8.0);
+    set.nudge_selected(&mut ps, -100.0);
+    assert_eq!(ps.scale, 0.5);
+}
+
+#[test]
+fn resolution_change_requests_recreate() {
+    let mut set = params::parametric_params();
+    let mut ps = IParametricSurface::default();
+    set.select_next();
+    assert_eq!(set.selected().unwrap().name, "u resolution");
+    let old = ps.u_resolution;
+    assert!(set.nudge_selected(&mut ps, 1.0));
+    assert_eq!(ps.u_resolution, old + 4);
+}
+
+#[test]
+fn selection_wraps_and_describes() {
+    let mut set = ParamSet::<[f32; 2]>::new()
+        .add(
+            "a",
+            [0.0, 1.0],
+            0.1,
+            |s| s[0],
+            |s, v| {
+                s[0] = v;
+                false
+            },
+        )
+        .add(
+            "b",
+            [0.0, 2.0],
+            0.5,
+            |s| s[1],
+            |s, v| {
+                s[1] = v;
+                false
+            },
+        );
+    set.select_previous();
+    assert_eq!(set.selected, 1);
+    set.select_next();
+    assert_eq!(set.selected, 0);
+    assert_eq!(set.describe(&[0.5, 0.0], 4), "a [##--] 0.50");
+}
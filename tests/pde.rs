@@ -0,0 +1,136 @@
+#![cfg(feature = "low_level")]
+
+use wgpu_surfaces::pde::{PdeKind, PdeMode, PdeSolver};
+use wgpu_surfaces::colormap;
+use wgpu_surfaces::surface_data::IStreamingSurface;
+
+fn max_abs(values: &[f32]) -> f32 {
+    values.iter().fold(0.0, |m, v| m.max(v.abs()))
+}
+
+#[test]
+fn heat_equation_decays() {
+    let mut solver = PdeSolver::new(PdeKind::Heat, 48, 40);
+    solver.add_gaussian(0.2, -0.3, 1.0, 0.2);
+    let mut last = max_abs(&solver.u);
+    for _ in 0..20 {
+        solver.advance(0.1);
+        let current = max_abs(&solver.u);
+        assert!(current.is_finite());
+        assert!(current <= last + 1e-6, "{current} > {last}");
+        last = current;
+    }
+    assert!(last < 0.5);
+}
+
+#[test]
+fn wave_equation_stays_bounded() {
+    let mut solver = PdeSolver::new(PdeKind::Wave, 64, 64);
+    solver.damping = 0.0;
+    solver.add_gaussian(0.0, 0.0, 1.0, 0.15);
+    for _ in 0..100 {
+        solver.advance(1.0 / 30.0);
+        assert!(max_abs(&solver.u) < 1.5);
+    }
+    assert!(solver.time > 3.0);
+}
+
+#[test]
+fn boundary_stays_fixed() {
+    let mut solver = PdeSolver::new(PdeKind::Wave, 16, 16);
+    solver.add_gaussian(0.9, 0.9, 1.0, 0.5);
+    solver.advance(1.0);
+    let cols = solver.cols as usize;
+    for i in 0..solver.rows as usize {
+        assert_eq!(solver.u[i * cols], 0.0);
+        assert_eq!(solver.u[i * cols + cols - 1], 0.0);
+    }
+}
+
+#[test]
+fn write_to_resizes_streaming_surface() {
+    let mut solver = PdeSolver::new(PdeKind::Heat, 10, 12);
+    solver.add_gaussian(0.0, 0.0, 2.0, 0.3);
+    let mut surface = IStreamingSurface::default();
+    solver.write_to(&mut surface);
+    assert_eq!((surface.rows, surface.cols), (10, 12));
+    assert_eq!(surface.values, solver.u);
+}
+
+#[test]
+fn modes_follow_the_analytic_solution() {
+    let mode = PdeMode {
+        m: 1,
+        n: 2,
+        amplitude: 0.8,
+    };
+    // the wave scheme drifts out of phase faster than the heat scheme loses accuracy
+    for (kind, tolerance) in [(PdeKind::Heat, 1e-3), (PdeKind::Wave, 0.05)] {
+        let mut solver = PdeSolver::new(kind, 48, 48);
+        solver.set_mode(&mode);
+        assert!(max_abs(&solver.residual(&mode)) < 1e-6);
+        let mut worst: f32 = 0.0;
+        for _ in 0..30 {
+            solver.advance(0.1);
+            worst = worst.max(max_abs(&solver.residual(&mode)));
+        }
+        assert!(worst < tolerance, "{kind:?}: {worst}");
+        // the solution has moved well away from the initial shape
+        assert_eq!(mode.factor(&solver, 0.0), 1.0);
+        assert!((mode.factor(&solver, solver.time) - 1.0).abs() > 0.3);
+    }
+}
+
+#[test]
+fn overdamped_modes_creep_back_without_oscillating() {
+    let mut solver = PdeSolver::new(PdeKind::Wave, 16, 16);
+    let mode = PdeMode::default();
+    solver.damping = 20.0;
+    let mut last = 1.0;
+    for step in 1..50 {
+        let factor = mode.factor(&solver, 0.1 * step as f32);
+        assert!(factor > 0.0 && factor < last);
+        last = factor;
+    }
+    // critical damping sits between the two branches
+    solver.damping = 2.0 * mode.wave_number2().sqrt();
+    let critical = mode.factor(&solver, 0.5);
+    assert!(critical > 0.0 && critical < 1.0);
+}
+
+#[test]
+fn residuals_show_where_the_solution_was_disturbed() {
+    let mode = PdeMode::default();
+    let mut solver = PdeSolver::new(PdeKind::Heat, 33, 33);
+    solver.set_mode(&mode);
+    solver.add_gaussian(0.5, 0.5, 0.5, 0.1);
+    let residual = solver.residual(&mode);
+    let (k, _) = residual
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .unwrap();
+    assert_eq!((k / 33, k % 33), (24, 24));
+    assert!((residual[k] - 0.5).abs() < 1e-5);
+    assert_eq!(solver.reference(&mode).len(), solver.u.len());
+}
+
+#[test]
+fn surfaces_color_by_the_residual() {
+    let mode = PdeMode::default();
+    let mut solver = PdeSolver::new(PdeKind::Heat, 9, 9);
+    solver.set_mode(&mode);
+    solver.add_gaussian(0.0, 0.0, 0.1, 0.05);
+    let mut surface = IStreamingSurface::default();
+    solver.write_to(&mut surface);
+    let mut data = surface.new();
+    let wireframe = data.colors2.clone();
+    let residual: Vec<f32> = solver.residual(&mode).iter().map(|r| r.abs()).collect();
+    surface.color_by_values(&mut data, &residual, [0.0, 0.1]);
+
+    // zero residual gets the low end of the colormap and the bump the high end
+    let cdata = colormap::colormap_data(&surface.colormap_name);
+    assert_eq!(data.colors[1], cdata[0]);
+    assert_eq!(data.colors[4 * 9 + 4], cdata[10]);
+    assert_eq!(data.colors2, wireframe);
+}
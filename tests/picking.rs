@@ -0,0 +1,280 @@
+#![cfg(feature = "low_level")]
+
+use cgmath::{InnerSpace, Matrix4, Point3, Vector3};
+use wgpu_surfaces::picking::{
+    self, Crosshair, DepthPicker, MarkerShape, ParamInset, ProbeEvent, ProbeHooks, Ray,
+};
+use wgpu_surfaces::surface_data::{IParametricSurface, ISurfaceOutput, ShadingMode};
+use wgpu_surfaces::wgpu_simplified as ws;
+
+mod common;
+
+// tilted plane y = 0.5 x over [-1, 1]^2 made of two triangles
+fn tilted_plane() -> ISurfaceOutput {
+    let n = [-0.5 / 1.25f32.sqrt(), 1.0 / 1.25f32.sqrt(), 0.0];
+    ISurfaceOutput {
+        positions: vec![
+            [-1.0, -0.5, -1.0],
+            [1.0, 0.5, -1.0],
+            [1.0, 0.5, 1.0],
+            [-1.0, -0.5, 1.0],
+        ],
+        normals: vec![n; 4],
+        indices: vec![0, 1, 2, 2, 3, 0],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn pick_returns_nearest_hit_and_slope() {
+    let ray = Ray {
+        origin: [0.4, 5.0, 0.2],
+        direction: [0.0, -1.0, 0.0],
+    };
+    let hit = picking::pick_mesh(&ray, &tilted_plane()).unwrap();
+    assert!((hit.position[1] - 0.2).abs() < 1e-5);
+    assert!((hit.distance - 4.8).abs() < 1e-5);
+
+    let event = ProbeEvent::from_hit(&hit);
+    assert!((event.value - 0.2).abs() < 1e-5);
+    assert!((event.gradient[0] - 0.5).abs() < 1e-5);
+    assert!(event.gradient[1].abs() < 1e-5);
+}
+
+#[test]
+fn pick_misses_outside_the_mesh() {
+    let ray = Ray {
+        origin: [3.0, 5.0, 0.0],
+        direction: [0.0, -1.0, 0.0],
+    };
+    assert!(picking::pick_mesh(&ray, &tilted_plane()).is_none());
+}
+
+#[test]
+fn screen_center_ray_points_at_look_target() {
+    let (view_mat, project_mat, _) = ws::create_vp_mat(
+        Point3::new(2.0, 3.0, 4.0),
+        Point3::new(0.0, 0.0, 0.0),
+        Vector3::unit_y(),
+        1.5,
+    );
+    let vp: Matrix4<f32> = project_mat * view_mat;
+    let ray = Ray::from_screen(&vp, [300.0, 200.0], [600.0, 400.0]).unwrap();
+    let len = (4.0f32 + 9.0 + 16.0).sqrt();
+    let expected = [-2.0 / len, -3.0 / len, -4.0 / len];
+    for (d, e) in ray.direction.iter().zip(expected) {
+        assert!((d - e).abs() < 1e-4);
+    }
+}
+
+#[test]
+fn probe_hooks_receive_events() {
+    let received = std::rc::Rc::new(std::cell::Cell::new(0.0));
+    let mut hooks = ProbeHooks::default();
+    let r = received.clone();
+    hooks.add(move |event| r.set(event.value));
+    hooks.emit(&ProbeEvent {
+        position: [0.0, 0.7, 0.0],
+        value: 0.7,
+        gradient: [0.0, 0.0],
+    });
+    assert_eq!(received.get(), 0.7);
+
+    let event = ProbeEvent {
+        position: [0.0; 3],
+        value: 1.0,
+        gradient: [0.0; 2],
+    };
+    assert_eq!(event.tone_frequency([-1.0, 1.0], 220.0, 2.0), 880.0);
+}
+
+#[test]
+fn crosshair_lies_in_the_tangent_plane_of_the_hit() {
+    let ray = Ray {
+        origin: [0.4, 5.0, 0.2],
+        direction: [0.0, -1.0, 0.0],
+    };
+    let hit = picking::pick_mesh(&ray, &tilted_plane()).unwrap();
+    let model_mat = Matrix4::from_translation(Vector3::new(1.0, 2.0, 3.0));
+    let crosshair = Crosshair::default()
+        .with_size(0.5)
+        .with_normal(0.0, [0.0; 3]);
+    let lines = crosshair.lines(&hit, &model_mat);
+    assert_eq!(lines.len(), 4);
+    let center = Vector3::new(1.4, 2.2, 3.2);
+    let normal = Vector3::from(hit.normal);
+    for (p, color) in &lines {
+        let offset = Vector3::from(*p) - center;
+        // half width out from the hit, barely off the plane
+        assert!((offset.magnitude() - 0.5).abs() < 0.01);
+        assert!(offset.dot(normal).abs() < 0.02);
+        assert_eq!(*color, crosshair.color);
+    }
+
+    let ring = Crosshair::default().with_shape(MarkerShape::Ring(16));
+    let lines = ring.lines(&hit, &model_mat);
+    assert_eq!(lines.len(), 2 * 16 + 2);
+    let (tip, color) = lines[lines.len() - 1];
+    assert_eq!(color, ring.normal_color);
+    let tick = Vector3::from(tip) - center;
+    assert!((tick.dot(normal) - ring.normal_length).abs() < 0.01);
+}
+
+#[test]
+fn inset_parameters_and_surface_points_correspond() {
+    let (nu, nv) = (16, 12);
+    let mut torus = IParametricSurface {
+        surface_type: 21,
+        u_resolution: nu,
+        v_resolution: nv,
+        ..Default::default()
+    };
+    let data = torus.new();
+    let inset = ParamInset::new(&torus);
+    assert!(inset.flipped); // the torus comes out facing inwards
+    let [umin, umax, vmin, vmax] = inset.domain;
+    let close = |a: [f32; 2], b: [f32; 2]| (a[0] - b[0]).abs() < 1e-4 && (a[1] - b[1]).abs() < 1e-4;
+
+    // a grid point is its vertex
+    let du = (umax - umin) / nu as f32;
+    let dv = (vmax - vmin) / nv as f32;
+    let hit = inset
+        .hit_at_uv(&data, [umin + 3.0 * du, vmin + 5.0 * dv])
+        .unwrap();
+    let vertex = data.positions[3 * (nv as usize + 1) + 5];
+    assert!((Vector3::from(hit.position) - Vector3::from(vertex)).magnitude() < 1e-5);
+
+    // the inset marks where a ray picks the surface, on both halves of a cell
+    for uv in [[1.3, 2.1], [1.35, 2.05], [5.0, 0.2]] {
+        let hit = inset.hit_at_uv(&data, uv).unwrap();
+        assert!(close(inset.uv_of_hit(&hit).unwrap(), uv), "{uv:?}");
+        let normal = Vector3::from(hit.normal);
+        let ray = Ray {
+            origin: (Vector3::from(hit.position) + 0.5 * normal).into(),
+            direction: (-normal).into(),
+        };
+        let picked = picking::pick_mesh(&ray, &data).unwrap();
+        assert!(close(inset.uv_of_hit(&picked).unwrap(), uv), "{uv:?}");
+    }
+
+    // closed seams and flat shading keep the triangles in place
+    torus.close_seams = true;
+    torus.shading = ShadingMode::Flat;
+    let flat = torus.new();
+    let hit = inset.hit_at_uv(&flat, [1.3, 2.1]).unwrap();
+    assert!(close(inset.uv_of_hit(&hit).unwrap(), [1.3, 2.1]));
+    let smooth = inset.hit_at_uv(&data, [1.3, 2.1]).unwrap();
+    assert!((Vector3::from(hit.position) - Vector3::from(smooth.position)).magnitude() < 1e-5);
+}
+
+#[test]
+fn inset_maps_the_cursor_to_the_domain() {
+    let inset = ParamInset {
+        domain: [0.0, 2.0, -1.0, 1.0],
+        rect: [0.5, 0.5, 1.0, 1.0],
+        ..Default::default()
+    };
+    let size = [200.0, 100.0];
+    // the center of the inset, which fills the top right quarter of the window
+    assert_eq!(inset.uv_at_cursor([175.0, 12.5], size), Some([1.0, 0.0]));
+    assert_eq!(
+        inset.uv_at_cursor([199.0, 1.0], size).map(|uv| uv[0] > 1.9),
+        Some(true)
+    );
+    assert_eq!(inset.uv_at_cursor([125.0, 75.0], size), None);
+
+    let lines = inset.lines(None);
+    assert_eq!(lines.len(), 2 * (2 * inset.grid_lines as usize + 4));
+    assert!(lines.iter().all(|(p, _)| p[2] == 0.0));
+    let marked = inset.lines(Some([1.0, 0.0]));
+    let marker: Vec<_> = marked[lines.len()..]
+        .iter()
+        .map(|(p, _)| [p[0], p[1]])
+        .collect();
+    assert_eq!(marker.len(), 4);
+    assert_eq!(0.5 * (marker[0][0] + marker[1][0]), 0.75);
+    assert_eq!(marked.last().unwrap().1, inset.marker_color);
+}
+
+#[test]
+fn unproject_inverts_the_view_projection() {
+    let view_project_mat = ws::create_perspective_mat(1.5, 1.0, 10.0)
+        * ws::create_view_mat(
+            Point3::new(1.0, 2.0, 6.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Vector3::unit_y(),
+        );
+    let size = [300.0, 200.0];
+    let p = picking::unproject(&view_project_mat, [120.0, 70.0], size, 0.8).unwrap();
+    let clip = view_project_mat * Vector3::from(p).extend(1.0);
+    let ndc = clip.truncate() / clip.w;
+    assert!((ndc.x - (2.0 * 120.0 / 300.0 - 1.0)).abs() < 1e-4);
+    assert!((ndc.y - (1.0 - 2.0 * 70.0 / 200.0)).abs() < 1e-4);
+    assert!((ndc.z - 0.8).abs() < 1e-4);
+}
+
+// depth buffer of the given size cleared to one depth value and stored
+fn cleared_depth(init: &ws::InitWgpuHeadless, depth: f32) -> wgpu::TextureView {
+    let depth_view = ws::create_depth_texture_view(init, true);
+    let mut encoder = init
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: None,
+        color_attachments: &[],
+        depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+            view: &depth_view,
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(depth),
+                store: wgpu::StoreOp::Store,
+            }),
+            stencil_ops: None,
+        }),
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    });
+    init.queue.submit(std::iter::once(encoder.finish()));
+    depth_view
+}
+
+#[test]
+fn depth_picker_unprojects_the_depth_under_the_cursor() {
+    let Some(init) = common::headless(16, 8, 1) else {
+        return;
+    };
+    let view_project_mat = ws::create_perspective_mat(2.0, 1.0, 10.0)
+        * ws::create_view_mat(
+            Point3::new(0.0, 0.0, 5.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Vector3::unit_y(),
+        );
+    let size = [16.0, 8.0];
+    let mut picker = DepthPicker::new(&init, &cleared_depth(&init, 0.5));
+    let pick = |picker: &mut DepthPicker, cursor: [f32; 2]| {
+        let mut encoder = init
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        let requested = picker.request(&init.queue, &mut encoder, cursor, size);
+        init.queue.submit(std::iter::once(encoder.finish()));
+        let hit = picker.read(&init.device, &view_project_mat).unwrap();
+        (requested, hit)
+    };
+
+    // a constant depth is a plane facing the camera, even at the last pixel
+    for cursor in [[4.2, 3.7], [15.5, 7.5]] {
+        let (requested, hit) = pick(&mut picker, cursor);
+        let hit = hit.unwrap();
+        assert!(requested);
+        assert!((hit.depth - 0.5).abs() < 1e-4);
+        let center = [hit.pixel[0] as f32 + 0.5, hit.pixel[1] as f32 + 0.5];
+        let expected = picking::unproject(&view_project_mat, center, size, hit.depth).unwrap();
+        assert!((Vector3::from(hit.position) - Vector3::from(expected)).magnitude() < 1e-4);
+        assert!(Vector3::from(hit.normal).dot(Vector3::unit_z()) > 0.999);
+        assert!(hit.probe_event().gradient[0].abs() < 1e-3);
+    }
+    assert_eq!(pick(&mut picker, [20.0, 3.0]), (false, None));
+
+    // the cleared far plane is the background
+    picker.set_depth_view(&init, &cleared_depth(&init, 1.0));
+    assert_eq!(pick(&mut picker, [4.0, 4.0]), (true, None));
+}
@@ -0,0 +1,180 @@
+#![cfg(feature = "low_level")]
+
+use wgpu_surfaces::animation::SharedClock;
+use wgpu_surfaces::plot;
+use wgpu_surfaces::surface_data::MaskMode;
+
+#[test]
+fn samples_function_on_grid() {
+    let data = plot::plot_surface(|x, z| x * z)
+        .resolution(16)
+        .surface_data();
+    assert_eq!(data.positions.len(), 16 * 16);
+    assert_eq!(data.indices.len(), 15 * 15 * 6);
+    // x * z peaks at opposite corners of the domain
+    let ys: Vec<f32> = data.positions.iter().map(|p| p[1]).collect();
+    assert_eq!(ys[0], ys[ys.len() - 1]);
+    assert!(ys[0] > ys[15]);
+}
+
+#[test]
+fn non_finite_values_are_zeroed() {
+    let data = plot::plot_surface(|x, _| 1.0 / x)
+        .resolution(3)
+        .surface_data();
+    assert!(data.positions.iter().flatten().all(|v| v.is_finite()));
+}
+
+#[test]
+fn plots_value_grid() {
+    let values: Vec<f32> = (0..12).map(|v| v as f32).collect();
+    let data = plot::plot_values(3, 4, values).surface_data();
+    assert_eq!(data.positions.len(), 12);
+    // the lowest and highest values end up at the bottom and top of the box
+    assert!(data.positions[0][1] < data.positions[11][1]);
+}
+
+#[test]
+fn subplot_viewports_tile_the_window() {
+    let plots = plot::subplots(2, 3).subplot(4, plot::plot_surface(|x, z| x + z));
+    assert_eq!(plots.len(), 6);
+    assert_eq!(
+        plots.get(4).unwrap().surface_data().positions.len(),
+        64 * 64
+    );
+    assert_eq!(plots.viewport(4, [600, 400]), [200.0, 200.0, 200.0, 200.0]);
+    assert_eq!(plots.viewport_at([250.0, 250.0], [600, 400]), Some(4));
+    assert_eq!(plots.viewport_at([650.0, 250.0], [600, 400]), None);
+}
+
+#[test]
+fn uncertainty_band_encloses_mean() {
+    let plot = plot::plot_surface(|x, z| x * z)
+        .resolution(8)
+        .uncertainty(|x, _| 0.1 + 0.1 * x.abs());
+    let mean = plot.surface_data();
+    let [upper, lower] = plot.band_data().unwrap();
+    for i in 0..mean.positions.len() {
+        assert!(upper.positions[i][1] > mean.positions[i][1]);
+        assert!(lower.positions[i][1] < mean.positions[i][1]);
+    }
+    assert!(plot::plot_surface(|x, z| x * z).band_data().is_none());
+}
+
+#[test]
+fn uncertainty_fades_colors() {
+    let plot = plot::plot_surface(|x, _| x)
+        .resolution(4)
+        .uncertainty(|_, _| 1.0)
+        .band(0.0, 0.5)
+        .color_by_uncertainty(true);
+    assert!(plot.band_data().is_none());
+    // a uniform sigma is the maximum everywhere, so every color is fully gray
+    for c in plot.surface_data().colors {
+        assert!((c[0] - c[1]).abs() < 1e-6 && (c[1] - c[2]).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn color_field_is_normalized_separately() {
+    let plot = plot::plot_surface(|x, _| x)
+        .resolution(4)
+        .color_by(|_, z| 100.0 * z);
+    assert_eq!(plot.color_field_range(), Some([-100.0, 100.0]));
+    let data = plot.surface_data();
+    // constant along x (the height), varying along z (the color field)
+    assert_eq!(data.colors[0], data.colors[12]);
+    assert_ne!(data.colors[0], data.colors[3]);
+}
+
+#[test]
+fn the_colorbar_is_labeled_with_the_plotted_range() {
+    let plot = plot::plot_surface(|x, z| x + 2.0 * z).resolution(5);
+    assert_eq!(plot.colorbar_range(), [-3.0, 3.0]);
+    // masked cells do not count, a color field takes over
+    let masked = plot::plot_surface(|x, z| x + 2.0 * z)
+        .resolution(5)
+        .mask(|x, _| x > 0.1);
+    assert_eq!(masked.colorbar_range(), [-3.0, 2.0]);
+    let colored = plot.color_by(|_, z| 100.0 * z);
+    assert_eq!(colored.colorbar_range(), [-100.0, 100.0]);
+}
+
+#[test]
+fn mask_hides_or_dims_cells() {
+    let masked = |mode| {
+        plot::plot_surface(|x, z| x + z)
+            .resolution(5)
+            .mask(|x, _| x > 0.1)
+            .mask_mode(mode)
+            .surface_data()
+    };
+    let full = plot::plot_surface(|x, z| x + z)
+        .resolution(5)
+        .surface_data();
+    let hidden = masked(MaskMode::Hide);
+    // x > 0.1 masks the last two of five rows, leaving 2 x 4 cells
+    assert_eq!(hidden.indices.len(), 2 * 4 * 6);
+    assert!(hidden.indices2.len() < full.indices2.len());
+    let dimmed = masked(MaskMode::Dim(0.5));
+    assert_eq!(dimmed.indices.len(), full.indices.len());
+    assert!(dimmed.colors[24][0] <= 0.5 * full.colors[24][0] + 1e-6);
+    assert_eq!(masked(MaskMode::Ignore).positions, full.positions);
+}
+
+#[test]
+fn animated_subplots_follow_their_clock() {
+    let clock = SharedClock::new();
+    clock.pause();
+    clock.set_time(1.0);
+    let own = SharedClock::new();
+    own.pause();
+    own.set_time(3.0);
+    let mut plots = plot::subplots(1, 3)
+        .clock(&clock)
+        .subplot(0, plot::plot_animated(|x, _, t| x * t).resolution(4))
+        .subplot(1, plot::plot_animated(|x, _, t| x * t).clock(&own))
+        .subplot(2, plot::plot_surface(|x, _| x));
+    assert!(plots.is_animated());
+    assert_eq!(plots.advance(), vec![0, 1]);
+    assert_eq!(plots.get(0).unwrap().time, 1.0);
+    assert_eq!(plots.get(1).unwrap().time, 3.0);
+    // nothing changes while the clocks are paused
+    assert!(plots.advance().is_empty());
+
+    plots.shared_clock().set_time(0.0);
+    assert_eq!(plots.advance(), vec![0]);
+    // at t = 0 the surface is flat
+    let data = plots.get(0).unwrap().surface_data();
+    assert!(data.positions.iter().all(|p| p[1] == data.positions[0][1]));
+}
+
+#[test]
+fn probe_interpolates_cross_section() {
+    let plot = plot::plot_surface(|x, z| x + 10.0 * z)
+        .resolution(5)
+        .probe(plot::ProbeAxis::X, 0.25);
+    let section = plot.cross_section().unwrap();
+    assert_eq!(section.len(), 5);
+    // the plane lies between two grid lines, the section is exact for linear data
+    for [z, y] in section {
+        assert!((y - (0.25 + 10.0 * z)).abs() < 1e-5);
+    }
+
+    // the plane is clamped to the plot range
+    let plot = plot.probe(plot::ProbeAxis::Z, 5.0);
+    assert_eq!(plot.probe, Some((plot::ProbeAxis::Z, 1.0)));
+    let section = plot.cross_section().unwrap();
+    assert_eq!(section[0], [-1.0, 9.0]);
+    assert!(plot::plot_surface(|x, _| x).cross_section().is_none());
+}
+
+#[test]
+fn unlit_plots_leave_out_normals() {
+    use wgpu_surfaces::mesh::VertexAttributes;
+    let plot = plot::plot_surface(|x, z| x * z);
+    assert_eq!(plot.vertex_attributes(), VertexAttributes::LIT);
+    let plot = plot.lighting(false);
+    assert_eq!(plot.vertex_attributes(), VertexAttributes::UNLIT);
+    assert_eq!(plot.vertex_attributes().stride(), 24);
+}
@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+use wgpu_surfaces::recorder::{self, RecordFormat, Recorder};
+use wgpu_surfaces::wgpu_simplified as ws;
+
+mod common;
+
+fn solid(width: u32, height: u32, rgba: [u8; 4]) -> Vec<u8> {
+    rgba.repeat((width * height) as usize)
+}
+
+#[test]
+fn gif_delays_follow_the_capture_times() {
+    let ms = Duration::from_millis;
+    let delays = recorder::gif_delays(&[ms(0), ms(40), ms(45), ms(145)], 25.0);
+    assert_eq!(delays, [4, 2, 10, 4]);
+}
+
+#[test]
+fn recording_stops_at_max_frames_and_encodes_a_gif() {
+    let mut recorder = Recorder::new(RecordFormat::Gif, 25.0, 3);
+    assert!(!recorder.add_frame(2, 2, solid(2, 2, [255, 0, 0, 255]), Duration::ZERO).unwrap());
+    assert!(recorder.toggle());
+
+    let colors = [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255]];
+    let mut done = vec![];
+    for (i, color) in colors.into_iter().enumerate() {
+        let time = Duration::from_millis(40 * i as u64);
+        done.push(recorder.add_frame(4, 2, solid(4, 2, color), time).unwrap());
+    }
+    assert_eq!(done, [false, false, true]);
+    assert!(!recorder.is_recording());
+    assert_eq!(recorder.size(), [4, 2]);
+
+    let bytes = recorder.encode_gif().unwrap();
+    assert_eq!(&bytes[..6], b"GIF89a");
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(&bytes[..]).unwrap();
+    let mut decoded = vec![];
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        assert_eq!(frame.delay, 4);
+        decoded.push(frame.buffer[..4].to_vec());
+    }
+    assert_eq!(decoded, colors);
+
+    // frames of another size than the first are rejected
+    recorder.start();
+    recorder.add_frame(4, 2, solid(4, 2, colors[0]), Duration::ZERO).unwrap();
+    assert!(recorder.add_frame(2, 2, solid(2, 2, colors[0]), Duration::ZERO).is_err());
+}
+
+#[test]
+fn rendered_frames_are_copied_and_read_back() {
+    let Some(init) = common::headless(8, 4, 1) else {
+        return;
+    };
+    let mut recorder = Recorder::new(RecordFormat::Frames, 1000.0, 10);
+    recorder.start();
+    let mut encoder = init
+        .device
+        .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    {
+        let mut attachment = ws::create_color_attachment(&init.view);
+        attachment.ops.load = wgpu::LoadOp::Clear(wgpu::Color::GREEN);
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(attachment)],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+    }
+    recorder.copy_frame(&init.device, &mut encoder, &init.texture, init.format);
+    init.queue.submit(std::iter::once(encoder.finish()));
+    assert!(!recorder.read_frame(&init.device).unwrap());
+    assert_eq!(recorder.size(), [8, 4]);
+    assert_eq!(recorder.frames()[0].rgba, solid(8, 4, [0, 255, 0, 255]));
+
+    // nothing was copied since the last read
+    assert!(!recorder.read_frame(&init.device).unwrap());
+    assert_eq!(recorder.frames().len(), 1);
+
+    let dir = std::env::temp_dir().join("wgpu_surfaces_recorder_frames");
+    recorder.save(&dir).unwrap();
+    assert!(dir.join("frame_00000.png").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
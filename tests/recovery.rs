@@ -0,0 +1,76 @@
+#![cfg(feature = "low_level")]
+
+use wgpu_surfaces::recovery::{self, Recovery, Session};
+use wgpu_surfaces::scene::Scene;
+
+fn args(line: &str) -> Vec<String> {
+    line.split_whitespace().map(String::from).collect()
+}
+
+#[test]
+fn repro_command_starts_from_the_recovery_file() {
+    let recovery = Recovery::new(
+        "crash.ron",
+        args("pde_surface --msaa 4 --scene old.ron --seed=9 --recovery-file crash.ron -- data"),
+    );
+    assert_eq!(
+        recovery.repro_command(),
+        "pde_surface --msaa 4 --scene crash.ron -- data"
+    );
+    let recovery = Recovery::new("my crash.ron", args("simple_surface"));
+    assert_eq!(
+        recovery.repro_command(),
+        "simple_surface --scene \"my crash.ron\""
+    );
+}
+
+#[test]
+fn saved_sessions_load_as_scenes() {
+    let path = std::env::temp_dir().join("wgpu_surfaces_recovery.ron");
+    let recovery = Recovery::new(&path, args("simple_surface --colormap hot"));
+    let session = Session {
+        scene: Some(Scene {
+            surface_type: 2,
+            seed: 11,
+            ..Default::default()
+        }),
+        adapter: "Test GPU (Vulkan)".to_string(),
+        frame: 120,
+    };
+    let report = recovery.save(&session);
+    assert!(report.contains("frame 120") && report.contains("Test GPU (Vulkan)"));
+    assert!(report.contains(&recovery.repro_command()));
+    assert_eq!(Scene::load(&path).unwrap(), session.scene.unwrap());
+    std::fs::remove_file(&path).unwrap();
+
+    // nothing to save before the first frame
+    let report = recovery.save(&Session::default());
+    assert!(report.starts_with("no scene was recorded"));
+    assert!(!path.exists());
+}
+
+#[test]
+fn the_panic_hook_saves_the_last_recorded_scene() {
+    let path = std::env::temp_dir().join("wgpu_surfaces_recovery_hook.json");
+    assert!(recovery::install(Recovery::new(
+        &path,
+        args("simple_surface")
+    )));
+    assert!(!recovery::install(Recovery::new(
+        &path,
+        args("simple_surface")
+    )));
+    assert!(recovery::is_installed());
+    for surface_type in 0..3 {
+        recovery::record(Scene {
+            surface_type,
+            ..Default::default()
+        });
+    }
+    assert_eq!(recovery::session().frame, 3);
+
+    let crashed = std::thread::spawn(|| panic!("device lost")).join();
+    assert!(crashed.is_err());
+    assert_eq!(Scene::load(&path).unwrap().surface_type, 2);
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,115 @@
+use wgpu_surfaces::report::{self, ICameraInfo, IReport};
+use wgpu_surfaces::surface_data::{IParametricSurface, ISimpleSurface};
+
+fn row(name: &str, value: &str) -> String {
+    format!("<tr><th>{}</th><td>{}</td></tr>", name, value)
+}
+
+#[test]
+fn images_are_embedded_as_rfc_4648_base64() {
+    // the test vectors of RFC 4648, section 10
+    let vectors = [
+        ("", ""),
+        ("f", "Zg=="),
+        ("fo", "Zm8="),
+        ("foo", "Zm9v"),
+        ("foob", "Zm9vYg=="),
+        ("fooba", "Zm9vYmE="),
+        ("foobar", "Zm9vYmFy"),
+    ];
+    for (input, encoded) in vectors {
+        let mut report = IReport::default();
+        report.add_png("vector", input.as_bytes().to_vec());
+        let uri = format!("src=\"data:image/png;base64,{}\"", encoded);
+        assert!(
+            report.to_html().contains(&uri),
+            "{:?} should encode to {}",
+            input,
+            encoded
+        );
+    }
+}
+
+#[test]
+fn text_is_html_escaped() {
+    let mut report = IReport {
+        title: "<b>\"Tom\" & 'Jerry'</b>".to_string(),
+        ..Default::default()
+    };
+    report.add_png("a < b", vec![]);
+    report.parameter("x > y", "it's");
+    let html = report.to_html();
+
+    let title = "&lt;b&gt;&quot;Tom&quot; &amp; &#39;Jerry&#39;&lt;/b&gt;";
+    assert!(html.contains(&format!("<title>{}</title>", title)));
+    assert!(html.contains(&format!("<h1>{}</h1>", title)));
+    assert!(html.contains("<figcaption>a &lt; b</figcaption>"));
+    assert!(html.contains(&row("x &gt; y", "it&#39;s")));
+    assert!(!html.contains("Tom\""));
+}
+
+#[test]
+fn parameter_table_lists_the_surface_parameters() {
+    let ps = IParametricSurface {
+        surface_type: 23,
+        u_resolution: 64,
+        v_resolution: 32,
+        scale: 2.0,
+        ..Default::default()
+    };
+    let html = report::parametric_surface_report(&ps).to_html();
+    assert!(html.contains("<h2>Surface parameters</h2>"));
+    assert!(html.contains(&row("surface type", "23 (torus_knot)")));
+    assert!(html.contains(&row("u range", "[-1, 1]")));
+    assert!(html.contains(&row("resolution", "64 x 32")));
+    assert!(html.contains(&row("scale", "2")));
+    assert!(html.contains(&row("knot (p, q)", "(2, 3)")));
+    assert!(html.contains(&row("tube radius", "0.3")));
+    assert!(!html.contains("deformation"));
+
+    // the shape parameters of other surfaces stay out of the table
+    let klein_bottle = report::parametric_surface_report(&IParametricSurface::default());
+    let html = klein_bottle.to_html();
+    assert!(html.contains(&row("surface type", "0 (klein_bottle)")));
+    assert!(!html.contains("knot") && !html.contains("tube radius"));
+
+    let ss = ISimpleSurface {
+        t: 0.5,
+        ..Default::default()
+    };
+    let html = report::simple_surface_report(&ss).to_html();
+    assert!(html.contains(&row("t", "0.5")));
+    assert!(html.contains(&row("wireframe color", &ss.wireframe_color)));
+}
+
+#[test]
+fn camera_table_lists_the_camera() {
+    let mut report = IReport::default();
+    assert!(!report.to_html().contains("<h2>Camera</h2>"));
+
+    report.camera = Some(ICameraInfo {
+        position: [1.0, 2.0, 3.0],
+        look_at: [0.0; 3],
+        up: [0.0, 1.0, 0.0],
+        fovy_degrees: 45.0,
+    });
+    let html = report.to_html();
+    assert!(html.contains("<h2>Camera</h2>"));
+    assert!(html.contains(&row("position", "[1.0, 2.0, 3.0]")));
+    assert!(html.contains(&row("look at", "[0.0, 0.0, 0.0]")));
+    assert!(html.contains(&row("up", "[0.0, 1.0, 0.0]")));
+    assert!(html.contains(&row("vertical fov (deg)", "45")));
+}
+
+#[test]
+fn legend_spans_the_whole_bar() {
+    let html = IReport::default().to_html();
+    let legend = html.split("linear-gradient(to right, ").nth(1).unwrap();
+    let legend = &legend[..legend.find(")\"").unwrap()];
+    assert!(legend.ends_with(" 100%"), "{}", legend);
+    assert!(
+        legend.contains(") 0%,") && legend.contains(") 50%,"),
+        "{}",
+        legend
+    );
+}
@@ -0,0 +1,98 @@
+#![cfg(feature = "low_level")]
+
+use wgpu_surfaces::cli::SurfaceArgs;
+use wgpu_surfaces::material::{Material, MaterialKind};
+use wgpu_surfaces::scene::Scene;
+use wgpu_surfaces::wgpu_simplified as ws;
+use wgpu_surfaces::{Error, SceneError};
+
+fn scene() -> Scene {
+    Scene {
+        surface_type: 2,
+        resolution: [40, 72],
+        scale: 2.5,
+        colormap: "hot".to_string(),
+        material: MaterialKind::Pbr.default_material(),
+        msaa: 4,
+        plot_type: 1,
+        seed: 7,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn scenes_round_trip_through_ron_and_json() {
+    let scene = scene();
+    let ron = scene.to_ron().unwrap();
+    assert_eq!(Scene::from_ron(&ron).unwrap(), scene);
+    let json = scene.to_json().unwrap();
+    assert_eq!(Scene::from_json(&json).unwrap(), scene);
+    // missing fields keep their defaults
+    let partial = Scene::from_json(r#"{"plot_type": 2}"#).unwrap();
+    assert_eq!(partial.plot_type, 2);
+    assert_eq!(partial.material, Material::default());
+}
+
+#[test]
+fn scene_files_pick_the_format_from_the_extension() {
+    let dir = std::env::temp_dir();
+    let mut scene = scene();
+    scene.set_camera(&ws::OrbitCamera::new(
+        (1.0, 2.0, 3.0).into(),
+        (0.0, 0.5, 0.0).into(),
+    ));
+    for name in ["wgpu_surfaces_scene.ron", "wgpu_surfaces_scene.json"] {
+        let path = dir.join(name);
+        scene.save(&path).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text.starts_with('{'), name.ends_with(".json"));
+        let loaded = Scene::load(&path).unwrap();
+        assert_eq!(loaded, scene);
+        assert_eq!(loaded.camera().eye(), scene.camera().eye());
+        std::fs::remove_file(&path).unwrap();
+    }
+    let error = Scene::load(dir.join("wgpu_surfaces_no_scene.ron")).unwrap_err();
+    assert!(error.to_string().starts_with("scene: cannot read"));
+    assert!(matches!(error, Error::Scene(SceneError::Read { .. })));
+
+    // parse errors keep the RON or JSON error as their source, under the path
+    let path = dir.join("wgpu_surfaces_bad_scene.ron");
+    std::fs::write(&path, "(plot_type: \"two\")").unwrap();
+    let error = Scene::load(&path).unwrap_err();
+    std::fs::remove_file(&path).unwrap();
+    let Error::Scene(SceneError::File { source, .. }) = error else {
+        panic!("{:?} is not a file error", error);
+    };
+    assert!(matches!(*source, SceneError::Ron(_)));
+    let error = Scene::from_json("{").unwrap_err();
+    assert!(matches!(error, Error::Scene(SceneError::Json(_))));
+}
+
+#[test]
+fn the_scene_flag_sets_what_the_pipelines_need() {
+    let path = std::env::temp_dir().join("wgpu_surfaces_scene_args.ron");
+    scene().save(&path).unwrap();
+    let args = SurfaceArgs::default()
+        .parse(["--scene".to_string(), path.display().to_string()])
+        .unwrap();
+    assert_eq!((args.msaa, args.material), (4, MaterialKind::Pbr));
+    assert_eq!(args.colormap, "hot");
+    assert_eq!(args.scene, Some(scene()));
+    assert_eq!(args.scene_file, Some(path.display().to_string()));
+    // the seed of the scene, unless --seed gives one
+    assert_eq!(args.seed, Some(7));
+    let args = SurfaceArgs::default()
+        .parse([
+            "--seed=3".to_string(),
+            "--scene".to_string(),
+            path.display().to_string(),
+        ])
+        .unwrap();
+    assert_eq!(args.seed, Some(3));
+
+    // an unsupported sample count is rejected like --msaa 3
+    Scene { msaa: 3, ..scene() }.save(&path).unwrap();
+    let args = SurfaceArgs::default().parse(["--scene".to_string(), path.display().to_string()]);
+    assert!(args.is_err());
+    std::fs::remove_file(&path).unwrap();
+}
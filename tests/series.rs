@@ -0,0 +1,92 @@
+#![cfg(feature = "low_level")]
+
+use std::time::{Duration, Instant};
+
+use wgpu_surfaces::plot;
+use wgpu_surfaces::series::{FnFrames, FrameCache, FrameList, FrameSource, RawFramesSource};
+
+// frame k of a 2 x 3 grid holds the value k everywhere
+fn constant_frames(steps: usize) -> FnFrames {
+    FnFrames {
+        rows: 2,
+        cols: 3,
+        steps,
+        f: Box::new(|step, _, _| step as f32),
+    }
+}
+
+// updates the cache until the step itself is resident
+fn wait_for(cache: &mut FrameCache, step: usize) {
+    let start = Instant::now();
+    while cache.update(step) != Some(step) {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "step {step} never loaded"
+        );
+        std::thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn raw_frames_source_matches_frame_list() {
+    let list = FrameList {
+        rows: 2,
+        cols: 2,
+        frames: vec![vec![0.0, 1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0, 7.0]],
+    };
+    let path = std::env::temp_dir().join(format!("series_test_{}.f32", std::process::id()));
+    let bytes: Vec<u8> = list
+        .frames
+        .iter()
+        .flatten()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    std::fs::write(&path, bytes).unwrap();
+
+    let raw = RawFramesSource::open(&path, 2, 2).unwrap();
+    assert_eq!(raw.len(), 2);
+    assert_eq!(raw.frame(1).unwrap(), list.frame(1).unwrap());
+    assert!(raw.frame(2).is_err());
+    assert!(RawFramesSource::open(&path, 4, 4).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn cache_preloads_neighbors_and_evicts_far_frames() {
+    let mut cache = FrameCache::new(Box::new(constant_frames(20)), 5, 2);
+    assert_eq!(cache.len(), 20);
+    wait_for(&mut cache, 0);
+    assert_eq!(cache.get(0).unwrap(), &[0.0; 6]);
+    // the neighbors wrap around the end of the series
+    for step in [1, 2, 19, 18] {
+        wait_for(&mut cache, step);
+    }
+
+    wait_for(&mut cache, 10);
+    cache.update(10);
+    assert!(cache.resident().len() <= 5);
+    assert!(!cache.contains(0));
+    let [vmin, vmax] = cache.value_range().unwrap();
+    assert_eq!(vmin, 0.0);
+    assert!(vmax >= 12.0);
+}
+
+#[test]
+fn series_plot_follows_time_steps() {
+    let mut series = plot::plot_series(constant_frames(8)).steps_per_second(4.0);
+    assert_eq!(series.series_len(), Some(8));
+    assert_eq!(series.step_at(0.6), Some(2));
+    // playback loops, also backwards
+    assert_eq!(series.step_at(2.1), Some(0));
+    assert_eq!(series.step_at(-0.1), Some(7));
+    assert_eq!(series.step_at(series.time_of_step(5)), Some(5));
+
+    let start = Instant::now();
+    while series.shown_step() != Some(2) {
+        assert!(start.elapsed() < Duration::from_secs(5));
+        series.set_time(0.6);
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    // a plain function plot has no time steps
+    assert_eq!(plot::plot_surface(|x, _| x).step_at(1.0), None);
+}
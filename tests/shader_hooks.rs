@@ -0,0 +1,74 @@
+#![cfg(feature = "low_level")]
+
+use wgpu_surfaces::shader_hooks::{self, ShaderHooks};
+
+mod common;
+
+const VERTEX: &str = include_str!("../ch02/01_simple_surface/shader_vert.wgsl");
+const INSTANCE: &str =
+    include_str!("../ch02/02_multiple_simple_surfaces/shader_instance_vert.wgsl");
+const FRAGMENT: &str = include_str!("../ch02/common/directional_frag.wgsl");
+const CONTOURS: &str = include_str!("../ch02/01_simple_surface/contours.wgsl");
+
+#[test]
+fn snippets_are_spliced_at_hook_points() {
+    assert_eq!(
+        shader_hooks::hook_points(VERTEX),
+        ["vertex_declarations", "vertex"]
+    );
+    assert_eq!(
+        shader_hooks::hook_points(INSTANCE),
+        shader_hooks::hook_points(VERTEX)
+    );
+    assert_eq!(
+        shader_hooks::hook_points(FRAGMENT),
+        ["fragment_declarations", "fragment_normal", "fragment"]
+    );
+
+    let source = "fn f() {\n    // @hook body\n}\n";
+    let hooks = ShaderHooks::default()
+        .snippet("body", "let a = 1;\nlet b = a;")
+        .snippet("other", "unused")
+        .snippet("body", "let c = b;");
+    assert_eq!(
+        hooks.apply(source),
+        "fn f() {\n    // @hook body\n    let a = 1;\n    let b = a;\n    let c = b;\n}\n"
+    );
+    assert_eq!(ShaderHooks::default().apply(source), source);
+
+    assert!(hooks.check(&[source]).is_err());
+    let hooks = ShaderHooks::default()
+        .snippet("vertex", "")
+        .snippet("fragment", "");
+    assert!(hooks.check(&[VERTEX, FRAGMENT]).is_ok());
+    assert!(hooks.check(&[VERTEX]).is_err());
+
+    assert!(ShaderHooks::from_args(&["contours.wgsl".to_string()]).is_err());
+    assert!(ShaderHooks::from_args(&["fragment=missing.wgsl".to_string()]).is_err());
+    assert!(ShaderHooks::from_args(&[]).unwrap().is_empty());
+}
+
+#[test]
+fn hooked_shaders_compile() {
+    let Some(init) = common::headless(1, 1, 1) else {
+        return;
+    };
+    let hooks = ShaderHooks::default()
+        .snippet("vertex_declarations", "const LIFT: f32 = 0.1;")
+        .snippet(
+            "vertex",
+            "position.y += LIFT;\ncolor = vec4f(normal.xyz, 1.0);",
+        )
+        .snippet("fragment", CONTOURS);
+    for source in [VERTEX, INSTANCE, FRAGMENT] {
+        hooks
+            .create_shader_module(&init.device, "hooked", source)
+            .unwrap();
+    }
+
+    let broken = ShaderHooks::default().snippet("fragment", "color = undefined_name;");
+    let error = broken
+        .create_shader_module(&init.device, "broken", FRAGMENT)
+        .unwrap_err();
+    assert!(error.to_string().contains("broken"));
+}
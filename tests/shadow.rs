@@ -0,0 +1,225 @@
+#![cfg(feature = "low_level")]
+
+use cgmath::{Matrix4, SquareMatrix, Vector4};
+use wgpu::util::DeviceExt;
+use wgpu_surfaces::shadow::{self, ShadowMap};
+use wgpu_surfaces::wgpu_simplified as ws;
+
+mod common;
+
+fn project(mat: &Matrix4<f32>, p: [f32; 3]) -> [f32; 3] {
+    let v = mat * Vector4::new(p[0], p[1], p[2], 1.0);
+    [v.x / v.w, v.y / v.w, v.z / v.w]
+}
+
+fn close(a: [f32; 3], b: [f32; 3]) -> bool {
+    (0..3).all(|i| (a[i] - b[i]).abs() < 1e-4)
+}
+
+#[test]
+fn light_space_box_holds_the_bounding_sphere() {
+    let mat = shadow::light_space_mat([1.0, -1.0, 0.5], [1.0, 2.0, 3.0], 2.0);
+    assert!(close(project(&mat, [1.0, 2.0, 3.0]), [0.0, 0.0, 0.5]));
+
+    // straight down: the sphere spans the depth range from its top to its bottom
+    let mat = shadow::light_space_mat([0.0, -1.0, 0.0], [0.0; 3], 2.0);
+    assert!(close(project(&mat, [0.0, 2.0, 0.0]), [0.0, 0.0, 0.0]));
+    assert!(close(project(&mat, [0.0, -2.0, 0.0]), [0.0, 0.0, 1.0]));
+    let side = project(&mat, [2.0, 0.0, 0.0]);
+    assert!((side[0].abs() - 1.0).abs() < 1e-4 && side[1].abs() < 1e-4);
+}
+
+const SHADER: &str = r#"
+struct Output {
+    @builtin(position) position: vec4f,
+    @location(0) worldPos: vec3f,
+}
+
+// the ground at y = 0 seen from above, x and z in [-2, 2]
+@vertex
+fn vs_main(@location(0) position: vec3f) -> Output {
+    var output: Output;
+    output.position = vec4f(0.5 * position.x, 0.5 * position.z, 0.5, 1.0);
+    output.worldPos = position;
+    return output;
+}
+
+@fragment
+fn fs_main(in: Output) -> @location(0) vec4f {
+    return vec4f(vec3f(shadow_factor(in.worldPos)), 1.0);
+}
+"#;
+
+fn quad(x0: f32, x1: f32, y: f32) -> Vec<[f32; 3]> {
+    vec![[x0, y, -2.0], [x1, y, -2.0], [x1, y, 2.0], [x0, y, 2.0]]
+}
+
+#[test]
+fn occluder_shadows_the_ground_below_it() {
+    let Some(rgba) = render_shadowed_ground(&shadow::with_shadow(0, SHADER), None) else {
+        return;
+    };
+    // 1 - darkness in the shadow, fully lit beside it
+    assert!(rgba[0] > 100 && rgba[0] < 200, "{:?}", rgba);
+    assert_eq!(rgba[4], 255, "{:?}", rgba);
+}
+
+// the vertex stage of SHADER with the world position as the vec4f the catcher reads
+const CATCHER_VERTEX: &str = r#"
+struct CatcherOutput {
+    @builtin(position) position: vec4f,
+    @location(0) vPosition: vec4f,
+}
+
+@vertex
+fn vs_main(@location(0) position: vec3f) -> CatcherOutput {
+    var output: CatcherOutput;
+    output.position = vec4f(0.5 * position.x, 0.5 * position.z, 0.5, 1.0);
+    output.vPosition = vec4f(position, 1.0);
+    return output;
+}
+"#;
+
+#[test]
+fn shadow_catcher_is_transparent_outside_the_shadow() {
+    let source = shadow::shadow_catcher_wgsl(0) + CATCHER_VERTEX;
+    let Some(rgba) = render_shadowed_ground(&source, Some(shadow::SHADOW_CATCHER_BLENDING)) else {
+        return;
+    };
+    // black with darkness as alpha in the shadow, nothing beside it
+    assert_eq!(rgba[..3], [0, 0, 0], "{:?}", rgba);
+    assert!(rgba[3] > 120 && rgba[3] < 180, "{:?}", rgba);
+    assert_eq!(rgba[4..], [0, 0, 0, 0], "{:?}", rgba);
+}
+
+// draws the ground with the fragment shader of source over a transparent frame, with
+// the left half shadowed by an occluder above it, and reads the two pixels back
+fn render_shadowed_ground(source: &str, blend: Option<wgpu::BlendState>) -> Option<Vec<u8>> {
+    let init = common::headless(2, 1, 1)?;
+    let device = &init.device;
+    let mut shadow_map = ShadowMap::new(&init, 64);
+    shadow_map.pcf_radius = 0.0;
+    let light_mat = shadow::light_space_mat([0.0, -1.0, 0.0], [0.0; 3], 2.0);
+    shadow_map.write(&init.queue, &light_mat);
+    let data = shadow_map.uniform_data(&light_mat);
+    assert_eq!(data[16..], [0.002, 0.6, 1.0 / 64.0, 0.0]);
+
+    let buffer = |contents: &[u8], usage| {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents,
+            usage,
+        })
+    };
+    let indices = buffer(
+        bytemuck::cast_slice(&[0u16, 1, 2, 2, 3, 0]),
+        wgpu::BufferUsages::INDEX,
+    );
+    // the occluder covers the left half, one unit above the ground
+    let occluder = buffer(
+        bytemuck::cast_slice(&quad(-2.0, 0.0, 1.0)),
+        wgpu::BufferUsages::VERTEX,
+    );
+    let ground = buffer(
+        bytemuck::cast_slice(&quad(-2.0, 2.0, 0.0)),
+        wgpu::BufferUsages::VERTEX,
+    );
+    let vertex_buffer_layout = [wgpu::VertexBufferLayout {
+        array_stride: 12,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &wgpu::vertex_attr_array![0 => Float32x3],
+    }];
+
+    let mut pass_data = [0.0f32; 32];
+    pass_data[..16].copy_from_slice(AsRef::<[f32; 16]>::as_ref(&light_mat));
+    pass_data[16..].copy_from_slice(AsRef::<[f32; 16]>::as_ref(&Matrix4::<f32>::identity()));
+    let pass_buffer = buffer(
+        bytemuck::cast_slice(&pass_data),
+        wgpu::BufferUsages::UNIFORM,
+    );
+    let (pass_layout, pass_bind_group) = ws::create_bind_group(
+        device,
+        vec![wgpu::ShaderStages::VERTEX],
+        &[pass_buffer.as_entire_binding()],
+    )
+    .unwrap();
+    let depth_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(shadow::SHADOW_DEPTH_WGSL.into()),
+    });
+    let shadow_pipeline = shadow::create_shadow_pipeline(
+        device,
+        &depth_shader,
+        &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&pass_layout],
+            push_constant_ranges: &[],
+        }),
+        &vertex_buffer_layout,
+    );
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[&shadow_map.layout],
+        push_constant_ranges: &[],
+    });
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: None,
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            buffers: &vertex_buffer_layout,
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                blend,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: Default::default(),
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+        cache: None,
+    });
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    {
+        let mut shadow_pass = shadow_map.begin_pass(&mut encoder);
+        shadow_pass.set_pipeline(&shadow_pipeline);
+        shadow_pass.set_bind_group(0, &pass_bind_group, &[]);
+        shadow_pass.set_vertex_buffer(0, occluder.slice(..));
+        shadow_pass.set_index_buffer(indices.slice(..), wgpu::IndexFormat::Uint16);
+        shadow_pass.draw_indexed(0..6, 0, 0..1);
+    }
+    {
+        let mut attachment = ws::create_color_attachment(&init.view);
+        attachment.ops.load = wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT);
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(attachment)],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        render_pass.set_pipeline(&pipeline);
+        render_pass.set_bind_group(0, &shadow_map.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, ground.slice(..));
+        render_pass.set_index_buffer(indices.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.draw_indexed(0..6, 0, 0..1);
+    }
+    init.queue.submit(std::iter::once(encoder.finish()));
+
+    Some(init.read_rgba().unwrap())
+}
@@ -0,0 +1,102 @@
+#![cfg(all(feature = "low_level", feature = "stream"))]
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+use wgpu_surfaces::stream::{self, FrameServer};
+
+#[test]
+fn encoded_frames_are_jpeg() {
+    let jpeg = stream::encode_jpeg(&[128; 4 * 8 * 4], 8, 4, 80).unwrap();
+    assert_eq!(&jpeg[..2], &[0xff, 0xd8]);
+    assert_eq!(&jpeg[jpeg.len() - 2..], &[0xff, 0xd9]);
+    assert!(stream::encode_jpeg(&[], 70000, 1, 80).is_err());
+}
+
+#[test]
+fn clients_receive_published_frames() {
+    let server = FrameServer::bind("127.0.0.1:0").unwrap();
+    let mut client = TcpStream::connect(server.addr).unwrap();
+    client
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    client.write_all(b"GET / HTTP/1.0\r\n\r\n").unwrap();
+
+    let start = Instant::now();
+    while server.client_count() == 0 {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "client not accepted"
+        );
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    server.publish(&[200; 4 * 16 * 16], 16, 16).unwrap();
+
+    // the response head, then the first part with the JPEG
+    let mut received = vec![];
+    let mut buffer = [0u8; 4096];
+    while !received.windows(2).any(|w| w == [0xff, 0xd8]) {
+        let n = client.read(&mut buffer).unwrap();
+        assert!(n > 0, "stream closed");
+        received.extend_from_slice(&buffer[..n]);
+    }
+    let text = String::from_utf8_lossy(&received);
+    assert!(text.starts_with("HTTP/1.0 200 OK\r\n"));
+    assert!(text.contains("Content-Type: multipart/x-mixed-replace; boundary=frame\r\n"));
+    let part = text
+        .find("--frame\r\nContent-Type: image/jpeg\r\n")
+        .unwrap();
+    let body = received.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+    assert!(part > body);
+}
+
+fn wait_for_clients(server: &FrameServer, count: usize, limit: Duration) {
+    let start = Instant::now();
+    while server.client_count() < count {
+        assert!(start.elapsed() < limit, "{} clients", server.client_count());
+        std::thread::sleep(Duration::from_millis(5));
+    }
+}
+
+#[test]
+fn a_silent_client_does_not_hold_up_the_next_one() {
+    let server = FrameServer::bind("127.0.0.1:0").unwrap();
+    // connects but never sends its request, the handshake waits for it
+    let _silent = TcpStream::connect(server.addr).unwrap();
+    std::thread::sleep(Duration::from_millis(20));
+
+    let mut client = TcpStream::connect(server.addr).unwrap();
+    client.write_all(b"GET / HTTP/1.0\r\n\r\n").unwrap();
+    // well within the 500 ms the silent handshake can take
+    wait_for_clients(&server, 1, Duration::from_millis(300));
+}
+
+#[test]
+fn clients_survive_publishing_and_failed_ones_are_dropped() {
+    let server = FrameServer::bind("127.0.0.1:0").unwrap();
+    let mut clients: Vec<_> = (0..2)
+        .map(|_| {
+            let mut client = TcpStream::connect(server.addr).unwrap();
+            client.write_all(b"GET / HTTP/1.0\r\n\r\n").unwrap();
+            client
+        })
+        .collect();
+    wait_for_clients(&server, 2, Duration::from_secs(5));
+
+    server.publish(&[200; 4 * 8 * 8], 8, 8).unwrap();
+    assert_eq!(server.client_count(), 2);
+
+    // a closed viewer is dropped once a write to it fails
+    clients.pop();
+    let start = Instant::now();
+    while server.client_count() > 1 {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "closed client kept"
+        );
+        server.publish(&[200; 4 * 8 * 8], 8, 8).unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(server.client_count(), 1);
+}
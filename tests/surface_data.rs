@@ -0,0 +1,774 @@
+#![cfg(feature = "low_level")]
+
+use std::collections::HashSet;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use wgpu_surfaces::math_func as mf;
+use wgpu_surfaces::surface_data::{
+    self as sd, IHeightmapSurface, IImplicitSurface, IParametricSurface, ISimpleSurface,
+    IStreamingSurface, ISurfaceOutput, ITelemetrySurface, NormalMode, NormalOrientation,
+    ScrollAxis, Seam, ShadingMode, StreamingGrid,
+};
+
+const CASES: usize = 8;
+
+// region: checks
+fn check_counts(data: &ISurfaceOutput, nu: u16, nv: u16) {
+    let (nu, nv) = (nu as usize, nv as usize);
+    let vertices = (nu + 1) * (nv + 1);
+    assert_eq!(data.positions.len(), vertices);
+    assert_eq!(data.normals.len(), vertices);
+    assert_eq!(data.colors.len(), vertices);
+    assert_eq!(data.colors2.len(), vertices);
+    assert_eq!(data.uvs.len(), vertices);
+    assert_eq!(data.indices.len(), 6 * nu * nv);
+    // every cell has its top and left edge, the last row/column adds the closing edges
+    assert_eq!(data.indices2.len(), 4 * nu * nv + 4 * (nu + nv - 1));
+}
+
+fn check_index_bounds(data: &ISurfaceOutput) {
+    let n = data.positions.len();
+    assert!(data.indices.iter().all(|&i| (i as usize) < n));
+    assert!(data.indices2.iter().all(|&i| (i as usize) < n));
+}
+
+fn check_finite(data: &ISurfaceOutput, name: &str) {
+    for (k, p) in data.positions.iter().enumerate() {
+        assert!(
+            p.iter().all(|c| c.is_finite()),
+            "{name}: position {k} = {p:?}"
+        );
+    }
+    for (k, n) in data.normals.iter().enumerate() {
+        assert!(
+            n.iter().all(|c| c.is_finite()),
+            "{name}: normal {k} = {n:?}"
+        );
+    }
+}
+
+fn check_unit_normals(data: &ISurfaceOutput, name: &str) {
+    for (k, n) in data.normals.iter().enumerate() {
+        let len = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+        assert!((len - 1.0).abs() < 1e-3, "{name}: |normal {k}| = {len}");
+    }
+}
+
+// every directed edge may appear only once; a shared edge used twice in the same
+// direction means neighboring triangles are wound inconsistently
+fn check_winding(data: &ISurfaceOutput, name: &str) {
+    let mut edges = HashSet::new();
+    for tri in data.indices.chunks_exact(3) {
+        for (a, b) in [(tri[0], tri[1]), (tri[1], tri[2]), (tri[2], tri[0])] {
+            assert!(edges.insert((a, b)), "{name}: edge ({a}, {b}) repeated");
+        }
+    }
+}
+
+fn check_all(data: &ISurfaceOutput, nu: u16, nv: u16, name: &str) {
+    check_counts(data, nu, nv);
+    check_index_bounds(data);
+    check_finite(data, name);
+    check_unit_normals(data, name);
+    check_winding(data, name);
+}
+// endregion: checks
+
+// region: generators
+#[test]
+fn parametric_surfaces_default_resolution() {
+    for surface_type in 0..sd::PARAMETRIC_SURFACE_COUNT {
+        let mut ps = IParametricSurface {
+            surface_type,
+            ..Default::default()
+        };
+        let data = ps.new();
+        check_all(
+            &data,
+            ps.u_resolution,
+            ps.v_resolution,
+            &sd::get_surface_type(surface_type),
+        );
+    }
+}
+
+#[test]
+fn parametric_surfaces_random_resolution() {
+    let mut rng = StdRng::seed_from_u64(234);
+    for surface_type in 0..sd::PARAMETRIC_SURFACE_COUNT {
+        for _ in 0..CASES {
+            // (nu + 1) * (nv + 1) must fit into u16 indices
+            let nu = rng.random_range(1..=120);
+            let nv = rng.random_range(1..=120);
+            let mut ps = IParametricSurface {
+                surface_type,
+                u_resolution: nu,
+                v_resolution: nv,
+                scale: rng.random_range(0.5..3.0),
+                colormap_direction: rng.random_range(0..3),
+                ..Default::default()
+            };
+            let name = format!("{} {}x{}", sd::get_surface_type(surface_type), nu, nv);
+            check_all(&ps.new(), nu, nv, &name);
+        }
+    }
+}
+
+#[test]
+fn parametric_surfaces_random_shape_params() {
+    let mut rng = StdRng::seed_from_u64(235);
+    for surface_type in [23, 24] {
+        for _ in 0..CASES {
+            let mut ps = IParametricSurface {
+                surface_type,
+                u_resolution: 160,
+                v_resolution: 24,
+                knot_p: rng.random_range(1..=9),
+                knot_q: rng.random_range(1..=9),
+                tube_radius: rng.random_range(0.05..0.8),
+                ..Default::default()
+            };
+            let name = format!(
+                "{} p={} q={} r={}",
+                sd::get_surface_type(surface_type),
+                ps.knot_p,
+                ps.knot_q,
+                ps.tube_radius
+            );
+            check_all(&ps.new(), 160, 24, &name);
+        }
+    }
+}
+
+#[test]
+fn parametric_surfaces_fit_scale() {
+    for surface_type in 0..sd::PARAMETRIC_SURFACE_COUNT {
+        let mut ps = IParametricSurface {
+            surface_type,
+            scale: 2.0,
+            ..Default::default()
+        };
+        let data = ps.new();
+        // positions are centered and normalized by the largest extent
+        for p in data.positions.iter() {
+            assert!(p.iter().all(|c| c.abs() <= 1.0 + 1e-4));
+        }
+    }
+}
+
+#[test]
+fn simple_surfaces_random_resolution() {
+    let mut rng = StdRng::seed_from_u64(234);
+    for surface_type in 0..sd::SIMPLE_SURFACE_COUNT {
+        for _ in 0..CASES {
+            let nx = rng.random_range(1..=150);
+            let nz = rng.random_range(1..=150);
+            let mut ss = ISimpleSurface {
+                surface_type,
+                x_resolution: nx,
+                z_resolution: nz,
+                t: rng.random_range(0.0..10.0),
+                ..Default::default()
+            };
+            let name = format!("simple {} {}x{}", surface_type, nx, nz);
+            check_all(&ss.new(), nx, nz, &name);
+        }
+    }
+}
+
+#[test]
+fn closure_surfaces_match_built_in_types() {
+    let mut built_in = ISimpleSurface {
+        surface_type: 0,
+        t: 0.3,
+        ..Default::default()
+    };
+    let mut custom = ISimpleSurface {
+        aspect_ratio: 0.5,
+        t: 0.3,
+        ..ISimpleSurface::from_fn(|x, z, t| mf::sinc(x, z, t)[1]).domain([-8.0, 8.0, -8.0, 8.0])
+    };
+    let data = custom.new();
+    assert_eq!(data.positions, built_in.new().positions);
+    assert_eq!(
+        [custom.xmin, custom.xmax, custom.aspect_ratio],
+        [-8.0, 8.0, 0.5]
+    );
+    check_all(&data, 30, 30, "simple closure");
+
+    let tau = 2.0 * std::f32::consts::PI;
+    let mut built_in = IParametricSurface {
+        surface_type: 21,
+        ..Default::default()
+    };
+    let mut custom =
+        IParametricSurface::from_fn(|u, v, _| mf::torus(u, v)).domain([0.0, tau, 0.0, tau]);
+    let data = custom.new();
+    assert_eq!(data.positions, built_in.new().positions);
+    check_all(&data, 80, 80, "parametric closure");
+
+    // the time parameter reaches the function
+    let mut wave = IParametricSurface::from_fn(|u, v, t| [u, (u + t).sin(), v]);
+    let before = wave.new().positions;
+    wave.t = 1.0;
+    assert_ne!(wave.new().positions, before);
+}
+
+#[test]
+fn streaming_surface_random_grid() {
+    let mut rng = StdRng::seed_from_u64(234);
+    for _ in 0..CASES {
+        let rows = rng.random_range(2..=100);
+        let cols = rng.random_range(2..=100);
+        let mut ss = IStreamingSurface::default();
+        ss.resize(rows, cols);
+        let frame: Vec<f32> = (0..rows as usize * cols as usize)
+            .map(|_| rng.random_range(-5.0..5.0))
+            .collect();
+        ss.set_frame(&frame);
+        let name = format!("streaming {}x{}", rows, cols);
+        check_all(&ss.new(), rows - 1, cols - 1, &name);
+    }
+}
+
+#[test]
+fn streaming_surface_push_row_scrolls() {
+    let mut ss = IStreamingSurface::default();
+    ss.resize(3, 2);
+    ss.push_row(&[1.0, 2.0]);
+    ss.push_row(&[3.0]);
+    assert_eq!(ss.values, vec![0.0, 0.0, 1.0, 2.0, 3.0, 0.0]);
+    assert_eq!(ss.value(2, 0), 3.0);
+}
+
+#[test]
+fn streaming_surface_resize_fits_u16_indices() {
+    let mut ss = IStreamingSurface::default();
+    ss.resize(300, 1);
+    assert_eq!((ss.rows, ss.cols), (sd::MAX_STREAMING_SIDE, 2));
+    ss.resize(u16::MAX, u16::MAX);
+    assert_eq!(ss.values.len(), 256 * 256);
+    let data = ss.new();
+    assert_eq!(data.positions.len(), 256 * 256);
+    assert_eq!(data.indices.iter().max(), Some(&u16::MAX));
+}
+
+#[test]
+fn implicit_surfaces_random_resolution() {
+    let mut rng = StdRng::seed_from_u64(237);
+    for surface_type in 0..sd::IMPLICIT_SURFACE_COUNT {
+        for _ in 0..CASES {
+            let mut is = IImplicitSurface {
+                surface_type,
+                resolution: rng.random_range(4..=32),
+                iso_level: rng.random_range(-0.3..0.3),
+                ..Default::default()
+            };
+            let data = is.new().unwrap();
+            let name = format!("implicit {} {}", surface_type, is.resolution);
+            assert!(!data.indices.is_empty(), "{name}: empty mesh");
+            assert_eq!(data.indices.len() % 3, 0);
+            assert_eq!(data.normals.len(), data.positions.len());
+            check_index_bounds(&data);
+            check_finite(&data, &name);
+            check_unit_normals(&data, &name);
+            check_winding(&data, &name);
+        }
+    }
+}
+
+#[test]
+fn implicit_surface_fails_past_the_u16_indices() {
+    // ten sheets across a fine grid have far more vertices than u16 indices address
+    let is = IImplicitSurface {
+        resolution: 64,
+        ..Default::default()
+    };
+    let result = is.implicit_surface_data(&|x, _, _| (5.0 * x).sin());
+    assert!(
+        matches!(result, Err(wgpu_surfaces::Error::IndexOverflow(_))),
+        "{:?}",
+        result.map(|data| data.positions.len())
+    );
+    // the same sheets on a coarse grid fit
+    let is = IImplicitSurface {
+        resolution: 8,
+        ..Default::default()
+    };
+    let data = is.implicit_surface_data(&|x, _, _| (5.0 * x).sin()).unwrap();
+    check_index_bounds(&data);
+}
+
+#[test]
+fn grid_indices_cover_every_cell() {
+    let (indices, indices2) = sd::grid_indices(3, 2);
+    assert_eq!(indices.len(), 6 * 3 * 2);
+    assert_eq!(indices2.len(), 4 * 3 * 2 + 4 * (3 + 2 - 1));
+    assert_eq!(&indices[..6], &[0, 1, 4, 4, 3, 0]);
+    assert_eq!(*indices.iter().max().unwrap(), 11);
+}
+// endregion: generators
+
+#[test]
+fn simple_surface_color_field() {
+    let mut ss = ISimpleSurface {
+        surface_type: 2,
+        color_field: Some(0),
+        x_resolution: 10,
+        z_resolution: 10,
+        ..Default::default()
+    };
+    let dual = ss.new();
+    ss.color_field = None;
+    let own = ss.new();
+    assert_eq!(dual.positions, own.positions);
+    assert_ne!(dual.colors, own.colors);
+    let [min, max] = ss.color_field_range(&ss.color_field_fn(0));
+    assert!(min < max);
+}
+
+#[test]
+fn closed_surfaces_get_outward_normals() {
+    for surface_type in 0..sd::PARAMETRIC_SURFACE_COUNT {
+        let mut ps = IParametricSurface {
+            surface_type,
+            u_resolution: 24,
+            v_resolution: 24,
+            ..Default::default()
+        };
+        let data = ps.new();
+        let name = sd::get_surface_type(surface_type);
+        check_winding(&data, &name);
+        if ps.orientation() == NormalOrientation::Auto {
+            assert!(sd::orientation_score(&data) > -0.1, "{name} faces inwards");
+        }
+    }
+
+    // the torus comes out inward-facing; the override keeps or flips it
+    let mut torus = IParametricSurface {
+        surface_type: 21,
+        ..Default::default()
+    };
+    let outward = torus.new();
+    assert!(torus.flips_normals());
+    assert!(sd::orientation_score(&outward) > 0.5);
+    torus.normal_orientation = Some(NormalOrientation::Keep);
+    let mut inward = torus.new();
+    assert!(sd::orientation_score(&inward) < -0.5);
+    assert_eq!(inward.normals[7].map(|c| -c), outward.normals[7]);
+    assert_eq!(
+        &inward.indices[..3],
+        &[outward.indices[0], outward.indices[2], outward.indices[1]]
+    );
+    assert!(inward.orient(NormalOrientation::Auto));
+    assert_eq!(inward.normals, outward.normals);
+    assert!(!inward.orient(NormalOrientation::Auto));
+
+    // one-sided surfaces keep their normals unless told otherwise
+    assert_eq!(sd::default_normal_orientation(0), NormalOrientation::Keep);
+    let mut klein = IParametricSurface::default();
+    assert!(!klein.flips_normals());
+    klein.normal_orientation = Some(NormalOrientation::Flip);
+    assert!(klein.flips_normals());
+}
+
+#[test]
+fn analytic_partials_match_central_differences() {
+    let mut covered = 0;
+    for surface_type in 0..sd::PARAMETRIC_SURFACE_COUNT {
+        let ps = IParametricSurface {
+            surface_type,
+            ..Default::default()
+        };
+        let Some(partials) = ps.partials_fn() else {
+            continue;
+        };
+        covered += 1;
+        let (f, [umin, umax, vmin, vmax]) = ps.surface_fn();
+        let name = sd::get_surface_type(surface_type);
+        // interior points off the piecewise boundaries of klein_bottle2
+        for (a, b) in [(0.23, 0.31), (0.41, 0.77), (0.68, 0.12), (0.87, 0.56)] {
+            let (u, v) = (umin + a * (umax - umin), vmin + b * (vmax - vmin));
+            let (hu, hv) = (1e-3 * (umax - umin), 1e-3 * (vmax - vmin));
+            let [pu, pv] = partials(u, v);
+            let central =
+                |p: [f32; 3], q: [f32; 3], h: f32| [0, 1, 2].map(|k| (p[k] - q[k]) / (2.0 * h));
+            let cu = central(f(u + hu, v), f(u - hu, v), hu);
+            let cv = central(f(u, v + hv), f(u, v - hv), hv);
+            for (exact, approx) in [(pu, cu), (pv, cv)] {
+                let scale = exact.iter().fold(1.0f32, |m, c| m.max(c.abs()));
+                for k in 0..3 {
+                    assert!(
+                        (exact[k] - approx[k]).abs() < 1e-2 * scale,
+                        "{name} at ({u}, {v}): {exact:?} vs {approx:?}"
+                    );
+                }
+            }
+        }
+    }
+    // all but boy_surface and costa
+    assert_eq!(covered, sd::PARAMETRIC_SURFACE_COUNT - 2);
+}
+
+#[test]
+fn normal_mode_selects_analytic_or_finite_difference_normals() {
+    let mut klein = IParametricSurface {
+        u_resolution: 32,
+        v_resolution: 32,
+        ..Default::default()
+    };
+    assert_eq!(klein.normal_mode, NormalMode::Analytic);
+    let analytic = klein.new();
+    klein.normal_mode = NormalMode::FiniteDifference;
+    let finite = klein.new();
+    assert_eq!(analytic.positions, finite.positions);
+    let mut close = 0;
+    for (a, b) in analytic.normals.iter().zip(&finite.normals) {
+        let length = a.iter().map(|c| c * c).sum::<f32>().sqrt();
+        assert!((length - 1.0).abs() < 1e-4);
+        let dot: f32 = (0..3).map(|k| a[k] * b[k]).sum();
+        close += (dot > 0.999) as usize;
+    }
+    // they differ only a little, and only where the curvature is high
+    assert!(close > analytic.normals.len() * 9 / 10);
+    assert_ne!(analytic.normals, finite.normals);
+
+    // a custom function has no derivatives and keeps the central differences
+    let mut custom = IParametricSurface::from_fn(|u, v, _| mf::torus(u, v)).domain([
+        0.0,
+        2.0 * std::f32::consts::PI,
+        0.0,
+        2.0 * std::f32::consts::PI,
+    ]);
+    assert!(custom.partials_fn().is_none());
+    let analytic = custom.new();
+    custom.normal_mode = NormalMode::FiniteDifference;
+    assert_eq!(analytic.normals, custom.new().normals);
+    assert_eq!(
+        NormalMode::from_name("Finite_Difference"),
+        Some(NormalMode::FiniteDifference)
+    );
+    assert_eq!(NormalMode::Analytic.next(), NormalMode::FiniteDifference);
+}
+
+#[test]
+fn flat_and_smooth_shading_convert_the_mesh() {
+    let mut torus = IParametricSurface {
+        surface_type: 21,
+        u_resolution: 12,
+        v_resolution: 12,
+        ..Default::default()
+    };
+    let smooth = torus.new();
+    torus.shading = ShadingMode::Flat;
+    let flat = torus.new();
+    assert_eq!(flat.positions.len(), 12 * 12 * 6);
+    assert_eq!(
+        flat.indices,
+        (0..flat.positions.len() as u16).collect::<Vec<_>>()
+    );
+    assert_eq!(flat.indices2.len(), smooth.indices2.len());
+    assert_eq!(flat.colors.len(), flat.positions.len());
+    assert_eq!(flat.uvs.len(), flat.positions.len());
+    // the torus lies in the xz plane; its tube runs around the ring at this radius
+    let radial = |p: &[f32; 3]| p[0].hypot(p[2]);
+    let (rmin, rmax) = flat
+        .positions
+        .iter()
+        .fold((f32::MAX, 0.0f32), |(lo, hi), p| {
+            (lo.min(radial(p)), hi.max(radial(p)))
+        });
+    let ring = 0.5 * (rmin + rmax);
+    for tri in flat.indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|k| tri[k] as usize);
+        assert!(flat.normals[a] == flat.normals[b] && flat.normals[b] == flat.normals[c]);
+        let n = flat.normals[a];
+        for (p, q) in [(a, b), (b, c)] {
+            let edge = [0, 1, 2].map(|k| flat.positions[q][k] - flat.positions[p][k]);
+            let dot: f32 = (0..3).map(|k| edge[k] * n[k]).sum();
+            assert!(dot.abs() < 1e-5);
+        }
+        // on the side of the smooth normals, which face out of the tube
+        let p = flat.positions[a];
+        let axis = [p[0], 0.0, p[2]].map(|c| c * ring / radial(&p));
+        let out: f32 = (0..3).map(|k| n[k] * (p[k] - axis[k])).sum();
+        assert!(out > 0.0);
+    }
+
+    // welding the flat mesh back shares every grid vertex, the seams included
+    let mut welded = flat.clone();
+    assert!(welded.shade(ShadingMode::Smooth));
+    assert_eq!(welded.positions.len(), 12 * 12);
+    assert_eq!(welded.indices.len(), flat.indices.len());
+    for n in &welded.normals {
+        let length = n.iter().map(|c| c * c).sum::<f32>().sqrt();
+        assert!((length - 1.0).abs() < 1e-4);
+    }
+    assert!((welded.surface_area() - smooth.surface_area()).abs() < 1e-5);
+
+    // three vertices per triangle do not fit the u16 indices of a fine grid
+    let mut fine = IParametricSurface {
+        surface_type: 21,
+        u_resolution: 120,
+        v_resolution: 120,
+        ..Default::default()
+    }
+    .new();
+    let vertices = fine.positions.len();
+    assert!(!fine.shade(ShadingMode::Flat));
+    assert_eq!(fine.positions.len(), vertices);
+    assert_eq!(ShadingMode::from_name("FLAT"), Some(ShadingMode::Flat));
+}
+
+#[test]
+fn closed_seams_share_their_vertices() {
+    let (nu, nv) = (24, 16);
+    for surface_type in 0..sd::PARAMETRIC_SURFACE_COUNT {
+        let mut ps = IParametricSurface {
+            surface_type,
+            u_resolution: nu,
+            v_resolution: nv,
+            ..Default::default()
+        };
+        let open = ps.new();
+        ps.close_seams = true;
+        let closed = ps.new();
+        let name = sd::get_surface_type(surface_type);
+        let seams = ps.seams();
+        let rows = [nv + 1, nu + 1].map(|n| n as usize);
+        let mut dropped = 0;
+        for axis in 0..2 {
+            if seams[axis] != Seam::Open {
+                dropped += rows[axis];
+            }
+        }
+        if seams[0] != Seam::Open && seams[1] != Seam::Open {
+            dropped -= 1; // the corner belongs to both seams
+        }
+        assert_eq!(
+            closed.positions.len(),
+            open.positions.len() - dropped,
+            "{name}"
+        );
+        assert_eq!(closed.indices.len(), open.indices.len(), "{name}");
+        assert_eq!(closed.uvs.len(), closed.positions.len());
+        check_index_bounds(&closed);
+        check_unit_normals(&closed, &name);
+        // same triangles, now sharing the seam vertices
+        let area = |data: &ISurfaceOutput| data.surface_area();
+        assert!(
+            (area(&closed) - area(&open)).abs() < 1e-3 * area(&open),
+            "{name}"
+        );
+        // welding also drops the lines grid_indices draws twice
+        if seams != [Seam::Open; 2] {
+            let lines: HashSet<_> = closed
+                .indices2
+                .chunks_exact(2)
+                .map(|line| (line[0].min(line[1]), line[0].max(line[1])))
+                .collect();
+            assert_eq!(
+                lines.len() * 2,
+                closed.indices2.len(),
+                "{name}: duplicate lines"
+            );
+        }
+        // the one-sided surfaces cannot keep a consistent winding across a twist
+        if !matches!(seams, [Seam::Twisted(_), _] | [_, Seam::Twisted(_)]) {
+            check_winding(&closed, &name);
+        }
+    }
+
+    // every vertex of the torus is used by six triangles once the seams are closed
+    let torus = IParametricSurface {
+        surface_type: 21,
+        u_resolution: nu,
+        v_resolution: nv,
+        close_seams: true,
+        ..Default::default()
+    }
+    .new();
+    assert_eq!(torus.positions.len(), nu as usize * nv as usize);
+    let mut uses = vec![0; torus.positions.len()];
+    for &i in &torus.indices {
+        uses[i as usize] += 1;
+    }
+    assert!(uses.iter().all(|&n| n == 6));
+
+    // a twist that falls between the grid lines leaves the seam open
+    let mut klein = IParametricSurface {
+        surface_type: 0,
+        u_resolution: 20,
+        v_resolution: 15,
+        ..Default::default()
+    }
+    .new();
+    assert_eq!(
+        klein.close_seams(20, 15, sd::default_seams(0)),
+        [false, true]
+    );
+    assert_eq!(klein.positions.len(), 21 * 15);
+    // and a custom function is open unless told otherwise
+    let ps = IParametricSurface::from_fn(|u, v, _| [u, v, 0.0]);
+    assert_eq!(ps.seams(), [Seam::Open; 2]);
+}
+
+#[test]
+fn heightmaps_read_csv_grids_with_missing_cells() {
+    let csv = "# station grid\nx0,x1,x2\n0.0, 1.5, 3\n\n1;;2\n0.5,2.0,nan\n";
+    let mut heightmap = IHeightmapSurface::parse_csv(csv).unwrap();
+    assert_eq!((heightmap.rows, heightmap.cols), (3, 3));
+    assert_eq!(heightmap.values[..3], [0.0, 1.5, 3.0]);
+    let missing: Vec<usize> = (0..9).filter(|&k| heightmap.missing[k]).collect();
+    assert_eq!(missing, [4, 8]);
+    assert_eq!(heightmap.data_range(), [0.0, 3.0]);
+    assert_eq!(heightmap.filled_values()[4], 0.0);
+
+    let output = heightmap.new();
+    assert_eq!(output.positions.len(), 9);
+    assert!(output.normals.iter().flatten().all(|n| n.is_finite()));
+    // only the two triangles of the first quad keep all their corners
+    assert_eq!(output.indices.len(), 6);
+    assert!(output.indices.iter().all(|&k| k != 4 && k != 8));
+
+    assert!(IHeightmapSurface::parse_csv("1,2,3\n4,5\n").is_err());
+    assert!(IHeightmapSurface::parse_csv("1 2 3\n").is_err());
+    let spaced = IHeightmapSurface::parse_csv("1 2\n3 4\n").unwrap();
+    assert_eq!(spaced.values, [1.0, 2.0, 3.0, 4.0]);
+}
+
+#[test]
+fn large_heightmaps_are_resampled_to_fit_the_indices() {
+    let (rows, cols) = (1000, 500);
+    let values: Vec<f32> = (0..rows * cols).map(|k| (k / cols) as f32).collect();
+    let heightmap = IHeightmapSurface::from_grid(rows, cols, &values).unwrap();
+    assert_eq!((heightmap.rows, heightmap.cols), (256, 128));
+    assert_eq!(heightmap.values[0], 0.0);
+    assert!((heightmap.values[255 * 128] - 999.0).abs() < 1e-3);
+    assert!(IHeightmapSurface::from_grid(1, 4, &[0.0; 4]).is_err());
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn png_heightmaps_use_the_gray_levels() {
+    let path = std::env::temp_dir().join("wgpu_surfaces_heightmap.png");
+    {
+        let file = std::io::BufWriter::new(std::fs::File::create(&path).unwrap());
+        let mut encoder = png::Encoder::new(file, 3, 2);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Sixteen);
+        let levels: [u16; 6] = [0, 32768, 65535, 65535, 0, 16384];
+        let bytes: Vec<u8> = levels.iter().flat_map(|l| l.to_be_bytes()).collect();
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(&bytes)
+            .unwrap();
+    }
+    let heightmap = IHeightmapSurface::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!((heightmap.rows, heightmap.cols), (2, 3));
+    assert!((heightmap.values[1] - 0.5).abs() < 1e-4);
+    assert_eq!([heightmap.values[2], heightmap.values[4]], [1.0, 0.0]);
+    assert!(heightmap.missing.iter().all(|m| !m));
+}
+
+#[test]
+fn telemetry_rows_update_only_their_vertices() {
+    let (history, samples) = (16u16, 9u16);
+    let row = |n: usize| -> Vec<f32> {
+        (0..samples as usize)
+            .map(|j| ((n * 7 + j * 3) % 11) as f32 / 10.0 - 0.5)
+            .collect()
+    };
+    for axis in [ScrollAxis::X, ScrollAxis::Z] {
+        let mut surface = ITelemetrySurface::with_size(history, samples);
+        surface.grid.value_range = Some([-1.0, 1.0]);
+        surface.scroll_axis = axis;
+        let mut output = surface.new();
+        for n in 0..5 {
+            surface.push_row(&row(n));
+            let ranges = surface.update(&mut output);
+            let updated: usize = ranges.iter().map(|r| r.len()).sum();
+            assert_eq!(updated, 3 * samples as usize);
+        }
+        assert_eq!(surface.row(0), &row(4)[..]);
+
+        // the same rows laid out from scratch: the scrolled vertices match
+        let mut fresh = ITelemetrySurface::with_size(history, samples);
+        fresh.grid.value_range = Some([-1.0, 1.0]);
+        fresh.scroll_axis = axis;
+        (0..5).for_each(|n| fresh.push_row(&row(n)));
+        let expected = fresh.new();
+        assert_eq!(fresh.scroll_offset(), [0.0; 3]);
+        let offset = surface.scroll_offset();
+        for k in 0..output.positions.len() {
+            for (i, offset) in offset.iter().enumerate() {
+                let p = output.positions[k][i] + offset;
+                assert!((p - expected.positions[k][i]).abs() < 1e-5, "position {k}");
+                let n = output.normals[k][i] - expected.normals[k][i];
+                assert!(n.abs() < 1e-5, "normal {k}");
+            }
+            assert_eq!(output.colors[k], expected.colors[k]);
+        }
+        assert_eq!(output.indices, expected.indices);
+
+        // the seam between the newest and the oldest row is not drawn
+        let (triangles, lines) = surface.draw_ranges();
+        let count: usize = triangles.iter().map(|r| r.len()).sum();
+        assert_eq!(count, (history as usize - 1) * (samples as usize - 1) * 6);
+        let newest = 4 % history as u32;
+        let seam = (newest * samples as u32 + 1) as u16;
+        let drawn = |ranges: &[std::ops::Range<u32>; 2], indices: &[u16], per: usize| {
+            ranges
+                .iter()
+                .flat_map(|r| indices[r.start as usize..r.end as usize].chunks(per))
+                .filter(|p| p.contains(&seam))
+                .count()
+        };
+        // the newest row keeps its triangles towards the older row, not to the oldest
+        assert_eq!(drawn(&triangles, &output.indices, 3), 3);
+        assert!(drawn(&lines, &output.indices2, 2) >= 3);
+    }
+}
+
+#[test]
+fn telemetry_surfaces_are_streaming_grids() {
+    let mut surface = ITelemetrySurface::with_size(3, 300);
+    assert_eq!(surface.samples(), sd::MAX_STREAMING_SIDE);
+    StreamingGrid::resize(&mut surface, 3, 2);
+    assert_eq!((surface.history(), surface.samples()), (3, 2));
+    assert_eq!(surface.rows_pushed(), 0);
+
+    // a frame fills the history, its last row is the newest
+    StreamingGrid::set_frame(&mut surface, &[1.0, 2.0, 3.0, 4.0, 5.0]);
+    assert_eq!(surface.row(0), &[5.0, 0.0]);
+    assert_eq!(surface.row(2), &[1.0, 2.0]);
+    StreamingGrid::push_row(&mut surface, &[6.0, 7.0]);
+    assert_eq!(surface.row(1), &[5.0, 0.0]);
+    let output = surface.new();
+    assert_eq!(output.positions.len(), 6);
+    assert_eq!(output.indices.len(), 3 * 6);
+}
+
+#[test]
+fn telemetry_vertices_are_laid_out_again_after_a_full_history() {
+    let mut surface = ITelemetrySurface::with_size(4, 3);
+    let mut output = surface.new();
+    // a row beyond the automatic range needs new heights everywhere
+    surface.push_row(&[1.0, 2.0, 3.0]);
+    assert_eq!(surface.update(&mut output), vec![0..12usize; 1]);
+    surface.grid.value_range = Some([0.0, 3.0]);
+    output = surface.new();
+    for n in 0..3 {
+        surface.push_row(&[0.0, 1.0, n as f32]);
+        assert_eq!(surface.update(&mut output).len(), 3);
+    }
+    assert!(surface.scroll_offset()[0] < 0.0);
+    surface.push_row(&[f32::NAN, 1.0]);
+    assert_eq!(surface.update(&mut output), vec![0..12usize; 1]);
+    assert_eq!(surface.scroll_offset(), [0.0; 3]);
+    assert_eq!(surface.row(0), [0.0, 1.0, 0.0]);
+    assert_eq!(surface.rows_pushed(), 5);
+}
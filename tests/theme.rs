@@ -0,0 +1,47 @@
+#![cfg(feature = "low_level")]
+
+use wgpu_surfaces::overlay::{SceneStats, StatsOverlay};
+use wgpu_surfaces::theme::Theme;
+use wgpu_surfaces::wgpu_simplified::FpsCounter;
+
+#[test]
+fn themes_are_named_and_cycle() {
+    for theme in Theme::ALL {
+        assert_eq!(Theme::from_name(theme.name()), Some(theme));
+    }
+    assert_eq!(Theme::from_name("HIGH_CONTRAST"), Some(Theme::HighContrast));
+    assert_eq!(Theme::Standard.next(), Theme::HighContrast);
+    assert_eq!(Theme::HighContrast.next(), Theme::Standard);
+
+    // bolder, larger and thicker than the standard theme
+    let (standard, high) = (Theme::Standard, Theme::HighContrast);
+    assert!(high.text_scale() > standard.text_scale());
+    assert!(high.wireframe_width() > standard.wireframe_width());
+    assert_eq!(high.palette().background, [0.0; 3]);
+    assert!(high.palette().text.iter().all(|&c| c == 1.0));
+}
+
+#[test]
+fn the_ui_scale_grows_the_stats_overlay() {
+    assert_eq!(
+        StatsOverlay::default(),
+        StatsOverlay::themed(Theme::Standard, 1.0)
+    );
+    let large = StatsOverlay::themed(Theme::HighContrast, 2.0);
+    assert_eq!(large.text_height, 12.0 * 1.5 * 2.0);
+    assert_eq!(large.graph_size, [480.0, 120.0]);
+    assert_eq!(large.color, Theme::HighContrast.palette().text);
+
+    // the whole overlay, text and graph, reaches twice as far into the window
+    let fps = FpsCounter::new();
+    let scene = SceneStats::default();
+    let extent = |overlay: &StatsOverlay| {
+        let lines = overlay.lines(&fps, &scene, [1600, 1200]);
+        let right = lines.iter().map(|(p, _)| p[0]).fold(f32::MIN, f32::max);
+        let bottom = lines.iter().map(|(p, _)| p[1]).fold(f32::MAX, f32::min);
+        [(right + 1.0) * 800.0, (1.0 - bottom) * 600.0]
+    };
+    let [w, h] = extent(&StatsOverlay::themed(Theme::Standard, 1.0));
+    let [w2, h2] = extent(&StatsOverlay::themed(Theme::Standard, 2.0));
+    assert!((w2 - 2.0 * w).abs() < 0.1 && (h2 - 2.0 * h).abs() < 0.1);
+}
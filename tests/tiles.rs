@@ -0,0 +1,147 @@
+#![cfg(feature = "low_level")]
+
+use cgmath::{Point3, Vector3};
+use wgpu_surfaces::tiles::{
+    FnSource, GridSource, RawFileSource, TileCache, TileKey, TileSource, TiledSurface,
+};
+use wgpu_surfaces::wgpu_simplified as ws;
+
+fn procedural(n: u32) -> TiledSurface {
+    let source = FnSource {
+        rows: n,
+        cols: n,
+        f: Box::new(|i, j| (i as f32 * 0.01).sin() + (j as f32 * 0.013).cos()),
+    };
+    let mut ts = TiledSurface::from_source(Box::new(source)).unwrap();
+    ts.tile_size = 32;
+    ts
+}
+
+fn view_project(eye: Point3<f32>) -> cgmath::Matrix4<f32> {
+    let (view_mat, project_mat, _) =
+        ws::create_vp_mat(eye, (0.0, 0.0, 0.0).into(), Vector3::unit_y(), 1.0);
+    project_mat * view_mat
+}
+
+#[test]
+fn far_views_select_coarse_tiles() {
+    let ts = procedural(1025);
+    assert_eq!(ts.root_level(), 5);
+    let far = Point3::new(0.0, 40.0, 40.0);
+    assert_eq!(ts.select(far, &view_project(far)), vec![ts.root()]);
+
+    let near = Point3::new(0.0, 0.3, 0.3);
+    let tiles = ts.select(near, &view_project(near));
+    assert!(tiles.len() > 4);
+    assert!(tiles.iter().any(|t| t.level == 0));
+    // the selected tiles never overlap
+    for a in tiles.iter() {
+        assert!(tiles.iter().all(|b| !a.is_ancestor_of(b)));
+    }
+}
+
+#[test]
+fn neighboring_tiles_share_border_vertices() {
+    let ts = procedural(200);
+    let a = ts
+        .tile_data(&TileKey {
+            level: 0,
+            x: 0,
+            z: 0,
+        })
+        .unwrap();
+    let b = ts
+        .tile_data(&TileKey {
+            level: 0,
+            x: 1,
+            z: 0,
+        })
+        .unwrap();
+    // the last row of a is the first row of b, including the normals
+    let n = 33;
+    for j in 0..n {
+        assert_eq!(a.positions[32 * n + j], b.positions[j]);
+        assert_eq!(a.normals[32 * n + j], b.normals[j]);
+    }
+    // the last tile of a row is cut at the grid border: 199 = 6 * 32 + 7 cells
+    let last = ts
+        .tile_data(&TileKey {
+            level: 0,
+            x: 6,
+            z: 0,
+        })
+        .unwrap();
+    assert_eq!(last.positions.len(), 8 * 33 + 2 * (8 + 33));
+    assert!(
+        ts.tile_data(&TileKey {
+            level: 0,
+            x: 7,
+            z: 0
+        })
+        .unwrap()
+        .positions
+        .is_empty()
+    );
+}
+
+#[test]
+fn cache_falls_back_to_ancestors_and_evicts() {
+    let ts = procedural(1025);
+    let mut cache: TileCache<usize> = TileCache::new(8, 2);
+    let root = ts.root();
+    let children: Vec<TileKey> = (0..4)
+        .map(|k| TileKey {
+            level: root.level - 1,
+            x: k % 2,
+            z: k / 2,
+        })
+        .collect();
+    let mut loads = 0;
+    let mut load = |_: &TileKey| -> Result<usize, ()> {
+        loads += 1;
+        Ok(loads)
+    };
+    // only two of the four children fit the budget, so the root stands in for all
+    assert_eq!(
+        cache.update(&children, root, &mut load).unwrap(),
+        vec![root]
+    );
+    assert_eq!(cache.update(&children, root, &mut load).unwrap().len(), 4);
+    assert_eq!(cache.len(), 5);
+
+    let mut grandchildren = vec![];
+    for c in children.iter() {
+        for k in 0..4 {
+            grandchildren.push(TileKey {
+                level: c.level - 1,
+                x: 2 * c.x + k % 2,
+                z: 2 * c.z + k / 2,
+            });
+        }
+    }
+    for _ in 0..8 {
+        cache.update(&grandchildren, root, &mut load).unwrap();
+    }
+    assert!(cache.len() <= 16 + 1);
+    assert!(cache.contains(&root));
+}
+
+#[test]
+fn raw_file_source_matches_grid() {
+    let (rows, cols) = (40, 50);
+    let values: Vec<f32> = (0..rows * cols).map(|k| k as f32 * 0.5).collect();
+    let path = std::env::temp_dir().join(format!("tiles_test_{}.f32", std::process::id()));
+    let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+    std::fs::write(&path, bytes).unwrap();
+
+    let file = RawFileSource::open(&path, rows, cols).unwrap();
+    let grid = GridSource { rows, cols, values };
+    for stride in [1, 3, 20] {
+        assert_eq!(
+            file.read(-2, 5, 7, 9, stride).unwrap(),
+            grid.read(-2, 5, 7, 9, stride).unwrap()
+        );
+    }
+    assert!(RawFileSource::open(&path, rows + 1, cols).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
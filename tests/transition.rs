@@ -0,0 +1,47 @@
+#![cfg(feature = "low_level")]
+
+use wgpu_surfaces::transition::CrossFade;
+use wgpu_surfaces::wgpu_simplified as ws;
+
+mod common;
+
+#[test]
+fn cross_fade_keeps_the_outgoing_mesh_while_fading() {
+    let Some(init) = common::headless(1, 1, 1) else {
+        return;
+    };
+    let (device, queue) = (&init.device, &init.queue);
+    let mut fade = CrossFade::new(device);
+    assert!(!fade.is_fading());
+    assert_eq!(fade.opacities(), [0.0, 1.0]);
+
+    let vertices = [0.5f32; 27];
+    let indices: [u16; 6] = [0, 1, 2, 2, 1, 0];
+    let buffer = |usage, contents: &[u8]| {
+        let usage = usage | wgpu::BufferUsages::COPY_SRC;
+        ws::GrowableBuffer::new(device, "Shown", usage, contents)
+    };
+    let mut vertex_buffer = buffer(wgpu::BufferUsages::VERTEX, bytemuck::cast_slice(&vertices));
+    let index_buffer = buffer(wgpu::BufferUsages::INDEX, bytemuck::cast_slice(&indices));
+    fade.start(device, queue, &vertex_buffer, &index_buffer, 6);
+    // the next mesh replaces the shown one, the fade keeps its own copy
+    vertex_buffer.write(device, queue, bytemuck::cast_slice(&[1.0f32; 54]));
+    let (outgoing_vertices, outgoing_indices, count) = fade.outgoing_buffers();
+    assert_eq!(
+        (outgoing_vertices.len(), outgoing_indices.len(), count),
+        (108, 12, 6)
+    );
+
+    assert!(fade.is_fading());
+    assert_eq!(fade.opacities(), [1.0, 0.0]);
+    fade.advance(0.5 * fade.seconds);
+    let [outgoing, incoming] = fade.opacities();
+    assert!((outgoing - 0.5).abs() < 1e-6 && (incoming - 0.5).abs() < 1e-6);
+    fade.advance(0.5 * fade.seconds);
+    assert!(!fade.is_fading());
+    assert_eq!(fade.opacities(), [0.0, 1.0]);
+
+    fade.start(device, queue, &vertex_buffer, &index_buffer, 6);
+    fade.stop();
+    assert!(!fade.is_fading());
+}
@@ -0,0 +1,128 @@
+#![cfg(feature = "low_level")]
+
+use wgpu_surfaces::shader_hooks::ShaderHooks;
+use wgpu_surfaces::surface_data::IParametricSurface;
+use wgpu_surfaces::unfold::{self, Unfold, UnfoldVertex};
+use wgpu_surfaces::wgpu_simplified as ws;
+
+mod common;
+
+#[test]
+fn surfaces_flatten_onto_their_parameter_rectangle() {
+    let mut ps = IParametricSurface {
+        surface_type: 21,
+        scale: 4.0,
+        u_resolution: 8,
+        v_resolution: 6,
+        ..Default::default()
+    };
+    let output = ps.new();
+    let flat = ps.flat_positions(&output);
+    assert_eq!(flat.len(), output.positions.len());
+
+    // centered, with the longer side as wide as the surface and the aspect of the domain
+    let (w, h) = (ps.umax - ps.umin, ps.vmax - ps.vmin);
+    let (sx, sy) = (4.0 * w / w.max(h), 4.0 * h / w.max(h));
+    let max = |k: usize| flat.iter().map(|p| p[k]).fold(f32::MIN, f32::max);
+    let min = |k: usize| flat.iter().map(|p| p[k]).fold(f32::MAX, f32::min);
+    assert!((max(0) - 0.5 * sx).abs() < 1e-5 && (min(0) + 0.5 * sx).abs() < 1e-5);
+    assert!((max(1) - 0.5 * sy).abs() < 1e-5 && (min(1) + 0.5 * sy).abs() < 1e-5);
+    assert!(flat.iter().all(|p| p[2] == 0.0));
+
+    // both endpoints share the vertex order, so the mesh indices draw either
+    let vertices = unfold::unfold_vertices(&output, &flat, false);
+    assert_eq!(vertices.len(), output.positions.len());
+    assert_eq!(vertices[5].position, output.positions[5]);
+    assert_eq!(vertices[5].flat_position, flat[5]);
+    assert_eq!(std::mem::size_of::<UnfoldVertex>(), 60);
+}
+
+#[test]
+fn the_flat_normal_follows_the_winding() {
+    let mut ps = IParametricSurface {
+        surface_type: 21,
+        ..Default::default()
+    };
+    let mut output = ps.new();
+    let flat = ps.flat_positions(&output);
+    let normal = unfold::flat_normal(&output, &flat);
+    assert_eq!(normal[2].abs(), 1.0);
+
+    // flipped triangles face the other way
+    for tri in output.indices.chunks_exact_mut(3) {
+        tri.swap(1, 2);
+    }
+    assert_eq!(unfold::flat_normal(&output, &flat)[2], -normal[2]);
+}
+
+#[test]
+fn unfolding_eases_towards_its_target() {
+    let mut unfold = Unfold::default();
+    assert!(unfold.is_folded());
+    assert!(!unfold.advance(0.1));
+
+    unfold.toggle();
+    assert!(!unfold.is_folded());
+    assert!(unfold.advance(0.5 * unfold.seconds));
+    assert!((unfold.blend() - 0.5).abs() < 1e-6);
+    assert!(unfold.advance(unfold.seconds));
+    assert_eq!(unfold.uniform_data(), [1.0, 0.0, 0.0, 0.0]);
+    assert!(!unfold.advance(0.1));
+
+    // folding back turns around from where it is
+    unfold.toggle();
+    unfold.advance(0.25 * unfold.seconds);
+    assert!((unfold.progress - 0.75).abs() < 1e-6);
+    unfold.advance(unfold.seconds);
+    assert!(unfold.is_folded());
+}
+
+#[test]
+fn unfold_shader_builds_a_pipeline() {
+    let Some(init) = common::headless(1, 1, 1) else {
+        return;
+    };
+    let device = &init.device;
+    let vert_layout = ws::create_bind_group_layout(
+        device,
+        vec![wgpu::ShaderStages::VERTEX, wgpu::ShaderStages::VERTEX],
+    );
+    let frag_layout = ws::create_bind_group_layout(
+        device,
+        vec![wgpu::ShaderStages::FRAGMENT, wgpu::ShaderStages::FRAGMENT],
+    );
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[&vert_layout, &frag_layout],
+        push_constant_ranges: &[],
+    });
+    let hooks = ShaderHooks::default();
+    let vs_shader = hooks
+        .create_shader_module(device, "vertex", unfold::UNFOLD_VERT_WGSL)
+        .unwrap();
+    let fs_shader = hooks
+        .create_shader_module(
+            device,
+            "fragment",
+            include_str!("../ch02/common/directional_frag.wgsl"),
+        )
+        .unwrap();
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    for topology in [
+        wgpu::PrimitiveTopology::TriangleList,
+        wgpu::PrimitiveTopology::LineList,
+    ] {
+        ws::IRenderPipeline {
+            topology,
+            vs_shader: Some(&vs_shader),
+            fs_shader: Some(&fs_shader),
+            pipeline_layout: Some(&pipeline_layout),
+            vertex_buffer_layout: &[UnfoldVertex::layout()],
+            ..Default::default()
+        }
+        .new(&init)
+        .unwrap();
+    }
+    let error = pollster::block_on(device.pop_error_scope());
+    assert!(error.is_none(), "{:?}", error);
+}
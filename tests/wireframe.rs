@@ -0,0 +1,166 @@
+#![cfg(feature = "low_level")]
+
+use cgmath::{Matrix4, SquareMatrix};
+use std::collections::HashSet;
+use wgpu::util::DeviceExt;
+use wgpu_surfaces::surface_data::{ISimpleSurface, ISurfaceOutput};
+use wgpu_surfaces::wgpu_simplified as ws;
+use wgpu_surfaces::wireframe::{self, BarycentricVertex, WireframeStyle};
+
+mod common;
+
+#[test]
+fn styles_have_names() {
+    for style in WireframeStyle::ALL {
+        assert_eq!(WireframeStyle::from_name(style.name()), Some(style));
+    }
+    assert_eq!(
+        WireframeStyle::from_name("Barycentric"),
+        Some(WireframeStyle::Barycentric)
+    );
+    assert_eq!(WireframeStyle::Barycentric.next(), WireframeStyle::Lines);
+    assert_eq!(WireframeStyle::default().fs_entry(true), "fs_wire");
+}
+
+#[test]
+fn only_the_line_list_edges_are_drawn() {
+    let output = ISimpleSurface {
+        x_resolution: 6,
+        z_resolution: 4,
+        ..Default::default()
+    }
+    .new();
+    let vertices = wireframe::barycentric_vertices(&output);
+    assert_eq!(vertices.len(), output.indices.len());
+
+    // an edge is drawn where the coordinate of the opposite corner is 0 at both ends
+    let key = |p: [f32; 3]| p.map(f32::to_bits);
+    let mut drawn = HashSet::new();
+    for tri in vertices.chunks_exact(3) {
+        let mut hidden = 0;
+        for k in 0..3 {
+            let (a, b) = (&tri[(k + 1) % 3], &tri[(k + 2) % 3]);
+            if a.barycentric[k] == 0.0 && b.barycentric[k] == 0.0 {
+                let (a, b) = (key(a.position), key(b.position));
+                drawn.insert((a.min(b), a.max(b)));
+            } else {
+                hidden += 1;
+            }
+            assert_eq!(tri[k].barycentric[k], 1.0);
+        }
+        assert_eq!(hidden, 1, "the diagonal of the grid cell");
+    }
+    let lines: HashSet<_> = output
+        .indices2
+        .chunks_exact(2)
+        .map(|line| {
+            let [a, b] = [0, 1].map(|k| key(output.positions[line[k] as usize]));
+            (a.min(b), a.max(b))
+        })
+        .collect();
+    assert_eq!(drawn, lines);
+}
+
+#[test]
+fn one_pass_draws_edges_but_not_diagonals() {
+    let Some(init) = common::headless(32, 32, 1) else {
+        return;
+    };
+    let device = &init.device;
+    // a square over the middle of the frame, blue with a red border
+    let quad = ISurfaceOutput {
+        positions: vec![
+            [-0.75, -0.75, 0.5],
+            [0.75, -0.75, 0.5],
+            [0.75, 0.75, 0.5],
+            [-0.75, 0.75, 0.5],
+        ],
+        normals: vec![[0.0, 0.0, 1.0]; 4],
+        colors: vec![[0.0, 0.0, 1.0]; 4],
+        colors2: vec![[1.0, 0.0, 0.0]; 4],
+        indices: vec![0, 1, 2, 0, 2, 3],
+        indices2: vec![0, 1, 1, 2, 2, 3, 3, 0],
+        ..Default::default()
+    };
+    let vertices = wireframe::barycentric_vertices(&quad);
+    let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: None,
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::VERTEX,
+    });
+
+    // identity matrices and a light that leaves the colors as they are
+    let identity: [f32; 16] = *Matrix4::<f32>::identity().as_ref();
+    let uniform = |data: &[f32]| {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(data),
+            usage: wgpu::BufferUsages::UNIFORM,
+        })
+    };
+    let vert_buffer = uniform(&identity.repeat(3));
+    let light_buffer = uniform(&[0.0, 0.0, -1.0, 0.0, 0.0, 0.0, 5.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+    let material_buffer = uniform(&[1.0, 0.0, 0.0, 1.0]);
+    let (vert_layout, vert_group) = ws::create_bind_group(
+        device,
+        vec![wgpu::ShaderStages::VERTEX],
+        &[vert_buffer.as_entire_binding()],
+    )
+    .unwrap();
+    let (frag_layout, frag_group) = ws::create_bind_group(
+        device,
+        vec![wgpu::ShaderStages::FRAGMENT, wgpu::ShaderStages::FRAGMENT],
+        &[
+            light_buffer.as_entire_binding(),
+            material_buffer.as_entire_binding(),
+        ],
+    )
+    .unwrap();
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[&vert_layout, &frag_layout],
+        push_constant_ranges: &[],
+    });
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: None,
+        source: wgpu::ShaderSource::Wgsl(wireframe::barycentric_wireframe_wgsl(1.5).into()),
+    });
+    let pipeline = ws::IRenderPipeline {
+        shader: Some(&shader),
+        pipeline_layout: Some(&pipeline_layout),
+        vertex_buffer_layout: &[BarycentricVertex::layout()],
+        is_depth_stencil: false,
+        fs_entry: WireframeStyle::Barycentric.fs_entry(false).to_string(),
+        ..Default::default()
+    }
+    .new(&init)
+    .unwrap();
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(ws::create_color_attachment(&init.view))],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &vert_group, &[]);
+        pass.set_bind_group(1, &frag_group, &[]);
+        pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        pass.draw(0..vertices.len() as u32, 0..1);
+    }
+    init.queue.submit(std::iter::once(encoder.finish()));
+
+    let rgba = init.read_rgba().unwrap();
+    let pixel = |x: usize, y: usize| &rgba[4 * (32 * y + x)..4 * (32 * y + x) + 3];
+    assert_eq!(pixel(16, 16), [0, 0, 255]); // on the hidden diagonal
+    assert_eq!(pixel(10, 21), [0, 0, 255]);
+    for (x, y) in [(4, 16), (27, 16), (16, 4), (16, 27)] {
+        let p = pixel(x, y);
+        assert!(p[0] > p[2], "edge pixel {x}, {y}: {p:?}");
+    }
+    assert_eq!(pixel(1, 16), [0, 0, 0]); // outside the square
+}